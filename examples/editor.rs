@@ -0,0 +1,695 @@
+//! Example 09. Minimal scene editor.
+//!
+//! Difficulty: Advanced.
+//!
+//! This example shows how to build simple scene editor on top of the engine and its user
+//! interface. It has scene tree panel, inspector of transform of selected node, asset browser
+//! that instantiates models from `examples/data` and save/load of scenes. Scenes are saved in
+//! the same format as scenes made in [rusty-editor](https://github.com/mrDIMAS/rusty-editor),
+//! so they can be loaded as model resources in a game.
+//!
+//! Controls:
+//! - Hold right mouse button and move mouse to orbit camera around origin.
+//! - Mouse wheel to zoom.
+//! - Click on a node in scene tree to select it, then use inspector to move, rotate or
+//!   scale it.
+
+extern crate rg3d;
+
+use rg3d::{
+    core::{
+        color::Color,
+        math::{quat::Quat, vec2::Vec2, vec3::Vec3},
+        pool::Handle,
+        visitor::{Visit, Visitor},
+    },
+    event::{
+        DeviceEvent, ElementState, Event, MouseButton, MouseScrollDelta, VirtualKeyCode,
+        WindowEvent,
+    },
+    event_loop::{ControlFlow, EventLoop},
+    gui::{
+        button::ButtonBuilder,
+        grid::{Column, GridBuilder, Row},
+        message::{
+            ButtonMessage, MessageDirection, ScrollBarMessage, TextMessage, UiMessageData,
+            WidgetMessage,
+        },
+        node::StubNode,
+        scroll_bar::ScrollBarBuilder,
+        scroll_viewer::ScrollViewerBuilder,
+        stack_panel::StackPanelBuilder,
+        text::TextBuilder,
+        widget::WidgetBuilder,
+        window::{WindowBuilder, WindowTitle},
+        Orientation, Thickness, VerticalAlignment,
+    },
+    scene::{base::BaseBuilder, camera::CameraBuilder, node::Node, Scene},
+    utils::{log::Log, translate_event},
+};
+use std::{
+    path::{Path, PathBuf},
+    time::Instant,
+};
+
+// Create our own engine type aliases. These specializations are needed
+// because engine provides a way to extend UI with custom nodes and messages.
+type GameEngine = rg3d::engine::Engine<(), StubNode>;
+type UiNode = rg3d::gui::node::UINode<(), StubNode>;
+type BuildContext<'a> = rg3d::gui::BuildContext<'a, (), StubNode>;
+
+const SCENE_PATH: &str = "examples/data/editor_scene.rgs";
+const ASSETS_PATH: &str = "examples/data";
+const CAMERA_PIVOT_NAME: &str = "EditorCameraPivot";
+
+/// Camera that orbits around a point. It is a part of edited scene, but it is never shown in
+/// scene tree and is not saved.
+struct CameraController {
+    pivot: Handle<Node>,
+    hinge: Handle<Node>,
+    camera: Handle<Node>,
+    yaw: f32,
+    pitch: f32,
+    distance: f32,
+    rotate: bool,
+}
+
+impl CameraController {
+    fn new(scene: &mut Scene) -> Self {
+        // Reuse camera of previously edited scene, if any.
+        let pivot = scene.graph.find_by_name_from_root(CAMERA_PIVOT_NAME);
+        if pivot.is_some() {
+            let hinge = scene.graph[pivot].children()[0];
+            let camera = scene.graph[hinge].children()[0];
+            return Self {
+                pivot,
+                hinge,
+                camera,
+                yaw: 0.0,
+                pitch: 0.4,
+                distance: 10.0,
+                rotate: false,
+            };
+        }
+
+        let camera = scene
+            .graph
+            .add_node(Node::Camera(CameraBuilder::new(BaseBuilder::new()).build()));
+        let hinge = scene.graph.add_node(Node::Base(BaseBuilder::new().build()));
+        let pivot = scene.graph.add_node(Node::Base(
+            BaseBuilder::new().with_name(CAMERA_PIVOT_NAME).build(),
+        ));
+        scene.graph.link_nodes(camera, hinge);
+        scene.graph.link_nodes(hinge, pivot);
+
+        Self {
+            pivot,
+            hinge,
+            camera,
+            yaw: 0.0,
+            pitch: 0.4,
+            distance: 10.0,
+            rotate: false,
+        }
+    }
+
+    fn update(&self, scene: &mut Scene) {
+        scene.graph[self.pivot]
+            .local_transform_mut()
+            .set_rotation(Quat::from_axis_angle(Vec3::new(0.0, 1.0, 0.0), self.yaw));
+        scene.graph[self.hinge]
+            .local_transform_mut()
+            .set_rotation(Quat::from_axis_angle(Vec3::new(1.0, 0.0, 0.0), self.pitch));
+        scene.graph[self.camera]
+            .local_transform_mut()
+            .set_position(Vec3::new(0.0, 0.0, -self.distance));
+    }
+
+    fn is_camera_node(&self, node: Handle<Node>) -> bool {
+        node == self.pivot || node == self.hinge || node == self.camera
+    }
+}
+
+struct Inspector {
+    name: Handle<UiNode>,
+    position: [Handle<UiNode>; 3],
+    yaw: Handle<UiNode>,
+    scale: Handle<UiNode>,
+}
+
+struct Editor {
+    scene: Handle<Scene>,
+    camera_controller: CameraController,
+    selection: Handle<Node>,
+    tree_panel: Handle<UiNode>,
+    tree_items: Vec<(Handle<UiNode>, Handle<Node>)>,
+    asset_items: Vec<(Handle<UiNode>, PathBuf)>,
+    save: Handle<UiNode>,
+    load: Handle<UiNode>,
+    delete: Handle<UiNode>,
+    inspector: Inspector,
+}
+
+fn make_scroll_bar(ctx: &mut BuildContext, row: usize, min: f32, max: f32) -> Handle<UiNode> {
+    ScrollBarBuilder::new(
+        WidgetBuilder::new()
+            .on_row(row)
+            .on_column(1)
+            .with_vertical_alignment(VerticalAlignment::Center)
+            .with_margin(Thickness::uniform(2.0)),
+    )
+    .with_min(min)
+    .with_max(max)
+    .with_step((max - min) / 100.0)
+    .show_value(true)
+    .with_value_precision(2)
+    .build(ctx)
+}
+
+fn make_label(ctx: &mut BuildContext, row: usize, text: &str) -> Handle<UiNode> {
+    TextBuilder::new(
+        WidgetBuilder::new()
+            .on_row(row)
+            .on_column(0)
+            .with_vertical_alignment(VerticalAlignment::Center),
+    )
+    .with_text(text)
+    .build(ctx)
+}
+
+fn make_button(ctx: &mut BuildContext, text: &str) -> Handle<UiNode> {
+    ButtonBuilder::new(
+        WidgetBuilder::new()
+            .with_width(70.0)
+            .with_margin(Thickness::uniform(1.0)),
+    )
+    .with_text(text)
+    .build(ctx)
+}
+
+/// Returns every model and scene file in given directory.
+fn find_assets(path: &Path) -> Vec<PathBuf> {
+    let mut assets = std::fs::read_dir(path)
+        .map(|entries| {
+            entries
+                .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+                .filter(|path| {
+                    path.extension().map_or(false, |ext| {
+                        let ext = ext.to_string_lossy().to_lowercase();
+                        ext == "fbx" || ext == "rgs"
+                    })
+                })
+                .collect::<Vec<_>>()
+        })
+        .unwrap_or_default();
+    assets.sort();
+    assets
+}
+
+impl Editor {
+    fn new(engine: &mut GameEngine) -> Self {
+        let mut scene = if Path::new(SCENE_PATH).exists() {
+            Scene::from_file(SCENE_PATH, &mut engine.resource_manager.lock().unwrap())
+                .unwrap_or_default()
+        } else {
+            Scene::new()
+        };
+        let camera_controller = CameraController::new(&mut scene);
+        let scene = engine.scenes.add(scene);
+
+        let (window_width, _) = engine.renderer.get_frame_size();
+        let ctx = &mut engine.user_interface.build_ctx();
+
+        // Scene tree panel with buttons of scene operations on top.
+        let save = make_button(ctx, "Save");
+        let load = make_button(ctx, "Load");
+        let delete = make_button(ctx, "Delete");
+        let tree_panel = StackPanelBuilder::new(WidgetBuilder::new()).build(ctx);
+        WindowBuilder::new(
+            WidgetBuilder::new()
+                .with_desired_position(Vec2::new(0.0, 0.0))
+                .with_width(250.0)
+                .with_height(400.0),
+        )
+        .with_content(
+            GridBuilder::new(
+                WidgetBuilder::new()
+                    .with_child(
+                        StackPanelBuilder::new(
+                            WidgetBuilder::new()
+                                .on_row(0)
+                                .with_child(save)
+                                .with_child(load)
+                                .with_child(delete),
+                        )
+                        .with_orientation(Orientation::Horizontal)
+                        .build(ctx),
+                    )
+                    .with_child(
+                        ScrollViewerBuilder::new(WidgetBuilder::new().on_row(1))
+                            .with_content(tree_panel)
+                            .build(ctx),
+                    ),
+            )
+            .add_column(Column::stretch())
+            .add_row(Row::strict(30.0))
+            .add_row(Row::stretch())
+            .build(ctx),
+        )
+        .with_title(WindowTitle::text("Scene"))
+        .can_close(false)
+        .build(ctx);
+
+        // Asset browser, click on an asset instantiates it in the scene.
+        let mut asset_items = Vec::new();
+        let asset_panel = StackPanelBuilder::new(WidgetBuilder::new().with_children(&{
+            let mut buttons = Vec::new();
+            for path in find_assets(Path::new(ASSETS_PATH)) {
+                let name = path
+                    .file_name()
+                    .map(|name| name.to_string_lossy().to_string())
+                    .unwrap_or_default();
+                let button = ButtonBuilder::new(WidgetBuilder::new().with_height(24.0))
+                    .with_text(&name)
+                    .build(ctx);
+                buttons.push(button);
+                asset_items.push((button, path));
+            }
+            buttons
+        }))
+        .build(ctx);
+        WindowBuilder::new(
+            WidgetBuilder::new()
+                .with_desired_position(Vec2::new(0.0, 410.0))
+                .with_width(250.0)
+                .with_height(250.0),
+        )
+        .with_content(
+            ScrollViewerBuilder::new(WidgetBuilder::new())
+                .with_content(asset_panel)
+                .build(ctx),
+        )
+        .with_title(WindowTitle::text("Assets"))
+        .can_close(false)
+        .build(ctx);
+
+        // Inspector of transform of selected node.
+        let name;
+        let position;
+        let yaw;
+        let scale;
+        WindowBuilder::new(
+            WidgetBuilder::new()
+                .with_desired_position(Vec2::new(window_width as f32 - 300.0, 0.0))
+                .with_width(300.0),
+        )
+        .with_content({
+            name = TextBuilder::new(WidgetBuilder::new().on_row(0).on_column(1))
+                .with_text("Nothing selected")
+                .build(ctx);
+            position = [
+                make_scroll_bar(ctx, 1, -50.0, 50.0),
+                make_scroll_bar(ctx, 2, -50.0, 50.0),
+                make_scroll_bar(ctx, 3, -50.0, 50.0),
+            ];
+            yaw = make_scroll_bar(ctx, 4, 0.0, 360.0);
+            scale = make_scroll_bar(ctx, 5, 0.01, 10.0);
+            let labels = [
+                make_label(ctx, 0, "Name"),
+                make_label(ctx, 1, "Position X"),
+                make_label(ctx, 2, "Position Y"),
+                make_label(ctx, 3, "Position Z"),
+                make_label(ctx, 4, "Yaw"),
+                make_label(ctx, 5, "Scale"),
+            ];
+            let mut grid = GridBuilder::new(
+                WidgetBuilder::new()
+                    .with_children(&labels)
+                    .with_child(name)
+                    .with_children(&position)
+                    .with_child(yaw)
+                    .with_child(scale),
+            )
+            .add_column(Column::strict(90.0))
+            .add_column(Column::stretch());
+            for _ in 0..6 {
+                grid = grid.add_row(Row::strict(30.0));
+            }
+            grid.build(ctx)
+        })
+        .with_title(WindowTitle::text("Inspector"))
+        .can_close(false)
+        .build(ctx);
+
+        let mut editor = Self {
+            scene,
+            camera_controller,
+            selection: Handle::NONE,
+            tree_panel,
+            tree_items: Default::default(),
+            asset_items,
+            save,
+            load,
+            delete,
+            inspector: Inspector {
+                name,
+                position,
+                yaw,
+                scale,
+            },
+        };
+        editor.sync_tree(engine);
+        editor
+    }
+
+    /// Re-creates items of scene tree panel, must be called when nodes are added or removed.
+    fn sync_tree(&mut self, engine: &mut GameEngine) {
+        for (item, _) in self.tree_items.drain(..) {
+            engine
+                .user_interface
+                .send_message(WidgetMessage::remove(item, MessageDirection::ToWidget));
+        }
+
+        let scene = &engine.scenes[self.scene];
+        let ctx = &mut engine.user_interface.build_ctx();
+        let mut stack = scene.graph[scene.graph.get_root()]
+            .children()
+            .iter()
+            .rev()
+            .map(|&child| (child, 0))
+            .collect::<Vec<_>>();
+        while let Some((node, depth)) = stack.pop() {
+            if self.camera_controller.is_camera_node(node) {
+                continue;
+            }
+            let name = scene.graph[node].name();
+            let text = format!(
+                "{}{}",
+                if node == self.selection { "> " } else { "" },
+                if name.is_empty() { "<unnamed>" } else { name }
+            );
+            let item = ButtonBuilder::new(WidgetBuilder::new().with_height(22.0).with_margin(
+                Thickness {
+                    left: depth as f32 * 12.0,
+                    top: 0.0,
+                    right: 0.0,
+                    bottom: 0.0,
+                },
+            ))
+            .with_text(&text)
+            .build(ctx);
+            self.tree_items.push((item, node));
+            stack.extend(
+                scene.graph[node]
+                    .children()
+                    .iter()
+                    .rev()
+                    .map(|&child| (child, depth + 1)),
+            );
+        }
+
+        for &(item, _) in self.tree_items.iter() {
+            engine.user_interface.send_message(WidgetMessage::link(
+                item,
+                MessageDirection::ToWidget,
+                self.tree_panel,
+            ));
+        }
+    }
+
+    /// Shows transform of selected node in inspector.
+    fn sync_inspector(&self, engine: &mut GameEngine) {
+        let scene = &engine.scenes[self.scene];
+        if !scene.graph.is_valid_handle(self.selection) {
+            engine.user_interface.send_message(TextMessage::text(
+                self.inspector.name,
+                MessageDirection::ToWidget,
+                "Nothing selected".to_owned(),
+            ));
+            return;
+        }
+
+        let node = &scene.graph[self.selection];
+        let transform = node.local_transform();
+        let position = transform.position();
+        let rotation = transform.rotation();
+        // Angle of rotation around vertical axis.
+        let yaw = (2.0 * (rotation.w * rotation.y + rotation.x * rotation.z))
+            .atan2(1.0 - 2.0 * (rotation.x * rotation.x + rotation.y * rotation.y))
+            .to_degrees();
+        let yaw = if yaw < 0.0 { yaw + 360.0 } else { yaw };
+        let scale = transform.scale().x;
+
+        let ui = &mut engine.user_interface;
+        ui.send_message(TextMessage::text(
+            self.inspector.name,
+            MessageDirection::ToWidget,
+            node.name().to_owned(),
+        ));
+        for (&scroll_bar, &value) in self
+            .inspector
+            .position
+            .iter()
+            .zip([position.x, position.y, position.z].iter())
+        {
+            ui.send_message(ScrollBarMessage::value(
+                scroll_bar,
+                MessageDirection::ToWidget,
+                value,
+            ));
+        }
+        ui.send_message(ScrollBarMessage::value(
+            self.inspector.yaw,
+            MessageDirection::ToWidget,
+            yaw,
+        ));
+        ui.send_message(ScrollBarMessage::value(
+            self.inspector.scale,
+            MessageDirection::ToWidget,
+            scale,
+        ));
+    }
+
+    fn select(&mut self, node: Handle<Node>, engine: &mut GameEngine) {
+        self.selection = node;
+        self.sync_tree(engine);
+        self.sync_inspector(engine);
+    }
+
+    fn save_scene(&self, engine: &GameEngine) {
+        // Editor camera is not a part of the scene.
+        let camera_controller = &self.camera_controller;
+        let mut scene = engine.scenes[self.scene]
+            .clone(&mut |handle, _| !camera_controller.is_camera_node(handle));
+        let mut visitor = Visitor::new();
+        let result = scene
+            .visit("Scene", &mut visitor)
+            .and_then(|_| visitor.save_binary(SCENE_PATH));
+        match result {
+            Ok(_) => Log::writeln(format!("Scene is saved to {}", SCENE_PATH)),
+            Err(e) => Log::writeln(format!("Unable to save scene. Reason: {:?}", e)),
+        }
+    }
+
+    fn load_scene(&mut self, engine: &mut GameEngine) {
+        let result = Scene::from_file(SCENE_PATH, &mut engine.resource_manager.lock().unwrap());
+        match result {
+            Ok(mut scene) => {
+                self.camera_controller = CameraController::new(&mut scene);
+                engine.scenes.remove(self.scene);
+                self.scene = engine.scenes.add(scene);
+                self.select(Handle::NONE, engine);
+            }
+            Err(e) => Log::writeln(format!("Unable to load scene. Reason: {:?}", e)),
+        }
+    }
+
+    fn instantiate_asset(&mut self, path: &Path, engine: &mut GameEngine) {
+        let model = engine.resource_manager.lock().unwrap().request_model(path);
+        if let Some(model) = model {
+            let scene = &mut engine.scenes[self.scene];
+            let root = model.lock().unwrap().instantiate(scene).root;
+            self.select(root, engine);
+        }
+    }
+
+    fn handle_ui_message(
+        &mut self,
+        message: &rg3d::gui::message::UiMessage<(), StubNode>,
+        engine: &mut GameEngine,
+    ) {
+        if message.direction() != MessageDirection::FromWidget {
+            return;
+        }
+        let destination = message.destination();
+        match message.data() {
+            UiMessageData::Button(ButtonMessage::Click) => {
+                if destination == self.save {
+                    self.save_scene(engine);
+                } else if destination == self.load {
+                    self.load_scene(engine);
+                } else if destination == self.delete {
+                    let scene = &mut engine.scenes[self.scene];
+                    if scene.graph.is_valid_handle(self.selection) {
+                        scene.remove_node(self.selection);
+                        self.select(Handle::NONE, engine);
+                    }
+                } else if let Some(&(_, node)) = self
+                    .tree_items
+                    .iter()
+                    .find(|(item, _)| *item == destination)
+                {
+                    self.select(node, engine);
+                } else if let Some((_, path)) = self
+                    .asset_items
+                    .iter()
+                    .find(|(item, _)| *item == destination)
+                    .cloned()
+                {
+                    self.instantiate_asset(&path, engine);
+                }
+            }
+            &UiMessageData::ScrollBar(ScrollBarMessage::Value(value)) => {
+                let scene = &mut engine.scenes[self.scene];
+                if !scene.graph.is_valid_handle(self.selection) {
+                    return;
+                }
+                let transform = scene.graph[self.selection].local_transform_mut();
+                if let Some(axis) = self
+                    .inspector
+                    .position
+                    .iter()
+                    .position(|&scroll_bar| scroll_bar == destination)
+                {
+                    let mut position = transform.position();
+                    match axis {
+                        0 => position.x = value,
+                        1 => position.y = value,
+                        _ => position.z = value,
+                    }
+                    transform.set_position(position);
+                } else if destination == self.inspector.yaw {
+                    transform.set_rotation(Quat::from_axis_angle(
+                        Vec3::new(0.0, 1.0, 0.0),
+                        value.to_radians(),
+                    ));
+                } else if destination == self.inspector.scale {
+                    transform.set_scale(Vec3::new(value, value, value));
+                }
+            }
+            _ => (),
+        }
+    }
+
+    fn handle_window_event(&mut self, event: &WindowEvent) {
+        match event {
+            WindowEvent::MouseInput { button, state, .. } => {
+                if *button == MouseButton::Right {
+                    self.camera_controller.rotate = *state == ElementState::Pressed;
+                }
+            }
+            WindowEvent::MouseWheel { delta, .. } => {
+                let lines = match delta {
+                    MouseScrollDelta::LineDelta(_, y) => *y,
+                    MouseScrollDelta::PixelDelta(position) => position.y as f32 / 20.0,
+                };
+                self.camera_controller.distance = (self.camera_controller.distance - lines)
+                    .max(1.0)
+                    .min(200.0);
+            }
+            _ => (),
+        }
+    }
+
+    fn handle_device_event(&mut self, event: &DeviceEvent) {
+        if let DeviceEvent::MouseMotion { delta } = event {
+            let controller = &mut self.camera_controller;
+            if controller.rotate {
+                controller.yaw -= delta.0 as f32 * 0.01;
+                controller.pitch = (controller.pitch + delta.1 as f32 * 0.01)
+                    .max(-89.0f32.to_radians())
+                    .min(89.0f32.to_radians());
+            }
+        }
+    }
+
+    fn update(&mut self, engine: &mut GameEngine) {
+        self.camera_controller
+            .update(&mut engine.scenes[self.scene]);
+    }
+}
+
+fn main() {
+    let event_loop = EventLoop::new();
+
+    let window_builder = rg3d::window::WindowBuilder::new()
+        .with_title("Example - Scene Editor")
+        .with_resizable(true);
+
+    let mut engine = GameEngine::new(window_builder, &event_loop).unwrap();
+
+    engine
+        .resource_manager
+        .lock()
+        .unwrap()
+        .set_textures_path(ASSETS_PATH);
+
+    engine
+        .renderer
+        .set_ambient_color(Color::opaque(200, 200, 200));
+
+    let mut editor = Editor::new(&mut engine);
+
+    let clock = Instant::now();
+    let fixed_timestep = 1.0 / 60.0;
+    let mut elapsed_time = 0.0;
+
+    event_loop.run(move |event, _, control_flow| match event {
+        Event::MainEventsCleared => {
+            let mut dt = clock.elapsed().as_secs_f32() - elapsed_time;
+            while dt >= fixed_timestep {
+                dt -= fixed_timestep;
+                elapsed_time += fixed_timestep;
+
+                editor.update(&mut engine);
+                engine.update(fixed_timestep);
+            }
+
+            while let Some(ui_message) = engine.user_interface.poll_message() {
+                editor.handle_ui_message(&ui_message, &mut engine);
+            }
+
+            engine.get_window().request_redraw();
+        }
+        Event::RedrawRequested(_) => {
+            engine.render(fixed_timestep).unwrap();
+        }
+        Event::WindowEvent { event, .. } => {
+            match event {
+                WindowEvent::CloseRequested => *control_flow = ControlFlow::Exit,
+                WindowEvent::Resized(size) => {
+                    engine.renderer.set_frame_size(size.into());
+                }
+                _ => (),
+            }
+
+            editor.handle_window_event(&event);
+
+            if let Some(os_event) = translate_event(&event) {
+                engine.user_interface.process_os_event(&os_event);
+            }
+        }
+        Event::DeviceEvent { event, .. } => {
+            editor.handle_device_event(&event);
+
+            if let DeviceEvent::Key(key) = event {
+                if key.state == ElementState::Pressed
+                    && key.virtual_keycode == Some(VirtualKeyCode::Escape)
+                {
+                    *control_flow = ControlFlow::Exit;
+                }
+            }
+        }
+        _ => *control_flow = ControlFlow::Poll,
+    });
+}
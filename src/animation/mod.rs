@@ -11,7 +11,7 @@ use crate::{
     },
     resource::model::Model,
     scene::{graph::Graph, node::Node},
-    utils::log::Log,
+    utils::{jobs::JobSystem, log::Log},
 };
 use std::{
     collections::{HashMap, VecDeque},
@@ -262,6 +262,7 @@ impl Visit for AnimationSignal {
 
 #[derive(Debug)]
 pub struct Animation {
+    name: String,
     // TODO: Extract into separate struct AnimationTimeline
     tracks: Vec<Track>,
     length: f32,
@@ -364,6 +365,7 @@ impl AnimationPose {
 impl Clone for Animation {
     fn clone(&self) -> Self {
         Self {
+            name: self.name.clone(),
             tracks: self.tracks.clone(),
             speed: self.speed,
             length: self.length,
@@ -379,6 +381,18 @@ impl Clone for Animation {
 }
 
 impl Animation {
+    /// Sets new name of the animation. Animations loaded from model resources are named
+    /// after clips (takes) of source file.
+    pub fn set_name(&mut self, name: &str) -> &mut Self {
+        self.name = name.to_owned();
+        self
+    }
+
+    /// Returns name of the animation.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
     pub fn add_track(&mut self, track: Track) {
         self.tracks.push(track);
 
@@ -532,8 +546,14 @@ impl Animation {
         // from which key frames should be taken on load.
         if let Some(resource) = self.resource.clone() {
             let resource = resource.lock().unwrap();
-            // TODO: Here we assume that resource contains only *one* animation.
-            if let Some(ref_animation) = resource.get_scene().animations.pool.at(0) {
+            // Find source clip by name, animations without name are taken from resources
+            // that have only one animation.
+            let animations = &resource.get_scene().animations;
+            let ref_animation = animations
+                .iter()
+                .find(|animation| animation.name == self.name)
+                .or_else(|| animations.pool.at(0));
+            if let Some(ref_animation) = ref_animation {
                 for track in self.get_tracks_mut() {
                     // This may panic if animation has track that refers to a deleted node,
                     // it can happen if you deleted a node but forgot to remove animation
@@ -590,6 +610,7 @@ impl Animation {
 impl Default for Animation {
     fn default() -> Self {
         Self {
+            name: Default::default(),
             tracks: Vec::new(),
             speed: 1.0,
             length: 0.0,
@@ -616,6 +637,7 @@ impl Visit for Animation {
         self.looped.visit("Looped", visitor)?;
         self.enabled.visit("Enabled", visitor)?;
         self.signals.visit("Signals", visitor)?;
+        let _ = self.name.visit("Name", visitor);
 
         visitor.leave_region()
     }
@@ -702,6 +724,15 @@ impl AnimationContainer {
         self.pool.borrow_mut(handle)
     }
 
+    /// Tries to find animation by its name. Returns Handle::NONE if no animation was found.
+    pub fn find_by_name(&self, name: &str) -> Handle<Animation> {
+        self.pool
+            .pair_iter()
+            .find(|(_, animation)| animation.name == name)
+            .map(|(handle, _)| handle)
+            .unwrap_or_default()
+    }
+
     #[inline]
     pub fn retain<P>(&mut self, pred: P)
     where
@@ -718,10 +749,19 @@ impl AnimationContainer {
         Log::writeln("Animations resolved successfully!".to_owned());
     }
 
+    /// Advances time of every enabled animation and calculates their poses. Animations
+    /// are independent from each other, so they're updated in parallel using
+    /// [global](crate::utils::jobs::JobSystem::global) job system, unless it is forced to
+    /// run serially.
     pub fn update_animations(&mut self, dt: f32) {
-        for animation in self.pool.iter_mut().filter(|anim| anim.enabled) {
-            animation.tick(dt);
-        }
+        let mut animations = self
+            .pool
+            .iter_mut()
+            .filter(|anim| anim.enabled)
+            .collect::<Vec<_>>();
+        JobSystem::global().parallel_for(&mut animations, |animation: &mut &mut Animation| {
+            animation.tick(dt)
+        });
     }
 }
 
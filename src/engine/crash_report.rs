@@ -0,0 +1,233 @@
+//! Crash reporting.
+//!
+//! [CrashReporter] installs a panic hook that writes a crash report into a file. The report
+//! contains panic message, backtrace, summary of the state of the engine (scenes, last frame
+//! statistics, loaded resources) and last messages of the [log](../../utils/log/index.html).
+//! Optional callback receives every report, it can be used to upload reports or to show a
+//! message to a player.
+//!
+//! Panic may happen on any thread, so the engine can't be inspected at the moment of panic.
+//! Instead, the engine refreshes its state summary periodically in [Engine::update] while
+//! crash reporter is installed, so report contains state which is at most one second old.
+//!
+//! [Engine::update]: ../struct.Engine.html#method.update
+//!
+//! # Example
+//!
+//! ```no_run
+//! use rg3d::engine::crash_report::CrashReporter;
+//!
+//! CrashReporter::new("crashes")
+//!     .with_callback(|report| println!("Game crashed: {}", report.message))
+//!     .install();
+//! ```
+
+use crate::{
+    engine::Engine,
+    gui::{message::MessageData, Control},
+    utils::log::Log,
+};
+use std::{
+    fmt::{Display, Formatter},
+    fs::{self, File},
+    io::Write,
+    panic::{self, PanicInfo},
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Mutex,
+    },
+    thread,
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+/// How often engine refreshes its state summary.
+const STATE_UPDATE_INTERVAL: Duration = Duration::from_secs(1);
+
+static INSTALLED: AtomicBool = AtomicBool::new(false);
+
+lazy_static! {
+    static ref ENGINE_STATE: Mutex<(Option<Instant>, String)> = Mutex::new((None, String::new()));
+}
+
+/// Information about a crash.
+#[derive(Clone, Debug)]
+pub struct CrashReport {
+    /// Seconds since UNIX epoch when crash has happened.
+    pub timestamp: u64,
+    /// Panic message.
+    pub message: String,
+    /// Location of the panic in source code, if known.
+    pub location: Option<String>,
+    /// Name of the thread that panicked.
+    pub thread: String,
+    /// Backtrace of the panicked thread.
+    pub backtrace: String,
+    /// Summary of the state of the engine, see [Engine::state_summary].
+    pub engine_state: String,
+    /// Last messages of the log, oldest first.
+    pub log_tail: Vec<String>,
+}
+
+impl Display for CrashReport {
+    fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
+        writeln!(
+            f,
+            "Crash report ({} seconds since UNIX epoch)",
+            self.timestamp
+        )?;
+        writeln!(
+            f,
+            "Thread '{}' panicked at '{}', {}",
+            self.thread,
+            self.message,
+            self.location.as_deref().unwrap_or("unknown location")
+        )?;
+        writeln!(f, "\n--- Engine state ---\n{}", self.engine_state)?;
+        writeln!(f, "--- Log ---")?;
+        for message in self.log_tail.iter() {
+            write!(f, "{}", message)?;
+        }
+        writeln!(f, "\n--- Backtrace ---\n{}", self.backtrace)
+    }
+}
+
+/// Callback that receives every crash report.
+pub type CrashCallback = Box<dyn Fn(&CrashReport) + Send + Sync>;
+
+/// Builder of the panic hook. See module docs.
+pub struct CrashReporter {
+    directory: PathBuf,
+    callback: Option<CrashCallback>,
+}
+
+impl CrashReporter {
+    /// Creates new crash reporter that writes reports into given directory. Directory is
+    /// created when first report is written.
+    pub fn new<P: Into<PathBuf>>(directory: P) -> Self {
+        Self {
+            directory: directory.into(),
+            callback: None,
+        }
+    }
+
+    /// Sets callback that receives every crash report after it is written to file.
+    pub fn with_callback<F: Fn(&CrashReport) + Send + Sync + 'static>(
+        mut self,
+        callback: F,
+    ) -> Self {
+        self.callback = Some(Box::new(callback));
+        self
+    }
+
+    /// Installs the panic hook. Previous panic hook is still called after the report is
+    /// written, so default panic message is printed as usual.
+    pub fn install(self) {
+        let previous_hook = panic::take_hook();
+        let CrashReporter {
+            directory,
+            callback,
+        } = self;
+        panic::set_hook(Box::new(move |info| {
+            let report = make_report(info);
+            match write_report(&directory, &report) {
+                Ok(path) => Log::writeln(format!("Crash report is written to {:?}", path)),
+                Err(e) => Log::writeln(format!("Unable to write crash report. Reason: {}", e)),
+            }
+            if let Some(callback) = callback.as_ref() {
+                callback(&report);
+            }
+            previous_hook(info);
+        }));
+        INSTALLED.store(true, Ordering::SeqCst);
+    }
+}
+
+/// Returns true if crash reporter was installed.
+pub fn is_installed() -> bool {
+    INSTALLED.load(Ordering::SeqCst)
+}
+
+fn make_report(info: &PanicInfo) -> CrashReport {
+    let message = if let Some(message) = info.payload().downcast_ref::<&str>() {
+        message.to_string()
+    } else if let Some(message) = info.payload().downcast_ref::<String>() {
+        message.clone()
+    } else {
+        "Unknown panic".to_owned()
+    };
+    // Panic could happen while summary is refreshed, then state is locked by this very
+    // thread and waiting for it would be a deadlock.
+    let engine_state = match ENGINE_STATE.try_lock() {
+        Ok(state) if state.0.is_some() => state.1.clone(),
+        _ => "Unknown".to_owned(),
+    };
+    CrashReport {
+        timestamp: SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |duration| duration.as_secs()),
+        message,
+        location: info
+            .location()
+            .map(|location| format!("{}:{}", location.file(), location.line())),
+        thread: thread::current().name().unwrap_or("<unnamed>").to_owned(),
+        backtrace: format!("{:?}", backtrace::Backtrace::new()),
+        engine_state,
+        log_tail: Log::tail(),
+    }
+}
+
+fn write_report(directory: &Path, report: &CrashReport) -> std::io::Result<PathBuf> {
+    fs::create_dir_all(directory)?;
+    let path = directory.join(format!("crash-{}.txt", report.timestamp));
+    File::create(&path)?.write_all(report.to_string().as_bytes())?;
+    Ok(path)
+}
+
+/// Refreshes state summary of the engine if crash reporter is installed and summary is
+/// outdated.
+pub(in crate) fn update_engine_state<F: FnOnce() -> String>(summary: F) {
+    if !is_installed() {
+        return;
+    }
+    // Mutex is poisoned if summary has panicked, state is overwritten anyway.
+    let mut state = ENGINE_STATE.lock().unwrap_or_else(|e| e.into_inner());
+    let outdated = state
+        .0
+        .map_or(true, |time| time.elapsed() >= STATE_UPDATE_INTERVAL);
+    if outdated {
+        *state = (Some(Instant::now()), summary());
+    }
+}
+
+impl<M: MessageData, C: Control<M, C>> Engine<M, C> {
+    /// Returns text summary of the state of the engine - scenes, statistics of last frame and
+    /// amounts of loaded resources. It is included in crash reports.
+    pub fn state_summary(&self) -> String {
+        let statistics = self.renderer.get_statistics();
+        let mut summary = format!(
+            "FPS: {}\nFrame time: {:.2} ms\nDraw calls: {}\nTriangles: {}\n",
+            statistics.frames_per_second,
+            statistics.pure_frame_time * 1000.0,
+            statistics.geometry.draw_calls,
+            statistics.geometry.triangles_rendered,
+        );
+        for (i, scene) in self.scenes.iter().enumerate() {
+            summary += &format!("Scene {}: {} nodes\n", i, scene.graph.node_count());
+        }
+        // Resource manager can be locked by a loader thread, do not wait for it.
+        match self.resource_manager.try_lock() {
+            Ok(resource_manager) => {
+                summary += &format!(
+                    "Textures: {}\nModels: {}\nSound buffers: {}\nFonts: {}\n",
+                    resource_manager.textures().len(),
+                    resource_manager.models().len(),
+                    resource_manager.sound_buffers().len(),
+                    resource_manager.fonts().len(),
+                );
+            }
+            Err(_) => summary += "Resources: unknown, resource manager is busy\n",
+        }
+        summary
+    }
+}
@@ -0,0 +1,266 @@
+//! Deterministic simulation mode.
+//!
+//! Lockstep networking and verification of replays require that the same sequence of inputs
+//! produces exactly the same simulation on every run and on every machine. [Simulation] gives
+//! everything that is needed for this on the engine side:
+//!
+//! - Fixed time step. Wall clock only decides how many steps are performed during a frame,
+//! every step is performed with the same `dt`, so result does not depend on frame rate.
+//! Simulation time is a number of steps multiplied by time step, it never reads the clock.
+//! - Seeded random number generator, see [Random](../../utils/random/struct.Random.html).
+//! Gameplay code must use it instead of `rand::thread_rng`.
+//! - Consistent float behavior, see [FloatMode].
+//! - Serial update of scenes. Nodes and animations are normally updated in parallel by
+//! workers of [job system](crate::utils::jobs::JobSystem::global), whose float mode is not
+//! controlled and whose amount depends on the machine. While simulation exists, the job
+//! system is forced to run serially, so the whole update is done on the thread that has
+//! created simulation - engine must be updated on that thread.
+//! - Checksum of state of scenes to detect desync of peers or mismatch of replay.
+//!
+//! Update order of the engine is fixed: events of event bus, scheduler, resources, scenes in
+//! order of creation, nodes of each scene in order of their handles, then user interface.
+//! Particle systems use their own random numbers and are not deterministic, they must be used
+//! only for visual effects which do not affect gameplay.
+//!
+//! # Example
+//!
+//! ```no_run
+//! # use rg3d::engine::determinism::{Simulation, SimulationSettings};
+//! # use rg3d::{engine::Engine, gui::node::StubNode};
+//! # fn f(engine: &mut Engine<(), StubNode>, frame_time: f32) {
+//! let mut simulation = Simulation::new(SimulationSettings {
+//!     seed: 42,
+//!     ..Default::default()
+//! });
+//!
+//! // Every frame:
+//! for _ in 0..simulation.advance(frame_time) {
+//!     // Apply inputs of current tick, run gameplay with simulation.rng().
+//!     engine.update(simulation.time_step());
+//!     simulation.finish_step();
+//! }
+//! let checksum = simulation.checksum(&engine.scenes);
+//! # }
+//! ```
+
+use crate::{
+    scene::SceneContainer,
+    utils::{jobs::JobSystem, random::Random},
+};
+
+/// Floating point behavior of current thread.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum FloatMode {
+    /// Leave settings of OS and other libraries as is.
+    Default,
+    /// Round to nearest and flush denormal numbers to zero. Denormals are handled differently
+    /// by different hardware and some libraries change this mode implicitly, so fixing it
+    /// makes results reproducible. Only supported on x86 and x86_64, on other architectures
+    /// it is the same as [FloatMode::Default].
+    Strict,
+}
+
+/// Sets floating point behavior of current thread. Returns false if mode is not supported.
+pub fn set_float_mode(mode: FloatMode) -> bool {
+    match mode {
+        FloatMode::Default => true,
+        FloatMode::Strict => set_strict_float_mode(),
+    }
+}
+
+#[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+#[allow(deprecated)]
+fn set_strict_float_mode() -> bool {
+    #[cfg(target_arch = "x86")]
+    use std::arch::x86::{_mm_getcsr, _mm_setcsr};
+    #[cfg(target_arch = "x86_64")]
+    use std::arch::x86_64::{_mm_getcsr, _mm_setcsr};
+
+    // Bits of MXCSR register.
+    const ROUNDING_MASK: u32 = 0b11 << 13;
+    const FLUSH_TO_ZERO: u32 = 1 << 15;
+    const DENORMALS_ARE_ZERO: u32 = 1 << 6;
+
+    unsafe {
+        let csr = _mm_getcsr() & !ROUNDING_MASK;
+        _mm_setcsr(csr | FLUSH_TO_ZERO | DENORMALS_ARE_ZERO);
+    }
+    true
+}
+
+#[cfg(not(any(target_arch = "x86", target_arch = "x86_64")))]
+fn set_strict_float_mode() -> bool {
+    false
+}
+
+/// Parameters of deterministic simulation, every peer and replay must use the same.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct SimulationSettings {
+    /// Seed of random number generator.
+    pub seed: u64,
+    /// Duration of one step in seconds.
+    pub time_step: f32,
+    /// Maximum amount of steps per frame. If machine can't keep up, simulation slows down
+    /// instead of spending more and more time to catch up.
+    pub max_steps_per_frame: u32,
+    /// Floating point behavior, it is applied to the thread that creates simulation. Update
+    /// of scenes must be done on the same thread.
+    pub float_mode: FloatMode,
+}
+
+impl Default for SimulationSettings {
+    fn default() -> Self {
+        Self {
+            seed: 0,
+            time_step: 1.0 / 60.0,
+            max_steps_per_frame: 8,
+            float_mode: FloatMode::Strict,
+        }
+    }
+}
+
+/// State of deterministic simulation. See module docs.
+pub struct Simulation {
+    settings: SimulationSettings,
+    rng: Random,
+    tick: u64,
+    accumulator: f32,
+    /// Serial mode of job system before simulation was created.
+    was_serial: bool,
+}
+
+fn hash(state: &mut u64, value: u32) {
+    // FNV-1a over bytes of the value.
+    for byte in value.to_le_bytes().iter() {
+        *state = (*state ^ *byte as u64).wrapping_mul(0x0100_0000_01b3);
+    }
+}
+
+impl Simulation {
+    /// Creates new simulation at tick zero, applies float mode to current thread and forces
+    /// serial update of scenes until simulation is dropped.
+    pub fn new(settings: SimulationSettings) -> Self {
+        set_float_mode(settings.float_mode);
+        let jobs = JobSystem::global();
+        let was_serial = jobs.is_serial();
+        jobs.set_serial(true);
+        Self {
+            settings,
+            rng: Random::new(settings.seed),
+            tick: 0,
+            accumulator: 0.0,
+            was_serial,
+        }
+    }
+
+    /// Returns settings of the simulation.
+    pub fn settings(&self) -> &SimulationSettings {
+        &self.settings
+    }
+
+    /// Returns duration of one step.
+    pub fn time_step(&self) -> f32 {
+        self.settings.time_step
+    }
+
+    /// Returns index of current step.
+    pub fn tick(&self) -> u64 {
+        self.tick
+    }
+
+    /// Returns simulation time in seconds, it is calculated from amount of steps.
+    pub fn time(&self) -> f64 {
+        self.tick as f64 * self.settings.time_step as f64
+    }
+
+    /// Returns random number generator of the simulation.
+    pub fn rng(&mut self) -> &mut Random {
+        &mut self.rng
+    }
+
+    /// Accumulates real time of a frame and returns amount of steps that must be performed.
+    /// This is the only place where wall clock affects simulation and it affects only
+    /// amount of steps, not their result.
+    pub fn advance(&mut self, frame_time: f32) -> u32 {
+        self.accumulator += frame_time.max(0.0);
+        let steps = (self.accumulator / self.settings.time_step) as u32;
+        let steps = steps.min(self.settings.max_steps_per_frame);
+        self.accumulator -= steps as f32 * self.settings.time_step;
+        // Drop time that can't be caught up.
+        self.accumulator = self.accumulator.min(self.settings.time_step);
+        steps
+    }
+
+    /// Must be called after every step of simulation.
+    pub fn finish_step(&mut self) {
+        self.tick += 1;
+    }
+
+    /// Returns checksum of transforms of every node of every scene and of state of random
+    /// number generator. Peers that are in sync have equal checksums on equal ticks.
+    pub fn checksum(&self, scenes: &SceneContainer) -> u64 {
+        let mut state = 0xcbf2_9ce4_8422_2325u64;
+        let mut rng = self.rng;
+        hash(&mut state, rng.range_int(0, std::i32::MAX) as u32);
+        for scene in scenes.iter() {
+            for (_, node) in scene.graph.pair_iter() {
+                let transform = node.local_transform();
+                let position = transform.position();
+                let rotation = transform.rotation();
+                let scale = transform.scale();
+                for value in [
+                    position.x, position.y, position.z, rotation.x, rotation.y, rotation.z,
+                    rotation.w, scale.x, scale.y, scale.z,
+                ]
+                .iter()
+                {
+                    hash(&mut state, value.to_bits());
+                }
+            }
+        }
+        state
+    }
+}
+
+impl Drop for Simulation {
+    fn drop(&mut self) {
+        JobSystem::global().set_serial(self.was_serial);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        engine::determinism::{FloatMode, Simulation, SimulationSettings},
+        scene::{base::BaseBuilder, node::Node, Scene, SceneContainer},
+    };
+
+    #[test]
+    fn simulation_test() {
+        let settings = SimulationSettings {
+            seed: 7,
+            time_step: 0.25,
+            max_steps_per_frame: 3,
+            float_mode: FloatMode::Default,
+        };
+        let mut simulation = Simulation::new(settings);
+        assert_eq!(simulation.advance(0.375), 1);
+        assert_eq!(simulation.advance(0.125), 1);
+        // Too long frame is clamped.
+        assert_eq!(simulation.advance(10.0), 3);
+        assert_eq!(simulation.advance(0.0), 1);
+
+        let make_scenes = || {
+            let mut scenes = SceneContainer::new();
+            let mut scene = Scene::new();
+            scene.graph.add_node(Node::Base(BaseBuilder::new().build()));
+            scenes.add(scene);
+            scenes
+        };
+        let scenes = make_scenes();
+        let mut other = Simulation::new(settings);
+        assert_eq!(simulation.checksum(&scenes), other.checksum(&make_scenes()));
+        other.rng().next_f32();
+        assert_ne!(simulation.checksum(&scenes), other.checksum(&scenes));
+    }
+}
@@ -0,0 +1,176 @@
+//! Runtime control of main window - window modes, resolution, title, icon and cursor. Useful
+//! to make video settings menu.
+//!
+//! Changes of size of the window are reported by `WindowEvent::Resized` event, as usual, so
+//! game must keep passing new size to `Renderer::set_frame_size`.
+
+use crate::{
+    dpi::PhysicalSize,
+    engine::{error::EngineError, Engine},
+    gui::{message::MessageData, Control},
+    monitor::{MonitorHandle, VideoMode as WinitVideoMode},
+    window::{Fullscreen, Icon},
+};
+use std::path::Path;
+
+/// Video mode of a monitor.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct VideoMode {
+    /// Width in pixels.
+    pub width: u32,
+    /// Height in pixels.
+    pub height: u32,
+    /// Refresh rate in Hz.
+    pub refresh_rate: u16,
+    /// Bits per pixel.
+    pub bit_depth: u16,
+}
+
+impl From<&WinitVideoMode> for VideoMode {
+    fn from(mode: &WinitVideoMode) -> Self {
+        let size = mode.size();
+        Self {
+            width: size.width,
+            height: size.height,
+            refresh_rate: mode.refresh_rate(),
+            bit_depth: mode.bit_depth(),
+        }
+    }
+}
+
+/// Information about a monitor.
+#[derive(Clone, Debug)]
+pub struct MonitorInfo {
+    /// Name of the monitor, if OS provides it.
+    pub name: Option<String>,
+    /// Current resolution of the monitor in pixels.
+    pub size: (u32, u32),
+    /// Position of top-left corner of the monitor on virtual desktop.
+    pub position: (i32, i32),
+    /// Ratio of physical pixels to logical pixels (DPI scale).
+    pub scale_factor: f64,
+    /// Every video mode supported by the monitor, highest resolution first.
+    pub video_modes: Vec<VideoMode>,
+}
+
+impl From<&MonitorHandle> for MonitorInfo {
+    fn from(monitor: &MonitorHandle) -> Self {
+        let size = monitor.size();
+        let position = monitor.position();
+        let mut video_modes = monitor
+            .video_modes()
+            .map(|mode| VideoMode::from(&mode))
+            .collect::<Vec<_>>();
+        video_modes.sort_by(|a, b| {
+            (b.width * b.height, b.refresh_rate, b.bit_depth).cmp(&(
+                a.width * a.height,
+                a.refresh_rate,
+                a.bit_depth,
+            ))
+        });
+        video_modes.dedup();
+        Self {
+            name: monitor.name(),
+            size: (size.width, size.height),
+            position: (position.x, position.y),
+            scale_factor: monitor.scale_factor(),
+            video_modes,
+        }
+    }
+}
+
+/// Mode of main window.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum WindowMode {
+    /// Usual window with decorations.
+    Windowed,
+    /// Window without decorations that covers whole current monitor, video mode of the
+    /// monitor is not changed, so switching is fast.
+    Borderless,
+    /// Exclusive fullscreen with given video mode of current monitor.
+    Fullscreen(VideoMode),
+}
+
+impl<M: MessageData, C: Control<M, C>> Engine<M, C> {
+    /// Returns information about every monitor.
+    pub fn monitors(&self) -> Vec<MonitorInfo> {
+        self.get_window()
+            .available_monitors()
+            .map(|monitor| MonitorInfo::from(&monitor))
+            .collect()
+    }
+
+    /// Returns information about monitor on which the window is.
+    pub fn current_monitor(&self) -> MonitorInfo {
+        MonitorInfo::from(&self.get_window().current_monitor())
+    }
+
+    /// Returns current mode of the window.
+    pub fn window_mode(&self) -> WindowMode {
+        match self.get_window().fullscreen() {
+            None => WindowMode::Windowed,
+            Some(Fullscreen::Borderless(_)) => WindowMode::Borderless,
+            Some(Fullscreen::Exclusive(mode)) => WindowMode::Fullscreen(VideoMode::from(&mode)),
+        }
+    }
+
+    /// Switches the window to given mode. Video mode of exclusive fullscreen must be one of
+    /// the video modes of current monitor, see [Engine::current_monitor].
+    pub fn set_window_mode(&mut self, mode: WindowMode) -> Result<(), EngineError> {
+        let window = self.get_window();
+        let fullscreen = match mode {
+            WindowMode::Windowed => None,
+            WindowMode::Borderless => Some(Fullscreen::Borderless(window.current_monitor())),
+            WindowMode::Fullscreen(video_mode) => {
+                let winit_mode = window
+                    .current_monitor()
+                    .video_modes()
+                    .find(|m| VideoMode::from(m) == video_mode)
+                    .ok_or(EngineError::UnsupportedVideoMode(video_mode))?;
+                Some(Fullscreen::Exclusive(winit_mode))
+            }
+        };
+        window.set_fullscreen(fullscreen);
+        Ok(())
+    }
+
+    /// Sets size of client area of the window in pixels. It has no effect in fullscreen
+    /// modes, change video mode by [Engine::set_window_mode] instead.
+    pub fn set_window_size(&mut self, width: u32, height: u32) {
+        self.get_window()
+            .set_inner_size(PhysicalSize::new(width, height));
+    }
+
+    /// Returns size of client area of the window in pixels.
+    pub fn window_size(&self) -> (u32, u32) {
+        let size = self.get_window().inner_size();
+        (size.width, size.height)
+    }
+
+    /// Sets title of the window.
+    pub fn set_window_title(&mut self, title: &str) {
+        self.get_window().set_title(title);
+    }
+
+    /// Sets icon of the window from an image file (png, tga, etc.).
+    pub fn set_window_icon<P: AsRef<Path>>(&mut self, path: P) -> Result<(), EngineError> {
+        let image = image::open(path.as_ref())
+            .map_err(|e| EngineError::InvalidIcon(e.to_string()))?
+            .to_rgba();
+        let (width, height) = image.dimensions();
+        let icon = Icon::from_rgba(image.into_raw(), width, height)
+            .map_err(|e| EngineError::InvalidIcon(e.to_string()))?;
+        self.get_window().set_window_icon(Some(icon));
+        Ok(())
+    }
+
+    /// Confines cursor to the window, so it can't leave it. Useful for games with mouse look.
+    pub fn set_cursor_grab(&mut self, grab: bool) -> Result<(), EngineError> {
+        Ok(self.get_window().set_cursor_grab(grab)?)
+    }
+
+    /// Shows or hides cursor while it is over the window.
+    pub fn set_cursor_visible(&mut self, visible: bool) {
+        self.get_window().set_cursor_visible(visible);
+    }
+}
@@ -1,6 +1,9 @@
 //! All possible errors that can happen in the engine.
 
-use crate::{renderer::error::RendererError, sound::error::SoundError};
+use crate::{
+    engine::display::VideoMode, error::ExternalError, renderer::error::RendererError,
+    sound::error::SoundError,
+};
 use glutin::{ContextError, CreationError};
 
 /// See module docs.
@@ -14,6 +17,12 @@ pub enum EngineError {
     ContextCreationError(CreationError),
     /// Runtime OpenGL context error.
     ContextError(ContextError),
+    /// Operation on a window is not supported by platform.
+    WindowError(ExternalError),
+    /// Current monitor does not support given video mode.
+    UnsupportedVideoMode(VideoMode),
+    /// Icon of a window could not be loaded.
+    InvalidIcon(String),
 }
 
 impl From<SoundError> for EngineError {
@@ -39,3 +48,9 @@ impl From<ContextError> for EngineError {
         EngineError::ContextError(e)
     }
 }
+
+impl From<ExternalError> for EngineError {
+    fn from(e: ExternalError) -> Self {
+        EngineError::WindowError(e)
+    }
+}
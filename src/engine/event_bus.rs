@@ -0,0 +1,220 @@
+//! Typed publish/subscribe event bus.
+//!
+//! Event bus allows decoupled systems (gameplay, UI, audio, analytics, etc.) to talk to each
+//! other without direct references. Any `Clone + Send` type can be an event. A system that is
+//! interested in some type of events subscribes to it and receives [Subscription], other
+//! systems publish events of that type and don't know anything about subscribers.
+//!
+//! Delivery is deferred: published events are queued and delivered all at once in
+//! [EventBus::dispatch], the engine calls it at the beginning of [Engine::update], so events
+//! published during a frame are received by every subscriber during the next frame in order
+//! of publishing. This means that there is no re-entrance - subscriber never receives an
+//! event while it is publishing one.
+//!
+//! [Engine::update]: ../struct.Engine.html#method.update
+//!
+//! # Example
+//!
+//! ```
+//! use rg3d::engine::event_bus::EventBus;
+//!
+//! #[derive(Clone)]
+//! struct EnemyKilled {
+//!     score: u32,
+//! }
+//!
+//! let mut bus = EventBus::new();
+//! let score_counter = bus.subscribe::<EnemyKilled>();
+//!
+//! bus.publish(EnemyKilled { score: 100 });
+//! bus.dispatch();
+//!
+//! let total = score_counter.iter().map(|e| e.score).sum::<u32>();
+//! assert_eq!(total, 100);
+//! ```
+
+use std::{
+    any::{Any, TypeId},
+    collections::HashMap,
+    sync::{
+        mpsc::{self, Receiver, Sender},
+        Arc, Mutex,
+    },
+};
+
+type PendingEvents = Arc<Mutex<Vec<(TypeId, Box<dyn Any + Send>)>>>;
+
+trait Channel {
+    fn deliver(&mut self, event: Box<dyn Any + Send>);
+
+    fn subscriber_count(&self) -> usize;
+
+    fn as_any_mut(&mut self) -> &mut dyn Any;
+}
+
+struct TypedChannel<T> {
+    subscribers: Vec<Sender<T>>,
+}
+
+impl<T: Clone + Send + 'static> Channel for TypedChannel<T> {
+    fn deliver(&mut self, event: Box<dyn Any + Send>) {
+        if let Ok(event) = event.downcast::<T>() {
+            // Sending fails only if subscription was dropped, so forget such subscribers.
+            self.subscribers
+                .retain(|subscriber| subscriber.send((*event).clone()).is_ok());
+        }
+    }
+
+    fn subscriber_count(&self) -> usize {
+        self.subscribers.len()
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
+
+/// Receiving side of a subscription to events of some type. Dropping subscription
+/// unsubscribes from events.
+pub struct Subscription<T> {
+    receiver: Receiver<T>,
+}
+
+impl<T> Subscription<T> {
+    /// Takes next received event, if any.
+    pub fn poll(&self) -> Option<T> {
+        self.receiver.try_recv().ok()
+    }
+
+    /// Returns iterator that takes every received event.
+    pub fn iter(&self) -> impl Iterator<Item = T> + '_ {
+        self.receiver.try_iter()
+    }
+}
+
+/// Publishing side of the event bus that can be cloned and sent to other threads.
+#[derive(Clone)]
+pub struct EventSender {
+    pending: PendingEvents,
+}
+
+impl EventSender {
+    /// Queues event for delivery on next dispatch of the bus.
+    pub fn publish<T: Clone + Send + 'static>(&self, event: T) {
+        self.pending
+            .lock()
+            .unwrap()
+            .push((TypeId::of::<T>(), Box::new(event)));
+    }
+}
+
+/// See module docs.
+pub struct EventBus {
+    channels: HashMap<TypeId, Box<dyn Channel>>,
+    pending: PendingEvents,
+}
+
+impl Default for EventBus {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl EventBus {
+    /// Creates new event bus without subscribers.
+    pub fn new() -> Self {
+        Self {
+            channels: Default::default(),
+            pending: Default::default(),
+        }
+    }
+
+    /// Subscribes to events of given type. Subscription receives only events that were
+    /// published after subscribing.
+    pub fn subscribe<T: Clone + Send + 'static>(&mut self) -> Subscription<T> {
+        let (sender, receiver) = mpsc::channel();
+        let channel = self.channels.entry(TypeId::of::<T>()).or_insert_with(|| {
+            Box::new(TypedChannel::<T> {
+                subscribers: Default::default(),
+            })
+        });
+        // Channels are stored by type id of their events, so downcast can't fail.
+        channel
+            .as_any_mut()
+            .downcast_mut::<TypedChannel<T>>()
+            .unwrap()
+            .subscribers
+            .push(sender);
+        Subscription { receiver }
+    }
+
+    /// Queues event for delivery on next dispatch.
+    pub fn publish<T: Clone + Send + 'static>(&self, event: T) {
+        self.sender().publish(event)
+    }
+
+    /// Returns publishing side of the bus, it can be used from other threads or stored in
+    /// systems that must not have access to the whole bus.
+    pub fn sender(&self) -> EventSender {
+        EventSender {
+            pending: self.pending.clone(),
+        }
+    }
+
+    /// Returns amount of alive subscriptions to events of given type. Dropped subscriptions
+    /// are detected only on delivery of an event.
+    pub fn subscriber_count<T: 'static>(&self) -> usize {
+        self.channels
+            .get(&TypeId::of::<T>())
+            .map_or(0, |channel| channel.subscriber_count())
+    }
+
+    /// Delivers every queued event to subscribers. Events without subscribers are discarded.
+    /// Called by the engine once per frame, call it manually only if you use the bus
+    /// outside of the engine.
+    pub fn dispatch(&mut self) {
+        let pending = std::mem::take(&mut *self.pending.lock().unwrap());
+        for (type_id, event) in pending {
+            if let Some(channel) = self.channels.get_mut(&type_id) {
+                channel.deliver(event);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::engine::event_bus::EventBus;
+
+    #[derive(Clone, Debug, PartialEq)]
+    struct Damage(u32);
+
+    #[derive(Clone, Debug, PartialEq)]
+    struct Heal(u32);
+
+    #[test]
+    fn event_bus_test() {
+        let mut bus = EventBus::new();
+        let a = bus.subscribe::<Damage>();
+        let b = bus.subscribe::<Damage>();
+        let heal = bus.subscribe::<Heal>();
+
+        bus.publish(Damage(1));
+        bus.sender().publish(Damage(2));
+        bus.publish(Heal(3));
+
+        // Delivery is deferred.
+        assert_eq!(a.poll(), None);
+        bus.dispatch();
+        assert_eq!(a.iter().collect::<Vec<_>>(), vec![Damage(1), Damage(2)]);
+        assert_eq!(b.iter().collect::<Vec<_>>(), vec![Damage(1), Damage(2)]);
+        assert_eq!(heal.poll(), Some(Heal(3)));
+        assert_eq!(heal.poll(), None);
+
+        drop(b);
+        bus.publish(Damage(4));
+        bus.dispatch();
+        assert_eq!(bus.subscriber_count::<Damage>(), 1);
+        assert_eq!(a.poll(), Some(Damage(4)));
+    }
+}
@@ -0,0 +1,326 @@
+//! Hot reload of game logic compiled as a dynamic library, available with `hot_reload` feature.
+//!
+//! Compile-run-quit cycle takes most of the time when tuning gameplay. In development mode game
+//! logic can be moved into a separate crate that is compiled as a dynamic library, while the
+//! executable only creates the engine and [HotReloader]. Reloader watches the library file and
+//! when it is rebuilt (for example by `cargo watch -x build` running in background) loads new
+//! version without restarting the game. Engine with its scenes, resources and window persists
+//! across reloads, state of game logic itself is handed over by serializing it with
+//! [Visit](../../core/visitor/trait.Visit.html) and deserializing into new instance - the same
+//! way the state is saved in save files.
+//!
+//! # Game library
+//!
+//! Library must export constructor of game logic using [export_game_logic] macro:
+//!
+//! ```ignore
+//! // Cargo.toml of game crate: crate-type = ["cdylib"]
+//! use rg3d::{
+//!     core::visitor::{Visit, VisitResult, Visitor},
+//!     engine::{hot_reload::GameLogic, Engine},
+//!     gui::node::StubNode,
+//! };
+//!
+//! #[derive(Default)]
+//! struct Game {
+//!     score: u32,
+//! }
+//!
+//! impl Visit for Game {
+//!     fn visit(&mut self, name: &str, visitor: &mut Visitor) -> VisitResult {
+//!         visitor.enter_region(name)?;
+//!         self.score.visit("Score", visitor)?;
+//!         visitor.leave_region()
+//!     }
+//! }
+//!
+//! impl GameLogic<(), StubNode> for Game {
+//!     fn update(&mut self, engine: &mut Engine<(), StubNode>, dt: f32) {
+//!         // ...
+//!     }
+//! }
+//!
+//! rg3d::export_game_logic!(Game, (), StubNode);
+//! ```
+//!
+//! # Limitations
+//!
+//! - Library and executable must be built by the same compiler with the same version and
+//! features of the engine, there is no ABI check.
+//! - Any code or data that belongs to the library becomes invalid when it is unloaded. Game
+//! logic must remove everything it has put into the engine that refers to its code - callbacks
+//! of [scheduler](../scheduler/index.html), custom UI nodes, etc. - in [GameLogic::on_unload].
+//! - Library has its own copies of global state of the engine (log, profiler, etc.).
+//!
+//! Hot reload is meant for development only, release builds should link game logic
+//! statically.
+
+use crate::{
+    core::visitor::{Visit, VisitError, Visitor},
+    engine::Engine,
+    gui::{message::MessageData, Control},
+    utils::log::Log,
+};
+use libloading::Library;
+use std::{
+    fmt::{Display, Formatter},
+    mem::ManuallyDrop,
+    path::{Path, PathBuf},
+    time::{Duration, Instant, SystemTime},
+};
+
+/// Name of constructor of game logic exported by game library.
+pub const ENTRY_POINT: &str = "rg3d_create_game_logic";
+
+/// Signature of constructor of game logic exported by game library.
+pub type CreateGameLogic<M, C> = fn() -> Box<dyn GameLogic<M, C>>;
+
+/// How often reloader checks whether the library was rebuilt.
+const CHECK_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Game logic that lives in a dynamic library. See module docs.
+pub trait GameLogic<M: MessageData, C: Control<M, C>>: Visit {
+    /// Called when logic is created, `reloaded` is true if the state was restored from
+    /// previous version of the library.
+    fn on_load(&mut self, _engine: &mut Engine<M, C>, _reloaded: bool) {}
+
+    /// Called every frame.
+    fn update(&mut self, engine: &mut Engine<M, C>, dt: f32);
+
+    /// Called right before library is unloaded, logic must remove everything from the engine
+    /// that refers to code of the library.
+    fn on_unload(&mut self, _engine: &mut Engine<M, C>) {}
+}
+
+/// Exports constructor of game logic from game library. Logic type must implement `Default`,
+/// message and node types must match the types of the engine of the executable.
+#[macro_export]
+macro_rules! export_game_logic {
+    ($logic:ty, $message:ty, $node:ty) => {
+        #[no_mangle]
+        pub fn rg3d_create_game_logic(
+        ) -> Box<dyn $crate::engine::hot_reload::GameLogic<$message, $node>> {
+            Box::new(<$logic>::default())
+        }
+    };
+}
+
+/// All possible errors that can occur during (re)loading of game library.
+#[derive(Debug)]
+pub enum HotReloadError {
+    /// An input/output error has occurred.
+    Io(std::io::Error),
+    /// Library can't be loaded or does not export [ENTRY_POINT].
+    Library(libloading::Error),
+    /// State of game logic can't be handed over to new version of the library.
+    Visit(VisitError),
+}
+
+impl Display for HotReloadError {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), std::fmt::Error> {
+        match self {
+            HotReloadError::Io(io) => write!(f, "Io error: {}", io),
+            HotReloadError::Library(library) => write!(f, "Library error: {}", library),
+            HotReloadError::Visit(visit) => write!(f, "Visit error: {:?}", visit),
+        }
+    }
+}
+
+impl From<std::io::Error> for HotReloadError {
+    fn from(err: std::io::Error) -> Self {
+        HotReloadError::Io(err)
+    }
+}
+
+impl From<libloading::Error> for HotReloadError {
+    fn from(err: libloading::Error) -> Self {
+        HotReloadError::Library(err)
+    }
+}
+
+impl From<VisitError> for HotReloadError {
+    fn from(err: VisitError) -> Self {
+        HotReloadError::Visit(err)
+    }
+}
+
+fn modification_time(path: &Path) -> Option<SystemTime> {
+    std::fs::metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .ok()
+}
+
+/// Loaded copy of game library with logic created by it. Copy of the library is removed when
+/// it is dropped.
+struct LoadedLibrary<M: MessageData, C: Control<M, C>> {
+    logic: ManuallyDrop<Box<dyn GameLogic<M, C>>>,
+    library: ManuallyDrop<Library>,
+    path: PathBuf,
+}
+
+impl<M: MessageData, C: Control<M, C>> Drop for LoadedLibrary<M, C> {
+    fn drop(&mut self) {
+        // Logic must be dropped before library, its code and vtable belong to the library.
+        // Library must be unloaded before its file can be removed.
+        unsafe {
+            ManuallyDrop::drop(&mut self.logic);
+            ManuallyDrop::drop(&mut self.library);
+        }
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
+
+/// Loads game library and reloads it when it is rebuilt. See module docs.
+pub struct HotReloader<M: MessageData, C: Control<M, C>> {
+    source: PathBuf,
+    current: Option<LoadedLibrary<M, C>>,
+    loaded_time: Option<SystemTime>,
+    /// Modification time seen on previous check, library is reloaded only when its file
+    /// stops changing, so half-written file is never loaded.
+    pending_time: Option<SystemTime>,
+    last_check: Instant,
+    generation: usize,
+}
+
+impl<M: MessageData, C: Control<M, C>> HotReloader<M, C> {
+    /// Loads game library from given path and creates game logic.
+    pub fn new<P: AsRef<Path>>(path: P, engine: &mut Engine<M, C>) -> Result<Self, HotReloadError> {
+        let mut reloader = Self {
+            source: path.as_ref().to_owned(),
+            current: None,
+            loaded_time: None,
+            pending_time: None,
+            last_check: Instant::now(),
+            generation: 0,
+        };
+        reloader.reload(engine)?;
+        Ok(reloader)
+    }
+
+    /// Returns how many times library was loaded, including initial load.
+    pub fn generation(&self) -> usize {
+        self.generation
+    }
+
+    /// Returns game logic.
+    pub fn logic(&self) -> Option<&dyn GameLogic<M, C>> {
+        self.current.as_ref().map(|current| &**current.logic)
+    }
+
+    /// Returns game logic.
+    pub fn logic_mut(&mut self) -> Option<&mut dyn GameLogic<M, C>> {
+        self.current.as_mut().map(|current| &mut **current.logic)
+    }
+
+    /// Makes a copy of the library with unique name, so the original file can be replaced by
+    /// compiler while library is loaded and the OS does not return cached old version.
+    fn make_copy(&self) -> Result<PathBuf, HotReloadError> {
+        let name = self
+            .source
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_default();
+        let path = std::env::temp_dir().join(format!(
+            "{}-{}-{}",
+            std::process::id(),
+            self.generation,
+            name
+        ));
+        std::fs::copy(&self.source, &path)?;
+        Ok(path)
+    }
+
+    /// Loads current version of the library, hands state of game logic over to new version.
+    /// Previous version stays loaded if new one can't be loaded.
+    pub fn reload(&mut self, engine: &mut Engine<M, C>) -> Result<(), HotReloadError> {
+        let time = modification_time(&self.source);
+        let path = self.make_copy()?;
+        let library = match Library::new(&path) {
+            Ok(library) => library,
+            Err(e) => {
+                let _ = std::fs::remove_file(&path);
+                return Err(e.into());
+            }
+        };
+        let entry_point = format!("{}\0", ENTRY_POINT);
+        let logic = unsafe {
+            library
+                .get::<CreateGameLogic<M, C>>(entry_point.as_bytes())
+                .map(|create| create())
+        };
+        let logic = match logic {
+            Ok(logic) => logic,
+            Err(e) => {
+                drop(library);
+                let _ = std::fs::remove_file(&path);
+                return Err(e.into());
+            }
+        };
+        let mut new = LoadedLibrary {
+            logic: ManuallyDrop::new(logic),
+            library: ManuallyDrop::new(library),
+            path,
+        };
+
+        let reloaded = if let Some(old) = self.current.as_mut() {
+            // Visitor can't switch from writing to reading, so state goes through a file.
+            let state_path = new.path.with_extension("state");
+            let mut visitor = Visitor::new();
+            let result = old
+                .logic
+                .visit("State", &mut visitor)
+                .and_then(|_| visitor.save_binary(&state_path))
+                .and_then(|_| Visitor::load_binary(&state_path))
+                .and_then(|mut visitor| new.logic.visit("State", &mut visitor));
+            let _ = std::fs::remove_file(&state_path);
+            // Old version is unloaded only when state is handed over, otherwise new version is
+            // dropped and old one continues to run.
+            result?;
+            true
+        } else {
+            false
+        };
+
+        if let Some(mut old) = self.current.take() {
+            old.logic.on_unload(engine);
+        }
+        new.logic.on_load(engine, reloaded);
+        self.current = Some(new);
+        self.loaded_time = time;
+        self.pending_time = time;
+        self.generation += 1;
+        Ok(())
+    }
+
+    /// Reloads library if it was rebuilt and updates game logic. Reload errors are written to
+    /// the log, game continues to run previous version.
+    pub fn update(&mut self, engine: &mut Engine<M, C>, dt: f32) {
+        if self.last_check.elapsed() >= CHECK_INTERVAL {
+            self.last_check = Instant::now();
+            let time = modification_time(&self.source);
+            if time.is_some() && time != self.loaded_time {
+                if time == self.pending_time {
+                    Log::writeln(format!("Reloading game library {:?}", self.source));
+                    if let Err(e) = self.reload(engine) {
+                        Log::writeln(format!("Unable to reload game library. Reason: {}", e));
+                        // Do not try again until library is rebuilt.
+                        self.loaded_time = time;
+                    }
+                } else {
+                    self.pending_time = time;
+                }
+            }
+        }
+
+        if let Some(current) = self.current.as_mut() {
+            current.logic.update(engine, dt);
+        }
+    }
+
+    /// Unloads game library, game logic receives [GameLogic::on_unload].
+    pub fn unload(&mut self, engine: &mut Engine<M, C>) {
+        if let Some(mut current) = self.current.take() {
+            current.logic.on_unload(engine);
+        }
+    }
+}
@@ -0,0 +1,359 @@
+//! Memory profiler - estimates memory used by assets and engine structures, attributes it to
+//! particular assets and warns when a category of memory goes over its budget.
+//!
+//! Numbers are estimations of sizes of CPU-side data: pixels of textures, vertices and
+//! triangles of meshes, samples of sound buffers, glyph atlases of fonts and storage of
+//! pools. They do not include allocator overhead and copies of data in GPU memory (except
+//! streamed textures), but they are good enough to find out what is eating memory.
+//!
+//! # Example
+//!
+//! ```no_run
+//! # use rg3d::engine::{Engine, memory::MemoryCategory};
+//! # use rg3d::gui::node::StubNode;
+//! # fn f(engine: &mut Engine<(), StubNode>) {
+//! engine
+//!     .memory_budgets
+//!     .set_budget(MemoryCategory::Textures, Some(512 * 1024 * 1024));
+//!
+//! let report = engine.memory_report();
+//! for asset in report.assets().iter().take(10) {
+//!     println!("{:?} {} - {} bytes", asset.category, asset.name, asset.bytes);
+//! }
+//! # }
+//! ```
+
+use crate::{
+    engine::resource_manager::ResourceManager,
+    gui::{message::MessageData, node::UINode, Control, UserInterface},
+    renderer::{
+        surface::{SurfaceSharedData, TriangleDefinition, Vertex, VertexWeightSet},
+        Renderer,
+    },
+    scene::{graph::Graph, node::Node, SceneContainer},
+    sound::buffer::SoundBuffer,
+    utils::log::Log,
+};
+use std::{
+    collections::HashSet,
+    fmt::{Display, Formatter},
+    sync::Arc,
+};
+
+/// How often budgets are checked, in seconds.
+const CHECK_INTERVAL: f32 = 1.0;
+
+/// Category of memory.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum MemoryCategory {
+    /// Pixels of textures, including streamed textures.
+    Textures = 0,
+    /// Vertices and triangles of meshes.
+    Meshes = 1,
+    /// Samples of sound buffers.
+    Sounds = 2,
+    /// Font atlases and nodes of user interface.
+    Ui = 3,
+    /// Storage of pools of scene nodes.
+    Pools = 4,
+}
+
+impl MemoryCategory {
+    /// Amount of categories.
+    pub const COUNT: usize = 5;
+
+    /// All categories in order of their indices.
+    pub const ALL: [MemoryCategory; MemoryCategory::COUNT] = [
+        MemoryCategory::Textures,
+        MemoryCategory::Meshes,
+        MemoryCategory::Sounds,
+        MemoryCategory::Ui,
+        MemoryCategory::Pools,
+    ];
+}
+
+impl Display for MemoryCategory {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), std::fmt::Error> {
+        let name = match self {
+            MemoryCategory::Textures => "Textures",
+            MemoryCategory::Meshes => "Meshes",
+            MemoryCategory::Sounds => "Sounds",
+            MemoryCategory::Ui => "UI",
+            MemoryCategory::Pools => "Pools",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// Memory used by a single asset or structure.
+#[derive(Clone, Debug)]
+pub struct AssetMemory {
+    /// Path of the asset or description of the structure.
+    pub name: String,
+    /// Category of the memory.
+    pub category: MemoryCategory,
+    /// Estimated size in bytes.
+    pub bytes: usize,
+}
+
+/// Snapshot of memory usage, see module docs.
+#[derive(Clone, Debug, Default)]
+pub struct MemoryReport {
+    assets: Vec<AssetMemory>,
+    totals: [usize; MemoryCategory::COUNT],
+}
+
+fn surface_size(data: &SurfaceSharedData) -> usize {
+    data.get_vertices().len() * std::mem::size_of::<Vertex>()
+        + data.triangles().len() * std::mem::size_of::<TriangleDefinition>()
+}
+
+impl MemoryReport {
+    /// Creates empty report.
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Adds an entry to the report.
+    pub fn add<S: Into<String>>(&mut self, category: MemoryCategory, name: S, bytes: usize) {
+        self.totals[category as usize] += bytes;
+        self.assets.push(AssetMemory {
+            name: name.into(),
+            category,
+            bytes,
+        });
+    }
+
+    /// Collects memory usage of resources, scenes and user interface. Mesh data that is shared
+    /// between instances of a model is attributed to the model and counted once. Resources
+    /// that are locked at the moment (being loaded) are skipped.
+    pub fn collect<M: MessageData, C: Control<M, C>>(
+        resource_manager: &ResourceManager,
+        scenes: &SceneContainer,
+        ui: &UserInterface<M, C>,
+        renderer: &Renderer,
+    ) -> Self {
+        let mut report = Self::new();
+
+        for texture in resource_manager.textures() {
+            let texture = match texture.try_lock() {
+                Ok(texture) => texture,
+                Err(_) => continue,
+            };
+            report.add(
+                MemoryCategory::Textures,
+                texture.path().to_string_lossy(),
+                texture.bytes.len(),
+            );
+        }
+        report.add(
+            MemoryCategory::Textures,
+            "Streamed textures",
+            renderer.streamed_texture_memory(),
+        );
+
+        let mut counted = HashSet::new();
+        let mut add_graph_meshes = |report: &mut Self, graph: &Graph, owner: &str| {
+            for (_, node) in graph.pair_iter() {
+                if let Node::Mesh(mesh) = node {
+                    let mut bytes = 0;
+                    for surface in mesh.surfaces() {
+                        let weights = surface.vertex_weights.len();
+                        bytes += weights * std::mem::size_of::<VertexWeightSet>();
+                        let data = surface.data();
+                        if counted.insert(Arc::as_ptr(&data) as usize) {
+                            bytes += surface_size(&data.lock().unwrap());
+                        }
+                    }
+                    if bytes > 0 {
+                        report.add(
+                            MemoryCategory::Meshes,
+                            format!("{}/{}", owner, mesh.name()),
+                            bytes,
+                        );
+                    }
+                }
+            }
+        };
+        for model in resource_manager.models() {
+            let model = match model.try_lock() {
+                Ok(model) => model,
+                Err(_) => continue,
+            };
+            let path = model.path.to_string_lossy().to_string();
+            add_graph_meshes(&mut report, &model.get_scene().graph, &path);
+        }
+        for (i, scene) in scenes.iter().enumerate() {
+            let owner = format!("Scene {}", i);
+            add_graph_meshes(&mut report, &scene.graph, &owner);
+            report.add(
+                MemoryCategory::Pools,
+                format!("{} nodes", owner),
+                scene.graph.capacity() * std::mem::size_of::<Node>(),
+            );
+        }
+
+        for sound_buffer in resource_manager.sound_buffers() {
+            let sound_buffer = match sound_buffer.try_lock() {
+                Ok(sound_buffer) => sound_buffer,
+                Err(_) => continue,
+            };
+            let samples = match &*sound_buffer {
+                SoundBuffer::Generic(generic) => generic.samples().len(),
+                SoundBuffer::Streaming(streaming) => streaming.samples().len(),
+            };
+            let name = sound_buffer
+                .external_data_path()
+                .map(|path| path.to_string_lossy().to_string())
+                .unwrap_or_else(|| "Embedded sound".to_owned());
+            report.add(
+                MemoryCategory::Sounds,
+                name,
+                samples * std::mem::size_of::<f32>(),
+            );
+        }
+
+        for entry in resource_manager.fonts() {
+            let font = match entry.font.try_lock() {
+                Ok(font) => font,
+                Err(_) => continue,
+            };
+            report.add(
+                MemoryCategory::Ui,
+                format!("{} ({}px)", entry.path.display(), entry.height),
+                font.atlas_pixels().len(),
+            );
+        }
+        report.add(
+            MemoryCategory::Ui,
+            "UI nodes",
+            ui.nodes().get_capacity() * std::mem::size_of::<UINode<M, C>>(),
+        );
+
+        report.assets.sort_by(|a, b| b.bytes.cmp(&a.bytes));
+        report
+    }
+
+    /// Returns every entry of the report, largest first if report was collected.
+    pub fn assets(&self) -> &[AssetMemory] {
+        &self.assets
+    }
+
+    /// Returns total size of given category.
+    pub fn total(&self, category: MemoryCategory) -> usize {
+        self.totals[category as usize]
+    }
+
+    /// Returns total size of every category.
+    pub fn total_all(&self) -> usize {
+        self.totals.iter().sum()
+    }
+}
+
+/// Budgets of memory categories. Engine periodically compares memory usage with the budgets
+/// and writes a warning to the log when a category goes over its budget.
+#[derive(Clone, Debug, Default)]
+pub struct MemoryBudgets {
+    budgets: [Option<usize>; MemoryCategory::COUNT],
+    exceeded: [bool; MemoryCategory::COUNT],
+    timer: f32,
+}
+
+impl MemoryBudgets {
+    /// Creates budgets without limits.
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Sets budget of a category in bytes, `None` removes the limit.
+    pub fn set_budget(&mut self, category: MemoryCategory, budget: Option<usize>) {
+        self.budgets[category as usize] = budget;
+        self.exceeded[category as usize] = false;
+    }
+
+    /// Returns budget of a category in bytes.
+    pub fn budget(&self, category: MemoryCategory) -> Option<usize> {
+        self.budgets[category as usize]
+    }
+
+    /// Returns true if given category was over its budget on last check.
+    pub fn is_exceeded(&self, category: MemoryCategory) -> bool {
+        self.exceeded[category as usize]
+    }
+
+    /// Returns true if at least one category has a budget.
+    pub fn is_any_set(&self) -> bool {
+        self.budgets.iter().any(|budget| budget.is_some())
+    }
+
+    /// Compares report with the budgets and returns categories that went over budget since
+    /// previous check. Warning is written to the log for each of them.
+    pub fn check(&mut self, report: &MemoryReport) -> Vec<MemoryCategory> {
+        let mut newly_exceeded = Vec::new();
+        for &category in MemoryCategory::ALL.iter() {
+            let index = category as usize;
+            let total = report.total(category);
+            let exceeded = self.budgets[index].map_or(false, |budget| total > budget);
+            if exceeded && !self.exceeded[index] {
+                let largest = report
+                    .assets()
+                    .iter()
+                    .filter(|asset| asset.category == category)
+                    .max_by_key(|asset| asset.bytes)
+                    .map(|asset| asset.name.as_str())
+                    .unwrap_or_default();
+                Log::writeln(format!(
+                    "WARNING: {} memory is over budget: {} of {} bytes. Largest: {}",
+                    category,
+                    total,
+                    self.budgets[index].unwrap_or_default(),
+                    largest
+                ));
+                newly_exceeded.push(category);
+            }
+            self.exceeded[index] = exceeded;
+        }
+        newly_exceeded
+    }
+
+    /// Returns true if it is time to check budgets again.
+    pub(in crate) fn tick(&mut self, dt: f32) -> bool {
+        if !self.is_any_set() {
+            return false;
+        }
+        self.timer -= dt;
+        if self.timer <= 0.0 {
+            self.timer = CHECK_INTERVAL;
+            true
+        } else {
+            false
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::engine::memory::{MemoryBudgets, MemoryCategory, MemoryReport};
+
+    #[test]
+    fn memory_budgets_test() {
+        let mut report = MemoryReport::new();
+        report.add(MemoryCategory::Textures, "a.png", 100);
+        report.add(MemoryCategory::Textures, "b.png", 300);
+        report.add(MemoryCategory::Sounds, "c.ogg", 50);
+        assert_eq!(report.total(MemoryCategory::Textures), 400);
+        assert_eq!(report.total_all(), 450);
+
+        let mut budgets = MemoryBudgets::new();
+        budgets.set_budget(MemoryCategory::Textures, Some(350));
+        budgets.set_budget(MemoryCategory::Sounds, Some(50));
+        assert_eq!(budgets.check(&report), vec![MemoryCategory::Textures]);
+        assert!(budgets.is_exceeded(MemoryCategory::Textures));
+        // Warning is issued only once while category stays over budget.
+        assert!(budgets.check(&report).is_empty());
+
+        budgets.set_budget(MemoryCategory::Textures, Some(1000));
+        assert!(budgets.check(&report).is_empty());
+        assert!(!budgets.is_exceeded(MemoryCategory::Textures));
+    }
+}
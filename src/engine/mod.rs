@@ -3,20 +3,48 @@
 
 #![warn(missing_docs)]
 
+pub mod crash_report;
+pub mod determinism;
+pub mod display;
 pub mod error;
+pub mod event_bus;
+#[cfg(feature = "hot_reload")]
+pub mod hot_reload;
+pub mod memory;
 pub mod resource_manager;
+pub mod save;
+pub mod scheduler;
+pub mod settings;
+pub mod sound_events;
+pub mod statistics_overlay;
+pub mod time_control;
 
 use crate::{
     core::{
         math::vec2::Vec2,
+        pool::Handle,
         visitor::{Visit, VisitResult, Visitor},
     },
-    engine::{error::EngineError, resource_manager::ResourceManager},
+    dpi::PhysicalSize,
+    engine::{
+        crash_report,
+        error::EngineError,
+        event_bus::EventBus,
+        memory::{MemoryBudgets, MemoryReport},
+        resource_manager::{ResourceManager, SharedSoundBuffer},
+        scheduler::Scheduler,
+        settings::EngineSettings,
+        sound_events::{SoundEvents, SoundParams, SoundTarget},
+        statistics_overlay::StatisticsOverlay,
+        time_control::TimeControl,
+    },
     event_loop::EventLoop,
+    frame_scope,
     gui::{Control, UserInterface},
     renderer::{error::RendererError, Renderer},
     scene::SceneContainer,
-    sound::context::Context,
+    sound::{context::Context, error::SoundError, source::SoundSource},
+    utils::frame_profiler,
     window::{Window, WindowBuilder},
     Api, GlProfile, GlRequest, NotCurrent, PossiblyCurrent, WindowedContext,
 };
@@ -50,6 +78,22 @@ pub struct Engine<M: MessageData, C: Control<M, C>> {
     /// for such statistics, probably it is best to make separate structure to hold all
     /// such data.
     pub ui_time: Duration,
+    /// Event bus to publish and subscribe to events of any type, events are delivered at the
+    /// beginning of [update](struct.Engine.html#method.update).
+    pub event_bus: EventBus,
+    /// Scheduler of delayed callbacks, timers and coroutines, it is ticked right after
+    /// delivery of events of the event bus.
+    pub scheduler: Scheduler,
+    /// Memory budgets, engine checks them every second and writes a warning to the log when
+    /// a category of memory goes over its budget. See [memory](memory/index.html) module.
+    pub memory_budgets: MemoryBudgets,
+    /// Global time scale, pause and frame stepping. See [time_control](time_control/index.html)
+    /// module.
+    pub time_control: TimeControl,
+    /// One-shot sounds that follow nodes. See [sound_events](sound_events/index.html) module.
+    pub sound_events: SoundEvents,
+    statistics_overlay: Option<StatisticsOverlay<M, C>>,
+    suspended: bool,
 }
 
 impl<M: MessageData, C: Control<M, C>> Engine<M, C> {
@@ -75,9 +119,31 @@ impl<M: MessageData, C: Control<M, C>> Engine<M, C> {
     pub fn new(
         window_builder: WindowBuilder,
         events_loop: &EventLoop<()>,
+    ) -> Result<Engine<M, C>, EngineError> {
+        Self::create(window_builder, events_loop, true)
+    }
+
+    /// Creates new instance of engine and applies given settings to it - vsync, window size
+    /// and mode, quality and volume. See [settings](settings/index.html) module.
+    pub fn with_settings(
+        window_builder: WindowBuilder,
+        events_loop: &EventLoop<()>,
+        settings: &EngineSettings,
+    ) -> Result<Engine<M, C>, EngineError> {
+        let (width, height) = settings.window_size;
+        let window_builder = window_builder.with_inner_size(PhysicalSize::new(width, height));
+        let mut engine = Self::create(window_builder, events_loop, settings.vsync)?;
+        settings.apply(&mut engine)?;
+        Ok(engine)
+    }
+
+    fn create(
+        window_builder: WindowBuilder,
+        events_loop: &EventLoop<()>,
+        vsync: bool,
     ) -> Result<Engine<M, C>, EngineError> {
         let context_wrapper: WindowedContext<NotCurrent> = glutin::ContextBuilder::new()
-            .with_vsync(true)
+            .with_vsync(vsync)
             .with_gl_profile(GlProfile::Core)
             .with_gl(GlRequest::Specific(Api::OpenGl, (3, 3)))
             .build_windowed(window_builder, events_loop)?;
@@ -99,12 +165,19 @@ impl<M: MessageData, C: Control<M, C>> Engine<M, C> {
                 client_size.height as f32,
             )),
             ui_time: Default::default(),
+            event_bus: EventBus::new(),
+            scheduler: Scheduler::new(),
+            memory_budgets: MemoryBudgets::new(),
+            time_control: TimeControl::new(),
+            sound_events: SoundEvents::new(),
+            statistics_overlay: None,
+            suspended: false,
             context,
         })
     }
 
-    /// Returns reference to main window. Could be useful to set fullscreen mode, change
-    /// size of window, its title, etc.
+    /// Returns reference to main window. Window mode, size, title, icon and cursor can also be
+    /// changed by methods of the engine, see [display](display/index.html) module.
     #[inline]
     pub fn get_window(&self) -> &Window {
         self.context.window()
@@ -112,8 +185,17 @@ impl<M: MessageData, C: Control<M, C>> Engine<M, C> {
 
     /// Performs single update tick with given time delta. Engine internally will perform update
     /// of all scenes, sub-systems, user interface, etc. Must be called in order to get engine
-    /// functioning.
+    /// functioning. Scenes and scheduler are updated using time scaled by
+    /// [time_control](time_control/index.html).
     pub fn update(&mut self, dt: f32) {
+        frame_profiler::next_frame();
+        frame_scope!("Update");
+
+        let game_dt = self.time_control.game_time(dt);
+
+        self.event_bus.dispatch();
+        self.scheduler.update(game_dt);
+
         let inner_size = self.context.window().inner_size();
         let frame_size = Vec2::new(inner_size.width as f32, inner_size.height as f32);
 
@@ -121,22 +203,171 @@ impl<M: MessageData, C: Control<M, C>> Engine<M, C> {
         // engine will try to update it in next frame. Resource update is just controls TTLs of
         // resource so it is not problem to defer update call.
         if let Ok(mut resource_manager) = self.resource_manager.try_lock() {
-            resource_manager.update(dt);
+            frame_scope!("Resources");
+            let reloaded = resource_manager.update(dt);
+            if !reloaded.is_empty() {
+                for texture in reloaded.textures {
+                    self.renderer.unload_texture(texture);
+                }
+                // Instances of reloaded models hold copies of old surfaces, re-sync them.
+                if !reloaded.models.is_empty() {
+                    for scene in self.scenes.iter_mut() {
+                        scene.resolve();
+                    }
+                }
+            }
         }
 
         for scene in self.scenes.iter_mut() {
-            scene.update(frame_size, dt);
+            scene.update(frame_size, game_dt);
         }
 
+        if self.sound_events.attached_count() > 0 {
+            self.sound_events
+                .update(&self.scenes, &mut self.sound_context.lock().unwrap());
+        }
+
+        // Sources created since last update must be scaled too.
+        if self.time_control.is_sound_scaled() {
+            self.time_control
+                .update_sound(&mut self.sound_context.lock().unwrap());
+        }
+
+        if self.memory_budgets.tick(dt) {
+            if let Ok(resource_manager) = self.resource_manager.try_lock() {
+                let report = MemoryReport::collect(
+                    &resource_manager,
+                    &self.scenes,
+                    &self.user_interface,
+                    &self.renderer,
+                );
+                self.memory_budgets.check(&report);
+            }
+        }
+
+        if let Some(statistics_overlay) = self.statistics_overlay.as_mut() {
+            statistics_overlay.update(
+                &mut self.user_interface,
+                &self.renderer,
+                &self.scenes,
+                &self.resource_manager,
+                &self.memory_budgets,
+            );
+        }
+
+        frame_scope!("UI");
         let time = time::Instant::now();
-        self.user_interface.update(frame_size, dt);
+        self.user_interface
+            .update(frame_size, self.time_control.ui_time(dt, game_dt));
         self.ui_time = time::Instant::now() - time;
+
+        crash_report::update_engine_state(|| self.state_summary());
+    }
+
+    /// Collects memory usage of resources, scenes and user interface, attributed to
+    /// particular assets. See [memory](memory/index.html) module.
+    pub fn memory_report(&self) -> MemoryReport {
+        MemoryReport::collect(
+            &self.resource_manager.lock().unwrap(),
+            &self.scenes,
+            &self.user_interface,
+            &self.renderer,
+        )
+    }
+
+    /// Plays given buffer once at given position or attached to a node, source is removed when
+    /// it stops. See [sound_events](sound_events/index.html) module.
+    pub fn play_sound_at(
+        &mut self,
+        buffer: SharedSoundBuffer,
+        target: SoundTarget,
+        params: SoundParams,
+    ) -> Result<Handle<SoundSource>, SoundError> {
+        self.sound_events.play(
+            buffer,
+            target,
+            params,
+            &self.scenes,
+            &mut self.sound_context.lock().unwrap(),
+        )
+    }
+
+    /// Sets global time scale, 1.0 is normal speed. See [time_control](time_control/index.html).
+    pub fn set_time_scale(&mut self, scale: f32) {
+        self.time_control
+            .set_scale(scale, &mut self.sound_context.lock().unwrap());
+    }
+
+    /// Returns global time scale.
+    pub fn time_scale(&self) -> f32 {
+        self.time_control.scale()
+    }
+
+    /// Pauses or resumes game. Paused game is still rendered and user interface is still
+    /// updated.
+    pub fn set_paused(&mut self, paused: bool) {
+        self.time_control
+            .set_paused(paused, &mut self.sound_context.lock().unwrap());
+    }
+
+    /// Returns true if game is paused.
+    pub fn is_paused(&self) -> bool {
+        self.time_control.is_paused()
+    }
+
+    /// Advances paused game by one step of given duration on next update.
+    pub fn step_frame(&mut self, dt: f32) {
+        self.time_control.step_frame(dt);
+    }
+
+    /// Shows or hides overlay with statistics of the engine - FPS, graph of frame times, draw
+    /// calls, etc. Overlay is created on first use.
+    pub fn set_statistics_overlay_visible(&mut self, visible: bool) {
+        if self.statistics_overlay.is_none() && visible {
+            self.statistics_overlay = Some(StatisticsOverlay::new(&mut self.user_interface));
+        }
+        if let Some(statistics_overlay) = self.statistics_overlay.as_mut() {
+            statistics_overlay.set_visible(&mut self.user_interface, visible);
+        }
+    }
+
+    /// Returns true if statistics overlay is shown.
+    pub fn is_statistics_overlay_visible(&self) -> bool {
+        self.statistics_overlay
+            .as_ref()
+            .map_or(false, |overlay| overlay.is_visible())
+    }
+
+    /// Tells the engine that application was suspended or resumed by the OS, it must be called
+    /// on `Event::Suspended` and `Event::Resumed`. Nothing is rendered while application is
+    /// suspended - on mobile platforms the surface of the window is destroyed at this moment.
+    /// On resume textures and geometry are uploaded to GPU again, in case if OS has dropped
+    /// them, this may cause a lag in first frame. Other objects of renderer (shaders, frame
+    /// buffers) are not recreated, so loss of GL context is not handled.
+    ///
+    /// Note that this is only a part of support of mobile platforms - renderer still requires
+    /// desktop OpenGL 3.3 and assets packaged into APK can't be loaded yet, so the engine does
+    /// not run on Android.
+    pub fn set_suspended(&mut self, suspended: bool) {
+        if self.suspended && !suspended {
+            self.renderer.flush();
+        }
+        self.suspended = suspended;
+    }
+
+    /// Returns true if application is suspended, see [set_suspended](#method.set_suspended).
+    pub fn is_suspended(&self) -> bool {
+        self.suspended
     }
 
     /// Performs rendering of single frame, must be called from your game loop, otherwise you won't
-    /// see anything.
+    /// see anything. Does nothing while application is suspended.
     #[inline]
     pub fn render(&mut self, dt: f32) -> Result<(), RendererError> {
+        if self.suspended {
+            return Ok(());
+        }
+        frame_scope!("Render");
         self.user_interface.draw();
         self.renderer.render_and_swap_buffers(
             &self.scenes,
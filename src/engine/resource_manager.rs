@@ -1,16 +1,82 @@
 //! Resource manager controls loading and lifetime of resource in the engine.
+//!
+//! Resource manager owns every texture, model, sound buffer and font which was loaded through
+//! it, each resource is loaded only once and then shared by path. Resources that are not used
+//! anymore will be unloaded automatically after [ResourceManager::MAX_RESOURCE_TTL] seconds.
+//!
+//! Every kind of resource can be loaded asynchronously on workers of [IO](JobSystem::io) job
+//! system, use [ResourceManager::loading_progress] to get aggregate progress of such loading,
+//! it is useful for loading screens. Textures and custom resources are requested through
+//! resource manager itself, models, sound buffers and fonts - through shared resource manager
+//! (see [ResourceManager::request_model_async]), because their loaders register the resource
+//! when it is loaded. Resource manager is not locked while resource is loading.
+//!
+//! # Dependencies and unloading
+//!
+//! Resources reference each other - scenes use models and textures, models use textures.
+//! Use [Scene::dependencies](crate::scene::Scene::dependencies) and
+//! [Model::dependencies](crate::resource::model::Model::dependencies) to get such references,
+//! [ResourceManager::report] to get reference counts of every resource along with list of
+//! unused and missing resources. When a level is changed, call [ResourceManager::unload_unused]
+//! after old scene is destroyed to free its resources right away instead of waiting until
+//! their TTL expires.
+//!
+//! # Custom resources
+//!
+//! Games can register loaders of their own resource types, see [custom](crate::resource::custom)
+//! module docs.
+//!
+//! # Hot reload
+//!
+//! Resource manager can watch source files of textures, models and fonts and reload them
+//! automatically when they're changed on disk, see [ResourceManager::set_watch_enabled]. Live
+//! instances are patched - engine re-uploads textures to GPU and re-syncs model instances in
+//! every scene with reloaded resource. Built-in shaders are embedded into the engine so they
+//! cannot be reloaded.
+//!
+//! # Derived data cache
+//!
+//! Decoded textures and converted models can be stored in [derived data cache](DerivedDataCache),
+//! so next runs of a game will load them much faster, see
+//! [ResourceManager::set_derived_data_cache].
+//!
+//! # Virtual file system
+//!
+//! Textures, models, fonts and custom resources are read through
+//! [virtual file system](VirtualFileSystem), so they can be loaded from mounted resource packs. Scenes (rgs) and sound buffers are still
+//! read directly from the disk.
 
 use crate::{
     core::visitor::{Visit, VisitResult, Visitor},
-    resource::{model::Model, texture::Texture, texture::TextureKind},
+    gui::ttf::{Font, SharedFont},
+    resource::{
+        cache::DerivedDataCache,
+        custom::{
+            CustomResource, CustomResourceEntry, ErasedLoader, ResourceLoader, SharedCustomResource,
+        },
+        import::ModelImportOptions,
+        model::Model,
+        texture::Texture,
+        texture::TextureKind,
+        vfs::VirtualFileSystem,
+        ResourceState,
+    },
     sound::buffer::{DataSource, SoundBuffer},
-    utils::log::Log,
+    utils::{
+        jobs::{JobHandle, JobSystem},
+        log::Log,
+    },
 };
 use std::{
+    any::TypeId,
+    collections::{HashMap, HashSet},
     ops::{Deref, DerefMut},
     path::{Path, PathBuf},
-    sync::{Arc, Mutex},
-    time,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
+    time::{self, SystemTime},
 };
 
 /// Resource container with fixed TTL (time-to-live). Resource will be removed
@@ -82,11 +148,125 @@ pub type SharedModel = Arc<Mutex<Model>>;
 /// Type alias for Arc<Mutex<SoundBuffer>> to make code less noisy.
 pub type SharedSoundBuffer = Arc<Mutex<SoundBuffer>>;
 
+/// Font resource entry. Fonts are identified by path *and* height, because same font file
+/// produces different atlases for different heights.
+#[derive(Clone)]
+pub struct FontEntry {
+    /// Path to font file.
+    pub path: PathBuf,
+    /// Height of font in pixels.
+    pub height: f32,
+    /// Shared font.
+    pub font: SharedFont,
+}
+
+/// Aggregate progress of asynchronous loading of resources.
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct LoadingProgress {
+    /// Total amount of resources which were requested asynchronously and not yet destroyed.
+    pub total: usize,
+    /// Amount of resources which are still loading.
+    pub pending: usize,
+}
+
+impl LoadingProgress {
+    /// Returns true if there are no resources which are still loading.
+    pub fn is_done(&self) -> bool {
+        self.pending == 0
+    }
+
+    /// Returns progress in [0; 1] range.
+    pub fn fraction(&self) -> f32 {
+        if self.total == 0 {
+            1.0
+        } else {
+            (self.total - self.pending) as f32 / self.total as f32
+        }
+    }
+}
+
+/// List of resources which were reloaded by hot reload during last update.
+#[derive(Default)]
+pub struct ReloadedResources {
+    /// Reloaded textures, their GPU copies must be re-uploaded.
+    pub textures: Vec<SharedTexture>,
+    /// Reloaded models, their instances must be re-synced with new data.
+    pub models: Vec<SharedModel>,
+    /// Paths of reloaded custom resources.
+    pub custom_resources: Vec<PathBuf>,
+}
+
+impl ReloadedResources {
+    /// Returns true if nothing was reloaded.
+    pub fn is_empty(&self) -> bool {
+        self.textures.is_empty() && self.models.is_empty() && self.custom_resources.is_empty()
+    }
+}
+
+/// Kind of resource in [ResourceReport].
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ResourceKind {
+    /// Texture resource.
+    Texture,
+    /// Model resource.
+    Model,
+    /// Sound buffer resource.
+    SoundBuffer,
+    /// Font resource.
+    Font,
+    /// Custom resource, see [custom](crate::resource::custom) module docs.
+    Custom,
+}
+
+/// Usage info of a single resource.
+#[derive(Clone, Debug)]
+pub struct ResourceUsage {
+    /// Path to source file of resource.
+    pub path: PathBuf,
+    /// Kind of resource.
+    pub kind: ResourceKind,
+    /// Amount of references to the resource outside of resource manager. Note that every
+    /// node of model instance holds its own reference to the model.
+    pub reference_count: usize,
+    /// Paths of resources which are used by the resource (textures of a model).
+    pub dependencies: Vec<PathBuf>,
+}
+
+/// Usage report of all resources in resource manager, see [ResourceManager::report].
+#[derive(Clone, Debug, Default)]
+pub struct ResourceReport {
+    /// Usage info of every resource.
+    pub resources: Vec<ResourceUsage>,
+    /// Paths of resources which are not used by anything, they will be unloaded when their
+    /// TTL expires or when [ResourceManager::unload_unused] is called.
+    pub unused: Vec<PathBuf>,
+    /// Paths of resources which were requested but failed to load.
+    pub missing: Vec<PathBuf>,
+}
+
 /// See module docs.
 pub struct ResourceManager {
     textures: Vec<TimedEntry<SharedTexture>>,
     models: Vec<TimedEntry<SharedModel>>,
     sound_buffers: Vec<TimedEntry<SharedSoundBuffer>>,
+    fonts: Vec<TimedEntry<FontEntry>>,
+    custom_resources: Vec<TimedEntry<CustomResourceEntry>>,
+    /// Loaders of custom resources by type of resource.
+    custom_loaders: HashMap<TypeId, Arc<dyn ErasedLoader>>,
+    /// Amount of models, sound buffers and fonts which are loading on worker threads right
+    /// now.
+    pending: Arc<AtomicUsize>,
+    /// Paths of resources which were requested asynchronously, only they are counted in
+    /// loading progress.
+    async_paths: HashSet<PathBuf>,
+    vfs: Arc<Mutex<VirtualFileSystem>>,
+    derived_data_cache: Option<Arc<DerivedDataCache>>,
+    watch_enabled: bool,
+    watch_timer: f32,
+    /// Last known modification times of source files of resources.
+    modification_times: HashMap<PathBuf, SystemTime>,
+    /// Paths of resources which were failed to load.
+    missing: HashSet<PathBuf>,
     /// Path to textures, extensively used for resource files which stores path in weird
     /// format (either relative or absolute) which is obviously not good for engine.
     textures_path: PathBuf,
@@ -96,17 +276,32 @@ impl ResourceManager {
     /// Lifetime of orphaned resource in seconds (with only one strong ref which is resource manager itself)
     pub const MAX_RESOURCE_TTL: f32 = 20.0;
 
-    pub(in crate::engine) fn new() -> ResourceManager {
+    /// Interval in seconds between checks of source files of resources when hot reload is enabled.
+    pub const WATCH_INTERVAL: f32 = 1.0;
+
+    pub(in crate) fn new() -> ResourceManager {
         Self {
             textures: Vec::new(),
             models: Vec::new(),
             sound_buffers: Vec::new(),
+            fonts: Vec::new(),
+            custom_resources: Vec::new(),
+            custom_loaders: Default::default(),
+            pending: Arc::new(AtomicUsize::new(0)),
+            async_paths: Default::default(),
+            vfs: Default::default(),
+            derived_data_cache: None,
+            watch_enabled: false,
+            watch_timer: 0.0,
+            modification_times: Default::default(),
+            missing: Default::default(),
             textures_path: PathBuf::from("data/textures/"),
         }
     }
 
-    /// Experimental async texture loader. Always returns valid texture object which could still
-    /// be not loaded, you should check is_loaded flag to ensure.
+    /// Asynchronous texture loader. Always returns valid texture object which could still
+    /// be not loaded, you should check its state to ensure. Texture is loaded on a worker
+    /// thread, requesting same texture again while it is loading returns same instance.
     ///
     /// It extensively used in model loader to speed up loading.
     pub fn request_texture_async<P: AsRef<Path>>(
@@ -118,18 +313,30 @@ impl ResourceManager {
             return texture;
         }
 
-        let texture = Arc::new(Mutex::new(Texture::default()));
+        let path = PathBuf::from(path.as_ref());
+        self.async_paths.insert(path.clone());
+
+        let texture = Arc::new(Mutex::new(Texture {
+            path: path.clone(),
+            kind,
+            state: ResourceState::Pending,
+            ..Default::default()
+        }));
         self.textures.push(TimedEntry {
             value: texture.clone(),
             time_to_live: Self::MAX_RESOURCE_TTL,
         });
         let result = texture.clone();
+        let vfs = self.vfs.clone();
+        let cache = self.derived_data_cache.clone();
 
-        let path = PathBuf::from(path.as_ref());
-        std::thread::spawn(move || {
+        JobSystem::io().spawn(move || {
+            let time = time::Instant::now();
+            // Load texture *before* locking it, so other threads can query state of
+            // texture while it is loading.
+            let raw_texture = Texture::load_from_vfs(&vfs, cache.as_deref(), &path, kind);
             if let Ok(mut texture) = texture.lock() {
-                let time = time::Instant::now();
-                match Texture::load_from_file(&path, kind) {
+                match raw_texture {
                     Ok(raw_texture) => {
                         *texture = raw_texture;
                         Log::writeln(format!(
@@ -139,6 +346,7 @@ impl ResourceManager {
                         ));
                     }
                     Err(e) => {
+                        texture.state = ResourceState::LoadError;
                         Log::writeln(format!("Unable to load texture {:?}! Reason {}", path, e));
                     }
                 }
@@ -154,8 +362,10 @@ impl ResourceManager {
     ///
     /// # Supported formats
     ///
-    /// To load images and decode them, rg3d uses image create which supports following image
-    /// formats: png, tga, bmp, dds, jpg, gif, tiff, dxt.
+    /// Supported formats are: png, jpg, tga, bmp, gif (first frame only), tiff, dds, ktx2, hdr
+    /// and exr. DDS and KTX2 containers are loaded with their mip chains, cube maps and
+    /// compressed formats as is. Radiance HDR and OpenEXR images are loaded as floating-point
+    /// textures. See [Texture] docs.
     pub fn request_texture<P: AsRef<Path>>(
         &mut self,
         path: P,
@@ -165,7 +375,12 @@ impl ResourceManager {
             return Some(texture);
         }
 
-        match Texture::load_from_file(path.as_ref(), kind) {
+        match Texture::load_from_vfs(
+            &self.vfs,
+            self.derived_data_cache.as_deref(),
+            path.as_ref(),
+            kind,
+        ) {
             Ok(texture) => {
                 let shared_texture = Arc::new(Mutex::new(texture));
                 self.textures.push(TimedEntry {
@@ -173,9 +388,11 @@ impl ResourceManager {
                     time_to_live: Self::MAX_RESOURCE_TTL,
                 });
                 Log::writeln(format!("Texture {} is loaded!", path.as_ref().display()));
+                self.missing.remove(path.as_ref());
                 Some(shared_texture)
             }
             Err(e) => {
+                self.missing.insert(path.as_ref().to_owned());
                 Log::writeln(format!(
                     "Unable to load texture {}! Reason {}",
                     path.as_ref().display(),
@@ -192,8 +409,13 @@ impl ResourceManager {
     ///
     /// # Supported formats
     ///
-    /// Currently only FBX (common format in game industry for storing complex 3d models)
-    /// and RGS (native rusty-editor format) formats are supported.
+    /// Currently FBX (common format in game industry for storing complex 3d models),
+    /// OBJ (with MTL materials) and RGS (native rusty-editor format) formats are supported.
+    ///
+    /// # Import options
+    ///
+    /// If there is a sidecar file with import options next to the model, the options are
+    /// applied to the model, see [set_model_import_options](Self::set_model_import_options).
     pub fn request_model<P: AsRef<Path>>(&mut self, path: P) -> Option<SharedModel> {
         if let Some(model) = self.find_model(path.as_ref()) {
             return Some(model);
@@ -208,9 +430,11 @@ impl ResourceManager {
                     time_to_live: Self::MAX_RESOURCE_TTL,
                 });
                 Log::writeln(format!("Model {} is loaded!", path.as_ref().display()));
+                self.missing.remove(path.as_ref());
                 Some(model)
             }
             Err(e) => {
+                self.missing.insert(path.as_ref().to_owned());
                 Log::writeln(format!(
                     "Unable to load model from {:?}! Reason {:?}",
                     path.as_ref(),
@@ -221,6 +445,140 @@ impl ResourceManager {
         }
     }
 
+    /// Runs `load` on IO job system, then locks resource manager and passes result of loading
+    /// to `finish`. Resource manager is not locked while `load` works, so it must lock the
+    /// manager by itself if needed.
+    fn spawn_load<T, R, L, F>(
+        resource_manager: Arc<Mutex<ResourceManager>>,
+        load: L,
+        finish: F,
+    ) -> JobHandle<T>
+    where
+        T: Send + 'static,
+        R: Send + 'static,
+        L: FnOnce(&Mutex<ResourceManager>) -> R + Send + 'static,
+        F: FnOnce(&mut ResourceManager, R) -> T + Send + 'static,
+    {
+        let pending = resource_manager.lock().unwrap().pending.clone();
+        pending.fetch_add(1, Ordering::SeqCst);
+        JobSystem::io().spawn(move || {
+            let loaded = load(&*resource_manager);
+            let mut resource_manager = resource_manager.lock().unwrap();
+            // Decreased under lock, so loading progress never counts a resource twice.
+            pending.fetch_sub(1, Ordering::SeqCst);
+            finish(&mut resource_manager, loaded)
+        })
+    }
+
+    /// Creates resource manager with the same settings and file system which is used to load
+    /// a model without locking this resource manager.
+    fn staging(&self) -> ResourceManager {
+        ResourceManager {
+            custom_loaders: self.custom_loaders.clone(),
+            vfs: self.vfs.clone(),
+            derived_data_cache: self.derived_data_cache.clone(),
+            textures_path: self.textures_path.clone(),
+            ..ResourceManager::new()
+        }
+    }
+
+    /// Registers model that was loaded by staging resource manager and moves every resource
+    /// of the staging manager here. Resources that were loaded here while model was loading
+    /// are kept, references of the model are redirected to them.
+    fn adopt_model(&mut self, staging: ResourceManager, path: &Path, model: Model) -> SharedModel {
+        if let Some(model) = self.find_model(path) {
+            return model;
+        }
+
+        let mut textures = HashMap::new();
+        for texture in staging.textures {
+            let existing = self.find_texture(&texture.lock().unwrap().path);
+            match existing {
+                Some(existing) => {
+                    textures.insert(Arc::as_ptr(&texture.value), existing);
+                }
+                None => self.textures.push(texture),
+            }
+        }
+        let mut models = HashMap::new();
+        let mut adopted = Vec::new();
+        for nested in staging.models {
+            let existing = self.find_model(&nested.lock().unwrap().path);
+            match existing {
+                Some(existing) => {
+                    models.insert(Arc::as_ptr(&nested.value), existing);
+                }
+                None => adopted.push(nested),
+            }
+        }
+        for nested in adopted.iter() {
+            nested
+                .lock()
+                .unwrap()
+                .redirect_resources(&textures, &models);
+        }
+        self.models.extend(adopted);
+        self.async_paths.extend(staging.async_paths);
+        self.missing.extend(staging.missing);
+
+        let mut model = model;
+        model.redirect_resources(&textures, &models);
+        let model = Arc::new(Mutex::new(model));
+        model.lock().unwrap().self_weak_ref = Some(Arc::downgrade(&model));
+        self.models.push(TimedEntry {
+            value: model.clone(),
+            time_to_live: Self::MAX_RESOURCE_TTL,
+        });
+        self.async_paths.insert(path.to_owned());
+        self.missing.remove(path);
+        Log::writeln(format!("Model {} is loaded!", path.display()));
+        model
+    }
+
+    /// Loads model on a worker of [IO](JobSystem::io) job system. Model is loaded by separate
+    /// resource manager with the same settings, so given resource manager is locked only at
+    /// the beginning and at the end of loading, game loop continues to work while model is
+    /// loading. Textures of the model are loaded asynchronously too.
+    ///
+    /// Returned job handle can be used to wait for the result. Use `loading_progress` to
+    /// track progress without blocking.
+    pub fn request_model_async<P: AsRef<Path>>(
+        resource_manager: Arc<Mutex<ResourceManager>>,
+        path: P,
+    ) -> JobHandle<Option<SharedModel>> {
+        let path = path.as_ref().to_owned();
+        let load_path = path.clone();
+        Self::spawn_load(
+            resource_manager,
+            move |resource_manager| {
+                let mut staging = {
+                    let resource_manager = resource_manager.lock().unwrap();
+                    if resource_manager.find_model(&load_path).is_some() {
+                        return None;
+                    }
+                    resource_manager.staging()
+                };
+                let model = Model::load(&load_path, &mut staging);
+                Some((staging, model))
+            },
+            move |resource_manager, loaded| match loaded {
+                // Model was loaded already.
+                None => resource_manager.find_model(&path),
+                Some((staging, Ok(model))) => {
+                    Some(resource_manager.adopt_model(staging, &path, model))
+                }
+                Some((_, Err(e))) => {
+                    resource_manager.missing.insert(path.clone());
+                    Log::writeln(format!(
+                        "Unable to load model from {:?}! Reason {:?}",
+                        path, e
+                    ));
+                    None
+                }
+            },
+        )
+    }
+
     /// Tries to load new sound buffer from given path or get instance of existing, if any.
     /// This method is **blocking**, so it will block current thread until sound buffer is
     /// loading. On failure it returns None and prints failure reason to log.
@@ -237,41 +595,454 @@ impl ResourceManager {
             return Some(sound_buffer);
         }
 
-        match DataSource::from_file(path.as_ref()) {
-            Ok(source) => {
-                let buffer = if stream {
-                    SoundBuffer::new_streaming(source)
-                } else {
-                    SoundBuffer::new_generic(source)
-                };
-                match buffer {
-                    Ok(sound_buffer) => {
-                        self.sound_buffers.push(TimedEntry {
-                            value: sound_buffer.clone(),
-                            time_to_live: Self::MAX_RESOURCE_TTL,
-                        });
-                        Log::writeln(format!(
-                            "Sound buffer {} is loaded!",
-                            path.as_ref().display()
-                        ));
-                        Some(sound_buffer)
-                    }
-                    Err(_) => {
-                        Log::writeln(format!(
-                            "Unable to load sound buffer from {}!",
-                            path.as_ref().display()
-                        ));
-                        None
-                    }
+        let sound_buffer = load_sound_buffer(path.as_ref(), stream);
+        self.register_sound_buffer(path.as_ref(), sound_buffer)
+    }
+
+    fn register_sound_buffer(
+        &mut self,
+        path: &Path,
+        sound_buffer: Result<SharedSoundBuffer, String>,
+    ) -> Option<SharedSoundBuffer> {
+        match sound_buffer {
+            Ok(sound_buffer) => {
+                // Same buffer could be loaded by another worker meanwhile.
+                if let Some(sound_buffer) = self.find_sound_buffer(path) {
+                    return Some(sound_buffer);
                 }
+                self.sound_buffers.push(TimedEntry {
+                    value: sound_buffer.clone(),
+                    time_to_live: Self::MAX_RESOURCE_TTL,
+                });
+                Log::writeln(format!("Sound buffer {} is loaded!", path.display()));
+                self.missing.remove(path);
+                Some(sound_buffer)
             }
             Err(e) => {
-                Log::writeln(format!("Invalid data source: {:?}", e));
+                self.missing.insert(path.to_owned());
+                Log::writeln(format!(
+                    "Unable to load sound buffer from {}! Reason {}",
+                    path.display(),
+                    e
+                ));
                 None
             }
         }
     }
 
+    /// Loads sound buffer on a worker of [IO](JobSystem::io) job system, see
+    /// [request_sound_buffer](Self::request_sound_buffer). Resource manager is not locked
+    /// while sound buffer is loading.
+    pub fn request_sound_buffer_async<P: AsRef<Path>>(
+        resource_manager: Arc<Mutex<ResourceManager>>,
+        path: P,
+        stream: bool,
+    ) -> JobHandle<Option<SharedSoundBuffer>> {
+        let path = path.as_ref().to_owned();
+        let load_path = path.clone();
+        Self::spawn_load(
+            resource_manager,
+            move |resource_manager| {
+                let existing = resource_manager
+                    .lock()
+                    .unwrap()
+                    .find_sound_buffer(&load_path);
+                existing.map_or_else(|| load_sound_buffer(&load_path, stream), Ok)
+            },
+            move |resource_manager, sound_buffer| {
+                resource_manager.async_paths.insert(path.clone());
+                resource_manager.register_sound_buffer(&path, sound_buffer)
+            },
+        )
+    }
+
+    /// Tries to load font from given path with given height or get instance of existing, if
+    /// any. This method is **blocking**. On failure it returns None and prints failure reason
+    /// to log.
+    ///
+    /// # Supported formats
+    ///
+    /// Only TTF fonts are supported.
+    pub fn request_font<P: AsRef<Path>>(&mut self, path: P, height: f32) -> Option<SharedFont> {
+        if let Some(font) = self.find_font(path.as_ref(), height) {
+            return Some(font);
+        }
+
+        let font = load_font(&self.vfs, path.as_ref(), height)
+            .map(|font| SharedFont(Arc::new(Mutex::new(font))));
+        self.register_font(path.as_ref(), height, font)
+    }
+
+    fn register_font(
+        &mut self,
+        path: &Path,
+        height: f32,
+        font: Result<SharedFont, String>,
+    ) -> Option<SharedFont> {
+        match font {
+            Ok(font) => {
+                // Same font could be loaded by another worker meanwhile.
+                if let Some(font) = self.find_font(path, height) {
+                    return Some(font);
+                }
+                self.fonts.push(TimedEntry {
+                    value: FontEntry {
+                        path: path.to_owned(),
+                        height,
+                        font: font.clone(),
+                    },
+                    time_to_live: Self::MAX_RESOURCE_TTL,
+                });
+                Log::writeln(format!("Font {} is loaded!", path.display()));
+                self.missing.remove(path);
+                Some(font)
+            }
+            Err(e) => {
+                self.missing.insert(path.to_owned());
+                Log::writeln(format!(
+                    "Unable to load font from {}! Reason {}",
+                    path.display(),
+                    e
+                ));
+                None
+            }
+        }
+    }
+
+    /// Loads font on a worker of [IO](JobSystem::io) job system, see
+    /// [request_font](Self::request_font). Resource manager is not locked while font is
+    /// loading.
+    pub fn request_font_async<P: AsRef<Path>>(
+        resource_manager: Arc<Mutex<ResourceManager>>,
+        path: P,
+        height: f32,
+    ) -> JobHandle<Option<SharedFont>> {
+        let path = path.as_ref().to_owned();
+        let load_path = path.clone();
+        Self::spawn_load(
+            resource_manager,
+            move |resource_manager| {
+                let (existing, vfs) = {
+                    let resource_manager = resource_manager.lock().unwrap();
+                    (
+                        resource_manager.find_font(&load_path, height),
+                        resource_manager.vfs(),
+                    )
+                };
+                existing.map_or_else(
+                    || {
+                        load_font(&vfs, &load_path, height)
+                            .map(|font| SharedFont(Arc::new(Mutex::new(font))))
+                    },
+                    Ok,
+                )
+            },
+            move |resource_manager, font| {
+                resource_manager.async_paths.insert(path.clone());
+                resource_manager.register_font(&path, height, font)
+            },
+        )
+    }
+
+    /// Returns shared reference to list of available fonts.
+    #[inline]
+    pub fn fonts(&self) -> &[TimedEntry<FontEntry>] {
+        &self.fonts
+    }
+
+    /// Tries to find font by its path and height. Returns None if no such font was found.
+    pub fn find_font<P: AsRef<Path>>(&self, path: P, height: f32) -> Option<SharedFont> {
+        self.fonts
+            .iter()
+            .find(|entry| entry.path == path.as_ref() && entry.height == height)
+            .map(|entry| entry.font.clone())
+    }
+
+    /// Registers loader of custom resources, previous loader of same resource type is
+    /// replaced. See [custom](crate::resource::custom) module docs.
+    pub fn register_loader<L: ResourceLoader>(&mut self, loader: L) {
+        self.custom_loaders
+            .insert(TypeId::of::<L::Resource>(), Arc::new(loader));
+    }
+
+    /// Tries to find custom resource by its path and type. Returns None if no such resource
+    /// was found.
+    pub fn find_custom<T: Send + 'static, P: AsRef<Path>>(
+        &self,
+        path: P,
+    ) -> Option<SharedCustomResource<T>> {
+        self.custom_resources
+            .iter()
+            .filter(|entry| entry.resource.path() == path.as_ref())
+            .find_map(|entry| entry.downcast::<T>())
+    }
+
+    fn add_custom<T: Send + 'static>(&mut self, path: &Path) -> Option<CustomResourceEntry> {
+        let loader = match self.custom_loaders.get(&TypeId::of::<T>()) {
+            Some(loader) => loader.clone(),
+            None => {
+                Log::writeln(format!(
+                    "Unable to load custom resource {:?}! There is no loader for {}",
+                    path,
+                    std::any::type_name::<T>()
+                ));
+                return None;
+            }
+        };
+        let entry = CustomResourceEntry {
+            resource: Arc::new(Mutex::new(CustomResource::<T>::new(
+                path.to_owned(),
+                ResourceState::Pending,
+                None,
+            ))),
+            loader,
+        };
+        self.custom_resources.push(TimedEntry {
+            value: entry.clone(),
+            time_to_live: Self::MAX_RESOURCE_TTL,
+        });
+        Some(entry)
+    }
+
+    /// Tries to load custom resource of type `T` from given path or get instance of existing,
+    /// if any. This method is **blocking**. Loader of `T` must be registered using
+    /// [register_loader](Self::register_loader). On failure it returns None and prints failure
+    /// reason to log.
+    pub fn request_custom<T: Send + 'static, P: AsRef<Path>>(
+        &mut self,
+        path: P,
+    ) -> Option<SharedCustomResource<T>> {
+        if let Some(resource) = self.find_custom::<T, _>(path.as_ref()) {
+            return Some(resource);
+        }
+
+        let entry = self.add_custom::<T>(path.as_ref())?;
+        let data = self.vfs.lock().unwrap().read(path.as_ref());
+        match entry.load(data) {
+            Ok(_) => {
+                Log::writeln(format!(
+                    "Custom resource {} is loaded!",
+                    path.as_ref().display()
+                ));
+                self.missing.remove(path.as_ref());
+                entry.downcast::<T>()
+            }
+            Err(e) => {
+                self.custom_resources
+                    .retain(|other| !Arc::ptr_eq(&other.resource, &entry.resource));
+                self.missing.insert(path.as_ref().to_owned());
+                Log::writeln(format!(
+                    "Unable to load custom resource {}! Reason {}",
+                    path.as_ref().display(),
+                    e
+                ));
+                None
+            }
+        }
+    }
+
+    /// Asynchronous loader of custom resources. Always returns valid resource which could
+    /// still be not loaded, you should check its state to ensure. Resource is loaded on a
+    /// worker thread. Returns None only if there is no loader of `T`.
+    pub fn request_custom_async<T: Send + 'static, P: AsRef<Path>>(
+        &mut self,
+        path: P,
+    ) -> Option<SharedCustomResource<T>> {
+        if let Some(resource) = self.find_custom::<T, _>(path.as_ref()) {
+            return Some(resource);
+        }
+
+        let entry = self.add_custom::<T>(path.as_ref())?;
+        let result = entry.downcast::<T>();
+        let vfs = self.vfs.clone();
+        let path = path.as_ref().to_owned();
+        self.async_paths.insert(path.clone());
+
+        JobSystem::io().spawn(move || {
+            let data = vfs.lock().unwrap().read(&path);
+            match entry.load(data) {
+                Ok(_) => Log::writeln(format!("Custom resource {:?} is loaded!", path)),
+                Err(e) => Log::writeln(format!(
+                    "Unable to load custom resource {:?}! Reason {}",
+                    path, e
+                )),
+            }
+        });
+
+        result
+    }
+
+    /// Returns aggregate progress of asynchronous loading of resources. Resources that were
+    /// loaded by blocking methods are not counted. Useful for loading screens.
+    pub fn loading_progress(&self) -> LoadingProgress {
+        let is_async = |path: &Path| self.async_paths.contains(path);
+        let mut total = self.pending.load(Ordering::SeqCst);
+        let mut pending = total;
+        for texture in self.textures.iter() {
+            let texture = texture.lock().unwrap();
+            if is_async(&texture.path) {
+                total += 1;
+                if texture.state() == ResourceState::Pending {
+                    pending += 1;
+                }
+            }
+        }
+        for entry in self.custom_resources.iter() {
+            if is_async(&entry.resource.path()) {
+                total += 1;
+                if entry.resource.state() == ResourceState::Pending {
+                    pending += 1;
+                }
+            }
+        }
+        total += self
+            .models
+            .iter()
+            .filter(|model| is_async(&model.lock().unwrap().path))
+            .count();
+        total += self
+            .sound_buffers
+            .iter()
+            .filter(|buffer| {
+                let path = buffer.lock().unwrap().external_data_path();
+                path.map_or(false, |path| is_async(&path))
+            })
+            .count();
+        total += self
+            .fonts
+            .iter()
+            .filter(|entry| is_async(&entry.path))
+            .count();
+        LoadingProgress { total, pending }
+    }
+
+    /// Forgets paths of asynchronously requested resources which were unloaded.
+    fn retain_async_paths(&mut self) {
+        if self.async_paths.is_empty() {
+            return;
+        }
+        let mut paths = HashSet::new();
+        paths.extend(self.textures.iter().map(|t| t.lock().unwrap().path.clone()));
+        paths.extend(self.models.iter().map(|m| m.lock().unwrap().path.clone()));
+        paths.extend(
+            self.sound_buffers
+                .iter()
+                .filter_map(|b| b.lock().unwrap().external_data_path()),
+        );
+        paths.extend(self.fonts.iter().map(|entry| entry.path.clone()));
+        paths.extend(self.custom_resources.iter().map(|e| e.resource.path()));
+        self.async_paths.retain(|path| paths.contains(path));
+    }
+
+    fn resource_count(&self) -> usize {
+        self.textures.len()
+            + self.models.len()
+            + self.sound_buffers.len()
+            + self.fonts.len()
+            + self.custom_resources.len()
+    }
+
+    /// Returns usage report of every resource - reference counts, dependencies, list of unused
+    /// and missing resources. Useful to find leaks of resources between levels.
+    pub fn report(&self) -> ResourceReport {
+        let mut report = ResourceReport::default();
+
+        // Every resource is referenced by resource manager itself, exclude such references.
+        for texture in self.textures.iter() {
+            let texture_ref = texture.lock().unwrap();
+            if texture_ref.state() == ResourceState::LoadError {
+                report.missing.push(texture_ref.path.clone());
+            }
+            report.resources.push(ResourceUsage {
+                path: texture_ref.path.clone(),
+                kind: ResourceKind::Texture,
+                reference_count: Arc::strong_count(texture) - 1,
+                dependencies: Default::default(),
+            });
+        }
+        for model in self.models.iter() {
+            let model_ref = model.lock().unwrap();
+            report.resources.push(ResourceUsage {
+                path: model_ref.path.clone(),
+                kind: ResourceKind::Model,
+                reference_count: Arc::strong_count(model) - 1,
+                dependencies: model_ref
+                    .dependencies()
+                    .iter()
+                    .map(|texture| texture.lock().unwrap().path.clone())
+                    .collect(),
+            });
+        }
+        for buffer in self.sound_buffers.iter() {
+            if let Some(path) = buffer.lock().unwrap().external_data_path() {
+                report.resources.push(ResourceUsage {
+                    path,
+                    kind: ResourceKind::SoundBuffer,
+                    reference_count: Arc::strong_count(buffer) - 1,
+                    dependencies: Default::default(),
+                });
+            }
+        }
+        for entry in self.fonts.iter() {
+            report.resources.push(ResourceUsage {
+                path: entry.path.clone(),
+                kind: ResourceKind::Font,
+                reference_count: Arc::strong_count(&entry.font.0) - 1,
+                dependencies: Default::default(),
+            });
+        }
+        for entry in self.custom_resources.iter() {
+            let path = entry.resource.path();
+            if entry.resource.state() == ResourceState::LoadError {
+                report.missing.push(path.clone());
+            }
+            report.resources.push(ResourceUsage {
+                path,
+                kind: ResourceKind::Custom,
+                reference_count: Arc::strong_count(&entry.resource) - 1,
+                dependencies: Default::default(),
+            });
+        }
+
+        report.unused = report
+            .resources
+            .iter()
+            .filter(|usage| usage.reference_count == 0)
+            .map(|usage| usage.path.clone())
+            .collect();
+        report.missing.extend(self.missing.iter().cloned());
+
+        report
+    }
+
+    /// Unloads every resource which is not used by anything right away, without waiting
+    /// until its TTL expires. Returns amount of unloaded resources. Resources which are still
+    /// loading are never unloaded.
+    pub fn unload_unused(&mut self) -> usize {
+        let count = self.resource_count();
+
+        // Models hold references to textures, so they must be unloaded first.
+        self.models.retain(|model| Arc::strong_count(model) > 1);
+        self.textures.retain(|texture| {
+            Arc::strong_count(texture) > 1
+                || texture.lock().unwrap().state() == ResourceState::Pending
+        });
+        self.sound_buffers
+            .retain(|buffer| Arc::strong_count(buffer) > 1);
+        self.fonts
+            .retain(|entry| Arc::strong_count(&entry.font.0) > 1);
+        self.custom_resources.retain(|entry| {
+            Arc::strong_count(&entry.resource) > 1
+                || entry.resource.state() == ResourceState::Pending
+        });
+
+        self.retain_async_paths();
+
+        let unloaded = count - self.resource_count();
+        Log::writeln(format!("{} unused resources were unloaded!", unloaded));
+        unloaded
+    }
+
     /// Returns shared reference to list of available textures.
     #[inline]
     pub fn textures(&self) -> &[TimedEntry<SharedTexture>] {
@@ -322,6 +1093,56 @@ impl ResourceManager {
         None
     }
 
+    /// Remembers import options of a model at given path by writing them into sidecar file
+    /// next to the model. Options are applied on next load of the model, or right away if
+    /// hot reload is enabled and the model is loaded.
+    pub fn set_model_import_options<P: AsRef<Path>>(
+        &mut self,
+        path: P,
+        options: &ModelImportOptions,
+    ) -> std::io::Result<()> {
+        options.save(path.as_ref())?;
+        Log::writeln(format!(
+            "Import options of model {} were saved!",
+            path.as_ref().display()
+        ));
+        Ok(())
+    }
+
+    /// Returns import options of a model at given path, they're read from sidecar file of
+    /// the model. Returns default options if there is no sidecar file or it is invalid.
+    pub fn model_import_options<P: AsRef<Path>>(&self, path: P) -> ModelImportOptions {
+        ModelImportOptions::load(&self.vfs, path.as_ref()).unwrap_or_else(|e| {
+            Log::writeln(format!("Invalid import options! Reason: {}", e));
+            Default::default()
+        })
+    }
+
+    /// Sets derived data cache which is used to store results of expensive processing of
+    /// resources (decoded images, converted models), pass None to disable caching. Cache is
+    /// disabled by default. Cache should be set before loading of resources.
+    ///
+    /// ```no_run
+    /// # use rg3d::{engine::resource_manager::ResourceManager, resource::cache::DerivedDataCache};
+    /// # fn set(resource_manager: &mut ResourceManager) {
+    /// resource_manager.set_derived_data_cache(DerivedDataCache::new("data/cache").ok());
+    /// # }
+    /// ```
+    pub fn set_derived_data_cache(&mut self, cache: Option<DerivedDataCache>) {
+        self.derived_data_cache = cache.map(Arc::new);
+    }
+
+    /// Returns derived data cache, if any.
+    pub fn derived_data_cache(&self) -> Option<&DerivedDataCache> {
+        self.derived_data_cache.as_deref()
+    }
+
+    /// Returns virtual file system which is used to read resources. Use it to mount resource
+    /// packs and directories, mounting should be done before loading of resources.
+    pub fn vfs(&self) -> Arc<Mutex<VirtualFileSystem>> {
+        self.vfs.clone()
+    }
+
     /// Returns current path where to search texture when loading complex model resources.
     #[inline]
     pub fn textures_path(&self) -> &Path {
@@ -342,7 +1163,11 @@ impl ResourceManager {
     fn update_textures(&mut self, dt: f32) {
         for texture in self.textures.iter_mut() {
             texture.time_to_live -= dt;
-            if texture.lock().unwrap().loaded && Arc::strong_count(texture) > 1 {
+            // Pending textures must not be destroyed, otherwise worker thread will load
+            // texture for nothing.
+            if texture.lock().unwrap().state() == ResourceState::Pending
+                || Arc::strong_count(texture) > 1
+            {
                 texture.time_to_live = Self::MAX_RESOURCE_TTL;
             }
         }
@@ -398,48 +1223,209 @@ impl ResourceManager {
         });
     }
 
-    pub(in crate) fn update(&mut self, dt: f32) {
+    fn update_fonts(&mut self, dt: f32) {
+        for entry in self.fonts.iter_mut() {
+            entry.time_to_live -= dt;
+            if Arc::strong_count(&entry.font.0) > 1 {
+                entry.time_to_live = Self::MAX_RESOURCE_TTL;
+            }
+        }
+        self.fonts.retain(|entry| {
+            let retain = entry.time_to_live > 0.0;
+            if !retain {
+                Log::writeln(format!(
+                    "Font resource {:?} destroyed because it not used anymore!",
+                    entry.path
+                ));
+            }
+            retain
+        });
+    }
+
+    fn update_custom_resources(&mut self, dt: f32) {
+        for entry in self.custom_resources.iter_mut() {
+            entry.time_to_live -= dt;
+            if entry.resource.state() == ResourceState::Pending
+                || Arc::strong_count(&entry.resource) > 1
+            {
+                entry.time_to_live = Self::MAX_RESOURCE_TTL;
+            }
+        }
+        self.custom_resources.retain(|entry| {
+            let retain = entry.time_to_live > 0.0;
+            if !retain {
+                Log::writeln(format!(
+                    "Custom resource {:?} destroyed because it not used anymore!",
+                    entry.resource.path()
+                ));
+            }
+            retain
+        });
+    }
+
+    pub(in crate) fn update(&mut self, dt: f32) -> ReloadedResources {
+        let count = self.resource_count();
         self.update_textures(dt);
         self.update_model(dt);
         self.update_sound_buffers(dt);
+        self.update_fonts(dt);
+        self.update_custom_resources(dt);
+        if self.resource_count() != count {
+            self.retain_async_paths();
+        }
+
+        if self.watch_enabled {
+            self.watch_timer -= dt;
+            if self.watch_timer <= 0.0 {
+                self.watch_timer = Self::WATCH_INTERVAL;
+                return self.reload_changed_resources();
+            }
+        }
+
+        Default::default()
+    }
+
+    /// Enables or disables hot reload of resources. When enabled, resource manager will check
+    /// source files of textures, models, fonts and custom resources every [WATCH_INTERVAL](Self::WATCH_INTERVAL)
+    /// seconds and reload resources which were changed. This is useful during development,
+    /// but should be disabled in release builds because checking of files is not free.
+    pub fn set_watch_enabled(&mut self, enabled: bool) {
+        self.watch_enabled = enabled;
+        self.watch_timer = 0.0;
+        self.modification_times.clear();
+    }
+
+    /// Returns true if hot reload is enabled.
+    pub fn is_watch_enabled(&self) -> bool {
+        self.watch_enabled
+    }
+
+    /// Returns true if file at given path has changed since last check. First check of a
+    /// file only remembers its modification time.
+    fn is_file_changed(&mut self, path: &Path) -> bool {
+        let modified = match std::fs::metadata(path).and_then(|m| m.modified()) {
+            Ok(modified) => modified,
+            // File can be removed or can be in process of writing, ignore it for now.
+            Err(_) => return false,
+        };
+        match self.modification_times.insert(path.to_owned(), modified) {
+            Some(previous) => previous != modified,
+            None => false,
+        }
+    }
+
+    fn reload_changed_resources(&mut self) -> ReloadedResources {
+        let mut reloaded = ReloadedResources::default();
+
+        for texture in self.textures.clone() {
+            let path = texture.lock().unwrap().path.clone();
+            if self.is_file_changed(&path) && self.reload_texture(&texture) {
+                Log::writeln(format!("Texture {:?} was hot-reloaded!", path));
+                reloaded.textures.push(texture.value);
+            }
+        }
+
+        for model in self.models.clone() {
+            let path = model.lock().unwrap().path.clone();
+            // Changed import options also require model to be reloaded. Both files must be
+            // checked to remember their modification times.
+            let model_changed = self.is_file_changed(&path);
+            let options_changed = self.is_file_changed(&ModelImportOptions::sidecar_path(&path));
+            if (model_changed || options_changed) && self.reload_model(&model) {
+                Log::writeln(format!("Model {:?} was hot-reloaded!", path));
+                reloaded.models.push(model.value);
+            }
+        }
+
+        for entry in self.fonts.clone() {
+            if self.is_file_changed(&entry.path) {
+                match load_font(&self.vfs, &entry.path, entry.height) {
+                    Ok(font) => {
+                        // Atlas texture of old font will be dropped, renderer will create
+                        // new one from new atlas.
+                        *entry.font.0.lock().unwrap() = font;
+                        Log::writeln(format!("Font {:?} was hot-reloaded!", entry.path));
+                    }
+                    Err(e) => Log::writeln(format!(
+                        "Unable to reload {:?} font! Reason {}",
+                        entry.path, e
+                    )),
+                }
+            }
+        }
+
+        for entry in self.custom_resources.clone() {
+            let path = entry.resource.path();
+            if self.is_file_changed(&path) {
+                let data = self.vfs.lock().unwrap().read(&path);
+                match entry.load(data) {
+                    Ok(_) => {
+                        Log::writeln(format!("Custom resource {:?} was hot-reloaded!", path));
+                        reloaded.custom_resources.push(path);
+                    }
+                    Err(e) => Log::writeln(format!(
+                        "Unable to reload {:?} custom resource! Reason {}",
+                        path, e
+                    )),
+                }
+            }
+        }
+
+        reloaded
+    }
+
+    /// Reloads texture in-place, so every user of the texture will get new data. Returns
+    /// true on success.
+    fn reload_texture(&self, texture: &SharedTexture) -> bool {
+        let mut old_texture = texture.lock().unwrap();
+        match Texture::load_from_vfs(
+            &self.vfs,
+            self.derived_data_cache.as_deref(),
+            old_texture.path.as_path(),
+            old_texture.kind,
+        ) {
+            Ok(new_texture) => {
+                *old_texture = new_texture;
+                true
+            }
+            Err(e) => {
+                Log::writeln(format!(
+                    "Unable to reload {:?} texture! Reason: {}",
+                    old_texture.path, e
+                ));
+                false
+            }
+        }
     }
 
     fn reload_textures(&mut self) {
         for old_texture in self.textures.iter() {
-            let mut old_texture = old_texture.lock().unwrap();
-            let new_texture =
-                match Texture::load_from_file(old_texture.path.as_path(), old_texture.kind) {
-                    Ok(texture) => texture,
-                    Err(e) => {
-                        Log::writeln(format!(
-                            "Unable to reload {:?} texture! Reason: {}",
-                            old_texture.path, e
-                        ));
-                        continue;
-                    }
-                };
-            old_texture.path = Default::default();
-            *old_texture = new_texture;
+            self.reload_texture(old_texture);
+        }
+    }
+
+    /// Reloads model in-place. Returns true on success. Instances of the model must be
+    /// resolved afterwards to get new data.
+    fn reload_model(&mut self, model: &SharedModel) -> bool {
+        // Model must not be locked while loading, loading of a scene requests models and
+        // locks every model to find requested one.
+        let path = model.lock().unwrap().path.clone();
+        match Model::load(path.as_path(), self) {
+            Ok(mut new_model) => {
+                new_model.self_weak_ref = Some(Arc::downgrade(model));
+                *model.lock().unwrap() = new_model;
+                true
+            }
+            Err(e) => {
+                Log::writeln(format!("Unable to reload {:?} model! Reason: {:?}", path, e));
+                false
+            }
         }
     }
 
     fn reload_models(&mut self) {
         for old_model in self.models().to_vec() {
-            let old_model_arc = old_model.clone();
-            let mut old_model = old_model.lock().unwrap();
-            let mut new_model = match Model::load(old_model.path.as_path(), self) {
-                Ok(new_model) => new_model,
-                Err(e) => {
-                    Log::writeln(format!(
-                        "Unable to reload {:?} model! Reason: {:?}",
-                        old_model.path, e
-                    ));
-                    continue;
-                }
-            };
-            new_model.self_weak_ref = Some(Arc::downgrade(&old_model_arc));
-            old_model.path = Default::default();
-            *old_model = new_model;
+            self.reload_model(&old_model);
         }
     }
 
@@ -471,6 +1457,20 @@ impl ResourceManager {
         self.reload_textures();
         self.reload_models();
         self.reload_sound_buffers();
+        self.reload_custom_resources();
+    }
+
+    fn reload_custom_resources(&mut self) {
+        for entry in self.custom_resources.iter() {
+            let path = entry.resource.path();
+            let data = self.vfs.lock().unwrap().read(&path);
+            if let Err(e) = entry.load(data) {
+                Log::writeln(format!(
+                    "Unable to reload {:?} custom resource! Reason {}",
+                    path, e
+                ));
+            }
+        }
     }
 }
 
@@ -485,3 +1485,19 @@ impl Visit for ResourceManager {
         visitor.leave_region()
     }
 }
+
+fn load_sound_buffer(path: &Path, stream: bool) -> Result<SharedSoundBuffer, String> {
+    let source =
+        DataSource::from_file(path).map_err(|e| format!("Invalid data source: {:?}", e))?;
+    let buffer = if stream {
+        SoundBuffer::new_streaming(source)
+    } else {
+        SoundBuffer::new_generic(source)
+    };
+    buffer.map_err(|_| "Unsupported or corrupted data".to_owned())
+}
+
+fn load_font(vfs: &Mutex<VirtualFileSystem>, path: &Path, height: f32) -> Result<Font, String> {
+    let data = vfs.lock().unwrap().read(path).map_err(|e| e.to_string())?;
+    Font::from_memory(data, height, Font::default_char_set()).map_err(|e| e.to_string())
+}
@@ -0,0 +1,428 @@
+//! Versioned save files on top of the visitor.
+//!
+//! Visitor writes any state that implements [Visit](crate::core::visitor::Visit) - the whole
+//! [Engine](crate::engine::Engine), a single scene or game-specific structure with selected
+//! scenes and player data. Raw visitor files have no version, so there is no way to tell
+//! whether a save was made by older version of a game and must be upgraded. [SaveGame] wraps
+//! visitor data into a container with a header, which stores version of the game that made
+//! the save, time of saving and optional compression.
+//!
+//! # Migrations
+//!
+//! Every time the game changes layout of its saved state, it should increase save version and
+//! keep old saves loadable:
+//!
+//! - New fields should be optional: visit them with `let _ = field.visit("Field", visitor);`,
+//! so old saves without such fields are still loaded.
+//! - Renamed, split or recalculated fields are handled by migration hooks. Hook registered for
+//! version N is called for every save with version less than N, after the state was visited.
+//! Visitor is given to the hook, so it can read old fields and fix the state. Hooks are called
+//! in ascending order of their versions, so a save made many versions ago passes through all
+//! migrations one by one.
+//!
+//! # Example
+//!
+//! ```no_run
+//! use rg3d::{
+//!     core::visitor::{Visit, VisitResult, Visitor},
+//!     engine::save::SaveGame,
+//! };
+//!
+//! #[derive(Default)]
+//! struct Player {
+//!     health: f32,
+//!     // Added in version 2, was called "Armour" in version 1.
+//!     armor: f32,
+//! }
+//!
+//! impl Visit for Player {
+//!     fn visit(&mut self, name: &str, visitor: &mut Visitor) -> VisitResult {
+//!         visitor.enter_region(name)?;
+//!         self.health.visit("Health", visitor)?;
+//!         let _ = self.armor.visit("Armor", visitor);
+//!         visitor.leave_region()
+//!     }
+//! }
+//!
+//! let save_game = SaveGame::new(2)
+//!     .with_compression(true)
+//!     .with_migration(2, |player: &mut Player, visitor| {
+//!         visitor.enter_region("State")?;
+//!         player.armor.visit("Armour", visitor)?;
+//!         visitor.leave_region()
+//!     });
+//!
+//! let mut player = Player::default();
+//! save_game.save("save1.bin", &mut player).unwrap();
+//! save_game.load("save1.bin", &mut player).unwrap();
+//! ```
+//!
+//! # Format
+//!
+//! All numbers are little-endian.
+//!
+//! ```text
+//! magic: [u8; 8] = "RG3DSAVE"
+//! format_version: u32 - version of the container itself.
+//! version: u32 - version of the game that made the save.
+//! flags: u8 - bit 0 - payload is compressed (zlib).
+//! timestamp: u64 - seconds since UNIX epoch.
+//! payload_size: u64
+//! payload: [u8; payload_size] - visitor data.
+//! ```
+
+use crate::core::visitor::{Visit, VisitError, VisitResult, Visitor};
+use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
+use std::{
+    fmt::Formatter,
+    io::{Cursor, Read},
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+const SAVE_MAGIC: &[u8; 8] = b"RG3DSAVE";
+const SAVE_FORMAT_VERSION: u32 = 1;
+
+const FLAG_COMPRESSED: u8 = 1;
+
+/// All possible errors that can occur during saving or loading.
+#[derive(Debug)]
+pub enum SaveError {
+    /// An input/output error has occurred.
+    Io(std::io::Error),
+    /// Visitor error, state is not valid or does not match the save.
+    Visit(VisitError),
+    /// File is not a save file.
+    InvalidMagic,
+    /// Container was made by newer version of the engine.
+    UnsupportedFormat(u32),
+    /// Save was made by newer version of the game.
+    UnsupportedVersion {
+        /// Version of the save.
+        version: u32,
+        /// Newest version supported by the game.
+        current: u32,
+    },
+    /// Compressed data is corrupted.
+    Decompression(String),
+    /// Size of payload in header does not match actual size of data, file is truncated or
+    /// corrupted.
+    InvalidPayloadSize {
+        /// Size of payload stored in header.
+        expected: u64,
+        /// Amount of bytes that are actually in the file after header.
+        actual: u64,
+    },
+}
+
+impl std::fmt::Display for SaveError {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), std::fmt::Error> {
+        match self {
+            SaveError::Io(io) => write!(f, "Io error: {}", io),
+            SaveError::Visit(visit) => write!(f, "Visit error: {:?}", visit),
+            SaveError::InvalidMagic => write!(f, "Not a save file."),
+            SaveError::UnsupportedFormat(version) => {
+                write!(f, "Unsupported save format version {}", version)
+            }
+            SaveError::UnsupportedVersion { version, current } => write!(
+                f,
+                "Save version {} is newer than supported version {}",
+                version, current
+            ),
+            SaveError::Decompression(reason) => write!(f, "Unable to decompress: {}", reason),
+            SaveError::InvalidPayloadSize { expected, actual } => write!(
+                f,
+                "Invalid payload size {}, file has only {} bytes of payload",
+                expected, actual
+            ),
+        }
+    }
+}
+
+impl From<std::io::Error> for SaveError {
+    fn from(err: std::io::Error) -> Self {
+        SaveError::Io(err)
+    }
+}
+
+impl From<VisitError> for SaveError {
+    fn from(err: VisitError) -> Self {
+        SaveError::Visit(err)
+    }
+}
+
+/// Header of a save file, can be read without loading whole save, for example to show list
+/// of saves in menu.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct SaveHeader {
+    /// Version of the game that made the save.
+    pub version: u32,
+    /// Whether payload is compressed or not.
+    pub compressed: bool,
+    /// Time when save was made.
+    pub timestamp: SystemTime,
+}
+
+impl SaveHeader {
+    fn write(&self, payload_size: u64, data: &mut Vec<u8>) {
+        data.extend_from_slice(SAVE_MAGIC);
+        data.write_u32::<LittleEndian>(SAVE_FORMAT_VERSION).unwrap();
+        data.write_u32::<LittleEndian>(self.version).unwrap();
+        data.write_u8(if self.compressed { FLAG_COMPRESSED } else { 0 })
+            .unwrap();
+        let seconds = self
+            .timestamp
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or_default();
+        data.write_u64::<LittleEndian>(seconds).unwrap();
+        data.write_u64::<LittleEndian>(payload_size).unwrap();
+    }
+
+    /// Reads header and returns it with payload size.
+    fn read<R: Read>(reader: &mut R) -> Result<(Self, u64), SaveError> {
+        let mut magic = [0; 8];
+        reader.read_exact(&mut magic)?;
+        if &magic != SAVE_MAGIC {
+            return Err(SaveError::InvalidMagic);
+        }
+        let format_version = reader.read_u32::<LittleEndian>()?;
+        if format_version > SAVE_FORMAT_VERSION {
+            return Err(SaveError::UnsupportedFormat(format_version));
+        }
+        let version = reader.read_u32::<LittleEndian>()?;
+        let flags = reader.read_u8()?;
+        let seconds = reader.read_u64::<LittleEndian>()?;
+        let payload_size = reader.read_u64::<LittleEndian>()?;
+        Ok((
+            Self {
+                version,
+                compressed: flags & FLAG_COMPRESSED != 0,
+                timestamp: UNIX_EPOCH + Duration::from_secs(seconds),
+            },
+            payload_size,
+        ))
+    }
+}
+
+/// Packs visitor data into save container.
+fn pack(header: &SaveHeader, payload: &[u8]) -> Vec<u8> {
+    let compressed;
+    let payload = if header.compressed {
+        compressed = deflate::deflate_bytes_zlib(payload);
+        &compressed
+    } else {
+        payload
+    };
+    let mut data = Vec::with_capacity(payload.len() + 40);
+    header.write(payload.len() as u64, &mut data);
+    data.extend_from_slice(payload);
+    data
+}
+
+/// Extracts header and visitor data from save container.
+fn unpack(data: &[u8]) -> Result<(SaveHeader, Vec<u8>), SaveError> {
+    let mut reader = Cursor::new(data);
+    let (header, payload_size) = SaveHeader::read(&mut reader)?;
+    // Size comes from the file, so it must be checked before use - corrupted file must not
+    // cause huge allocation.
+    let payload = &data[reader.position() as usize..];
+    if payload_size != payload.len() as u64 {
+        return Err(SaveError::InvalidPayloadSize {
+            expected: payload_size,
+            actual: payload.len() as u64,
+        });
+    }
+    let payload = if header.compressed {
+        inflate::inflate_bytes_zlib(payload).map_err(SaveError::Decompression)?
+    } else {
+        payload.to_vec()
+    };
+    Ok((header, payload))
+}
+
+/// Visitor can work only with files, so its data goes through temporary file next to the save.
+fn temp_path(path: &Path) -> PathBuf {
+    let mut path = path.as_os_str().to_owned();
+    path.push(".tmp");
+    PathBuf::from(path)
+}
+
+struct Migration<T> {
+    version: u32,
+    hook: Box<dyn Fn(&mut T, &mut Visitor) -> VisitResult>,
+}
+
+/// Saves and loads state of type `T` in versioned container. See module docs.
+pub struct SaveGame<T: Visit> {
+    version: u32,
+    compression: bool,
+    migrations: Vec<Migration<T>>,
+}
+
+impl<T: Visit> SaveGame<T> {
+    /// Creates new save game with given current version of saved state.
+    pub fn new(version: u32) -> Self {
+        Self {
+            version,
+            compression: false,
+            migrations: Default::default(),
+        }
+    }
+
+    /// Enables or disables compression of new saves. Loading handles both kinds of saves.
+    pub fn with_compression(mut self, compression: bool) -> Self {
+        self.compression = compression;
+        self
+    }
+
+    /// Adds migration hook which upgrades saves older than given version. See module docs.
+    pub fn with_migration<F>(mut self, version: u32, hook: F) -> Self
+    where
+        F: Fn(&mut T, &mut Visitor) -> VisitResult + 'static,
+    {
+        let index = self
+            .migrations
+            .iter()
+            .position(|m| m.version > version)
+            .unwrap_or(self.migrations.len());
+        self.migrations.insert(
+            index,
+            Migration {
+                version,
+                hook: Box::new(hook),
+            },
+        );
+        self
+    }
+
+    /// Returns current version of saved state.
+    pub fn version(&self) -> u32 {
+        self.version
+    }
+
+    /// Returns true if new saves are compressed.
+    pub fn is_compressed(&self) -> bool {
+        self.compression
+    }
+
+    /// Reads header of a save file without loading the save.
+    pub fn read_header<P: AsRef<Path>>(path: P) -> Result<SaveHeader, SaveError> {
+        let mut file = std::fs::File::open(path)?;
+        Ok(SaveHeader::read(&mut file)?.0)
+    }
+
+    /// Writes state to a file. Existing save is replaced only when new one is fully written,
+    /// so crash during saving does not destroy previous save.
+    pub fn save<P: AsRef<Path>>(&self, path: P, state: &mut T) -> Result<(), SaveError> {
+        let path = path.as_ref();
+        let temp_path = temp_path(path);
+
+        let result = self.write_temp(&temp_path, state).and_then(|_| {
+            std::fs::rename(&temp_path, path)?;
+            Ok(())
+        });
+        if result.is_err() {
+            // Temporary file may be left partially written.
+            let _ = std::fs::remove_file(&temp_path);
+        }
+        result
+    }
+
+    fn write_temp(&self, temp_path: &Path, state: &mut T) -> Result<(), SaveError> {
+        let mut visitor = Visitor::new();
+        state.visit("State", &mut visitor)?;
+        visitor.save_binary(temp_path)?;
+        let payload = std::fs::read(temp_path)?;
+
+        let header = SaveHeader {
+            version: self.version,
+            compressed: self.compression,
+            timestamp: SystemTime::now(),
+        };
+        std::fs::write(temp_path, pack(&header, &payload))?;
+        Ok(())
+    }
+
+    /// Loads state from a file, applies migrations if save was made by older version. Returns
+    /// header of loaded save.
+    pub fn load<P: AsRef<Path>>(&self, path: P, state: &mut T) -> Result<SaveHeader, SaveError> {
+        let path = path.as_ref();
+
+        let (header, payload) = unpack(&std::fs::read(path)?)?;
+        if header.version > self.version {
+            return Err(SaveError::UnsupportedVersion {
+                version: header.version,
+                current: self.version,
+            });
+        }
+
+        let temp_path = temp_path(path);
+        let visitor = std::fs::write(&temp_path, payload)
+            .map_err(SaveError::from)
+            .and_then(|_| Ok(Visitor::load_binary(&temp_path)?));
+        let _ = std::fs::remove_file(temp_path);
+        let mut visitor = visitor?;
+
+        state.visit("State", &mut visitor)?;
+
+        for migration in self
+            .migrations
+            .iter()
+            .filter(|m| header.version < m.version)
+        {
+            (migration.hook)(state, &mut visitor)?;
+        }
+
+        Ok(header)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::engine::save::{pack, unpack, SaveError, SaveHeader};
+    use std::time::{Duration, UNIX_EPOCH};
+
+    #[test]
+    fn pack_unpack_test() {
+        let payload = (0..200u8).cycle().take(4096).collect::<Vec<_>>();
+        for &compressed in &[false, true] {
+            let header = SaveHeader {
+                version: 3,
+                compressed,
+                timestamp: UNIX_EPOCH + Duration::from_secs(1_600_000_000),
+            };
+            let data = pack(&header, &payload);
+            if compressed {
+                assert!(data.len() < payload.len());
+            }
+            let (restored, restored_payload) = unpack(&data).unwrap();
+            assert_eq!(restored, header);
+            assert_eq!(restored_payload, payload);
+        }
+
+        match unpack(b"NOTASAVE0000000000000000000000000") {
+            Err(SaveError::InvalidMagic) => (),
+            _ => panic!("invalid magic must be reported"),
+        }
+
+        // Truncated file and corrupted size must not be read.
+        let header = SaveHeader {
+            version: 1,
+            compressed: false,
+            timestamp: UNIX_EPOCH,
+        };
+        let data = pack(&header, &payload);
+        match unpack(&data[..data.len() - 1]) {
+            Err(SaveError::InvalidPayloadSize { expected, actual }) => {
+                assert_eq!(expected, payload.len() as u64);
+                assert_eq!(actual, payload.len() as u64 - 1);
+            }
+            _ => panic!("truncated payload must be reported"),
+        }
+        let mut data = data;
+        let size_offset = data.len() - payload.len() - 8;
+        data[size_offset..size_offset + 8].copy_from_slice(&u64::MAX.to_le_bytes());
+        assert!(unpack(&data).is_err());
+    }
+}
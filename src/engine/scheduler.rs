@@ -0,0 +1,378 @@
+//! Scheduler of delayed callbacks, repeating timers and coroutines.
+//!
+//! Sequenced gameplay logic - cutscenes, spawn waves, tutorials - is hard to write as per-frame
+//! state machines. Scheduler allows to write such logic as a sequence of steps: a delayed
+//! callback runs once after given time, a repeating timer runs with given interval, and a
+//! coroutine is an `async` block that can suspend itself with [CoroutineContext::wait_seconds],
+//! [CoroutineContext::wait_event] and other waits of [CoroutineContext].
+//!
+//! Engine owns a scheduler and ticks it in [Engine::update] right after dispatching events of
+//! [event bus](../event_bus/index.html), so a coroutine that waits for an event continues in
+//! the same frame the event is delivered. Callbacks and coroutines don't have access to the
+//! engine, they talk to the rest of the game by publishing events or through shared state.
+//!
+//! [Engine::update]: ../struct.Engine.html#method.update
+//!
+//! # Example
+//!
+//! ```
+//! use rg3d::engine::{event_bus::EventBus, scheduler::Scheduler};
+//!
+//! #[derive(Clone)]
+//! struct SpawnWave(u32);
+//!
+//! #[derive(Clone)]
+//! struct WaveCleared;
+//!
+//! let mut bus = EventBus::new();
+//! let mut scheduler = Scheduler::new();
+//!
+//! let sender = bus.sender();
+//! let cleared = bus.subscribe::<WaveCleared>();
+//! scheduler.start(|ctx| async move {
+//!     for wave in 0..3 {
+//!         ctx.wait_seconds(5.0).await;
+//!         sender.publish(SpawnWave(wave));
+//!         ctx.wait_event(&cleared).await;
+//!     }
+//! });
+//! ```
+
+use crate::engine::event_bus::Subscription;
+use std::{
+    cell::Cell,
+    future::Future,
+    pin::Pin,
+    ptr,
+    rc::Rc,
+    task::{Context, Poll, RawWaker, RawWakerVTable, Waker},
+};
+
+/// Handle of a scheduled task - callback, timer or coroutine.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct TaskHandle(u64);
+
+struct Timer {
+    handle: TaskHandle,
+    fire_time: f64,
+    /// Timer is repeating if it has interval.
+    interval: Option<f64>,
+    callback: Box<dyn FnMut()>,
+}
+
+struct Coroutine {
+    handle: TaskHandle,
+    future: Pin<Box<dyn Future<Output = ()>>>,
+}
+
+/// Allows coroutine to wait for time, frames, events or conditions.
+#[derive(Clone)]
+pub struct CoroutineContext {
+    time: Rc<Cell<f64>>,
+}
+
+impl CoroutineContext {
+    /// Returns current time of the scheduler in seconds.
+    pub fn time(&self) -> f64 {
+        self.time.get()
+    }
+
+    /// Suspends coroutine for given amount of seconds, counting from the moment coroutine
+    /// reaches the wait.
+    pub fn wait_seconds(&self, seconds: f32) -> impl Future<Output = ()> {
+        let time = self.time.clone();
+        let end = time.get() + seconds as f64;
+        WaitUntil {
+            predicate: move || time.get() >= end,
+        }
+    }
+
+    /// Suspends coroutine until next update of the scheduler.
+    pub fn next_frame(&self) -> impl Future<Output = ()> {
+        self.wait_frames(1)
+    }
+
+    /// Suspends coroutine for given amount of updates of the scheduler.
+    pub fn wait_frames(&self, frames: usize) -> impl Future<Output = ()> {
+        let mut frames = frames;
+        WaitUntil {
+            predicate: move || {
+                if frames == 0 {
+                    true
+                } else {
+                    frames -= 1;
+                    false
+                }
+            },
+        }
+    }
+
+    /// Suspends coroutine until given predicate returns true, predicate is checked once per
+    /// update.
+    pub fn wait_until<F: FnMut() -> bool + Unpin>(&self, predicate: F) -> impl Future<Output = ()> {
+        WaitUntil { predicate }
+    }
+
+    /// Suspends coroutine until an event is received by given subscription and returns the
+    /// event.
+    pub fn wait_event<'a, T>(
+        &self,
+        subscription: &'a Subscription<T>,
+    ) -> impl Future<Output = T> + 'a {
+        WaitEvent { subscription }
+    }
+}
+
+struct WaitUntil<F> {
+    predicate: F,
+}
+
+impl<F: FnMut() -> bool + Unpin> Future for WaitUntil<F> {
+    type Output = ();
+
+    fn poll(mut self: Pin<&mut Self>, _: &mut Context) -> Poll<()> {
+        if (self.predicate)() {
+            Poll::Ready(())
+        } else {
+            Poll::Pending
+        }
+    }
+}
+
+struct WaitEvent<'a, T> {
+    subscription: &'a Subscription<T>,
+}
+
+impl<'a, T> Future for WaitEvent<'a, T> {
+    type Output = T;
+
+    fn poll(self: Pin<&mut Self>, _: &mut Context) -> Poll<T> {
+        match self.subscription.poll() {
+            Some(event) => Poll::Ready(event),
+            None => Poll::Pending,
+        }
+    }
+}
+
+// Coroutines are polled every update, so waker does nothing.
+unsafe fn clone_waker(_: *const ()) -> RawWaker {
+    RawWaker::new(ptr::null(), &WAKER_VTABLE)
+}
+
+unsafe fn noop(_: *const ()) {}
+
+const WAKER_VTABLE: RawWakerVTable = RawWakerVTable::new(clone_waker, noop, noop, noop);
+
+/// See module docs.
+pub struct Scheduler {
+    time: Rc<Cell<f64>>,
+    next_id: u64,
+    timers: Vec<Timer>,
+    coroutines: Vec<Coroutine>,
+}
+
+impl Default for Scheduler {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Scheduler {
+    /// Creates new scheduler without tasks.
+    pub fn new() -> Self {
+        Self {
+            time: Rc::new(Cell::new(0.0)),
+            next_id: 0,
+            timers: Default::default(),
+            coroutines: Default::default(),
+        }
+    }
+
+    fn make_handle(&mut self) -> TaskHandle {
+        self.next_id += 1;
+        TaskHandle(self.next_id)
+    }
+
+    /// Returns time in seconds passed since creation of the scheduler.
+    pub fn time(&self) -> f64 {
+        self.time.get()
+    }
+
+    /// Calls given callback once after given amount of seconds.
+    pub fn delay<F: FnOnce() + 'static>(&mut self, seconds: f32, callback: F) -> TaskHandle {
+        let handle = self.make_handle();
+        let mut callback = Some(callback);
+        self.timers.push(Timer {
+            handle,
+            fire_time: self.time() + seconds as f64,
+            interval: None,
+            callback: Box::new(move || {
+                if let Some(callback) = callback.take() {
+                    callback()
+                }
+            }),
+        });
+        handle
+    }
+
+    /// Calls given callback every `interval` seconds until timer is cancelled. First call
+    /// happens after `interval` seconds. If time step is larger than interval, callback is
+    /// called several times during one update.
+    pub fn repeat<F: FnMut() + 'static>(&mut self, interval: f32, callback: F) -> TaskHandle {
+        // Zero interval would hang update.
+        let interval = (interval as f64).max(0.0001);
+        let handle = self.make_handle();
+        self.timers.push(Timer {
+            handle,
+            fire_time: self.time() + interval,
+            interval: Some(interval),
+            callback: Box::new(callback),
+        });
+        handle
+    }
+
+    /// Starts new coroutine. Given function receives context of the coroutine and returns
+    /// coroutine itself - usually an `async move` block. Coroutine starts running on next
+    /// update.
+    pub fn start<F, C>(&mut self, func: F) -> TaskHandle
+    where
+        F: FnOnce(CoroutineContext) -> C,
+        C: Future<Output = ()> + 'static,
+    {
+        let handle = self.make_handle();
+        let context = CoroutineContext {
+            time: self.time.clone(),
+        };
+        self.coroutines.push(Coroutine {
+            handle,
+            future: Box::pin(func(context)),
+        });
+        handle
+    }
+
+    /// Cancels a task, returns false if task was already finished or cancelled.
+    pub fn cancel(&mut self, handle: TaskHandle) -> bool {
+        let count = self.timers.len() + self.coroutines.len();
+        self.timers.retain(|timer| timer.handle != handle);
+        self.coroutines
+            .retain(|coroutine| coroutine.handle != handle);
+        count != self.timers.len() + self.coroutines.len()
+    }
+
+    /// Returns true if task is not finished nor cancelled.
+    pub fn is_alive(&self, handle: TaskHandle) -> bool {
+        self.timers.iter().any(|timer| timer.handle == handle)
+            || self
+                .coroutines
+                .iter()
+                .any(|coroutine| coroutine.handle == handle)
+    }
+
+    /// Cancels every task.
+    pub fn clear(&mut self) {
+        self.timers.clear();
+        self.coroutines.clear();
+    }
+
+    /// Advances time, calls callbacks of timers that are due and resumes coroutines. Called
+    /// by the engine once per frame.
+    pub fn update(&mut self, dt: f32) {
+        let time = self.time() + dt as f64;
+        self.time.set(time);
+
+        for timer in self.timers.iter_mut() {
+            while timer.fire_time <= time {
+                (timer.callback)();
+                match timer.interval {
+                    Some(interval) => timer.fire_time += interval,
+                    None => break,
+                }
+            }
+        }
+        self.timers
+            .retain(|timer| timer.interval.is_some() || timer.fire_time > time);
+
+        let waker = unsafe { Waker::from_raw(RawWaker::new(ptr::null(), &WAKER_VTABLE)) };
+        let mut context = Context::from_waker(&waker);
+        let mut i = 0;
+        while i < self.coroutines.len() {
+            if self.coroutines[i]
+                .future
+                .as_mut()
+                .poll(&mut context)
+                .is_ready()
+            {
+                self.coroutines.remove(i);
+            } else {
+                i += 1;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::engine::{event_bus::EventBus, scheduler::Scheduler};
+    use std::{cell::RefCell, rc::Rc};
+
+    #[test]
+    fn timers_test() {
+        let mut scheduler = Scheduler::new();
+        let log = Rc::new(RefCell::new(Vec::new()));
+
+        let delay_log = log.clone();
+        let delayed = scheduler.delay(1.0, move || delay_log.borrow_mut().push("delay"));
+        let repeat_log = log.clone();
+        let repeating = scheduler.repeat(0.4, move || repeat_log.borrow_mut().push("repeat"));
+
+        scheduler.update(0.5);
+        assert_eq!(*log.borrow(), vec!["repeat"]);
+        // Second repeat and delay.
+        scheduler.update(0.5);
+        assert_eq!(log.borrow().len(), 3);
+        assert!(log.borrow().contains(&"delay"));
+        assert!(!scheduler.is_alive(delayed));
+
+        assert!(scheduler.cancel(repeating));
+        assert!(!scheduler.cancel(repeating));
+        scheduler.update(10.0);
+        assert_eq!(log.borrow().len(), 3);
+    }
+
+    #[test]
+    fn coroutine_test() {
+        #[derive(Clone)]
+        struct Go(u32);
+
+        let mut bus = EventBus::new();
+        let mut scheduler = Scheduler::new();
+        let state = Rc::new(RefCell::new(0));
+
+        let go = bus.subscribe::<Go>();
+        let coroutine_state = state.clone();
+        let coroutine = scheduler.start(move |ctx| async move {
+            ctx.wait_seconds(0.5).await;
+            *coroutine_state.borrow_mut() = 1;
+            let Go(value) = ctx.wait_event(&go).await;
+            *coroutine_state.borrow_mut() = value;
+            ctx.next_frame().await;
+            *coroutine_state.borrow_mut() += 1;
+        });
+
+        scheduler.update(0.6);
+        assert_eq!(*state.borrow(), 0);
+        scheduler.update(0.6);
+        assert_eq!(*state.borrow(), 1);
+        scheduler.update(0.6);
+        assert_eq!(*state.borrow(), 1);
+
+        bus.publish(Go(10));
+        bus.dispatch();
+        scheduler.update(0.1);
+        assert_eq!(*state.borrow(), 10);
+        assert!(scheduler.is_alive(coroutine));
+        scheduler.update(0.1);
+        assert_eq!(*state.borrow(), 11);
+        assert!(!scheduler.is_alive(coroutine));
+    }
+}
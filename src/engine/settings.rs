@@ -0,0 +1,538 @@
+//! Engine settings.
+//!
+//! [EngineSettings] holds settings that players usually change in options menu - resolution,
+//! window mode, vsync, graphics quality, audio volumes and key bindings. Settings are stored
+//! in a human-readable text file, one `key = value` pair per line:
+//!
+//! ```text
+//! # Lines that start with '#' are comments.
+//! window_size = 1280x720
+//! window_mode = windowed
+//! vsync = true
+//! quality = high
+//! master_volume = 1
+//! music_volume = 0.8
+//! effects_volume = 1
+//! bind.Jump = Key:Space, Gamepad:South
+//! ```
+//!
+//! Keys that are unknown to the engine are kept in [EngineSettings::custom], so a game can
+//! store its own settings in the same file. Any setting can be overridden from command line
+//! with `--key=value` or `--key value` arguments, for example `--window_mode=fullscreen`.
+//!
+//! Typical usage is to load settings at startup, create the engine with them, and save them
+//! again when player changes something in options menu:
+//!
+//! ```no_run
+//! use rg3d::{
+//!     engine::{settings::EngineSettings, Engine},
+//!     event_loop::EventLoop,
+//!     gui::node::StubNode,
+//!     window::WindowBuilder,
+//! };
+//!
+//! let mut settings = EngineSettings::load_or_default("settings.cfg").unwrap();
+//! settings.apply_overrides(std::env::args().skip(1)).unwrap();
+//!
+//! let event_loop = EventLoop::new();
+//! let window_builder = WindowBuilder::new().with_title("Game");
+//! let mut engine: Engine<(), StubNode> =
+//!     Engine::with_settings(window_builder, &event_loop, &settings).unwrap();
+//!
+//! // Later, in options menu.
+//! settings.vsync = false;
+//! settings.apply(&mut engine).unwrap();
+//! settings.save("settings.cfg").unwrap();
+//! ```
+//!
+//! Vsync is chosen when OpenGL context is created, so change of vsync takes effect on next
+//! start of the game.
+
+use crate::{
+    engine::{display::WindowMode, error::EngineError, Engine},
+    gui::{message::MessageData, Control},
+    input::{InputBinding, InputMap},
+    renderer::QualitySettings,
+    utils::log::Log,
+};
+use std::{
+    collections::BTreeMap,
+    fmt::{Display, Formatter},
+    fs::File,
+    io::{self, Read, Write},
+    path::Path,
+};
+
+/// Prefix of keys of key bindings.
+const BINDING_PREFIX: &str = "bind.";
+
+/// All possible errors of settings.
+#[derive(Debug)]
+pub enum SettingsError {
+    /// Settings file could not be read or written.
+    Io(io::Error),
+    /// Line of settings file is not a `key = value` pair.
+    InvalidLine {
+        /// Number of the line, starting from 1.
+        line: usize,
+        /// Content of the line.
+        content: String,
+    },
+    /// Value of a setting could not be parsed.
+    InvalidValue {
+        /// Key of the setting.
+        key: String,
+        /// Value that could not be parsed.
+        value: String,
+    },
+    /// Command line argument does not have a value.
+    MissingValue(String),
+}
+
+impl Display for SettingsError {
+    fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
+        match self {
+            SettingsError::Io(e) => write!(f, "Io error: {}", e),
+            SettingsError::InvalidLine { line, content } => {
+                write!(f, "Line {} is not a key = value pair: {}", line, content)
+            }
+            SettingsError::InvalidValue { key, value } => {
+                write!(f, "Invalid value {} of setting {}", value, key)
+            }
+            SettingsError::MissingValue(key) => write!(f, "Setting {} has no value", key),
+        }
+    }
+}
+
+impl From<io::Error> for SettingsError {
+    fn from(e: io::Error) -> Self {
+        SettingsError::Io(e)
+    }
+}
+
+/// Preset of quality settings of renderer.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum QualityTier {
+    /// No shadows, no ambient occlusion, no light scattering.
+    Low,
+    /// Low resolution hard shadows, no ambient occlusion.
+    Medium,
+    /// Default quality of the renderer.
+    High,
+    /// High resolution shadows visible at larger distance.
+    Ultra,
+}
+
+impl QualityTier {
+    /// Returns quality settings of renderer that correspond to the tier.
+    pub fn quality_settings(self) -> QualitySettings {
+        let default = QualitySettings::default();
+        match self {
+            QualityTier::Low => QualitySettings {
+                point_shadows_enabled: false,
+                spot_shadows_enabled: false,
+                point_shadow_map_size: 256,
+                spot_shadow_map_size: 256,
+                use_ssao: false,
+                light_scatter_enabled: false,
+                ..default
+            },
+            QualityTier::Medium => QualitySettings {
+                point_shadow_map_size: 512,
+                spot_shadow_map_size: 512,
+                point_soft_shadows: false,
+                spot_soft_shadows: false,
+                point_shadows_distance: 10.0,
+                spot_shadows_distance: 10.0,
+                use_ssao: false,
+                ..default
+            },
+            QualityTier::High => default,
+            QualityTier::Ultra => QualitySettings {
+                point_shadow_map_size: 2048,
+                spot_shadow_map_size: 2048,
+                point_shadows_distance: 25.0,
+                spot_shadows_distance: 25.0,
+                ..default
+            },
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            QualityTier::Low => "low",
+            QualityTier::Medium => "medium",
+            QualityTier::High => "high",
+            QualityTier::Ultra => "ultra",
+        }
+    }
+}
+
+/// Mode of window in settings, exclusive fullscreen uses video mode of current monitor with
+/// size from settings.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum WindowModeSetting {
+    /// Usual window with decorations.
+    Windowed,
+    /// Window without decorations that covers whole monitor.
+    Borderless,
+    /// Exclusive fullscreen.
+    Fullscreen,
+}
+
+impl WindowModeSetting {
+    fn name(self) -> &'static str {
+        match self {
+            WindowModeSetting::Windowed => "windowed",
+            WindowModeSetting::Borderless => "borderless",
+            WindowModeSetting::Fullscreen => "fullscreen",
+        }
+    }
+}
+
+/// See module docs.
+#[derive(Clone, Debug, PartialEq)]
+pub struct EngineSettings {
+    /// Size of client area of window or resolution of fullscreen mode.
+    pub window_size: (u32, u32),
+    /// Mode of main window.
+    pub window_mode: WindowModeSetting,
+    /// Vertical synchronization, takes effect only when engine is created.
+    pub vsync: bool,
+    /// Quality of graphics.
+    pub quality: QualityTier,
+    /// Master volume in [0; 1] range, applied to sound context.
+    pub master_volume: f32,
+    /// Volume of music in [0; 1] range, it is up to a game how to apply it.
+    pub music_volume: f32,
+    /// Volume of sound effects in [0; 1] range, it is up to a game how to apply it.
+    pub effects_volume: f32,
+    /// Bindings of actions of input map by action name.
+    pub key_bindings: BTreeMap<String, Vec<InputBinding>>,
+    /// Settings unknown to the engine, they're stored as is.
+    pub custom: BTreeMap<String, String>,
+}
+
+impl Default for EngineSettings {
+    fn default() -> Self {
+        Self {
+            window_size: (1280, 720),
+            window_mode: WindowModeSetting::Windowed,
+            vsync: true,
+            quality: QualityTier::High,
+            master_volume: 1.0,
+            music_volume: 1.0,
+            effects_volume: 1.0,
+            key_bindings: Default::default(),
+            custom: Default::default(),
+        }
+    }
+}
+
+fn parse_bool(value: &str) -> Option<bool> {
+    match value {
+        "true" | "on" | "1" => Some(true),
+        "false" | "off" | "0" => Some(false),
+        _ => None,
+    }
+}
+
+fn parse_size(value: &str) -> Option<(u32, u32)> {
+    let mut parts = value.splitn(2, 'x');
+    match (parts.next(), parts.next()) {
+        (Some(width), Some(height)) => {
+            Some((width.trim().parse().ok()?, height.trim().parse().ok()?))
+        }
+        _ => None,
+    }
+}
+
+fn parse_volume(value: &str) -> Option<f32> {
+    value.parse::<f32>().ok().map(|v| v.max(0.0).min(1.0))
+}
+
+impl EngineSettings {
+    /// Loads settings from a file. Settings that are missing in the file have default
+    /// values.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, SettingsError> {
+        let mut text = String::new();
+        File::open(path)?.read_to_string(&mut text)?;
+        Self::from_text(&text)
+    }
+
+    /// Loads settings from a file or returns default settings if file does not exist, for
+    /// example on first start of a game.
+    pub fn load_or_default<P: AsRef<Path>>(path: P) -> Result<Self, SettingsError> {
+        if path.as_ref().exists() {
+            Self::load(path)
+        } else {
+            Ok(Self::default())
+        }
+    }
+
+    /// Parses settings from text, see module docs for format.
+    pub fn from_text(text: &str) -> Result<Self, SettingsError> {
+        let mut settings = Self::default();
+        for (i, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let mut parts = line.splitn(2, '=');
+            match (parts.next(), parts.next()) {
+                (Some(key), Some(value)) => settings.set(key.trim(), value.trim())?,
+                _ => {
+                    return Err(SettingsError::InvalidLine {
+                        line: i + 1,
+                        content: line.to_owned(),
+                    })
+                }
+            }
+        }
+        Ok(settings)
+    }
+
+    /// Saves settings to a file.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), SettingsError> {
+        File::create(path)?.write_all(self.to_text().as_bytes())?;
+        Ok(())
+    }
+
+    /// Returns text representation of settings, see module docs for format.
+    pub fn to_text(&self) -> String {
+        let mut text = format!(
+            concat!(
+                "window_size = {}x{}\nwindow_mode = {}\nvsync = {}\nquality = {}\n",
+                "master_volume = {}\nmusic_volume = {}\neffects_volume = {}\n"
+            ),
+            self.window_size.0,
+            self.window_size.1,
+            self.window_mode.name(),
+            self.vsync,
+            self.quality.name(),
+            self.master_volume,
+            self.music_volume,
+            self.effects_volume
+        );
+        for (action, bindings) in self.key_bindings.iter() {
+            let bindings = bindings
+                .iter()
+                .map(|binding| binding.to_string())
+                .collect::<Vec<_>>();
+            text += &format!("{}{} = {}\n", BINDING_PREFIX, action, bindings.join(", "));
+        }
+        for (key, value) in self.custom.iter() {
+            text += &format!("{} = {}\n", key, value);
+        }
+        text
+    }
+
+    /// Sets a setting by its key from its text value, unknown keys are stored in
+    /// [EngineSettings::custom].
+    pub fn set(&mut self, key: &str, value: &str) -> Result<(), SettingsError> {
+        let invalid_value = || SettingsError::InvalidValue {
+            key: key.to_owned(),
+            value: value.to_owned(),
+        };
+        match key {
+            "window_size" => self.window_size = parse_size(value).ok_or_else(invalid_value)?,
+            "window_mode" => {
+                self.window_mode = match value {
+                    "windowed" => WindowModeSetting::Windowed,
+                    "borderless" => WindowModeSetting::Borderless,
+                    "fullscreen" => WindowModeSetting::Fullscreen,
+                    _ => return Err(invalid_value()),
+                }
+            }
+            "vsync" => self.vsync = parse_bool(value).ok_or_else(invalid_value)?,
+            "quality" => {
+                self.quality = match value {
+                    "low" => QualityTier::Low,
+                    "medium" => QualityTier::Medium,
+                    "high" => QualityTier::High,
+                    "ultra" => QualityTier::Ultra,
+                    _ => return Err(invalid_value()),
+                }
+            }
+            "master_volume" => {
+                self.master_volume = parse_volume(value).ok_or_else(invalid_value)?
+            }
+            "music_volume" => self.music_volume = parse_volume(value).ok_or_else(invalid_value)?,
+            "effects_volume" => {
+                self.effects_volume = parse_volume(value).ok_or_else(invalid_value)?
+            }
+            _ if key.starts_with(BINDING_PREFIX) => {
+                let bindings = value
+                    .split(',')
+                    .filter(|binding| !binding.trim().is_empty())
+                    .map(|binding| binding.parse::<InputBinding>())
+                    .collect::<Result<Vec<_>, _>>()
+                    .map_err(|_| invalid_value())?;
+                self.key_bindings
+                    .insert(key[BINDING_PREFIX.len()..].to_owned(), bindings);
+            }
+            _ => {
+                self.custom.insert(key.to_owned(), value.to_owned());
+            }
+        }
+        Ok(())
+    }
+
+    /// Overrides settings from command line arguments of form `--key=value` or
+    /// `--key value`. Arguments that don't start with `--` are ignored, so it is fine to
+    /// pass every argument of the program except the first one.
+    pub fn apply_overrides<I, S>(&mut self, args: I) -> Result<(), SettingsError>
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            let arg = arg.as_ref();
+            if !arg.starts_with("--") {
+                continue;
+            }
+            let mut parts = arg[2..].splitn(2, '=');
+            match (parts.next(), parts.next()) {
+                (Some(key), Some(value)) => self.set(key, value)?,
+                (Some(key), None) => match args.next() {
+                    Some(value) => self.set(key, value.as_ref())?,
+                    None => return Err(SettingsError::MissingValue(key.to_owned())),
+                },
+                _ => (),
+            }
+        }
+        Ok(())
+    }
+
+    /// Replaces bindings of actions of input map with bindings from settings. Actions that
+    /// are not in settings keep their bindings, so defaults of a game can be set before.
+    pub fn apply_key_bindings(&self, input: &mut InputMap) {
+        for (action, bindings) in self.key_bindings.iter() {
+            input.set_action(action, bindings.clone());
+        }
+    }
+
+    /// Stores bindings of every action of input map, call it after player rebinds keys to
+    /// persist the change.
+    pub fn capture_key_bindings(&mut self, input: &InputMap) {
+        self.key_bindings = input
+            .action_names()
+            .map(|action| (action.to_owned(), input.action_bindings(action).to_vec()))
+            .collect();
+    }
+
+    /// Applies window size and mode, quality and master volume to the engine. Exclusive
+    /// fullscreen falls back to borderless window if current monitor does not support
+    /// size from settings.
+    pub fn apply<M: MessageData, C: Control<M, C>>(
+        &self,
+        engine: &mut Engine<M, C>,
+    ) -> Result<(), EngineError> {
+        let (width, height) = self.window_size;
+        let window_mode = match self.window_mode {
+            WindowModeSetting::Windowed => WindowMode::Windowed,
+            WindowModeSetting::Borderless => WindowMode::Borderless,
+            WindowModeSetting::Fullscreen => {
+                // Video modes are sorted, so the first one has highest refresh rate.
+                let video_mode = engine
+                    .current_monitor()
+                    .video_modes
+                    .into_iter()
+                    .find(|mode| mode.width == width && mode.height == height);
+                match video_mode {
+                    Some(video_mode) => WindowMode::Fullscreen(video_mode),
+                    None => {
+                        Log::writeln(format!(
+                            "Fullscreen mode {}x{} is not supported, using borderless window.",
+                            width, height
+                        ));
+                        WindowMode::Borderless
+                    }
+                }
+            }
+        };
+        if engine.window_mode() != window_mode {
+            engine.set_window_mode(window_mode)?;
+        }
+        if window_mode == WindowMode::Windowed && engine.window_size() != self.window_size {
+            engine.set_window_size(width, height);
+        }
+
+        let quality_settings = self.quality.quality_settings();
+        if engine.renderer.get_quality_settings() != quality_settings {
+            engine.renderer.set_quality_settings(&quality_settings)?;
+        }
+
+        engine
+            .sound_context
+            .lock()
+            .unwrap()
+            .set_master_gain(self.master_volume);
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        engine::settings::{EngineSettings, QualityTier, SettingsError, WindowModeSetting},
+        event::VirtualKeyCode,
+        input::{GamepadButton, InputBinding},
+    };
+
+    #[test]
+    fn settings_test() {
+        let settings = EngineSettings::from_text(
+            "# Comment\nwindow_size = 800x600\nquality=low\nvsync = off\n\
+             bind.Jump = Key:Space, Gamepad:South\nmy_setting = 42\n",
+        )
+        .unwrap();
+        assert_eq!(settings.window_size, (800, 600));
+        assert_eq!(settings.quality, QualityTier::Low);
+        assert!(!settings.vsync);
+        assert_eq!(
+            settings.key_bindings["Jump"],
+            vec![
+                InputBinding::Key(VirtualKeyCode::Space),
+                InputBinding::GamepadButton(GamepadButton::South)
+            ]
+        );
+        assert_eq!(settings.custom["my_setting"], "42");
+        assert_eq!(
+            EngineSettings::from_text(&settings.to_text()).unwrap(),
+            settings
+        );
+
+        assert!(matches!(
+            EngineSettings::from_text("quality = best"),
+            Err(SettingsError::InvalidValue { .. })
+        ));
+        assert!(matches!(
+            EngineSettings::from_text("quality"),
+            Err(SettingsError::InvalidLine { line: 1, .. })
+        ));
+    }
+
+    #[test]
+    fn overrides_test() {
+        let mut settings = EngineSettings::default();
+        settings
+            .apply_overrides(
+                [
+                    "game.exe",
+                    "--window_mode=fullscreen",
+                    "--quality",
+                    "ultra",
+                    "level1",
+                ]
+                .iter(),
+            )
+            .unwrap();
+        assert_eq!(settings.window_mode, WindowModeSetting::Fullscreen);
+        assert_eq!(settings.quality, QualityTier::Ultra);
+        assert!(settings.custom.is_empty());
+        assert!(settings.apply_overrides(["--vsync"].iter()).is_err());
+    }
+}
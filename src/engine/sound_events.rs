@@ -0,0 +1,176 @@
+//! One-shot sound events - fire-and-forget sounds at a position or attached to a node.
+//!
+//! [Engine::play_sound_at] spawns temporary spatial source which plays given buffer once. If
+//! the source is attached to a node, its position follows global position of the node on each
+//! [Engine::update]. Sound context removes play-once sources when they stop, engine then
+//! forgets about them. If the node (or its scene) is removed while the sound is playing, the
+//! source stays at last known position of the node and plays to the end.
+//!
+//! [Engine::play_sound_at]: ../struct.Engine.html#method.play_sound_at
+//! [Engine::update]: ../struct.Engine.html#method.update
+//!
+//! # Example
+//!
+//! ```no_run
+//! # use rg3d::{
+//! #     core::pool::Handle,
+//! #     engine::{
+//! #         resource_manager::SharedSoundBuffer,
+//! #         sound_events::{SoundParams, SoundTarget},
+//! #         Engine,
+//! #     },
+//! #     gui::node::StubNode,
+//! #     scene::{node::Node, Scene},
+//! # };
+//! # fn f(
+//! #     engine: &mut Engine<(), StubNode>,
+//! #     buffer: SharedSoundBuffer,
+//! #     scene: Handle<Scene>,
+//! #     node: Handle<Node>,
+//! # ) {
+//! // Footstep follows the character.
+//! engine
+//!     .play_sound_at(buffer, SoundTarget::Node(scene, node), SoundParams::default())
+//!     .unwrap();
+//! # }
+//! ```
+
+use crate::{
+    core::{math::vec3::Vec3, pool::Handle},
+    engine::resource_manager::SharedSoundBuffer,
+    scene::{node::Node, Scene, SceneContainer},
+    sound::{
+        context::Context,
+        error::SoundError,
+        source::{
+            generic::GenericSourceBuilder, spatial::SpatialSourceBuilder, SoundSource, Status,
+        },
+    },
+};
+
+/// Where one-shot sound is played.
+#[derive(Copy, Clone, Debug)]
+pub enum SoundTarget {
+    /// Fixed position in world space.
+    Position(Vec3),
+    /// Node of a scene, sound follows global position of the node.
+    Node(Handle<Scene>, Handle<Node>),
+}
+
+/// Parameters of one-shot sound.
+#[derive(Copy, Clone, Debug)]
+pub struct SoundParams {
+    /// Gain (volume) of the sound.
+    pub gain: f32,
+    /// Pitch of the sound, 1.0 is normal pitch.
+    pub pitch: f64,
+    /// Radius of spatial source, sound is not attenuated inside of it.
+    pub radius: f32,
+}
+
+impl Default for SoundParams {
+    fn default() -> Self {
+        Self {
+            gain: 1.0,
+            pitch: 1.0,
+            radius: 1.0,
+        }
+    }
+}
+
+#[derive(Debug)]
+struct SoundEvent {
+    source: Handle<SoundSource>,
+    scene: Handle<Scene>,
+    node: Handle<Node>,
+}
+
+/// Sources of one-shot sounds that are attached to nodes. See module docs.
+#[derive(Debug, Default)]
+pub struct SoundEvents {
+    events: Vec<SoundEvent>,
+}
+
+fn target_position(
+    scenes: &SceneContainer,
+    scene: Handle<Scene>,
+    node: Handle<Node>,
+) -> Option<Vec3> {
+    if scenes.is_valid_handle(scene) && scenes[scene].graph.is_valid_handle(node) {
+        Some(scenes[scene].graph[node].global_position())
+    } else {
+        None
+    }
+}
+
+impl SoundEvents {
+    /// Creates empty set of sound events.
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Spawns spatial source that plays given buffer once at given target. See module docs.
+    pub fn play(
+        &mut self,
+        buffer: SharedSoundBuffer,
+        target: SoundTarget,
+        params: SoundParams,
+        scenes: &SceneContainer,
+        sound_context: &mut Context,
+    ) -> Result<Handle<SoundSource>, SoundError> {
+        let position = match target {
+            SoundTarget::Position(position) => position,
+            SoundTarget::Node(scene, node) => {
+                target_position(scenes, scene, node).unwrap_or(Vec3::ZERO)
+            }
+        };
+
+        let generic = GenericSourceBuilder::new(buffer)
+            .with_status(Status::Playing)
+            .with_play_once(true)
+            .with_gain(params.gain)
+            .with_pitch(params.pitch)
+            .build()?;
+        let source = SpatialSourceBuilder::new(generic)
+            .with_position(position)
+            .with_radius(params.radius)
+            .build_source();
+        let handle = sound_context.add_source(source);
+
+        if let SoundTarget::Node(scene, node) = target {
+            self.events.push(SoundEvent {
+                source: handle,
+                scene,
+                node,
+            });
+        }
+
+        Ok(handle)
+    }
+
+    /// Returns amount of sounds that follow nodes at the moment.
+    pub fn attached_count(&self) -> usize {
+        self.events.len()
+    }
+
+    /// Moves sources to global positions of their nodes and forgets sources that were removed
+    /// by sound context when they stopped.
+    pub(in crate) fn update(&mut self, scenes: &SceneContainer, sound_context: &mut Context) {
+        let sources = sound_context.sources_mut();
+        self.events.retain(|event| {
+            if !sources.is_valid_handle(event.source) {
+                return false;
+            }
+            match target_position(scenes, event.scene, event.node) {
+                Some(position) => {
+                    if let SoundSource::Spatial(spatial) = &mut sources[event.source] {
+                        spatial.set_position(&position);
+                    }
+                    true
+                }
+                // Node is gone, source stays where it is and will be removed by the context.
+                None => false,
+            }
+        });
+    }
+}
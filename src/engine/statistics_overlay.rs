@@ -0,0 +1,260 @@
+//! Overlay that shows statistics of the engine on top of everything - FPS, graph of frame
+//! times, draw calls, counts of scene nodes and resources, and memory used by each category
+//! of [memory](crate::engine::memory). Use `Engine::set_statistics_overlay_visible` to
+//! toggle it.
+//!
+//! When [frame profiler](crate::utils::frame_profiler) is enabled, overlay also shows time of
+//! main stages of last frame.
+
+use crate::{
+    core::{color::Color, pool::Handle},
+    engine::{
+        memory::{MemoryBudgets, MemoryCategory, MemoryReport},
+        resource_manager::ResourceManager,
+    },
+    gui::{
+        border::BorderBuilder,
+        brush::Brush,
+        message::{MessageData, MessageDirection, TextMessage, WidgetMessage},
+        node::UINode,
+        stack_panel::StackPanelBuilder,
+        text::TextBuilder,
+        widget::WidgetBuilder,
+        Control, HorizontalAlignment, Orientation, Thickness, UserInterface, VerticalAlignment,
+    },
+    renderer::Renderer,
+    scene::SceneContainer,
+    utils::frame_profiler,
+};
+use std::{collections::VecDeque, sync::Mutex, time::Instant};
+
+/// Amount of frames shown on the graph.
+const GRAPH_LENGTH: usize = 90;
+
+/// Frame time (in milliseconds) which fills whole height of the graph.
+const GRAPH_MAX_FRAME_TIME: f32 = 50.0;
+
+const GRAPH_HEIGHT: f32 = 60.0;
+
+/// Text is not updated every frame, otherwise it is impossible to read.
+const TEXT_UPDATE_INTERVAL: f32 = 0.25;
+
+/// Stages of a frame that are shown when frame profiler is enabled.
+const PROFILED_STAGES: [&str; 6] = ["Update", "Resources", "Scene", "Physics", "UI", "Render"];
+
+fn to_megabytes(bytes: usize) -> f32 {
+    bytes as f32 / (1024.0 * 1024.0)
+}
+
+fn frame_time_color(frame_time: f32) -> Color {
+    if frame_time <= 1000.0 / 60.0 {
+        Color::opaque(80, 200, 80)
+    } else if frame_time <= 1000.0 / 30.0 {
+        Color::opaque(220, 200, 60)
+    } else {
+        Color::opaque(220, 60, 60)
+    }
+}
+
+/// See module docs.
+pub struct StatisticsOverlay<M: MessageData, C: Control<M, C>> {
+    root: Handle<UINode<M, C>>,
+    text: Handle<UINode<M, C>>,
+    bars: Vec<Handle<UINode<M, C>>>,
+    /// Frame times in milliseconds, newest last.
+    frame_times: VecDeque<f32>,
+    last_update: Instant,
+    text_timer: f32,
+    visible: bool,
+}
+
+impl<M: MessageData, C: Control<M, C>> StatisticsOverlay<M, C> {
+    pub(crate) fn new(ui: &mut UserInterface<M, C>) -> Self {
+        let ctx = &mut ui.build_ctx();
+
+        let text;
+        let mut bars = Vec::with_capacity(GRAPH_LENGTH);
+        let root = StackPanelBuilder::new(
+            WidgetBuilder::new()
+                .with_visibility(false)
+                .with_margin(Thickness::uniform(4.0))
+                .with_horizontal_alignment(HorizontalAlignment::Left)
+                .with_vertical_alignment(VerticalAlignment::Top)
+                .with_child({
+                    text = TextBuilder::new(
+                        WidgetBuilder::new()
+                            .with_foreground(Brush::Solid(Color::opaque(255, 255, 255))),
+                    )
+                    .build(ctx);
+                    text
+                })
+                .with_child(
+                    BorderBuilder::new(
+                        WidgetBuilder::new()
+                            .with_height(GRAPH_HEIGHT)
+                            .with_background(Brush::Solid(Color::from_rgba(0, 0, 0, 140)))
+                            .with_child(
+                                StackPanelBuilder::new(WidgetBuilder::new().with_children({
+                                    for _ in 0..GRAPH_LENGTH {
+                                        bars.push(
+                                            BorderBuilder::new(
+                                                WidgetBuilder::new()
+                                                    .with_width(2.0)
+                                                    .with_height(0.0)
+                                                    .with_vertical_alignment(
+                                                        VerticalAlignment::Bottom,
+                                                    )
+                                                    .with_background(Brush::Solid(
+                                                        frame_time_color(0.0),
+                                                    )),
+                                            )
+                                            .build(ctx),
+                                        );
+                                    }
+                                    &bars
+                                }))
+                                .with_orientation(Orientation::Horizontal)
+                                .build(ctx),
+                            ),
+                    )
+                    .build(ctx),
+                ),
+        )
+        .build(ctx);
+
+        Self {
+            root,
+            text,
+            bars,
+            frame_times: VecDeque::with_capacity(GRAPH_LENGTH),
+            last_update: Instant::now(),
+            text_timer: 0.0,
+            visible: false,
+        }
+    }
+
+    pub(crate) fn set_visible(&mut self, ui: &mut UserInterface<M, C>, visible: bool) {
+        if self.visible != visible {
+            self.visible = visible;
+            ui.send_message(WidgetMessage::visibility(
+                self.root,
+                MessageDirection::ToWidget,
+                visible,
+            ));
+        }
+    }
+
+    pub(crate) fn is_visible(&self) -> bool {
+        self.visible
+    }
+
+    /// Measures time from previous update and refreshes the graph and the text.
+    pub(crate) fn update(
+        &mut self,
+        ui: &mut UserInterface<M, C>,
+        renderer: &Renderer,
+        scenes: &SceneContainer,
+        resource_manager: &Mutex<ResourceManager>,
+        memory_budgets: &MemoryBudgets,
+    ) {
+        let now = Instant::now();
+        let frame_time = (now - self.last_update).as_secs_f32();
+        self.last_update = now;
+
+        if self.frame_times.len() == GRAPH_LENGTH {
+            self.frame_times.pop_front();
+        }
+        self.frame_times.push_back(frame_time * 1000.0);
+
+        if !self.visible {
+            return;
+        }
+
+        // Newest frame is on the right.
+        let offset = GRAPH_LENGTH - self.frame_times.len();
+        for (i, &time) in self.frame_times.iter().enumerate() {
+            let bar = self.bars[offset + i];
+            ui.send_message(WidgetMessage::height(
+                bar,
+                MessageDirection::ToWidget,
+                (time / GRAPH_MAX_FRAME_TIME).min(1.0) * GRAPH_HEIGHT,
+            ));
+            ui.send_message(WidgetMessage::background(
+                bar,
+                MessageDirection::ToWidget,
+                Brush::Solid(frame_time_color(time)),
+            ));
+        }
+
+        self.text_timer -= frame_time;
+        if self.text_timer > 0.0 {
+            return;
+        }
+        self.text_timer = TEXT_UPDATE_INTERVAL;
+
+        let statistics = renderer.get_statistics();
+        let (average, worst) = {
+            let sum: f32 = self.frame_times.iter().sum();
+            let worst = self.frame_times.iter().cloned().fold(0.0, f32::max);
+            (sum / self.frame_times.len() as f32, worst)
+        };
+        let mut text = format!(
+            concat!(
+                "FPS: {}\nFrame: {:.2} ms (worst {:.2} ms)\nRender: {:.2} ms\n",
+                "Draw calls: {}\nTriangles: {}\n"
+            ),
+            statistics.frames_per_second,
+            average,
+            worst,
+            statistics.pure_frame_time * 1000.0,
+            statistics.geometry.draw_calls,
+            statistics.geometry.triangles_rendered,
+        );
+
+        let (scene_count, node_count) = scenes.iter().fold((0, 0), |(scenes, nodes), scene| {
+            (scenes + 1, nodes + scene.graph.node_count())
+        });
+        text += &format!("Scenes: {}\nNodes: {}\n", scene_count, node_count);
+
+        // Resource manager can be busy with loading, then resource info is skipped for now.
+        if let Ok(resource_manager) = resource_manager.try_lock() {
+            text += &format!(
+                "Textures: {}\nModels: {}\n",
+                resource_manager.textures().len(),
+                resource_manager.models().len(),
+            );
+            let report = MemoryReport::collect(&resource_manager, scenes, ui, renderer);
+            text += &format!("Memory: {:.1} MB\n", to_megabytes(report.total_all()));
+            for &category in MemoryCategory::ALL.iter() {
+                text += &format!(
+                    "  {}: {:.1} MB{}\n",
+                    category,
+                    to_megabytes(report.total(category)),
+                    if memory_budgets.is_exceeded(category) {
+                        " (over budget)"
+                    } else {
+                        ""
+                    }
+                );
+            }
+        }
+
+        if frame_profiler::is_enabled() {
+            if let Some(frame) = frame_profiler::last_frame() {
+                for stage in PROFILED_STAGES.iter() {
+                    text += &format!(
+                        "{}: {:.2} ms\n",
+                        stage,
+                        frame.total_time(stage).as_secs_f64() * 1000.0
+                    );
+                }
+            }
+        }
+
+        ui.send_message(TextMessage::text(
+            self.text,
+            MessageDirection::ToWidget,
+            text,
+        ));
+    }
+}
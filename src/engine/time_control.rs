@@ -0,0 +1,264 @@
+//! Global time control - time scale (slow motion, fast forward), pause and frame stepping.
+//!
+//! Scaled time is used to update scenes (physics, animations, particle systems, lifetimes of
+//! nodes) and scheduler, pitch of every sound source is multiplied by time scale. User
+//! interface uses real time by default, so menus stay responsive in slow motion. While game
+//! is paused scenes are still rendered and user interface still works, so pause menus can be
+//! shown on top of frozen game. Frame stepping allows to advance paused game by exactly one
+//! step which is useful for debugging.
+//!
+//! Time control is accessible through methods of the engine:
+//!
+//! ```no_run
+//! # use rg3d::{engine::Engine, gui::node::StubNode};
+//! # fn f(engine: &mut Engine<(), StubNode>) {
+//! // Bullet time.
+//! engine.set_time_scale(0.25);
+//! // Pause menu.
+//! engine.set_paused(true);
+//! // Advance paused game by one step.
+//! engine.step_frame(1.0 / 60.0);
+//! # }
+//! ```
+//!
+//! Time control remembers base pitch of every sound source - pitch that source had when time
+//! control has seen it first, pitch of the source is its base pitch multiplied by time scale.
+//! Sources that were added while time is scaled are scaled on next update of the engine. If
+//! game changes pitch of a source while time is scaled, new pitch becomes base pitch of the
+//! source.
+
+use crate::{
+    core::pool::Handle,
+    sound::{
+        context::Context,
+        source::{SoundSource, Status},
+    },
+};
+use std::collections::HashMap;
+
+/// Minimal time scale, use pause to stop time completely.
+pub const MIN_TIME_SCALE: f32 = 0.01;
+
+/// State of global time control. See module docs.
+#[derive(Debug)]
+pub struct TimeControl {
+    scale: f32,
+    paused: bool,
+    pending_steps: Vec<f32>,
+    scale_ui: bool,
+    affect_sound: bool,
+    paused_sources: Vec<Handle<SoundSource>>,
+    /// Base pitches of sources whose pitch is scaled at the moment.
+    base_pitches: HashMap<Handle<SoundSource>, f64>,
+    /// Scale that was applied to pitches of sources last time.
+    applied_scale: f64,
+}
+
+impl Default for TimeControl {
+    fn default() -> Self {
+        Self {
+            scale: 1.0,
+            paused: false,
+            pending_steps: Vec::new(),
+            scale_ui: false,
+            affect_sound: true,
+            paused_sources: Vec::new(),
+            base_pitches: HashMap::new(),
+            applied_scale: 1.0,
+        }
+    }
+}
+
+impl TimeControl {
+    /// Creates time control with normal flow of time.
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Returns current time scale.
+    pub fn scale(&self) -> f32 {
+        self.scale
+    }
+
+    /// Sets time scale, it is clamped to [MIN_TIME_SCALE]. Pitch of sound sources is changed
+    /// accordingly.
+    pub fn set_scale(&mut self, scale: f32, sound_context: &mut Context) {
+        self.scale = scale.max(MIN_TIME_SCALE);
+        self.update_sound(sound_context);
+    }
+
+    /// Returns true if pitch of sound sources must be updated.
+    pub(in crate) fn is_sound_scaled(&self) -> bool {
+        !self.base_pitches.is_empty() || (self.affect_sound && self.scale != 1.0)
+    }
+
+    /// Sets pitch of every source to its base pitch multiplied by time scale, or to its base
+    /// pitch if sound is not affected. See module docs.
+    pub(in crate) fn update_sound(&mut self, sound_context: &mut Context) {
+        let scale = if self.affect_sound {
+            self.scale as f64
+        } else {
+            1.0
+        };
+        let sources = sound_context.sources_mut();
+        self.base_pitches
+            .retain(|handle, _| sources.is_valid_handle(*handle));
+        for (handle, source) in sources.pair_iter_mut() {
+            let base = match self.base_pitches.get(&handle) {
+                Some(&base) if source.pitch() == base * self.applied_scale => base,
+                // New source or pitch was changed by game.
+                _ => source.pitch(),
+            };
+            let pitch = base * scale;
+            if source.pitch() != pitch {
+                source.set_pitch(pitch);
+            }
+            if scale == 1.0 {
+                self.base_pitches.remove(&handle);
+            } else {
+                self.base_pitches.insert(handle, base);
+            }
+        }
+        self.applied_scale = scale;
+    }
+
+    /// Returns true if game is paused.
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    /// Pauses or resumes game. Sound sources that are playing at the moment of pause are
+    /// paused too and resumed later.
+    pub fn set_paused(&mut self, paused: bool, sound_context: &mut Context) {
+        if self.paused == paused {
+            return;
+        }
+        self.paused = paused;
+        if paused {
+            self.pending_steps.clear();
+        }
+        if !self.affect_sound {
+            return;
+        }
+        if paused {
+            self.pause_sources(sound_context);
+        } else {
+            self.resume_sources(sound_context);
+        }
+    }
+
+    fn pause_sources(&mut self, sound_context: &mut Context) {
+        for (handle, source) in sound_context.sources_mut().pair_iter_mut() {
+            if source.status() == Status::Playing {
+                source.pause();
+                self.paused_sources.push(handle);
+            }
+        }
+    }
+
+    fn resume_sources(&mut self, sound_context: &mut Context) {
+        let sources = sound_context.sources_mut();
+        for handle in self.paused_sources.drain(..) {
+            if sources.is_valid_handle(handle) {
+                sources[handle].play();
+            }
+        }
+    }
+
+    /// Requests single step of given duration in (unscaled) seconds while game is paused.
+    /// Does nothing if game is not paused.
+    pub fn step_frame(&mut self, dt: f32) {
+        if self.paused {
+            self.pending_steps.push(dt);
+        }
+    }
+
+    /// Sets whether user interface uses scaled time. If true, animations of user interface
+    /// are slowed down with the rest of the game and stopped while game is paused, but
+    /// interface still handles input.
+    pub fn set_ui_time_scaled(&mut self, scale_ui: bool) {
+        self.scale_ui = scale_ui;
+    }
+
+    /// Returns true if user interface uses scaled time.
+    pub fn is_ui_time_scaled(&self) -> bool {
+        self.scale_ui
+    }
+
+    /// Sets whether time control changes pitch of sound sources and pauses them. When sound
+    /// stops being affected, base pitches of sources are restored and sources paused by time
+    /// control are resumed.
+    pub fn set_sound_affected(&mut self, affect_sound: bool, sound_context: &mut Context) {
+        if self.affect_sound == affect_sound {
+            return;
+        }
+        self.affect_sound = affect_sound;
+        self.update_sound(sound_context);
+        if self.paused {
+            if affect_sound {
+                self.pause_sources(sound_context);
+            } else {
+                self.resume_sources(sound_context);
+            }
+        }
+    }
+
+    /// Returns true if time control affects sound sources.
+    pub fn is_sound_affected(&self) -> bool {
+        self.affect_sound
+    }
+
+    /// Converts real time of a frame to game time of the frame. Returns zero if game is paused
+    /// and no steps were requested.
+    pub fn game_time(&mut self, dt: f32) -> f32 {
+        if self.paused {
+            if self.pending_steps.is_empty() {
+                0.0
+            } else {
+                self.pending_steps.remove(0) * self.scale
+            }
+        } else {
+            dt * self.scale
+        }
+    }
+
+    /// Converts real time of a frame to time of user interface, depending on
+    /// [set_ui_time_scaled](#method.set_ui_time_scaled).
+    pub fn ui_time(&self, dt: f32, game_time: f32) -> f32 {
+        if self.scale_ui {
+            game_time
+        } else {
+            dt
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::engine::time_control::TimeControl;
+
+    #[test]
+    fn game_time_test() {
+        let mut time_control = TimeControl::new();
+        assert_eq!(time_control.game_time(0.5), 0.5);
+
+        time_control.scale = 0.25;
+        assert_eq!(time_control.game_time(0.5), 0.125);
+        assert_eq!(time_control.ui_time(0.5, 0.125), 0.5);
+        time_control.set_ui_time_scaled(true);
+        assert_eq!(time_control.ui_time(0.5, 0.125), 0.125);
+
+        time_control.paused = true;
+        assert_eq!(time_control.game_time(0.5), 0.0);
+        time_control.step_frame(1.0);
+        time_control.step_frame(2.0);
+        assert_eq!(time_control.game_time(0.5), 0.25);
+        assert_eq!(time_control.game_time(0.5), 0.5);
+        assert_eq!(time_control.game_time(0.5), 0.0);
+
+        // Steps are ignored while game is running.
+        time_control.paused = false;
+        time_control.step_frame(1.0);
+        assert_eq!(time_control.game_time(0.5), 0.125);
+    }
+}
@@ -0,0 +1,508 @@
+//! Gamepad support, available with `gamepad` feature.
+//!
+//! [Gamepads] polls gamepads, feeds their buttons and axes into [InputMap], so gamepads work
+//! with actions and axes like keyboard and mouse, and reports connection of gamepads. Any
+//! connected gamepad drives same buttons and axes of input map, games for few local players
+//! can read state of each gamepad separately by [Gamepads::button_value] and
+//! [Gamepads::axis_value].
+//!
+//! Gamepad can also drive user interface - [Gamepads::ui_events] returns keyboard events of
+//! navigation keys (arrows, enter, escape) for presses of D-pad and face buttons and for tilts
+//! of left stick. Mapping of buttons to keys is configured by [UiNavigation].
+//!
+//! ```no_run
+//! use rg3d::input::{gamepad::{GamepadEvent, Gamepads}, InputMap};
+//!
+//! let mut input = InputMap::new();
+//! let mut gamepads = Gamepads::new().unwrap();
+//!
+//! // Every frame, before game logic.
+//! for event in gamepads.update(&mut input) {
+//!     if let GamepadEvent::Connected { id, name } = event {
+//!         println!("{} connected", name);
+//!         let _ = gamepads.rumble(id, 0.5, 0.2);
+//!     }
+//! }
+//! ```
+
+use crate::{
+    gui::message::{ButtonState, KeyCode, OsEvent},
+    input::{apply_dead_zone, GamepadAxis, GamepadButton, InputMap},
+    utils::log::Log,
+};
+use gilrs::{
+    ff::{BaseEffect, BaseEffectType, Effect, EffectBuilder, Replay, Ticks},
+    Axis, Button, EventType, Gilrs, GilrsBuilder,
+};
+use std::{
+    collections::HashMap,
+    fmt::{Display, Formatter},
+};
+
+pub use gilrs::GamepadId;
+
+/// Default dead zone of sticks for [Gamepads::axis_value], dead zone of axes of input map is
+/// set per axis.
+const DEFAULT_DEAD_ZONE: f32 = 0.15;
+
+/// Threshold of analog trigger after which it is treated as pressed button.
+const TRIGGER_THRESHOLD: f32 = 0.5;
+
+/// Gamepad error.
+#[derive(Debug)]
+pub enum GamepadError {
+    /// Gamepads are not supported on current platform or backend failed to start.
+    NotSupported(String),
+    /// Force feedback effect could not be created or played.
+    ForceFeedback(String),
+}
+
+impl Display for GamepadError {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), std::fmt::Error> {
+        match self {
+            GamepadError::NotSupported(v) => write!(f, "Gamepads are not supported: {}", v),
+            GamepadError::ForceFeedback(v) => write!(f, "Force feedback error: {}", v),
+        }
+    }
+}
+
+/// Event of a gamepad.
+#[derive(Clone, Debug, PartialEq)]
+pub enum GamepadEvent {
+    /// Gamepad was connected.
+    Connected {
+        /// Identifier of the gamepad.
+        id: GamepadId,
+        /// Name of the gamepad reported by OS.
+        name: String,
+    },
+    /// Gamepad was disconnected.
+    Disconnected {
+        /// Identifier of the gamepad.
+        id: GamepadId,
+    },
+    /// Button was pressed or released.
+    Button {
+        /// Identifier of the gamepad.
+        id: GamepadId,
+        /// Button.
+        button: GamepadButton,
+        /// New state of the button.
+        pressed: bool,
+    },
+    /// Value of an axis was changed.
+    Axis {
+        /// Identifier of the gamepad.
+        id: GamepadId,
+        /// Axis.
+        axis: GamepadAxis,
+        /// New value of the axis, without dead zone.
+        value: f32,
+    },
+}
+
+fn translate_button(button: Button) -> Option<GamepadButton> {
+    Some(match button {
+        Button::South => GamepadButton::South,
+        Button::East => GamepadButton::East,
+        Button::North => GamepadButton::North,
+        Button::West => GamepadButton::West,
+        Button::LeftTrigger => GamepadButton::LeftBumper,
+        Button::RightTrigger => GamepadButton::RightBumper,
+        Button::LeftTrigger2 => GamepadButton::LeftTrigger,
+        Button::RightTrigger2 => GamepadButton::RightTrigger,
+        Button::Select => GamepadButton::Select,
+        Button::Start => GamepadButton::Start,
+        Button::Mode => GamepadButton::Mode,
+        Button::LeftThumb => GamepadButton::LeftStick,
+        Button::RightThumb => GamepadButton::RightStick,
+        Button::DPadUp => GamepadButton::DPadUp,
+        Button::DPadDown => GamepadButton::DPadDown,
+        Button::DPadLeft => GamepadButton::DPadLeft,
+        Button::DPadRight => GamepadButton::DPadRight,
+        _ => return None,
+    })
+}
+
+fn translate_axis(axis: Axis) -> Option<GamepadAxis> {
+    Some(match axis {
+        Axis::LeftStickX => GamepadAxis::LeftStickX,
+        Axis::LeftStickY => GamepadAxis::LeftStickY,
+        Axis::RightStickX => GamepadAxis::RightStickX,
+        Axis::RightStickY => GamepadAxis::RightStickY,
+        _ => return None,
+    })
+}
+
+/// Mapping of gamepad to navigation keys of user interface.
+pub struct UiNavigation {
+    keys: HashMap<GamepadButton, KeyCode>,
+    /// Tilt of left stick after which it is treated as arrow key, zero disables the stick.
+    pub stick_threshold: f32,
+    /// Delay in seconds before held stick starts to repeat the key.
+    pub repeat_delay: f32,
+    /// Interval in seconds between repeats of held stick.
+    pub repeat_interval: f32,
+    stick_key: Option<KeyCode>,
+    repeat_timer: f32,
+}
+
+impl Default for UiNavigation {
+    fn default() -> Self {
+        let keys = [
+            (GamepadButton::DPadUp, KeyCode::Up),
+            (GamepadButton::DPadDown, KeyCode::Down),
+            (GamepadButton::DPadLeft, KeyCode::Left),
+            (GamepadButton::DPadRight, KeyCode::Right),
+            (GamepadButton::South, KeyCode::Return),
+            (GamepadButton::East, KeyCode::Escape),
+            (GamepadButton::LeftBumper, KeyCode::PageUp),
+            (GamepadButton::RightBumper, KeyCode::PageDown),
+        ]
+        .iter()
+        .cloned()
+        .collect();
+        Self {
+            keys,
+            stick_threshold: 0.6,
+            repeat_delay: 0.4,
+            repeat_interval: 0.12,
+            stick_key: None,
+            repeat_timer: 0.0,
+        }
+    }
+}
+
+impl UiNavigation {
+    /// Maps button to a key, D-pad and A/B (south/east) buttons are mapped to arrows, enter
+    /// and escape by default.
+    pub fn bind(&mut self, button: GamepadButton, key: KeyCode) {
+        self.keys.insert(button, key);
+    }
+
+    /// Removes mapping of a button.
+    pub fn unbind(&mut self, button: GamepadButton) {
+        self.keys.remove(&button);
+    }
+
+    /// Returns navigation key of user interface for a button of a gamepad.
+    pub fn key(&self, button: GamepadButton) -> Option<KeyCode> {
+        self.keys.get(&button).cloned()
+    }
+
+    /// Turns position of a stick into presses of arrow keys, held stick repeats the key.
+    fn update_stick(&mut self, x: f32, y: f32, dt: f32, events: &mut Vec<OsEvent>) {
+        let key = if self.stick_threshold <= 0.0 || x.abs().max(y.abs()) < self.stick_threshold {
+            None
+        } else if x.abs() > y.abs() {
+            Some(if x > 0.0 {
+                KeyCode::Right
+            } else {
+                KeyCode::Left
+            })
+        } else {
+            Some(if y > 0.0 { KeyCode::Up } else { KeyCode::Down })
+        };
+
+        if key != self.stick_key {
+            if let Some(previous) = self.stick_key {
+                events.push(OsEvent::KeyboardInput {
+                    button: previous,
+                    state: ButtonState::Released,
+                });
+            }
+            if let Some(key) = key {
+                events.push(OsEvent::KeyboardInput {
+                    button: key,
+                    state: ButtonState::Pressed,
+                });
+            }
+            self.stick_key = key;
+            self.repeat_timer = self.repeat_delay;
+        } else if let Some(key) = key {
+            self.repeat_timer -= dt;
+            if self.repeat_timer <= 0.0 {
+                self.repeat_timer = self.repeat_interval;
+                events.push(OsEvent::KeyboardInput {
+                    button: key,
+                    state: ButtonState::Pressed,
+                });
+            }
+        }
+    }
+}
+
+#[derive(Default)]
+struct GamepadState {
+    buttons: HashMap<GamepadButton, bool>,
+    axes: HashMap<GamepadAxis, f32>,
+}
+
+struct Rumble {
+    // Effect is stopped when dropped, so it is kept until it is done.
+    _effect: Effect,
+    time_left: f32,
+}
+
+/// Set of connected gamepads. See module docs.
+pub struct Gamepads {
+    gilrs: Gilrs,
+    states: HashMap<GamepadId, GamepadState>,
+    rumbles: Vec<Rumble>,
+    ui_events: Vec<OsEvent>,
+    ui_navigation: UiNavigation,
+    last_update: std::time::Instant,
+}
+
+impl Gamepads {
+    /// Starts gamepad backend of current platform.
+    pub fn new() -> Result<Self, GamepadError> {
+        let gilrs = GilrsBuilder::new()
+            // Analog triggers are reported as buttons as well, so they can be bound to actions.
+            .set_axis_to_btn(TRIGGER_THRESHOLD, TRIGGER_THRESHOLD * 0.8)
+            .build()
+            .map_err(|e| GamepadError::NotSupported(e.to_string()))?;
+        let states = gilrs
+            .gamepads()
+            .map(|(id, _)| (id, GamepadState::default()))
+            .collect();
+        Ok(Self {
+            gilrs,
+            states,
+            rumbles: Default::default(),
+            ui_events: Default::default(),
+            ui_navigation: Default::default(),
+            last_update: std::time::Instant::now(),
+        })
+    }
+
+    /// Returns identifiers and names of connected gamepads.
+    pub fn connected(&self) -> Vec<(GamepadId, String)> {
+        self.gilrs
+            .gamepads()
+            .filter(|(_, gamepad)| gamepad.is_connected())
+            .map(|(id, gamepad)| (id, gamepad.name().to_owned()))
+            .collect()
+    }
+
+    /// Polls gamepads, updates state of gamepad buttons and axes of input map and returns
+    /// events of gamepads. Must be called every frame.
+    pub fn update(&mut self, input: &mut InputMap) -> Vec<GamepadEvent> {
+        let now = std::time::Instant::now();
+        let dt = (now - self.last_update).as_secs_f32();
+        self.last_update = now;
+        for rumble in self.rumbles.iter_mut() {
+            rumble.time_left -= dt;
+        }
+        self.rumbles.retain(|rumble| rumble.time_left > 0.0);
+
+        let mut events = Vec::new();
+        while let Some(event) = self.gilrs.next_event() {
+            let id = event.id;
+            match event.event {
+                EventType::Connected => {
+                    self.states.insert(id, Default::default());
+                    let name = self.gilrs.gamepad(id).name().to_owned();
+                    Log::writeln(format!("Gamepad {} connected", name));
+                    events.push(GamepadEvent::Connected { id, name });
+                }
+                EventType::Disconnected => {
+                    self.states.remove(&id);
+                    events.push(GamepadEvent::Disconnected { id });
+                }
+                EventType::ButtonPressed(button, _) | EventType::ButtonReleased(button, _) => {
+                    if let Some(button) = translate_button(button) {
+                        let pressed = matches!(event.event, EventType::ButtonPressed(..));
+                        self.states
+                            .entry(id)
+                            .or_default()
+                            .buttons
+                            .insert(button, pressed);
+                        if let Some(key) = self.ui_navigation.key(button) {
+                            self.ui_events.push(OsEvent::KeyboardInput {
+                                button: key,
+                                state: if pressed {
+                                    ButtonState::Pressed
+                                } else {
+                                    ButtonState::Released
+                                },
+                            });
+                        }
+                        events.push(GamepadEvent::Button {
+                            id,
+                            button,
+                            pressed,
+                        });
+                    }
+                }
+                EventType::ButtonChanged(Button::LeftTrigger2, value, _)
+                | EventType::ButtonChanged(Button::RightTrigger2, value, _) => {
+                    // Analog triggers are reported as buttons with value.
+                    let axis = match event.event {
+                        EventType::ButtonChanged(Button::LeftTrigger2, ..) => {
+                            GamepadAxis::LeftTrigger
+                        }
+                        _ => GamepadAxis::RightTrigger,
+                    };
+                    self.states.entry(id).or_default().axes.insert(axis, value);
+                    events.push(GamepadEvent::Axis { id, axis, value });
+                }
+                EventType::AxisChanged(axis, value, _) => {
+                    if let Some(axis) = translate_axis(axis) {
+                        self.states.entry(id).or_default().axes.insert(axis, value);
+                        events.push(GamepadEvent::Axis { id, axis, value });
+                    }
+                }
+                _ => (),
+            }
+        }
+
+        // Input map sees combined state of every gamepad - pressed on any gamepad, and axis
+        // with largest magnitude.
+        for &button in GamepadButton::ALL.iter() {
+            let pressed = self
+                .states
+                .values()
+                .any(|state| state.buttons.get(&button).cloned().unwrap_or(false));
+            input.set_gamepad_button(button, pressed);
+        }
+        for &axis in GamepadAxis::ALL.iter() {
+            let value = self
+                .states
+                .values()
+                .filter_map(|state| state.axes.get(&axis).cloned())
+                .fold(0.0f32, |a, b| if b.abs() > a.abs() { b } else { a });
+            input.set_gamepad_axis(axis, value);
+        }
+        let stick = |axis| input.gamepad_axes.get(&axis).cloned().unwrap_or(0.0);
+        self.ui_navigation.update_stick(
+            stick(GamepadAxis::LeftStickX),
+            stick(GamepadAxis::LeftStickY),
+            dt,
+            &mut self.ui_events,
+        );
+
+        events
+    }
+
+    /// Returns keyboard events of navigation keys produced by gamepads since last call, they
+    /// should be passed to `UserInterface::process_os_event`.
+    pub fn ui_events(&mut self) -> Vec<OsEvent> {
+        std::mem::take(&mut self.ui_events)
+    }
+
+    /// Returns mapping of gamepad to navigation keys of user interface.
+    pub fn ui_navigation(&self) -> &UiNavigation {
+        &self.ui_navigation
+    }
+
+    /// Returns mapping of gamepad to navigation keys of user interface.
+    pub fn ui_navigation_mut(&mut self) -> &mut UiNavigation {
+        &mut self.ui_navigation
+    }
+
+    /// Returns true if a button of given gamepad is pressed.
+    pub fn button_value(&self, id: GamepadId, button: GamepadButton) -> bool {
+        self.states
+            .get(&id)
+            .and_then(|state| state.buttons.get(&button).cloned())
+            .unwrap_or(false)
+    }
+
+    /// Returns value of an axis of given gamepad with default dead zone.
+    pub fn axis_value(&self, id: GamepadId, axis: GamepadAxis) -> f32 {
+        let value = self
+            .states
+            .get(&id)
+            .and_then(|state| state.axes.get(&axis).cloned())
+            .unwrap_or(0.0);
+        apply_dead_zone(value, DEFAULT_DEAD_ZONE)
+    }
+
+    /// Vibrates gamepad with given strength (in [0; 1] range) for given time in seconds.
+    pub fn rumble(
+        &mut self,
+        id: GamepadId,
+        strength: f32,
+        duration: f32,
+    ) -> Result<(), GamepadError> {
+        let magnitude = (strength.max(0.0).min(1.0) * u16::MAX as f32) as u16;
+        let effect = EffectBuilder::new()
+            .add_effect(BaseEffect {
+                kind: BaseEffectType::Strong { magnitude },
+                scheduling: Replay {
+                    play_for: Ticks::from_ms((duration * 1000.0) as u32),
+                    ..Default::default()
+                },
+                envelope: Default::default(),
+            })
+            .add_effect(BaseEffect {
+                kind: BaseEffectType::Weak { magnitude },
+                scheduling: Replay {
+                    play_for: Ticks::from_ms((duration * 1000.0) as u32),
+                    ..Default::default()
+                },
+                envelope: Default::default(),
+            })
+            .gamepads(&[id])
+            .finish(&mut self.gilrs)
+            .map_err(|e| GamepadError::ForceFeedback(e.to_string()))?;
+        effect
+            .play()
+            .map_err(|e| GamepadError::ForceFeedback(e.to_string()))?;
+        self.rumbles.push(Rumble {
+            _effect: effect,
+            time_left: duration,
+        });
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        gui::message::KeyCode,
+        input::{
+            gamepad::{translate_button, UiNavigation},
+            GamepadButton,
+        },
+    };
+    use gilrs::Button;
+
+    #[test]
+    fn mapping_test() {
+        assert_eq!(
+            translate_button(Button::LeftTrigger2),
+            Some(GamepadButton::LeftTrigger)
+        );
+        assert_eq!(
+            translate_button(Button::LeftTrigger),
+            Some(GamepadButton::LeftBumper)
+        );
+        assert_eq!(translate_button(Button::Unknown), None);
+
+        let mut navigation = UiNavigation::default();
+        assert!(navigation.key(GamepadButton::DPadUp) == Some(KeyCode::Up));
+        assert!(navigation.key(GamepadButton::North).is_none());
+        navigation.bind(GamepadButton::North, KeyCode::Tab);
+        assert!(navigation.key(GamepadButton::North) == Some(KeyCode::Tab));
+    }
+
+    #[test]
+    fn stick_navigation_test() {
+        let mut navigation = UiNavigation::default();
+        let mut events = Vec::new();
+        navigation.update_stick(0.1, 0.0, 0.1, &mut events);
+        assert!(events.is_empty());
+        navigation.update_stick(0.0, -0.9, 0.1, &mut events);
+        assert_eq!(events.len(), 1);
+        // Held stick repeats the key only after the delay.
+        navigation.update_stick(0.0, -0.9, 0.1, &mut events);
+        assert_eq!(events.len(), 1);
+        navigation.update_stick(0.0, -0.9, 0.5, &mut events);
+        assert_eq!(events.len(), 2);
+        // Release.
+        navigation.update_stick(0.0, 0.0, 0.1, &mut events);
+        assert_eq!(events.len(), 3);
+    }
+}
@@ -0,0 +1,1109 @@
+//! Input mapping - physical inputs (keys, mouse, gamepad) are mapped to named actions and
+//! axes, so game code does not depend on concrete keys and bindings can be changed at
+//! runtime, for example from settings menu.
+//!
+//! Action is a digital input - it is either pressed or not, it can be bound to any amount of
+//! keys and buttons. Axis is an analog input in a range, its value is a sum of values of its
+//! sources - analog axes of mouse and gamepads, or pairs of keys and buttons. Dead zone is
+//! applied to gamepad axes, sensitivity scales final value.
+//!
+//! ```no_run
+//! use rg3d::{
+//!     event::{Event, VirtualKeyCode},
+//!     input::{AnalogInput, AxisSource, InputAxis, InputBinding, InputMap, MouseAxis},
+//! };
+//!
+//! let mut input = InputMap::new();
+//! input.set_action("Jump", vec![InputBinding::Key(VirtualKeyCode::Space)]);
+//! input.set_axis(
+//!     "MoveForward",
+//!     InputAxis::new().with_source(AxisSource::Buttons {
+//!         negative: InputBinding::Key(VirtualKeyCode::S),
+//!         positive: InputBinding::Key(VirtualKeyCode::W),
+//!     }),
+//! );
+//! input.set_axis(
+//!     "Yaw",
+//!     InputAxis::new()
+//!         .with_source(AxisSource::Analog(AnalogInput::Mouse(MouseAxis::X)))
+//!         .with_sensitivity(0.2),
+//! );
+//!
+//! // Every frame: feed events, query actions and axes, then finish frame.
+//! # let event: Event<()> = Event::Suspended;
+//! match &event {
+//!     Event::WindowEvent { event, .. } => input.process_window_event(event),
+//!     Event::DeviceEvent { event, .. } => input.process_device_event(event),
+//!     _ => (),
+//! }
+//! if input.is_action_just_pressed("Jump") {
+//!     // ...
+//! }
+//! let forward = input.axis_value("MoveForward");
+//! input.update();
+//! ```
+//!
+//! Bindings can be saved and loaded by [InputMap::save] and [InputMap::load], or visited as
+//! a part of game settings.
+//!
+//! Gamepads are fed into input map by [gamepad](gamepad/index.html) module, which is
+//! available with `gamepad` feature.
+//!
+//! On touch screens first touching finger acts as left mouse button and its motion is
+//! motion of a mouse, so mouse bindings work without changes. Every active touch is available
+//! in [InputMap::touches] for gestures.
+
+#[cfg(feature = "gamepad")]
+pub mod gamepad;
+pub mod replay;
+
+use crate::{
+    core::{
+        math::vec2::Vec2,
+        visitor::{Visit, VisitResult, Visitor},
+    },
+    event::{
+        DeviceEvent, ElementState, MouseButton, MouseScrollDelta, Touch, TouchPhase,
+        VirtualKeyCode, WindowEvent,
+    },
+};
+use std::{
+    collections::{HashMap, HashSet},
+    fmt::{Display, Formatter},
+    path::Path,
+    str::FromStr,
+};
+
+/// Amount of pixels of precise (touchpad) scrolling which is treated as one line of wheel.
+const PIXELS_PER_WHEEL_LINE: f32 = 20.0;
+
+/// Axis of a mouse.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum MouseAxis {
+    /// Horizontal motion, positive to the right.
+    X = 0,
+    /// Vertical motion, positive to the bottom.
+    Y = 1,
+    /// Vertical wheel, positive away from user.
+    Wheel = 2,
+}
+
+impl MouseAxis {
+    fn from_id(id: u32) -> Result<Self, String> {
+        match id {
+            0 => Ok(MouseAxis::X),
+            1 => Ok(MouseAxis::Y),
+            2 => Ok(MouseAxis::Wheel),
+            _ => Err(format!("Invalid mouse axis {}", id)),
+        }
+    }
+}
+
+/// Button of a gamepad, named by position on the gamepad, so it does not depend on labels.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum GamepadButton {
+    /// Bottom button of right cluster (A on Xbox, Cross on PlayStation).
+    South = 0,
+    /// Right button of right cluster (B on Xbox, Circle on PlayStation).
+    East = 1,
+    /// Top button of right cluster (Y on Xbox, Triangle on PlayStation).
+    North = 2,
+    /// Left button of right cluster (X on Xbox, Square on PlayStation).
+    West = 3,
+    LeftBumper = 4,
+    RightBumper = 5,
+    LeftTrigger = 6,
+    RightTrigger = 7,
+    Select = 8,
+    Start = 9,
+    /// Central button (Xbox, PS).
+    Mode = 10,
+    LeftStick = 11,
+    RightStick = 12,
+    DPadUp = 13,
+    DPadDown = 14,
+    DPadLeft = 15,
+    DPadRight = 16,
+}
+
+impl GamepadButton {
+    const ALL: [GamepadButton; 17] = [
+        GamepadButton::South,
+        GamepadButton::East,
+        GamepadButton::North,
+        GamepadButton::West,
+        GamepadButton::LeftBumper,
+        GamepadButton::RightBumper,
+        GamepadButton::LeftTrigger,
+        GamepadButton::RightTrigger,
+        GamepadButton::Select,
+        GamepadButton::Start,
+        GamepadButton::Mode,
+        GamepadButton::LeftStick,
+        GamepadButton::RightStick,
+        GamepadButton::DPadUp,
+        GamepadButton::DPadDown,
+        GamepadButton::DPadLeft,
+        GamepadButton::DPadRight,
+    ];
+
+    fn from_id(id: u32) -> Result<Self, String> {
+        Self::ALL
+            .get(id as usize)
+            .cloned()
+            .ok_or_else(|| format!("Invalid gamepad button {}", id))
+    }
+}
+
+/// Analog axis of a gamepad, values of sticks are in [-1; 1] range (positive is right and
+/// up), values of triggers are in [0; 1] range.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum GamepadAxis {
+    LeftStickX = 0,
+    LeftStickY = 1,
+    RightStickX = 2,
+    RightStickY = 3,
+    LeftTrigger = 4,
+    RightTrigger = 5,
+}
+
+impl GamepadAxis {
+    const ALL: [GamepadAxis; 6] = [
+        GamepadAxis::LeftStickX,
+        GamepadAxis::LeftStickY,
+        GamepadAxis::RightStickX,
+        GamepadAxis::RightStickY,
+        GamepadAxis::LeftTrigger,
+        GamepadAxis::RightTrigger,
+    ];
+
+    fn from_id(id: u32) -> Result<Self, String> {
+        Self::ALL
+            .get(id as usize)
+            .cloned()
+            .ok_or_else(|| format!("Invalid gamepad axis {}", id))
+    }
+}
+
+/// Digital physical input.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum InputBinding {
+    /// Key of a keyboard.
+    Key(VirtualKeyCode),
+    /// Button of a mouse.
+    MouseButton(MouseButton),
+    /// Button of a gamepad, any connected gamepad can press it.
+    GamepadButton(GamepadButton),
+}
+
+impl Default for InputBinding {
+    fn default() -> Self {
+        InputBinding::Key(VirtualKeyCode::Space)
+    }
+}
+
+impl InputBinding {
+    fn id(&self) -> u32 {
+        match self {
+            InputBinding::Key(_) => 0,
+            InputBinding::MouseButton(_) => 1,
+            InputBinding::GamepadButton(_) => 2,
+        }
+    }
+}
+
+impl Visit for InputBinding {
+    fn visit(&mut self, name: &str, visitor: &mut Visitor) -> VisitResult {
+        visitor.enter_region(name)?;
+
+        let mut kind = self.id();
+        kind.visit("Kind", visitor)?;
+
+        match kind {
+            0 => {
+                // Keys are stored by names, so saved bindings do not depend on order of keys
+                // in windowing library.
+                let mut key_name = match self {
+                    InputBinding::Key(key) => format!("{:?}", key),
+                    _ => Default::default(),
+                };
+                key_name.visit("Key", visitor)?;
+                if visitor.is_reading() {
+                    *self = InputBinding::Key(
+                        key_from_name(&key_name)
+                            .ok_or_else(|| format!("Invalid key {}", key_name))?,
+                    );
+                }
+            }
+            1 => {
+                // Left, right, middle, then other buttons with offset.
+                let mut button = match self {
+                    InputBinding::MouseButton(MouseButton::Left) => 0u32,
+                    InputBinding::MouseButton(MouseButton::Right) => 1,
+                    InputBinding::MouseButton(MouseButton::Middle) => 2,
+                    InputBinding::MouseButton(MouseButton::Other(i)) => 3 + *i as u32,
+                    _ => 0,
+                };
+                button.visit("Button", visitor)?;
+                if visitor.is_reading() {
+                    *self = InputBinding::MouseButton(match button {
+                        0 => MouseButton::Left,
+                        1 => MouseButton::Right,
+                        2 => MouseButton::Middle,
+                        i => MouseButton::Other((i - 3) as u8),
+                    });
+                }
+            }
+            2 => {
+                let mut button = match self {
+                    InputBinding::GamepadButton(button) => *button as u32,
+                    _ => 0,
+                };
+                button.visit("Button", visitor)?;
+                if visitor.is_reading() {
+                    *self = InputBinding::GamepadButton(GamepadButton::from_id(button)?);
+                }
+            }
+            _ => return Err(format!("Invalid input binding {}", kind).into()),
+        }
+
+        visitor.leave_region()
+    }
+}
+
+/// Text form of a binding is `Key:<key>`, `Mouse:<Left|Right|Middle|index>` or
+/// `Gamepad:<button>`, names of keys and gamepad buttons are names of enum variants. It is
+/// used in text settings files.
+impl Display for InputBinding {
+    fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
+        match self {
+            InputBinding::Key(key) => write!(f, "Key:{:?}", key),
+            InputBinding::MouseButton(MouseButton::Other(i)) => write!(f, "Mouse:{}", i),
+            InputBinding::MouseButton(button) => write!(f, "Mouse:{:?}", button),
+            InputBinding::GamepadButton(button) => write!(f, "Gamepad:{:?}", button),
+        }
+    }
+}
+
+impl FromStr for InputBinding {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parts = s.trim().splitn(2, ':');
+        let (kind, name) = match (parts.next(), parts.next()) {
+            (Some(kind), Some(name)) => (kind.trim(), name.trim()),
+            _ => return Err(format!("Invalid input binding {}", s)),
+        };
+        let binding = match kind {
+            "Key" => key_from_name(name).map(InputBinding::Key),
+            "Mouse" => match name {
+                "Left" => Some(MouseButton::Left),
+                "Right" => Some(MouseButton::Right),
+                "Middle" => Some(MouseButton::Middle),
+                _ => name.parse().ok().map(MouseButton::Other),
+            }
+            .map(InputBinding::MouseButton),
+            "Gamepad" => GamepadButton::ALL
+                .iter()
+                .find(|button| format!("{:?}", button) == name)
+                .map(|button| InputBinding::GamepadButton(*button)),
+            _ => None,
+        };
+        binding.ok_or_else(|| format!("Invalid input binding {}", s))
+    }
+}
+
+/// Analog physical input.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum AnalogInput {
+    /// Motion of a mouse, value is a distance passed during a frame.
+    Mouse(MouseAxis),
+    /// Axis of a gamepad.
+    Gamepad(GamepadAxis),
+}
+
+/// Source of a value of an axis.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum AxisSource {
+    /// Analog input.
+    Analog(AnalogInput),
+    /// Pair of digital inputs, value is -1 while negative is pressed and 1 while positive is
+    /// pressed.
+    Buttons {
+        /// Input that gives negative value.
+        negative: InputBinding,
+        /// Input that gives positive value.
+        positive: InputBinding,
+    },
+}
+
+impl Default for AxisSource {
+    fn default() -> Self {
+        AxisSource::Analog(AnalogInput::Mouse(MouseAxis::X))
+    }
+}
+
+impl Visit for AxisSource {
+    fn visit(&mut self, name: &str, visitor: &mut Visitor) -> VisitResult {
+        visitor.enter_region(name)?;
+
+        let mut kind: u32 = match self {
+            AxisSource::Analog(AnalogInput::Mouse(_)) => 0,
+            AxisSource::Analog(AnalogInput::Gamepad(_)) => 1,
+            AxisSource::Buttons { .. } => 2,
+        };
+        kind.visit("Kind", visitor)?;
+
+        match kind {
+            0 | 1 => {
+                let mut axis = match self {
+                    AxisSource::Analog(AnalogInput::Mouse(axis)) => *axis as u32,
+                    AxisSource::Analog(AnalogInput::Gamepad(axis)) => *axis as u32,
+                    _ => 0,
+                };
+                axis.visit("Axis", visitor)?;
+                if visitor.is_reading() {
+                    *self = AxisSource::Analog(if kind == 0 {
+                        AnalogInput::Mouse(MouseAxis::from_id(axis)?)
+                    } else {
+                        AnalogInput::Gamepad(GamepadAxis::from_id(axis)?)
+                    });
+                }
+            }
+            2 => {
+                let (mut negative, mut positive) = match self {
+                    AxisSource::Buttons { negative, positive } => (*negative, *positive),
+                    _ => Default::default(),
+                };
+                negative.visit("Negative", visitor)?;
+                positive.visit("Positive", visitor)?;
+                if visitor.is_reading() {
+                    *self = AxisSource::Buttons { negative, positive };
+                }
+            }
+            _ => return Err(format!("Invalid axis source {}", kind).into()),
+        }
+
+        visitor.leave_region()
+    }
+}
+
+/// Named axis, see module docs.
+#[derive(Clone, Debug, PartialEq)]
+pub struct InputAxis {
+    /// Sources of a value of the axis, their values are summed.
+    pub sources: Vec<AxisSource>,
+    /// Values of gamepad axes with smaller magnitude are treated as zero, this hides drift
+    /// of worn sticks. Rest of the range is remapped, so value still starts from zero.
+    pub dead_zone: f32,
+    /// Multiplier of the value, negative sensitivity inverts the axis.
+    pub sensitivity: f32,
+}
+
+impl Default for InputAxis {
+    fn default() -> Self {
+        Self {
+            sources: Default::default(),
+            dead_zone: 0.15,
+            sensitivity: 1.0,
+        }
+    }
+}
+
+impl InputAxis {
+    /// Creates new axis without sources.
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Adds new source of a value.
+    pub fn with_source(mut self, source: AxisSource) -> Self {
+        self.sources.push(source);
+        self
+    }
+
+    /// Sets dead zone of gamepad axes.
+    pub fn with_dead_zone(mut self, dead_zone: f32) -> Self {
+        self.dead_zone = dead_zone;
+        self
+    }
+
+    /// Sets multiplier of the value.
+    pub fn with_sensitivity(mut self, sensitivity: f32) -> Self {
+        self.sensitivity = sensitivity;
+        self
+    }
+}
+
+impl Visit for InputAxis {
+    fn visit(&mut self, name: &str, visitor: &mut Visitor) -> VisitResult {
+        visitor.enter_region(name)?;
+
+        self.sources.visit("Sources", visitor)?;
+        self.dead_zone.visit("DeadZone", visitor)?;
+        self.sensitivity.visit("Sensitivity", visitor)?;
+
+        visitor.leave_region()
+    }
+}
+
+/// Applies dead zone to a value of an axis and remaps rest of the range to [0; 1].
+pub fn apply_dead_zone(value: f32, dead_zone: f32) -> f32 {
+    if dead_zone >= 1.0 || value.abs() <= dead_zone {
+        0.0
+    } else {
+        (value.signum() * (value.abs() - dead_zone) / (1.0 - dead_zone))
+            .max(-1.0)
+            .min(1.0)
+    }
+}
+
+#[derive(Default, Clone, Debug)]
+struct NamedAction {
+    name: String,
+    bindings: Vec<InputBinding>,
+}
+
+impl Visit for NamedAction {
+    fn visit(&mut self, name: &str, visitor: &mut Visitor) -> VisitResult {
+        visitor.enter_region(name)?;
+
+        self.name.visit("Name", visitor)?;
+        self.bindings.visit("Bindings", visitor)?;
+
+        visitor.leave_region()
+    }
+}
+
+#[derive(Default, Clone, Debug)]
+struct NamedAxis {
+    name: String,
+    axis: InputAxis,
+}
+
+impl Visit for NamedAxis {
+    fn visit(&mut self, name: &str, visitor: &mut Visitor) -> VisitResult {
+        visitor.enter_region(name)?;
+
+        self.name.visit("Name", visitor)?;
+        self.axis.visit("Axis", visitor)?;
+
+        visitor.leave_region()
+    }
+}
+
+/// Finger that touches the screen.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct TouchPoint {
+    /// Unique identifier of the touch, it is the same while finger touches the screen.
+    pub id: u64,
+    /// Current position in window coordinates.
+    pub position: Vec2,
+    /// Position where the touch has started.
+    pub start_position: Vec2,
+}
+
+/// Set of actions and axes with state of physical inputs. See module docs.
+#[derive(Default)]
+pub struct InputMap {
+    actions: Vec<NamedAction>,
+    axes: Vec<NamedAxis>,
+    pressed: HashSet<InputBinding>,
+    /// Inputs pressed in previous frame.
+    previous: HashSet<InputBinding>,
+    gamepad_axes: HashMap<GamepadAxis, f32>,
+    /// Motion of a mouse during current frame by X, Y and wheel.
+    mouse_delta: [f32; 3],
+    last_input: Option<InputBinding>,
+    /// Active touches in order of touching, first one is primary.
+    touches: Vec<TouchPoint>,
+}
+
+impl InputMap {
+    /// Creates new input map without actions and axes.
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Sets bindings of an action, action is created if it does not exist.
+    pub fn set_action(&mut self, name: &str, bindings: Vec<InputBinding>) {
+        match self.actions.iter_mut().find(|a| a.name == name) {
+            Some(action) => action.bindings = bindings,
+            None => self.actions.push(NamedAction {
+                name: name.to_owned(),
+                bindings,
+            }),
+        }
+    }
+
+    /// Adds binding to an action, action is created if it does not exist.
+    pub fn bind_action(&mut self, name: &str, binding: InputBinding) {
+        match self.actions.iter_mut().find(|a| a.name == name) {
+            Some(action) => {
+                if !action.bindings.contains(&binding) {
+                    action.bindings.push(binding);
+                }
+            }
+            None => self.set_action(name, vec![binding]),
+        }
+    }
+
+    /// Removes binding from an action.
+    pub fn unbind_action(&mut self, name: &str, binding: InputBinding) {
+        if let Some(action) = self.actions.iter_mut().find(|a| a.name == name) {
+            action.bindings.retain(|b| *b != binding);
+        }
+    }
+
+    /// Removes an action.
+    pub fn remove_action(&mut self, name: &str) {
+        self.actions.retain(|a| a.name != name);
+    }
+
+    /// Returns bindings of an action, empty slice if there is no such action.
+    pub fn action_bindings(&self, name: &str) -> &[InputBinding] {
+        self.actions
+            .iter()
+            .find(|a| a.name == name)
+            .map_or(&[], |a| a.bindings.as_slice())
+    }
+
+    /// Returns names of all actions.
+    pub fn action_names(&self) -> impl Iterator<Item = &str> {
+        self.actions.iter().map(|a| a.name.as_str())
+    }
+
+    /// Sets an axis, existing axis with same name is replaced.
+    pub fn set_axis(&mut self, name: &str, axis: InputAxis) {
+        match self.axes.iter_mut().find(|a| a.name == name) {
+            Some(named) => named.axis = axis,
+            None => self.axes.push(NamedAxis {
+                name: name.to_owned(),
+                axis,
+            }),
+        }
+    }
+
+    /// Returns an axis by name.
+    pub fn axis(&self, name: &str) -> Option<&InputAxis> {
+        self.axes.iter().find(|a| a.name == name).map(|a| &a.axis)
+    }
+
+    /// Returns an axis by name, use it to change sources, dead zone or sensitivity.
+    pub fn axis_mut(&mut self, name: &str) -> Option<&mut InputAxis> {
+        self.axes
+            .iter_mut()
+            .find(|a| a.name == name)
+            .map(|a| &mut a.axis)
+    }
+
+    /// Removes an axis.
+    pub fn remove_axis(&mut self, name: &str) {
+        self.axes.retain(|a| a.name != name);
+    }
+
+    /// Returns names of all axes.
+    pub fn axis_names(&self) -> impl Iterator<Item = &str> {
+        self.axes.iter().map(|a| a.name.as_str())
+    }
+
+    fn set_pressed(&mut self, binding: InputBinding, pressed: bool) {
+        if pressed {
+            if self.pressed.insert(binding) {
+                self.last_input = Some(binding);
+            }
+        } else {
+            self.pressed.remove(&binding);
+        }
+    }
+
+    /// Updates state of keyboard, mouse buttons and wheel.
+    pub fn process_window_event(&mut self, event: &WindowEvent) {
+        match event {
+            WindowEvent::KeyboardInput { input, .. } => {
+                if let Some(key) = input.virtual_keycode {
+                    self.set_pressed(InputBinding::Key(key), input.state == ElementState::Pressed);
+                }
+            }
+            WindowEvent::MouseInput { state, button, .. } => {
+                self.set_pressed(
+                    InputBinding::MouseButton(*button),
+                    *state == ElementState::Pressed,
+                );
+            }
+            WindowEvent::MouseWheel { delta, .. } => {
+                self.mouse_delta[MouseAxis::Wheel as usize] += match delta {
+                    MouseScrollDelta::LineDelta(_, y) => *y,
+                    MouseScrollDelta::PixelDelta(pos) => pos.y as f32 / PIXELS_PER_WHEEL_LINE,
+                };
+            }
+            WindowEvent::Touch(touch) => self.process_touch(touch),
+            WindowEvent::Focused(false) => {
+                // Release events won't come while window is not focused, so keys would
+                // stay pressed forever.
+                self.pressed.clear();
+                self.touches.clear();
+            }
+            _ => (),
+        }
+    }
+
+    fn process_touch(&mut self, touch: &Touch) {
+        let position = Vec2::new(touch.location.x as f32, touch.location.y as f32);
+        let index = self.touches.iter().position(|t| t.id == touch.id);
+        match (touch.phase, index) {
+            (TouchPhase::Started, None) => {
+                if self.touches.is_empty() {
+                    self.set_pressed(InputBinding::MouseButton(MouseButton::Left), true);
+                }
+                self.touches.push(TouchPoint {
+                    id: touch.id,
+                    position,
+                    start_position: position,
+                });
+            }
+            (TouchPhase::Moved, Some(index)) => {
+                if index == 0 {
+                    let previous = self.touches[0].position;
+                    self.mouse_delta[MouseAxis::X as usize] += position.x - previous.x;
+                    self.mouse_delta[MouseAxis::Y as usize] += position.y - previous.y;
+                }
+                self.touches[index].position = position;
+            }
+            (TouchPhase::Ended, Some(index)) | (TouchPhase::Cancelled, Some(index)) => {
+                self.touches.remove(index);
+                if self.touches.is_empty() {
+                    self.set_pressed(InputBinding::MouseButton(MouseButton::Left), false);
+                }
+            }
+            _ => (),
+        }
+    }
+
+    /// Returns fingers that touch the screen in order of touching.
+    pub fn touches(&self) -> &[TouchPoint] {
+        &self.touches
+    }
+
+    /// Updates motion of a mouse, raw device events are used because they're not limited by
+    /// borders of a window and not affected by mouse acceleration of OS.
+    pub fn process_device_event(&mut self, event: &DeviceEvent) {
+        if let DeviceEvent::MouseMotion { delta } = event {
+            self.mouse_delta[MouseAxis::X as usize] += delta.0 as f32;
+            self.mouse_delta[MouseAxis::Y as usize] += delta.1 as f32;
+        }
+    }
+
+    /// Sets state of a button of a gamepad, it is used by gamepad backends.
+    pub fn set_gamepad_button(&mut self, button: GamepadButton, pressed: bool) {
+        self.set_pressed(InputBinding::GamepadButton(button), pressed);
+    }
+
+    /// Sets value of an axis of a gamepad, it is used by gamepad backends.
+    pub fn set_gamepad_axis(&mut self, axis: GamepadAxis, value: f32) {
+        self.gamepad_axes.insert(axis, value);
+    }
+
+    /// Returns true if physical input is pressed.
+    pub fn is_pressed(&self, binding: InputBinding) -> bool {
+        self.pressed.contains(&binding)
+    }
+
+    /// Returns true if any input of an action is pressed.
+    pub fn is_action_pressed(&self, name: &str) -> bool {
+        self.action_bindings(name)
+            .iter()
+            .any(|b| self.pressed.contains(b))
+    }
+
+    fn was_action_pressed(&self, name: &str) -> bool {
+        self.action_bindings(name)
+            .iter()
+            .any(|b| self.previous.contains(b))
+    }
+
+    /// Returns true if action was pressed during current frame.
+    pub fn is_action_just_pressed(&self, name: &str) -> bool {
+        self.is_action_pressed(name) && !self.was_action_pressed(name)
+    }
+
+    /// Returns true if action was released during current frame.
+    pub fn is_action_just_released(&self, name: &str) -> bool {
+        !self.is_action_pressed(name) && self.was_action_pressed(name)
+    }
+
+    /// Returns current value of an analog input, without dead zone.
+    pub fn analog_value(&self, input: AnalogInput) -> f32 {
+        match input {
+            AnalogInput::Mouse(axis) => self.mouse_delta[axis as usize],
+            AnalogInput::Gamepad(axis) => self.gamepad_axes.get(&axis).cloned().unwrap_or(0.0),
+        }
+    }
+
+    /// Returns value of an axis, zero if there is no such axis.
+    pub fn axis_value(&self, name: &str) -> f32 {
+        let axis = match self.axis(name) {
+            Some(axis) => axis,
+            None => return 0.0,
+        };
+        let value: f32 = axis
+            .sources
+            .iter()
+            .map(|source| match *source {
+                AxisSource::Analog(input @ AnalogInput::Mouse(_)) => self.analog_value(input),
+                AxisSource::Analog(input @ AnalogInput::Gamepad(_)) => {
+                    apply_dead_zone(self.analog_value(input), axis.dead_zone)
+                }
+                AxisSource::Buttons { negative, positive } => {
+                    self.is_pressed(positive) as i32 as f32
+                        - self.is_pressed(negative) as i32 as f32
+                }
+            })
+            .sum();
+        value * axis.sensitivity
+    }
+
+    /// Returns last pressed physical input and forgets it. It is useful for rebinding
+    /// screens - clear it when screen waits for a new binding, then poll it every frame.
+    pub fn take_last_input(&mut self) -> Option<InputBinding> {
+        self.last_input.take()
+    }
+
+    /// Finishes current frame - remembers state of inputs for "just pressed" queries and
+    /// resets motion of a mouse. Must be called once per frame after game logic.
+    pub fn update(&mut self) {
+        self.previous.clone_from(&self.pressed);
+        self.mouse_delta = [0.0; 3];
+    }
+
+    /// Saves actions and axes to a file.
+    pub fn save<P: AsRef<Path>>(&mut self, path: P) -> VisitResult {
+        let mut visitor = Visitor::new();
+        self.visit("InputMap", &mut visitor)?;
+        visitor.save_binary(path)
+    }
+
+    /// Loads actions and axes from a file, current actions and axes are replaced.
+    pub fn load<P: AsRef<Path>>(&mut self, path: P) -> VisitResult {
+        let mut visitor = Visitor::load_binary(path)?;
+        self.visit("InputMap", &mut visitor)
+    }
+}
+
+impl Visit for InputMap {
+    fn visit(&mut self, name: &str, visitor: &mut Visitor) -> VisitResult {
+        visitor.enter_region(name)?;
+
+        self.actions.visit("Actions", visitor)?;
+        self.axes.visit("Axes", visitor)?;
+
+        visitor.leave_region()
+    }
+}
+
+macro_rules! define_keys {
+    ($($key:ident),*) => {
+        const KEYS: &[VirtualKeyCode] = &[$(VirtualKeyCode::$key),*];
+    };
+}
+
+define_keys!(
+    Key1,
+    Key2,
+    Key3,
+    Key4,
+    Key5,
+    Key6,
+    Key7,
+    Key8,
+    Key9,
+    Key0,
+    A,
+    B,
+    C,
+    D,
+    E,
+    F,
+    G,
+    H,
+    I,
+    J,
+    K,
+    L,
+    M,
+    N,
+    O,
+    P,
+    Q,
+    R,
+    S,
+    T,
+    U,
+    V,
+    W,
+    X,
+    Y,
+    Z,
+    Escape,
+    F1,
+    F2,
+    F3,
+    F4,
+    F5,
+    F6,
+    F7,
+    F8,
+    F9,
+    F10,
+    F11,
+    F12,
+    F13,
+    F14,
+    F15,
+    F16,
+    F17,
+    F18,
+    F19,
+    F20,
+    F21,
+    F22,
+    F23,
+    F24,
+    Snapshot,
+    Scroll,
+    Pause,
+    Insert,
+    Home,
+    Delete,
+    End,
+    PageDown,
+    PageUp,
+    Left,
+    Up,
+    Right,
+    Down,
+    Back,
+    Return,
+    Space,
+    Compose,
+    Caret,
+    Numlock,
+    Numpad0,
+    Numpad1,
+    Numpad2,
+    Numpad3,
+    Numpad4,
+    Numpad5,
+    Numpad6,
+    Numpad7,
+    Numpad8,
+    Numpad9,
+    AbntC1,
+    AbntC2,
+    Add,
+    Apostrophe,
+    Apps,
+    At,
+    Ax,
+    Backslash,
+    Calculator,
+    Capital,
+    Colon,
+    Comma,
+    Convert,
+    Decimal,
+    Divide,
+    Equals,
+    Grave,
+    Kana,
+    Kanji,
+    LAlt,
+    LBracket,
+    LControl,
+    LShift,
+    LWin,
+    Mail,
+    MediaSelect,
+    MediaStop,
+    Minus,
+    Multiply,
+    Mute,
+    MyComputer,
+    NavigateForward,
+    NavigateBackward,
+    NextTrack,
+    NoConvert,
+    NumpadComma,
+    NumpadEnter,
+    NumpadEquals,
+    OEM102,
+    Period,
+    PlayPause,
+    Power,
+    PrevTrack,
+    RAlt,
+    RBracket,
+    RControl,
+    RShift,
+    RWin,
+    Semicolon,
+    Slash,
+    Sleep,
+    Stop,
+    Subtract,
+    Sysrq,
+    Tab,
+    Underline,
+    Unlabeled,
+    VolumeDown,
+    VolumeUp,
+    Wake,
+    WebBack,
+    WebFavorites,
+    WebForward,
+    WebHome,
+    WebRefresh,
+    WebSearch,
+    WebStop,
+    Yen,
+    Copy,
+    Paste,
+    Cut
+);
+
+/// Returns key by its name, name is same as name of a variant of [VirtualKeyCode].
+pub fn key_from_name(name: &str) -> Option<VirtualKeyCode> {
+    KEYS.iter()
+        .find(|key| format!("{:?}", key) == name)
+        .cloned()
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        dpi::PhysicalPosition,
+        event::{DeviceId, MouseButton, Touch, TouchPhase, VirtualKeyCode, WindowEvent},
+        input::{
+            apply_dead_zone, key_from_name, AnalogInput, AxisSource, GamepadAxis, GamepadButton,
+            InputAxis, InputBinding, InputMap, MouseAxis,
+        },
+    };
+
+    #[test]
+    fn binding_text_test() {
+        for binding in [
+            InputBinding::Key(VirtualKeyCode::Space),
+            InputBinding::MouseButton(MouseButton::Middle),
+            InputBinding::MouseButton(MouseButton::Other(5)),
+            InputBinding::GamepadButton(GamepadButton::DPadLeft),
+        ]
+        .iter()
+        {
+            assert_eq!(binding.to_string().parse::<InputBinding>(), Ok(*binding));
+        }
+        assert!("Key:NoSuchKey".parse::<InputBinding>().is_err());
+        assert!("Space".parse::<InputBinding>().is_err());
+    }
+
+    #[test]
+    fn action_test() {
+        let mut input = InputMap::new();
+        input.set_action("Jump", vec![InputBinding::Key(VirtualKeyCode::Space)]);
+        input.bind_action("Jump", InputBinding::GamepadButton(GamepadButton::South));
+
+        input.set_gamepad_button(GamepadButton::South, true);
+        assert!(input.is_action_pressed("Jump"));
+        assert!(input.is_action_just_pressed("Jump"));
+        assert_eq!(
+            input.take_last_input(),
+            Some(InputBinding::GamepadButton(GamepadButton::South))
+        );
+        input.update();
+        assert!(input.is_action_pressed("Jump"));
+        assert!(!input.is_action_just_pressed("Jump"));
+
+        input.set_gamepad_button(GamepadButton::South, false);
+        assert!(input.is_action_just_released("Jump"));
+
+        input.unbind_action("Jump", InputBinding::GamepadButton(GamepadButton::South));
+        input.set_gamepad_button(GamepadButton::South, true);
+        assert!(!input.is_action_pressed("Jump"));
+    }
+
+    #[test]
+    fn axis_test() {
+        let mut input = InputMap::new();
+        input.set_axis(
+            "Move",
+            InputAxis::new()
+                .with_source(AxisSource::Analog(AnalogInput::Gamepad(
+                    GamepadAxis::LeftStickY,
+                )))
+                .with_source(AxisSource::Buttons {
+                    negative: InputBinding::Key(VirtualKeyCode::S),
+                    positive: InputBinding::Key(VirtualKeyCode::W),
+                })
+                .with_dead_zone(0.2)
+                .with_sensitivity(2.0),
+        );
+
+        input.set_gamepad_axis(GamepadAxis::LeftStickY, 0.1);
+        assert_eq!(input.axis_value("Move"), 0.0);
+        input.set_gamepad_axis(GamepadAxis::LeftStickY, 0.6);
+        assert!((input.axis_value("Move") - 1.0).abs() < 1e-5);
+        input.set_gamepad_axis(GamepadAxis::LeftStickY, 0.0);
+        input.set_pressed(InputBinding::Key(VirtualKeyCode::W), true);
+        assert_eq!(input.axis_value("Move"), 2.0);
+        assert_eq!(input.axis_value("Unknown"), 0.0);
+    }
+
+    #[test]
+    fn touch_test() {
+        fn touch(input: &mut InputMap, id: u64, phase: TouchPhase, x: f64, y: f64) {
+            input.process_window_event(&WindowEvent::Touch(Touch {
+                device_id: unsafe { DeviceId::dummy() },
+                phase,
+                location: PhysicalPosition::new(x, y),
+                force: None,
+                id,
+            }));
+        }
+
+        let mut input = InputMap::new();
+        touch(&mut input, 1, TouchPhase::Started, 10.0, 10.0);
+        touch(&mut input, 2, TouchPhase::Started, 50.0, 50.0);
+        touch(&mut input, 2, TouchPhase::Moved, 60.0, 50.0);
+        touch(&mut input, 1, TouchPhase::Moved, 15.0, 12.0);
+        touch(&mut input, 1, TouchPhase::Ended, 15.0, 12.0);
+
+        let left = InputBinding::MouseButton(MouseButton::Left);
+        // Only primary finger moves the "mouse".
+        assert_eq!(input.analog_value(AnalogInput::Mouse(MouseAxis::X)), 5.0);
+        assert_eq!(input.analog_value(AnalogInput::Mouse(MouseAxis::Y)), 2.0);
+        assert!(input.is_pressed(left));
+        assert_eq!(input.touches().len(), 1);
+        assert_eq!(input.touches()[0].start_position.x, 50.0);
+
+        touch(&mut input, 2, TouchPhase::Cancelled, 60.0, 50.0);
+        assert!(!input.is_pressed(left));
+        assert!(input.touches().is_empty());
+    }
+
+    #[test]
+    fn dead_zone_test() {
+        assert_eq!(apply_dead_zone(0.1, 0.2), 0.0);
+        assert_eq!(apply_dead_zone(-1.0, 0.2), -1.0);
+        assert!((apply_dead_zone(0.6, 0.2) - 0.5).abs() < 1e-5);
+        assert_eq!(apply_dead_zone(0.5, 1.0), 0.0);
+    }
+
+    #[test]
+    fn key_name_test() {
+        assert_eq!(key_from_name("Space"), Some(VirtualKeyCode::Space));
+        assert_eq!(
+            key_from_name("NumpadEnter"),
+            Some(VirtualKeyCode::NumpadEnter)
+        );
+        assert_eq!(key_from_name("NoSuchKey"), None);
+    }
+}
@@ -0,0 +1,470 @@
+//! Recording and playback of input.
+//!
+//! Replay is a sequence of changes of physical inputs of an [InputMap] on every step of
+//! [deterministic simulation](../../engine/determinism/index.html), together with parameters
+//! of the simulation. Since simulation is deterministic, feeding the same inputs on the same
+//! steps reproduces the same game session - this allows to attach replays to bug reports and
+//! to run long automated soak tests from recorded sessions.
+//!
+//! Recorder can also store checksums of the simulation, then player verifies that the
+//! simulation during playback matches the recorded one, so any source of nondeterminism is
+//! found on the exact step where it has appeared.
+//!
+//! Recorded are keys, mouse buttons, gamepad buttons, mouse motion and gamepad axes. Touches
+//! are recorded only as the mouse they emulate.
+//!
+//! # Example
+//!
+//! ```no_run
+//! # use rg3d::{engine::determinism::{Simulation, SimulationSettings}, input::InputMap};
+//! use rg3d::input::replay::{InputRecorder, Replay, ReplayPlayer};
+//!
+//! # let mut input = InputMap::new();
+//! # let mut simulation = Simulation::new(SimulationSettings::default());
+//! // Recording, on every step after events are fed into input map:
+//! let mut recorder = InputRecorder::new(simulation.settings());
+//! recorder.record(simulation.tick(), &input);
+//! // ...
+//! recorder.finish(simulation.tick()).save("bug.replay").unwrap();
+//!
+//! // Playback, simulation is created with recorded settings:
+//! let replay = Replay::load("bug.replay").unwrap();
+//! let mut simulation = Simulation::new(replay.simulation_settings());
+//! let mut player = ReplayPlayer::new(replay);
+//! while !player.is_finished(simulation.tick()) {
+//!     player.apply(simulation.tick(), &mut input);
+//!     // Run step of the game, then:
+//!     input.update();
+//!     simulation.finish_step();
+//! }
+//! ```
+
+use crate::{
+    core::visitor::{Visit, VisitResult, Visitor},
+    engine::determinism::SimulationSettings,
+    input::{GamepadAxis, InputBinding, InputMap, MouseAxis},
+};
+use std::{collections::HashMap, path::Path};
+
+/// Version of replay format.
+const VERSION: u32 = 1;
+
+/// Change of a physical input.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum ReplayEvent {
+    /// Input was pressed.
+    Press(InputBinding),
+    /// Input was released.
+    Release(InputBinding),
+    /// Mouse has moved by given value during the step.
+    MouseMotion {
+        /// Axis of the motion.
+        axis: MouseAxis,
+        /// Distance.
+        value: f32,
+    },
+    /// Gamepad axis has new value.
+    GamepadAxis {
+        /// Axis of the gamepad.
+        axis: GamepadAxis,
+        /// New value.
+        value: f32,
+    },
+}
+
+impl Default for ReplayEvent {
+    fn default() -> Self {
+        ReplayEvent::Press(Default::default())
+    }
+}
+
+impl Visit for ReplayEvent {
+    fn visit(&mut self, name: &str, visitor: &mut Visitor) -> VisitResult {
+        visitor.enter_region(name)?;
+
+        let mut kind: u32 = match self {
+            ReplayEvent::Press(_) => 0,
+            ReplayEvent::Release(_) => 1,
+            ReplayEvent::MouseMotion { .. } => 2,
+            ReplayEvent::GamepadAxis { .. } => 3,
+        };
+        kind.visit("Kind", visitor)?;
+
+        match kind {
+            0 | 1 => {
+                let mut binding = match self {
+                    ReplayEvent::Press(binding) | ReplayEvent::Release(binding) => *binding,
+                    _ => Default::default(),
+                };
+                binding.visit("Binding", visitor)?;
+                if visitor.is_reading() {
+                    *self = if kind == 0 {
+                        ReplayEvent::Press(binding)
+                    } else {
+                        ReplayEvent::Release(binding)
+                    };
+                }
+            }
+            2 | 3 => {
+                let (mut axis, mut value) = match *self {
+                    ReplayEvent::MouseMotion { axis, value } => (axis as u32, value),
+                    ReplayEvent::GamepadAxis { axis, value } => (axis as u32, value),
+                    _ => (0, 0.0),
+                };
+                axis.visit("Axis", visitor)?;
+                value.visit("Value", visitor)?;
+                if visitor.is_reading() {
+                    *self = if kind == 2 {
+                        ReplayEvent::MouseMotion {
+                            axis: MouseAxis::from_id(axis)?,
+                            value,
+                        }
+                    } else {
+                        ReplayEvent::GamepadAxis {
+                            axis: GamepadAxis::from_id(axis)?,
+                            value,
+                        }
+                    };
+                }
+            }
+            _ => return Err(format!("Invalid replay event {}", kind).into()),
+        }
+
+        visitor.leave_region()
+    }
+}
+
+/// Event with the step of simulation on which it has happened.
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct ReplayFrame {
+    /// Step of simulation.
+    pub tick: u64,
+    /// Change of input.
+    pub event: ReplayEvent,
+}
+
+impl Visit for ReplayFrame {
+    fn visit(&mut self, name: &str, visitor: &mut Visitor) -> VisitResult {
+        visitor.enter_region(name)?;
+
+        self.tick.visit("Tick", visitor)?;
+        self.event.visit("Event", visitor)?;
+
+        visitor.leave_region()
+    }
+}
+
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+struct ReplayChecksum {
+    tick: u64,
+    checksum: u64,
+}
+
+impl Visit for ReplayChecksum {
+    fn visit(&mut self, name: &str, visitor: &mut Visitor) -> VisitResult {
+        visitor.enter_region(name)?;
+
+        self.tick.visit("Tick", visitor)?;
+        self.checksum.visit("Checksum", visitor)?;
+
+        visitor.leave_region()
+    }
+}
+
+/// Recorded session. See module docs.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Replay {
+    seed: u64,
+    time_step: f32,
+    length: u64,
+    frames: Vec<ReplayFrame>,
+    checksums: Vec<ReplayChecksum>,
+}
+
+impl Visit for Replay {
+    fn visit(&mut self, name: &str, visitor: &mut Visitor) -> VisitResult {
+        visitor.enter_region(name)?;
+
+        let mut version = VERSION;
+        version.visit("Version", visitor)?;
+        if version > VERSION {
+            return Err(format!("Unsupported replay version {}", version).into());
+        }
+
+        self.seed.visit("Seed", visitor)?;
+        self.time_step.visit("TimeStep", visitor)?;
+        self.length.visit("Length", visitor)?;
+        self.frames.visit("Frames", visitor)?;
+        self.checksums.visit("Checksums", visitor)?;
+
+        visitor.leave_region()
+    }
+}
+
+impl Replay {
+    /// Returns settings for simulation that reproduces this replay. Float mode and step limit
+    /// are not recorded, they're taken from default settings.
+    pub fn simulation_settings(&self) -> SimulationSettings {
+        SimulationSettings {
+            seed: self.seed,
+            time_step: self.time_step,
+            ..Default::default()
+        }
+    }
+
+    /// Returns amount of recorded steps.
+    pub fn length(&self) -> u64 {
+        self.length
+    }
+
+    /// Returns recorded changes of input in order of steps.
+    pub fn frames(&self) -> &[ReplayFrame] {
+        &self.frames
+    }
+
+    /// Saves replay to a file.
+    pub fn save<P: AsRef<Path>>(&mut self, path: P) -> VisitResult {
+        let mut visitor = Visitor::new();
+        self.visit("Replay", &mut visitor)?;
+        visitor.save_binary(path)
+    }
+
+    /// Loads replay from a file.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, crate::core::visitor::VisitError> {
+        let mut visitor = Visitor::load_binary(path)?;
+        let mut replay = Replay::default();
+        replay.visit("Replay", &mut visitor)?;
+        Ok(replay)
+    }
+}
+
+/// Records changes of input map. See module docs.
+pub struct InputRecorder {
+    replay: Replay,
+    pressed: Vec<InputBinding>,
+    gamepad_axes: HashMap<GamepadAxis, f32>,
+    start_tick: Option<u64>,
+}
+
+impl InputRecorder {
+    /// Creates new recorder for simulation with given settings.
+    pub fn new(settings: &SimulationSettings) -> Self {
+        Self {
+            replay: Replay {
+                seed: settings.seed,
+                time_step: settings.time_step,
+                ..Default::default()
+            },
+            pressed: Default::default(),
+            gamepad_axes: Default::default(),
+            start_tick: None,
+        }
+    }
+
+    fn relative_tick(&mut self, tick: u64) -> u64 {
+        tick - *self.start_tick.get_or_insert(tick)
+    }
+
+    /// Records state of input map on given step of simulation, must be called on every step
+    /// after events are fed into input map and before game logic. First recorded step becomes
+    /// step zero of the replay.
+    pub fn record(&mut self, tick: u64, input: &InputMap) {
+        let tick = self.relative_tick(tick);
+        let frames = &mut self.replay.frames;
+
+        // Sort changes, so order of events does not depend on order of hash set.
+        let mut released = self
+            .pressed
+            .iter()
+            .filter(|binding| !input.pressed.contains(binding))
+            .cloned()
+            .collect::<Vec<_>>();
+        released.sort_by_key(|binding| binding.to_string());
+        let mut pressed = input
+            .pressed
+            .iter()
+            .filter(|binding| !self.pressed.contains(binding))
+            .cloned()
+            .collect::<Vec<_>>();
+        pressed.sort_by_key(|binding| binding.to_string());
+        for binding in released {
+            frames.push(ReplayFrame {
+                tick,
+                event: ReplayEvent::Release(binding),
+            });
+        }
+        for binding in pressed {
+            frames.push(ReplayFrame {
+                tick,
+                event: ReplayEvent::Press(binding),
+            });
+        }
+        self.pressed = input.pressed.iter().cloned().collect();
+
+        for &axis in [MouseAxis::X, MouseAxis::Y, MouseAxis::Wheel].iter() {
+            let value = input.mouse_delta[axis as usize];
+            if value != 0.0 {
+                frames.push(ReplayFrame {
+                    tick,
+                    event: ReplayEvent::MouseMotion { axis, value },
+                });
+            }
+        }
+
+        for &axis in GamepadAxis::ALL.iter() {
+            let value = input.gamepad_axes.get(&axis).cloned().unwrap_or(0.0);
+            let previous = self.gamepad_axes.get(&axis).cloned().unwrap_or(0.0);
+            if value != previous {
+                frames.push(ReplayFrame {
+                    tick,
+                    event: ReplayEvent::GamepadAxis { axis, value },
+                });
+                self.gamepad_axes.insert(axis, value);
+            }
+        }
+
+        self.replay.length = tick + 1;
+    }
+
+    /// Records checksum of simulation on given step, see
+    /// [Simulation::checksum](../../engine/determinism/struct.Simulation.html#method.checksum).
+    /// Checksums are verified during playback.
+    pub fn record_checksum(&mut self, tick: u64, checksum: u64) {
+        let tick = self.relative_tick(tick);
+        self.replay
+            .checksums
+            .push(ReplayChecksum { tick, checksum });
+    }
+
+    /// Finishes recording on given step and returns replay.
+    pub fn finish(mut self, tick: u64) -> Replay {
+        let tick = self.relative_tick(tick);
+        self.replay.length = self.replay.length.max(tick);
+        self.replay
+    }
+}
+
+/// Plays replay back into input map. See module docs.
+pub struct ReplayPlayer {
+    replay: Replay,
+    next_frame: usize,
+    start_tick: Option<u64>,
+}
+
+impl ReplayPlayer {
+    /// Creates new player, playback starts on first call of [apply](#method.apply).
+    pub fn new(replay: Replay) -> Self {
+        Self {
+            replay,
+            next_frame: 0,
+            start_tick: None,
+        }
+    }
+
+    /// Returns replay that is played.
+    pub fn replay(&self) -> &Replay {
+        &self.replay
+    }
+
+    fn relative_tick(&mut self, tick: u64) -> u64 {
+        tick - *self.start_tick.get_or_insert(tick)
+    }
+
+    /// Sets state of input map on given step of simulation, must be called on every step
+    /// instead of feeding events into input map. On first call state of input map is reset.
+    pub fn apply(&mut self, tick: u64, input: &mut InputMap) {
+        if self.start_tick.is_none() {
+            input.pressed.clear();
+            input.gamepad_axes.clear();
+            input.touches.clear();
+        }
+        let tick = self.relative_tick(tick);
+        input.mouse_delta = [0.0; 3];
+
+        while let Some(frame) = self.replay.frames.get(self.next_frame) {
+            if frame.tick > tick {
+                break;
+            }
+            match frame.event {
+                ReplayEvent::Press(binding) => input.set_pressed(binding, true),
+                ReplayEvent::Release(binding) => input.set_pressed(binding, false),
+                ReplayEvent::MouseMotion { axis, value } => {
+                    input.mouse_delta[axis as usize] = value
+                }
+                ReplayEvent::GamepadAxis { axis, value } => input.set_gamepad_axis(axis, value),
+            }
+            self.next_frame += 1;
+        }
+    }
+
+    /// Returns true if every recorded step was played.
+    pub fn is_finished(&self, tick: u64) -> bool {
+        self.start_tick.map_or(self.replay.length == 0, |start| {
+            tick - start >= self.replay.length
+        })
+    }
+
+    /// Verifies checksum of simulation on given step. Returns false if recorded checksum on
+    /// this step differs, which means that simulation has diverged from recorded one.
+    pub fn verify_checksum(&self, tick: u64, checksum: u64) -> bool {
+        let tick = match self.start_tick {
+            Some(start) => tick - start,
+            None => return true,
+        };
+        self.replay
+            .checksums
+            .iter()
+            .filter(|recorded| recorded.tick == tick)
+            .all(|recorded| recorded.checksum == checksum)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        engine::determinism::SimulationSettings,
+        event::VirtualKeyCode,
+        input::{
+            replay::{InputRecorder, ReplayPlayer},
+            GamepadAxis, InputBinding, InputMap,
+        },
+    };
+
+    #[test]
+    fn replay_test() {
+        let space = InputBinding::Key(VirtualKeyCode::Space);
+        let mut input = InputMap::new();
+        let mut recorder = InputRecorder::new(&SimulationSettings::default());
+
+        // Recording starts on step 10.
+        recorder.record(10, &input);
+        input.set_pressed(space, true);
+        input.mouse_delta[0] = 3.0;
+        recorder.record(11, &input);
+        input.update();
+        input.set_gamepad_axis(GamepadAxis::LeftStickX, 0.5);
+        recorder.record(12, &input);
+        recorder.record_checksum(12, 1234);
+        input.set_pressed(space, false);
+        recorder.record(13, &input);
+        let replay = recorder.finish(14);
+        assert_eq!(replay.length(), 4);
+
+        let mut input = InputMap::new();
+        let mut player = ReplayPlayer::new(replay);
+        player.apply(0, &mut input);
+        assert!(!input.is_pressed(space));
+        player.apply(1, &mut input);
+        assert!(input.is_pressed(space));
+        assert_eq!(input.mouse_delta[0], 3.0);
+        player.apply(2, &mut input);
+        assert_eq!(input.mouse_delta[0], 0.0);
+        assert_eq!(input.gamepad_axes[&GamepadAxis::LeftStickX], 0.5);
+        assert!(player.verify_checksum(2, 1234));
+        assert!(!player.verify_checksum(2, 4321));
+        assert!(!player.is_finished(3));
+        player.apply(3, &mut input);
+        assert!(!input.is_pressed(space));
+        assert!(player.is_finished(4));
+    }
+}
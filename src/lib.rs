@@ -3,6 +3,10 @@
 //! Features:
 //! - Scene graph with pivot, camera, mesh, light, particle system, sprite nodes.
 //! - FBX Loader - both ASCII and binary. Note: Only 7100 - 7400 versions are supported!
+//! - OBJ Loader with MTL materials.
+//! - DDS and KTX2 textures with mip chains, cube maps and block compression.
+//! - HDR textures (Radiance HDR, OpenEXR) and equirectangular to cube map conversion.
+//! - Streaming of texture mip levels by distance to camera within VRAM budget.
 //! - Advanced node-based UI with these widgets:
 //!     - Border
 //!     - Button
@@ -25,6 +29,12 @@
 //!     - Texture
 //!     - Models
 //!     - Sound buffers
+//!     - Hot reload
+//!     - Resource packs with virtual file system
+//!     - Model import options (scale, units, up axis, handedness, normals and tangents)
+//!     - Dependency tracking, usage reports and unloading of unused resources
+//!     - Derived data cache for decoded textures and converted models
+//!     - Custom resource types registered by games
 //! - Deferred shading
 //!     - Point light
 //!     - Spot light
@@ -32,6 +42,32 @@
 //! - Particle systems with soft particles.
 //! - Sounds
 //! - Physics
+//! - Versioned save files with compression and migration of old saves
+//! - Job system with work-stealing thread pool
+//! - Frame profiler with export to chrome://tracing
+//! - Statistics overlay (FPS, frame time graph, draw calls, memory)
+//! - Input mapping of keys, mouse and gamepads to rebindable actions and axes
+//! - Gamepads with rumble and navigation of UI (`gamepad` feature)
+//! - Runtime control of window mode, resolution, title, icon and cursor
+//! - Lua scripting of scene nodes (`lua` feature)
+//! - Sandboxed WebAssembly gameplay plugins (`wasm` feature)
+//! - UDP networking with reliable messages and replication of scene nodes
+//! - Keyframe curves and splines (Bezier, Catmull-Rom) with arc-length parameterization
+//! - Seeded Perlin, simplex and Worley noise with fractal octaves
+//! - Seedable random number generator for reproducible gameplay randomness
+//! - Typed publish/subscribe event bus with deferred delivery
+//! - Scheduler of delayed callbacks, repeating timers and async coroutines
+//! - Crash reports with backtrace, engine state and log tail
+//! - Settings file with command line overrides (resolution, quality, volumes, key bindings)
+//! - Transform gizmos (translate, rotate, scale) with axis/plane constraints and snapping
+//! - Hot reload of game logic from dynamic libraries with state handoff (`hot_reload` feature)
+//! - Deterministic simulation mode (fixed time step, seeded random numbers, state checksums)
+//! - Input replay recording and playback with checksum verification
+//! - Memory profiler with per-asset attribution and per-category budgets
+//! - Global time control: time scale, pause with interactive UI and frame stepping
+//! - Behavior trees for AI with blackboards, shared data-driven trees and serialization
+//! - Declarative UI layouts loaded from XML markup
+//! - Data binding of observable game state to widgets
 //!
 //! # Demos
 //!
@@ -53,9 +89,15 @@ extern crate imageproc;
 
 pub mod animation;
 pub mod engine;
+pub mod input;
+pub mod network;
+#[cfg(feature = "wasm")]
+pub mod plugin;
 pub mod renderer;
 pub mod resource;
 pub mod scene;
+#[cfg(feature = "lua")]
+pub mod scripting;
 pub mod utils;
 
 pub use glutin::*;
@@ -0,0 +1,74 @@
+//! Networking for multiplayer games.
+//!
+//! [peer](peer/index.html) module is a UDP transport, it maintains connections between peers
+//! and delivers reliable and unreliable messages. [replication](replication/index.html)
+//! module is built on top of it and synchronizes scene nodes from server to clients.
+//!
+//! Typical server loop:
+//!
+//! ```no_run
+//! use rg3d::network::{
+//!     peer::{NetworkEvent, NetworkPeer, PeerConfig},
+//!     replication::ReplicationServer,
+//! };
+//! # use rg3d::scene::Scene;
+//! # let scene = Scene::new();
+//!
+//! let mut peer = NetworkPeer::bind(
+//!     "0.0.0.0:7777",
+//!     PeerConfig {
+//!         accept_connections: true,
+//!         ..Default::default()
+//!     },
+//! )
+//! .unwrap();
+//! let mut replication = ReplicationServer::new();
+//!
+//! loop {
+//!     peer.update(1.0 / 60.0).unwrap();
+//!     while let Some(event) = peer.poll_event() {
+//!         if let NetworkEvent::Message { from, data } = event {
+//!             // Handle input of a client.
+//!         }
+//!     }
+//!     // Update scene here, then send its state to clients.
+//!     replication.update(&mut peer, &scene, 1.0 / 60.0).unwrap();
+//! }
+//! ```
+
+use std::{
+    fmt::{Display, Formatter},
+    net::SocketAddr,
+};
+
+pub mod peer;
+pub mod replication;
+
+/// Network error.
+#[derive(Debug)]
+pub enum NetworkError {
+    /// An input/output error has occurred.
+    Io(std::io::Error),
+    /// Message is larger than [MAX_MESSAGE_SIZE](peer/constant.MAX_MESSAGE_SIZE.html).
+    MessageTooLarge(usize),
+    /// There is no established connection with given address.
+    NotConnected(SocketAddr),
+}
+
+impl Display for NetworkError {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), std::fmt::Error> {
+        match self {
+            NetworkError::Io(io) => write!(f, "Io error: {}", io),
+            NetworkError::MessageTooLarge(size) => {
+                write!(f, "Message of {} bytes is too large", size)
+            }
+            NetworkError::NotConnected(addr) => write!(f, "Not connected to {}", addr),
+        }
+    }
+}
+
+impl From<std::io::Error> for NetworkError {
+    fn from(err: std::io::Error) -> Self {
+        NetworkError::Io(err)
+    }
+}
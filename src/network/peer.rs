@@ -0,0 +1,736 @@
+//! UDP transport with connections, reliable ordered and unreliable messages.
+//!
+//! Every packet carries sequence number of the packet and acknowledgements of recently
+//! received packets of remote side, so both sides know which of their packets were
+//! delivered. Reliable messages are re-sent until packet with them is acknowledged and are
+//! delivered in order they were sent. Unreliable messages are sent once and can be lost,
+//! duplicated packets are dropped. Connection sends small heartbeat packets if there is
+//! nothing to send, and connection which received nothing for a while is closed.
+
+use crate::network::NetworkError;
+use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
+use std::{
+    collections::{BTreeMap, HashMap, VecDeque},
+    io::{Cursor, ErrorKind, Read},
+    net::{SocketAddr, ToSocketAddrs, UdpSocket},
+};
+
+const PROTOCOL_ID: u32 = 0x4452_4752;
+
+/// Packets are kept small enough to not be fragmented by IP.
+const MAX_PACKET_SIZE: usize = 1200;
+
+/// Size of header of data packet: protocol, kind, sequence, ack, ack bits, message count.
+const DATA_HEADER_SIZE: usize = 4 + 1 + 4 + 4 + 4 + 2;
+
+/// Size of header of a message in data packet: flags, id of reliable message, length.
+const MESSAGE_HEADER_SIZE: usize = 1 + 4 + 2;
+
+/// Maximum size of a message in bytes. Larger data must be split by the game.
+pub const MAX_MESSAGE_SIZE: usize = MAX_PACKET_SIZE - DATA_HEADER_SIZE - MESSAGE_HEADER_SIZE;
+
+/// Reliable messages which are that far ahead of next expected one are dropped, so remote
+/// side can't make connection buffer unlimited amount of out-of-order messages.
+const RELIABLE_WINDOW: u32 = 1024;
+
+/// Amount of sent packets which are waiting for acknowledgement, older packets are
+/// considered lost.
+const MAX_SENT_PACKETS: usize = 64;
+
+const PACKET_CONNECT: u8 = 0;
+const PACKET_ACCEPT: u8 = 1;
+const PACKET_DATA: u8 = 2;
+const PACKET_DISCONNECT: u8 = 3;
+
+const FLAG_RELIABLE: u8 = 1;
+
+/// Defines how a message is delivered.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Delivery {
+    /// Message is sent once, it can be lost. Use for frequent state updates where only the
+    /// latest value matters.
+    Unreliable,
+    /// Message is re-sent until it is delivered, reliable messages are delivered in order.
+    Reliable,
+}
+
+/// Events of a peer, see [NetworkPeer::poll_event].
+#[derive(Clone, Debug, PartialEq)]
+pub enum NetworkEvent {
+    /// Connection was established, either by [NetworkPeer::connect] or by incoming request.
+    Connected(SocketAddr),
+    /// Connection was closed by remote side, timed out or could not be established.
+    Disconnected(SocketAddr),
+    /// Message was received.
+    Message {
+        /// Address of sender.
+        from: SocketAddr,
+        /// Contents of the message.
+        data: Vec<u8>,
+    },
+}
+
+/// Settings of a peer.
+#[derive(Copy, Clone, Debug)]
+pub struct PeerConfig {
+    /// Whether to accept incoming connections or not. Servers should accept connections,
+    /// clients should not.
+    pub accept_connections: bool,
+    /// Maximum amount of incoming connections.
+    pub max_connections: usize,
+    /// Time in seconds after which silent connection is closed.
+    pub timeout: f32,
+    /// Time in seconds after which a packet is sent even if there is nothing to send.
+    pub heartbeat_interval: f32,
+    /// Time in seconds after which not acknowledged reliable message is re-sent.
+    pub resend_interval: f32,
+    /// Time in seconds between connection requests.
+    pub connect_interval: f32,
+}
+
+impl Default for PeerConfig {
+    fn default() -> Self {
+        Self {
+            accept_connections: false,
+            max_connections: 32,
+            timeout: 10.0,
+            heartbeat_interval: 0.1,
+            resend_interval: 0.2,
+            connect_interval: 0.5,
+        }
+    }
+}
+
+struct SentPacket {
+    sequence: u32,
+    reliable_ids: Vec<u32>,
+}
+
+struct PendingMessage {
+    id: u32,
+    data: Vec<u8>,
+    /// None if message was never sent.
+    time_since_send: Option<f32>,
+}
+
+struct DataPacket {
+    sequence: u32,
+    ack: u32,
+    ack_bits: u32,
+    /// Messages with ids of reliable messages.
+    messages: Vec<(Option<u32>, Vec<u8>)>,
+}
+
+impl DataPacket {
+    fn write(&self) -> Vec<u8> {
+        let mut data = Vec::with_capacity(MAX_PACKET_SIZE);
+        data.write_u32::<LittleEndian>(PROTOCOL_ID).unwrap();
+        data.write_u8(PACKET_DATA).unwrap();
+        data.write_u32::<LittleEndian>(self.sequence).unwrap();
+        data.write_u32::<LittleEndian>(self.ack).unwrap();
+        data.write_u32::<LittleEndian>(self.ack_bits).unwrap();
+        data.write_u16::<LittleEndian>(self.messages.len() as u16)
+            .unwrap();
+        for (id, message) in self.messages.iter() {
+            match id {
+                Some(id) => {
+                    data.write_u8(FLAG_RELIABLE).unwrap();
+                    data.write_u32::<LittleEndian>(*id).unwrap();
+                }
+                None => data.write_u8(0).unwrap(),
+            }
+            data.write_u16::<LittleEndian>(message.len() as u16)
+                .unwrap();
+            data.extend_from_slice(message);
+        }
+        data
+    }
+
+    /// Reads packet after protocol id and kind.
+    fn read(reader: &mut Cursor<&[u8]>) -> std::io::Result<Self> {
+        let sequence = reader.read_u32::<LittleEndian>()?;
+        let ack = reader.read_u32::<LittleEndian>()?;
+        let ack_bits = reader.read_u32::<LittleEndian>()?;
+        let count = reader.read_u16::<LittleEndian>()?;
+        let mut messages = Vec::with_capacity(count as usize);
+        for _ in 0..count {
+            let flags = reader.read_u8()?;
+            let id = if flags & FLAG_RELIABLE != 0 {
+                Some(reader.read_u32::<LittleEndian>()?)
+            } else {
+                None
+            };
+            let len = reader.read_u16::<LittleEndian>()?;
+            let mut message = vec![0; len as usize];
+            reader.read_exact(&mut message)?;
+            messages.push((id, message));
+        }
+        Ok(Self {
+            sequence,
+            ack,
+            ack_bits,
+            messages,
+        })
+    }
+}
+
+fn control_packet(kind: u8) -> Vec<u8> {
+    let mut data = Vec::with_capacity(5);
+    data.write_u32::<LittleEndian>(PROTOCOL_ID).unwrap();
+    data.write_u8(kind).unwrap();
+    data
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+enum ConnectionState {
+    Connecting { time_since_request: f32 },
+    Connected,
+}
+
+/// Reliability layer of a connection, it does not know anything about sockets.
+struct Connection {
+    state: ConnectionState,
+    time_since_receive: f32,
+    time_since_send: f32,
+    /// Sequence of next sent packet, sequences start from 1, so zero ack means "nothing".
+    local_sequence: u32,
+    /// Newest received sequence.
+    remote_sequence: u32,
+    /// Bit N is set if packet `remote_sequence - 1 - N` was received.
+    received_bits: u32,
+    /// Something was received since last sent packet, so remote side waits for ack.
+    ack_pending: bool,
+    sent_packets: VecDeque<SentPacket>,
+    reliable_out: Vec<PendingMessage>,
+    next_reliable_id: u32,
+    unreliable_out: Vec<Vec<u8>>,
+    next_expected_reliable: u32,
+    /// Reliable messages that came out of order.
+    reliable_in: BTreeMap<u32, Vec<u8>>,
+}
+
+impl Connection {
+    fn new(state: ConnectionState) -> Self {
+        Self {
+            state,
+            time_since_receive: 0.0,
+            time_since_send: 0.0,
+            local_sequence: 1,
+            remote_sequence: 0,
+            received_bits: 0,
+            ack_pending: false,
+            sent_packets: Default::default(),
+            reliable_out: Default::default(),
+            next_reliable_id: 0,
+            unreliable_out: Default::default(),
+            next_expected_reliable: 0,
+            reliable_in: Default::default(),
+        }
+    }
+
+    fn queue(&mut self, data: &[u8], delivery: Delivery) {
+        match delivery {
+            Delivery::Unreliable => self.unreliable_out.push(data.to_vec()),
+            Delivery::Reliable => {
+                self.reliable_out.push(PendingMessage {
+                    id: self.next_reliable_id,
+                    data: data.to_vec(),
+                    time_since_send: None,
+                });
+                self.next_reliable_id += 1;
+            }
+        }
+    }
+
+    /// Registers received packet sequence, returns false if packet is a duplicate or too old.
+    fn register_received(&mut self, sequence: u32) -> bool {
+        if sequence > self.remote_sequence {
+            let shift = sequence - self.remote_sequence;
+            self.received_bits = match shift {
+                1..=31 => (self.received_bits << shift) | (1 << (shift - 1)),
+                32 => 1 << 31,
+                _ => 0,
+            };
+            self.remote_sequence = sequence;
+            true
+        } else {
+            let distance = self.remote_sequence - sequence;
+            if distance == 0 || distance > 32 {
+                false
+            } else {
+                let bit = 1 << (distance - 1);
+                let is_new = self.received_bits & bit == 0;
+                self.received_bits |= bit;
+                is_new
+            }
+        }
+    }
+
+    /// Removes acknowledged packets and reliable messages that were delivered with them.
+    fn process_ack(&mut self, ack: u32, ack_bits: u32) {
+        let is_acked = |sequence: u32| {
+            sequence == ack
+                || (sequence < ack && ack - sequence <= 32 && {
+                    ack_bits & (1 << (ack - sequence - 1)) != 0
+                })
+        };
+        let mut delivered = Vec::new();
+        self.sent_packets.retain(|packet| {
+            if is_acked(packet.sequence) {
+                delivered.extend_from_slice(&packet.reliable_ids);
+                false
+            } else {
+                true
+            }
+        });
+        if !delivered.is_empty() {
+            self.reliable_out
+                .retain(|message| !delivered.contains(&message.id));
+        }
+    }
+
+    /// Processes received packet, returns messages ready for delivery.
+    fn receive(&mut self, packet: DataPacket) -> Vec<Vec<u8>> {
+        self.time_since_receive = 0.0;
+        self.process_ack(packet.ack, packet.ack_bits);
+        if !self.register_received(packet.sequence) {
+            return Vec::new();
+        }
+        self.ack_pending = true;
+
+        let mut delivered = Vec::new();
+        for (id, message) in packet.messages {
+            match id {
+                None => delivered.push(message),
+                Some(id) => {
+                    if id >= self.next_expected_reliable
+                        && id - self.next_expected_reliable < RELIABLE_WINDOW
+                    {
+                        self.reliable_in.insert(id, message);
+                    }
+                }
+            }
+        }
+        while let Some(message) = self.reliable_in.remove(&self.next_expected_reliable) {
+            delivered.push(message);
+            self.next_expected_reliable += 1;
+        }
+        delivered
+    }
+
+    /// Collects messages that must be sent now and packs them into packets.
+    fn collect_packets(&mut self, dt: f32, config: &PeerConfig) -> Vec<Vec<u8>> {
+        self.time_since_send += dt;
+
+        let mut messages = Vec::new();
+        for message in self.reliable_out.iter_mut() {
+            let resend = match message.time_since_send.as_mut() {
+                Some(time) => {
+                    *time += dt;
+                    *time >= config.resend_interval
+                }
+                None => true,
+            };
+            if resend {
+                message.time_since_send = Some(0.0);
+                messages.push((Some(message.id), message.data.clone()));
+            }
+        }
+        messages.extend(self.unreliable_out.drain(..).map(|data| (None, data)));
+
+        if messages.is_empty()
+            && !self.ack_pending
+            && self.time_since_send < config.heartbeat_interval
+        {
+            return Vec::new();
+        }
+
+        let mut packets = Vec::new();
+        let mut messages = messages.into_iter().peekable();
+        loop {
+            let mut packet = DataPacket {
+                sequence: self.local_sequence,
+                ack: self.remote_sequence,
+                ack_bits: self.received_bits,
+                messages: Vec::new(),
+            };
+            let mut size = DATA_HEADER_SIZE;
+            while let Some((_, data)) = messages.peek() {
+                let message_size = MESSAGE_HEADER_SIZE + data.len();
+                if size + message_size > MAX_PACKET_SIZE {
+                    break;
+                }
+                size += message_size;
+                packet.messages.push(messages.next().unwrap());
+            }
+
+            self.local_sequence += 1;
+            self.sent_packets.push_back(SentPacket {
+                sequence: packet.sequence,
+                reliable_ids: packet.messages.iter().filter_map(|(id, _)| *id).collect(),
+            });
+            if self.sent_packets.len() > MAX_SENT_PACKETS {
+                self.sent_packets.pop_front();
+            }
+            packets.push(packet.write());
+
+            if messages.peek().is_none() {
+                break;
+            }
+        }
+
+        self.ack_pending = false;
+        self.time_since_send = 0.0;
+        packets
+    }
+}
+
+/// Network endpoint, it can be a server that accepts connections, or a client that
+/// connects to a server, or both. See module docs.
+pub struct NetworkPeer {
+    socket: UdpSocket,
+    config: PeerConfig,
+    connections: HashMap<SocketAddr, Connection>,
+    events: VecDeque<NetworkEvent>,
+    buffer: Vec<u8>,
+}
+
+impl NetworkPeer {
+    /// Creates new peer bound to given address. Servers should use well-known port, clients
+    /// can use port 0, so OS will pick free port.
+    pub fn bind<A: ToSocketAddrs>(addr: A, config: PeerConfig) -> Result<Self, NetworkError> {
+        let socket = UdpSocket::bind(addr)?;
+        socket.set_nonblocking(true)?;
+        Ok(Self {
+            socket,
+            config,
+            connections: Default::default(),
+            events: Default::default(),
+            buffer: vec![0; MAX_PACKET_SIZE],
+        })
+    }
+
+    /// Returns address to which the peer is bound.
+    pub fn local_addr(&self) -> Result<SocketAddr, NetworkError> {
+        Ok(self.socket.local_addr()?)
+    }
+
+    /// Starts connecting to a remote peer. [NetworkEvent::Connected] is emitted when
+    /// connection is established, or [NetworkEvent::Disconnected] if remote peer does not
+    /// respond within timeout.
+    pub fn connect(&mut self, addr: SocketAddr) {
+        self.connections.entry(addr).or_insert_with(|| {
+            Connection::new(ConnectionState::Connecting {
+                // Request will be sent on next update.
+                time_since_request: f32::MAX,
+            })
+        });
+    }
+
+    /// Closes connection and notifies remote side.
+    pub fn disconnect(&mut self, addr: SocketAddr) {
+        if self.connections.remove(&addr).is_some() {
+            let _ = self
+                .socket
+                .send_to(&control_packet(PACKET_DISCONNECT), addr);
+        }
+    }
+
+    /// Returns true if connection with given address is established.
+    pub fn is_connected(&self, addr: SocketAddr) -> bool {
+        self.connections
+            .get(&addr)
+            .map_or(false, |c| c.state == ConnectionState::Connected)
+    }
+
+    /// Returns addresses of every established connection.
+    pub fn connections(&self) -> impl Iterator<Item = SocketAddr> + '_ {
+        self.connections
+            .iter()
+            .filter(|(_, c)| c.state == ConnectionState::Connected)
+            .map(|(addr, _)| *addr)
+    }
+
+    /// Queues message for sending, it will be sent on next update.
+    pub fn send(
+        &mut self,
+        to: SocketAddr,
+        data: &[u8],
+        delivery: Delivery,
+    ) -> Result<(), NetworkError> {
+        if data.len() > MAX_MESSAGE_SIZE {
+            return Err(NetworkError::MessageTooLarge(data.len()));
+        }
+        match self.connections.get_mut(&to) {
+            Some(connection) if connection.state == ConnectionState::Connected => {
+                connection.queue(data, delivery);
+                Ok(())
+            }
+            _ => Err(NetworkError::NotConnected(to)),
+        }
+    }
+
+    /// Queues message for every established connection.
+    pub fn broadcast(&mut self, data: &[u8], delivery: Delivery) -> Result<(), NetworkError> {
+        if data.len() > MAX_MESSAGE_SIZE {
+            return Err(NetworkError::MessageTooLarge(data.len()));
+        }
+        for connection in self.connections.values_mut() {
+            if connection.state == ConnectionState::Connected {
+                connection.queue(data, delivery);
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns next event, if any. Should be called until it returns None after every
+    /// update.
+    pub fn poll_event(&mut self) -> Option<NetworkEvent> {
+        self.events.pop_front()
+    }
+
+    /// Receives incoming packets, closes timed out connections and sends queued messages.
+    /// Must be called every frame.
+    pub fn update(&mut self, dt: f32) -> Result<(), NetworkError> {
+        loop {
+            match self.socket.recv_from(&mut self.buffer) {
+                Ok((size, addr)) => {
+                    let packet = self.buffer[..size].to_vec();
+                    self.handle_packet(addr, &packet);
+                }
+                Err(e) if e.kind() == ErrorKind::WouldBlock => break,
+                // Windows reports unreachable remote side of previous send this way.
+                Err(e) if e.kind() == ErrorKind::ConnectionReset => continue,
+                Err(e) => return Err(e.into()),
+            }
+        }
+
+        let mut closed = Vec::new();
+        let mut outgoing = Vec::new();
+        for (addr, connection) in self.connections.iter_mut() {
+            connection.time_since_receive += dt;
+            if connection.time_since_receive > self.config.timeout {
+                closed.push(*addr);
+                continue;
+            }
+            match &mut connection.state {
+                ConnectionState::Connecting { time_since_request } => {
+                    *time_since_request += dt;
+                    if *time_since_request >= self.config.connect_interval {
+                        *time_since_request = 0.0;
+                        outgoing.push((*addr, control_packet(PACKET_CONNECT)));
+                    }
+                }
+                ConnectionState::Connected => {
+                    for packet in connection.collect_packets(dt, &self.config) {
+                        outgoing.push((*addr, packet));
+                    }
+                }
+            }
+        }
+
+        for addr in closed {
+            self.connections.remove(&addr);
+            self.events.push_back(NetworkEvent::Disconnected(addr));
+        }
+
+        for (addr, packet) in outgoing {
+            match self.socket.send_to(&packet, addr) {
+                Ok(_) => (),
+                // Packet is lost, reliability layer will handle this.
+                Err(e) if e.kind() == ErrorKind::WouldBlock => (),
+                Err(e) => return Err(e.into()),
+            }
+        }
+
+        Ok(())
+    }
+
+    fn handle_packet(&mut self, addr: SocketAddr, packet: &[u8]) {
+        let mut reader = Cursor::new(packet);
+        match reader.read_u32::<LittleEndian>() {
+            Ok(PROTOCOL_ID) => (),
+            // Not our packet.
+            _ => return,
+        }
+        let kind = match reader.read_u8() {
+            Ok(kind) => kind,
+            Err(_) => return,
+        };
+
+        match kind {
+            PACKET_CONNECT => {
+                if let Some(connection) = self.connections.get_mut(&addr) {
+                    // Our accept was lost, repeat it.
+                    connection.time_since_receive = 0.0;
+                    let _ = self.socket.send_to(&control_packet(PACKET_ACCEPT), addr);
+                } else if self.config.accept_connections
+                    && self.connections.len() < self.config.max_connections
+                {
+                    self.connections
+                        .insert(addr, Connection::new(ConnectionState::Connected));
+                    self.events.push_back(NetworkEvent::Connected(addr));
+                    let _ = self.socket.send_to(&control_packet(PACKET_ACCEPT), addr);
+                }
+            }
+            PACKET_ACCEPT => {
+                if let Some(connection) = self.connections.get_mut(&addr) {
+                    connection.time_since_receive = 0.0;
+                    if connection.state != ConnectionState::Connected {
+                        connection.state = ConnectionState::Connected;
+                        self.events.push_back(NetworkEvent::Connected(addr));
+                    }
+                }
+            }
+            PACKET_DATA => {
+                let packet = match DataPacket::read(&mut reader) {
+                    Ok(packet) => packet,
+                    Err(_) => return,
+                };
+                if let Some(connection) = self.connections.get_mut(&addr) {
+                    if connection.state != ConnectionState::Connected {
+                        // Accept was lost, but data means that remote side accepted us.
+                        connection.state = ConnectionState::Connected;
+                        self.events.push_back(NetworkEvent::Connected(addr));
+                    }
+                    for data in connection.receive(packet) {
+                        self.events
+                            .push_back(NetworkEvent::Message { from: addr, data });
+                    }
+                }
+            }
+            PACKET_DISCONNECT => {
+                if self.connections.remove(&addr).is_some() {
+                    self.events.push_back(NetworkEvent::Disconnected(addr));
+                }
+            }
+            _ => (),
+        }
+    }
+}
+
+impl Drop for NetworkPeer {
+    fn drop(&mut self) {
+        let addrs = self.connections.keys().cloned().collect::<Vec<_>>();
+        for addr in addrs {
+            self.disconnect(addr);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::network::peer::{
+        Connection, ConnectionState, DataPacket, Delivery, NetworkEvent, NetworkPeer, PeerConfig,
+    };
+    use std::io::Cursor;
+
+    fn transfer(from: &mut Connection, to: &mut Connection, lose: bool) -> Vec<Vec<u8>> {
+        let mut received = Vec::new();
+        for packet in from.collect_packets(1.0, &PeerConfig::default()) {
+            if !lose {
+                let mut reader = Cursor::new(&packet[5..]);
+                received.extend(to.receive(DataPacket::read(&mut reader).unwrap()));
+            }
+        }
+        received
+    }
+
+    #[test]
+    fn reliability_test() {
+        let mut a = Connection::new(ConnectionState::Connected);
+        let mut b = Connection::new(ConnectionState::Connected);
+
+        a.queue(b"first", Delivery::Reliable);
+        a.queue(b"lost", Delivery::Unreliable);
+        // Packet is lost, unreliable message is lost with it.
+        assert!(transfer(&mut a, &mut b, true).is_empty());
+
+        a.queue(b"second", Delivery::Reliable);
+        // Resend interval has passed, so first message is re-sent with second one.
+        assert_eq!(
+            transfer(&mut a, &mut b, false),
+            vec![b"first".to_vec(), b"second".to_vec()]
+        );
+
+        // Acknowledgement reaches sender, nothing to re-send anymore.
+        transfer(&mut b, &mut a, false);
+        assert!(a.reliable_out.is_empty());
+    }
+
+    #[test]
+    fn reliable_window_test() {
+        let mut connection = Connection::new(ConnectionState::Connected);
+        let packet = |sequence, id| DataPacket {
+            sequence,
+            ack: 0,
+            ack_bits: 0,
+            messages: vec![(Some(id), b"data".to_vec())],
+        };
+        connection.receive(packet(1, 5));
+        // Message that is too far ahead is dropped instead of being buffered.
+        connection.receive(packet(2, 1_000_000));
+        assert_eq!(connection.reliable_in.len(), 1);
+    }
+
+    #[test]
+    fn received_bits_test() {
+        let mut connection = Connection::new(ConnectionState::Connected);
+        assert!(connection.register_received(1));
+        assert!(connection.register_received(3));
+        assert!(!connection.register_received(3));
+        assert!(connection.register_received(2));
+        assert!(!connection.register_received(1));
+        assert_eq!(connection.remote_sequence, 3);
+        assert_eq!(connection.received_bits, 0b11);
+        assert!(connection.register_received(40));
+        assert!(!connection.register_received(3));
+    }
+
+    #[test]
+    fn loopback_test() {
+        let mut server = NetworkPeer::bind(
+            "127.0.0.1:0",
+            PeerConfig {
+                accept_connections: true,
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        let mut client = NetworkPeer::bind("127.0.0.1:0", Default::default()).unwrap();
+        let server_addr = server.local_addr().unwrap();
+        let client_addr = client.local_addr().unwrap();
+
+        client.connect(server_addr);
+        let mut events = Vec::new();
+        let mut sent = false;
+        for _ in 0..200 {
+            client.update(0.01).unwrap();
+            server.update(0.01).unwrap();
+            if !sent && client.is_connected(server_addr) {
+                client
+                    .send(server_addr, b"hello", Delivery::Reliable)
+                    .unwrap();
+                sent = true;
+            }
+            while let Some(event) = server.poll_event() {
+                events.push(event);
+            }
+            if events.len() == 2 {
+                break;
+            }
+            std::thread::sleep(std::time::Duration::from_millis(5));
+        }
+
+        assert_eq!(
+            events,
+            vec![
+                NetworkEvent::Connected(client_addr),
+                NetworkEvent::Message {
+                    from: client_addr,
+                    data: b"hello".to_vec()
+                }
+            ]
+        );
+    }
+}
@@ -0,0 +1,735 @@
+//! Replication of scene nodes from server to clients.
+//!
+//! Server registers nodes which must be replicated, every registered node gets unique
+//! [NetworkId] and a kind - arbitrary string which tells clients what to create for the node,
+//! for example path to a model. Clients are notified about new and removed entities by
+//! reliable messages, and server sends snapshots of local transforms of entities by
+//! unreliable messages, so only latest state matters. Games can attach small blobs of data
+//! (components) to entities, they're sent with snapshots as well.
+//!
+//! Clients render entities slightly in the past and interpolate between received snapshots,
+//! this hides jitter of network. Entities controlled by the client itself (player character)
+//! can be marked as predicted - snapshots are not applied to them, instead the game gets
+//! [Correction] with authoritative state and last input processed by server, so it can
+//! reconcile its prediction by re-applying inputs that were not processed yet.
+//!
+//! Every message that starts with [REPLICATION_MESSAGE] byte is reserved for replication, so
+//! games should not start their own messages with this byte.
+
+use crate::{
+    core::{
+        math::{quat::Quat, vec3::Vec3},
+        pool::Handle,
+    },
+    network::{
+        peer::{Delivery, NetworkPeer, MAX_MESSAGE_SIZE},
+        NetworkError,
+    },
+    scene::{node::Node, Scene},
+};
+use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
+use std::{
+    collections::{BTreeMap, HashMap, HashSet, VecDeque},
+    io::{Cursor, Read},
+    net::SocketAddr,
+};
+
+/// First byte of every replication message.
+pub const REPLICATION_MESSAGE: u8 = 0xFF;
+
+const MESSAGE_SPAWN: u8 = 0;
+const MESSAGE_DESPAWN: u8 = 1;
+const MESSAGE_SNAPSHOT: u8 = 2;
+
+/// Size of snapshot header: tag, kind, time, last input, entity count.
+const SNAPSHOT_HEADER_SIZE: usize = 1 + 1 + 8 + 4 + 2;
+
+/// Size of entity in snapshot without components: id, state, component count.
+const ENTITY_HEADER_SIZE: usize = 4 + 10 * 4 + 1;
+
+/// Size of component in snapshot: id, length and data.
+fn component_size(data: &[u8]) -> usize {
+    2 + 2 + data.len()
+}
+
+/// Maximum amount of snapshots of an entity kept by a client.
+const MAX_SNAPSHOTS: usize = 32;
+
+/// Unique identifier of a replicated entity, same on server and clients.
+pub type NetworkId = u32;
+
+/// State of an entity which is sent with snapshots.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct EntityState {
+    /// Local position of a node.
+    pub position: Vec3,
+    /// Local rotation of a node.
+    pub rotation: Quat,
+    /// Local scale of a node.
+    pub scale: Vec3,
+}
+
+impl EntityState {
+    /// Takes state from local transform of a node.
+    pub fn from_node(node: &Node) -> Self {
+        let transform = node.local_transform();
+        Self {
+            position: transform.position(),
+            rotation: transform.rotation(),
+            scale: transform.scale(),
+        }
+    }
+
+    /// Sets local transform of a node.
+    pub fn apply(&self, node: &mut Node) {
+        node.local_transform_mut()
+            .set_position(self.position)
+            .set_rotation(self.rotation)
+            .set_scale(self.scale);
+    }
+
+    /// Returns state between this one and other, `t` must be in [0; 1] range.
+    pub fn interpolate(&self, other: &Self, t: f32) -> Self {
+        Self {
+            position: self.position.lerp(&other.position, t),
+            rotation: self.rotation.slerp(&other.rotation, t),
+            scale: self.scale.lerp(&other.scale, t),
+        }
+    }
+
+    fn write(&self, data: &mut Vec<u8>) {
+        for v in [self.position, self.scale].iter() {
+            data.write_f32::<LittleEndian>(v.x).unwrap();
+            data.write_f32::<LittleEndian>(v.y).unwrap();
+            data.write_f32::<LittleEndian>(v.z).unwrap();
+        }
+        data.write_f32::<LittleEndian>(self.rotation.x).unwrap();
+        data.write_f32::<LittleEndian>(self.rotation.y).unwrap();
+        data.write_f32::<LittleEndian>(self.rotation.z).unwrap();
+        data.write_f32::<LittleEndian>(self.rotation.w).unwrap();
+    }
+
+    fn read(reader: &mut Cursor<&[u8]>) -> std::io::Result<Self> {
+        let mut values = [0.0; 10];
+        for value in values.iter_mut() {
+            *value = reader.read_f32::<LittleEndian>()?;
+        }
+        Ok(Self {
+            position: Vec3::new(values[0], values[1], values[2]),
+            scale: Vec3::new(values[3], values[4], values[5]),
+            rotation: Quat::new(values[6], values[7], values[8], values[9]),
+        })
+    }
+}
+
+struct ServerEntity {
+    node: Handle<Node>,
+    kind: String,
+    components: BTreeMap<u16, Vec<u8>>,
+}
+
+fn spawn_message(id: NetworkId, kind: &str) -> Vec<u8> {
+    let mut data = vec![REPLICATION_MESSAGE, MESSAGE_SPAWN];
+    data.write_u32::<LittleEndian>(id).unwrap();
+    data.write_u16::<LittleEndian>(kind.len() as u16).unwrap();
+    data.extend_from_slice(kind.as_bytes());
+    data
+}
+
+fn despawn_message(id: NetworkId) -> Vec<u8> {
+    let mut data = vec![REPLICATION_MESSAGE, MESSAGE_DESPAWN];
+    data.write_u32::<LittleEndian>(id).unwrap();
+    data
+}
+
+fn snapshot_header(time: f64, last_input: u32) -> Vec<u8> {
+    let mut data = vec![REPLICATION_MESSAGE, MESSAGE_SNAPSHOT];
+    data.write_f64::<LittleEndian>(time).unwrap();
+    data.write_u32::<LittleEndian>(last_input).unwrap();
+    // Amount of entities, written when message is complete.
+    data.write_u16::<LittleEndian>(0).unwrap();
+    data
+}
+
+fn set_snapshot_entity_count(data: &mut [u8], count: u16) {
+    let offset = SNAPSHOT_HEADER_SIZE - 2;
+    data[offset..SNAPSHOT_HEADER_SIZE].copy_from_slice(&count.to_le_bytes());
+}
+
+/// Writes snapshot of given entities, snapshot is split into few messages if it does not fit
+/// into one.
+fn write_snapshot<'a, I>(time: f64, last_input: u32, entities: I) -> Vec<Vec<u8>>
+where
+    I: Iterator<Item = (NetworkId, EntityState, &'a BTreeMap<u16, Vec<u8>>)>,
+{
+    let mut messages = Vec::new();
+    let mut current = snapshot_header(time, last_input);
+    let mut count = 0u16;
+    for (id, state, components) in entities {
+        let mut entity = Vec::new();
+        entity.write_u32::<LittleEndian>(id).unwrap();
+        state.write(&mut entity);
+        entity.write_u8(components.len() as u8).unwrap();
+        for (component, data) in components.iter() {
+            entity.write_u16::<LittleEndian>(*component).unwrap();
+            entity.write_u16::<LittleEndian>(data.len() as u16).unwrap();
+            entity.extend_from_slice(data);
+        }
+
+        if count > 0 && current.len() + entity.len() > MAX_MESSAGE_SIZE {
+            set_snapshot_entity_count(&mut current, count);
+            messages.push(std::mem::replace(
+                &mut current,
+                snapshot_header(time, last_input),
+            ));
+            count = 0;
+        }
+        current.extend_from_slice(&entity);
+        count += 1;
+    }
+    set_snapshot_entity_count(&mut current, count);
+    messages.push(current);
+    messages
+}
+
+/// Server side of replication. See module docs.
+#[derive(Default)]
+pub struct ReplicationServer {
+    entities: BTreeMap<NetworkId, ServerEntity>,
+    next_id: NetworkId,
+    /// Clients which were told about every entity.
+    clients: HashSet<SocketAddr>,
+    last_inputs: HashMap<SocketAddr, u32>,
+    time: f64,
+}
+
+impl ReplicationServer {
+    /// Creates new replication server without entities.
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Registers node for replication, `kind` is passed to clients so they know what to
+    /// create for the node. Returns identifier of new entity.
+    pub fn register(
+        &mut self,
+        peer: &mut NetworkPeer,
+        node: Handle<Node>,
+        kind: &str,
+    ) -> Result<NetworkId, NetworkError> {
+        let id = self.next_id;
+        self.next_id += 1;
+        for &client in self.clients.iter() {
+            peer.send(client, &spawn_message(id, kind), Delivery::Reliable)?;
+        }
+        self.entities.insert(
+            id,
+            ServerEntity {
+                node,
+                kind: kind.to_owned(),
+                components: Default::default(),
+            },
+        );
+        Ok(id)
+    }
+
+    /// Stops replication of an entity, clients will remove their copies of the entity.
+    pub fn unregister(
+        &mut self,
+        peer: &mut NetworkPeer,
+        id: NetworkId,
+    ) -> Result<(), NetworkError> {
+        if self.entities.remove(&id).is_some() {
+            for &client in self.clients.iter() {
+                peer.send(client, &despawn_message(id), Delivery::Reliable)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns identifier of an entity of given node, if node is registered.
+    pub fn network_id(&self, node: Handle<Node>) -> Option<NetworkId> {
+        self.entities
+            .iter()
+            .find(|(_, entity)| entity.node == node)
+            .map(|(id, _)| *id)
+    }
+
+    /// Sets component of an entity, it will be sent with every next snapshot. Components
+    /// should be small, entity with its components must fit into one message, otherwise
+    /// [NetworkError::MessageTooLarge] is returned and component is not set.
+    pub fn set_component(
+        &mut self,
+        id: NetworkId,
+        component: u16,
+        data: Vec<u8>,
+    ) -> Result<(), NetworkError> {
+        if let Some(entity) = self.entities.get_mut(&id) {
+            let size = SNAPSHOT_HEADER_SIZE
+                + ENTITY_HEADER_SIZE
+                + entity
+                    .components
+                    .iter()
+                    .filter(|(other, _)| **other != component)
+                    .map(|(_, data)| component_size(data))
+                    .sum::<usize>()
+                + component_size(&data);
+            if size > MAX_MESSAGE_SIZE {
+                return Err(NetworkError::MessageTooLarge(size));
+            }
+            entity.components.insert(component, data);
+        }
+        Ok(())
+    }
+
+    /// Removes component of an entity.
+    pub fn remove_component(&mut self, id: NetworkId, component: u16) {
+        if let Some(entity) = self.entities.get_mut(&id) {
+            entity.components.remove(&component);
+        }
+    }
+
+    /// Sets sequence number of last input of a client which was applied by server, it is sent
+    /// to the client with snapshots and used for reconciliation of prediction.
+    pub fn set_last_input(&mut self, client: SocketAddr, input: u32) {
+        self.last_inputs.insert(client, input);
+    }
+
+    /// Tells new clients about every entity and sends snapshot of entities to every client.
+    /// Entities of removed nodes are unregistered automatically. Game decides how often to
+    /// send snapshots, usually 10-30 times per second is enough.
+    pub fn update(
+        &mut self,
+        peer: &mut NetworkPeer,
+        scene: &Scene,
+        dt: f32,
+    ) -> Result<(), NetworkError> {
+        self.time += dt as f64;
+
+        let removed = self
+            .entities
+            .iter()
+            .filter(|(_, entity)| !scene.graph.is_valid_handle(entity.node))
+            .map(|(id, _)| *id)
+            .collect::<Vec<_>>();
+        for id in removed {
+            self.unregister(peer, id)?;
+        }
+
+        let connections = peer.connections().collect::<HashSet<_>>();
+        self.clients.retain(|client| connections.contains(client));
+        self.last_inputs
+            .retain(|client, _| connections.contains(client));
+        for &client in connections.iter() {
+            if self.clients.insert(client) {
+                for (id, entity) in self.entities.iter() {
+                    peer.send(
+                        client,
+                        &spawn_message(*id, &entity.kind),
+                        Delivery::Reliable,
+                    )?;
+                }
+            }
+        }
+
+        for &client in self.clients.iter() {
+            let last_input = self.last_inputs.get(&client).cloned().unwrap_or_default();
+            let entities = self.entities.iter().map(|(id, entity)| {
+                (
+                    *id,
+                    EntityState::from_node(&scene.graph[entity.node]),
+                    &entity.components,
+                )
+            });
+            for message in write_snapshot(self.time, last_input, entities) {
+                peer.send(client, &message, Delivery::Unreliable)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Authoritative state of a predicted entity, see [ReplicationClient::poll_correction].
+#[derive(Clone, Debug, PartialEq)]
+pub struct Correction {
+    /// Identifier of an entity.
+    pub id: NetworkId,
+    /// Node of the entity on client.
+    pub node: Handle<Node>,
+    /// State of the entity on server.
+    pub state: EntityState,
+    /// Last input of the client that was applied by server before the state was captured.
+    pub last_input: u32,
+}
+
+struct ClientEntity {
+    node: Handle<Node>,
+    snapshots: VecDeque<(f64, EntityState)>,
+    components: HashMap<u16, Vec<u8>>,
+}
+
+impl ClientEntity {
+    /// Returns interpolated state at given time.
+    fn state_at(&mut self, time: f64) -> Option<EntityState> {
+        // Keep one snapshot before given time, older ones are not needed anymore.
+        while self.snapshots.len() > 2 && self.snapshots[1].0 <= time {
+            self.snapshots.pop_front();
+        }
+        let (first_time, first) = *self.snapshots.front()?;
+        match self.snapshots.get(1) {
+            Some(&(second_time, second)) if time > first_time => {
+                let t = ((time - first_time) / (second_time - first_time)).min(1.0);
+                Some(first.interpolate(&second, t as f32))
+            }
+            _ => Some(first),
+        }
+    }
+}
+
+enum ClientMessage {
+    Spawn {
+        id: NetworkId,
+        kind: String,
+    },
+    Despawn {
+        id: NetworkId,
+    },
+    Snapshot {
+        time: f64,
+        last_input: u32,
+        entities: Vec<(NetworkId, EntityState, Vec<(u16, Vec<u8>)>)>,
+    },
+}
+
+impl ClientMessage {
+    fn read(data: &[u8]) -> std::io::Result<Self> {
+        let mut reader = Cursor::new(data);
+        // Tag is checked by caller.
+        reader.read_u8()?;
+        match reader.read_u8()? {
+            MESSAGE_SPAWN => {
+                let id = reader.read_u32::<LittleEndian>()?;
+                let len = reader.read_u16::<LittleEndian>()?;
+                let mut kind = vec![0; len as usize];
+                reader.read_exact(&mut kind)?;
+                Ok(ClientMessage::Spawn {
+                    id,
+                    kind: String::from_utf8_lossy(&kind).into_owned(),
+                })
+            }
+            MESSAGE_DESPAWN => Ok(ClientMessage::Despawn {
+                id: reader.read_u32::<LittleEndian>()?,
+            }),
+            MESSAGE_SNAPSHOT => {
+                let time = reader.read_f64::<LittleEndian>()?;
+                let last_input = reader.read_u32::<LittleEndian>()?;
+                let count = reader.read_u16::<LittleEndian>()?;
+                let mut entities = Vec::with_capacity(count as usize);
+                for _ in 0..count {
+                    let id = reader.read_u32::<LittleEndian>()?;
+                    let state = EntityState::read(&mut reader)?;
+                    let component_count = reader.read_u8()?;
+                    let mut components = Vec::with_capacity(component_count as usize);
+                    for _ in 0..component_count {
+                        let component = reader.read_u16::<LittleEndian>()?;
+                        let len = reader.read_u16::<LittleEndian>()?;
+                        let mut data = vec![0; len as usize];
+                        reader.read_exact(&mut data)?;
+                        components.push((component, data));
+                    }
+                    entities.push((id, state, components));
+                }
+                Ok(ClientMessage::Snapshot {
+                    time,
+                    last_input,
+                    entities,
+                })
+            }
+            _ => Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                "Unknown replication message",
+            )),
+        }
+    }
+}
+
+/// Client side of replication. See module docs.
+pub struct ReplicationClient {
+    entities: HashMap<NetworkId, ClientEntity>,
+    spawner: Box<dyn FnMut(&mut Scene, NetworkId, &str) -> Handle<Node>>,
+    predicted: HashSet<NetworkId>,
+    corrections: VecDeque<Correction>,
+    interpolation_delay: f32,
+    latest_time: f64,
+    render_time: f64,
+    last_input: u32,
+}
+
+impl ReplicationClient {
+    /// Creates new replication client. Spawner is called when server registers new entity,
+    /// it must create node for the entity by its kind and return handle of the node.
+    pub fn new<F>(spawner: F) -> Self
+    where
+        F: FnMut(&mut Scene, NetworkId, &str) -> Handle<Node> + 'static,
+    {
+        Self {
+            entities: Default::default(),
+            spawner: Box::new(spawner),
+            predicted: Default::default(),
+            corrections: Default::default(),
+            interpolation_delay: 0.1,
+            latest_time: 0.0,
+            render_time: 0.0,
+            last_input: 0,
+        }
+    }
+
+    /// Sets how far in the past entities are rendered. It should be larger than interval
+    /// between snapshots, so there is always a pair of snapshots to interpolate between.
+    pub fn set_interpolation_delay(&mut self, delay: f32) {
+        self.interpolation_delay = delay.max(0.0);
+    }
+
+    /// Returns current interpolation delay.
+    pub fn interpolation_delay(&self) -> f32 {
+        self.interpolation_delay
+    }
+
+    /// Marks entity as predicted by the client, see module docs.
+    pub fn set_predicted(&mut self, id: NetworkId, predicted: bool) {
+        if predicted {
+            self.predicted.insert(id);
+        } else {
+            self.predicted.remove(&id);
+        }
+    }
+
+    /// Returns node of an entity, if it was spawned.
+    pub fn node(&self, id: NetworkId) -> Option<Handle<Node>> {
+        self.entities.get(&id).map(|entity| entity.node)
+    }
+
+    /// Returns latest received data of a component of an entity.
+    pub fn component(&self, id: NetworkId, component: u16) -> Option<&[u8]> {
+        self.entities
+            .get(&id)
+            .and_then(|entity| entity.components.get(&component))
+            .map(|data| data.as_slice())
+    }
+
+    /// Returns last input of the client which was applied by server.
+    pub fn last_input(&self) -> u32 {
+        self.last_input
+    }
+
+    /// Returns next correction of a predicted entity, if any.
+    pub fn poll_correction(&mut self) -> Option<Correction> {
+        self.corrections.pop_front()
+    }
+
+    /// Handles message received from server. Returns false if message is not a replication
+    /// message, such message should be handled by the game.
+    pub fn handle_message(&mut self, scene: &mut Scene, data: &[u8]) -> bool {
+        if data.first() != Some(&REPLICATION_MESSAGE) {
+            return false;
+        }
+        let message = match ClientMessage::read(data) {
+            Ok(message) => message,
+            // Malformed message is still ours, just ignore it.
+            Err(_) => return true,
+        };
+        match message {
+            ClientMessage::Spawn { id, kind } => {
+                if !self.entities.contains_key(&id) {
+                    let node = (self.spawner)(scene, id, &kind);
+                    self.entities.insert(
+                        id,
+                        ClientEntity {
+                            node,
+                            snapshots: Default::default(),
+                            components: Default::default(),
+                        },
+                    );
+                }
+            }
+            ClientMessage::Despawn { id } => {
+                if let Some(entity) = self.entities.remove(&id) {
+                    if scene.graph.is_valid_handle(entity.node) {
+                        scene.graph.remove_node(entity.node);
+                    }
+                }
+                self.predicted.remove(&id);
+            }
+            ClientMessage::Snapshot {
+                time,
+                last_input,
+                entities,
+            } => {
+                if self.latest_time == 0.0 {
+                    self.render_time = time - self.interpolation_delay as f64;
+                }
+                self.latest_time = self.latest_time.max(time);
+                self.last_input = self.last_input.max(last_input);
+                for (id, state, components) in entities {
+                    // Snapshot can outrun spawn message, entity will get next one.
+                    let entity = match self.entities.get_mut(&id) {
+                        Some(entity) => entity,
+                        None => continue,
+                    };
+                    entity.components.extend(components);
+                    if self.predicted.contains(&id) {
+                        self.corrections.push_back(Correction {
+                            id,
+                            node: entity.node,
+                            state,
+                            last_input,
+                        });
+                    } else if entity
+                        .snapshots
+                        .back()
+                        .map_or(true, |(last_time, _)| time > *last_time)
+                    {
+                        // Unreliable snapshots can come out of order, old ones are dropped.
+                        entity.snapshots.push_back((time, state));
+                        if entity.snapshots.len() > MAX_SNAPSHOTS {
+                            entity.snapshots.pop_front();
+                        }
+                    }
+                }
+            }
+        }
+        true
+    }
+
+    /// Moves entities to their interpolated states. Must be called every frame.
+    pub fn update(&mut self, scene: &mut Scene, dt: f32) {
+        self.render_time += dt as f64;
+        // Stay behind latest snapshot by interpolation delay, big drift (lag spike) snaps
+        // instantly, small one is corrected smoothly.
+        let target = self.latest_time - self.interpolation_delay as f64;
+        let drift = target - self.render_time;
+        if drift.abs() > 0.25 {
+            self.render_time = target;
+        } else {
+            self.render_time += drift * 0.1;
+        }
+
+        for (id, entity) in self.entities.iter_mut() {
+            if self.predicted.contains(id) || !scene.graph.is_valid_handle(entity.node) {
+                continue;
+            }
+            if let Some(state) = entity.state_at(self.render_time) {
+                state.apply(&mut scene.graph[entity.node]);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        core::{
+            math::{quat::Quat, vec3::Vec3},
+            pool::Handle,
+        },
+        network::{
+            replication::{
+                write_snapshot, ClientEntity, ClientMessage, EntityState, ReplicationServer,
+                ServerEntity, MAX_MESSAGE_SIZE,
+            },
+            NetworkError,
+        },
+    };
+    use std::collections::BTreeMap;
+
+    fn state(x: f32) -> EntityState {
+        EntityState {
+            position: Vec3::new(x, 0.0, 0.0),
+            rotation: Quat::from_axis_angle(Vec3::new(0.0, 1.0, 0.0), x),
+            scale: Vec3::new(1.0, 1.0, 1.0),
+        }
+    }
+
+    #[test]
+    fn snapshot_test() {
+        let mut components = BTreeMap::new();
+        components.insert(3, vec![1, 2, 3]);
+        let messages = write_snapshot(
+            1.5,
+            42,
+            (0..100).map(|id| (id, state(id as f32), &components)),
+        );
+        // 100 entities do not fit into one message.
+        assert!(messages.len() > 1);
+        assert!(messages.iter().all(|m| m.len() <= MAX_MESSAGE_SIZE));
+
+        let mut total = 0;
+        for message in messages {
+            match ClientMessage::read(&message).unwrap() {
+                ClientMessage::Snapshot {
+                    time,
+                    last_input,
+                    entities,
+                } => {
+                    assert_eq!(time, 1.5);
+                    assert_eq!(last_input, 42);
+                    for (id, entity_state, entity_components) in entities {
+                        assert_eq!(entity_state, state(id as f32));
+                        assert_eq!(entity_components, vec![(3, vec![1, 2, 3])]);
+                        total += 1;
+                    }
+                }
+                _ => panic!("Snapshot expected"),
+            }
+        }
+        assert_eq!(total, 100);
+    }
+
+    #[test]
+    fn component_size_test() {
+        let mut server = ReplicationServer::new();
+        server.entities.insert(
+            0,
+            ServerEntity {
+                node: Handle::NONE,
+                kind: "Player".to_owned(),
+                components: Default::default(),
+            },
+        );
+        server.set_component(0, 0, vec![0; 512]).unwrap();
+        // Replacing a component does not count its old data.
+        server.set_component(0, 0, vec![0; 600]).unwrap();
+        assert!(matches!(
+            server.set_component(0, 1, vec![0; 600]),
+            Err(NetworkError::MessageTooLarge(_))
+        ));
+        assert_eq!(server.entities[&0].components.len(), 1);
+
+        // Every entity fits into one snapshot message.
+        let entities = server
+            .entities
+            .iter()
+            .map(|(id, entity)| (*id, state(0.0), &entity.components));
+        let messages = write_snapshot(0.0, 0, entities);
+        assert!(messages.iter().all(|m| m.len() <= MAX_MESSAGE_SIZE));
+    }
+
+    #[test]
+    fn interpolation_test() {
+        let mut entity = ClientEntity {
+            node: Handle::NONE,
+            snapshots: vec![(0.0, state(0.0)), (1.0, state(1.0)), (2.0, state(3.0))]
+                .into_iter()
+                .collect(),
+            components: Default::default(),
+        };
+        let position = |s: EntityState| s.position.x;
+        assert_eq!(entity.state_at(0.5).map(position), Some(0.5));
+        assert_eq!(entity.state_at(1.5).map(position), Some(2.0));
+        // First snapshot is not needed anymore.
+        assert_eq!(entity.snapshots.len(), 2);
+        // No extrapolation past latest snapshot.
+        assert_eq!(entity.state_at(5.0).map(position), Some(3.0));
+    }
+}
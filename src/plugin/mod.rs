@@ -0,0 +1,508 @@
+//! Host of sandboxed WebAssembly gameplay plugins, available with `wasm` feature.
+//!
+//! Plugins are WebAssembly modules executed by an interpreter, they have no access to file
+//! system, network or native code - the only way to affect the game is the host API below.
+//! This makes plugins suitable for mods downloaded from untrusted sources. For trusted game
+//! code see [Lua scripting](crate::scripting) (`lua` feature).
+//!
+//! # Plugin exports
+//!
+//! - `init()` - optional, called once before first update.
+//! - `update(dt: f32)` - optional, called every frame.
+//! - `memory` - linear memory, required if plugin passes strings to the host.
+//!
+//! # Host API
+//!
+//! Functions are imported from `env` module. Nodes are identified by indices of their
+//! handles, negative index means "no node". Strings are passed as pointer and length of
+//! UTF-8 bytes in plugin memory. Functions that return `i32` status return 1 on success and 0
+//! if node does not exist.
+//!
+//! ```text
+//! rg3d_log(ptr: i32, len: i32)
+//! rg3d_find_node(name_ptr: i32, name_len: i32) -> i32 - node or -1.
+//! rg3d_get_position(node: i32, out_ptr: i32) -> i32 - writes x, y, z as three f32.
+//! rg3d_set_position(node: i32, x: f32, y: f32, z: f32) -> i32
+//! rg3d_move_node(node: i32, x: f32, y: f32, z: f32) -> i32
+//! rg3d_set_rotation(node: i32, axis_x: f32, axis_y: f32, axis_z: f32, angle: f32) -> i32
+//! rg3d_set_visible(node: i32, visible: i32) -> i32
+//! rg3d_remove_node(node: i32) -> i32
+//! rg3d_spawn_model(path_ptr: i32, path_len: i32) -> i32 - root of new instance or -1.
+//! rg3d_next_event(out_ptr: i32, capacity: i32) -> i32
+//! ```
+//!
+//! `rg3d_next_event` writes next event of current frame as `name:value` string and returns
+//! its length, -1 if there are no more events. If the event does not fit in given capacity,
+//! nothing is written and `-(required capacity + 1)` is returned.
+//! Events are sent by the game with [PluginHost::push_event], every plugin receives every
+//! event.
+//!
+//! Host API is stable, new functions are added with new names, existing functions never
+//! change their signatures.
+
+use crate::{
+    core::{
+        math::{quat::Quat, vec3::Vec3},
+        pool::Handle,
+    },
+    engine::resource_manager::ResourceManager,
+    scene::{node::Node, Scene},
+    utils::log::Log,
+};
+use std::{
+    fmt::{Display, Formatter},
+    path::Path,
+    sync::Mutex,
+};
+use wasmi::{
+    Externals, FuncInstance, FuncRef, HostError, ImportsBuilder, MemoryRef, Module,
+    ModuleImportResolver, ModuleInstance, ModuleRef, NopExternals, RuntimeArgs, RuntimeValue,
+    Signature, Trap, TrapKind, ValueType,
+    ValueType::{F32, I32},
+};
+
+/// All possible errors that can occur when loading a plugin.
+#[derive(Debug)]
+pub enum PluginError {
+    /// An input/output error has occurred.
+    Io(std::io::Error),
+    /// Module is not valid, imports unknown functions or its start function failed.
+    Wasm(wasmi::Error),
+}
+
+impl Display for PluginError {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), std::fmt::Error> {
+        match self {
+            PluginError::Io(io) => write!(f, "Io error: {}", io),
+            PluginError::Wasm(wasm) => write!(f, "WebAssembly error: {}", wasm),
+        }
+    }
+}
+
+impl From<std::io::Error> for PluginError {
+    fn from(err: std::io::Error) -> Self {
+        PluginError::Io(err)
+    }
+}
+
+impl From<wasmi::Error> for PluginError {
+    fn from(err: wasmi::Error) -> Self {
+        PluginError::Wasm(err)
+    }
+}
+
+#[derive(Debug)]
+struct PluginTrap(String);
+
+impl Display for PluginTrap {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), std::fmt::Error> {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl HostError for PluginTrap {}
+
+fn trap<S: Into<String>>(message: S) -> Trap {
+    Trap::new(TrapKind::Host(Box::new(PluginTrap(message.into()))))
+}
+
+/// Host API: name, parameters and return type. Index of a function is its import index.
+const HOST_FUNCTIONS: &[(&str, &[ValueType], Option<ValueType>)] = &[
+    ("rg3d_log", &[I32, I32], None),
+    ("rg3d_find_node", &[I32, I32], Some(I32)),
+    ("rg3d_get_position", &[I32, I32], Some(I32)),
+    ("rg3d_set_position", &[I32, F32, F32, F32], Some(I32)),
+    ("rg3d_move_node", &[I32, F32, F32, F32], Some(I32)),
+    ("rg3d_set_rotation", &[I32, F32, F32, F32, F32], Some(I32)),
+    ("rg3d_set_visible", &[I32, I32], Some(I32)),
+    ("rg3d_remove_node", &[I32], Some(I32)),
+    ("rg3d_spawn_model", &[I32, I32], Some(I32)),
+    ("rg3d_next_event", &[I32, I32], Some(I32)),
+];
+
+struct HostResolver;
+
+impl ModuleImportResolver for HostResolver {
+    fn resolve_func(
+        &self,
+        field_name: &str,
+        signature: &Signature,
+    ) -> Result<FuncRef, wasmi::Error> {
+        let (index, (_, params, return_type)) = HOST_FUNCTIONS
+            .iter()
+            .enumerate()
+            .find(|(_, (name, _, _))| *name == field_name)
+            .ok_or_else(|| {
+                wasmi::Error::Instantiation(format!("Unknown host function {}", field_name))
+            })?;
+        if signature.params() != *params || signature.return_type() != *return_type {
+            return Err(wasmi::Error::Instantiation(format!(
+                "Wrong signature of host function {}",
+                field_name
+            )));
+        }
+        Ok(FuncInstance::alloc_host(
+            Signature::new(*params, *return_type),
+            index,
+        ))
+    }
+}
+
+/// Everything host functions can access during a call into a plugin.
+struct HostContext<'a> {
+    plugin: &'a str,
+    scene: &'a mut Scene,
+    resource_manager: &'a Mutex<ResourceManager>,
+    memory: Option<&'a MemoryRef>,
+    events: &'a [String],
+    next_event: &'a mut usize,
+}
+
+fn int(args: &RuntimeArgs, index: usize) -> Result<i32, Trap> {
+    match args.nth_value_checked(index)? {
+        RuntimeValue::I32(value) => Ok(value),
+        _ => Err(trap("Expected i32 argument")),
+    }
+}
+
+fn float(args: &RuntimeArgs, index: usize) -> Result<f32, Trap> {
+    match args.nth_value_checked(index)? {
+        RuntimeValue::F32(value) => Ok(value.to_float()),
+        _ => Err(trap("Expected f32 argument")),
+    }
+}
+
+fn vec3(args: &RuntimeArgs, first: usize) -> Result<Vec3, Trap> {
+    Ok(Vec3::new(
+        float(args, first)?,
+        float(args, first + 1)?,
+        float(args, first + 2)?,
+    ))
+}
+
+fn status(success: bool) -> Result<Option<RuntimeValue>, Trap> {
+    Ok(Some(RuntimeValue::I32(success as i32)))
+}
+
+impl<'a> HostContext<'a> {
+    fn memory(&self) -> Result<&MemoryRef, Trap> {
+        self.memory
+            .ok_or_else(|| trap("Plugin does not export memory"))
+    }
+
+    fn read_string(&self, ptr: i32, len: i32) -> Result<String, Trap> {
+        let bytes = self
+            .memory()?
+            .get(ptr as u32, len.max(0) as usize)
+            .map_err(|e| trap(e.to_string()))?;
+        String::from_utf8(bytes).map_err(|_| trap("String is not valid UTF-8"))
+    }
+
+    fn write(&self, ptr: i32, bytes: &[u8]) -> Result<(), Trap> {
+        self.memory()?
+            .set(ptr as u32, bytes)
+            .map_err(|e| trap(e.to_string()))
+    }
+
+    fn handle(&self, node: i32) -> Handle<Node> {
+        if node < 0 {
+            Handle::NONE
+        } else {
+            self.scene.graph.handle_from_index(node as usize)
+        }
+    }
+
+    fn node(&mut self, node: i32) -> Option<&mut Node> {
+        let handle = self.handle(node);
+        if handle.is_some() {
+            Some(&mut self.scene.graph[handle])
+        } else {
+            None
+        }
+    }
+
+    fn spawn_model(&mut self, path: &str) -> i32 {
+        let model = self.resource_manager.lock().unwrap().request_model(path);
+        match model {
+            Some(model) => model
+                .lock()
+                .unwrap()
+                .instantiate_geometry(self.scene)
+                .index() as i32,
+            None => -1,
+        }
+    }
+}
+
+impl<'a> Externals for HostContext<'a> {
+    fn invoke_index(
+        &mut self,
+        index: usize,
+        args: RuntimeArgs,
+    ) -> Result<Option<RuntimeValue>, Trap> {
+        let name = HOST_FUNCTIONS
+            .get(index)
+            .map(|(name, _, _)| *name)
+            .ok_or_else(|| trap("Unknown host function"))?;
+        match name {
+            "rg3d_log" => {
+                let message = self.read_string(int(&args, 0)?, int(&args, 1)?)?;
+                Log::writeln(format!("[{}] {}", self.plugin, message));
+                Ok(None)
+            }
+            "rg3d_find_node" => {
+                let name = self.read_string(int(&args, 0)?, int(&args, 1)?)?;
+                let handle = self.scene.graph.find_by_name_from_root(&name);
+                let node = if handle.is_some() {
+                    handle.index() as i32
+                } else {
+                    -1
+                };
+                Ok(Some(RuntimeValue::I32(node)))
+            }
+            "rg3d_get_position" => {
+                let position = match self.node(int(&args, 0)?) {
+                    Some(node) => node.local_transform().position(),
+                    None => return status(false),
+                };
+                let mut bytes = Vec::with_capacity(12);
+                for component in &[position.x, position.y, position.z] {
+                    bytes.extend_from_slice(&component.to_le_bytes());
+                }
+                self.write(int(&args, 1)?, &bytes)?;
+                status(true)
+            }
+            "rg3d_set_position" | "rg3d_move_node" => {
+                let v = vec3(&args, 1)?;
+                match self.node(int(&args, 0)?) {
+                    Some(node) => {
+                        let transform = node.local_transform_mut();
+                        if name == "rg3d_set_position" {
+                            transform.set_position(v);
+                        } else {
+                            transform.offset(v);
+                        }
+                        status(true)
+                    }
+                    None => status(false),
+                }
+            }
+            "rg3d_set_rotation" => {
+                let rotation = Quat::from_axis_angle(vec3(&args, 1)?, float(&args, 4)?);
+                match self.node(int(&args, 0)?) {
+                    Some(node) => {
+                        node.local_transform_mut().set_rotation(rotation);
+                        status(true)
+                    }
+                    None => status(false),
+                }
+            }
+            "rg3d_set_visible" => {
+                let visible = int(&args, 1)? != 0;
+                match self.node(int(&args, 0)?) {
+                    Some(node) => {
+                        node.set_visibility(visible);
+                        status(true)
+                    }
+                    None => status(false),
+                }
+            }
+            "rg3d_remove_node" => {
+                let handle = self.handle(int(&args, 0)?);
+                if handle.is_some() {
+                    self.scene.graph.remove_node(handle);
+                }
+                status(handle.is_some())
+            }
+            "rg3d_spawn_model" => {
+                let path = self.read_string(int(&args, 0)?, int(&args, 1)?)?;
+                Ok(Some(RuntimeValue::I32(self.spawn_model(&path))))
+            }
+            "rg3d_next_event" => {
+                let result = match self.events.get(*self.next_event) {
+                    Some(event) => {
+                        let capacity = int(&args, 1)?;
+                        if event.len() as i32 > capacity {
+                            -(event.len() as i32 + 1)
+                        } else {
+                            self.write(int(&args, 0)?, event.as_bytes())?;
+                            *self.next_event += 1;
+                            event.len() as i32
+                        }
+                    }
+                    None => -1,
+                };
+                Ok(Some(RuntimeValue::I32(result)))
+            }
+            _ => Err(trap("Unknown host function")),
+        }
+    }
+}
+
+struct Plugin {
+    name: String,
+    instance: ModuleRef,
+    memory: Option<MemoryRef>,
+    initialized: bool,
+}
+
+impl Plugin {
+    fn call(
+        &self,
+        function: &str,
+        args: &[RuntimeValue],
+        context: &mut HostContext,
+    ) -> Result<(), wasmi::Error> {
+        if self.instance.export_by_name(function).is_some() {
+            self.instance.invoke_export(function, args, context)?;
+        }
+        Ok(())
+    }
+}
+
+/// Runs WebAssembly plugins. See module docs.
+#[derive(Default)]
+pub struct PluginHost {
+    plugins: Vec<Plugin>,
+    events: Vec<String>,
+}
+
+impl PluginHost {
+    /// Creates new plugin host without plugins.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Loads plugin from WebAssembly module. Name is used to unload the plugin and in log
+    /// messages.
+    pub fn load(&mut self, name: &str, bytes: &[u8]) -> Result<(), PluginError> {
+        let module = Module::from_buffer(bytes)?;
+        let imports = ImportsBuilder::new().with_resolver("env", &HostResolver);
+        let instance = ModuleInstance::new(&module, &imports)?
+            .run_start(&mut NopExternals)
+            .map_err(wasmi::Error::from)?;
+        let memory = instance
+            .export_by_name("memory")
+            .and_then(|export| export.as_memory().cloned());
+        self.plugins.push(Plugin {
+            name: name.to_owned(),
+            instance,
+            memory,
+            initialized: false,
+        });
+        Ok(())
+    }
+
+    /// Loads plugin from a file through virtual file system of resource manager. Path is
+    /// used as name of the plugin.
+    pub fn load_file<P: AsRef<Path>>(
+        &mut self,
+        resource_manager: &ResourceManager,
+        path: P,
+    ) -> Result<(), PluginError> {
+        let bytes = resource_manager.vfs().lock().unwrap().read(path.as_ref())?;
+        self.load(&path.as_ref().to_string_lossy(), &bytes)
+    }
+
+    /// Unloads every plugin with given name.
+    pub fn unload(&mut self, name: &str) {
+        self.plugins.retain(|plugin| plugin.name != name);
+    }
+
+    /// Returns true if there is a plugin with given name.
+    pub fn is_loaded(&self, name: &str) -> bool {
+        self.plugins.iter().any(|plugin| plugin.name == name)
+    }
+
+    /// Queues event for plugins, events are delivered on next update.
+    pub fn push_event(&mut self, name: &str, value: &str) {
+        self.events.push(format!("{}:{}", name, value));
+    }
+
+    /// Updates every plugin. Errors of plugins are written to the log, one failing plugin
+    /// does not stop others.
+    pub fn update(
+        &mut self,
+        scene: &mut Scene,
+        resource_manager: &Mutex<ResourceManager>,
+        dt: f32,
+    ) {
+        let events = std::mem::take(&mut self.events);
+        for plugin in self.plugins.iter_mut() {
+            let mut next_event = 0;
+            let mut context = HostContext {
+                plugin: &plugin.name,
+                scene: &mut *scene,
+                resource_manager,
+                memory: plugin.memory.as_ref(),
+                events: &events,
+                next_event: &mut next_event,
+            };
+            let mut result = Ok(());
+            if !plugin.initialized {
+                result = plugin.call("init", &[], &mut context);
+            }
+            if result.is_ok() {
+                result = plugin.call("update", &[RuntimeValue::F32(dt.into())], &mut context);
+            }
+            plugin.initialized = true;
+            if let Err(e) = result {
+                Log::writeln(format!("Plugin {} failed! Reason: {}", plugin.name, e));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        engine::resource_manager::ResourceManager,
+        plugin::PluginHost,
+        scene::{base::BaseBuilder, Scene},
+    };
+    use std::sync::Mutex;
+
+    #[test]
+    fn plugin_test() {
+        let mut scene = Scene::new();
+        let node = scene
+            .graph
+            .add_node(BaseBuilder::new().with_name("Box").build_node());
+        let resource_manager = Mutex::new(ResourceManager::new());
+
+        let module = wat::parse_str(
+            r#"
+            (module
+                (import "env" "rg3d_find_node" (func $find (param i32 i32) (result i32)))
+                (import "env" "rg3d_move_node"
+                    (func $move (param i32 f32 f32 f32) (result i32)))
+                (import "env" "rg3d_next_event" (func $event (param i32 i32) (result i32)))
+                (memory (export "memory") 1)
+                (data (i32.const 0) "Box")
+                (global $node (mut i32) (i32.const -1))
+                (func (export "init")
+                    (global.set $node (call $find (i32.const 0) (i32.const 3))))
+                (func (export "update") (param $dt f32)
+                    ;; Every event makes the box move twice as fast.
+                    (if (i32.gt_s (call $event (i32.const 16) (i32.const 64)) (i32.const 0))
+                        (then (local.set $dt (f32.mul (local.get $dt) (f32.const 2)))))
+                    (drop (call $move (global.get $node)
+                        (f32.const 0) (local.get $dt) (f32.const 0)))))
+            "#,
+        )
+        .unwrap();
+
+        let mut host = PluginHost::new();
+        host.load("mover", &module).unwrap();
+        assert!(host.is_loaded("mover"));
+
+        host.update(&mut scene, &resource_manager, 1.0);
+        host.push_event("boost", "");
+        host.update(&mut scene, &resource_manager, 1.0);
+        assert_eq!(scene.graph[node].local_transform().position().y, 3.0);
+
+        // Unknown imports are rejected.
+        let module = wat::parse_str(r#"(module (import "env" "exec" (func)))"#).unwrap();
+        assert!(host.load("bad", &module).is_err());
+    }
+}
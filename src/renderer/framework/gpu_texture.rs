@@ -7,7 +7,7 @@ use crate::{
         error::RendererError,
         framework::{gl, gl::types::GLuint, state::State},
     },
-    resource::texture::TextureKind,
+    resource::texture::{ColorSpace, TextureKind},
     utils::log::Log,
 };
 use std::{ffi::c_void, marker::PhantomData};
@@ -43,6 +43,16 @@ impl GpuTextureKind {
     }
 }
 
+// S3TC formats are not part of core OpenGL, they're defined by EXT_texture_compression_s3tc
+// which is supported by every desktop GPU.
+const COMPRESSED_RGBA_S3TC_DXT1_EXT: GLuint = 0x83F1;
+const COMPRESSED_RGBA_S3TC_DXT3_EXT: GLuint = 0x83F2;
+const COMPRESSED_RGBA_S3TC_DXT5_EXT: GLuint = 0x83F3;
+// sRGB variants are defined by EXT_texture_sRGB.
+const COMPRESSED_SRGB_ALPHA_S3TC_DXT1_EXT: GLuint = 0x8C4D;
+const COMPRESSED_SRGB_ALPHA_S3TC_DXT3_EXT: GLuint = 0x8C4E;
+const COMPRESSED_SRGB_ALPHA_S3TC_DXT5_EXT: GLuint = 0x8C4F;
+
 #[derive(Copy, Clone)]
 pub enum PixelKind {
     F32,
@@ -52,6 +62,24 @@ pub enum PixelKind {
     RGB8,
     RG8,
     R8,
+    DXT1RGBA,
+    DXT3RGBA,
+    DXT5RGBA,
+    R8RGTC,
+    RG8RGTC,
+    RGBA8BPTC,
+    RGB32F,
+    RGBA32F,
+    // Half-precision kinds, data is given as 32-bit floats.
+    RGB16F,
+    RGBA16F,
+    // sRGB kinds, values are converted to linear space on sampling.
+    SRGBA8,
+    SRGB8,
+    SRGBDXT1RGBA,
+    SRGBDXT3RGBA,
+    SRGBDXT5RGBA,
+    SRGBA8BPTC,
 }
 
 impl From<TextureKind> for PixelKind {
@@ -60,6 +88,15 @@ impl From<TextureKind> for PixelKind {
             TextureKind::R8 => Self::R8,
             TextureKind::RGB8 => Self::RGB8,
             TextureKind::RGBA8 => Self::RGBA8,
+            TextureKind::DXT1RGBA => Self::DXT1RGBA,
+            TextureKind::DXT3RGBA => Self::DXT3RGBA,
+            TextureKind::DXT5RGBA => Self::DXT5RGBA,
+            TextureKind::R8RGTC => Self::R8RGTC,
+            TextureKind::RG8RGTC => Self::RG8RGTC,
+            TextureKind::RGBA8BPTC => Self::RGBA8BPTC,
+            // Half precision is enough for HDR images and takes two times less memory.
+            TextureKind::RGB32F => Self::RGB16F,
+            TextureKind::RGBA32F => Self::RGBA16F,
         }
     }
 }
@@ -72,20 +109,79 @@ pub struct GpuTexture {
 }
 
 impl PixelKind {
+    /// Returns pixel kind for texture of given kind with pixels in given color space. Kinds
+    /// without sRGB variant (single or two channel, floating-point) are always linear.
+    pub fn from_texture(kind: TextureKind, color_space: ColorSpace) -> Self {
+        let pixel_kind = Self::from(kind);
+        if color_space == ColorSpace::Linear {
+            return pixel_kind;
+        }
+        match pixel_kind {
+            Self::RGBA8 => Self::SRGBA8,
+            Self::RGB8 => Self::SRGB8,
+            Self::DXT1RGBA => Self::SRGBDXT1RGBA,
+            Self::DXT3RGBA => Self::SRGBDXT3RGBA,
+            Self::DXT5RGBA => Self::SRGBDXT5RGBA,
+            Self::RGBA8BPTC => Self::SRGBA8BPTC,
+            _ => pixel_kind,
+        }
+    }
+
+    /// Size of 4x4 block in bytes for compressed kinds.
+    fn block_size(self) -> Option<usize> {
+        match self {
+            Self::DXT1RGBA | Self::SRGBDXT1RGBA | Self::R8RGTC => Some(8),
+            Self::DXT3RGBA
+            | Self::DXT5RGBA
+            | Self::SRGBDXT3RGBA
+            | Self::SRGBDXT5RGBA
+            | Self::RG8RGTC
+            | Self::RGBA8BPTC
+            | Self::SRGBA8BPTC => Some(16),
+            _ => None,
+        }
+    }
+
+    pub fn is_compressed(self) -> bool {
+        self.block_size().is_some()
+    }
+
     fn size_bytes(self) -> usize {
         match self {
-            Self::RGBA8 | Self::D24S8 | Self::D32 | Self::F32 => 4,
-            Self::RGB8 => 3,
+            Self::RGBA32F | Self::RGBA16F => 16,
+            Self::RGB32F | Self::RGB16F => 12,
+            Self::RGBA8 | Self::SRGBA8 | Self::D24S8 | Self::D32 | Self::F32 => 4,
+            Self::RGB8 | Self::SRGB8 => 3,
             Self::RG8 => 2,
             Self::R8 => 1,
+            // Meaningless for compressed kinds, use image_size instead.
+            _ => 0,
+        }
+    }
+
+    /// Size of image of given size in bytes.
+    fn image_size(self, width: usize, height: usize) -> usize {
+        match self.block_size() {
+            Some(block_size) => ((width + 3) / 4) * ((height + 3) / 4) * block_size,
+            None => width * height * self.size_bytes(),
         }
     }
 
     fn unpack_alignment(self) -> i32 {
         match self {
-            Self::RGBA8 | Self::RGB8 | Self::D24S8 | Self::D32 | Self::F32 => 4,
+            Self::RGBA8
+            | Self::RGB8
+            | Self::SRGBA8
+            | Self::SRGB8
+            | Self::D24S8
+            | Self::D32
+            | Self::F32
+            | Self::RGB32F
+            | Self::RGBA32F
+            | Self::RGB16F
+            | Self::RGBA16F => 4,
             Self::RG8 => 2,
-            Self::R8 => 1,
+            _ => 1,
         }
     }
 }
@@ -186,7 +282,11 @@ impl<'a> TextureBinding<'a> {
         unsafe {
             let mut aniso = 0.0;
             gl::GetFloatv(gl::MAX_TEXTURE_MAX_ANISOTROPY_EXT, &mut aniso);
-            gl::TexParameterf(gl::TEXTURE_2D, gl::TEXTURE_MAX_ANISOTROPY_EXT, aniso);
+            gl::TexParameterf(
+                self.texture.kind.to_texture_target(),
+                gl::TEXTURE_MAX_ANISOTROPY_EXT,
+                aniso,
+            );
         }
         self
     }
@@ -260,13 +360,40 @@ impl GpuTexture {
         kind: GpuTextureKind,
         pixel_kind: PixelKind,
         data: Option<&[u8]>,
+    ) -> Result<Self, RendererError> {
+        Self::with_mips(state, kind, pixel_kind, 1, data)
+    }
+
+    /// Creates new GPU texture of specified kind with given amount of mip levels.
+    ///
+    /// # Data layout
+    ///
+    /// Mip levels of each image are stored one after another starting from largest one.
+    /// In case of Cube texture, `bytes` should contain all 6 cube faces (each with its mip
+    /// chain) ordered like so, +X, -X, +Y, -Y, +Z, -Z. Compressed pixel kinds are supported
+    /// only for Rectangle and Cube textures.
+    pub fn with_mips(
+        state: &mut State,
+        kind: GpuTextureKind,
+        pixel_kind: PixelKind,
+        mip_count: usize,
+        data: Option<&[u8]>,
     ) -> Result<Self, RendererError> {
         let bytes_per_pixel = pixel_kind.size_bytes();
+        let mip_count = mip_count.max(1);
+
+        let chain_size = |width: usize, height: usize| -> usize {
+            (0..mip_count)
+                .map(|level| {
+                    pixel_kind.image_size((width >> level).max(1), (height >> level).max(1))
+                })
+                .sum()
+        };
 
         let desired_byte_count = match kind {
             GpuTextureKind::Line { length } => length * bytes_per_pixel,
-            GpuTextureKind::Rectangle { width, height } => width * height * bytes_per_pixel,
-            GpuTextureKind::Cube { width, height } => 6 * width * height * bytes_per_pixel,
+            GpuTextureKind::Rectangle { width, height } => chain_size(width, height),
+            GpuTextureKind::Cube { width, height } => 6 * chain_size(width, height),
             GpuTextureKind::Volume {
                 width,
                 height,
@@ -303,10 +430,67 @@ impl GpuTexture {
                 PixelKind::RGB8 => (gl::UNSIGNED_BYTE, gl::RGB, gl::RGB8),
                 PixelKind::RG8 => (gl::UNSIGNED_BYTE, gl::RG, gl::RG8),
                 PixelKind::R8 => (gl::UNSIGNED_BYTE, gl::RED, gl::R8),
+                PixelKind::SRGBA8 => (gl::UNSIGNED_BYTE, gl::RGBA, gl::SRGB8_ALPHA8),
+                PixelKind::SRGB8 => (gl::UNSIGNED_BYTE, gl::RGB, gl::SRGB8),
+                PixelKind::RGB32F => (gl::FLOAT, gl::RGB, gl::RGB32F),
+                PixelKind::RGBA32F => (gl::FLOAT, gl::RGBA, gl::RGBA32F),
+                PixelKind::RGB16F => (gl::FLOAT, gl::RGB, gl::RGB16F),
+                PixelKind::RGBA16F => (gl::FLOAT, gl::RGBA, gl::RGBA16F),
+                // Type and format are not used for compressed kinds.
+                PixelKind::DXT1RGBA => (0, 0, COMPRESSED_RGBA_S3TC_DXT1_EXT),
+                PixelKind::DXT3RGBA => (0, 0, COMPRESSED_RGBA_S3TC_DXT3_EXT),
+                PixelKind::DXT5RGBA => (0, 0, COMPRESSED_RGBA_S3TC_DXT5_EXT),
+                PixelKind::R8RGTC => (0, 0, gl::COMPRESSED_RED_RGTC1),
+                PixelKind::RG8RGTC => (0, 0, gl::COMPRESSED_RG_RGTC2),
+                PixelKind::RGBA8BPTC => (0, 0, gl::COMPRESSED_RGBA_BPTC_UNORM),
+                PixelKind::SRGBDXT1RGBA => (0, 0, COMPRESSED_SRGB_ALPHA_S3TC_DXT1_EXT),
+                PixelKind::SRGBDXT3RGBA => (0, 0, COMPRESSED_SRGB_ALPHA_S3TC_DXT3_EXT),
+                PixelKind::SRGBDXT5RGBA => (0, 0, COMPRESSED_SRGB_ALPHA_S3TC_DXT5_EXT),
+                PixelKind::SRGBA8BPTC => (0, 0, gl::COMPRESSED_SRGB_ALPHA_BPTC_UNORM),
             };
 
             gl::PixelStorei(gl::UNPACK_ALIGNMENT, pixel_kind.unpack_alignment());
 
+            // Uploads mip chain of a 2D image to given target, returns amount of consumed bytes.
+            let upload_chain = |target: GLuint, width: usize, height: usize, begin: usize| {
+                let mut offset = begin;
+                for level in 0..mip_count {
+                    let level_width = (width >> level).max(1);
+                    let level_height = (height >> level).max(1);
+                    let size = pixel_kind.image_size(level_width, level_height);
+                    let pixels = match data {
+                        None => std::ptr::null(),
+                        Some(data) => data[offset..(offset + size)].as_ptr() as *const c_void,
+                    };
+                    if pixel_kind.is_compressed() {
+                        gl::CompressedTexImage2D(
+                            target,
+                            level as i32,
+                            internal_format,
+                            level_width as i32,
+                            level_height as i32,
+                            0,
+                            size as i32,
+                            pixels,
+                        );
+                    } else {
+                        gl::TexImage2D(
+                            target,
+                            level as i32,
+                            internal_format as i32,
+                            level_width as i32,
+                            level_height as i32,
+                            0,
+                            format,
+                            type_,
+                            pixels,
+                        );
+                    }
+                    offset += size;
+                }
+                offset - begin
+            };
+
             let pixels = match data {
                 None => std::ptr::null(),
                 Some(data) => data.as_ptr() as *const c_void,
@@ -326,40 +510,16 @@ impl GpuTexture {
                     );
                 }
                 GpuTextureKind::Rectangle { width, height } => {
-                    gl::TexImage2D(
-                        gl::TEXTURE_2D,
-                        0,
-                        internal_format as i32,
-                        width as i32,
-                        height as i32,
-                        0,
-                        format,
-                        type_,
-                        pixels,
-                    );
+                    upload_chain(gl::TEXTURE_2D, width, height, 0);
                 }
                 GpuTextureKind::Cube { width, height } => {
+                    let mut offset = 0;
                     for face in 0..6 {
-                        let bytes_per_face = width * height * bytes_per_pixel;
-
-                        let begin = face * bytes_per_face;
-                        let end = (face + 1) * bytes_per_face;
-
-                        let face_pixels = match data {
-                            None => std::ptr::null(),
-                            Some(data) => data[begin..end].as_ptr() as *const c_void,
-                        };
-
-                        gl::TexImage2D(
+                        offset += upload_chain(
                             gl::TEXTURE_CUBE_MAP_POSITIVE_X + face as u32,
-                            0,
-                            internal_format as i32,
-                            width as i32,
-                            height as i32,
-                            0,
-                            format,
-                            type_,
-                            face_pixels,
+                            width,
+                            height,
+                            offset,
                         );
                     }
                 }
@@ -383,6 +543,12 @@ impl GpuTexture {
                 }
             }
 
+            if mip_count > 1 {
+                // Pre-generated mip chains may end before 1x1 level, tell driver that
+                // texture is complete anyway.
+                gl::TexParameteri(target, gl::TEXTURE_MAX_LEVEL, mip_count as i32 - 1);
+            }
+
             gl::TexParameteri(target, gl::TEXTURE_MAG_FILTER, gl::NEAREST as i32);
             gl::TexParameteri(target, gl::TEXTURE_MIN_FILTER, gl::NEAREST as i32);
 
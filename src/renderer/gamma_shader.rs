@@ -0,0 +1,26 @@
+use crate::renderer::{
+    error::RendererError,
+    framework::gpu_program::{GpuProgram, UniformLocation},
+};
+
+/// Draws frame texture which is in linear color space with gamma correction, it is used to
+/// show final frame on screen.
+pub struct GammaShader {
+    pub program: GpuProgram,
+    pub wvp_matrix: UniformLocation,
+    pub frame_texture: UniformLocation,
+}
+
+impl GammaShader {
+    pub fn new() -> Result<Self, RendererError> {
+        let fragment_source = include_str!("shaders/gamma_fs.glsl");
+        let vertex_source = include_str!("shaders/flat_vs.glsl");
+
+        let program = GpuProgram::from_source("GammaShader", vertex_source, fragment_source)?;
+        Ok(Self {
+            wvp_matrix: program.uniform_location("worldViewProjection")?,
+            frame_texture: program.uniform_location("frameTexture")?,
+            program,
+        })
+    }
+}
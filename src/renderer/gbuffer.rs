@@ -29,6 +29,7 @@ struct GBufferShader {
     diffuse_texture: UniformLocation,
     normal_texture: UniformLocation,
     lightmap_texture: UniformLocation,
+    specular_texture: UniformLocation,
     diffuse_color: UniformLocation,
 }
 
@@ -45,6 +46,7 @@ impl GBufferShader {
             diffuse_texture: program.uniform_location("diffuseTexture")?,
             normal_texture: program.uniform_location("normalTexture")?,
             lightmap_texture: program.uniform_location("lightmapTexture")?,
+            specular_texture: program.uniform_location("specularTexture")?,
             diffuse_color: program.uniform_location("diffuseColor")?,
             program,
         })
@@ -54,6 +56,9 @@ impl GBufferShader {
 pub struct GBuffer {
     framebuffer: FrameBuffer,
     pub final_frame: FrameBuffer,
+    /// Gamma-corrected copy of final frame, it is made only when frame is rendered into a
+    /// render target.
+    pub gamma_corrected_frame: FrameBuffer,
     shader: GBufferShader,
     bone_matrices: Vec<Mat4>,
     pub width: i32,
@@ -140,10 +145,12 @@ impl GBuffer {
             ],
         )?;
 
+        // Frame is in linear space, 8 bits per channel is not enough to store dark colors
+        // without banding.
         let frame_texture = GpuTexture::new(
             state,
             GpuTextureKind::Rectangle { width, height },
-            PixelKind::RGBA8,
+            PixelKind::RGBA16F,
             None,
         )?;
 
@@ -159,8 +166,23 @@ impl GBuffer {
             }],
         )?;
 
+        let gamma_corrected_frame = FrameBuffer::new(
+            state,
+            None,
+            vec![Attachment {
+                kind: AttachmentKind::Color,
+                texture: Rc::new(RefCell::new(GpuTexture::new(
+                    state,
+                    GpuTextureKind::Rectangle { width, height },
+                    PixelKind::RGBA8,
+                    None,
+                )?)),
+            }],
+        )?;
+
         Ok(GBuffer {
             framebuffer,
+            gamma_corrected_frame,
             shader: GBufferShader::new()?,
             bone_matrices: Vec::new(),
             width: width as i32,
@@ -173,6 +195,12 @@ impl GBuffer {
         self.final_frame.color_attachments()[0].texture.clone()
     }
 
+    pub fn gamma_corrected_frame_texture(&self) -> Rc<RefCell<GpuTexture>> {
+        self.gamma_corrected_frame.color_attachments()[0]
+            .texture
+            .clone()
+    }
+
     pub fn depth(&self) -> Rc<RefCell<GpuTexture>> {
         self.framebuffer.depth_attachment().unwrap().texture.clone()
     }
@@ -241,6 +269,9 @@ impl GBuffer {
                 initial_view_projection
             };
 
+            // Used to request detailed enough mip levels of streamed textures.
+            let distance = camera.global_position().distance(&mesh.global_position());
+
             for surface in mesh.surfaces().iter() {
                 let is_skinned = !surface.bones.is_empty();
 
@@ -252,7 +283,9 @@ impl GBuffer {
                 let mvp = view_projection * world;
 
                 let diffuse_texture = if let Some(texture) = surface.diffuse_texture() {
-                    if let Some(texture) = texture_cache.get(state, texture) {
+                    if let Some(texture) =
+                        texture_cache.get_at_distance(state, texture, distance, true)
+                    {
                         texture
                     } else {
                         white_dummy.clone()
@@ -262,7 +295,9 @@ impl GBuffer {
                 };
 
                 let normal_texture = if let Some(texture) = surface.normal_texture() {
-                    if let Some(texture) = texture_cache.get(state, texture) {
+                    if let Some(texture) =
+                        texture_cache.get_at_distance(state, texture, distance, false)
+                    {
                         texture
                     } else {
                         normal_dummy.clone()
@@ -272,7 +307,9 @@ impl GBuffer {
                 };
 
                 let lightmap_texture = if let Some(texture) = surface.lightmap_texture() {
-                    if let Some(texture) = texture_cache.get(state, texture) {
+                    if let Some(texture) =
+                        texture_cache.get_at_distance(state, texture, distance, false)
+                    {
                         texture
                     } else {
                         white_dummy.clone()
@@ -281,6 +318,20 @@ impl GBuffer {
                     white_dummy.clone()
                 };
 
+                // Diffuse texture is used as fallback to keep old behaviour for surfaces
+                // without specular map.
+                let specular_texture = if let Some(texture) = surface.specular_texture() {
+                    if let Some(texture) =
+                        texture_cache.get_at_distance(state, texture, distance, false)
+                    {
+                        texture
+                    } else {
+                        diffuse_texture.clone()
+                    }
+                } else {
+                    diffuse_texture.clone()
+                };
+
                 statistics += self.framebuffer.draw(
                     geom_cache.get(state, &surface.data().lock().unwrap()),
                     state,
@@ -317,6 +368,13 @@ impl GBuffer {
                                 texture: lightmap_texture,
                             },
                         ),
+                        (
+                            self.shader.specular_texture,
+                            UniformValue::Sampler {
+                                index: 3,
+                                texture: specular_texture,
+                            },
+                        ),
                         (self.shader.wvp_matrix, UniformValue::Mat4(mvp)),
                         (self.shader.world_matrix, UniformValue::Mat4(world)),
                         (
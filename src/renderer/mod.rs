@@ -21,12 +21,14 @@ mod framework;
 mod blur;
 mod deferred_light_renderer;
 mod flat_shader;
+mod gamma_shader;
 mod gbuffer;
 mod light_volume;
 mod particle_system_renderer;
 mod shadow_map_renderer;
 mod sprite_renderer;
 mod ssao;
+mod texture_streaming;
 mod ui_renderer;
 
 use crate::{
@@ -42,7 +44,7 @@ use crate::{
         debug_renderer::DebugRenderer,
         deferred_light_renderer::{DeferredLightRenderer, DeferredRendererContext},
         error::RendererError,
-        flat_shader::FlatShader,
+        gamma_shader::GammaShader,
         framework::{
             framebuffer::{BackBuffer, CullFace, DrawParameters, FrameBufferTrait},
             geometry_buffer::{
@@ -51,18 +53,17 @@ use crate::{
             },
             gl,
             gpu_program::UniformValue,
-            gpu_texture::{
-                GpuTexture, GpuTextureKind, MagnificationFilter, MininificationFilter, PixelKind,
-            },
+            gpu_texture::{GpuTexture, GpuTextureKind, PixelKind},
             state::State,
         },
         gbuffer::{GBuffer, GBufferRenderContext},
         particle_system_renderer::{ParticleSystemRenderContext, ParticleSystemRenderer},
         sprite_renderer::{SpriteRenderContext, SpriteRenderer},
         surface::SurfaceSharedData,
+        texture_streaming::TextureStreamer,
         ui_renderer::{UiRenderContext, UiRenderer},
     },
-    resource::texture::{Texture, TextureKind},
+    resource::texture::{ColorSpace, Texture, TextureKind},
     scene::{node::Node, SceneContainer},
 };
 use glutin::PossiblyCurrent;
@@ -163,6 +164,17 @@ pub struct QualitySettings {
     /// Global switch to enable or disable light scattering. Each light can have
     /// its own scatter switch, but this one is able to globally disable scatter.
     pub light_scatter_enabled: bool,
+
+    /// Texture streaming
+    /// Whether to stream mip levels of textures or not. Only textures with pre-generated
+    /// mip levels (DDS, KTX2) are streamed, they're uploaded starting from small levels and
+    /// more detailed levels are uploaded when they're needed.
+    pub texture_streaming: bool,
+    /// Maximum total size in bytes of streamed textures on GPU.
+    pub texture_streaming_budget: usize,
+    /// Distance from camera up to which textures should have full resolution, every next
+    /// doubling of distance halves required resolution.
+    pub texture_streaming_distance: f32,
 }
 
 impl Default for QualitySettings {
@@ -182,6 +194,10 @@ impl Default for QualitySettings {
             ssao_radius: 0.5,
 
             light_scatter_enabled: true,
+
+            texture_streaming: false,
+            texture_streaming_budget: 512 * 1024 * 1024,
+            texture_streaming_distance: 10.0,
         }
     }
 }
@@ -240,7 +256,7 @@ pub struct Renderer {
     state: State,
     backbuffer: BackBuffer,
     deferred_light_renderer: DeferredLightRenderer,
-    flat_shader: FlatShader,
+    gamma_shader: GammaShader,
     sprite_renderer: SpriteRenderer,
     particle_system_renderer: ParticleSystemRenderer,
     /// Dummy white one pixel texture which will be used as stub when rendering
@@ -314,6 +330,10 @@ impl GeometryCache {
                         kind: AttributeKind::UnsignedByte4,
                         normalized: false,
                     },
+                    AttributeDefinition {
+                        kind: AttributeKind::UnsignedByte4,
+                        normalized: true,
+                    },
                 ])
                 .unwrap()
                 .set_vertices(data.vertices.as_slice())
@@ -344,39 +364,96 @@ impl GeometryCache {
 #[derive(Default)]
 pub(in crate) struct TextureCache {
     map: HashMap<usize, TimedEntry<Rc<RefCell<GpuTexture>>>>,
+    streamer: TextureStreamer,
+    settings: QualitySettings,
+}
+
+/// Returns color space in which texture is sampled. Textures that hold non-color data are
+/// sampled as is, regardless of their color space.
+fn sampled_color_space(texture: &Arc<Mutex<Texture>>, is_color: bool) -> ColorSpace {
+    if is_color {
+        texture.lock().unwrap().color_space
+    } else {
+        ColorSpace::Linear
+    }
+}
+
+/// Returns key of GPU copy of texture in the cache. Texture can have two GPU copies - one
+/// which is converted from sRGB on sampling and one which is sampled as is.
+fn texture_key(texture: &Arc<Mutex<Texture>>, color_space: ColorSpace) -> usize {
+    let key = (&**texture as *const _) as usize;
+    match color_space {
+        ColorSpace::Linear => key,
+        // Textures are aligned, so this can't be a key of another texture.
+        ColorSpace::Srgb => key + 1,
+    }
 }
 
 impl TextureCache {
+    /// Returns GPU copy of texture which is used as color, values of texture in sRGB color
+    /// space are converted to linear space on sampling.
     fn get(
         &mut self,
         state: &mut State,
         texture: Arc<Mutex<Texture>>,
+    ) -> Option<Rc<RefCell<GpuTexture>>> {
+        self.get_sampled(state, texture, true, false)
+    }
+
+    /// Returns GPU copy of texture which is sampled as is. Must be used for non-color data
+    /// (normal maps, lightmaps, etc.) and for images that are drawn directly to back buffer.
+    fn get_raw(
+        &mut self,
+        state: &mut State,
+        texture: Arc<Mutex<Texture>>,
+    ) -> Option<Rc<RefCell<GpuTexture>>> {
+        self.get_sampled(state, texture, false, false)
+    }
+
+    /// Uploads texture if needed. Texture is streamed only if it is `streamed` - its users
+    /// must request levels every frame, otherwise it is fully resident.
+    fn get_sampled(
+        &mut self,
+        state: &mut State,
+        texture: Arc<Mutex<Texture>>,
+        is_color: bool,
+        streamed: bool,
     ) -> Option<Rc<RefCell<GpuTexture>>> {
         scope_profile!();
 
-        if texture.lock().unwrap().loaded {
-            let key = (&*texture as *const _) as usize;
+        if texture.lock().unwrap().is_loaded() {
+            let color_space = sampled_color_space(&texture, is_color);
+            let key = texture_key(&texture, color_space);
+            let streamer = &mut self.streamer;
+            let streaming = streamed && self.settings.texture_streaming;
             let gpu_texture = self.map.entry(key).or_insert_with(move || {
-                let texture = texture.lock().unwrap();
-                let kind = GpuTextureKind::Rectangle {
-                    width: texture.width as usize,
-                    height: texture.height as usize,
+                let texture_ref = texture.lock().unwrap();
+                let base_level = if streaming && texture_streaming::is_streamable(&texture_ref) {
+                    texture_streaming::initial_level(&texture_ref)
+                } else {
+                    0
                 };
-                let mut gpu_texture = GpuTexture::new(
-                    state,
-                    kind,
-                    PixelKind::from(texture.kind),
-                    Some(texture.bytes.as_slice()),
-                )
-                .unwrap();
-                gpu_texture
-                    .bind_mut(state, 0)
-                    .generate_mip_maps()
-                    .set_minification_filter(MininificationFilter::LinearMip)
-                    .set_magnification_filter(MagnificationFilter::Linear)
-                    .set_max_anisotropy();
+                let gpu_texture = Rc::new(RefCell::new(
+                    texture_streaming::create_gpu_texture(
+                        state,
+                        &texture_ref,
+                        base_level,
+                        color_space,
+                    )
+                    .unwrap(),
+                ));
+                if base_level != 0 {
+                    streamer.register(
+                        key,
+                        &texture_ref,
+                        Arc::downgrade(&texture),
+                        gpu_texture.clone(),
+                        color_space,
+                        base_level,
+                    );
+                }
                 TimedEntry {
-                    value: Rc::new(RefCell::new(gpu_texture)),
+                    value: gpu_texture,
                     time_to_live: 20.0,
                 }
             });
@@ -388,15 +465,56 @@ impl TextureCache {
         }
     }
 
-    fn update(&mut self, dt: f32) {
+    /// Same as [get](Self::get) or [get_raw](Self::get_raw) depending on `is_color`, but also
+    /// requests mip level of streamed texture which is detailed enough for given distance from
+    /// camera.
+    fn get_at_distance(
+        &mut self,
+        state: &mut State,
+        texture: Arc<Mutex<Texture>>,
+        distance: f32,
+        is_color: bool,
+    ) -> Option<Rc<RefCell<GpuTexture>>> {
+        let key = texture_key(&texture, sampled_color_space(&texture, is_color));
+        let gpu_texture = self.get_sampled(state, texture, is_color, true);
+        self.streamer.request(key, distance, &self.settings);
+        gpu_texture
+    }
+
+    fn update(&mut self, state: &mut State, dt: f32, settings: &QualitySettings) {
         for entry in self.map.values_mut() {
             entry.time_to_live -= dt;
         }
-        self.map.retain(|_, v| v.time_to_live > 0.0);
+        let streamer = &mut self.streamer;
+        self.map.retain(|key, v| {
+            let retain = v.time_to_live > 0.0;
+            if !retain {
+                streamer.remove(*key);
+            }
+            retain
+        });
+
+        if settings.texture_streaming != self.settings.texture_streaming {
+            // Re-upload everything to start or stop streaming.
+            self.clear();
+        }
+        self.settings = *settings;
+        if settings.texture_streaming {
+            self.streamer.update(state, settings);
+        }
     }
 
     fn clear(&mut self) {
         self.map.clear();
+        self.streamer.clear();
+    }
+
+    fn unload(&mut self, texture: Arc<Mutex<Texture>>) {
+        for &color_space in [ColorSpace::Linear, ColorSpace::Srgb].iter() {
+            let key = texture_key(&texture, color_space);
+            self.map.remove(&key);
+            self.streamer.remove(key);
+        }
     }
 }
 
@@ -414,7 +532,7 @@ impl Renderer {
             backbuffer: BackBuffer,
             frame_size,
             deferred_light_renderer: DeferredLightRenderer::new(&mut state, frame_size, &settings)?,
-            flat_shader: FlatShader::new()?,
+            gamma_shader: GammaShader::new()?,
             statistics: Statistics::default(),
             sprite_renderer: SpriteRenderer::new()?,
             white_dummy: Rc::new(RefCell::new(GpuTexture::new(
@@ -515,6 +633,18 @@ impl Renderer {
         self.geometry_cache.clear();
     }
 
+    /// Returns total size in bytes of streamed textures on GPU. See
+    /// [QualitySettings::texture_streaming].
+    pub fn streamed_texture_memory(&self) -> usize {
+        self.texture_cache.streamer.resident_size()
+    }
+
+    /// Removes GPU copy of given texture, so renderer will upload it again next time it
+    /// will be used. Useful when contents of texture has changed (i.e. it was reloaded).
+    pub fn unload_texture(&mut self, texture: Arc<Mutex<Texture>>) {
+        self.texture_cache.unload(texture);
+    }
+
     fn render_frame(
         &mut self,
         scenes: &SceneContainer,
@@ -531,7 +661,8 @@ impl Renderer {
 
         // Update caches - this will remove timed out resources.
         self.geometry_cache.update(dt);
-        self.texture_cache.update(dt);
+        self.texture_cache
+            .update(&mut self.state, dt, &self.quality_settings);
 
         self.statistics.begin_frame();
 
@@ -583,15 +714,23 @@ impl Renderer {
                 // to draw something on offscreen and then draw it on some mesh.
                 // TODO: However it can be dangerous to use frame texture as it may be bound to
                 //  pipeline.
+                // Render target is in sRGB color space - it is used as color through linear
+                // frame and it is sampled as is (by UI) through gamma-corrected copy of it.
                 if let Some(rt) = scene.render_target.clone() {
-                    let key = (&*rt as *const _) as usize;
-                    self.texture_cache.map.insert(
-                        key,
-                        TimedEntry {
-                            value: gbuffer.frame_texture(),
-                            time_to_live: std::f32::INFINITY,
-                        },
-                    );
+                    for (color_space, texture) in [
+                        (ColorSpace::Srgb, gbuffer.frame_texture()),
+                        (ColorSpace::Linear, gbuffer.gamma_corrected_frame_texture()),
+                    ]
+                    .iter()
+                    {
+                        self.texture_cache.map.insert(
+                            texture_key(&rt, *color_space),
+                            TimedEntry {
+                                value: texture.clone(),
+                                time_to_live: std::f32::INFINITY,
+                            },
+                        );
+                    }
 
                     // Make sure to sync texture info with actual render target.
                     if let Ok(mut rt) = rt.lock() {
@@ -599,6 +738,7 @@ impl Renderer {
                         rt.height = gbuffer.height as u32;
                         // TODO: For now only RGBA8 textures are supported.
                         rt.kind = TextureKind::RGBA8;
+                        rt.color_space = ColorSpace::Srgb;
                     }
                 }
 
@@ -658,50 +798,53 @@ impl Renderer {
                     self.debug_renderer
                         .render(state, viewport, &mut gbuffer.final_frame, camera);
 
-                // Finally render everything into back buffer.
-                if scene.render_target.is_none() {
-                    self.statistics.geometry += self.backbuffer.draw(
-                        self.geometry_cache.get(state, &self.quad),
+                // Finally render everything into back buffer (or into gamma-corrected copy of
+                // render target), frame is in linear space and it is gamma-corrected on the way.
+                let params = DrawParameters {
+                    cull_face: CullFace::Back,
+                    culling: false,
+                    color_write: Default::default(),
+                    depth_write: true,
+                    stencil_test: false,
+                    depth_test: false,
+                    blend: false,
+                };
+                let uniforms = [
+                    (
+                        self.gamma_shader.wvp_matrix,
+                        UniformValue::Mat4({
+                            Mat4::ortho(0.0, viewport.w as f32, viewport.h as f32, 0.0, -1.0, 1.0)
+                                * Mat4::scale(Vec3::new(viewport.w as f32, viewport.h as f32, 0.0))
+                        }),
+                    ),
+                    (
+                        self.gamma_shader.frame_texture,
+                        UniformValue::Sampler {
+                            index: 0,
+                            texture: gbuffer.frame_texture(),
+                        },
+                    ),
+                ];
+                let quad = self.geometry_cache.get(state, &self.quad);
+                self.statistics.geometry += if scene.render_target.is_none() {
+                    self.backbuffer.draw(
+                        quad,
                         state,
                         viewport,
-                        &self.flat_shader.program,
-                        DrawParameters {
-                            cull_face: CullFace::Back,
-                            culling: false,
-                            color_write: Default::default(),
-                            depth_write: true,
-                            stencil_test: false,
-                            depth_test: false,
-                            blend: false,
-                        },
-                        &[
-                            (
-                                self.flat_shader.wvp_matrix,
-                                UniformValue::Mat4({
-                                    Mat4::ortho(
-                                        0.0,
-                                        viewport.w as f32,
-                                        viewport.h as f32,
-                                        0.0,
-                                        -1.0,
-                                        1.0,
-                                    ) * Mat4::scale(Vec3::new(
-                                        viewport.w as f32,
-                                        viewport.h as f32,
-                                        0.0,
-                                    ))
-                                }),
-                            ),
-                            (
-                                self.flat_shader.diffuse_texture,
-                                UniformValue::Sampler {
-                                    index: 0,
-                                    texture: gbuffer.frame_texture(),
-                                },
-                            ),
-                        ],
-                    );
-                }
+                        &self.gamma_shader.program,
+                        params,
+                        &uniforms,
+                    )
+                } else {
+                    gbuffer.gamma_corrected_frame.draw(
+                        quad,
+                        state,
+                        Rect::new(0, 0, viewport.w, viewport.h),
+                        &self.gamma_shader.program,
+                        params,
+                        &uniforms,
+                    )
+                };
             }
         }
 
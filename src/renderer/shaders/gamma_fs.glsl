@@ -0,0 +1,22 @@
+#version 330 core
+
+uniform sampler2D frameTexture;
+
+out vec4 FragColor;
+
+in vec2 texCoord;
+
+// Converts linear color to sRGB.
+vec3 linearToSrgb(vec3 color)
+{
+    vec3 clamped = clamp(color, 0.0, 1.0);
+    vec3 low = clamped * 12.92;
+    vec3 high = 1.055 * pow(clamped, vec3(1.0 / 2.4)) - 0.055;
+    return mix(high, low, vec3(lessThanEqual(clamped, vec3(0.0031308))));
+}
+
+void main()
+{
+    vec4 color = texture(frameTexture, texCoord);
+    FragColor = vec4(linearToSrgb(color.rgb), color.a);
+}
\ No newline at end of file
@@ -15,10 +15,11 @@ in vec2 texCoord;
 in vec3 tangent;
 in vec3 binormal;
 in vec2 secondTexCoord;
+in vec4 color;
 
 void main()
 {
-    outColor = diffuseColor * texture(diffuseTexture, texCoord);
+    outColor = color * diffuseColor * texture(diffuseTexture, texCoord);
     if (outColor.a < 0.5) discard;
     outColor.a = 1;
     vec4 n = normalize(texture(normalTexture, texCoord) * 2.0 - 1.0);
@@ -7,6 +7,7 @@ layout(location = 3) in vec3 vertexNormal;
 layout(location = 4) in vec4 vertexTangent;
 layout(location = 5) in vec4 boneWeights;
 layout(location = 6) in vec4 boneIndices;
+layout(location = 7) in vec4 vertexColor;
 
 uniform mat4 worldMatrix;
 uniform mat4 worldViewProjection;
@@ -18,6 +19,7 @@ out vec2 texCoord;
 out vec3 tangent;
 out vec3 binormal;
 out vec2 secondTexCoord;
+out vec4 color;
 
 void main()
 {
@@ -60,4 +62,5 @@ void main()
     binormal = normalize(vertexTangent.w * cross(tangent, normal));
     texCoord = vertexTexCoord;
     secondTexCoord = vertexSecondTexCoord;
+    color = vertexColor;
 }
\ No newline at end of file
@@ -9,6 +9,7 @@ uniform vec3 cameraUpVector;
 uniform vec3 cameraSideVector;
 uniform float size;
 uniform float rotation;
+uniform vec4 uvRect;
 
 out vec2 texCoord;
 
@@ -22,7 +23,7 @@ vec2 rotateVec2(vec2 v, float angle)
 
 void main()
 {
-    texCoord = vertexTexCoord;
+    texCoord = uvRect.xy + vertexTexCoord * uvRect.zw;
     vec2 vertexOffset = rotateVec2(vertexTexCoord * 2.0 - 1.0, rotation);
     vec4 worldPosition = worldMatrix * vec4(vertexPosition, 1.0);
     vec3 offset = (vertexOffset.x * cameraSideVector + vertexOffset.y * cameraUpVector) * size;
@@ -1,5 +1,8 @@
 use crate::{
-    core::{math::Rect, scope_profile},
+    core::{
+        math::{vec4::Vec4, Rect},
+        scope_profile,
+    },
     renderer::{
         error::RendererError,
         framework::{
@@ -26,6 +29,7 @@ struct SpriteShader {
     diffuse_texture: UniformLocation,
     size: UniformLocation,
     rotation: UniformLocation,
+    uv_rect: UniformLocation,
 }
 
 impl SpriteShader {
@@ -42,6 +46,7 @@ impl SpriteShader {
             diffuse_texture: program.uniform_location("diffuseTexture")?,
             color: program.uniform_location("color")?,
             rotation: program.uniform_location("rotation")?,
+            uv_rect: program.uniform_location("uvRect")?,
             program,
         })
     }
@@ -152,6 +157,10 @@ impl SpriteRenderer {
                     (self.shader.size, UniformValue::Float(sprite.size())),
                     (self.shader.color, UniformValue::Color(sprite.color())),
                     (self.shader.rotation, UniformValue::Float(sprite.rotation())),
+                    (self.shader.uv_rect, {
+                        let uv_rect = sprite.uv_rect();
+                        UniformValue::Vec4(Vec4::new(uv_rect.x, uv_rect.y, uv_rect.w, uv_rect.h))
+                    }),
                 ],
             );
         }
@@ -16,6 +16,7 @@ use crate::{
     utils::raw_mesh::{RawMesh, RawMeshBuilder},
 };
 use std::{
+    collections::HashMap,
     hash::{Hash, Hasher},
     sync::{Arc, Mutex},
 };
@@ -27,7 +28,7 @@ use std::{
 /// This vertex format maybe too big for some cases thus impacting performance.
 /// The ability to make your own vertices is nice to have but this is still a
 /// TODO.
-#[derive(Copy, Clone, Debug, Default)]
+#[derive(Copy, Clone, Debug)]
 #[repr(C)] // OpenGL expects this structure packed as in C
 pub struct Vertex {
     /// Position of vertex in local coordinates.
@@ -46,6 +47,25 @@ pub struct Vertex {
     /// Array of bone indices. It has indices of bones in array of bones of a
     /// surface.
     pub bone_indices: [u8; 4],
+    /// Color of vertex in RGBA format, it is multiplied with diffuse color of a
+    /// surface. Usually it is white so it won't affect final color.
+    pub color: [u8; 4],
+}
+
+impl Default for Vertex {
+    fn default() -> Self {
+        Self {
+            position: Default::default(),
+            tex_coord: Default::default(),
+            second_tex_coord: Default::default(),
+            normal: Default::default(),
+            tangent: Default::default(),
+            bone_weights: Default::default(),
+            bone_indices: Default::default(),
+            // White, so vertex color does not affect surface.
+            color: [255; 4],
+        }
+    }
 }
 
 impl Visit for Vertex {
@@ -68,6 +88,16 @@ impl Visit for Vertex {
         self.bone_indices[2].visit("BoneIndex2", visitor)?;
         self.bone_indices[3].visit("BoneIndex3", visitor)?;
 
+        // Vertex color can be missing on previous versions, such vertices must be white,
+        // otherwise they'll be transparent.
+        if self.color[0].visit("ColorR", visitor).is_err()
+            || self.color[1].visit("ColorG", visitor).is_err()
+            || self.color[2].visit("ColorB", visitor).is_err()
+            || self.color[3].visit("ColorA", visitor).is_err()
+        {
+            self.color = [255; 4];
+        }
+
         visitor.leave_region()
     }
 }
@@ -79,6 +109,7 @@ impl Vertex {
             position,
             tex_coord,
             second_tex_coord: Default::default(),
+            color: [255; 4],
             normal: Vec3::new(0.0, 1.0, 0.0),
             tangent: Vec4 {
                 x: 0.0,
@@ -100,6 +131,7 @@ impl PartialEq for Vertex {
             && self.tangent == other.tangent
             && self.bone_weights == other.bone_weights
             && self.bone_indices == other.bone_indices
+            && self.color == other.color
     }
 }
 
@@ -165,6 +197,11 @@ impl SurfaceSharedData {
         }
     }
 
+    /// Sets whether surface data is procedural, procedural data is serialized.
+    pub(in crate) fn set_procedural(&mut self, is_procedural: bool) {
+        self.is_procedural = is_procedural;
+    }
+
     /// Returns shared reference to vertices array.
     #[inline]
     pub fn get_vertices(&self) -> &[Vertex] {
@@ -272,6 +309,7 @@ impl SurfaceSharedData {
                 bone_weights: [0.0, 0.0, 0.0, 0.0],
                 bone_indices: [0, 0, 0, 0],
                 second_tex_coord: Default::default(),
+                color: [255; 4],
             },
             Vertex {
                 position: Vec3 {
@@ -294,6 +332,7 @@ impl SurfaceSharedData {
                 bone_weights: [0.0, 0.0, 0.0, 0.0],
                 bone_indices: [0, 0, 0, 0],
                 second_tex_coord: Default::default(),
+                color: [255; 4],
             },
             Vertex {
                 position: Vec3 {
@@ -316,6 +355,7 @@ impl SurfaceSharedData {
                 bone_weights: [0.0, 0.0, 0.0, 0.0],
                 bone_indices: [0, 0, 0, 0],
                 second_tex_coord: Default::default(),
+                color: [255; 4],
             },
             Vertex {
                 position: Vec3 {
@@ -338,6 +378,7 @@ impl SurfaceSharedData {
                 bone_weights: [0.0, 0.0, 0.0, 0.0],
                 bone_indices: [0, 0, 0, 0],
                 second_tex_coord: Default::default(),
+                color: [255; 4],
             },
         ];
 
@@ -372,6 +413,7 @@ impl SurfaceSharedData {
                 bone_weights: [0.0, 0.0, 0.0, 0.0],
                 bone_indices: [0, 0, 0, 0],
                 second_tex_coord: Default::default(),
+                color: [255; 4],
             },
             Vertex {
                 position: Vec3 {
@@ -394,6 +436,7 @@ impl SurfaceSharedData {
                 bone_weights: [0.0, 0.0, 0.0, 0.0],
                 bone_indices: [0, 0, 0, 0],
                 second_tex_coord: Default::default(),
+                color: [255; 4],
             },
             Vertex {
                 position: Vec3 {
@@ -416,6 +459,7 @@ impl SurfaceSharedData {
                 bone_weights: [0.0, 0.0, 0.0, 0.0],
                 bone_indices: [0, 0, 0, 0],
                 second_tex_coord: Default::default(),
+                color: [255; 4],
             },
             Vertex {
                 position: Vec3 {
@@ -438,6 +482,7 @@ impl SurfaceSharedData {
                 bone_weights: [0.0, 0.0, 0.0, 0.0],
                 bone_indices: [0, 0, 0, 0],
                 second_tex_coord: Default::default(),
+                color: [255; 4],
             },
         ];
 
@@ -470,6 +515,7 @@ impl SurfaceSharedData {
                 bone_weights: [0.0, 0.0, 0.0, 0.0],
                 bone_indices: [0, 0, 0, 0],
                 second_tex_coord: Default::default(),
+                color: [255; 4],
             },
             Vertex {
                 position: Vec3 {
@@ -492,6 +538,7 @@ impl SurfaceSharedData {
                 bone_weights: [0.0, 0.0, 0.0, 0.0],
                 bone_indices: [0, 0, 0, 0],
                 second_tex_coord: Default::default(),
+                color: [255; 4],
             },
             Vertex {
                 position: Vec3 {
@@ -514,6 +561,7 @@ impl SurfaceSharedData {
                 bone_weights: [0.0, 0.0, 0.0, 0.0],
                 bone_indices: [0, 0, 0, 0],
                 second_tex_coord: Default::default(),
+                color: [255; 4],
             },
             Vertex {
                 position: Vec3 {
@@ -536,6 +584,7 @@ impl SurfaceSharedData {
                 bone_weights: [0.0, 0.0, 0.0, 0.0],
                 bone_indices: [0, 0, 0, 0],
                 second_tex_coord: Default::default(),
+                color: [255; 4],
             },
         ];
 
@@ -573,6 +622,63 @@ impl SurfaceSharedData {
         }
     }
 
+    /// Calculates smooth normals. Faces which share vertex position are smoothed together only
+    /// if angle between them is less or equal than `smoothing_angle` (in radians), so sharp
+    /// edges are preserved. Normal of every face is weighted by its area. Vertex that is used
+    /// by several faces takes first face as reference to decide which faces to smooth with.
+    pub fn calculate_smooth_normals(&mut self, smoothing_angle: f32) {
+        let face_normals = self
+            .triangles
+            .iter()
+            .map(|triangle| {
+                let a = self.vertices[triangle[0] as usize].position;
+                let b = self.vertices[triangle[1] as usize].position;
+                let c = self.vertices[triangle[2] as usize].position;
+                (b - a).cross(&(c - a))
+            })
+            .collect::<Vec<_>>();
+
+        // Group faces by positions of their vertices, so faces with split vertices (because of
+        // different texture coordinates for example) will still be smoothed together.
+        let position_key = |v: &Vertex| {
+            [
+                v.position.x.to_bits(),
+                v.position.y.to_bits(),
+                v.position.z.to_bits(),
+            ]
+        };
+        let mut faces_by_position = HashMap::<[u32; 3], Vec<usize>>::new();
+        let mut reference_face = vec![None; self.vertices.len()];
+        for (face_index, triangle) in self.triangles.iter().enumerate() {
+            for &index in triangle.indices() {
+                faces_by_position
+                    .entry(position_key(&self.vertices[index as usize]))
+                    .or_default()
+                    .push(face_index);
+                reference_face[index as usize].get_or_insert(face_index);
+            }
+        }
+
+        let cos_threshold = smoothing_angle.cos();
+        for (vertex, reference) in self.vertices.iter_mut().zip(reference_face) {
+            let reference = match reference {
+                Some(reference) => face_normals[reference].normalized().unwrap_or(Vec3::UP),
+                // Vertex is not used by any face.
+                None => continue,
+            };
+            let mut sum = Vec3::ZERO;
+            for &face in faces_by_position[&position_key(vertex)].iter() {
+                let normal = face_normals[face];
+                if let Some(direction) = normal.normalized() {
+                    if direction.dot(&reference) >= cos_threshold {
+                        sum += normal;
+                    }
+                }
+            }
+            vertex.normal = sum.normalized().unwrap_or(reference);
+        }
+    }
+
     /// Creates sphere of specified radius with given slices and stacks.
     pub fn make_sphere(slices: usize, stacks: usize, r: f32) -> Self {
         let mut builder = RawMeshBuilder::<Vertex>::new(stacks * slices, stacks * slices * 3);
@@ -799,6 +905,7 @@ impl SurfaceSharedData {
                 bone_weights: [0.0, 0.0, 0.0, 0.0],
                 bone_indices: [0, 0, 0, 0],
                 second_tex_coord: Default::default(),
+                color: [255; 4],
             },
             Vertex {
                 position: Vec3 {
@@ -821,6 +928,7 @@ impl SurfaceSharedData {
                 bone_weights: [0.0, 0.0, 0.0, 0.0],
                 bone_indices: [0, 0, 0, 0],
                 second_tex_coord: Default::default(),
+                color: [255; 4],
             },
             Vertex {
                 position: Vec3 {
@@ -843,6 +951,7 @@ impl SurfaceSharedData {
                 bone_weights: [0.0, 0.0, 0.0, 0.0],
                 bone_indices: [0, 0, 0, 0],
                 second_tex_coord: Default::default(),
+                color: [255; 4],
             },
             Vertex {
                 position: Vec3 {
@@ -865,6 +974,7 @@ impl SurfaceSharedData {
                 bone_weights: [0.0, 0.0, 0.0, 0.0],
                 bone_indices: [0, 0, 0, 0],
                 second_tex_coord: Default::default(),
+                color: [255; 4],
             },
             // Back
             Vertex {
@@ -888,6 +998,7 @@ impl SurfaceSharedData {
                 bone_weights: [0.0, 0.0, 0.0, 0.0],
                 bone_indices: [0, 0, 0, 0],
                 second_tex_coord: Default::default(),
+                color: [255; 4],
             },
             Vertex {
                 position: Vec3 {
@@ -910,6 +1021,7 @@ impl SurfaceSharedData {
                 bone_weights: [0.0, 0.0, 0.0, 0.0],
                 bone_indices: [0, 0, 0, 0],
                 second_tex_coord: Default::default(),
+                color: [255; 4],
             },
             Vertex {
                 position: Vec3 {
@@ -932,6 +1044,7 @@ impl SurfaceSharedData {
                 bone_weights: [0.0, 0.0, 0.0, 0.0],
                 bone_indices: [0, 0, 0, 0],
                 second_tex_coord: Default::default(),
+                color: [255; 4],
             },
             Vertex {
                 position: Vec3 {
@@ -954,6 +1067,7 @@ impl SurfaceSharedData {
                 bone_weights: [0.0, 0.0, 0.0, 0.0],
                 bone_indices: [0, 0, 0, 0],
                 second_tex_coord: Default::default(),
+                color: [255; 4],
             },
             // Left
             Vertex {
@@ -977,6 +1091,7 @@ impl SurfaceSharedData {
                 bone_weights: [0.0, 0.0, 0.0, 0.0],
                 bone_indices: [0, 0, 0, 0],
                 second_tex_coord: Default::default(),
+                color: [255; 4],
             },
             Vertex {
                 position: Vec3 {
@@ -999,6 +1114,7 @@ impl SurfaceSharedData {
                 bone_weights: [0.0, 0.0, 0.0, 0.0],
                 bone_indices: [0, 0, 0, 0],
                 second_tex_coord: Default::default(),
+                color: [255; 4],
             },
             Vertex {
                 position: Vec3 {
@@ -1021,6 +1137,7 @@ impl SurfaceSharedData {
                 bone_weights: [0.0, 0.0, 0.0, 0.0],
                 bone_indices: [0, 0, 0, 0],
                 second_tex_coord: Default::default(),
+                color: [255; 4],
             },
             Vertex {
                 position: Vec3 {
@@ -1043,6 +1160,7 @@ impl SurfaceSharedData {
                 bone_weights: [0.0, 0.0, 0.0, 0.0],
                 bone_indices: [0, 0, 0, 0],
                 second_tex_coord: Default::default(),
+                color: [255; 4],
             },
             // Right
             Vertex {
@@ -1066,6 +1184,7 @@ impl SurfaceSharedData {
                 bone_weights: [0.0, 0.0, 0.0, 0.0],
                 bone_indices: [0, 0, 0, 0],
                 second_tex_coord: Default::default(),
+                color: [255; 4],
             },
             Vertex {
                 position: Vec3 {
@@ -1088,6 +1207,7 @@ impl SurfaceSharedData {
                 bone_weights: [0.0, 0.0, 0.0, 0.0],
                 bone_indices: [0, 0, 0, 0],
                 second_tex_coord: Default::default(),
+                color: [255; 4],
             },
             Vertex {
                 position: Vec3 {
@@ -1110,6 +1230,7 @@ impl SurfaceSharedData {
                 bone_weights: [0.0, 0.0, 0.0, 0.0],
                 bone_indices: [0, 0, 0, 0],
                 second_tex_coord: Default::default(),
+                color: [255; 4],
             },
             Vertex {
                 position: Vec3 {
@@ -1132,6 +1253,7 @@ impl SurfaceSharedData {
                 bone_weights: [0.0, 0.0, 0.0, 0.0],
                 bone_indices: [0, 0, 0, 0],
                 second_tex_coord: Default::default(),
+                color: [255; 4],
             },
             // Top
             Vertex {
@@ -1155,6 +1277,7 @@ impl SurfaceSharedData {
                 bone_weights: [0.0, 0.0, 0.0, 0.0],
                 bone_indices: [0, 0, 0, 0],
                 second_tex_coord: Default::default(),
+                color: [255; 4],
             },
             Vertex {
                 position: Vec3 {
@@ -1177,6 +1300,7 @@ impl SurfaceSharedData {
                 bone_weights: [0.0, 0.0, 0.0, 0.0],
                 bone_indices: [0, 0, 0, 0],
                 second_tex_coord: Default::default(),
+                color: [255; 4],
             },
             Vertex {
                 position: Vec3 {
@@ -1199,6 +1323,7 @@ impl SurfaceSharedData {
                 bone_weights: [0.0, 0.0, 0.0, 0.0],
                 bone_indices: [0, 0, 0, 0],
                 second_tex_coord: Default::default(),
+                color: [255; 4],
             },
             Vertex {
                 position: Vec3 {
@@ -1221,6 +1346,7 @@ impl SurfaceSharedData {
                 bone_weights: [0.0, 0.0, 0.0, 0.0],
                 bone_indices: [0, 0, 0, 0],
                 second_tex_coord: Default::default(),
+                color: [255; 4],
             },
             // Bottom
             Vertex {
@@ -1244,6 +1370,7 @@ impl SurfaceSharedData {
                 bone_weights: [0.0, 0.0, 0.0, 0.0],
                 bone_indices: [0, 0, 0, 0],
                 second_tex_coord: Default::default(),
+                color: [255; 4],
             },
             Vertex {
                 position: Vec3 {
@@ -1266,6 +1393,7 @@ impl SurfaceSharedData {
                 bone_weights: [0.0, 0.0, 0.0, 0.0],
                 bone_indices: [0, 0, 0, 0],
                 second_tex_coord: Default::default(),
+                color: [255; 4],
             },
             Vertex {
                 position: Vec3 {
@@ -1288,6 +1416,7 @@ impl SurfaceSharedData {
                 bone_weights: [0.0, 0.0, 0.0, 0.0],
                 bone_indices: [0, 0, 0, 0],
                 second_tex_coord: Default::default(),
+                color: [255; 4],
             },
             Vertex {
                 position: Vec3 {
@@ -1310,6 +1439,7 @@ impl SurfaceSharedData {
                 bone_weights: [0.0, 0.0, 0.0, 0.0],
                 bone_indices: [0, 0, 0, 0],
                 second_tex_coord: Default::default(),
+                color: [255; 4],
             },
         ];
 
@@ -1454,6 +1584,7 @@ pub struct Surface {
     diffuse_texture: Option<Arc<Mutex<Texture>>>,
     normal_texture: Option<Arc<Mutex<Texture>>>,
     lightmap_texture: Option<Arc<Mutex<Texture>>>,
+    specular_texture: Option<Arc<Mutex<Texture>>>,
     /// Temporal array for FBX conversion needs, it holds skinning data (weight + bone handle)
     /// and will be used to fill actual bone indices and weight in vertices that will be
     /// sent to GPU. The idea is very simple: GPU needs to know only indices of matrices of
@@ -1482,6 +1613,7 @@ impl Clone for Surface {
             vertex_weights: Vec::new(), // Intentionally not copied.
             color: self.color,
             lightmap_texture: self.lightmap_texture.clone(),
+            specular_texture: self.specular_texture.clone(),
         }
     }
 }
@@ -1498,6 +1630,7 @@ impl Surface {
             vertex_weights: Vec::new(),
             color: Color::WHITE,
             lightmap_texture: None,
+            specular_texture: None,
         }
     }
 
@@ -1543,6 +1676,19 @@ impl Surface {
         self.lightmap_texture.clone()
     }
 
+    /// Sets new specular map texture. Specular map defines intensity of specular highlights
+    /// per texel, only red channel is used.
+    #[inline]
+    pub fn set_specular_texture(&mut self, tex: Arc<Mutex<Texture>>) {
+        self.specular_texture = Some(tex);
+    }
+
+    /// Returns specular map texture.
+    #[inline]
+    pub fn specular_texture(&self) -> Option<Arc<Mutex<Texture>>> {
+        self.specular_texture.clone()
+    }
+
     /// Sets color of surface.
     #[inline]
     pub fn set_color(&mut self, color: Color) {
@@ -1576,6 +1722,7 @@ impl Visit for Surface {
         // Try to get lightmap texture but don't care if it is missing, it can
         // be missing on previous versions.
         let _ = self.lightmap_texture.visit("LightmapTexture", visitor);
+        let _ = self.specular_texture.visit("SpecularTexture", visitor);
 
         visitor.leave_region()
     }
@@ -1587,6 +1734,7 @@ pub struct SurfaceBuilder {
     diffuse_texture: Option<Arc<Mutex<Texture>>>,
     normal_texture: Option<Arc<Mutex<Texture>>>,
     lightmap_texture: Option<Arc<Mutex<Texture>>>,
+    specular_texture: Option<Arc<Mutex<Texture>>>,
     bones: Vec<Handle<Node>>,
     color: Color,
 }
@@ -1599,6 +1747,7 @@ impl SurfaceBuilder {
             diffuse_texture: None,
             normal_texture: None,
             lightmap_texture: None,
+            specular_texture: None,
             bones: Default::default(),
             color: Color::WHITE,
         }
@@ -1622,6 +1771,12 @@ impl SurfaceBuilder {
         self
     }
 
+    /// Sets desired specular map texture.
+    pub fn with_specular_texture(mut self, tex: Arc<Mutex<Texture>>) -> Self {
+        self.specular_texture = Some(tex);
+        self
+    }
+
     /// Sets desired color of surface.
     pub fn with_color(mut self, color: Color) -> Self {
         self.color = color;
@@ -1641,6 +1796,7 @@ impl SurfaceBuilder {
             diffuse_texture: self.diffuse_texture,
             normal_texture: self.normal_texture,
             lightmap_texture: self.lightmap_texture,
+            specular_texture: self.specular_texture,
             vertex_weights: Default::default(),
             bones: self.bones,
             color: self.color,
@@ -0,0 +1,397 @@
+//! Progressive streaming of mip levels of textures.
+//!
+//! Textures with pre-generated mip chains (DDS, KTX2) do not need to be fully resident on GPU -
+//! distant surfaces are sampled from small mip levels anyway. When streaming is enabled, such
+//! textures are uploaded starting from a small mip level, then renderer requests levels that
+//! are needed for distance from camera to surfaces that use textures, and more detailed levels
+//! are uploaded few textures per frame. Levels that are not needed anymore are evicted, and if
+//! total size of streamed textures exceeds the budget, most detailed levels are evicted first.
+//!
+//! Textures without mip chains (png, jpg, etc.) and cube maps are always fully resident.
+
+use crate::{
+    renderer::{
+        error::RendererError,
+        framework::{
+            gpu_texture::{
+                GpuTexture, GpuTextureKind, MagnificationFilter, MininificationFilter, PixelKind,
+            },
+            state::State,
+        },
+        QualitySettings,
+    },
+    resource::texture::{ColorSpace, Texture, TextureType},
+};
+use std::{
+    cell::RefCell,
+    collections::HashMap,
+    rc::Rc,
+    sync::{Mutex, Weak},
+};
+
+/// Largest side of a mip level that is uploaded first.
+const INITIAL_LEVEL_SIZE: u32 = 64;
+
+/// Maximum amount of textures that get more detailed level per frame, uploading is not free
+/// and should be spread across frames.
+const MAX_UPLOADS_PER_FRAME: usize = 4;
+
+/// Returns true if texture can be streamed.
+pub(in crate) fn is_streamable(texture: &Texture) -> bool {
+    texture.mip_count > 1 && texture.texture_type == TextureType::Rectangle
+}
+
+/// Returns first mip level to upload, it is the most detailed level which fits in
+/// [INITIAL_LEVEL_SIZE].
+pub(in crate) fn initial_level(texture: &Texture) -> usize {
+    let mut level = 0;
+    while level + 1 < texture.mip_count
+        && texture.width.max(texture.height) >> level > INITIAL_LEVEL_SIZE
+    {
+        level += 1;
+    }
+    level as usize
+}
+
+/// Returns mip level which is detailed enough for given distance. Full resolution is needed
+/// up to `full_detail_distance`, every next doubling of distance halves required resolution.
+fn level_for_distance(distance: f32, full_detail_distance: f32, mip_count: usize) -> usize {
+    if full_detail_distance <= 0.0 || distance <= full_detail_distance {
+        0
+    } else {
+        ((distance / full_detail_distance).log2().floor() as usize).min(mip_count - 1)
+    }
+}
+
+/// Returns offset in bytes of given mip level and size in bytes of levels starting from it.
+fn level_range(texture: &Texture, base_level: usize) -> (usize, usize) {
+    let mut offset = 0;
+    let mut size = 0;
+    for level in 0..texture.mip_count {
+        let level_size = texture.kind.level_size(
+            (texture.width >> level).max(1),
+            (texture.height >> level).max(1),
+        ) as usize;
+        if (level as usize) < base_level {
+            offset += level_size;
+        } else {
+            size += level_size;
+        }
+    }
+    (offset, size)
+}
+
+/// Creates GPU copy of texture with mip levels starting from given one. Color space defines
+/// how pixels are sampled, it can differ from color space of the texture when texture is
+/// used as non-color data.
+pub(in crate) fn create_gpu_texture(
+    state: &mut State,
+    texture: &Texture,
+    base_level: usize,
+    color_space: ColorSpace,
+) -> Result<GpuTexture, RendererError> {
+    // Levels of cube maps are interleaved with faces, so they're always uploaded fully.
+    let base_level = if texture.texture_type == TextureType::Cube {
+        0
+    } else {
+        base_level.min(texture.mip_count.max(1) as usize - 1)
+    };
+    let (width, height) = (
+        (texture.width as usize >> base_level).max(1),
+        (texture.height as usize >> base_level).max(1),
+    );
+    let kind = match texture.texture_type {
+        TextureType::Rectangle => GpuTextureKind::Rectangle { width, height },
+        TextureType::Cube => GpuTextureKind::Cube { width, height },
+    };
+    let mip_count = texture.mip_count as usize - base_level;
+    let (offset, size) = level_range(texture, base_level);
+    let pixel_kind = PixelKind::from_texture(texture.kind, color_space);
+    let mut gpu_texture = GpuTexture::with_mips(
+        state,
+        kind,
+        pixel_kind,
+        mip_count,
+        Some(if base_level == 0 {
+            texture.bytes.as_slice()
+        } else {
+            &texture.bytes[offset..(offset + size)]
+        }),
+    )?;
+    let mut binding = gpu_texture.bind_mut(state, 0);
+    // Mips cannot be generated for compressed textures, such textures must come
+    // with pre-generated mips, otherwise they'll look aliased on distance.
+    let has_mips = mip_count > 1 || !pixel_kind.is_compressed();
+    if mip_count == 1 && has_mips {
+        binding = binding.generate_mip_maps();
+    }
+    binding
+        .set_minification_filter(if has_mips {
+            MininificationFilter::LinearMip
+        } else {
+            MininificationFilter::Linear
+        })
+        .set_magnification_filter(MagnificationFilter::Linear)
+        .set_max_anisotropy();
+    Ok(gpu_texture)
+}
+
+/// Mip levels of streamed texture, streaming decisions are made using only these.
+struct StreamedLevels {
+    mip_count: usize,
+    /// Most detailed level which is resident on GPU.
+    resident_level: usize,
+    /// Most detailed level which was requested since last update. Texture that was not
+    /// requested (not drawn in last frame) keeps its current level.
+    requested_level: Option<usize>,
+    /// Size in bytes of every level.
+    level_sizes: Vec<usize>,
+}
+
+impl StreamedLevels {
+    fn new(texture: &Texture, resident_level: usize) -> Self {
+        Self {
+            mip_count: texture.mip_count as usize,
+            resident_level,
+            requested_level: None,
+            level_sizes: (0..texture.mip_count)
+                .map(|level| {
+                    texture.kind.level_size(
+                        (texture.width >> level).max(1),
+                        (texture.height >> level).max(1),
+                    ) as usize
+                })
+                .collect(),
+        }
+    }
+
+    fn resident_size(&self) -> usize {
+        self.level_sizes[self.resident_level..].iter().sum()
+    }
+
+    fn request(&mut self, level: usize) {
+        self.requested_level = Some(self.requested_level.map_or(level, |l| l.min(level)));
+    }
+
+    /// Returns level to which texture must be evicted because more detailed levels are not
+    /// needed anymore.
+    fn level_to_evict(&self) -> Option<usize> {
+        self.requested_level
+            .filter(|&requested| self.resident_level < requested)
+    }
+
+    /// Returns next more detailed level that must be uploaded.
+    fn level_to_upload(&self) -> Option<usize> {
+        self.requested_level
+            .filter(|&requested| requested < self.resident_level)
+            .map(|_| self.resident_level - 1)
+    }
+}
+
+struct StreamedTexture {
+    texture: Weak<Mutex<Texture>>,
+    gpu_texture: Rc<RefCell<GpuTexture>>,
+    color_space: ColorSpace,
+    levels: StreamedLevels,
+}
+
+impl StreamedTexture {
+    fn resident_size(&self) -> usize {
+        self.levels.resident_size()
+    }
+
+    /// Re-creates GPU texture with levels starting from given one. Users of the texture hold
+    /// same shared GPU texture, so they will get new one automatically.
+    fn set_resident_level(&mut self, state: &mut State, level: usize) -> bool {
+        let texture = match self.texture.upgrade() {
+            Some(texture) => texture,
+            None => return false,
+        };
+        let texture = texture.lock().unwrap();
+        match create_gpu_texture(state, &texture, level, self.color_space) {
+            Ok(gpu_texture) => {
+                *self.gpu_texture.borrow_mut() = gpu_texture;
+                self.levels.resident_level = level;
+                true
+            }
+            Err(_) => false,
+        }
+    }
+}
+
+#[derive(Default)]
+pub(in crate) struct TextureStreamer {
+    textures: HashMap<usize, StreamedTexture>,
+}
+
+impl TextureStreamer {
+    /// Starts streaming of texture that was uploaded from given level. Texture must be
+    /// requested every frame when it is drawn, see [request](Self::request).
+    pub(in crate) fn register(
+        &mut self,
+        key: usize,
+        texture: &Texture,
+        texture_ref: Weak<Mutex<Texture>>,
+        gpu_texture: Rc<RefCell<GpuTexture>>,
+        color_space: ColorSpace,
+        resident_level: usize,
+    ) {
+        self.textures.insert(
+            key,
+            StreamedTexture {
+                texture: texture_ref,
+                gpu_texture,
+                color_space,
+                levels: StreamedLevels::new(texture, resident_level),
+            },
+        );
+    }
+
+    /// Requests level of a texture which is detailed enough for given distance from camera.
+    pub(in crate) fn request(&mut self, key: usize, distance: f32, settings: &QualitySettings) {
+        if let Some(streamed) = self.textures.get_mut(&key) {
+            let level = level_for_distance(
+                distance,
+                settings.texture_streaming_distance,
+                streamed.levels.mip_count,
+            );
+            streamed.levels.request(level);
+        }
+    }
+
+    pub(in crate) fn remove(&mut self, key: usize) {
+        self.textures.remove(&key);
+    }
+
+    pub(in crate) fn clear(&mut self) {
+        self.textures.clear();
+    }
+
+    /// Returns total size in bytes of resident levels of streamed textures.
+    pub(in crate) fn resident_size(&self) -> usize {
+        self.textures.values().map(|t| t.resident_size()).sum()
+    }
+
+    /// Uploads requested levels and evicts levels that are not needed anymore. Must be
+    /// called once per frame, requests are reset after update.
+    pub(in crate) fn update(&mut self, state: &mut State, settings: &QualitySettings) {
+        // Textures which were destroyed by their owners can't be streamed anymore.
+        self.textures
+            .retain(|_, streamed| streamed.texture.strong_count() > 0);
+
+        // Evict levels that are more detailed than requested ones.
+        for streamed in self.textures.values_mut() {
+            if let Some(level) = streamed.levels.level_to_evict() {
+                streamed.set_resident_level(state, level);
+            }
+        }
+
+        // Stay within budget - evict most detailed levels of largest textures first.
+        let mut total_size = self.resident_size();
+        while total_size > settings.texture_streaming_budget {
+            let largest = self
+                .textures
+                .values_mut()
+                .filter(|streamed| streamed.levels.resident_level + 1 < streamed.levels.mip_count)
+                .max_by_key(|streamed| streamed.levels.level_sizes[streamed.levels.resident_level]);
+            match largest {
+                Some(streamed) => {
+                    let old_size = streamed.resident_size();
+                    let level = streamed.levels.resident_level + 1;
+                    if !streamed.set_resident_level(state, level) {
+                        break;
+                    }
+                    total_size = total_size - old_size + streamed.resident_size();
+                }
+                None => break,
+            }
+        }
+
+        // Upload more detailed levels, textures that are closest to their requested levels go
+        // first, so the most visible textures get sharp faster.
+        let mut candidates = self
+            .textures
+            .values_mut()
+            .filter_map(|streamed| Some((streamed.levels.level_to_upload()?, streamed)))
+            .collect::<Vec<_>>();
+        candidates.sort_by_key(|(_, streamed)| streamed.levels.requested_level);
+        // One level at a time, this keeps uploads small.
+        for (level, streamed) in candidates.into_iter().take(MAX_UPLOADS_PER_FRAME) {
+            let grow = streamed.levels.level_sizes[level];
+            if total_size + grow > settings.texture_streaming_budget {
+                continue;
+            }
+            if streamed.set_resident_level(state, level) {
+                total_size += grow;
+            }
+        }
+
+        for streamed in self.textures.values_mut() {
+            streamed.levels.requested_level = None;
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        renderer::texture_streaming::{
+            initial_level, level_for_distance, level_range, StreamedLevels,
+        },
+        resource::texture::{Texture, TextureKind},
+    };
+
+    #[test]
+    fn level_for_distance_test() {
+        assert_eq!(level_for_distance(5.0, 10.0, 8), 0);
+        assert_eq!(level_for_distance(20.0, 10.0, 8), 1);
+        assert_eq!(level_for_distance(45.0, 10.0, 8), 2);
+        assert_eq!(level_for_distance(10000.0, 10.0, 8), 7);
+        // Zero distance disables streaming by distance.
+        assert_eq!(level_for_distance(10000.0, 0.0, 8), 0);
+    }
+
+    #[test]
+    fn levels_test() {
+        let texture = Texture {
+            width: 256,
+            height: 128,
+            kind: TextureKind::RGBA8,
+            mip_count: 4,
+            ..Default::default()
+        };
+        // 256x128, 128x64, 64x32, 32x16
+        assert_eq!(initial_level(&texture), 2);
+        assert_eq!(
+            level_range(&texture, 0),
+            (0, 4 * (32768 + 8192 + 2048 + 512))
+        );
+        assert_eq!(
+            level_range(&texture, 2),
+            (4 * (32768 + 8192), 4 * (2048 + 512))
+        );
+    }
+
+    #[test]
+    fn not_requested_texture_keeps_level_test() {
+        let texture = Texture {
+            width: 256,
+            height: 256,
+            kind: TextureKind::RGBA8,
+            mip_count: 9,
+            ..Default::default()
+        };
+        let mut levels = StreamedLevels::new(&texture, 2);
+        // Texture that is not drawn (UI image, hidden mesh) must not collapse to the smallest
+        // level and must not grow either.
+        assert_eq!(levels.level_to_evict(), None);
+        assert_eq!(levels.level_to_upload(), None);
+
+        levels.request(5);
+        levels.request(4);
+        assert_eq!(levels.level_to_evict(), Some(4));
+        levels.request(0);
+        assert_eq!(levels.level_to_evict(), None);
+        assert_eq!(levels.level_to_upload(), Some(1));
+    }
+}
@@ -188,7 +188,7 @@ impl UiRenderer {
                                     font.texture = Some(SharedTexture(Arc::new(Mutex::new(tex))));
                                 }
                             }
-                            if let Some(texture) = texture_cache.get(
+                            if let Some(texture) = texture_cache.get_raw(
                                 state,
                                 font.texture
                                     .clone()
@@ -203,7 +203,7 @@ impl UiRenderer {
                         }
                         CommandTexture::Texture(texture) => {
                             if let Ok(texture) = texture.clone().0.downcast::<Mutex<Texture>>() {
-                                if let Some(texture) = texture_cache.get(state, texture) {
+                                if let Some(texture) = texture_cache.get_raw(state, texture) {
                                     diffuse_texture = texture;
                                 }
                             }
@@ -0,0 +1,433 @@
+//! Texture atlas combines many small images into few big textures (pages).
+//!
+//! Each texture bind is a state change for GPU, scenes with lots of sprites or UIs with lots
+//! of small images will benefit from atlases because many objects can be drawn with the same
+//! texture. Atlas can be built at runtime from loaded textures or offline (in a build script
+//! or a tool) and saved to disk together with its description.
+//!
+//! # Usage
+//!
+//! ```no_run
+//! use rg3d::resource::atlas::AtlasBuilder;
+//! # use rg3d::scene::Scene;
+//! # use rg3d::resource::texture::Texture;
+//! # use std::sync::{Arc, Mutex};
+//! # let mut scene = Scene::new();
+//! # let textures: Vec<Arc<Mutex<Texture>>> = Vec::new();
+//!
+//! let mut builder = AtlasBuilder::new(1024);
+//! for texture in textures {
+//!     builder.add_texture(texture);
+//! }
+//! let atlas = builder.build().unwrap();
+//! // Every sprite in the scene that uses one of the textures will now use atlas page.
+//! atlas.remap_sprites(&mut scene.graph);
+//! ```
+//!
+//! UI images should use [TextureAtlas::region] to get page and uv rectangle of an image.
+
+use crate::{
+    core::{
+        math::Rect,
+        visitor::{Visit, VisitResult, Visitor},
+    },
+    resource::texture::{Texture, TextureKind, TextureType},
+    scene::{graph::Graph, node::Node},
+};
+use std::{
+    fmt::Formatter,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+};
+
+/// All possible errors that can occur during atlas building.
+#[derive(Debug)]
+pub enum AtlasError {
+    /// Texture does not fit in a page. Contains path of texture.
+    TooLarge(PathBuf),
+    /// Texture is not loaded yet or failed to load. Contains path of texture.
+    NotLoaded(PathBuf),
+    /// Texture is compressed or is a cube map. Contains path of texture.
+    UnsupportedFormat(PathBuf),
+    /// Unable to save a page.
+    Image(image::ImageError),
+}
+
+impl std::fmt::Display for AtlasError {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), std::fmt::Error> {
+        match self {
+            AtlasError::TooLarge(path) => write!(f, "Texture {:?} does not fit in a page", path),
+            AtlasError::NotLoaded(path) => write!(f, "Texture {:?} is not loaded", path),
+            AtlasError::UnsupportedFormat(path) => {
+                write!(f, "Texture {:?} has unsupported format", path)
+            }
+            AtlasError::Image(e) => write!(f, "Image error: {}", e),
+        }
+    }
+}
+
+impl From<image::ImageError> for AtlasError {
+    fn from(e: image::ImageError) -> Self {
+        AtlasError::Image(e)
+    }
+}
+
+/// Region of a source image in an atlas.
+#[derive(Clone, Debug)]
+pub struct AtlasRegion {
+    source: PathBuf,
+    page: u32,
+    pixel_rect: Rect<u32>,
+    uv_rect: Rect<f32>,
+}
+
+impl Default for AtlasRegion {
+    fn default() -> Self {
+        Self {
+            source: Default::default(),
+            page: 0,
+            pixel_rect: Rect::new(0, 0, 0, 0),
+            uv_rect: Rect::new(0.0, 0.0, 1.0, 1.0),
+        }
+    }
+}
+
+impl AtlasRegion {
+    /// Returns path of source image.
+    pub fn source(&self) -> &Path {
+        &self.source
+    }
+
+    /// Returns index of page where image is located.
+    pub fn page(&self) -> usize {
+        self.page as usize
+    }
+
+    /// Returns rectangle of image in pixels on the page.
+    pub fn pixel_rect(&self) -> Rect<u32> {
+        self.pixel_rect
+    }
+
+    /// Returns rectangle of image in normalized texture coordinates of the page.
+    pub fn uv_rect(&self) -> Rect<f32> {
+        self.uv_rect
+    }
+}
+
+impl Visit for AtlasRegion {
+    fn visit(&mut self, name: &str, visitor: &mut Visitor) -> VisitResult {
+        visitor.enter_region(name)?;
+
+        self.source.visit("Source", visitor)?;
+        self.page.visit("Page", visitor)?;
+        self.pixel_rect.x.visit("X", visitor)?;
+        self.pixel_rect.y.visit("Y", visitor)?;
+        self.pixel_rect.w.visit("W", visitor)?;
+        self.pixel_rect.h.visit("H", visitor)?;
+        self.uv_rect.x.visit("U", visitor)?;
+        self.uv_rect.y.visit("V", visitor)?;
+        self.uv_rect.w.visit("UvW", visitor)?;
+        self.uv_rect.h.visit("UvH", visitor)?;
+
+        visitor.leave_region()
+    }
+}
+
+/// See module docs.
+#[derive(Default, Debug)]
+pub struct TextureAtlas {
+    pages: Vec<Option<Arc<Mutex<Texture>>>>,
+    regions: Vec<AtlasRegion>,
+}
+
+impl TextureAtlas {
+    /// Returns page and region of image that was loaded from given path.
+    pub fn region<P: AsRef<Path>>(&self, source: P) -> Option<(Arc<Mutex<Texture>>, &AtlasRegion)> {
+        let region = self.regions.iter().find(|r| r.source == source.as_ref())?;
+        let page = self.pages.get(region.page())?.clone()?;
+        Some((page, region))
+    }
+
+    /// Returns list of pages.
+    pub fn pages(&self) -> impl Iterator<Item = Arc<Mutex<Texture>>> + '_ {
+        self.pages.iter().filter_map(|p| p.clone())
+    }
+
+    /// Returns list of regions of every image in the atlas.
+    pub fn regions(&self) -> &[AtlasRegion] {
+        &self.regions
+    }
+
+    /// Replaces texture of every sprite in the graph which uses one of atlas images with
+    /// atlas page and sets uv rectangle of the sprite. Returns amount of remapped sprites.
+    pub fn remap_sprites(&self, graph: &mut Graph) -> usize {
+        let mut count = 0;
+        for node in graph.linear_iter_mut() {
+            if let Node::Sprite(sprite) = node {
+                let source = match sprite.texture() {
+                    Some(texture) => texture.lock().unwrap().path.clone(),
+                    None => continue,
+                };
+                if let Some((page, region)) = self.region(source) {
+                    sprite.set_texture(page);
+                    sprite.set_uv_rect(region.uv_rect);
+                    count += 1;
+                }
+            }
+        }
+        count
+    }
+
+    /// Saves every page as PNG image in given directory with names `<name>_<index>.png`.
+    /// Pages will remember new paths, so atlas can be saved using [Visit] and then
+    /// restored - pages will be loaded by resource manager as usual textures.
+    pub fn save_pages<P: AsRef<Path>>(&self, dir: P, name: &str) -> Result<(), AtlasError> {
+        for (i, page) in self.pages().enumerate() {
+            let mut page = page.lock().unwrap();
+            let path = dir.as_ref().join(format!("{}_{}.png", name, i));
+            page.set_path(&path);
+            page.save()?;
+        }
+        Ok(())
+    }
+}
+
+impl Visit for TextureAtlas {
+    fn visit(&mut self, name: &str, visitor: &mut Visitor) -> VisitResult {
+        visitor.enter_region(name)?;
+
+        self.pages.visit("Pages", visitor)?;
+        self.regions.visit("Regions", visitor)?;
+
+        visitor.leave_region()
+    }
+}
+
+struct Shelf {
+    y: u32,
+    height: u32,
+    x: u32,
+}
+
+struct Page {
+    bytes: Vec<u8>,
+    shelves: Vec<Shelf>,
+}
+
+impl Page {
+    fn new(size: u32) -> Self {
+        Self {
+            bytes: vec![0; (size * size * 4) as usize],
+            shelves: Vec::new(),
+        }
+    }
+
+    /// Tries to find place for a rectangle of given size using shelf packing - images are
+    /// placed in rows, new row is started when image does not fit into existing ones.
+    fn allocate(&mut self, size: u32, width: u32, height: u32) -> Option<(u32, u32)> {
+        for shelf in self.shelves.iter_mut() {
+            if height <= shelf.height && shelf.x + width <= size {
+                let position = (shelf.x, shelf.y);
+                shelf.x += width;
+                return Some(position);
+            }
+        }
+        let y = self.shelves.last().map_or(0, |s| s.y + s.height);
+        if y + height <= size && width <= size {
+            self.shelves.push(Shelf {
+                y,
+                height,
+                x: width,
+            });
+            Some((0, y))
+        } else {
+            None
+        }
+    }
+
+    fn blit(&mut self, size: u32, x: u32, y: u32, width: u32, rgba: &[u8]) {
+        for (row, src) in rgba.chunks(width as usize * 4).enumerate() {
+            let offset = (((y + row as u32) * size + x) * 4) as usize;
+            self.bytes[offset..(offset + src.len())].copy_from_slice(src);
+        }
+    }
+}
+
+fn to_rgba(texture: &Texture) -> Option<Vec<u8>> {
+    if texture.texture_type != TextureType::Rectangle {
+        return None;
+    }
+    // Take only first mip level.
+    let bytes = &texture.bytes[..texture.kind.level_size(texture.width, texture.height) as usize];
+    Some(match texture.kind {
+        TextureKind::R8 => bytes.iter().flat_map(|&l| vec![l, l, l, 255]).collect(),
+        TextureKind::RGB8 => bytes
+            .chunks(3)
+            .flat_map(|p| vec![p[0], p[1], p[2], 255])
+            .collect(),
+        TextureKind::RGBA8 => bytes.to_vec(),
+        _ => return None,
+    })
+}
+
+/// Atlas builder collects textures and packs them into pages.
+pub struct AtlasBuilder {
+    page_size: u32,
+    padding: u32,
+    textures: Vec<Arc<Mutex<Texture>>>,
+}
+
+impl AtlasBuilder {
+    /// Creates new builder which will produce square pages of given size.
+    pub fn new(page_size: u32) -> Self {
+        Self {
+            page_size,
+            padding: 1,
+            textures: Vec::new(),
+        }
+    }
+
+    /// Sets amount of empty pixels around each image, it prevents bleeding of neighbour
+    /// images when texture filtering is used. Default is 1.
+    pub fn with_padding(mut self, padding: u32) -> Self {
+        self.padding = padding;
+        self
+    }
+
+    /// Adds texture to the atlas. Texture must be loaded at the moment of building.
+    pub fn add_texture(&mut self, texture: Arc<Mutex<Texture>>) -> &mut Self {
+        self.textures.push(texture);
+        self
+    }
+
+    /// Packs every texture into pages. Pages are RGBA8 textures without a path, so they
+    /// should be saved using [TextureAtlas::save_pages] if atlas is built offline.
+    pub fn build(self) -> Result<TextureAtlas, AtlasError> {
+        let mut textures = Vec::with_capacity(self.textures.len());
+        for texture in self.textures {
+            let texture = texture.lock().unwrap();
+            if !texture.is_loaded() {
+                return Err(AtlasError::NotLoaded(texture.path.clone()));
+            }
+            let rgba = to_rgba(&texture)
+                .ok_or_else(|| AtlasError::UnsupportedFormat(texture.path.clone()))?;
+            textures.push((texture.path.clone(), texture.width, texture.height, rgba));
+        }
+
+        // Tallest first gives good enough packing for shelf packer.
+        textures.sort_by(|a, b| b.2.cmp(&a.2));
+
+        let size = self.page_size;
+        let mut pages: Vec<Page> = Vec::new();
+        let mut regions = Vec::with_capacity(textures.len());
+        for (source, width, height, rgba) in textures {
+            let padded_width = width + self.padding * 2;
+            let padded_height = height + self.padding * 2;
+            if padded_width > size || padded_height > size {
+                return Err(AtlasError::TooLarge(source));
+            }
+
+            let mut location = None;
+            for (index, page) in pages.iter_mut().enumerate() {
+                if let Some(position) = page.allocate(size, padded_width, padded_height) {
+                    location = Some((index, position));
+                    break;
+                }
+            }
+            let (page_index, (x, y)) = match location {
+                Some(location) => location,
+                None => {
+                    let mut page = Page::new(size);
+                    let position = page
+                        .allocate(size, padded_width, padded_height)
+                        .expect("image must fit into empty page");
+                    pages.push(page);
+                    (pages.len() - 1, position)
+                }
+            };
+
+            let x = x + self.padding;
+            let y = y + self.padding;
+            pages[page_index].blit(size, x, y, width, &rgba);
+
+            let k = 1.0 / size as f32;
+            regions.push(AtlasRegion {
+                source,
+                page: page_index as u32,
+                pixel_rect: Rect::new(x, y, width, height),
+                uv_rect: Rect::new(
+                    x as f32 * k,
+                    y as f32 * k,
+                    width as f32 * k,
+                    height as f32 * k,
+                ),
+            });
+        }
+
+        Ok(TextureAtlas {
+            pages: pages
+                .into_iter()
+                .map(|page| {
+                    let texture = Texture::from_bytes(size, size, TextureKind::RGBA8, page.bytes)
+                        .expect("page must have correct size");
+                    Some(Arc::new(Mutex::new(texture)))
+                })
+                .collect(),
+            regions,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::resource::{
+        atlas::AtlasBuilder,
+        texture::{Texture, TextureKind},
+    };
+    use std::sync::{Arc, Mutex};
+
+    fn make_texture(name: &str, size: u32, value: u8) -> Arc<Mutex<Texture>> {
+        let mut texture = Texture::from_bytes(
+            size,
+            size,
+            TextureKind::R8,
+            vec![value; (size * size) as usize],
+        )
+        .unwrap();
+        texture.set_path(&name);
+        Arc::new(Mutex::new(texture))
+    }
+
+    #[test]
+    fn atlas_packing_test() {
+        let mut builder = AtlasBuilder::new(16);
+        builder
+            .add_texture(make_texture("a.png", 6, 10))
+            .add_texture(make_texture("b.png", 6, 20))
+            .add_texture(make_texture("c.png", 14, 30));
+        let atlas = builder.build().unwrap();
+
+        // c.png fills whole page with padding, a.png and b.png go to second page.
+        assert_eq!(atlas.pages().count(), 2);
+        let (_, c) = atlas.region("c.png").unwrap();
+        assert_eq!(c.page(), 0);
+        let (page, a) = atlas.region("a.png").unwrap();
+        let (_, b) = atlas.region("b.png").unwrap();
+        assert_eq!(a.page(), 1);
+        assert_eq!(b.page(), 1);
+        assert_eq!(a.pixel_rect().y, b.pixel_rect().y);
+
+        let page = page.lock().unwrap();
+        let rect = a.pixel_rect();
+        let offset = ((rect.y * 16 + rect.x) * 4) as usize;
+        assert_eq!(&page.bytes[offset..offset + 4], &[10, 10, 10, 255]);
+        assert!(atlas.region("d.png").is_none());
+    }
+
+    #[test]
+    fn atlas_too_large_test() {
+        let mut builder = AtlasBuilder::new(8);
+        builder.add_texture(make_texture("a.png", 8, 0));
+        assert!(builder.build().is_err());
+    }
+}
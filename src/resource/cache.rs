@@ -0,0 +1,142 @@
+//! Derived data cache stores results of expensive processing of resources.
+//!
+//! Decoding of images and parsing of complex models can take a lot of time, results of such
+//! processing are written into cache folder, so next runs will read ready-to-use data instead
+//! of processing source files again. Every entry is keyed by hash of source file (and of any
+//! parameters that affect processing), so changed source file will never hit stale entry.
+//!
+//! Cache is disabled by default, enable it using
+//! [ResourceManager::set_derived_data_cache](crate::engine::resource_manager::ResourceManager::set_derived_data_cache).
+//! Cache folder can be safely removed at any time, it will be filled again on demand.
+//!
+//! # Cached data
+//!
+//! - Decoded images (png, jpg, tga, bmp, gif, tiff, hdr, exr). DDS and KTX2 are not cached,
+//! because they're already in ready-to-use format.
+//! - Converted FBX and OBJ models (geometry, node hierarchy and animations). Import options are
+//! applied after reading from cache, so changing them does not invalidate cache entries.
+
+use std::{
+    fs::File,
+    io::Write,
+    path::{Path, PathBuf},
+};
+
+/// Version of format of cache entries. Must be increased when format of any cached data is
+/// changed, so old entries won't be used.
+pub const CACHE_VERSION: u32 = 2;
+
+/// Key of cache entry, it is a hash of source data and processing parameters.
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
+pub struct CacheKey(u64);
+
+impl CacheKey {
+    /// Creates new key from given source data and parameters which affect processing.
+    pub fn new(source: &[u8], parameters: &[&[u8]]) -> Self {
+        // 64-bit FNV-1a, fast and good enough to identify files.
+        let mut hash = 0xcbf2_9ce4_8422_2325u64;
+        let mut feed = |bytes: &[u8]| {
+            for &byte in bytes {
+                hash ^= byte as u64;
+                hash = hash.wrapping_mul(0x0100_0000_01b3);
+            }
+        };
+        feed(&CACHE_VERSION.to_le_bytes());
+        feed(&(source.len() as u64).to_le_bytes());
+        feed(source);
+        for parameter in parameters {
+            feed(&(parameter.len() as u64).to_le_bytes());
+            feed(parameter);
+        }
+        Self(hash)
+    }
+}
+
+/// See module docs.
+#[derive(Debug)]
+pub struct DerivedDataCache {
+    root: PathBuf,
+}
+
+impl DerivedDataCache {
+    /// Creates new cache in given folder, the folder will be created if it does not exist.
+    pub fn new<P: AsRef<Path>>(root: P) -> std::io::Result<Self> {
+        std::fs::create_dir_all(root.as_ref())?;
+        Ok(Self {
+            root: root.as_ref().to_owned(),
+        })
+    }
+
+    /// Returns path of cache folder.
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Returns path to file of entry with given key. Extension defines kind of cached data.
+    pub fn entry_path(&self, key: CacheKey, extension: &str) -> PathBuf {
+        self.root.join(format!("{:016x}.{}", key.0, extension))
+    }
+
+    /// Returns true if there is an entry with given key.
+    pub fn contains(&self, key: CacheKey, extension: &str) -> bool {
+        self.entry_path(key, extension).is_file()
+    }
+
+    /// Reads entry with given key. Returns None if there is no such entry.
+    pub fn read(&self, key: CacheKey, extension: &str) -> Option<Vec<u8>> {
+        std::fs::read(self.entry_path(key, extension)).ok()
+    }
+
+    /// Writes entry with given key. Data is written to temporary file first, so other
+    /// processes will never see partially written entry.
+    pub fn write(&self, key: CacheKey, extension: &str, data: &[u8]) -> std::io::Result<()> {
+        let path = self.entry_path(key, extension);
+        let temp_path = path.with_extension(format!("{}.tmp", extension));
+        let mut file = File::create(&temp_path)?;
+        file.write_all(data)?;
+        drop(file);
+        std::fs::rename(temp_path, path)
+    }
+
+    /// Removes every entry from cache.
+    pub fn clear(&self) -> std::io::Result<()> {
+        for entry in std::fs::read_dir(&self.root)? {
+            let entry = entry?;
+            if entry.file_type()?.is_file() {
+                std::fs::remove_file(entry.path())?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::resource::cache::{CacheKey, DerivedDataCache};
+
+    #[test]
+    fn key_test() {
+        let key = CacheKey::new(b"source", &[b"param"]);
+        assert_eq!(key, CacheKey::new(b"source", &[b"param"]));
+        assert_ne!(key, CacheKey::new(b"source2", &[b"param"]));
+        assert_ne!(key, CacheKey::new(b"source", &[b"param2"]));
+        // Parameters must not be mixed with source.
+        assert_ne!(
+            CacheKey::new(b"ab", &[b"c"]),
+            CacheKey::new(b"a", &[b"bc"])
+        );
+    }
+
+    #[test]
+    fn read_write_test() {
+        let root = std::env::temp_dir().join("rg3d_cache_test");
+        let cache = DerivedDataCache::new(&root).unwrap();
+        let key = CacheKey::new(b"data", &[]);
+        cache.write(key, "bin", &[1, 2, 3]).unwrap();
+        assert!(cache.contains(key, "bin"));
+        assert_eq!(cache.read(key, "bin"), Some(vec![1, 2, 3]));
+        cache.clear().unwrap();
+        assert!(!cache.contains(key, "bin"));
+        std::fs::remove_dir_all(root).unwrap();
+    }
+}
@@ -0,0 +1,228 @@
+//! Custom resources are user-defined resource types - dialogues, quests, behavior trees, etc.
+//!
+//! Game registers a [loader](ResourceLoader) for each of its resource types in resource
+//! manager, after that such resources are requested by path like built-in ones. Custom
+//! resources are shared by path, unloaded when not used, read through virtual file system (so
+//! they can be stored in resource packs), can be loaded on worker threads and are hot-reloaded
+//! when their source files change.
+//!
+//! ```no_run
+//! use rg3d::{
+//!     engine::resource_manager::ResourceManager,
+//!     resource::custom::ResourceLoader,
+//! };
+//! use std::path::Path;
+//!
+//! struct Dialogue {
+//!     lines: Vec<String>,
+//! }
+//!
+//! struct DialogueLoader;
+//!
+//! impl ResourceLoader for DialogueLoader {
+//!     type Resource = Dialogue;
+//!
+//!     fn load(&self, _path: &Path, data: &[u8]) -> Result<Dialogue, String> {
+//!         Ok(Dialogue {
+//!             lines: String::from_utf8_lossy(data).lines().map(|l| l.to_owned()).collect(),
+//!         })
+//!     }
+//! }
+//!
+//! # fn load(resource_manager: &mut ResourceManager) {
+//! resource_manager.register_loader(DialogueLoader);
+//! let dialogue = resource_manager
+//!     .request_custom::<Dialogue, _>("data/dialogues/intro.txt")
+//!     .unwrap();
+//! let lines = dialogue.lock().unwrap().data().unwrap().lines.len();
+//! # }
+//! ```
+
+use crate::resource::ResourceState;
+use std::{
+    any::Any,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+};
+
+/// Loader of a custom resource type.
+pub trait ResourceLoader: Send + Sync + 'static {
+    /// Type of resources produced by the loader.
+    type Resource: Send + 'static;
+
+    /// Creates resource from contents of its source file. Error will be printed to the log.
+    fn load(&self, path: &Path, data: &[u8]) -> Result<Self::Resource, String>;
+}
+
+/// Custom resource with its loading state.
+#[derive(Debug)]
+pub struct CustomResource<T> {
+    path: PathBuf,
+    state: ResourceState,
+    data: Option<T>,
+}
+
+/// Type alias for Arc<Mutex<CustomResource<T>>> to make code less noisy.
+pub type SharedCustomResource<T> = Arc<Mutex<CustomResource<T>>>;
+
+impl<T> CustomResource<T> {
+    pub(in crate) fn new(path: PathBuf, state: ResourceState, data: Option<T>) -> Self {
+        Self { path, state, data }
+    }
+
+    /// Returns path of source file of the resource.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Returns current state of the resource.
+    pub fn state(&self) -> ResourceState {
+        self.state
+    }
+
+    /// Returns data of the resource, None if it is not loaded yet or failed to load.
+    pub fn data(&self) -> Option<&T> {
+        self.data.as_ref()
+    }
+
+    /// Returns data of the resource, None if it is not loaded yet or failed to load.
+    pub fn data_mut(&mut self) -> Option<&mut T> {
+        self.data.as_mut()
+    }
+}
+
+/// Type-erased loader, allows to store loaders of different types in one container.
+pub(in crate) trait ErasedLoader: Send + Sync {
+    fn load_erased(&self, path: &Path, data: &[u8]) -> Result<Box<dyn Any + Send>, String>;
+}
+
+impl<L: ResourceLoader> ErasedLoader for L {
+    fn load_erased(&self, path: &Path, data: &[u8]) -> Result<Box<dyn Any + Send>, String> {
+        self.load(path, data)
+            .map(|resource| Box::new(resource) as Box<dyn Any + Send>)
+    }
+}
+
+/// Type-erased shared custom resource, allows to store resources of different types in one
+/// container.
+pub(in crate) trait ErasedResource: Send + Sync {
+    fn path(&self) -> PathBuf;
+
+    fn state(&self) -> ResourceState;
+
+    /// Sets result of loading. Previous data is kept if loading failed and there was data.
+    fn set(&self, result: Result<Box<dyn Any + Send>, String>);
+
+    fn into_any(self: Arc<Self>) -> Arc<dyn Any + Send + Sync>;
+}
+
+impl<T: Send + 'static> ErasedResource for Mutex<CustomResource<T>> {
+    fn path(&self) -> PathBuf {
+        self.lock().unwrap().path.clone()
+    }
+
+    fn state(&self) -> ResourceState {
+        self.lock().unwrap().state
+    }
+
+    fn set(&self, result: Result<Box<dyn Any + Send>, String>) {
+        let mut resource = self.lock().unwrap();
+        match result.map(|data| data.downcast::<T>()) {
+            Ok(Ok(data)) => {
+                resource.data = Some(*data);
+                resource.state = ResourceState::Ok;
+            }
+            _ => {
+                if resource.data.is_none() {
+                    resource.state = ResourceState::LoadError;
+                }
+            }
+        }
+    }
+
+    fn into_any(self: Arc<Self>) -> Arc<dyn Any + Send + Sync> {
+        self
+    }
+}
+
+/// Entry of custom resource in resource manager.
+#[derive(Clone)]
+pub(in crate) struct CustomResourceEntry {
+    pub(in crate) resource: Arc<dyn ErasedResource>,
+    pub(in crate) loader: Arc<dyn ErasedLoader>,
+}
+
+impl CustomResourceEntry {
+    /// Returns typed resource, None if the resource has different type.
+    pub(in crate) fn downcast<T: Send + 'static>(&self) -> Option<SharedCustomResource<T>> {
+        self.resource
+            .clone()
+            .into_any()
+            .downcast::<Mutex<CustomResource<T>>>()
+            .ok()
+    }
+
+    /// Loads resource in-place from contents of its source file.
+    pub(in crate) fn load(&self, data: std::io::Result<Vec<u8>>) -> Result<(), String> {
+        let path = self.resource.path();
+        let result = data
+            .map_err(|e| e.to_string())
+            .and_then(|data| self.loader.load_erased(&path, &data));
+        let error = result.as_ref().err().cloned();
+        self.resource.set(result);
+        match error {
+            Some(e) => Err(e),
+            None => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::resource::{
+        custom::{CustomResource, CustomResourceEntry, ResourceLoader},
+        ResourceState,
+    };
+    use std::{
+        path::{Path, PathBuf},
+        sync::{Arc, Mutex},
+    };
+
+    struct NumberLoader;
+
+    impl ResourceLoader for NumberLoader {
+        type Resource = u32;
+
+        fn load(&self, _path: &Path, data: &[u8]) -> Result<u32, String> {
+            String::from_utf8_lossy(data)
+                .trim()
+                .parse()
+                .map_err(|_| "not a number".to_owned())
+        }
+    }
+
+    #[test]
+    fn entry_test() {
+        let resource = Arc::new(Mutex::new(CustomResource::<u32>::new(
+            PathBuf::from("number.txt"),
+            ResourceState::Pending,
+            None,
+        )));
+        let entry = CustomResourceEntry {
+            resource: resource.clone(),
+            loader: Arc::new(NumberLoader),
+        };
+
+        assert!(entry.downcast::<u32>().is_some());
+        assert!(entry.downcast::<String>().is_none());
+
+        assert!(entry.load(Ok(b"42".to_vec())).is_ok());
+        assert_eq!(resource.lock().unwrap().data(), Some(&42));
+        assert_eq!(resource.lock().unwrap().state(), ResourceState::Ok);
+
+        // Failed reload keeps old data.
+        assert!(entry.load(Ok(b"abc".to_vec())).is_err());
+        assert_eq!(resource.lock().unwrap().data(), Some(&42));
+        assert_eq!(resource.lock().unwrap().state(), ResourceState::Ok);
+    }
+}
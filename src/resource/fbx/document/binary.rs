@@ -121,9 +121,13 @@ where
                 .attributes
                 .push(read_string(file)?),
             b'R' => {
-                // Ignore Raw data
-                let length = i64::from(file.read_u32::<LittleEndian>()?);
-                file.seek(SeekFrom::Current(length))?;
+                // Raw data is used to store embedded media (textures, etc.)
+                let length = file.read_u32::<LittleEndian>()? as usize;
+                let mut data = vec![0; length];
+                file.read_exact(data.as_mut_slice())?;
+                pool.borrow_mut(node_handle)
+                    .attributes
+                    .push(FbxAttribute::RawData(data));
             }
             _ => (),
         }
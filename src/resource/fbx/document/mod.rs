@@ -9,11 +9,7 @@ use crate::{
     },
     resource::fbx::{document::attribute::FbxAttribute, error::FbxError},
 };
-use std::{
-    fs::File,
-    io::{BufReader, Read},
-    path::Path,
-};
+use std::io::Cursor;
 
 pub struct FbxNode {
     name: String,
@@ -118,21 +114,16 @@ pub struct FbxDocument {
     nodes: FbxNodeContainer,
 }
 
-fn is_binary<P: AsRef<Path>>(path: P) -> Result<bool, FbxError> {
-    let mut file = File::open(path)?;
-    let mut magic = [0; 18];
-    file.read_exact(&mut magic)?;
+fn is_binary(data: &[u8]) -> bool {
     let fbx_magic = b"Kaydara FBX Binary";
-    Ok(magic == *fbx_magic)
+    data.starts_with(fbx_magic)
 }
 
 impl FbxDocument {
-    pub fn new<P: AsRef<Path>>(path: P) -> Result<FbxDocument, FbxError> {
-        let is_bin = is_binary(path.as_ref())?;
-
-        let mut reader = BufReader::new(File::open(path)?);
+    pub fn from_bytes(data: &[u8]) -> Result<FbxDocument, FbxError> {
+        let mut reader = Cursor::new(data);
 
-        if is_bin {
+        if is_binary(data) {
             binary::read_binary(&mut reader)
         } else {
             ascii::read_ascii(&mut reader)
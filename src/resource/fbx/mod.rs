@@ -3,6 +3,13 @@
 //! FBX is most flexible format to store and distribute 3D models, it has lots of useful features
 //! such as skeletal animation, keyframe animation, support tangents, binormals, materials, etc.
 //!
+//! Every animation stack (take) of a file is converted to a separate animation with the name of
+//! the stack.
+//!
+//! Loader supports two UV sets (second one is used for lightmaps), vertex colors, diffuse, normal
+//! and specular maps. Embedded media (binary FBX only) is extracted to textures path of resource
+//! manager, existing files are never overwritten.
+//!
 //! Normally you should never use methods from this module directly, use resource manager to load
 //! models and create their instances.
 
@@ -20,6 +27,7 @@ use std::{
 use crate::{
     animation::{Animation, AnimationContainer, KeyFrame, Track},
     core::{
+        color::Color,
         math::{
             mat4::Mat4,
             quat::{Quat, RotationOrder},
@@ -37,7 +45,10 @@ use crate::{
             document::FbxDocument,
             error::FbxError,
             scene::{
-                animation::FbxAnimationCurveNodeType, geometry::FbxGeometry, model::FbxModel,
+                animation::{FbxAnimationCurveNode, FbxAnimationCurveNodeType},
+                geometry::FbxGeometry,
+                model::FbxModel,
+                video::FbxVideo,
                 FbxComponent, FbxMapping, FbxScene,
             },
         },
@@ -136,6 +147,8 @@ struct UnpackedVertex {
     normal: Vec3,
     tangent: Vec3,
     uv: Vec2,
+    second_uv: Vec2,
+    color: [u8; 4],
     // Set of weights for skinning.
     weights: Option<VertexWeightSet>,
 }
@@ -145,9 +158,8 @@ impl Into<Vertex> for UnpackedVertex {
         Vertex {
             position: self.position,
             tex_coord: self.uv,
-            // TODO: FBX can contain second texture coordinates so they should be
-            //  extracted when available
-            second_tex_coord: Default::default(),
+            second_tex_coord: self.second_uv,
+            color: self.color,
             normal: self.normal,
             tangent: Vec4::from_vec3(self.tangent, 1.0),
             // Correct values will be assigned in second pass of conversion
@@ -178,11 +190,21 @@ fn convert_vertex(
         None => Vec3::UP,
     };
 
-    let uv = match geom.uvs.as_ref() {
+    let uv = match geom.uvs.first() {
         Some(uvs) => *uvs.get(index, index_in_polygon)?,
         None => Vec2::ZERO,
     };
 
+    let second_uv = match geom.uvs.get(1) {
+        Some(uvs) => *uvs.get(index, index_in_polygon)?,
+        None => Vec2::ZERO,
+    };
+
+    let color = match geom.colors.as_ref() {
+        Some(colors) => *colors.get(index, index_in_polygon)?,
+        None => [255; 4],
+    };
+
     let material = match geom.materials.as_ref() {
         Some(materials) => *materials.get(material_index, index_in_polygon)?,
         None => 0,
@@ -193,6 +215,11 @@ fn convert_vertex(
         normal: geometric_transform.transform_vector_normal(normal),
         tangent: geometric_transform.transform_vector_normal(tangent),
         uv: Vec2 { x: uv.x, y: -uv.y }, // Invert Y because OpenGL has origin at left *bottom* corner.
+        second_uv: Vec2 {
+            x: second_uv.x,
+            y: -second_uv.y,
+        },
+        color,
         surface: material as usize,
         weights: if skin_data.is_empty() {
             None
@@ -208,6 +235,28 @@ struct SurfaceData {
     skin_data: Vec<VertexWeightSet>,
 }
 
+/// Writes embedded media content to given path, so it can be loaded by resource manager as
+/// any other texture. This is needed because textures are referenced by path and serialized
+/// by path as well. Existing files are never overwritten.
+fn extract_embedded_media(video: &FbxVideo, path: &Path) {
+    if video.content().is_empty() || path.exists() {
+        return;
+    }
+    match std::fs::write(path, video.content()) {
+        Ok(_) => Log::writeln(format!(
+            "FBX: Embedded media {:?} was extracted to {:?}",
+            video.get_file_path(),
+            path
+        )),
+        Err(e) => Log::writeln(format!(
+            "FBX: Unable to extract embedded media {:?} to {:?}! Reason: {}",
+            video.get_file_path(),
+            path,
+            e
+        )),
+    }
+}
+
 fn create_surfaces(
     fbx_scene: &FbxScene,
     data_set: Vec<SurfaceData>,
@@ -238,21 +287,43 @@ fn create_surfaces(
                 let texture = fbx_scene.get(*texture_handle).as_texture()?;
                 let path = texture.get_file_path();
                 if let Some(filename) = path.file_name() {
-                    let diffuse_path = resource_manager.textures_path().join(&filename);
+                    let texture_path = resource_manager.textures_path().join(&filename);
+                    if texture.video.is_some() {
+                        extract_embedded_media(
+                            fbx_scene.get(texture.video).as_video()?,
+                            &texture_path,
+                        );
+                    }
                     // Here we will load *every* texture as RGBA8, this probably is overkill,
                     // that will lead to higher memory consumption, but this will remove
                     // problems with transparent textures (like mesh texture, etc.)
                     let texture = resource_manager
-                        .request_texture_async(diffuse_path.as_path(), TextureKind::RGBA8);
+                        .request_texture_async(texture_path.as_path(), TextureKind::RGBA8);
                     match name.as_str() {
                         "AmbientColor" => (), // TODO: Add ambient occlusion (AO) map support.
                         "DiffuseColor" => surface.set_diffuse_texture(texture),
                         // No idea why it can be different for normal maps.
                         "Bump" | "NormalMap" => surface.set_normal_texture(texture),
+                        "SpecularColor" | "SpecularFactor" => surface.set_specular_texture(texture),
+                        "EmissiveColor" | "EmissiveFactor" => Log::writeln(format!(
+                            "FBX: Emissive map {:?} is ignored, emission is not supported yet!",
+                            path
+                        )),
                         _ => (),
                     }
                 }
             }
+            if material.emissive_factor > 0.0 && material.emissive_color != Color::opaque(0, 0, 0) {
+                Log::writeln(String::from(
+                    "FBX: Material has emissive color, but emission is not supported yet!",
+                ));
+            }
+            // Diffuse color of material is usually some gray color when there is a diffuse
+            // texture, so use it only for surfaces without texture, otherwise textured
+            // surfaces will be darker than they should.
+            if surface.diffuse_texture().is_none() {
+                surface.set_color(material.diffuse_color);
+            }
             mesh.add_surface(surface);
         }
     }
@@ -341,13 +412,125 @@ fn convert_mesh(
     Ok(mesh)
 }
 
+/// Animation clip of FBX file.
+struct FbxClip {
+    /// Curve nodes of the clip, None for files without animation stacks - every curve node
+    /// belongs to the only clip in this case.
+    curve_nodes: Option<HashSet<Handle<FbxComponent>>>,
+    /// Time of first key of the clip, clips of a file share same timeline, but every
+    /// animation starts from zero.
+    start_time: f32,
+    animation: Handle<Animation>,
+}
+
+fn prepare_clips(fbx_scene: &FbxScene, animations: &mut AnimationContainer) -> Vec<FbxClip> {
+    let mut clips = Vec::new();
+    for (_, component) in fbx_scene.pair_iter() {
+        if let FbxComponent::AnimationStack(stack) = component {
+            let curve_nodes = stack.curve_nodes(fbx_scene);
+            let start_time = curve_nodes
+                .iter()
+                .filter_map(|&handle| match fbx_scene.get(handle) {
+                    FbxComponent::AnimationCurveNode(curve_node) => Some(curve_node),
+                    _ => None,
+                })
+                .flat_map(|curve_node| curve_node.curves(fbx_scene))
+                .filter_map(|curve| curve.keys.first().map(|key| key.time))
+                .fold(std::f32::MAX, f32::min);
+            let mut animation = Animation::default();
+            animation.set_name(&stack.name);
+            clips.push(FbxClip {
+                curve_nodes: Some(curve_nodes),
+                start_time: if start_time < std::f32::MAX {
+                    start_time
+                } else {
+                    0.0
+                },
+                animation: animations.add(animation),
+            });
+        }
+    }
+    if clips.is_empty() {
+        clips.push(FbxClip {
+            curve_nodes: None,
+            start_time: 0.0,
+            animation: animations.add(Animation::default()),
+        });
+    }
+    clips
+}
+
+fn convert_animation_track(
+    fbx_scene: &FbxScene,
+    model: &FbxModel,
+    curve_nodes: &[&FbxAnimationCurveNode],
+    node_handle: Handle<Node>,
+    start_time: f32,
+) -> Track {
+    let find = |actual_type: FbxAnimationCurveNodeType| {
+        curve_nodes
+            .iter()
+            .copied()
+            .find(|curve_node| curve_node.actual_type == actual_type)
+    };
+    let lcl_translation = find(FbxAnimationCurveNodeType::Translation);
+    let lcl_rotation = find(FbxAnimationCurveNodeType::Rotation);
+    let lcl_scale = find(FbxAnimationCurveNodeType::Scale);
+
+    let mut track = Track::new();
+    track.set_node(node_handle);
+
+    let mut time = start_time;
+    loop {
+        let translation = lcl_translation
+            .map(|curve| curve.eval_vec3(fbx_scene, time))
+            .unwrap_or(model.translation);
+
+        let rotation = lcl_rotation
+            .map(|curve| curve.eval_quat(fbx_scene, time))
+            .unwrap_or_else(|| quat_from_euler(model.rotation));
+
+        // Scale can be non-uniform, components without curves keep their values.
+        let scale = lcl_scale
+            .map(|curve| curve.eval_vec3(fbx_scene, time))
+            .unwrap_or(model.scale);
+
+        track.add_key_frame(KeyFrame::new(
+            time - start_time,
+            translation,
+            scale,
+            rotation,
+        ));
+
+        // Find closest next key of any curve.
+        let mut next_time = std::f32::MAX;
+        for curve_node in [lcl_translation, lcl_rotation, lcl_scale].iter().flatten() {
+            for curve in curve_node.curves(fbx_scene) {
+                for key in curve.keys.iter() {
+                    if key.time > time && key.time < next_time {
+                        next_time = key.time;
+                    }
+                }
+            }
+        }
+
+        if next_time >= std::f32::MAX {
+            break;
+        }
+
+        time = next_time;
+    }
+
+    track
+}
+
 fn convert_model(
     fbx_scene: &FbxScene,
     model: &FbxModel,
     resource_manager: &mut ResourceManager,
     graph: &mut Graph,
     animations: &mut AnimationContainer,
-    animation_handle: Handle<Animation>,
+    clips: &[FbxClip],
 ) -> Result<Handle<Node>, FbxError> {
     // Create node with correct kind.
     let mut node = if !model.geoms.is_empty() {
@@ -375,72 +558,36 @@ fn convert_model(
 
     let node_handle = graph.add_node(node);
 
-    // Convert animations
-    if !model.animation_curve_nodes.is_empty() {
-        // Find supported curve nodes (translation, rotation, scale)
-        let mut lcl_translation = None;
-        let mut lcl_rotation = None;
-        let mut lcl_scale = None;
-        for &anim_curve_node_handle in model.animation_curve_nodes.iter() {
-            let component = fbx_scene.get(anim_curve_node_handle);
-            if let FbxComponent::AnimationCurveNode(curve_node) = component {
-                if curve_node.actual_type == FbxAnimationCurveNodeType::Rotation {
-                    lcl_rotation = Some(curve_node);
-                } else if curve_node.actual_type == FbxAnimationCurveNodeType::Translation {
-                    lcl_translation = Some(curve_node);
-                } else if curve_node.actual_type == FbxAnimationCurveNodeType::Scale {
-                    lcl_scale = Some(curve_node);
-                }
-            }
-        }
-
-        // Convert to engine format
-        let mut track = Track::new();
-        track.set_node(node_handle);
-
-        let mut time = 0.0;
-        loop {
-            let translation = lcl_translation
-                .map(|curve| curve.eval_vec3(fbx_scene, time))
-                .unwrap_or(model.translation);
-
-            let rotation = lcl_rotation
-                .map(|curve| curve.eval_quat(fbx_scene, time))
-                .unwrap_or(node_local_rotation);
-
-            let scale = lcl_scale
-                .map(|curve| curve.eval_vec3(fbx_scene, time))
-                .unwrap_or(model.scale);
-
-            track.add_key_frame(KeyFrame::new(time, translation, scale, rotation));
-
-            let mut next_time = std::f32::MAX;
-            for node in &[lcl_translation, lcl_rotation, lcl_scale] {
-                if let Some(node) = node {
-                    for &curve_handle in node.curves.iter() {
-                        let curve_component = fbx_scene.get(curve_handle);
-                        if let FbxComponent::AnimationCurve(curve) = curve_component {
-                            for key in curve.keys.iter() {
-                                if key.time > time {
-                                    let distance = key.time - time;
-                                    if distance < next_time - key.time {
-                                        next_time = key.time;
-                                    }
-                                }
-                            }
-                        }
-                    }
+    // Convert animations, each clip gets its own track.
+    for clip in clips {
+        let curve_nodes = model
+            .animation_curve_nodes
+            .iter()
+            .filter(|handle| {
+                clip.curve_nodes
+                    .as_ref()
+                    .map_or(true, |curve_nodes| curve_nodes.contains(*handle))
+            })
+            .filter_map(|&handle| match fbx_scene.get(handle) {
+                FbxComponent::AnimationCurveNode(curve_node)
+                    if curve_node.actual_type != FbxAnimationCurveNodeType::Unknown =>
+                {
+                    Some(curve_node)
                 }
-            }
-
-            if next_time >= std::f32::MAX {
-                break;
-            }
+                _ => None,
+            })
+            .collect::<Vec<_>>();
 
-            time = next_time;
+        if !curve_nodes.is_empty() {
+            let track = convert_animation_track(
+                fbx_scene,
+                model,
+                &curve_nodes,
+                node_handle,
+                clip.start_time,
+            );
+            animations.get_mut(clip.animation).add_track(track);
         }
-
-        animations.get_mut(animation_handle).add_track(track);
     }
 
     Ok(node_handle)
@@ -455,7 +602,7 @@ fn convert(
     scene: &mut Scene,
 ) -> Result<Handle<Node>, FbxError> {
     let root = scene.graph.add_node(Node::Base(Base::default()));
-    let animation_handle = scene.animations.add(Animation::default());
+    let clips = prepare_clips(fbx_scene, &mut scene.animations);
     let mut fbx_model_to_node_map = HashMap::new();
     for (component_handle, component) in fbx_scene.pair_iter() {
         if let FbxComponent::Model(model) = component {
@@ -465,12 +612,20 @@ fn convert(
                 resource_manager,
                 &mut scene.graph,
                 &mut scene.animations,
-                animation_handle,
+                &clips,
             )?;
             scene.graph.link_nodes(node, root);
             fbx_model_to_node_map.insert(component_handle, node);
         }
     }
+    // Stacks without curves (for example default take of static models) are useless.
+    for clip in clips.iter() {
+        if clip.curve_nodes.is_some()
+            && scene.animations.get(clip.animation).get_tracks().is_empty()
+        {
+            scene.animations.remove(clip.animation);
+        }
+    }
     // Link according to hierarchy
     for (&fbx_model_handle, node_handle) in fbx_model_to_node_map.iter() {
         if let FbxComponent::Model(fbx_model) = fbx_scene.get(fbx_model_handle) {
@@ -540,7 +695,8 @@ pub fn load_to_scene<P: AsRef<Path>>(
     Log::writeln(format!("Trying to load {:?}", path.as_ref()));
 
     let now = Instant::now();
-    let fbx = FbxDocument::new(path.as_ref())?;
+    let data = resource_manager.vfs().lock().unwrap().read(path.as_ref())?;
+    let fbx = FbxDocument::from_bytes(&data)?;
     let parsing_time = now.elapsed().as_millis();
 
     let now = Instant::now();
@@ -10,6 +10,7 @@ use crate::{
     },
     utils::log::Log,
 };
+use std::collections::HashSet;
 
 pub struct FbxTimeValuePair {
     pub time: f32,
@@ -85,6 +86,42 @@ impl FbxAnimationCurve {
     }
 }
 
+/// Animation stack is a single animation clip (take) of a file.
+pub struct FbxAnimationStack {
+    pub name: String,
+    pub layers: Vec<Handle<FbxComponent>>,
+}
+
+impl FbxAnimationStack {
+    pub fn read(node_handle: Handle<FbxNode>, nodes: &FbxNodeContainer) -> Result<Self, String> {
+        let node = nodes.get(node_handle);
+        let mut name = node.get_attrib(1)?.as_string();
+        // Remove prefix
+        if name.starts_with("AnimStack::") {
+            name = name.chars().skip(11).collect();
+        }
+        Ok(FbxAnimationStack {
+            name,
+            layers: Vec::new(),
+        })
+    }
+
+    /// Collects handles of curve nodes of every layer of the stack.
+    pub fn curve_nodes(&self, scene: &FbxScene) -> HashSet<Handle<FbxComponent>> {
+        let mut curve_nodes = HashSet::new();
+        for &layer in self.layers.iter() {
+            if let FbxComponent::AnimationLayer(layer) = scene.get(layer) {
+                curve_nodes.extend(layer.curve_nodes.iter().copied());
+            }
+        }
+        curve_nodes
+    }
+}
+
+pub struct FbxAnimationLayer {
+    pub curve_nodes: Vec<Handle<FbxComponent>>,
+}
+
 #[derive(PartialEq)]
 pub enum FbxAnimationCurveNodeType {
     Unknown,
@@ -95,43 +132,91 @@ pub enum FbxAnimationCurveNodeType {
 
 pub struct FbxAnimationCurveNode {
     pub actual_type: FbxAnimationCurveNodeType,
-    pub curves: Vec<Handle<FbxComponent>>,
+    /// Curves of X, Y, Z components. Exporters write curves only for animated components,
+    /// so some curves can be missing.
+    pub curves: [Handle<FbxComponent>; 3],
+    /// Values of components which have no curves.
+    pub default: Vec3,
 }
 
 impl FbxAnimationCurveNode {
     pub fn read(node_handle: Handle<FbxNode>, nodes: &FbxNodeContainer) -> Result<Self, String> {
         let node = nodes.get(node_handle);
+        let actual_type = match node.get_attrib(1)?.as_string().as_str() {
+            "T" | "AnimCurveNode::T" => FbxAnimationCurveNodeType::Translation,
+            "R" | "AnimCurveNode::R" => FbxAnimationCurveNodeType::Rotation,
+            "S" | "AnimCurveNode::S" => FbxAnimationCurveNodeType::Scale,
+            _ => FbxAnimationCurveNodeType::Unknown,
+        };
+        let mut default = if actual_type == FbxAnimationCurveNodeType::Scale {
+            Vec3::UNIT
+        } else {
+            Vec3::ZERO
+        };
+        if let Ok(props) = nodes.get_by_name(node_handle, "Properties70") {
+            for prop_handle in props.children() {
+                let prop = nodes.get(*prop_handle);
+                match prop.get_attrib(0)?.as_string().as_str() {
+                    "d|X" => default.x = prop.get_attrib(4)?.as_f32()?,
+                    "d|Y" => default.y = prop.get_attrib(4)?.as_f32()?,
+                    "d|Z" => default.z = prop.get_attrib(4)?.as_f32()?,
+                    _ => (),
+                }
+            }
+        }
         Ok(FbxAnimationCurveNode {
-            actual_type: match node.get_attrib(1)?.as_string().as_str() {
-                "T" | "AnimCurveNode::T" => FbxAnimationCurveNodeType::Translation,
-                "R" | "AnimCurveNode::R" => FbxAnimationCurveNodeType::Rotation,
-                "S" | "AnimCurveNode::S" => FbxAnimationCurveNodeType::Scale,
-                _ => FbxAnimationCurveNodeType::Unknown,
-            },
-            curves: Vec::new(),
+            actual_type,
+            curves: Default::default(),
+            default,
         })
     }
 
-    pub fn eval_vec3(&self, scene: &FbxScene, time: f32) -> Vec3 {
-        let x = if let FbxComponent::AnimationCurve(curve) = scene.get(self.curves[0]) {
-            curve.eval(time)
-        } else {
-            0.0
+    /// Links curve with the node, property is the name of animated component (`d|X`).
+    pub fn add_curve(&mut self, property: &str, curve: Handle<FbxComponent>) {
+        let index = match property {
+            "d|X" => 0,
+            "d|Y" => 1,
+            "d|Z" => 2,
+            // Unknown property, take first free slot.
+            _ => match self.curves.iter().position(|c| c.is_none()) {
+                Some(index) => index,
+                None => return,
+            },
         };
+        self.curves[index] = curve;
+    }
 
-        let y = if let FbxComponent::AnimationCurve(curve) = scene.get(self.curves[1]) {
-            curve.eval(time)
-        } else {
-            0.0
-        };
+    /// Returns iterator over linked curves.
+    pub fn curves<'a>(
+        &'a self,
+        scene: &'a FbxScene,
+    ) -> impl Iterator<Item = &'a FbxAnimationCurve> + 'a {
+        self.curves.iter().filter_map(move |&curve| {
+            if curve.is_some() {
+                if let FbxComponent::AnimationCurve(curve) = scene.get(curve) {
+                    return Some(curve);
+                }
+            }
+            None
+        })
+    }
 
-        let z = if let FbxComponent::AnimationCurve(curve) = scene.get(self.curves[2]) {
-            curve.eval(time)
-        } else {
-            0.0
-        };
+    fn eval_component(&self, scene: &FbxScene, index: usize, default: f32, time: f32) -> f32 {
+        let curve = self.curves[index];
+        if curve.is_some() {
+            if let FbxComponent::AnimationCurve(curve) = scene.get(curve) {
+                return curve.eval(time);
+            }
+        }
+        default
+    }
 
-        Vec3::new(x, y, z)
+    pub fn eval_vec3(&self, scene: &FbxScene, time: f32) -> Vec3 {
+        Vec3::new(
+            self.eval_component(scene, 0, self.default.x, time),
+            self.eval_component(scene, 1, self.default.y, time),
+            self.eval_component(scene, 2, self.default.z, time),
+        )
     }
 
     pub fn eval_quat(&self, scene: &FbxScene, time: f32) -> Quat {
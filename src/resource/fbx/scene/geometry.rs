@@ -21,7 +21,10 @@ pub struct FbxGeometry {
 
     // Normals, UVs, etc. are optional.
     pub normals: Option<FbxContainer<Vec3>>,
-    pub uvs: Option<FbxContainer<Vec2>>,
+    // There could be any number of UV sets, first one is used for texture mapping,
+    // second one is used for lightmaps, rest are ignored.
+    pub uvs: Vec<FbxContainer<Vec2>>,
+    pub colors: Option<FbxContainer<[u8; 4]>>,
     pub materials: Option<FbxContainer<i32>>,
     pub tangents: Option<FbxContainer<Vec3>>,
     pub binormals: Option<FbxContainer<Vec3>>,
@@ -108,21 +111,50 @@ fn read_binormals(
 fn read_uvs(
     geom_node_handle: Handle<FbxNode>,
     nodes: &FbxNodeContainer,
-) -> Result<Option<FbxContainer<Vec2>>, FbxError> {
-    if let Ok(layer_element_uv) = nodes.find(geom_node_handle, "LayerElementUV") {
+) -> Result<Vec<FbxContainer<Vec2>>, FbxError> {
+    let mut layers = Vec::new();
+    for &child_handle in nodes.get(geom_node_handle).children() {
+        let child = nodes.get(child_handle);
+        if child.name() != "LayerElementUV" {
+            continue;
+        }
+        // First attribute is index of UV layer, layers can be stored in any order.
+        let layer_index = child.get_attrib(0)?.as_i32()?;
+        let container = FbxContainer::new(nodes, child_handle, "UV", |attributes| {
+            let mut uvs = Vec::with_capacity(attributes.len() / 2);
+            for uv in attributes.chunks_exact(2) {
+                uvs.push(Vec2 {
+                    x: uv[0].as_f32()?,
+                    y: uv[1].as_f32()?,
+                });
+            }
+            Ok(uvs)
+        })?;
+        layers.push((layer_index, container));
+    }
+    layers.sort_by_key(|(index, _)| *index);
+    Ok(layers.into_iter().map(|(_, container)| container).collect())
+}
+
+fn read_colors(
+    geom_node_handle: Handle<FbxNode>,
+    nodes: &FbxNodeContainer,
+) -> Result<Option<FbxContainer<[u8; 4]>>, FbxError> {
+    if let Ok(layer_element_color) = nodes.find(geom_node_handle, "LayerElementColor") {
         Ok(Some(FbxContainer::new(
             nodes,
-            layer_element_uv,
-            "UV",
+            layer_element_color,
+            "Colors",
             |attributes| {
-                let mut uvs = Vec::with_capacity(attributes.len() / 2);
-                for uv in attributes.chunks_exact(2) {
-                    uvs.push(Vec2 {
-                        x: uv[0].as_f32()?,
-                        y: uv[1].as_f32()?,
-                    });
+                let mut colors = Vec::with_capacity(attributes.len() / 4);
+                for color in attributes.chunks_exact(4) {
+                    let mut rgba = [0; 4];
+                    for (component, attribute) in rgba.iter_mut().zip(color.iter()) {
+                        *component = (attribute.as_f32()?.min(1.0).max(0.0) * 255.0) as u8;
+                    }
+                    colors.push(rgba);
                 }
-                Ok(uvs)
+                Ok(colors)
             },
         )?))
     } else {
@@ -164,6 +196,7 @@ impl FbxGeometry {
             indices: read_indices(geom_node_handle, nodes)?,
             normals: read_normals(geom_node_handle, nodes)?,
             uvs: read_uvs(geom_node_handle, nodes)?,
+            colors: read_colors(geom_node_handle, nodes)?,
             materials: read_materials(geom_node_handle, nodes)?,
             tangents: read_tangents(geom_node_handle, nodes)?,
             binormals: read_binormals(geom_node_handle, nodes)?,
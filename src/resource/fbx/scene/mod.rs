@@ -1,5 +1,6 @@
 use crate::{
     core::{
+        color::Color,
         math::{mat4::Mat4, vec3::Vec3},
         pool::{Handle, Pool, PoolPairIterator},
     },
@@ -7,11 +8,14 @@ use crate::{
         document::{attribute::FbxAttribute, FbxDocument, FbxNode, FbxNodeContainer},
         error::FbxError,
         scene::{
-            animation::{FbxAnimationCurve, FbxAnimationCurveNode},
+            animation::{
+                FbxAnimationCurve, FbxAnimationCurveNode, FbxAnimationLayer, FbxAnimationStack,
+            },
             geometry::FbxGeometry,
             light::FbxLight,
             model::FbxModel,
             texture::FbxTexture,
+            video::FbxVideo,
         },
     },
 };
@@ -22,6 +26,7 @@ pub mod geometry;
 pub mod light;
 pub mod model;
 pub mod texture;
+pub mod video;
 
 pub struct FbxScene {
     components: Pool<FbxComponent>,
@@ -62,8 +67,10 @@ impl FbxScene {
                     )));
                 }
                 "Material" => {
-                    component_handle = components
-                        .spawn(FbxComponent::Material(FbxMaterial::read(*object_handle)?));
+                    component_handle = components.spawn(FbxComponent::Material(FbxMaterial::read(
+                        *object_handle,
+                        nodes,
+                    )?));
                 }
                 "Texture" => {
                     component_handle = components.spawn(FbxComponent::Texture(FbxTexture::read(
@@ -71,6 +78,10 @@ impl FbxScene {
                         nodes,
                     )?));
                 }
+                "Video" => {
+                    component_handle = components
+                        .spawn(FbxComponent::Video(FbxVideo::read(*object_handle, nodes)?));
+                }
                 "NodeAttribute" => {
                     if object.attrib_count() > 2 && object.get_attrib(2)?.as_string() == "Light" {
                         component_handle = components
@@ -82,6 +93,17 @@ impl FbxScene {
                         FbxAnimationCurve::read(*object_handle, nodes)?,
                     ));
                 }
+                "AnimationStack" => {
+                    component_handle = components.spawn(FbxComponent::AnimationStack(
+                        FbxAnimationStack::read(*object_handle, nodes)?,
+                    ));
+                }
+                "AnimationLayer" => {
+                    component_handle =
+                        components.spawn(FbxComponent::AnimationLayer(FbxAnimationLayer {
+                            curve_nodes: Vec::new(),
+                        }));
+                }
                 "AnimationCurveNode" => {
                     component_handle = components.spawn(FbxComponent::AnimationCurveNode(
                         FbxAnimationCurveNode::read(*object_handle, nodes)?,
@@ -160,10 +182,28 @@ fn link_child_with_parent_component(
                 material.textures.push((property, child_handle));
             }
         }
+        // Link texture with embedded media
+        FbxComponent::Texture(texture) => {
+            if let FbxComponent::Video(_) = child {
+                texture.video = child_handle;
+            }
+        }
         // Link animation curve node with animation curve
         FbxComponent::AnimationCurveNode(anim_curve_node) => {
             if let FbxComponent::AnimationCurve(_) = child {
-                anim_curve_node.curves.push(child_handle);
+                anim_curve_node.add_curve(&property, child_handle);
+            }
+        }
+        // Link animation stack with its layers
+        FbxComponent::AnimationStack(stack) => {
+            if let FbxComponent::AnimationLayer(_) = child {
+                stack.layers.push(child_handle);
+            }
+        }
+        // Link animation layer with curve nodes
+        FbxComponent::AnimationLayer(layer) => {
+            if let FbxComponent::AnimationCurveNode(_) = child {
+                layer.curve_nodes.push(child_handle);
             }
         }
         // Link deformer with sub-deformers
@@ -182,7 +222,11 @@ fn link_child_with_parent_component(
         FbxComponent::SubDeformer(sub_deformer) => {
             if let FbxComponent::Model(model) = child {
                 sub_deformer.model = child_handle;
-                model.inv_bind_transform = sub_deformer.transform;
+                // Clusters without weights have no bind transform, they must not override
+                // bind pose that came from other clusters of the bone.
+                if let Some(transform) = sub_deformer.transform {
+                    model.inv_bind_transform = transform;
+                }
             }
         }
         // Ignore rest
@@ -194,9 +238,12 @@ pub enum FbxComponent {
     Deformer(FbxDeformer),
     SubDeformer(FbxSubDeformer),
     Texture(FbxTexture),
+    Video(FbxVideo),
     Light(FbxLight),
     Model(Box<FbxModel>),
     Material(FbxMaterial),
+    AnimationStack(FbxAnimationStack),
+    AnimationLayer(FbxAnimationLayer),
     AnimationCurveNode(FbxAnimationCurveNode),
     AnimationCurve(FbxAnimationCurve),
     Geometry(Box<FbxGeometry>),
@@ -218,6 +265,7 @@ impl FbxComponent {
     define_as!(self, as_deformer, FbxDeformer, Deformer);
     define_as!(self, as_sub_deformer, FbxSubDeformer, SubDeformer);
     define_as!(self, as_texture, FbxTexture, Texture);
+    define_as!(self, as_video, FbxVideo, Video);
     define_as!(self, as_light, FbxLight, Light);
     define_as!(self, as_material, FbxMaterial, Material);
     define_as!(self, as_geometry, FbxGeometry, Geometry);
@@ -229,7 +277,8 @@ const FBX_TIME_UNIT: f64 = 1.0 / 46_186_158_000.0;
 pub struct FbxSubDeformer {
     model: Handle<FbxComponent>,
     weights: Vec<(i32, f32)>,
-    transform: Mat4,
+    /// Inverse bind transform of the bone, stored in bone space.
+    transform: Option<Mat4>,
 }
 
 impl FbxSubDeformer {
@@ -270,7 +319,7 @@ impl FbxSubDeformer {
             let mut sub_deformer = FbxSubDeformer {
                 model: Handle::NONE,
                 weights: Vec::with_capacity(weights.attrib_count()),
-                transform,
+                transform: Some(transform),
             };
 
             for i in 0..weights.attrib_count() {
@@ -285,7 +334,7 @@ impl FbxSubDeformer {
             Ok(FbxSubDeformer {
                 model: Handle::NONE,
                 weights: Default::default(),
-                transform: Default::default(),
+                transform: None,
             })
         }
     }
@@ -293,13 +342,44 @@ impl FbxSubDeformer {
 
 pub struct FbxMaterial {
     pub textures: Vec<(String, Handle<FbxComponent>)>,
+    pub diffuse_color: Color,
+    pub emissive_color: Color,
+    pub emissive_factor: f32,
+}
+
+fn read_color(property: &FbxNode) -> Result<Color, String> {
+    let r = (property.get_attrib(4)?.as_f64()?.min(1.0).max(0.0) * 255.0) as u8;
+    let g = (property.get_attrib(5)?.as_f64()?.min(1.0).max(0.0) * 255.0) as u8;
+    let b = (property.get_attrib(6)?.as_f64()?.min(1.0).max(0.0) * 255.0) as u8;
+    Ok(Color::opaque(r, g, b))
 }
 
 impl FbxMaterial {
-    fn read(_material_node_handle: Handle<FbxNode>) -> Result<FbxMaterial, String> {
-        Ok(FbxMaterial {
+    fn read(
+        material_node_handle: Handle<FbxNode>,
+        nodes: &FbxNodeContainer,
+    ) -> Result<FbxMaterial, String> {
+        let mut material = FbxMaterial {
             textures: Default::default(),
-        })
+            diffuse_color: Color::WHITE,
+            emissive_color: Color::opaque(0, 0, 0),
+            emissive_factor: 1.0,
+        };
+
+        // Properties are optional, some exporters do not write them at all.
+        if let Ok(props) = nodes.get_by_name(material_node_handle, "Properties70") {
+            for prop_handle in props.children() {
+                let prop = nodes.get(*prop_handle);
+                match prop.get_attrib(0)?.as_string().as_str() {
+                    "DiffuseColor" => material.diffuse_color = read_color(prop)?,
+                    "EmissiveColor" => material.emissive_color = read_color(prop)?,
+                    "EmissiveFactor" => material.emissive_factor = prop.get_attrib(4)?.as_f32()?,
+                    _ => (),
+                }
+            }
+        }
+
+        Ok(material)
     }
 }
 
@@ -390,10 +470,13 @@ impl<T> FbxContainer<T> {
         // See: https://developer.blender.org/D402
         if data_name.as_ref() != "Materials" {
             if reference == FbxReference::IndexToDirect {
-                let index_node = nodes.find(
-                    container_node,
-                    format!("{}Index", data_name.as_ref()).as_str(),
-                )?;
+                // Index array of colors does not follow naming scheme of other arrays.
+                let index_name = if data_name.as_ref() == "Colors" {
+                    String::from("ColorIndex")
+                } else {
+                    format!("{}Index", data_name.as_ref())
+                };
+                let index_node = nodes.find(container_node, index_name.as_str())?;
                 let index_array_node = nodes.get_by_name(index_node, "a")?;
                 for attribute in index_array_node.attributes() {
                     index.push(attribute.as_i32()?);
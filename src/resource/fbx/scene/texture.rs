@@ -1,11 +1,16 @@
 use crate::{
     core::pool::Handle,
-    resource::fbx::document::{FbxNode, FbxNodeContainer},
+    resource::fbx::{
+        document::{FbxNode, FbxNodeContainer},
+        scene::FbxComponent,
+    },
 };
 use std::path::PathBuf;
 
 pub struct FbxTexture {
     filename: PathBuf,
+    /// Handle to video component with embedded content, if any.
+    pub video: Handle<FbxComponent>,
 }
 
 impl FbxTexture {
@@ -15,6 +20,7 @@ impl FbxTexture {
     ) -> Result<Self, String> {
         let mut texture = FbxTexture {
             filename: PathBuf::new(),
+            video: Handle::NONE,
         };
         if let Ok(relative_file_name_node) =
             nodes.get_by_name(texture_node_handle, "RelativeFilename")
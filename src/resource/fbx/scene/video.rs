@@ -0,0 +1,49 @@
+use crate::{
+    core::pool::Handle,
+    resource::fbx::document::{FbxNode, FbxNodeContainer},
+};
+use std::path::PathBuf;
+
+/// Video is a container for embedded media, usually it is a texture that was embedded
+/// into FBX file by exporter. Only binary FBX can have embedded media.
+pub struct FbxVideo {
+    filename: PathBuf,
+    content: Vec<u8>,
+}
+
+impl FbxVideo {
+    pub(in crate::resource::fbx) fn read(
+        video_node_handle: Handle<FbxNode>,
+        nodes: &FbxNodeContainer,
+    ) -> Result<Self, String> {
+        let mut video = FbxVideo {
+            filename: PathBuf::new(),
+            content: Vec::new(),
+        };
+        if let Ok(relative_file_name_node) =
+            nodes.get_by_name(video_node_handle, "RelativeFilename")
+        {
+            let str_path = relative_file_name_node
+                .get_attrib(0)?
+                .as_string()
+                .replace("\\", "/");
+            video.filename = PathBuf::from(str_path);
+        }
+        if let Ok(content_node) = nodes.get_by_name(video_node_handle, "Content") {
+            // Content can be empty if media is not embedded, or it can be stored as base64
+            // string in ASCII FBX which is not supported.
+            if let Ok(data) = content_node.get_attrib(0).and_then(|a| a.as_raw_data()) {
+                video.content = data.to_vec();
+            }
+        }
+        Ok(video)
+    }
+
+    pub(in crate::resource::fbx) fn get_file_path(&self) -> &PathBuf {
+        &self.filename
+    }
+
+    pub(in crate::resource::fbx) fn content(&self) -> &[u8] {
+        &self.content
+    }
+}
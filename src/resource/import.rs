@@ -0,0 +1,363 @@
+//! Import options of model resources.
+//!
+//! Different 3d modelling software use different conventions - Blender uses Z axis as up
+//! axis, 3ds Max works in centimeters or inches by default, some tools export left-handed
+//! coordinates, etc. Import options allow to convert models to engine conventions (Y up,
+//! right-handed, meters) at load time, so there is no need to scale or rotate instances
+//! manually.
+//!
+//! Options are remembered per model in a sidecar file next to the model, it has same name
+//! as the model plus `.import` extension (`models/tree.fbx.import` for `models/tree.fbx`).
+//! Sidecar file is a simple text file which can be edited by hand:
+//!
+//! ```text
+//! scale = 1.0
+//! units = centimeters
+//! up_axis = z
+//! handedness = right
+//! generate_tangents = false
+//! smoothing_angle = 60
+//! ```
+//!
+//! Every line is optional, missing lines have default values. Lines starting with `#` are
+//! comments.
+
+use crate::{
+    core::math::{
+        quat::{Quat, RotationOrder},
+        vec3::Vec3,
+    },
+    renderer::surface::SurfaceSharedData,
+    resource::vfs::VirtualFileSystem,
+    scene::{base::BaseBuilder, node::Node, transform::TransformBuilder, Scene},
+};
+use std::{
+    collections::HashSet,
+    fmt::{Display, Formatter},
+    path::{Path, PathBuf},
+    str::FromStr,
+    sync::{Arc, Mutex},
+};
+
+/// Unit of length that is used in source file.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum LengthUnit {
+    /// Meters, engine unit.
+    Meters,
+    /// Centimeters, default unit of many tools (3ds Max, Maya).
+    Centimeters,
+    /// Millimeters.
+    Millimeters,
+    /// Inches.
+    Inches,
+    /// Feet.
+    Feet,
+}
+
+impl LengthUnit {
+    /// Returns size of the unit in meters.
+    pub fn to_meters(self) -> f32 {
+        match self {
+            LengthUnit::Meters => 1.0,
+            LengthUnit::Centimeters => 0.01,
+            LengthUnit::Millimeters => 0.001,
+            LengthUnit::Inches => 0.0254,
+            LengthUnit::Feet => 0.3048,
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            LengthUnit::Meters => "meters",
+            LengthUnit::Centimeters => "centimeters",
+            LengthUnit::Millimeters => "millimeters",
+            LengthUnit::Inches => "inches",
+            LengthUnit::Feet => "feet",
+        }
+    }
+}
+
+/// Up axis of source file.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum UpAxis {
+    /// Y is up, engine convention.
+    Y,
+    /// Z is up (Blender, 3ds Max).
+    Z,
+}
+
+/// Handedness of coordinate system of source file.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Handedness {
+    /// Right-handed coordinate system, engine convention.
+    Right,
+    /// Left-handed coordinate system. Model will be mirrored by Z axis.
+    Left,
+}
+
+/// Options that are applied to a model when it is loaded. See module docs.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ModelImportOptions {
+    /// Uniform scale which is applied on top of unit conversion.
+    pub scale: f32,
+    /// Unit of length of source file.
+    pub units: LengthUnit,
+    /// Up axis of source file.
+    pub up_axis: UpAxis,
+    /// Handedness of source file.
+    pub handedness: Handedness,
+    /// Recalculate tangents of every surface. Loaders always calculate tangents, so it is
+    /// needed only if tangents are broken. Tangents are always recalculated if normals
+    /// are recalculated.
+    pub generate_tangents: bool,
+    /// If set, normals of every surface are recalculated. Faces with angle between them
+    /// less or equal than given angle (in degrees) are smoothed together.
+    pub smoothing_angle: Option<f32>,
+}
+
+impl Default for ModelImportOptions {
+    fn default() -> Self {
+        Self {
+            scale: 1.0,
+            units: LengthUnit::Meters,
+            up_axis: UpAxis::Y,
+            handedness: Handedness::Right,
+            generate_tangents: false,
+            smoothing_angle: None,
+        }
+    }
+}
+
+impl Display for ModelImportOptions {
+    fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
+        writeln!(f, "scale = {}", self.scale)?;
+        writeln!(f, "units = {}", self.units.name())?;
+        writeln!(
+            f,
+            "up_axis = {}",
+            match self.up_axis {
+                UpAxis::Y => "y",
+                UpAxis::Z => "z",
+            }
+        )?;
+        writeln!(
+            f,
+            "handedness = {}",
+            match self.handedness {
+                Handedness::Right => "right",
+                Handedness::Left => "left",
+            }
+        )?;
+        writeln!(f, "generate_tangents = {}", self.generate_tangents)?;
+        if let Some(angle) = self.smoothing_angle {
+            writeln!(f, "smoothing_angle = {}", angle)?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for ModelImportOptions {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut options = ModelImportOptions::default();
+        for (i, line) in s.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let mut parts = line.splitn(2, '=');
+            let key = parts.next().unwrap_or_default().trim();
+            let value = parts
+                .next()
+                .ok_or_else(|| format!("Line {}: expected key = value", i + 1))?
+                .trim()
+                .to_lowercase();
+            let invalid = || format!("Line {}: invalid value {} of {}", i + 1, value, key);
+            match key {
+                "scale" => options.scale = value.parse().map_err(|_| invalid())?,
+                "units" => {
+                    options.units = match value.as_str() {
+                        "meters" | "m" => LengthUnit::Meters,
+                        "centimeters" | "cm" => LengthUnit::Centimeters,
+                        "millimeters" | "mm" => LengthUnit::Millimeters,
+                        "inches" | "in" => LengthUnit::Inches,
+                        "feet" | "ft" => LengthUnit::Feet,
+                        _ => return Err(invalid()),
+                    }
+                }
+                "up_axis" => {
+                    options.up_axis = match value.as_str() {
+                        "y" => UpAxis::Y,
+                        "z" => UpAxis::Z,
+                        _ => return Err(invalid()),
+                    }
+                }
+                "handedness" => {
+                    options.handedness = match value.as_str() {
+                        "right" => Handedness::Right,
+                        "left" => Handedness::Left,
+                        _ => return Err(invalid()),
+                    }
+                }
+                "generate_tangents" => {
+                    options.generate_tangents = value.parse().map_err(|_| invalid())?
+                }
+                "smoothing_angle" => {
+                    options.smoothing_angle = Some(value.parse().map_err(|_| invalid())?)
+                }
+                _ => return Err(format!("Line {}: unknown option {}", i + 1, key)),
+            }
+        }
+        Ok(options)
+    }
+}
+
+impl ModelImportOptions {
+    /// Returns path of sidecar file of a model at given path.
+    pub fn sidecar_path<P: AsRef<Path>>(model_path: P) -> PathBuf {
+        let mut path = model_path.as_ref().as_os_str().to_owned();
+        path.push(".import");
+        PathBuf::from(path)
+    }
+
+    /// Reads options of a model at given path from its sidecar file. Returns default options
+    /// if there is no sidecar file.
+    pub(in crate) fn load(
+        vfs: &Mutex<VirtualFileSystem>,
+        model_path: &Path,
+    ) -> Result<Self, String> {
+        let path = Self::sidecar_path(model_path);
+        let vfs = vfs.lock().unwrap();
+        if !vfs.exists(&path) {
+            return Ok(Default::default());
+        }
+        let data = vfs.read(&path).map_err(|e| e.to_string())?;
+        String::from_utf8_lossy(&data)
+            .parse()
+            .map_err(|e| format!("{:?}: {}", path, e))
+    }
+
+    /// Writes options into sidecar file of a model at given path.
+    pub fn save<P: AsRef<Path>>(&self, model_path: P) -> std::io::Result<()> {
+        std::fs::write(Self::sidecar_path(model_path), self.to_string())
+    }
+
+    /// Returns true if options change geometry or transforms of a model.
+    pub fn is_identity(&self) -> bool {
+        self.scale * self.units.to_meters() == 1.0
+            && self.up_axis == UpAxis::Y
+            && self.handedness == Handedness::Right
+            && !self.generate_tangents
+            && self.smoothing_angle.is_none()
+    }
+
+    /// Applies options to scene of a model.
+    ///
+    /// Scale and axis conversion are done by new node which is inserted between root of the
+    /// scene and its children, this way animations and skinning are left intact. Mirroring
+    /// flips winding order of triangles and handedness of tangents, so mirrored meshes are
+    /// still rendered correctly.
+    pub(in crate) fn apply(&self, scene: &mut Scene) {
+        if self.is_identity() {
+            return;
+        }
+
+        let mirror = self.handedness == Handedness::Left;
+
+        // Surfaces can share data, so process each data only once.
+        let mut processed = HashSet::new();
+        for node in scene.graph.linear_iter_mut() {
+            if let Node::Mesh(mesh) = node {
+                for surface in mesh.surfaces_mut() {
+                    let data = surface.data();
+                    if processed.insert(Arc::as_ptr(&data) as usize) {
+                        self.apply_to_data(&mut data.lock().unwrap(), mirror);
+                    }
+                }
+            }
+        }
+
+        let scale = self.scale * self.units.to_meters();
+        let rotation = match self.up_axis {
+            UpAxis::Y => Quat::IDENTITY,
+            UpAxis::Z => Quat::from_euler(
+                Vec3::new(-std::f32::consts::FRAC_PI_2, 0.0, 0.0),
+                RotationOrder::XYZ,
+            ),
+        };
+        if scale != 1.0 || self.up_axis != UpAxis::Y || mirror {
+            let root = scene.graph.get_root();
+            let children = scene.graph[root].children().to_vec();
+            let conversion = scene.graph.add_node(
+                BaseBuilder::new()
+                    .with_name("ImportConversion")
+                    .with_local_transform(
+                        TransformBuilder::new()
+                            .with_local_rotation(rotation)
+                            .with_local_scale(Vec3::new(
+                                scale,
+                                scale,
+                                if mirror { -scale } else { scale },
+                            ))
+                            .build(),
+                    )
+                    .build_node(),
+            );
+            scene.graph.link_nodes(conversion, root);
+            for child in children {
+                scene.graph.link_nodes(child, conversion);
+            }
+            scene.graph.update_hierachical_data();
+        }
+    }
+
+    fn apply_to_data(&self, data: &mut SurfaceSharedData, mirror: bool) {
+        // Normals must be calculated before winding order is flipped, otherwise they will
+        // point inside.
+        if let Some(angle) = self.smoothing_angle {
+            data.calculate_smooth_normals(angle.to_radians());
+        }
+        if self.generate_tangents || self.smoothing_angle.is_some() {
+            data.calculate_tangents();
+        }
+        if mirror {
+            // Negative scale of parent node flips winding order, flip it back.
+            for triangle in data.triangles.iter_mut() {
+                triangle.0.swap(1, 2);
+            }
+            // Mirroring also changes handedness of tangent space.
+            for vertex in data.get_vertices_mut() {
+                vertex.tangent.w = -vertex.tangent.w;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::resource::import::{Handedness, LengthUnit, ModelImportOptions, UpAxis};
+
+    #[test]
+    fn parse_test() {
+        let options = "# Exported from Max\nscale = 2\nunits = cm\nup_axis = Z\nhandedness = left\nsmoothing_angle = 45\n"
+            .parse::<ModelImportOptions>()
+            .unwrap();
+        assert_eq!(options.scale, 2.0);
+        assert_eq!(options.units, LengthUnit::Centimeters);
+        assert_eq!(options.up_axis, UpAxis::Z);
+        assert_eq!(options.handedness, Handedness::Left);
+        assert!(!options.generate_tangents);
+        assert_eq!(options.smoothing_angle, Some(45.0));
+
+        // Round trip.
+        assert_eq!(
+            options.to_string().parse::<ModelImportOptions>().unwrap(),
+            options
+        );
+
+        assert!("units = parsecs".parse::<ModelImportOptions>().is_err());
+        assert!("foo = 1".parse::<ModelImportOptions>().is_err());
+        assert!(ModelImportOptions::default().is_identity());
+    }
+}
@@ -2,6 +2,31 @@
 
 //!
 
+pub mod atlas;
+pub mod cache;
+pub mod custom;
 pub mod fbx;
+pub mod import;
 pub mod model;
+pub mod obj;
+pub mod pack;
 pub mod texture;
+pub mod ui_layout;
+pub mod vfs;
+
+/// State of a resource that can be loaded asynchronously.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum ResourceState {
+    /// Resource is being loaded on a worker thread, its data is not available yet.
+    Pending,
+    /// Resource is fully loaded and ready to use.
+    Ok,
+    /// Resource failed to load, reason was printed to the log.
+    LoadError,
+}
+
+impl Default for ResourceState {
+    fn default() -> Self {
+        ResourceState::Ok
+    }
+}
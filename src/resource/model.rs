@@ -15,8 +15,19 @@
 //!
 //! # Supported formats
 //!
-//! Currently only FBX (common format in game industry for storing complex 3d models)
-//! and RGS (native rusty-editor format) formats are supported.
+//! Currently FBX (common format in game industry for storing complex 3d models),
+//! OBJ (simple format for static meshes) and RGS (native rusty-editor format) formats
+//! are supported.
+//!
+//! # Derived data cache
+//!
+//! Converted FBX and OBJ models are stored in [derived data cache](crate::resource::cache),
+//! if the cache is enabled in resource manager.
+//!
+//! # Import options
+//!
+//! Scale, unit and axis conversion, normals and tangents recalculation can be applied to a
+//! model at load time, see [import](crate::resource::import) module docs.
 use crate::{
     animation::Animation,
     core::{
@@ -24,11 +35,15 @@ use crate::{
         visitor::{Visit, VisitError, VisitResult, Visitor},
     },
     engine::resource_manager::ResourceManager,
-    resource::{fbx, fbx::error::FbxError},
+    resource::{
+        cache::CacheKey, fbx, fbx::error::FbxError, import::ModelImportOptions, obj,
+        obj::error::ObjError, texture::Texture,
+    },
     scene::{node::Node, Scene},
     utils::log::Log,
 };
 use std::{
+    collections::{HashMap, HashSet},
     path::{Path, PathBuf},
     sync::{Arc, Mutex, Weak},
 };
@@ -40,6 +55,7 @@ pub struct Model {
     pub(in crate) self_weak_ref: Option<Weak<Mutex<Model>>>,
     pub(in crate) path: PathBuf,
     scene: Scene,
+    import_options: ModelImportOptions,
 }
 
 impl Default for Model {
@@ -48,6 +64,7 @@ impl Default for Model {
             self_weak_ref: None,
             path: PathBuf::new(),
             scene: Scene::new(),
+            import_options: Default::default(),
         }
     }
 }
@@ -97,6 +114,10 @@ pub enum ModelLoadError {
     NotSupported(String),
     /// An error occurred while loading FBX file.
     Fbx(FbxError),
+    /// An error occurred while loading OBJ file.
+    Obj(ObjError),
+    /// Sidecar file with import options is invalid.
+    ImportOptions(String),
 }
 
 impl From<FbxError> for ModelLoadError {
@@ -105,12 +126,187 @@ impl From<FbxError> for ModelLoadError {
     }
 }
 
+impl From<ObjError> for ModelLoadError {
+    fn from(obj: ObjError) -> Self {
+        ModelLoadError::Obj(obj)
+    }
+}
+
 impl From<VisitError> for ModelLoadError {
     fn from(e: VisitError) -> Self {
         ModelLoadError::Visit(e)
     }
 }
 
+/// Extension of converted models in derived data cache.
+const CACHE_EXTENSION: &str = "model";
+
+fn convert_to_scene(
+    path: &Path,
+    extension: &str,
+    resource_manager: &mut ResourceManager,
+) -> Result<Scene, ModelLoadError> {
+    let mut scene = Scene::new();
+    if extension == "fbx" {
+        fbx::load_to_scene(&mut scene, resource_manager, path)?;
+    } else {
+        obj::load_to_scene(&mut scene, resource_manager, path)?;
+    }
+    Ok(scene)
+}
+
+/// Loads FBX or OBJ model, takes converted scene from derived data cache if possible.
+fn load_converted(
+    path: &Path,
+    extension: &str,
+    resource_manager: &mut ResourceManager,
+) -> Result<Scene, ModelLoadError> {
+    let cache_entry = resource_manager.derived_data_cache().and_then(|cache| {
+        let source = resource_manager.vfs().lock().unwrap().read(path).ok()?;
+        // Path to textures affects conversion, so it must be a part of the key.
+        let textures_path = resource_manager.textures_path().to_string_lossy();
+        let key = CacheKey::new(&source, &[textures_path.as_bytes()]);
+        Some(cache.entry_path(key, CACHE_EXTENSION))
+    });
+
+    if let Some(entry) = cache_entry.as_ref() {
+        if entry.exists() {
+            match read_cached_scene(entry, resource_manager) {
+                Ok(scene) => return Ok(scene),
+                Err(e) => Log::writeln(format!(
+                    "Unable to read cached model {:?}, it will be converted again. Reason: {:?}",
+                    path, e
+                )),
+            }
+        }
+    }
+
+    let mut scene = convert_to_scene(path, extension, resource_manager)?;
+
+    if let Some(entry) = cache_entry.as_ref() {
+        if let Err(e) = write_cached_scene(&mut scene, entry) {
+            Log::writeln(format!(
+                "Unable to write {:?} model to cache. Reason: {:?}",
+                path, e
+            ));
+        }
+    }
+
+    Ok(scene)
+}
+
+fn surface_data(scene: &Scene) -> Vec<Arc<Mutex<SurfaceSharedData>>> {
+    let mut processed = HashSet::new();
+    let mut result = Vec::new();
+    for node in scene.graph.linear_iter() {
+        if let Node::Mesh(mesh) = node {
+            for surface in mesh.surfaces() {
+                let data = surface.data();
+                if processed.insert(Arc::as_ptr(&data) as usize) {
+                    result.push(data);
+                }
+            }
+        }
+    }
+    result
+}
+
+fn visit_cached_scene(scene: &mut Scene, visitor: &mut Visitor) -> VisitResult {
+    visitor.enter_region("CachedModel")?;
+
+    scene.visit("Scene", visitor)?;
+
+    // Key frames and bind poses are not serialized by scene, because save files take them
+    // from resources, but cache is the resource itself.
+    visitor.enter_region("KeyFrames")?;
+    for (i, animation) in scene.animations.iter_mut().enumerate() {
+        for (j, track) in animation.get_tracks_mut().iter_mut().enumerate() {
+            let mut key_frames = track.get_key_frames().to_vec();
+            key_frames.visit(&format!("Track{}_{}", i, j), visitor)?;
+            if visitor.is_reading() {
+                track.set_key_frames(&key_frames);
+            }
+        }
+    }
+    visitor.leave_region()?;
+
+    visitor.enter_region("BindPoses")?;
+    for (i, node) in scene.graph.linear_iter_mut().enumerate() {
+        node.inv_bind_pose_transform
+            .visit(&format!("Node{}", i), visitor)?;
+    }
+    visitor.leave_region()?;
+
+    visitor.leave_region()
+}
+
+fn write_cached_scene(scene: &mut Scene, path: &Path) -> VisitResult {
+    // Surface data of models is not serialized, mark it as procedural for a while to force
+    // serialization.
+    let data = surface_data(scene);
+    for data in data.iter() {
+        data.lock().unwrap().set_procedural(true);
+    }
+
+    let mut visitor = Visitor::new();
+    let result = visit_cached_scene(scene, &mut visitor);
+
+    for data in data.iter() {
+        data.lock().unwrap().set_procedural(false);
+    }
+    result?;
+
+    // Write to temporary file first, so other processes will never see partially written
+    // entry.
+    let temp_path = path.with_extension("tmp");
+    visitor.save_binary(&temp_path)?;
+    std::fs::rename(temp_path, path)?;
+    Ok(())
+}
+
+fn read_cached_scene(
+    path: &Path,
+    resource_manager: &mut ResourceManager,
+) -> Result<Scene, VisitError> {
+    let mut scene = Scene::new();
+    let mut visitor = Visitor::load_binary(path)?;
+    visit_cached_scene(&mut scene, &mut visitor)?;
+
+    for data in surface_data(&scene) {
+        data.lock().unwrap().set_procedural(false);
+    }
+
+    // Scene stores only paths and kinds of textures, request real textures instead.
+    let mut request = |texture: Option<Arc<Mutex<Texture>>>| {
+        texture.map(|texture| {
+            let texture = texture.lock().unwrap();
+            resource_manager.request_texture_async(&texture.path, texture.kind)
+        })
+    };
+    for node in scene.graph.linear_iter_mut() {
+        if let Node::Mesh(mesh) = node {
+            for surface in mesh.surfaces_mut() {
+                if let Some(texture) = request(surface.diffuse_texture()) {
+                    surface.set_diffuse_texture(texture);
+                }
+                if let Some(texture) = request(surface.normal_texture()) {
+                    surface.set_normal_texture(texture);
+                }
+                if let Some(texture) = request(surface.specular_texture()) {
+                    surface.set_specular_texture(texture);
+                }
+                if let Some(texture) = request(surface.lightmap_texture()) {
+                    surface.set_lightmap_texture(texture);
+                }
+            }
+        }
+    }
+
+    scene.graph.update_hierachical_data();
+
+    Ok(scene)
+}
+
 impl Model {
     pub(in crate) fn load<P: AsRef<Path>>(
         path: P,
@@ -123,12 +319,11 @@ impl Model {
             .to_string_lossy()
             .as_ref()
             .to_lowercase();
-        let scene = match extension.as_ref() {
-            "fbx" => {
-                let mut scene = Scene::new();
-                fbx::load_to_scene(&mut scene, resource_manager, path.as_ref())?;
-                scene
-            }
+        let import_options = ModelImportOptions::load(&resource_manager.vfs(), path.as_ref())
+            .map_err(ModelLoadError::ImportOptions)?;
+
+        let mut scene = match extension.as_ref() {
+            "fbx" | "obj" => load_converted(path.as_ref(), &extension, resource_manager)?,
             // Scene can be used directly as model resource. Such scenes can be created from
             // rusty-editor (https://github.com/mrDIMAS/rusty-editor) for example.
             "rgs" => Scene::from_file(path.as_ref(), resource_manager)?,
@@ -141,10 +336,13 @@ impl Model {
             }
         };
 
+        import_options.apply(&mut scene);
+
         Ok(Model {
             self_weak_ref: None,
             path: path.as_ref().to_owned(),
             scene,
+            import_options,
         })
     }
 
@@ -202,44 +400,77 @@ impl Model {
     ///
     /// # Notes
     ///
-    /// Most of the 3d model formats can contain only one animation, so in most cases
-    /// this function will return vector with only one animation.
+    /// FBX files can contain multiple animations (takes), every take is retargetted as a
+    /// separate animation with the name of the take. Use
+    /// [retarget_animation](Self::retarget_animation) to retarget only specific one.
     pub fn retarget_animations(
         &self,
         root: Handle<Node>,
         dest_scene: &mut Scene,
     ) -> Vec<Handle<Animation>> {
-        let mut animation_handles = Vec::new();
-
-        for ref_anim in self.scene.animations.iter() {
-            let mut anim_copy = ref_anim.clone();
-
-            // Keep reference to resource from which this animation was taken from. This will help
-            // us to correctly reload keyframes for each track when we'll be loading a save file.
-            anim_copy.resource = Some(upgrade_self_weak_ref(&self.self_weak_ref));
-
-            // Remap animation track nodes from resource to instance. This is required
-            // because we've made a plain copy and it has tracks with node handles mapped
-            // to nodes of internal scene.
-            for (i, ref_track) in ref_anim.get_tracks().iter().enumerate() {
-                let ref_node = &self.scene.graph[ref_track.get_node()];
-                // Find instantiated node that corresponds to node in resource
-                let instance_node = dest_scene.graph.find_by_name(root, ref_node.name());
-                if instance_node.is_none() {
-                    Log::writeln(format!(
-                        "Failed to retarget animation {:?} for node {}",
-                        self.path,
-                        ref_node.name()
-                    ));
-                }
-                // One-to-one track mapping so there is [i] indexing.
-                anim_copy.get_tracks_mut()[i].set_node(instance_node);
-            }
+        self.scene
+            .animations
+            .iter()
+            .map(|ref_anim| self.retarget(ref_anim, root, dest_scene))
+            .collect()
+    }
+
+    /// Retargets animation with given name from the model to a node hierarchy starting from
+    /// `root` on a given scene. Returns None if there is no such animation. See
+    /// [retarget_animations](Self::retarget_animations) for more info.
+    pub fn retarget_animation(
+        &self,
+        name: &str,
+        root: Handle<Node>,
+        dest_scene: &mut Scene,
+    ) -> Option<Handle<Animation>> {
+        self.scene
+            .animations
+            .iter()
+            .find(|animation| animation.name() == name)
+            .map(|ref_anim| self.retarget(ref_anim, root, dest_scene))
+    }
+
+    /// Returns names of all animations of the model.
+    pub fn animation_names(&self) -> Vec<&str> {
+        self.scene
+            .animations
+            .iter()
+            .map(|animation| animation.name())
+            .collect()
+    }
+
+    fn retarget(
+        &self,
+        ref_anim: &Animation,
+        root: Handle<Node>,
+        dest_scene: &mut Scene,
+    ) -> Handle<Animation> {
+        let mut anim_copy = ref_anim.clone();
 
-            animation_handles.push(dest_scene.animations.add(anim_copy));
+        // Keep reference to resource from which this animation was taken from. This will help
+        // us to correctly reload keyframes for each track when we'll be loading a save file.
+        anim_copy.resource = Some(upgrade_self_weak_ref(&self.self_weak_ref));
+
+        // Remap animation track nodes from resource to instance. This is required
+        // because we've made a plain copy and it has tracks with node handles mapped
+        // to nodes of internal scene.
+        for (i, ref_track) in ref_anim.get_tracks().iter().enumerate() {
+            let ref_node = &self.scene.graph[ref_track.get_node()];
+            // Find instantiated node that corresponds to node in resource
+            let instance_node = dest_scene.graph.find_by_name(root, ref_node.name());
+            if instance_node.is_none() {
+                Log::writeln(format!(
+                    "Failed to retarget animation {:?} for node {}",
+                    self.path,
+                    ref_node.name()
+                ));
+            }
+            // One-to-one track mapping so there is [i] indexing.
+            anim_copy.get_tracks_mut()[i].set_node(instance_node);
         }
 
-        animation_handles
+        dest_scene.animations.add(anim_copy)
     }
 
     /// Returns shared reference to internal scene, there is no way to obtain
@@ -249,9 +480,60 @@ impl Model {
         &self.scene
     }
 
+    /// Returns list of textures which are used by the model.
+    pub fn dependencies(&self) -> Vec<Arc<Mutex<Texture>>> {
+        self.scene.dependencies().textures
+    }
+
+    /// Returns import options that were applied to the model when it was loaded.
+    pub fn import_options(&self) -> &ModelImportOptions {
+        &self.import_options
+    }
+
     /// Tries to find node in resource by its name. Returns Handle::NONE if
     /// no node was found.
     pub fn find_node_by_name(&self, name: &str) -> Handle<Node> {
         self.scene.graph.find_by_name_from_root(name)
     }
+
+    /// Replaces references to textures and models with other instances, maps are keyed by
+    /// pointers of instances that must be replaced. Used when model was loaded by separate
+    /// resource manager which has loaded some resources for the second time.
+    pub(in crate) fn redirect_resources(
+        &mut self,
+        textures: &HashMap<*const Mutex<Texture>, Arc<Mutex<Texture>>>,
+        models: &HashMap<*const Mutex<Model>, Arc<Mutex<Model>>>,
+    ) {
+        if textures.is_empty() && models.is_empty() {
+            return;
+        }
+        let redirect = |texture: Option<Arc<Mutex<Texture>>>| {
+            texture.and_then(|texture| textures.get(&Arc::as_ptr(&texture)).cloned())
+        };
+        for node in self.scene.graph.linear_iter_mut() {
+            let model = node
+                .resource
+                .as_ref()
+                .and_then(|model| models.get(&Arc::as_ptr(model)));
+            if let Some(model) = model {
+                node.resource = Some(model.clone());
+            }
+            if let Node::Mesh(mesh) = node {
+                for surface in mesh.surfaces_mut() {
+                    if let Some(texture) = redirect(surface.diffuse_texture()) {
+                        surface.set_diffuse_texture(texture);
+                    }
+                    if let Some(texture) = redirect(surface.normal_texture()) {
+                        surface.set_normal_texture(texture);
+                    }
+                    if let Some(texture) = redirect(surface.specular_texture()) {
+                        surface.set_specular_texture(texture);
+                    }
+                    if let Some(texture) = redirect(surface.lightmap_texture()) {
+                        surface.set_lightmap_texture(texture);
+                    }
+                }
+            }
+        }
+    }
 }
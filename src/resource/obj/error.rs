@@ -0,0 +1,33 @@
+//! Contains all possible errors that can occur during OBJ and MTL parsing and conversion.
+
+use std::fmt::Formatter;
+
+/// See module docs.
+#[derive(Debug)]
+pub enum ObjError {
+    /// An input/output error has occurred (unexpected end of file, etc.)
+    Io(std::io::Error),
+    /// Unable to parse a number in a statement. Contains line number.
+    InvalidNumber(usize),
+    /// Statement has insufficient amount of arguments. Contains line number.
+    MissingArgument(usize),
+    /// Face references vertex attribute that does not exist.
+    IndexOutOfBounds,
+}
+
+impl std::fmt::Display for ObjError {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), std::fmt::Error> {
+        match self {
+            ObjError::Io(io) => write!(f, "Io error: {}", io),
+            ObjError::InvalidNumber(line) => write!(f, "Invalid number at line {}", line),
+            ObjError::MissingArgument(line) => write!(f, "Missing argument at line {}", line),
+            ObjError::IndexOutOfBounds => write!(f, "Index out of bounds."),
+        }
+    }
+}
+
+impl From<std::io::Error> for ObjError {
+    fn from(err: std::io::Error) -> Self {
+        ObjError::Io(err)
+    }
+}
@@ -0,0 +1,489 @@
+//! Contains all methods to load and convert Wavefront OBJ model format.
+//!
+//! OBJ is very simple text format to store static meshes, it does not support skinning,
+//! animations, lights, etc. but it is still useful for quick prototyping, because almost
+//! every 3d modelling software can export to it. Materials are loaded from material libraries
+//! (.mtl files) referenced by OBJ file, only diffuse color, dissolve, diffuse and normal maps are
+//! supported.
+//!
+//! Each object (`o`) or group (`g`) in OBJ file will be converted to separate mesh node, each
+//! material usage (`usemtl`) inside object will be converted to separate surface.
+//!
+//! Normally you should never use methods from this module directly, use resource manager to load
+//! models and create their instances.
+
+pub mod error;
+mod mtl;
+
+use crate::{
+    core::{
+        color::Color,
+        math::{triangulator::triangulate, vec2::Vec2, vec3::Vec3, vec4::Vec4},
+        pool::Handle,
+    },
+    engine::resource_manager::ResourceManager,
+    renderer::surface::{Surface, SurfaceSharedData, Vertex},
+    resource::{
+        obj::{
+            error::ObjError,
+            mtl::{MtlMaterial, ObjMaterialLibrary},
+        },
+        texture::TextureKind,
+        vfs::VirtualFileSystem,
+    },
+    scene::{base::Base, mesh::Mesh, node::Node, Scene},
+    utils::{log::Log, raw_mesh::RawMeshBuilder},
+};
+use std::{
+    io::{BufRead, Cursor},
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+    time::Instant,
+};
+
+fn parse_f32(str: &str, line: usize) -> Result<f32, ObjError> {
+    lexical::parse_lossy::<f32, _>(str).map_err(|_| ObjError::InvalidNumber(line))
+}
+
+/// OBJ indices are 1-based and can be negative - in this case they're relative to the
+/// end of list of currently defined attributes.
+fn parse_index(str: &str, count: usize, line: usize) -> Result<usize, ObjError> {
+    let index = lexical::parse::<i64, _>(str).map_err(|_| ObjError::InvalidNumber(line))?;
+    let index = if index < 0 {
+        count as i64 + index
+    } else {
+        index - 1
+    };
+    if index < 0 || index as usize >= count {
+        Err(ObjError::IndexOutOfBounds)
+    } else {
+        Ok(index as usize)
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+struct ObjIndex {
+    position: usize,
+    tex_coord: Option<usize>,
+    normal: Option<usize>,
+}
+
+struct ObjGroup {
+    material: Option<String>,
+    faces: Vec<Vec<ObjIndex>>,
+}
+
+struct ObjObject {
+    name: String,
+    groups: Vec<ObjGroup>,
+}
+
+impl ObjObject {
+    fn new(name: String) -> Self {
+        Self {
+            name,
+            groups: Vec::new(),
+        }
+    }
+
+    fn current_group(&mut self) -> &mut ObjGroup {
+        if self.groups.is_empty() {
+            self.groups.push(ObjGroup {
+                material: None,
+                faces: Vec::new(),
+            });
+        }
+        self.groups.last_mut().unwrap()
+    }
+}
+
+#[derive(Default)]
+struct ObjDocument {
+    positions: Vec<Vec3>,
+    tex_coords: Vec<Vec2>,
+    normals: Vec<Vec3>,
+    objects: Vec<ObjObject>,
+    material_libraries: Vec<PathBuf>,
+}
+
+impl ObjDocument {
+    fn current_object(&mut self) -> &mut ObjObject {
+        if self.objects.is_empty() {
+            self.objects.push(ObjObject::new(String::from("Default")));
+        }
+        self.objects.last_mut().unwrap()
+    }
+
+    fn read_vec3(args: &[&str], line: usize) -> Result<Vec3, ObjError> {
+        if args.len() < 3 {
+            return Err(ObjError::MissingArgument(line));
+        }
+        Ok(Vec3::new(
+            parse_f32(args[0], line)?,
+            parse_f32(args[1], line)?,
+            parse_f32(args[2], line)?,
+        ))
+    }
+
+    fn read_face(&self, args: &[&str], line: usize) -> Result<Vec<ObjIndex>, ObjError> {
+        let mut face = Vec::with_capacity(args.len());
+        for arg in args {
+            // Possible variants: v, v/vt, v//vn, v/vt/vn
+            let mut components = arg.split('/');
+            let position = components.next().ok_or(ObjError::MissingArgument(line))?;
+            let tex_coord = components.next().filter(|s| !s.is_empty());
+            let normal = components.next().filter(|s| !s.is_empty());
+            face.push(ObjIndex {
+                position: parse_index(position, self.positions.len(), line)?,
+                tex_coord: match tex_coord {
+                    Some(tex_coord) => Some(parse_index(tex_coord, self.tex_coords.len(), line)?),
+                    None => None,
+                },
+                normal: match normal {
+                    Some(normal) => Some(parse_index(normal, self.normals.len(), line)?),
+                    None => None,
+                },
+            });
+        }
+        Ok(face)
+    }
+
+    fn read<R: BufRead>(reader: R) -> Result<Self, ObjError> {
+        let mut document = ObjDocument::default();
+
+        for (n, line) in reader.lines().enumerate() {
+            let line = line?;
+            let line_number = n + 1;
+            let mut tokens = line.split_whitespace();
+            let statement = match tokens.next() {
+                Some(statement) if !statement.starts_with('#') => statement,
+                _ => continue,
+            };
+            let args = tokens.collect::<Vec<_>>();
+
+            match statement {
+                "v" => {
+                    let position = Self::read_vec3(&args, line_number)?;
+                    document.positions.push(position);
+                }
+                "vn" => {
+                    let normal = Self::read_vec3(&args, line_number)?;
+                    document.normals.push(normal);
+                }
+                "vt" => {
+                    let u = args.first().ok_or(ObjError::MissingArgument(line_number))?;
+                    // Second coordinate is optional for 1D textures.
+                    let v = args.get(1).unwrap_or(&"0");
+                    document.tex_coords.push(Vec2::new(
+                        parse_f32(u, line_number)?,
+                        parse_f32(v, line_number)?,
+                    ));
+                }
+                "f" => {
+                    if args.len() < 3 {
+                        // Silently ignore invalid faces, same as FBX loader does.
+                        continue;
+                    }
+                    let face = document.read_face(&args, line_number)?;
+                    document.current_object().current_group().faces.push(face);
+                }
+                "o" | "g" => {
+                    let name = args.join(" ");
+                    // Keep material from previous group, because in OBJ usemtl is a state
+                    // which is not reset by object or group statements.
+                    let material = document
+                        .objects
+                        .last()
+                        .and_then(|o| o.groups.last())
+                        .and_then(|g| g.material.clone());
+                    let mut object = ObjObject::new(name);
+                    object.groups.push(ObjGroup {
+                        material,
+                        faces: Vec::new(),
+                    });
+                    document.objects.push(object);
+                }
+                "usemtl" => {
+                    let material = Some(args.join(" "));
+                    document.current_object().groups.push(ObjGroup {
+                        material,
+                        faces: Vec::new(),
+                    });
+                }
+                "mtllib" => {
+                    for library in args {
+                        document
+                            .material_libraries
+                            .push(PathBuf::from(library.replace("\\", "/")));
+                    }
+                }
+                // Smoothing groups, lines, points, free-form geometry, etc. are not supported.
+                _ => (),
+            }
+        }
+
+        Ok(document)
+    }
+}
+
+fn make_surface(
+    document: &ObjDocument,
+    group: &ObjGroup,
+    temp_vertices: &mut Vec<Vec3>,
+    triangles: &mut Vec<[usize; 3]>,
+) -> Result<Surface, ObjError> {
+    let mut builder = RawMeshBuilder::<Vertex>::new(group.faces.len() * 3, group.faces.len() * 3);
+
+    for face in group.faces.iter() {
+        triangles.clear();
+        if face.len() == 3 {
+            triangles.push([0, 1, 2]);
+        } else {
+            temp_vertices.clear();
+            for index in face.iter() {
+                temp_vertices.push(document.positions[index.position]);
+            }
+            triangulate(&temp_vertices, triangles);
+        }
+
+        for triangle in triangles.iter() {
+            let a = document.positions[face[triangle[0]].position];
+            let b = document.positions[face[triangle[1]].position];
+            let c = document.positions[face[triangle[2]].position];
+            // OBJ does not require normals to be present, so use face normal if there is none.
+            let face_normal = (b - a).cross(&(c - a)).normalized().unwrap_or(Vec3::UP);
+
+            for &i in triangle.iter() {
+                let index = face.get(i).ok_or(ObjError::IndexOutOfBounds)?;
+                let uv = index
+                    .tex_coord
+                    .map(|tex_coord| document.tex_coords[tex_coord])
+                    .unwrap_or(Vec2::ZERO);
+                builder.insert(Vertex {
+                    position: document.positions[index.position],
+                    // Invert Y because OpenGL has origin at left *bottom* corner.
+                    tex_coord: Vec2::new(uv.x, -uv.y),
+                    second_tex_coord: Default::default(),
+                    normal: index
+                        .normal
+                        .map(|normal| document.normals[normal])
+                        .unwrap_or(face_normal),
+                    tangent: Vec4::default(),
+                    bone_weights: Default::default(),
+                    bone_indices: Default::default(),
+                    color: [255; 4],
+                });
+            }
+        }
+    }
+
+    let mut data = SurfaceSharedData::from_raw_mesh(builder.build(), false);
+    data.calculate_tangents();
+
+    Ok(Surface::new(Arc::new(Mutex::new(data))))
+}
+
+fn apply_material(
+    surface: &mut Surface,
+    material: &MtlMaterial,
+    library: &ObjMaterialLibrary,
+    resource_manager: &mut ResourceManager,
+) {
+    let [r, g, b] = material.diffuse_color;
+    surface.set_color(Color::from_rgba(
+        (r.min(1.0).max(0.0) * 255.0) as u8,
+        (g.min(1.0).max(0.0) * 255.0) as u8,
+        (b.min(1.0).max(0.0) * 255.0) as u8,
+        (material.dissolve.min(1.0).max(0.0) * 255.0) as u8,
+    ));
+    if let Some(diffuse_map) = material.diffuse_map.as_ref() {
+        let path = library.resolve_texture_path(resource_manager, diffuse_map);
+        // Load every texture as RGBA8 for the same reasons as in FBX loader.
+        let texture = resource_manager.request_texture_async(path, TextureKind::RGBA8);
+        surface.set_diffuse_texture(texture);
+    }
+    if let Some(normal_map) = material.normal_map.as_ref() {
+        let path = library.resolve_texture_path(resource_manager, normal_map);
+        let texture = resource_manager.request_texture_async(path, TextureKind::RGBA8);
+        surface.set_normal_texture(texture);
+    }
+}
+
+fn load_material_libraries(
+    document: &ObjDocument,
+    obj_dir: &Path,
+    vfs: &VirtualFileSystem,
+) -> Vec<ObjMaterialLibrary> {
+    let mut libraries = Vec::new();
+    for path in document.material_libraries.iter() {
+        let path = obj_dir.join(path);
+        match ObjMaterialLibrary::load(&path, vfs) {
+            Ok(library) => libraries.push(library),
+            // Missing material library is not fatal, geometry still can be used.
+            Err(e) => Log::writeln(format!(
+                "Unable to load material library {:?}! Reason {}",
+                path, e
+            )),
+        }
+    }
+    libraries
+}
+
+///
+/// Converts OBJ document to native engine representation.
+///
+fn convert(
+    document: &ObjDocument,
+    libraries: &[ObjMaterialLibrary],
+    resource_manager: &mut ResourceManager,
+    scene: &mut Scene,
+) -> Result<Handle<Node>, ObjError> {
+    let root = scene.graph.add_node(Node::Base(Base::default()));
+
+    let mut temp_vertices = Vec::new();
+    let mut triangles = Vec::new();
+
+    for object in document.objects.iter() {
+        let mut mesh = Mesh::default();
+        for group in object.groups.iter().filter(|g| !g.faces.is_empty()) {
+            let mut surface = make_surface(document, group, &mut temp_vertices, &mut triangles)?;
+            if let Some(name) = group.material.as_ref() {
+                let material = libraries
+                    .iter()
+                    .find_map(|library| library.get(name).map(|m| (library, m)));
+                match material {
+                    Some((library, material)) => {
+                        apply_material(&mut surface, material, library, resource_manager)
+                    }
+                    None => Log::writeln(format!("OBJ: Material {} not found!", name)),
+                }
+            }
+            mesh.add_surface(surface);
+        }
+        if mesh.surfaces().is_empty() {
+            continue;
+        }
+        let mut node = Node::Mesh(mesh);
+        node.set_name(object.name.as_str());
+        let handle = scene.graph.add_node(node);
+        scene.graph.link_nodes(handle, root);
+    }
+
+    scene.graph.update_hierachical_data();
+
+    Ok(root)
+}
+
+/// Tries to load and convert OBJ from given path.
+///
+/// Normally you should never use this method, use resource manager to load models.
+pub fn load_to_scene<P: AsRef<Path>>(
+    scene: &mut Scene,
+    resource_manager: &mut ResourceManager,
+    path: P,
+) -> Result<Handle<Node>, ObjError> {
+    let start_time = Instant::now();
+
+    Log::writeln(format!("Trying to load {:?}", path.as_ref()));
+
+    let now = Instant::now();
+    let vfs = resource_manager.vfs();
+    let vfs = vfs.lock().unwrap();
+    let document = ObjDocument::read(Cursor::new(vfs.read(path.as_ref())?))?;
+    let obj_dir = path.as_ref().parent().unwrap_or_else(|| Path::new(""));
+    let libraries = load_material_libraries(&document, obj_dir, &vfs);
+    drop(vfs);
+    let parsing_time = now.elapsed().as_millis();
+
+    let now = Instant::now();
+    let result = convert(&document, &libraries, resource_manager, scene);
+    let conversion_time = now.elapsed().as_millis();
+
+    Log::writeln(format!(
+        "OBJ {:?} loaded in {} ms\n\t- Parsing - {} ms\n\t- Conversion - {} ms",
+        path.as_ref(),
+        start_time.elapsed().as_millis(),
+        parsing_time,
+        conversion_time
+    ));
+
+    result
+}
+
+#[cfg(test)]
+mod test {
+    use crate::resource::obj::{mtl::read_mtl, ObjDocument, ObjIndex};
+    use std::io::Cursor;
+
+    const CUBE_SIDES: &str = "# two quads
+mtllib cube.mtl
+v 0 0 0
+v 1 0 0
+v 1 1 0
+v 0 1 0
+v 0 0 1
+vt 0 0
+vt 1 0
+vt 1 1
+vn 0 0 1
+o Front
+usemtl Red
+f 1/1/1 2/2/1 3/3/1 4/1/1
+g Side
+f -5 -4 -1
+";
+
+    #[test]
+    fn obj_parse_test() {
+        let document = ObjDocument::read(Cursor::new(CUBE_SIDES)).unwrap();
+        assert_eq!(document.positions.len(), 5);
+        assert_eq!(document.tex_coords.len(), 3);
+        assert_eq!(document.normals.len(), 1);
+        assert_eq!(document.material_libraries.len(), 1);
+        assert_eq!(document.objects.len(), 2);
+
+        let front = &document.objects[0];
+        assert_eq!(front.name, "Front");
+        let group = front.groups.last().unwrap();
+        assert_eq!(group.material.as_deref(), Some("Red"));
+        assert_eq!(group.faces[0].len(), 4);
+        assert_eq!(
+            group.faces[0][1],
+            ObjIndex {
+                position: 1,
+                tex_coord: Some(1),
+                normal: Some(0)
+            }
+        );
+
+        // Material must be inherited and negative indices must be resolved.
+        let side = &document.objects[1];
+        let group = side.groups.last().unwrap();
+        assert_eq!(group.material.as_deref(), Some("Red"));
+        let positions = group.faces[0]
+            .iter()
+            .map(|i| i.position)
+            .collect::<Vec<_>>();
+        assert_eq!(positions, vec![0, 1, 4]);
+    }
+
+    #[test]
+    fn obj_invalid_index_test() {
+        assert!(ObjDocument::read(Cursor::new("v 0 0 0\nf 1 2 3\n")).is_err());
+    }
+
+    #[test]
+    fn mtl_parse_test() {
+        let materials = read_mtl(Cursor::new(
+            "newmtl Red\nKd 1.0 0.0 0.0\nd 0.5\nmap_Kd -bm 1 textures\\red.png\n",
+        ))
+        .unwrap();
+        let red = &materials["Red"];
+        assert_eq!(red.diffuse_color, [1.0, 0.0, 0.0]);
+        assert_eq!(red.dissolve, 0.5);
+        assert_eq!(
+            red.diffuse_map.as_ref().unwrap().to_str(),
+            Some("textures/red.png")
+        );
+    }
+}
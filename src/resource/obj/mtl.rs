@@ -0,0 +1,137 @@
+//! Contains parser for material libraries (.mtl) which are referenced by OBJ files.
+//!
+//! Only subset of MTL is supported - diffuse color, dissolve, diffuse and normal maps.
+//! Rest of statements are silently ignored.
+
+use crate::{
+    engine::resource_manager::ResourceManager,
+    resource::{
+        obj::{error::ObjError, parse_f32},
+        vfs::VirtualFileSystem,
+    },
+};
+use std::{
+    collections::HashMap,
+    io::{BufRead, Cursor},
+    path::{Path, PathBuf},
+};
+
+pub struct MtlMaterial {
+    pub diffuse_color: [f32; 3],
+    pub dissolve: f32,
+    pub diffuse_map: Option<PathBuf>,
+    pub normal_map: Option<PathBuf>,
+}
+
+impl Default for MtlMaterial {
+    fn default() -> Self {
+        Self {
+            diffuse_color: [1.0, 1.0, 1.0],
+            dissolve: 1.0,
+            diffuse_map: None,
+            normal_map: None,
+        }
+    }
+}
+
+/// Texture statements may have options before file name (like `-bm 1.0 file.png`),
+/// we don't support them so just take last argument as path. Paths are fixed the
+/// same way as in FBX - Windows back slashes replaced with forward slashes.
+fn texture_path(args: &[&str]) -> Option<PathBuf> {
+    args.last()
+        .map(|path| PathBuf::from(path.replace("\\", "/")))
+}
+
+pub fn read_mtl<R: BufRead>(reader: R) -> Result<HashMap<String, MtlMaterial>, ObjError> {
+    let mut materials = HashMap::new();
+    let mut current: Option<(String, MtlMaterial)> = None;
+
+    for (n, line) in reader.lines().enumerate() {
+        let line = line?;
+        let line_number = n + 1;
+        let mut tokens = line.split_whitespace();
+        let statement = match tokens.next() {
+            Some(statement) if !statement.starts_with('#') => statement,
+            _ => continue,
+        };
+        let args = tokens.collect::<Vec<_>>();
+
+        if statement == "newmtl" {
+            if let Some((name, material)) = current.take() {
+                materials.insert(name, material);
+            }
+            let name = args.join(" ");
+            current = Some((name, MtlMaterial::default()));
+            continue;
+        }
+
+        // Ignore everything before first newmtl.
+        let material = match current.as_mut() {
+            Some((_, material)) => material,
+            None => continue,
+        };
+
+        match statement {
+            "Kd" => {
+                if args.len() < 3 {
+                    return Err(ObjError::MissingArgument(line_number));
+                }
+                for (i, arg) in args.iter().take(3).enumerate() {
+                    material.diffuse_color[i] = parse_f32(arg, line_number)?;
+                }
+            }
+            "d" => {
+                let arg = args.first().ok_or(ObjError::MissingArgument(line_number))?;
+                material.dissolve = parse_f32(arg, line_number)?;
+            }
+            "Tr" => {
+                let arg = args.first().ok_or(ObjError::MissingArgument(line_number))?;
+                material.dissolve = 1.0 - parse_f32(arg, line_number)?;
+            }
+            "map_Kd" => material.diffuse_map = texture_path(&args),
+            // No single standard for normal maps in MTL, so support most common variants.
+            "map_Bump" | "map_bump" | "bump" | "norm" => material.normal_map = texture_path(&args),
+            _ => (),
+        }
+    }
+
+    if let Some((name, material)) = current.take() {
+        materials.insert(name, material);
+    }
+
+    Ok(materials)
+}
+
+pub struct ObjMaterialLibrary {
+    // Directory of library file, texture paths are relative to it.
+    dir: PathBuf,
+    materials: HashMap<String, MtlMaterial>,
+}
+
+impl ObjMaterialLibrary {
+    pub fn load(path: &Path, vfs: &VirtualFileSystem) -> Result<Self, ObjError> {
+        let materials = read_mtl(Cursor::new(vfs.read(path)?))?;
+        Ok(Self {
+            dir: path.parent().map(|p| p.to_owned()).unwrap_or_default(),
+            materials,
+        })
+    }
+
+    pub fn get(&self, name: &str) -> Option<&MtlMaterial> {
+        self.materials.get(name)
+    }
+
+    /// Resolves path to texture referenced by material library. MTL stores paths relative
+    /// to itself, but this is not always true - some exporters write absolute paths, in this
+    /// case fallback to textures path of resource manager as FBX loader does.
+    pub fn resolve_texture_path(&self, resource_manager: &ResourceManager, path: &Path) -> PathBuf {
+        let relative = self.dir.join(path);
+        if resource_manager.vfs().lock().unwrap().exists(&relative) {
+            relative
+        } else if let Some(file_name) = path.file_name() {
+            resource_manager.textures_path().join(file_name)
+        } else {
+            relative
+        }
+    }
+}
@@ -0,0 +1,473 @@
+//! Resource packs are archives which stores many resource files in a single file.
+//!
+//! Shipping thousands of loose files with a game is slow to install and easy to break,
+//! so release builds should put their data into one or few packs and mount them into
+//! [virtual file system](crate::resource::vfs::VirtualFileSystem).
+//!
+//! # Format
+//!
+//! Pack consists of a header, table of entries and data of each entry. All numbers are
+//! little-endian.
+//!
+//! ```text
+//! magic: [u8; 8] = "RG3DPACK"
+//! version: u32
+//! entry_count: u32
+//! entries: [Entry; entry_count]
+//! data: [u8]
+//!
+//! Entry:
+//!     path_len: u32
+//!     path: [u8; path_len] - UTF-8, relative, separated by forward slashes.
+//!     offset: u64 - offset of data from the beginning of the pack.
+//!     stored_size: u64 - size of data in the pack.
+//!     size: u64 - size of data after decompression.
+//!     flags: u8 - bit 0 - data is compressed (zlib), bit 1 - data is obfuscated.
+//! ```
+//!
+//! # Obfuscation
+//!
+//! Entries can be obfuscated with a key (XOR with a key stream), this prevents casual extraction
+//! of assets by end users. It is **not** encryption - it gives no confidentiality or integrity,
+//! and anyone with the executable can recover the key and the data.
+
+use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
+use std::{
+    collections::HashMap,
+    fmt::Formatter,
+    fs::File,
+    io::{BufReader, BufWriter, Read, Seek, SeekFrom, Write},
+    path::{Path, PathBuf},
+};
+
+const PACK_MAGIC: &[u8; 8] = b"RG3DPACK";
+const PACK_VERSION: u32 = 1;
+
+const FLAG_COMPRESSED: u8 = 1;
+const FLAG_OBFUSCATED: u8 = 2;
+
+/// All possible errors that can occur during reading or writing of resource packs.
+#[derive(Debug)]
+pub enum PackError {
+    /// An input/output error has occurred.
+    Io(std::io::Error),
+    /// File is not a resource pack.
+    InvalidMagic,
+    /// Pack was made by newer version of the engine.
+    UnsupportedVersion(u32),
+    /// Path of an entry is not valid UTF-8.
+    InvalidPath,
+    /// Pack contains obfuscated entries, but no key was given.
+    MissingKey,
+    /// Entry points outside of the pack file, pack is truncated or corrupted.
+    InvalidEntry(String),
+    /// Compressed data is corrupted.
+    Decompression(String),
+}
+
+impl std::fmt::Display for PackError {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), std::fmt::Error> {
+        match self {
+            PackError::Io(io) => write!(f, "Io error: {}", io),
+            PackError::InvalidMagic => write!(f, "Not a resource pack."),
+            PackError::UnsupportedVersion(version) => {
+                write!(f, "Unsupported pack version {}", version)
+            }
+            PackError::InvalidPath => write!(f, "Entry path is not valid UTF-8."),
+            PackError::MissingKey => write!(f, "Pack is obfuscated, but no key was given."),
+            PackError::InvalidEntry(path) => {
+                write!(f, "Entry {} points outside of the pack.", path)
+            }
+            PackError::Decompression(reason) => write!(f, "Unable to decompress: {}", reason),
+        }
+    }
+}
+
+impl From<std::io::Error> for PackError {
+    fn from(err: std::io::Error) -> Self {
+        PackError::Io(err)
+    }
+}
+
+impl From<PackError> for std::io::Error {
+    fn from(err: PackError) -> Self {
+        match err {
+            PackError::Io(io) => io,
+            _ => std::io::Error::new(std::io::ErrorKind::InvalidData, err.to_string()),
+        }
+    }
+}
+
+/// Converts path to a form which is used as a key in packs and in virtual file system:
+/// relative, separated by forward slashes, without `.` components. `..` removes previous
+/// component, it cannot go above root of the pack.
+pub fn normalize_path<P: AsRef<Path>>(path: P) -> String {
+    let path = path.as_ref().to_string_lossy().replace("\\", "/");
+    let mut components = Vec::new();
+    for component in path.split('/') {
+        match component {
+            "" | "." => (),
+            ".." => {
+                components.pop();
+            }
+            _ => components.push(component),
+        }
+    }
+    components.join("/")
+}
+
+/// Inflates zlib stream, fails as soon as output exceeds given size so malformed entry can't
+/// make it allocate arbitrary amount of memory.
+fn inflate_limited(data: &[u8], size: u64) -> Result<Vec<u8>, PackError> {
+    let mut stream = inflate::InflateStream::from_zlib();
+    let mut output = Vec::new();
+    let mut position = 0;
+    loop {
+        let (read, chunk) = stream
+            .update(&data[position..])
+            .map_err(PackError::Decompression)?;
+        if read == 0 && chunk.is_empty() {
+            return Ok(output);
+        }
+        if (output.len() + chunk.len()) as u64 > size {
+            return Err(PackError::Decompression(
+                "decompressed data is larger than entry".to_owned(),
+            ));
+        }
+        output.extend_from_slice(chunk);
+        position += read;
+    }
+}
+
+/// XOR-s data with key stream derived from given key and entry path. Path is mixed in so same
+/// files at different paths will produce different output. Operation is symmetric. This is
+/// obfuscation, not encryption, see module docs.
+fn obfuscate(data: &mut [u8], key: &[u8], path: &str) {
+    if key.is_empty() {
+        return;
+    }
+    let mut state = path.bytes().fold(0x811c_9dc5u32, |h, b| {
+        (h ^ b as u32).wrapping_mul(0x0100_0193)
+    });
+    for (i, byte) in data.iter_mut().enumerate() {
+        state ^= state << 13;
+        state ^= state >> 17;
+        state ^= state << 5;
+        *byte ^= key[i % key.len()] ^ (state as u8);
+    }
+}
+
+#[derive(Clone, Debug)]
+struct PackEntry {
+    offset: u64,
+    stored_size: u64,
+    size: u64,
+    flags: u8,
+}
+
+/// Read-only resource pack. Pack file is opened on every read, so pack can be shared between
+/// threads freely.
+#[derive(Debug)]
+pub struct ResourcePack {
+    path: PathBuf,
+    key: Vec<u8>,
+    entries: HashMap<String, PackEntry>,
+}
+
+impl ResourcePack {
+    /// Opens pack at given path and reads its table of entries.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, PackError> {
+        Self::open_obfuscated(path, Vec::new())
+    }
+
+    /// Opens pack which contains obfuscated entries, key must be the same as the key that was
+    /// used to build the pack. See module docs.
+    pub fn open_obfuscated<P: AsRef<Path>>(path: P, key: Vec<u8>) -> Result<Self, PackError> {
+        let file = File::open(path.as_ref())?;
+        let file_len = file.metadata()?.len();
+        let mut reader = BufReader::new(file);
+
+        let mut magic = [0; 8];
+        reader.read_exact(&mut magic)?;
+        if magic != *PACK_MAGIC {
+            return Err(PackError::InvalidMagic);
+        }
+
+        let version = reader.read_u32::<LittleEndian>()?;
+        if version > PACK_VERSION {
+            return Err(PackError::UnsupportedVersion(version));
+        }
+
+        let entry_count = reader.read_u32::<LittleEndian>()?;
+        let mut entries = HashMap::new();
+        for _ in 0..entry_count {
+            let path_len = reader.read_u32::<LittleEndian>()?;
+            // Sizes are not trusted, corrupted pack must not cause huge allocations.
+            if path_len as u64 > file_len {
+                return Err(PackError::InvalidPath);
+            }
+            let mut path = vec![0; path_len as usize];
+            reader.read_exact(&mut path)?;
+            let path = String::from_utf8(path).map_err(|_| PackError::InvalidPath)?;
+            let entry = PackEntry {
+                offset: reader.read_u64::<LittleEndian>()?,
+                stored_size: reader.read_u64::<LittleEndian>()?,
+                size: reader.read_u64::<LittleEndian>()?,
+                flags: reader.read_u8()?,
+            };
+            let in_bounds = entry
+                .offset
+                .checked_add(entry.stored_size)
+                .map_or(false, |end| end <= file_len);
+            if !in_bounds {
+                return Err(PackError::InvalidEntry(path));
+            }
+            if entry.flags & FLAG_OBFUSCATED != 0 && key.is_empty() {
+                return Err(PackError::MissingKey);
+            }
+            entries.insert(path, entry);
+        }
+
+        Ok(Self {
+            path: path.as_ref().to_owned(),
+            key,
+            entries,
+        })
+    }
+
+    /// Returns path of the pack file.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Returns true if pack contains file at given path.
+    pub fn contains<P: AsRef<Path>>(&self, path: P) -> bool {
+        self.entries.contains_key(&normalize_path(path))
+    }
+
+    /// Returns iterator over paths of every file in the pack.
+    pub fn paths(&self) -> impl Iterator<Item = &str> {
+        self.entries.keys().map(|p| p.as_str())
+    }
+
+    /// Reads whole file at given path. Returns `Ok(None)` if there is no such file in the pack.
+    pub fn read<P: AsRef<Path>>(&self, path: P) -> Result<Option<Vec<u8>>, PackError> {
+        let path = normalize_path(path);
+        let entry = match self.entries.get(&path) {
+            Some(entry) => entry,
+            None => return Ok(None),
+        };
+
+        let mut file = File::open(&self.path)?;
+        file.seek(SeekFrom::Start(entry.offset))?;
+        let mut data = vec![0; entry.stored_size as usize];
+        file.read_exact(&mut data)?;
+
+        if entry.flags & FLAG_OBFUSCATED != 0 {
+            obfuscate(&mut data, &self.key, &path);
+        }
+        if entry.flags & FLAG_COMPRESSED != 0 {
+            data = inflate_limited(&data, entry.size)?;
+        }
+        if data.len() as u64 != entry.size {
+            return Err(PackError::Decompression(format!(
+                "size mismatch for {}",
+                path
+            )));
+        }
+
+        Ok(Some(data))
+    }
+}
+
+/// Allows you to build resource packs, it is intended to be used in build scripts or
+/// in asset pipeline tools.
+///
+/// # Example
+///
+/// ```no_run
+/// use rg3d::resource::pack::PackBuilder;
+///
+/// PackBuilder::new()
+///     .with_compression(true)
+///     .add_directory("data")
+///     .unwrap()
+///     .build("data.pak")
+///     .unwrap();
+/// ```
+#[derive(Default)]
+pub struct PackBuilder {
+    compression: bool,
+    key: Vec<u8>,
+    files: Vec<(String, Vec<u8>)>,
+}
+
+impl PackBuilder {
+    /// Creates new empty pack builder.
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Enables or disables compression of entries. Entries that do not become smaller
+    /// after compression (already compressed images, etc.) are stored as is.
+    pub fn with_compression(mut self, compression: bool) -> Self {
+        self.compression = compression;
+        self
+    }
+
+    /// Sets key which will be used to obfuscate entries. Obfuscation only hides assets from
+    /// casual extraction, it is not encryption. See module docs.
+    pub fn with_obfuscation_key(mut self, key: Vec<u8>) -> Self {
+        self.key = key;
+        self
+    }
+
+    /// Adds file with given path in the pack and given contents.
+    pub fn add_file<P: AsRef<Path>>(mut self, path: P, data: Vec<u8>) -> Self {
+        self.files.push((normalize_path(path), data));
+        self
+    }
+
+    /// Adds every file from given directory recursively, paths in the pack will be the same
+    /// as on disk, so relative paths to resources remains valid.
+    pub fn add_directory<P: AsRef<Path>>(mut self, path: P) -> Result<Self, PackError> {
+        for entry in std::fs::read_dir(path.as_ref())? {
+            let entry_path = entry?.path();
+            if entry_path.is_dir() {
+                self = self.add_directory(entry_path)?;
+            } else {
+                let data = std::fs::read(&entry_path)?;
+                self.files.push((normalize_path(entry_path), data));
+            }
+        }
+        Ok(self)
+    }
+
+    /// Writes pack to given path.
+    pub fn build<P: AsRef<Path>>(self, path: P) -> Result<(), PackError> {
+        let mut table = Vec::with_capacity(self.files.len());
+        let mut blobs = Vec::with_capacity(self.files.len());
+        for (path, data) in self.files {
+            let size = data.len() as u64;
+            let mut flags = 0;
+            let mut data = if self.compression {
+                let compressed = deflate::deflate_bytes_zlib(&data);
+                if compressed.len() < data.len() {
+                    flags |= FLAG_COMPRESSED;
+                    compressed
+                } else {
+                    data
+                }
+            } else {
+                data
+            };
+            if !self.key.is_empty() {
+                flags |= FLAG_OBFUSCATED;
+                obfuscate(&mut data, &self.key, &path);
+            }
+            table.push((path, data.len() as u64, size, flags));
+            blobs.push(data);
+        }
+
+        let header_size = PACK_MAGIC.len()
+            + 2 * std::mem::size_of::<u32>()
+            + table
+                .iter()
+                .map(|(path, ..)| std::mem::size_of::<u32>() + path.len() + 3 * 8 + 1)
+                .sum::<usize>();
+
+        let mut writer = BufWriter::new(File::create(path)?);
+        writer.write_all(PACK_MAGIC)?;
+        writer.write_u32::<LittleEndian>(PACK_VERSION)?;
+        writer.write_u32::<LittleEndian>(table.len() as u32)?;
+        let mut offset = header_size as u64;
+        for (path, stored_size, size, flags) in table.iter() {
+            writer.write_u32::<LittleEndian>(path.len() as u32)?;
+            writer.write_all(path.as_bytes())?;
+            writer.write_u64::<LittleEndian>(offset)?;
+            writer.write_u64::<LittleEndian>(*stored_size)?;
+            writer.write_u64::<LittleEndian>(*size)?;
+            writer.write_u8(*flags)?;
+            offset += stored_size;
+        }
+        for blob in blobs {
+            writer.write_all(&blob)?;
+        }
+        writer.flush()?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::resource::pack::{
+        inflate_limited, normalize_path, PackBuilder, PackError, ResourcePack,
+    };
+
+    #[test]
+    fn pack_round_trip_test() {
+        let path = std::env::temp_dir().join("rg3d_pack_round_trip_test.pak");
+        let text = b"hello hello hello hello hello hello".to_vec();
+        PackBuilder::new()
+            .with_compression(true)
+            .with_obfuscation_key(b"secret".to_vec())
+            .add_file("data/text.txt", text.clone())
+            .add_file("data\\raw.bin", vec![1, 2, 3])
+            .build(&path)
+            .unwrap();
+
+        assert!(ResourcePack::open(&path).is_err());
+
+        let pack = ResourcePack::open_obfuscated(&path, b"secret".to_vec()).unwrap();
+        assert_eq!(pack.read("data/text.txt").unwrap().unwrap(), text);
+        assert_eq!(pack.read("./data/raw.bin").unwrap().unwrap(), vec![1, 2, 3]);
+        assert!(pack.read("data/missing.bin").unwrap().is_none());
+
+        let _ = std::fs::remove_file(path);
+    }
+
+    #[test]
+    fn pack_truncated_test() {
+        let path = std::env::temp_dir().join("rg3d_pack_truncated_test.pak");
+        PackBuilder::new()
+            .add_file("data/raw.bin", vec![7; 64])
+            .build(&path)
+            .unwrap();
+
+        let mut bytes = std::fs::read(&path).unwrap();
+        bytes.truncate(bytes.len() - 1);
+        std::fs::write(&path, bytes).unwrap();
+
+        assert!(matches!(
+            ResourcePack::open(&path),
+            Err(PackError::InvalidEntry(_))
+        ));
+
+        let _ = std::fs::remove_file(path);
+    }
+
+    #[test]
+    fn normalize_path_test() {
+        assert_eq!(
+            normalize_path("./data\\models//foo.fbx"),
+            "data/models/foo.fbx"
+        );
+        assert_eq!(
+            normalize_path("data/textures/../models/./foo.fbx"),
+            "data/models/foo.fbx"
+        );
+        assert_eq!(normalize_path("../../data/foo.fbx"), "data/foo.fbx");
+    }
+
+    #[test]
+    fn inflate_limited_test() {
+        let data = vec![42; 4096];
+        let compressed = deflate::deflate_bytes_zlib(&data);
+        assert_eq!(inflate_limited(&compressed, 4096).unwrap(), data);
+        assert!(matches!(
+            inflate_limited(&compressed, 100),
+            Err(PackError::Decompression(_))
+        ));
+    }
+}
@@ -0,0 +1,185 @@
+//! Conversion of equirectangular (latitude-longitude) panoramas into cube maps.
+
+use crate::{
+    core::math::vec3::Vec3,
+    resource::{
+        texture::{Texture, TextureError, TextureKind, TextureType},
+        ResourceState,
+    },
+};
+
+/// Returns amount of components and size of a component in bytes.
+fn layout(kind: TextureKind) -> Option<(usize, usize)> {
+    match kind {
+        TextureKind::R8 => Some((1, 1)),
+        TextureKind::RGB8 => Some((3, 1)),
+        TextureKind::RGBA8 => Some((4, 1)),
+        TextureKind::RGB32F => Some((3, 4)),
+        TextureKind::RGBA32F => Some((4, 4)),
+        _ => None,
+    }
+}
+
+fn read_component(bytes: &[u8], component_size: usize) -> f32 {
+    if component_size == 4 {
+        let mut raw = [0; 4];
+        raw.copy_from_slice(bytes);
+        f32::from_le_bytes(raw)
+    } else {
+        bytes[0] as f32 / 255.0
+    }
+}
+
+fn write_component(value: f32, component_size: usize, out: &mut Vec<u8>) {
+    if component_size == 4 {
+        out.extend_from_slice(&value.to_le_bytes());
+    } else {
+        out.push((value.max(0.0).min(1.0) * 255.0).round() as u8);
+    }
+}
+
+/// Returns direction for a texel of a face in OpenGL face order (+X, -X, +Y, -Y, +Z, -Z),
+/// `u` and `v` are in [-1; 1] range.
+fn face_direction(face: usize, u: f32, v: f32) -> Vec3 {
+    match face {
+        0 => Vec3::new(1.0, -v, -u),
+        1 => Vec3::new(-1.0, -v, u),
+        2 => Vec3::new(u, 1.0, v),
+        3 => Vec3::new(u, -1.0, -v),
+        4 => Vec3::new(u, -v, 1.0),
+        _ => Vec3::new(-u, -v, -1.0),
+    }
+}
+
+impl Texture {
+    fn texel(&self, x: u32, y: u32, components: usize, component_size: usize, out: &mut [f32]) {
+        let pixel_size = components * component_size;
+        let offset = (y * self.width + x) as usize * pixel_size;
+        for (i, value) in out.iter_mut().take(components).enumerate() {
+            let begin = offset + i * component_size;
+            *value = read_component(&self.bytes[begin..(begin + component_size)], component_size);
+        }
+    }
+
+    /// Bilinear sampling with wrapping by horizontal axis and clamping by vertical.
+    fn sample_equirectangular(
+        &self,
+        direction: Vec3,
+        components: usize,
+        component_size: usize,
+    ) -> [f32; 4] {
+        let direction = direction.normalized().unwrap_or(Vec3::UP);
+        let u = 0.5 + direction.x.atan2(-direction.z) / (2.0 * std::f32::consts::PI);
+        let v = direction.y.max(-1.0).min(1.0).acos() / std::f32::consts::PI;
+
+        let fx = u * self.width as f32 - 0.5;
+        let fy = (v * self.height as f32 - 0.5)
+            .max(0.0)
+            .min((self.height - 1) as f32);
+        let x0 = fx.floor();
+        let y0 = fy.floor();
+        let tx = fx - x0;
+        let ty = fy - y0;
+        let wrap = |x: f32| (x as i64).rem_euclid(self.width as i64) as u32;
+        let (x0, x1) = (wrap(x0), wrap(x0 + 1.0));
+        let (y0, y1) = (y0 as u32, (y0 as u32 + 1).min(self.height - 1));
+
+        let mut corners = [[0.0; 4]; 4];
+        self.texel(x0, y0, components, component_size, &mut corners[0]);
+        self.texel(x1, y0, components, component_size, &mut corners[1]);
+        self.texel(x0, y1, components, component_size, &mut corners[2]);
+        self.texel(x1, y1, components, component_size, &mut corners[3]);
+
+        let mut result = [0.0; 4];
+        for (i, value) in result.iter_mut().enumerate() {
+            let top = corners[0][i] + (corners[1][i] - corners[0][i]) * tx;
+            let bottom = corners[2][i] + (corners[3][i] - corners[2][i]) * tx;
+            *value = top + (bottom - top) * ty;
+        }
+        result
+    }
+
+    /// Converts equirectangular (latitude-longitude) panorama into cube map with given size
+    /// of a face. Resulting texture has same kind as source texture and single mip level,
+    /// renderer will generate the rest of the mip chain. Useful to make skyboxes and
+    /// environment maps from HDR images.
+    ///
+    /// Only loaded rectangle textures with uncompressed formats can be converted.
+    pub fn equirectangular_to_cube_map(&self, face_size: u32) -> Result<Texture, TextureError> {
+        if self.texture_type != TextureType::Rectangle || self.width == 0 || self.height == 0 {
+            return Err(TextureError::UnsupportedFormat(
+                "conversion of non-rectangle or empty texture to cube map".to_owned(),
+            ));
+        }
+        let (components, component_size) = layout(self.kind).ok_or_else(|| {
+            TextureError::UnsupportedFormat(format!(
+                "conversion of {:?} texture to cube map",
+                self.kind
+            ))
+        })?;
+        if face_size == 0 {
+            return Err(TextureError::InvalidData(
+                "cube map face size must be greater than zero".to_owned(),
+            ));
+        }
+
+        let mut bytes =
+            Vec::with_capacity(6 * (face_size * face_size) as usize * components * component_size);
+        for face in 0..6 {
+            for y in 0..face_size {
+                for x in 0..face_size {
+                    let u = 2.0 * (x as f32 + 0.5) / face_size as f32 - 1.0;
+                    let v = 2.0 * (y as f32 + 0.5) / face_size as f32 - 1.0;
+                    let texel = self.sample_equirectangular(
+                        face_direction(face, u, v),
+                        components,
+                        component_size,
+                    );
+                    for &value in texel.iter().take(components) {
+                        write_component(value, component_size, &mut bytes);
+                    }
+                }
+            }
+        }
+
+        Ok(Texture {
+            path: Default::default(),
+            width: face_size,
+            height: face_size,
+            bytes,
+            kind: self.kind,
+            state: ResourceState::Ok,
+            mip_count: 1,
+            texture_type: TextureType::Cube,
+            color_space: self.color_space,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::resource::texture::{Texture, TextureKind, TextureType};
+
+    #[test]
+    fn uniform_panorama_test() {
+        let panorama =
+            Texture::from_bytes(8, 4, TextureKind::RGB8, [10, 20, 30].repeat(8 * 4)).unwrap();
+        let cube_map = panorama.equirectangular_to_cube_map(4).unwrap();
+        assert_eq!(cube_map.texture_type(), TextureType::Cube);
+        assert_eq!(cube_map.kind(), TextureKind::RGB8);
+        assert_eq!(cube_map.bytes.len(), 6 * 4 * 4 * 3);
+        assert!(cube_map.bytes.chunks(3).all(|p| p == [10, 20, 30]));
+    }
+
+    #[test]
+    fn poles_test() {
+        // Upper half is white, lower half is black.
+        let mut bytes = vec![255; 4 * 2];
+        bytes.extend_from_slice(&[0; 4 * 2]);
+        let panorama = Texture::from_bytes(4, 4, TextureKind::R8, bytes).unwrap();
+        let cube_map = panorama.equirectangular_to_cube_map(2).unwrap();
+        // +Y face looks up, -Y face looks down.
+        assert!(cube_map.bytes[8..12].iter().all(|&p| p == 255));
+        assert!(cube_map.bytes[12..16].iter().all(|&p| p == 0));
+    }
+}
@@ -0,0 +1,324 @@
+//! DirectDraw Surface (.dds) container reader.
+//!
+//! Supports legacy headers and DX10 extension header, block-compressed formats (BC1-BC5, BC7),
+//! common uncompressed 8-bit formats, mip chains and cube maps. Volume textures and texture
+//! arrays are not supported.
+
+use crate::resource::texture::{
+    max_mip_count, ColorSpace, ContainerData, TextureError, TextureKind, TextureType,
+};
+use byteorder::{LittleEndian, ReadBytesExt};
+use std::io::{Cursor, Read};
+
+const DDS_MAGIC: &[u8; 4] = b"DDS ";
+const DDS_HEADER_SIZE: u32 = 124;
+
+const DDSD_MIPMAPCOUNT: u32 = 0x0002_0000;
+
+const DDPF_ALPHAPIXELS: u32 = 0x1;
+const DDPF_FOURCC: u32 = 0x4;
+const DDPF_RGB: u32 = 0x40;
+const DDPF_LUMINANCE: u32 = 0x0002_0000;
+
+const DDSCAPS2_CUBEMAP: u32 = 0x200;
+const DDSCAPS2_CUBEMAP_ALLFACES: u32 = 0xFC00;
+const DDSCAPS2_VOLUME: u32 = 0x0020_0000;
+
+const DDS_RESOURCE_MISC_TEXTURECUBE: u32 = 0x4;
+const DDS_DIMENSION_TEXTURE2D: u32 = 3;
+
+fn four_cc(code: &[u8; 4]) -> u32 {
+    u32::from_le_bytes(*code)
+}
+
+/// Pixel layout of uncompressed data which must be converted to native layout.
+#[derive(Copy, Clone, PartialEq)]
+enum Swizzle {
+    None,
+    Bgr,
+    Bgra,
+    // BGRX - alpha channel is unused and must be dropped.
+    Bgrx,
+}
+
+struct PixelFormat {
+    flags: u32,
+    four_cc: u32,
+    rgb_bit_count: u32,
+    r_mask: u32,
+    a_mask: u32,
+}
+
+fn dxgi_format_to_kind(format: u32) -> Result<TextureKind, TextureError> {
+    match format {
+        28 | 29 => Ok(TextureKind::RGBA8),
+        61 => Ok(TextureKind::R8),
+        71 | 72 => Ok(TextureKind::DXT1RGBA),
+        74 | 75 => Ok(TextureKind::DXT3RGBA),
+        77 | 78 => Ok(TextureKind::DXT5RGBA),
+        80 => Ok(TextureKind::R8RGTC),
+        83 => Ok(TextureKind::RG8RGTC),
+        98 | 99 => Ok(TextureKind::RGBA8BPTC),
+        _ => Err(TextureError::UnsupportedFormat(format!(
+            "DXGI format {}",
+            format
+        ))),
+    }
+}
+
+fn dxgi_format_color_space(format: u32) -> ColorSpace {
+    match format {
+        29 | 72 | 75 | 78 | 99 => ColorSpace::Srgb,
+        _ => ColorSpace::Linear,
+    }
+}
+
+fn legacy_format_to_kind(pf: &PixelFormat) -> Result<(TextureKind, Swizzle), TextureError> {
+    if pf.flags & DDPF_FOURCC != 0 {
+        let kind = if pf.four_cc == four_cc(b"DXT1") {
+            TextureKind::DXT1RGBA
+        } else if pf.four_cc == four_cc(b"DXT3") {
+            TextureKind::DXT3RGBA
+        } else if pf.four_cc == four_cc(b"DXT5") {
+            TextureKind::DXT5RGBA
+        } else if pf.four_cc == four_cc(b"ATI1") || pf.four_cc == four_cc(b"BC4U") {
+            TextureKind::R8RGTC
+        } else if pf.four_cc == four_cc(b"ATI2") || pf.four_cc == four_cc(b"BC5U") {
+            TextureKind::RG8RGTC
+        } else {
+            return Err(TextureError::UnsupportedFormat(format!(
+                "DDS FourCC {:?}",
+                String::from_utf8_lossy(&pf.four_cc.to_le_bytes())
+            )));
+        };
+        Ok((kind, Swizzle::None))
+    } else if pf.flags & DDPF_RGB != 0 {
+        let has_alpha = pf.flags & DDPF_ALPHAPIXELS != 0 && pf.a_mask != 0;
+        match (pf.rgb_bit_count, pf.r_mask) {
+            (32, 0x0000_00FF) if has_alpha => Ok((TextureKind::RGBA8, Swizzle::None)),
+            (32, 0x00FF_0000) if has_alpha => Ok((TextureKind::RGBA8, Swizzle::Bgra)),
+            (32, 0x00FF_0000) => Ok((TextureKind::RGB8, Swizzle::Bgrx)),
+            (24, 0x0000_00FF) => Ok((TextureKind::RGB8, Swizzle::None)),
+            (24, 0x00FF_0000) => Ok((TextureKind::RGB8, Swizzle::Bgr)),
+            _ => Err(TextureError::UnsupportedFormat(format!(
+                "DDS {}-bit RGB with red mask {:#x}",
+                pf.rgb_bit_count, pf.r_mask
+            ))),
+        }
+    } else if pf.flags & DDPF_LUMINANCE != 0 && pf.rgb_bit_count == 8 {
+        Ok((TextureKind::R8, Swizzle::None))
+    } else {
+        Err(TextureError::UnsupportedFormat(
+            "DDS pixel format".to_owned(),
+        ))
+    }
+}
+
+/// Converts one level of uncompressed data to native layout.
+fn swizzle(src: &[u8], swizzle: Swizzle) -> Vec<u8> {
+    match swizzle {
+        Swizzle::None => src.to_vec(),
+        Swizzle::Bgr => src.chunks(3).flat_map(|p| vec![p[2], p[1], p[0]]).collect(),
+        Swizzle::Bgra => src
+            .chunks(4)
+            .flat_map(|p| vec![p[2], p[1], p[0], p[3]])
+            .collect(),
+        Swizzle::Bgrx => src.chunks(4).flat_map(|p| vec![p[2], p[1], p[0]]).collect(),
+    }
+}
+
+/// Reads DDS file from memory.
+pub(in crate) fn read_dds(data: &[u8]) -> Result<ContainerData, TextureError> {
+    let mut reader = Cursor::new(data);
+
+    let mut magic = [0; 4];
+    reader.read_exact(&mut magic)?;
+    if magic != *DDS_MAGIC {
+        return Err(TextureError::InvalidData("Not a DDS file".to_owned()));
+    }
+
+    if reader.read_u32::<LittleEndian>()? != DDS_HEADER_SIZE {
+        return Err(TextureError::InvalidData(
+            "Invalid DDS header size".to_owned(),
+        ));
+    }
+    let flags = reader.read_u32::<LittleEndian>()?;
+    let height = reader.read_u32::<LittleEndian>()?;
+    let width = reader.read_u32::<LittleEndian>()?;
+    let _pitch_or_linear_size = reader.read_u32::<LittleEndian>()?;
+    let _depth = reader.read_u32::<LittleEndian>()?;
+    let mip_map_count = reader.read_u32::<LittleEndian>()?;
+    for _ in 0..11 {
+        reader.read_u32::<LittleEndian>()?;
+    }
+    let _pf_size = reader.read_u32::<LittleEndian>()?;
+    let pixel_format = PixelFormat {
+        flags: reader.read_u32::<LittleEndian>()?,
+        four_cc: reader.read_u32::<LittleEndian>()?,
+        rgb_bit_count: reader.read_u32::<LittleEndian>()?,
+        r_mask: reader.read_u32::<LittleEndian>()?,
+        a_mask: {
+            // Skip green and blue masks.
+            reader.read_u32::<LittleEndian>()?;
+            reader.read_u32::<LittleEndian>()?;
+            reader.read_u32::<LittleEndian>()?
+        },
+    };
+    let _caps = reader.read_u32::<LittleEndian>()?;
+    let caps2 = reader.read_u32::<LittleEndian>()?;
+    for _ in 0..3 {
+        reader.read_u32::<LittleEndian>()?;
+    }
+
+    if caps2 & DDSCAPS2_VOLUME != 0 {
+        return Err(TextureError::UnsupportedFormat(
+            "DDS volume texture".to_owned(),
+        ));
+    }
+
+    let mut is_cube = caps2 & DDSCAPS2_CUBEMAP != 0;
+    if is_cube && caps2 & DDSCAPS2_CUBEMAP_ALLFACES != DDSCAPS2_CUBEMAP_ALLFACES {
+        return Err(TextureError::UnsupportedFormat(
+            "DDS cube map with missing faces".to_owned(),
+        ));
+    }
+
+    let (kind, swizzle_mode, color_space) =
+        if pixel_format.flags & DDPF_FOURCC != 0 && pixel_format.four_cc == four_cc(b"DX10") {
+            let dxgi_format = reader.read_u32::<LittleEndian>()?;
+            let dimension = reader.read_u32::<LittleEndian>()?;
+            let misc_flag = reader.read_u32::<LittleEndian>()?;
+            let array_size = reader.read_u32::<LittleEndian>()?;
+            let _misc_flags2 = reader.read_u32::<LittleEndian>()?;
+            if dimension != DDS_DIMENSION_TEXTURE2D || array_size > 1 {
+                return Err(TextureError::UnsupportedFormat(
+                    "DDS texture array or non-2D texture".to_owned(),
+                ));
+            }
+            is_cube |= misc_flag & DDS_RESOURCE_MISC_TEXTURECUBE != 0;
+            (
+                dxgi_format_to_kind(dxgi_format)?,
+                Swizzle::None,
+                dxgi_format_color_space(dxgi_format),
+            )
+        } else {
+            let (kind, swizzle) = legacy_format_to_kind(&pixel_format)?;
+            // Legacy header has no color space info, assume that colors are in sRGB and
+            // one- or two-channel compressed formats store non-color data.
+            let color_space = match kind {
+                TextureKind::R8RGTC | TextureKind::RG8RGTC => ColorSpace::Linear,
+                _ => ColorSpace::Srgb,
+            };
+            (kind, swizzle, color_space)
+        };
+
+    let mip_count = if flags & DDSD_MIPMAPCOUNT != 0 {
+        mip_map_count.max(1)
+    } else {
+        1
+    };
+    // Header is not trusted, corrupted file must not cause overflows or huge allocations.
+    if mip_count > max_mip_count(width, height) {
+        return Err(TextureError::InvalidData(format!(
+            "Invalid DDS mip count {} for {}x{} image",
+            mip_count, width, height
+        )));
+    }
+    let face_count = if is_cube { 6 } else { 1 };
+
+    // Size of pixel in the file may differ from size of pixel of texture kind (BGRX).
+    let src_bytes_per_pixel = match swizzle_mode {
+        Swizzle::Bgra | Swizzle::Bgrx => Some(4),
+        Swizzle::Bgr => Some(3),
+        Swizzle::None => None,
+    };
+
+    let mut bytes = Vec::new();
+    let mut offset = reader.position() as usize;
+    // DDS stores every face with its full mip chain, this is the layout we want.
+    for _ in 0..face_count {
+        for level in 0..mip_count {
+            let level_width = (width >> level).max(1);
+            let level_height = (height >> level).max(1);
+            let size = match src_bytes_per_pixel {
+                Some(bpp) => level_width
+                    .checked_mul(level_height)
+                    .and_then(|pixels| pixels.checked_mul(bpp)),
+                None => kind.checked_level_size(level_width, level_height),
+            }
+            .ok_or_else(|| TextureError::InvalidData("DDS image is too large".to_owned()))?
+                as usize;
+            let level_data = offset
+                .checked_add(size)
+                .and_then(|end| data.get(offset..end))
+                .ok_or_else(|| TextureError::InvalidData("DDS data is truncated".to_owned()))?;
+            bytes.extend_from_slice(&swizzle(level_data, swizzle_mode));
+            offset += size;
+        }
+    }
+
+    Ok(ContainerData {
+        width,
+        height,
+        kind,
+        mip_count,
+        texture_type: if is_cube {
+            TextureType::Cube
+        } else {
+            TextureType::Rectangle
+        },
+        color_space,
+        bytes,
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use crate::resource::texture::{dds::read_dds, TextureKind, TextureType};
+    use byteorder::{LittleEndian, WriteBytesExt};
+
+    fn make_header(flags: u32, width: u32, height: u32, mips: u32, four_cc: &[u8; 4]) -> Vec<u8> {
+        let mut data = b"DDS ".to_vec();
+        data.write_u32::<LittleEndian>(124).unwrap();
+        data.write_u32::<LittleEndian>(flags).unwrap();
+        data.write_u32::<LittleEndian>(height).unwrap();
+        data.write_u32::<LittleEndian>(width).unwrap();
+        data.write_u32::<LittleEndian>(0).unwrap();
+        data.write_u32::<LittleEndian>(0).unwrap();
+        data.write_u32::<LittleEndian>(mips).unwrap();
+        data.extend_from_slice(&[0; 44]);
+        data.write_u32::<LittleEndian>(32).unwrap();
+        data.write_u32::<LittleEndian>(0x4).unwrap();
+        data.extend_from_slice(four_cc);
+        data.extend_from_slice(&[0; 20]);
+        data.extend_from_slice(&[0; 20]);
+        data
+    }
+
+    #[test]
+    fn dds_dxt1_mips_test() {
+        let mut data = make_header(0x0002_0000, 8, 8, 4, b"DXT1");
+        // 8x8 - 4 blocks, 4x4 - 1 block, 2x2 - 1 block, 1x1 - 1 block; 8 bytes per block.
+        data.extend_from_slice(&[1; 7 * 8]);
+        let texture = read_dds(&data).unwrap();
+        assert_eq!(texture.kind, TextureKind::DXT1RGBA);
+        assert_eq!(texture.mip_count, 4);
+        assert_eq!(texture.texture_type, TextureType::Rectangle);
+        assert_eq!(texture.bytes.len(), 7 * 8);
+
+        data.truncate(data.len() - 1);
+        assert!(read_dds(&data).is_err());
+    }
+
+    #[test]
+    fn dds_malformed_header_test() {
+        // Mip count exceeds amount of levels of 8x8 image.
+        let mut data = make_header(0x0002_0000, 8, 8, 40, b"DXT1");
+        data.extend_from_slice(&[1; 7 * 8]);
+        assert!(read_dds(&data).is_err());
+
+        // Size of image overflows.
+        let data = make_header(0, std::u32::MAX, std::u32::MAX, 1, b"DXT5");
+        assert!(read_dds(&data).is_err());
+    }
+}
@@ -0,0 +1,314 @@
+//! Single entry point for all image decoders supported by the engine.
+//!
+//! Every decoder handles set of file extensions and produces [ContainerData] with pixels,
+//! format and color space of the image. Raster formats (png, jpg, tga, bmp, gif, tiff) are
+//! decoded by image crate, containers and HDR formats have their own readers.
+
+use crate::resource::{
+    cache::{CacheKey, DerivedDataCache},
+    texture::{
+        dds, exr, hdr, ktx2, ColorSpace, ContainerData, TextureError, TextureKind, TextureType,
+    },
+};
+use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
+use image::{GenericImageView, ImageFormat};
+use std::io::{Cursor, Read};
+
+/// Decoder of a family of image formats.
+pub(in crate) trait ImageDecoder: Sync {
+    /// Returns list of lowercase file extensions handled by the decoder.
+    fn extensions(&self) -> &'static [&'static str];
+
+    /// Decodes image. Requested kind is a hint, decoder is free to ignore it if image has
+    /// its own pixel format (compressed or floating-point).
+    fn decode(&self, data: &[u8], kind: TextureKind) -> Result<ContainerData, TextureError>;
+}
+
+/// Decoder of raster formats with 8-bit components.
+struct RasterDecoder {
+    format: ImageFormat,
+    extensions: &'static [&'static str],
+}
+
+impl ImageDecoder for RasterDecoder {
+    fn extensions(&self) -> &'static [&'static str] {
+        self.extensions
+    }
+
+    fn decode(&self, data: &[u8], kind: TextureKind) -> Result<ContainerData, TextureError> {
+        // For animated GIFs only first frame is decoded.
+        let dyn_img = image::load_from_memory_with_format(data, self.format)?;
+
+        let (kind, bytes) = match kind {
+            TextureKind::R8 => (kind, dyn_img.to_luma().into_raw()),
+            TextureKind::RGB8 => (kind, dyn_img.to_rgb().into_raw()),
+            TextureKind::RGBA8 => (kind, dyn_img.to_rgba().into_raw()),
+            // Engine does not have a compressor and image decoders produce 8-bit images,
+            // fallback to uncompressed 8-bit format.
+            _ => (TextureKind::RGBA8, dyn_img.to_rgba().into_raw()),
+        };
+
+        Ok(ContainerData {
+            width: dyn_img.width(),
+            height: dyn_img.height(),
+            kind,
+            mip_count: 1,
+            texture_type: TextureType::Rectangle,
+            color_space: ColorSpace::Srgb,
+            bytes,
+        })
+    }
+}
+
+/// Decoder that is a simple function.
+struct FnDecoder {
+    decode: fn(&[u8]) -> Result<ContainerData, TextureError>,
+    extensions: &'static [&'static str],
+}
+
+impl ImageDecoder for FnDecoder {
+    fn extensions(&self) -> &'static [&'static str] {
+        self.extensions
+    }
+
+    fn decode(&self, data: &[u8], _kind: TextureKind) -> Result<ContainerData, TextureError> {
+        (self.decode)(data)
+    }
+}
+
+static DECODERS: &[&dyn ImageDecoder] = &[
+    &RasterDecoder {
+        format: ImageFormat::Png,
+        extensions: &["png"],
+    },
+    &RasterDecoder {
+        format: ImageFormat::Jpeg,
+        extensions: &["jpg", "jpeg"],
+    },
+    &RasterDecoder {
+        format: ImageFormat::Tga,
+        extensions: &["tga"],
+    },
+    &RasterDecoder {
+        format: ImageFormat::Bmp,
+        extensions: &["bmp"],
+    },
+    &RasterDecoder {
+        format: ImageFormat::Gif,
+        extensions: &["gif"],
+    },
+    &RasterDecoder {
+        format: ImageFormat::Tiff,
+        extensions: &["tif", "tiff"],
+    },
+    &FnDecoder {
+        decode: dds::read_dds,
+        extensions: &["dds"],
+    },
+    &FnDecoder {
+        decode: ktx2::read_ktx2,
+        extensions: &["ktx2"],
+    },
+    &FnDecoder {
+        decode: hdr::read_hdr,
+        extensions: &["hdr", "pic"],
+    },
+    &FnDecoder {
+        decode: exr::read_exr,
+        extensions: &["exr"],
+    },
+];
+
+/// Returns decoder for given lowercase extension.
+fn find_decoder(extension: &str) -> Option<&'static dyn ImageDecoder> {
+    DECODERS
+        .iter()
+        .find(|decoder| decoder.extensions().contains(&extension))
+        .copied()
+}
+
+/// Tries to find decoder by signature of the data, used when file has no extension or
+/// extension is unknown.
+fn guess_decoder(data: &[u8]) -> Option<&'static dyn ImageDecoder> {
+    if data.starts_with(b"DDS ") {
+        find_decoder("dds")
+    } else if data.starts_with(&[0xAB, 0x4B, 0x54, 0x58, 0x20, 0x32, 0x30, 0xBB]) {
+        find_decoder("ktx2")
+    } else if data.starts_with(b"#?RADIANCE") || data.starts_with(b"#?RGBE") {
+        find_decoder("hdr")
+    } else if data.starts_with(&[0x76, 0x2F, 0x31, 0x01]) {
+        find_decoder("exr")
+    } else {
+        // Note that TGA has no signature and cannot be guessed.
+        let format = image::guess_format(data).ok()?;
+        DECODERS.iter().copied().find(|decoder| {
+            decoder
+                .extensions()
+                .iter()
+                .any(|ext| ImageFormat::from_extension(ext) == Some(format))
+        })
+    }
+}
+
+/// Decodes image using decoder for given extension, falls back to guessing format by content
+/// if there is no such decoder.
+pub(in crate) fn decode(
+    extension: &str,
+    data: &[u8],
+    kind: TextureKind,
+) -> Result<ContainerData, TextureError> {
+    let decoder = find_decoder(extension)
+        .or_else(|| guess_decoder(data))
+        .ok_or_else(|| TextureError::UnsupportedFormat(format!("{:?} images", extension)))?;
+    decoder.decode(data, kind)
+}
+
+/// Containers which are already in ready-to-use format, there is no need to cache them.
+const NOT_CACHED: [&str; 2] = ["dds", "ktx2"];
+
+/// Extension of decoded images in derived data cache.
+const CACHE_EXTENSION: &str = "texture";
+
+fn write_cache_entry(image: &ContainerData) -> Vec<u8> {
+    let mut data = Vec::with_capacity(image.bytes.len() + 32);
+    for &value in &[
+        image.width,
+        image.height,
+        image.kind.id(),
+        image.mip_count,
+        match image.texture_type {
+            TextureType::Rectangle => 0,
+            TextureType::Cube => 1,
+        },
+        match image.color_space {
+            ColorSpace::Srgb => 0,
+            ColorSpace::Linear => 1,
+        },
+        image.bytes.len() as u32,
+    ] {
+        data.write_u32::<LittleEndian>(value).unwrap();
+    }
+    data.extend_from_slice(&image.bytes);
+    data
+}
+
+fn read_cache_entry(data: &[u8]) -> Option<ContainerData> {
+    let mut reader = Cursor::new(data);
+    let mut next = || reader.read_u32::<LittleEndian>().ok();
+    let width = next()?;
+    let height = next()?;
+    let kind = TextureKind::new(next()?).ok()?;
+    let mip_count = next()?;
+    let texture_type = match next()? {
+        0 => TextureType::Rectangle,
+        _ => TextureType::Cube,
+    };
+    let color_space = match next()? {
+        0 => ColorSpace::Srgb,
+        _ => ColorSpace::Linear,
+    };
+    let mut bytes = vec![0; next()? as usize];
+    reader.read_exact(&mut bytes).ok()?;
+    Some(ContainerData {
+        width,
+        height,
+        kind,
+        mip_count,
+        texture_type,
+        color_space,
+        bytes,
+    })
+}
+
+/// Same as [decode], but takes decoded image from derived data cache if possible and puts
+/// newly decoded image into the cache.
+pub(in crate) fn decode_cached(
+    cache: Option<&DerivedDataCache>,
+    extension: &str,
+    data: &[u8],
+    kind: TextureKind,
+) -> Result<ContainerData, TextureError> {
+    let cache = match cache {
+        Some(cache) if !NOT_CACHED.contains(&extension) => cache,
+        _ => return decode(extension, data, kind),
+    };
+
+    // Requested kind affects decoding of raster images.
+    let key = CacheKey::new(data, &[extension.as_bytes(), &kind.id().to_le_bytes()]);
+    if let Some(image) = cache
+        .read(key, CACHE_EXTENSION)
+        .and_then(|entry| read_cache_entry(&entry))
+    {
+        return Ok(image);
+    }
+
+    let image = decode(extension, data, kind)?;
+    // Failure to write cache entry is not critical, image is decoded anyway.
+    let _ = cache.write(key, CACHE_EXTENSION, &write_cache_entry(&image));
+    Ok(image)
+}
+
+/// Returns list of all supported file extensions.
+pub(in crate) fn supported_extensions() -> Vec<&'static str> {
+    DECODERS
+        .iter()
+        .flat_map(|decoder| decoder.extensions().iter().copied())
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use crate::resource::texture::{
+        decoder::{decode, read_cache_entry, supported_extensions, write_cache_entry},
+        ColorSpace, TextureError, TextureKind,
+    };
+
+    fn encode_png() -> Vec<u8> {
+        let mut data = Vec::new();
+        image::png::PngEncoder::new(&mut data)
+            .encode(&[255, 0, 0, 0, 255, 0], 2, 1, image::ColorType::Rgb8)
+            .unwrap();
+        data
+    }
+
+    #[test]
+    fn decode_png_test() {
+        let image = decode("png", &encode_png(), TextureKind::RGBA8).unwrap();
+        assert_eq!(image.kind, TextureKind::RGBA8);
+        assert_eq!(image.color_space, ColorSpace::Srgb);
+        assert_eq!(image.bytes, vec![255, 0, 0, 255, 0, 255, 0, 255]);
+    }
+
+    #[test]
+    fn guess_format_test() {
+        // Unknown extension, format is guessed by signature.
+        let image = decode("", &encode_png(), TextureKind::RGB8).unwrap();
+        assert_eq!(image.bytes, vec![255, 0, 0, 0, 255, 0]);
+
+        match decode("xyz", &[0; 16], TextureKind::RGB8) {
+            Err(TextureError::UnsupportedFormat(_)) => (),
+            _ => panic!("unknown format must be reported"),
+        }
+    }
+
+    #[test]
+    fn cache_entry_test() {
+        let image = decode("png", &encode_png(), TextureKind::RGB8).unwrap();
+        let restored = read_cache_entry(&write_cache_entry(&image)).unwrap();
+        assert_eq!(restored.width, 2);
+        assert_eq!(restored.kind, TextureKind::RGB8);
+        assert_eq!(restored.color_space, ColorSpace::Srgb);
+        assert_eq!(restored.bytes, image.bytes);
+        assert!(read_cache_entry(&[1, 2, 3]).is_none());
+    }
+
+    #[test]
+    fn extensions_test() {
+        let extensions = supported_extensions();
+        for ext in &[
+            "png", "jpg", "jpeg", "tga", "bmp", "gif", "dds", "ktx2", "hdr", "exr",
+        ] {
+            assert!(extensions.contains(ext));
+        }
+    }
+}
@@ -0,0 +1,461 @@
+//! OpenEXR (.exr) image reader.
+//!
+//! Supports single-part scanline images with NONE, RLE, ZIPS and ZIP compression and HALF,
+//! FLOAT or UINT channels. R, G, B and A channels are used (Y for grayscale images), layer
+//! prefixes are ignored. Tiled, deep, multi-part images and PIZ/PXR24/B44/DWA compression
+//! are not supported. Result is RGB or RGBA with 32-bit float components.
+
+use crate::resource::texture::{
+    ColorSpace, ContainerData, TextureError, TextureKind, TextureType, MAX_IMAGE_SIZE,
+};
+use byteorder::{LittleEndian, ReadBytesExt};
+use std::io::{Cursor, Read};
+
+const EXR_MAGIC: u32 = 20_000_630;
+
+const FLAG_TILED: u32 = 0x200;
+const FLAG_NON_IMAGE: u32 = 0x800;
+const FLAG_MULTI_PART: u32 = 0x1000;
+
+const PIXEL_TYPE_UINT: i32 = 0;
+const PIXEL_TYPE_HALF: i32 = 1;
+const PIXEL_TYPE_FLOAT: i32 = 2;
+
+fn invalid(reason: &str) -> TextureError {
+    TextureError::InvalidData(format!("OpenEXR: {}", reason))
+}
+
+#[derive(Copy, Clone, PartialEq)]
+enum Compression {
+    None,
+    Rle,
+    Zips,
+    Zip,
+}
+
+impl Compression {
+    fn lines_per_block(self) -> usize {
+        match self {
+            Compression::None | Compression::Rle | Compression::Zips => 1,
+            Compression::Zip => 16,
+        }
+    }
+}
+
+struct Channel {
+    name: String,
+    pixel_type: i32,
+}
+
+impl Channel {
+    fn sample_size(&self) -> usize {
+        if self.pixel_type == PIXEL_TYPE_HALF {
+            2
+        } else {
+            4
+        }
+    }
+}
+
+/// Converts IEEE 754 half-precision float to single-precision float.
+fn half_to_f32(half: u16) -> f32 {
+    let sign = ((half >> 15) as u32) << 31;
+    let exponent = ((half >> 10) & 0x1F) as u32;
+    let mantissa = (half & 0x3FF) as u32;
+    if exponent == 0 && mantissa != 0 {
+        // Subnormal half is a normal float.
+        let value = mantissa as f32 * 2.0f32.powi(-24);
+        return if sign != 0 { -value } else { value };
+    }
+    let bits = match exponent {
+        0 => sign,
+        0x1F => sign | 0x7F80_0000 | (mantissa << 13),
+        _ => sign | ((exponent + 127 - 15) << 23) | (mantissa << 13),
+    };
+    f32::from_bits(bits)
+}
+
+fn read_null_terminated(reader: &mut Cursor<&[u8]>) -> Result<String, TextureError> {
+    let mut bytes = Vec::new();
+    loop {
+        match reader.read_u8()? {
+            0 => break,
+            c => bytes.push(c),
+        }
+    }
+    String::from_utf8(bytes).map_err(|_| invalid("attribute name is not valid UTF-8"))
+}
+
+fn read_channels(value: &[u8]) -> Result<Vec<Channel>, TextureError> {
+    let mut reader = Cursor::new(value);
+    let mut channels = Vec::new();
+    loop {
+        let name = read_null_terminated(&mut reader)?;
+        if name.is_empty() {
+            break;
+        }
+        let pixel_type = reader.read_i32::<LittleEndian>()?;
+        // pLinear and reserved bytes.
+        reader.read_u32::<LittleEndian>()?;
+        let x_sampling = reader.read_i32::<LittleEndian>()?;
+        let y_sampling = reader.read_i32::<LittleEndian>()?;
+        if x_sampling != 1 || y_sampling != 1 {
+            return Err(TextureError::UnsupportedFormat(
+                "OpenEXR subsampled channels".to_owned(),
+            ));
+        }
+        if pixel_type != PIXEL_TYPE_UINT
+            && pixel_type != PIXEL_TYPE_HALF
+            && pixel_type != PIXEL_TYPE_FLOAT
+        {
+            return Err(invalid("unknown pixel type"));
+        }
+        channels.push(Channel { name, pixel_type });
+    }
+    Ok(channels)
+}
+
+/// Reverses predictor and byte reordering which is applied by RLE and ZIP compressors.
+fn reconstruct(data: &mut Vec<u8>) {
+    for i in 1..data.len() {
+        data[i] = data[i - 1].wrapping_add(data[i]).wrapping_sub(128);
+    }
+    let (first, second) = data.split_at((data.len() + 1) / 2);
+    let mut result = Vec::with_capacity(data.len());
+    for (i, &byte) in first.iter().enumerate() {
+        result.push(byte);
+        if let Some(&byte) = second.get(i) {
+            result.push(byte);
+        }
+    }
+    *data = result;
+}
+
+fn decompress_rle(data: &[u8], expected_size: usize) -> Result<Vec<u8>, TextureError> {
+    let mut result = Vec::with_capacity(expected_size);
+    let mut i = 0;
+    while i < data.len() {
+        let count = data[i] as i8;
+        i += 1;
+        if count < 0 {
+            let count = (-(count as i32)) as usize;
+            let literal = data
+                .get(i..(i + count))
+                .ok_or_else(|| invalid("RLE data is truncated"))?;
+            result.extend_from_slice(literal);
+            i += count;
+        } else {
+            let value = *data
+                .get(i)
+                .ok_or_else(|| invalid("RLE data is truncated"))?;
+            result.resize(result.len() + count as usize + 1, value);
+            i += 1;
+        }
+    }
+    Ok(result)
+}
+
+/// Reads OpenEXR file from memory.
+pub(in crate) fn read_exr(data: &[u8]) -> Result<ContainerData, TextureError> {
+    let mut reader = Cursor::new(data);
+
+    if reader.read_u32::<LittleEndian>()? != EXR_MAGIC {
+        return Err(invalid("not an OpenEXR file"));
+    }
+    let version = reader.read_u32::<LittleEndian>()?;
+    if version & (FLAG_TILED | FLAG_NON_IMAGE | FLAG_MULTI_PART) != 0 {
+        return Err(TextureError::UnsupportedFormat(
+            "OpenEXR tiled, deep or multi-part image".to_owned(),
+        ));
+    }
+
+    let mut channels = None;
+    let mut compression = None;
+    let mut data_window = None;
+    loop {
+        let name = read_null_terminated(&mut reader)?;
+        if name.is_empty() {
+            break;
+        }
+        let _type_name = read_null_terminated(&mut reader)?;
+        let size = reader.read_i32::<LittleEndian>()?;
+        if size < 0 || size as u64 > data.len() as u64 - reader.position() {
+            return Err(invalid("invalid attribute size"));
+        }
+        let mut value = vec![0; size as usize];
+        reader.read_exact(&mut value)?;
+        match name.as_str() {
+            "channels" => channels = Some(read_channels(&value)?),
+            "compression" => {
+                compression = Some(match value.first() {
+                    Some(0) => Compression::None,
+                    Some(1) => Compression::Rle,
+                    Some(2) => Compression::Zips,
+                    Some(3) => Compression::Zip,
+                    Some(other) => {
+                        return Err(TextureError::UnsupportedFormat(format!(
+                            "OpenEXR compression {}",
+                            other
+                        )))
+                    }
+                    None => return Err(invalid("empty compression attribute")),
+                })
+            }
+            "dataWindow" => {
+                let mut window = Cursor::new(value.as_slice());
+                data_window = Some([
+                    window.read_i32::<LittleEndian>()?,
+                    window.read_i32::<LittleEndian>()?,
+                    window.read_i32::<LittleEndian>()?,
+                    window.read_i32::<LittleEndian>()?,
+                ]);
+            }
+            _ => (),
+        }
+    }
+
+    let channels = channels.ok_or_else(|| invalid("missing channels attribute"))?;
+    let compression = compression.ok_or_else(|| invalid("missing compression attribute"))?;
+    let [x_min, y_min, x_max, y_max] =
+        data_window.ok_or_else(|| invalid("missing dataWindow attribute"))?;
+    if x_max < x_min || y_max < y_min {
+        return Err(invalid("invalid data window"));
+    }
+    let width = x_max as i64 - x_min as i64 + 1;
+    let height = y_max as i64 - y_min as i64 + 1;
+    if width > MAX_IMAGE_SIZE as i64 || height > MAX_IMAGE_SIZE as i64 {
+        return Err(TextureError::UnsupportedFormat(format!(
+            "OpenEXR image {}x{} is too large",
+            width, height
+        )));
+    }
+    let (width, height) = (width as usize, height as usize);
+
+    // Find source channel for every output component, layer prefix is ignored.
+    let find = |suffix: &str| {
+        channels
+            .iter()
+            .position(|c| c.name.rsplit('.').next() == Some(suffix))
+    };
+    let luminance = find("Y");
+    let sources = [
+        find("R").or(luminance),
+        find("G").or(luminance),
+        find("B").or(luminance),
+        find("A"),
+    ];
+    let component_count = if sources[3].is_some() { 4 } else { 3 };
+
+    let line_size = channels
+        .iter()
+        .map(|c| c.sample_size() * width)
+        .sum::<usize>();
+    let lines_per_block = compression.lines_per_block();
+    let block_count = (height + lines_per_block - 1) / lines_per_block;
+
+    // Chunks are read using offset table so line order does not matter. Table must be in the
+    // file, this limits size of the image by size of the file.
+    if (block_count * 8) as u64 > data.len() as u64 - reader.position() {
+        return Err(invalid("offset table is truncated"));
+    }
+    let mut offsets = Vec::with_capacity(block_count);
+    for _ in 0..block_count {
+        offsets.push(reader.read_u64::<LittleEndian>()? as usize);
+    }
+
+    let mut pixels = vec![0.0f32; width * height * component_count];
+    for offset in offsets {
+        let mut chunk = Cursor::new(data.get(offset..).ok_or_else(|| invalid("bad offset"))?);
+        let y = chunk.read_i32::<LittleEndian>()? as i64 - y_min as i64;
+        let packed_size = chunk.read_i32::<LittleEndian>()?;
+        if packed_size < 0 {
+            return Err(invalid("invalid chunk size"));
+        }
+        let packed_size = packed_size as usize;
+        let begin = offset + 8;
+        let packed = begin
+            .checked_add(packed_size)
+            .and_then(|end| data.get(begin..end))
+            .ok_or_else(|| invalid("chunk is truncated"))?;
+
+        if y < 0 || y as usize >= height {
+            return Err(invalid("chunk is outside of data window"));
+        }
+        let y = y as usize;
+        let line_count = lines_per_block.min(height - y);
+        let expected_size = line_count * line_size;
+
+        let block = if packed_size == expected_size {
+            // Data is stored uncompressed if compression does not reduce its size.
+            packed.to_vec()
+        } else {
+            match compression {
+                Compression::None => return Err(invalid("chunk size mismatch")),
+                Compression::Rle => {
+                    let mut block = decompress_rle(packed, expected_size)?;
+                    reconstruct(&mut block);
+                    block
+                }
+                Compression::Zips | Compression::Zip => {
+                    let mut block = inflate::inflate_bytes_zlib(packed).map_err(|e| invalid(&e))?;
+                    reconstruct(&mut block);
+                    block
+                }
+            }
+        };
+        if block.len() != expected_size {
+            return Err(invalid("decompressed chunk size mismatch"));
+        }
+
+        // Each line stores all samples of first channel, then all samples of second, etc.
+        let mut offset = 0;
+        for line in 0..line_count {
+            let row = y + line;
+            for (channel_index, channel) in channels.iter().enumerate() {
+                let sample_size = channel.sample_size();
+                for x in 0..width {
+                    let raw = &block[(offset + x * sample_size)..(offset + (x + 1) * sample_size)];
+                    let value = match channel.pixel_type {
+                        PIXEL_TYPE_HALF => half_to_f32(u16::from_le_bytes([raw[0], raw[1]])),
+                        PIXEL_TYPE_FLOAT => f32::from_le_bytes([raw[0], raw[1], raw[2], raw[3]]),
+                        _ => u32::from_le_bytes([raw[0], raw[1], raw[2], raw[3]]) as f32,
+                    };
+                    for (component, source) in sources.iter().enumerate().take(component_count) {
+                        if *source == Some(channel_index) {
+                            pixels[(row * width + x) * component_count + component] = value;
+                        }
+                    }
+                }
+                offset += sample_size * width;
+            }
+        }
+    }
+
+    Ok(ContainerData {
+        width: width as u32,
+        height: height as u32,
+        kind: if component_count == 4 {
+            TextureKind::RGBA32F
+        } else {
+            TextureKind::RGB32F
+        },
+        mip_count: 1,
+        texture_type: TextureType::Rectangle,
+        color_space: ColorSpace::Linear,
+        bytes: pixels
+            .iter()
+            .flat_map(|v| v.to_le_bytes().to_vec())
+            .collect(),
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use crate::resource::texture::{
+        exr::{half_to_f32, read_exr, reconstruct, EXR_MAGIC},
+        TextureError, TextureKind,
+    };
+    use byteorder::{LittleEndian, WriteBytesExt};
+
+    fn write_attribute(data: &mut Vec<u8>, name: &str, type_name: &str, value: &[u8]) {
+        data.extend_from_slice(name.as_bytes());
+        data.push(0);
+        data.extend_from_slice(type_name.as_bytes());
+        data.push(0);
+        data.write_i32::<LittleEndian>(value.len() as i32).unwrap();
+        data.extend_from_slice(value);
+    }
+
+    #[test]
+    fn half_test() {
+        assert_eq!(half_to_f32(0x3C00), 1.0);
+        assert_eq!(half_to_f32(0xC000), -2.0);
+        assert_eq!(half_to_f32(0x3800), 0.5);
+        assert_eq!(half_to_f32(0x0000), 0.0);
+        assert_eq!(half_to_f32(0x0001), 2.0f32.powi(-24));
+    }
+
+    #[test]
+    fn reconstruct_test() {
+        // Predictor of [1, 2, 3, 4] split into [1, 3] and [2, 4].
+        let mut data = vec![1, 130, 127, 130];
+        reconstruct(&mut data);
+        assert_eq!(data, vec![1, 2, 3, 4]);
+    }
+
+    #[test]
+    fn exr_uncompressed_test() {
+        let mut data = Vec::new();
+        data.write_u32::<LittleEndian>(EXR_MAGIC).unwrap();
+        data.write_u32::<LittleEndian>(2).unwrap();
+
+        let mut channels = Vec::new();
+        for name in &["B", "G", "R"] {
+            channels.extend_from_slice(name.as_bytes());
+            channels.push(0);
+            channels.write_i32::<LittleEndian>(1).unwrap();
+            channels.write_u32::<LittleEndian>(0).unwrap();
+            channels.write_i32::<LittleEndian>(1).unwrap();
+            channels.write_i32::<LittleEndian>(1).unwrap();
+        }
+        channels.push(0);
+        write_attribute(&mut data, "channels", "chlist", &channels);
+        write_attribute(&mut data, "compression", "compression", &[0]);
+        let mut window = Vec::new();
+        for v in &[0, 0, 1, 0] {
+            window.write_i32::<LittleEndian>(*v).unwrap();
+        }
+        write_attribute(&mut data, "dataWindow", "box2i", &window);
+        data.push(0);
+
+        // Single scanline - single chunk.
+        let chunk_offset = data.len() as u64 + 8;
+        data.write_u64::<LittleEndian>(chunk_offset).unwrap();
+        data.write_i32::<LittleEndian>(0).unwrap();
+        data.write_i32::<LittleEndian>(2 * 3 * 2).unwrap();
+        // B: 0.5, 0.5; G: 1.0, 1.0; R: 2.0, -2.0
+        for half in &[0x3800u16, 0x3800, 0x3C00, 0x3C00, 0x4000, 0xC000] {
+            data.write_u16::<LittleEndian>(*half).unwrap();
+        }
+
+        let image = read_exr(&data).unwrap();
+        assert_eq!(image.kind, TextureKind::RGB32F);
+        assert_eq!(image.width, 2);
+        let floats = image
+            .bytes
+            .chunks(4)
+            .map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+            .collect::<Vec<_>>();
+        assert_eq!(floats, vec![2.0, 1.0, 0.5, -2.0, 1.0, 0.5]);
+    }
+
+    #[test]
+    fn exr_malformed_header_test() {
+        let mut data = Vec::new();
+        data.write_u32::<LittleEndian>(EXR_MAGIC).unwrap();
+        data.write_u32::<LittleEndian>(2).unwrap();
+        // Negative attribute size.
+        data.extend_from_slice(b"channels\0chlist\0");
+        data.write_i32::<LittleEndian>(-1).unwrap();
+        assert!(matches!(read_exr(&data), Err(TextureError::InvalidData(_))));
+
+        // Data window which overflows i32.
+        let mut data = Vec::new();
+        data.write_u32::<LittleEndian>(EXR_MAGIC).unwrap();
+        data.write_u32::<LittleEndian>(2).unwrap();
+        let mut channels = b"R\0".to_vec();
+        for v in &[1, 0, 1, 1] {
+            channels.write_i32::<LittleEndian>(*v).unwrap();
+        }
+        channels.push(0);
+        write_attribute(&mut data, "channels", "chlist", &channels);
+        write_attribute(&mut data, "compression", "compression", &[0]);
+        let mut window = Vec::new();
+        for v in &[std::i32::MIN, 0, std::i32::MAX, 0] {
+            window.write_i32::<LittleEndian>(*v).unwrap();
+        }
+        write_attribute(&mut data, "dataWindow", "box2i", &window);
+        data.push(0);
+        assert!(read_exr(&data).is_err());
+    }
+}
@@ -0,0 +1,212 @@
+//! Radiance HDR (.hdr, .pic) image reader.
+//!
+//! Supports RGBE pixel format with flat and run-length encoded scanlines in standard
+//! (`-Y height +X width`) orientation. Result is always RGB with 32-bit float components.
+
+use crate::resource::texture::{
+    ColorSpace, ContainerData, TextureError, TextureKind, TextureType, MAX_IMAGE_SIZE,
+};
+
+fn invalid(reason: &str) -> TextureError {
+    TextureError::InvalidData(format!("Radiance HDR: {}", reason))
+}
+
+/// Reads line of header (without new line character), advances position.
+fn read_line<'a>(data: &'a [u8], position: &mut usize) -> Result<&'a str, TextureError> {
+    let begin = *position;
+    let end = data[begin..]
+        .iter()
+        .position(|&c| c == b'\n')
+        .map(|p| begin + p)
+        .ok_or_else(|| invalid("unexpected end of header"))?;
+    *position = end + 1;
+    std::str::from_utf8(&data[begin..end]).map_err(|_| invalid("header is not valid UTF-8"))
+}
+
+fn rgbe_to_float(rgbe: &[u8], out: &mut Vec<f32>) {
+    if rgbe[3] == 0 {
+        out.extend_from_slice(&[0.0, 0.0, 0.0]);
+    } else {
+        let scale = 2.0f32.powi(rgbe[3] as i32 - (128 + 8));
+        out.extend_from_slice(&[
+            rgbe[0] as f32 * scale,
+            rgbe[1] as f32 * scale,
+            rgbe[2] as f32 * scale,
+        ]);
+    }
+}
+
+/// Reads one RLE-encoded scanline, each component is encoded separately.
+fn read_rle_scanline(
+    data: &[u8],
+    position: &mut usize,
+    width: usize,
+    scanline: &mut [u8],
+) -> Result<(), TextureError> {
+    let mut next = || -> Result<u8, TextureError> {
+        let byte = *data
+            .get(*position)
+            .ok_or_else(|| invalid("unexpected end of data"))?;
+        *position += 1;
+        Ok(byte)
+    };
+    for component in 0..4 {
+        let mut x = 0;
+        while x < width {
+            let count = next()? as usize;
+            if count > 128 {
+                let count = count - 128;
+                let value = next()?;
+                if x + count > width {
+                    return Err(invalid("run exceeds scanline"));
+                }
+                for _ in 0..count {
+                    scanline[x * 4 + component] = value;
+                    x += 1;
+                }
+            } else {
+                if count == 0 || x + count > width {
+                    return Err(invalid("invalid run length"));
+                }
+                for _ in 0..count {
+                    scanline[x * 4 + component] = next()?;
+                    x += 1;
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Reads Radiance HDR file from memory.
+pub(in crate) fn read_hdr(data: &[u8]) -> Result<ContainerData, TextureError> {
+    let mut position = 0;
+
+    let signature = read_line(data, &mut position)?;
+    if !signature.starts_with("#?RADIANCE") && !signature.starts_with("#?RGBE") {
+        return Err(invalid("invalid signature"));
+    }
+
+    loop {
+        let line = read_line(data, &mut position)?;
+        if line.is_empty() {
+            break;
+        }
+        if line.starts_with("FORMAT=") && line != "FORMAT=32-bit_rle_rgbe" {
+            return Err(TextureError::UnsupportedFormat(line.to_owned()));
+        }
+    }
+
+    let resolution = read_line(data, &mut position)?;
+    let tokens = resolution.split_whitespace().collect::<Vec<_>>();
+    let (height, width) = match tokens.as_slice() {
+        ["-Y", height, "+X", width] => (
+            height
+                .parse::<usize>()
+                .map_err(|_| invalid("invalid height"))?,
+            width
+                .parse::<usize>()
+                .map_err(|_| invalid("invalid width"))?,
+        ),
+        _ => {
+            return Err(TextureError::UnsupportedFormat(format!(
+                "Radiance HDR orientation {}",
+                resolution
+            )))
+        }
+    };
+
+    if width > MAX_IMAGE_SIZE || height > MAX_IMAGE_SIZE {
+        return Err(TextureError::UnsupportedFormat(format!(
+            "Radiance HDR image {}x{} is too large",
+            width, height
+        )));
+    }
+
+    // Pixels are not preallocated, so memory grows only with scanlines that are actually in
+    // the file, even if its header is corrupted.
+    let mut pixels = Vec::new();
+    let mut scanline = vec![0; width * 4];
+    for _ in 0..height {
+        let header = data.get(position..(position + 4));
+        match header {
+            Some(&[2, 2, hi, lo]) if (8..0x8000).contains(&width) => {
+                if ((hi as usize) << 8 | lo as usize) != width {
+                    return Err(invalid("scanline width mismatch"));
+                }
+                position += 4;
+                read_rle_scanline(data, &mut position, width, &mut scanline)?;
+            }
+            _ => {
+                let flat = data
+                    .get(position..(position + width * 4))
+                    .ok_or_else(|| invalid("unexpected end of data"))?;
+                scanline.copy_from_slice(flat);
+                position += width * 4;
+            }
+        }
+        for rgbe in scanline.chunks(4) {
+            rgbe_to_float(rgbe, &mut pixels);
+        }
+    }
+
+    Ok(ContainerData {
+        width: width as u32,
+        height: height as u32,
+        kind: TextureKind::RGB32F,
+        mip_count: 1,
+        texture_type: TextureType::Rectangle,
+        color_space: ColorSpace::Linear,
+        bytes: pixels
+            .iter()
+            .flat_map(|v| v.to_le_bytes().to_vec())
+            .collect(),
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use crate::resource::texture::{hdr::read_hdr, TextureKind};
+
+    fn pixel(bytes: &[u8], index: usize) -> [f32; 3] {
+        let mut result = [0.0; 3];
+        for (i, v) in result.iter_mut().enumerate() {
+            let offset = (index * 3 + i) * 4;
+            let mut raw = [0; 4];
+            raw.copy_from_slice(&bytes[offset..offset + 4]);
+            *v = f32::from_le_bytes(raw);
+        }
+        result
+    }
+
+    #[test]
+    fn hdr_flat_test() {
+        let mut data = b"#?RADIANCE\nFORMAT=32-bit_rle_rgbe\n\n-Y 1 +X 2\n".to_vec();
+        data.extend_from_slice(&[128, 64, 0, 129, 0, 0, 0, 0]);
+        let image = read_hdr(&data).unwrap();
+        assert_eq!(image.kind, TextureKind::RGB32F);
+        assert_eq!(pixel(&image.bytes, 0), [1.0, 0.5, 0.0]);
+        assert_eq!(pixel(&image.bytes, 1), [0.0, 0.0, 0.0]);
+    }
+
+    #[test]
+    fn hdr_malformed_header_test() {
+        let data = b"#?RADIANCE\n\n-Y 4000000000 +X 4000000000\n".to_vec();
+        assert!(read_hdr(&data).is_err());
+        // Scanlines are missing.
+        let data = b"#?RADIANCE\n\n-Y 16384 +X 16384\n".to_vec();
+        assert!(read_hdr(&data).is_err());
+    }
+
+    #[test]
+    fn hdr_rle_test() {
+        let mut data = b"#?RGBE\n\n-Y 1 +X 8\n".to_vec();
+        data.extend_from_slice(&[2, 2, 0, 8]);
+        // Each component is a run of 8 same values.
+        data.extend_from_slice(&[136, 128, 136, 128, 136, 128, 136, 129]);
+        let image = read_hdr(&data).unwrap();
+        for i in 0..8 {
+            assert_eq!(pixel(&image.bytes, i), [1.0, 1.0, 1.0]);
+        }
+    }
+}
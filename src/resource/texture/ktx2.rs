@@ -0,0 +1,234 @@
+//! Khronos Texture 2.0 (.ktx2) container reader.
+//!
+//! Supports block-compressed formats (BC1-BC5, BC7), common uncompressed 8-bit formats,
+//! mip chains and cube maps. Supercompressed files (Basis Universal, Zstandard), texture
+//! arrays and volume textures are not supported.
+
+use crate::resource::texture::{
+    max_mip_count, ColorSpace, ContainerData, TextureError, TextureKind, TextureType,
+};
+use byteorder::{LittleEndian, ReadBytesExt};
+use std::io::{Cursor, Read};
+
+const KTX2_IDENTIFIER: [u8; 12] = [
+    0xAB, 0x4B, 0x54, 0x58, 0x20, 0x32, 0x30, 0xBB, 0x0D, 0x0A, 0x1A, 0x0A,
+];
+
+fn vk_format_to_kind(format: u32) -> Result<TextureKind, TextureError> {
+    match format {
+        // VK_FORMAT_R8_UNORM
+        9 => Ok(TextureKind::R8),
+        // VK_FORMAT_R8G8B8_UNORM, VK_FORMAT_R8G8B8_SRGB
+        23 | 29 => Ok(TextureKind::RGB8),
+        // VK_FORMAT_R8G8B8A8_UNORM, VK_FORMAT_R8G8B8A8_SRGB
+        37 | 43 => Ok(TextureKind::RGBA8),
+        // VK_FORMAT_BC1_RGBA_UNORM_BLOCK, VK_FORMAT_BC1_RGBA_SRGB_BLOCK
+        133 | 134 => Ok(TextureKind::DXT1RGBA),
+        // VK_FORMAT_BC2_UNORM_BLOCK, VK_FORMAT_BC2_SRGB_BLOCK
+        135 | 136 => Ok(TextureKind::DXT3RGBA),
+        // VK_FORMAT_BC3_UNORM_BLOCK, VK_FORMAT_BC3_SRGB_BLOCK
+        137 | 138 => Ok(TextureKind::DXT5RGBA),
+        // VK_FORMAT_BC4_UNORM_BLOCK
+        139 => Ok(TextureKind::R8RGTC),
+        // VK_FORMAT_BC5_UNORM_BLOCK
+        141 => Ok(TextureKind::RG8RGTC),
+        // VK_FORMAT_BC7_UNORM_BLOCK, VK_FORMAT_BC7_SRGB_BLOCK
+        145 | 146 => Ok(TextureKind::RGBA8BPTC),
+        _ => Err(TextureError::UnsupportedFormat(format!(
+            "Vulkan format {}",
+            format
+        ))),
+    }
+}
+
+fn vk_format_color_space(format: u32) -> ColorSpace {
+    match format {
+        29 | 43 | 134 | 136 | 138 | 146 => ColorSpace::Srgb,
+        _ => ColorSpace::Linear,
+    }
+}
+
+struct Level {
+    offset: u64,
+    length: u64,
+}
+
+/// Reads KTX2 file from memory.
+pub(in crate) fn read_ktx2(data: &[u8]) -> Result<ContainerData, TextureError> {
+    let mut reader = Cursor::new(data);
+
+    let mut identifier = [0; 12];
+    reader.read_exact(&mut identifier)?;
+    if identifier != KTX2_IDENTIFIER {
+        return Err(TextureError::InvalidData("Not a KTX2 file".to_owned()));
+    }
+
+    let vk_format = reader.read_u32::<LittleEndian>()?;
+    let _type_size = reader.read_u32::<LittleEndian>()?;
+    let width = reader.read_u32::<LittleEndian>()?;
+    let height = reader.read_u32::<LittleEndian>()?.max(1);
+    let depth = reader.read_u32::<LittleEndian>()?;
+    let layer_count = reader.read_u32::<LittleEndian>()?;
+    let face_count = reader.read_u32::<LittleEndian>()?;
+    let level_count = reader.read_u32::<LittleEndian>()?;
+    let supercompression_scheme = reader.read_u32::<LittleEndian>()?;
+
+    if vk_format == 0 {
+        return Err(TextureError::UnsupportedFormat(
+            "KTX2 with undefined format (Basis Universal)".to_owned(),
+        ));
+    }
+    if supercompression_scheme != 0 {
+        return Err(TextureError::UnsupportedFormat(format!(
+            "KTX2 supercompression scheme {}",
+            supercompression_scheme
+        )));
+    }
+    if depth > 1 || layer_count > 1 {
+        return Err(TextureError::UnsupportedFormat(
+            "KTX2 texture array or volume texture".to_owned(),
+        ));
+    }
+    if face_count != 1 && face_count != 6 {
+        return Err(TextureError::InvalidData(format!(
+            "Invalid KTX2 face count {}",
+            face_count
+        )));
+    }
+
+    let kind = vk_format_to_kind(vk_format)?;
+
+    // Skip data format descriptor, key-value data and supercompression global data indices,
+    // we do not need them.
+    for _ in 0..4 {
+        reader.read_u32::<LittleEndian>()?;
+    }
+    for _ in 0..2 {
+        reader.read_u64::<LittleEndian>()?;
+    }
+
+    // Zero level count means that mips must be generated by loader, renderer will do that.
+    let mip_count = level_count.max(1);
+    // Header is not trusted, corrupted file must not cause overflows or huge allocations.
+    if mip_count > max_mip_count(width, height) {
+        return Err(TextureError::InvalidData(format!(
+            "Invalid KTX2 level count {} for {}x{} image",
+            mip_count, width, height
+        )));
+    }
+    let mut levels = Vec::with_capacity(mip_count as usize);
+    for _ in 0..mip_count {
+        levels.push(Level {
+            offset: reader.read_u64::<LittleEndian>()?,
+            length: reader.read_u64::<LittleEndian>()?,
+        });
+        let _uncompressed_length = reader.read_u64::<LittleEndian>()?;
+    }
+
+    // KTX2 stores every level with all its faces, but we need every face with its mip chain.
+    let mut bytes = Vec::new();
+    for face in 0..face_count {
+        for (i, level) in levels.iter().enumerate() {
+            let level_width = (width >> i).max(1);
+            let level_height = (height >> i).max(1);
+            let invalid_size =
+                || TextureError::InvalidData(format!("Invalid size of KTX2 level {}", i));
+            let face_size = kind
+                .checked_level_size(level_width, level_height)
+                .ok_or_else(invalid_size)? as u64;
+            if face_size * face_count as u64 != level.length {
+                return Err(invalid_size());
+            }
+            let face_data = level
+                .offset
+                .checked_add(face as u64 * face_size)
+                .and_then(|begin| Some((begin, begin.checked_add(face_size)?)))
+                .filter(|&(_, end)| end <= data.len() as u64)
+                .map(|(begin, end)| &data[begin as usize..end as usize])
+                .ok_or_else(|| TextureError::InvalidData("KTX2 data is truncated".to_owned()))?;
+            bytes.extend_from_slice(face_data);
+        }
+    }
+
+    Ok(ContainerData {
+        width,
+        height,
+        kind,
+        mip_count,
+        texture_type: if face_count == 6 {
+            TextureType::Cube
+        } else {
+            TextureType::Rectangle
+        },
+        color_space: vk_format_color_space(vk_format),
+        bytes,
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use crate::resource::texture::{
+        ktx2::{read_ktx2, KTX2_IDENTIFIER},
+        ColorSpace, TextureKind, TextureType,
+    };
+    use byteorder::{LittleEndian, WriteBytesExt};
+
+    #[test]
+    fn ktx2_cube_map_test() {
+        let mut data = KTX2_IDENTIFIER.to_vec();
+        // R8G8B8A8, 2x2 cube map with 2 levels.
+        for value in &[37, 1, 2, 2, 0, 0, 6, 2, 0] {
+            data.write_u32::<LittleEndian>(*value).unwrap();
+        }
+        data.extend_from_slice(&[0; 4 * 4 + 2 * 8]);
+        let level_index_end = data.len() as u64 + 2 * 3 * 8;
+        let level0_length = 6 * 2 * 2 * 4;
+        let level1_length = 6 * 4;
+        for &(offset, length) in &[
+            (level_index_end + level1_length, level0_length),
+            (level_index_end, level1_length),
+        ] {
+            data.write_u64::<LittleEndian>(offset).unwrap();
+            data.write_u64::<LittleEndian>(length).unwrap();
+            data.write_u64::<LittleEndian>(length).unwrap();
+        }
+        // Levels are stored from smallest to largest, every face is filled with its index.
+        for face in 0..6u8 {
+            data.extend_from_slice(&[face; 4]);
+        }
+        for face in 0..6u8 {
+            data.extend_from_slice(&[face; 16]);
+        }
+
+        let texture = read_ktx2(&data).unwrap();
+        assert_eq!(texture.kind, TextureKind::RGBA8);
+        assert_eq!(texture.texture_type, TextureType::Cube);
+        assert_eq!(texture.mip_count, 2);
+        assert_eq!(texture.color_space, ColorSpace::Linear);
+        assert_eq!(texture.bytes.len(), 6 * (16 + 4));
+        // Second face with its mip chain.
+        assert!(texture.bytes[20..40].iter().all(|&b| b == 1));
+    }
+
+    #[test]
+    fn ktx2_malformed_header_test() {
+        let mut data = KTX2_IDENTIFIER.to_vec();
+        // R8G8B8A8, 2x2 image with absurd level count.
+        for value in &[37, 1, 2, 2, 0, 0, 1, std::u32::MAX, 0] {
+            data.write_u32::<LittleEndian>(*value).unwrap();
+        }
+        data.extend_from_slice(&[0; 4 * 4 + 2 * 8]);
+        assert!(read_ktx2(&data).is_err());
+
+        // Level points far outside of the file.
+        let mut data = KTX2_IDENTIFIER.to_vec();
+        for value in &[37, 1, 1, 1, 0, 0, 1, 1, 0] {
+            data.write_u32::<LittleEndian>(*value).unwrap();
+        }
+        data.extend_from_slice(&[0; 4 * 4 + 2 * 8]);
+        data.write_u64::<LittleEndian>(std::u64::MAX - 1).unwrap();
+        data.write_u64::<LittleEndian>(4).unwrap();
+        data.write_u64::<LittleEndian>(4).unwrap();
+        assert!(read_ktx2(&data).is_err());
+    }
+}
@@ -0,0 +1,462 @@
+//! Texture is an image that used to fill faces to add details to them.
+//!
+//! In most cases textures are just 2D images, however there are some exclusions to that -
+//! for example cube maps, that may be used for environment mapping. Cube maps can be loaded
+//! from DDS and KTX2 containers or made from equirectangular panoramas.
+//!
+//! # Supported formats
+//!
+//! All formats are loaded through single decoder interface, format is selected by file
+//! extension or guessed by content if extension is unknown. Unsupported or broken files are
+//! reported by [TextureError]. Supported formats:
+//!
+//! - png, jpg, tga, bmp, gif (first frame only), tiff - decoded with image crate into 8-bit
+//! textures of requested kind.
+//! - dds, ktx2 - see below.
+//! - hdr, exr - see below.
+//!
+//! See [supported_extensions] for full list of extensions.
+//!
+//! # Color space
+//!
+//! Every texture is tagged with color space of its pixels - [ColorSpace::Srgb] for usual
+//! images (colors), [ColorSpace::Linear] for floating-point images and for containers which
+//! store linear data (normal maps, masks, etc.). Renderer converts colors of textures in sRGB
+//! color space to linear space on sampling, lighting is done in linear space and final frame
+//! is gamma-corrected. Normal maps, specular maps and lightmaps are always sampled as is.
+//!
+//! DDS and KTX2 containers are read by the engine itself, texture keeps format of the file
+//! (including block-compressed formats) and pre-generated mip chain, requested kind is ignored
+//! for such textures.
+//!
+//! # HDR images
+//!
+//! Radiance HDR (.hdr) and OpenEXR (.exr) images are loaded as floating-point textures,
+//! requested kind is ignored too. Such images are usually equirectangular panoramas which
+//! are used as skyboxes and environment maps, use [Texture::equirectangular_to_cube_map]
+//! to convert them to cube maps.
+//!
+//! # Render target
+//!
+//! Texture can be used as render target to render scene in it. To do this you should make
+//! default instance of a texture and pass it to scene's render target property. Renderer
+//! will automatically provide you info about metrics of texture, but it won't give you
+//! access to pixels of render target.
+
+use crate::{
+    core::visitor::{Visit, VisitResult, Visitor},
+    resource::{cache::DerivedDataCache, vfs::VirtualFileSystem, ResourceState},
+};
+use image::{ColorType, ImageError};
+use std::{
+    fmt::Formatter,
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
+
+mod cube_map;
+mod dds;
+mod decoder;
+mod exr;
+mod hdr;
+mod ktx2;
+
+/// All possible errors that can occur during texture loading.
+#[derive(Debug)]
+pub enum TextureError {
+    /// An input/output error has occurred.
+    Io(std::io::Error),
+    /// Image decoder has failed.
+    Image(ImageError),
+    /// File uses pixel format or feature that is not supported.
+    UnsupportedFormat(String),
+    /// File is corrupted.
+    InvalidData(String),
+}
+
+impl std::fmt::Display for TextureError {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), std::fmt::Error> {
+        match self {
+            TextureError::Io(io) => write!(f, "Io error: {}", io),
+            TextureError::Image(image) => write!(f, "Image error: {}", image),
+            TextureError::UnsupportedFormat(format) => write!(f, "Unsupported format: {}", format),
+            TextureError::InvalidData(reason) => write!(f, "Invalid data: {}", reason),
+        }
+    }
+}
+
+impl From<std::io::Error> for TextureError {
+    fn from(err: std::io::Error) -> Self {
+        TextureError::Io(err)
+    }
+}
+
+impl From<ImageError> for TextureError {
+    fn from(err: ImageError) -> Self {
+        TextureError::Image(err)
+    }
+}
+
+fn unsupported_save(feature: String) -> ImageError {
+    ImageError::Unsupported(image::error::UnsupportedError::from_format_and_kind(
+        image::error::ImageFormatHint::Unknown,
+        image::error::UnsupportedErrorKind::GenericFeature(feature),
+    ))
+}
+
+/// Returns list of lowercase file extensions of all supported image formats.
+pub fn supported_extensions() -> Vec<&'static str> {
+    decoder::supported_extensions()
+}
+
+/// Defines how texture data is interpreted.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum TextureType {
+    /// Usual 2D image.
+    Rectangle,
+    /// Six square 2D images (faces), ordered +X, -X, +Y, -Y, +Z, -Z.
+    Cube,
+}
+
+/// Defines how color values of texture must be interpreted.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum ColorSpace {
+    /// Gamma-corrected values, usual for images made by artists.
+    Srgb,
+    /// Linear values, usual for floating-point images and non-color data like normal maps.
+    Linear,
+}
+
+impl ColorSpace {
+    /// Returns color space which is usually used with given texture kind.
+    pub fn default_for(kind: TextureKind) -> Self {
+        match kind {
+            TextureKind::RGB32F | TextureKind::RGBA32F => ColorSpace::Linear,
+            _ => ColorSpace::Srgb,
+        }
+    }
+}
+
+/// Decoded image or contents of texture container file.
+pub(in crate) struct ContainerData {
+    pub width: u32,
+    pub height: u32,
+    pub kind: TextureKind,
+    pub mip_count: u32,
+    pub texture_type: TextureType,
+    pub color_space: ColorSpace,
+    pub bytes: Vec<u8>,
+}
+
+/// See module docs.
+#[derive(Debug)]
+pub struct Texture {
+    pub(in crate) path: PathBuf,
+    pub(in crate) width: u32,
+    pub(in crate) height: u32,
+    pub(in crate) bytes: Vec<u8>,
+    pub(in crate) kind: TextureKind,
+    pub(in crate) state: ResourceState,
+    pub(in crate) mip_count: u32,
+    pub(in crate) texture_type: TextureType,
+    pub(in crate) color_space: ColorSpace,
+}
+
+impl Default for Texture {
+    fn default() -> Self {
+        Self {
+            path: PathBuf::new(),
+            width: 0,
+            height: 0,
+            bytes: Vec::new(),
+            kind: TextureKind::RGBA8,
+            state: ResourceState::Ok,
+            mip_count: 1,
+            texture_type: TextureType::Rectangle,
+            color_space: ColorSpace::Srgb,
+        }
+    }
+}
+
+impl Visit for Texture {
+    fn visit(&mut self, name: &str, visitor: &mut Visitor) -> VisitResult {
+        visitor.enter_region(name)?;
+
+        let mut kind = self.kind.id();
+        kind.visit("KindId", visitor)?;
+        if visitor.is_reading() {
+            self.kind = TextureKind::new(kind)?;
+        }
+
+        self.path.visit("Path", visitor)?;
+
+        visitor.leave_region()
+    }
+}
+
+/// Texture kind defines pixel format of texture.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum TextureKind {
+    /// Only red component as 1 byte.
+    R8,
+    /// Red, green, and blue components, each by 1 byte.
+    RGB8,
+    /// Red, green, blue, and alpha components, each by 1 byte.
+    RGBA8,
+    /// Block-compressed RGBA with 1-bit alpha (BC1).
+    DXT1RGBA,
+    /// Block-compressed RGBA with explicit alpha (BC2).
+    DXT3RGBA,
+    /// Block-compressed RGBA with interpolated alpha (BC3).
+    DXT5RGBA,
+    /// Block-compressed single red component (BC4).
+    R8RGTC,
+    /// Block-compressed red and green components (BC5), usually used for normal maps.
+    RG8RGTC,
+    /// Block-compressed high quality RGBA (BC7).
+    RGBA8BPTC,
+    /// Red, green and blue components, each is 32-bit float.
+    RGB32F,
+    /// Red, green, blue and alpha components, each is 32-bit float.
+    RGBA32F,
+}
+
+impl TextureKind {
+    fn new(id: u32) -> Result<Self, String> {
+        match id {
+            0 => Ok(Self::R8),
+            1 => Ok(Self::RGB8),
+            2 => Ok(Self::RGBA8),
+            3 => Ok(Self::DXT1RGBA),
+            4 => Ok(Self::DXT3RGBA),
+            5 => Ok(Self::DXT5RGBA),
+            6 => Ok(Self::R8RGTC),
+            7 => Ok(Self::RG8RGTC),
+            8 => Ok(Self::RGBA8BPTC),
+            9 => Ok(Self::RGB32F),
+            10 => Ok(Self::RGBA32F),
+            _ => Err(format!("Invalid texture kind {}!", id)),
+        }
+    }
+
+    fn id(self) -> u32 {
+        match self {
+            Self::R8 => 0,
+            Self::RGB8 => 1,
+            Self::RGBA8 => 2,
+            Self::DXT1RGBA => 3,
+            Self::DXT3RGBA => 4,
+            Self::DXT5RGBA => 5,
+            Self::R8RGTC => 6,
+            Self::RG8RGTC => 7,
+            Self::RGBA8BPTC => 8,
+            Self::RGB32F => 9,
+            Self::RGBA32F => 10,
+        }
+    }
+
+    /// Returns true if pixels are stored in 4x4 compressed blocks.
+    pub fn is_compressed(self) -> bool {
+        self.block_size().is_some()
+    }
+
+    /// Size of 4x4 block of compressed kinds in bytes.
+    fn block_size(self) -> Option<u32> {
+        match self {
+            Self::R8 | Self::RGB8 | Self::RGBA8 | Self::RGB32F | Self::RGBA32F => None,
+            Self::DXT1RGBA | Self::R8RGTC => Some(8),
+            Self::DXT3RGBA | Self::DXT5RGBA | Self::RG8RGTC | Self::RGBA8BPTC => Some(16),
+        }
+    }
+
+    /// Size of pixel in bytes, meaningful only for uncompressed kinds.
+    fn bytes_per_pixel(self) -> u32 {
+        match self {
+            Self::R8 => 1,
+            Self::RGB8 => 3,
+            Self::RGBA8 => 4,
+            Self::RGB32F => 12,
+            Self::RGBA32F => 16,
+            _ => 0,
+        }
+    }
+
+    /// Returns size in bytes of a single image (single mip level of single face) of given size.
+    /// Size saturates at `u32::MAX` for huge images.
+    pub fn level_size(self, width: u32, height: u32) -> u32 {
+        self.checked_level_size(width, height)
+            .unwrap_or(std::u32::MAX)
+    }
+
+    /// Same as [level_size](Self::level_size), but returns `None` on overflow. Decoders must
+    /// use it, because sizes come from headers of files.
+    pub(in crate) fn checked_level_size(self, width: u32, height: u32) -> Option<u32> {
+        match self.block_size() {
+            Some(block_size) => ((width / 4) + (width % 4 != 0) as u32)
+                .checked_mul((height / 4) + (height % 4 != 0) as u32)?
+                .checked_mul(block_size),
+            None => width
+                .checked_mul(height)?
+                .checked_mul(self.bytes_per_pixel()),
+        }
+    }
+}
+
+/// Largest supported width or height of decoded image, GPUs do not support bigger textures
+/// anyway. Decoders reject such images before allocating memory for pixels.
+pub(in crate) const MAX_IMAGE_SIZE: usize = 16384;
+
+/// Returns maximum amount of mip levels of image of given size - down to 1x1 level.
+pub(in crate) fn max_mip_count(width: u32, height: u32) -> u32 {
+    32 - width.max(height).max(1).leading_zeros()
+}
+
+impl Texture {
+    pub(in crate) fn load_from_vfs<P: AsRef<Path>>(
+        vfs: &Mutex<VirtualFileSystem>,
+        cache: Option<&DerivedDataCache>,
+        path: P,
+        kind: TextureKind,
+    ) -> Result<Self, TextureError> {
+        // Keep vfs locked only while reading, decoding could take a lot of time.
+        let data = vfs.lock().unwrap().read(path.as_ref())?;
+        Self::load_from_memory(cache, path, &data, kind)
+    }
+
+    /// Decodes texture from given data, path is used to determine image format and is
+    /// stored in the texture as is. Decoded image is taken from (or put into) derived data
+    /// cache, if the cache is given.
+    pub(in crate) fn load_from_memory<P: AsRef<Path>>(
+        cache: Option<&DerivedDataCache>,
+        path: P,
+        data: &[u8],
+        kind: TextureKind,
+    ) -> Result<Self, TextureError> {
+        let extension = path
+            .as_ref()
+            .extension()
+            .unwrap_or_default()
+            .to_string_lossy()
+            .to_lowercase();
+        let image = decoder::decode_cached(cache, &extension, data, kind)?;
+        Ok(Self {
+            path: path.as_ref().to_path_buf(),
+            width: image.width,
+            height: image.height,
+            bytes: image.bytes,
+            kind: image.kind,
+            state: ResourceState::Ok,
+            mip_count: image.mip_count,
+            texture_type: image.texture_type,
+            color_space: image.color_space,
+        })
+    }
+
+    /// Creates new texture instance from given parameters.
+    pub fn from_bytes(
+        width: u32,
+        height: u32,
+        kind: TextureKind,
+        bytes: Vec<u8>,
+    ) -> Result<Self, ()> {
+        let required_bytes = kind.level_size(width, height);
+        if required_bytes != bytes.len() as u32 {
+            Err(())
+        } else {
+            Ok(Self {
+                path: Default::default(),
+                width,
+                height,
+                bytes,
+                kind,
+                state: ResourceState::Ok,
+                mip_count: 1,
+                texture_type: TextureType::Rectangle,
+                color_space: ColorSpace::default_for(kind),
+            })
+        }
+    }
+
+    /// Returns width of texture (of first mip level).
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    /// Returns height of texture (of first mip level).
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    /// Returns pixel format of texture.
+    pub fn kind(&self) -> TextureKind {
+        self.kind
+    }
+
+    /// Returns amount of mip levels stored in texture. If there is only one level, renderer
+    /// will generate mips itself (except compressed textures).
+    pub fn mip_count(&self) -> u32 {
+        self.mip_count
+    }
+
+    /// Returns type of texture.
+    pub fn texture_type(&self) -> TextureType {
+        self.texture_type
+    }
+
+    /// Returns color space of texture pixels.
+    pub fn color_space(&self) -> ColorSpace {
+        self.color_space
+    }
+
+    /// Sets color space of texture pixels, use it to override color space of decoded image.
+    pub fn set_color_space(&mut self, color_space: ColorSpace) {
+        self.color_space = color_space;
+    }
+
+    /// Returns true if texture is loaded and its pixels can be used.
+    pub fn is_loaded(&self) -> bool {
+        self.state == ResourceState::Ok
+    }
+
+    /// Returns current load state of texture. Textures requested asynchronously will be in
+    /// `Pending` state until worker thread finish loading.
+    pub fn state(&self) -> ResourceState {
+        self.state
+    }
+
+    /// Returns path to source file of texture.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Sets new path to source file.
+    pub fn set_path<P: AsRef<Path>>(&mut self, path: &P) {
+        self.path = path.as_ref().to_owned();
+    }
+
+    /// Tries to save internal buffer into source file. Only first mip level is saved,
+    /// compressed textures and cube maps cannot be saved.
+    pub fn save(&self) -> Result<(), ImageError> {
+        let color_type = match self.kind {
+            TextureKind::R8 => ColorType::L8,
+            TextureKind::RGB8 => ColorType::Rgb8,
+            TextureKind::RGBA8 => ColorType::Rgba8,
+            _ => {
+                return Err(unsupported_save(format!(
+                    "saving of {:?} textures",
+                    self.kind
+                )))
+            }
+        };
+        if self.texture_type != TextureType::Rectangle {
+            return Err(unsupported_save("saving of cube maps".to_owned()));
+        }
+        image::save_buffer(
+            &self.path,
+            &self.bytes[..self.kind.level_size(self.width, self.height) as usize],
+            self.width,
+            self.height,
+            color_type,
+        )
+    }
+}
@@ -0,0 +1,1009 @@
+//! Declarative layouts of user interface.
+//!
+//! Layout is a file in a small subset of XML which describes tree of widgets, so layouts can be
+//! changed without recompilation - game only needs to build the layout again. Every element is
+//! a widget, its children are nested elements:
+//!
+//! ```text
+//! <!-- Comments are allowed. -->
+//! <Grid rows="30,stretch" columns="stretch,100" margin="4">
+//!     <Style name="header" valign="center" foreground="255,200,0"/>
+//!     <Text name="title" row="0" column="0" text="Options" style="header"/>
+//!     <Button name="close" row="0" column="1" text="Close"/>
+//!     <StackPanel row="1" column="0" orientation="vertical">
+//!         <ScrollBar name="volume" min="0" max="1" value="0.5" step="0.1" height="20"/>
+//!         <CheckBox name="fullscreen" checked="true" height="20"/>
+//!         <DropdownList name="quality" selected="0" height="20">
+//!             <Text text="Low"/>
+//!             <Text text="High"/>
+//!         </DropdownList>
+//!     </StackPanel>
+//! </Grid>
+//! ```
+//!
+//! Supported elements are:
+//!
+//! - `Grid` (`rows`, `columns` - comma-separated list of `auto`, `stretch` or size in pixels);
+//! - `StackPanel` (`orientation`), `Border`, `Canvas`;
+//! - `Text` (`text`), `Button` (`text`), `TextBox` (`text`), `CheckBox` (`checked`);
+//! - `ScrollBar` (`min`, `max`, `value`, `step`, `orientation`);
+//! - `Image` (`texture` - path to a texture, it is loaded only by
+//! [UiLayout::build_with_resources]);
+//! - `ListView` and `DropdownList` (`selected`) - child elements are items;
+//! - `Window` (`title`, `can_close`) and `ScrollViewer` - single child element is content.
+//!
+//! `Style` element defines named set of attributes, any element can apply it with `style`
+//! attribute. Attributes of a style are applied before attributes of the element, so the
+//! element can override them. Styles are visible in the whole layout regardless of where
+//! they are defined, they are not widgets and not children of enclosing element.
+//!
+//! Every element accepts common attributes of widgets:
+//!
+//! - `name` - name to find handle of the widget after build;
+//! - `width`, `height` - size in pixels;
+//! - `margin` - one value for every side or four values `left,top,right,bottom`;
+//! - `halign` (`left`, `center`, `right`, `stretch`), `valign` (`top`, `center`, `bottom`,
+//! `stretch`);
+//! - `row`, `column` - cell in parent grid;
+//! - `visible` - `true` or `false`;
+//! - `background`, `foreground` - color as `r,g,b` or `r,g,b,a`.
+//!
+//! Layout is parsed by [UiLayout::load] or `str::parse` and is validated completely at this
+//! moment, so building never fails.
+//!
+//! # Example
+//!
+//! ```no_run
+//! use rg3d::{engine::Engine, gui::node::StubNode, resource::ui_layout::UiLayout};
+//!
+//! # fn f(engine: &mut Engine<(), StubNode>) {
+//! let layout = UiLayout::load("data/ui/options.xml").unwrap();
+//! let built = layout.build(&mut engine.user_interface.build_ctx());
+//! let close = built.find("close");
+//! # }
+//! ```
+
+use crate::{
+    core::{color::Color, pool::Handle},
+    engine::resource_manager::ResourceManager,
+    gui::{
+        border::BorderBuilder,
+        brush::Brush,
+        button::ButtonBuilder,
+        canvas::CanvasBuilder,
+        check_box::CheckBoxBuilder,
+        dropdown_list::DropdownListBuilder,
+        grid::{Column, GridBuilder, Row},
+        image::ImageBuilder,
+        list_view::ListViewBuilder,
+        message::MessageData,
+        node::UINode,
+        scroll_bar::ScrollBarBuilder,
+        scroll_viewer::ScrollViewerBuilder,
+        stack_panel::StackPanelBuilder,
+        text::TextBuilder,
+        text_box::TextBoxBuilder,
+        widget::WidgetBuilder,
+        window::{WindowBuilder, WindowTitle},
+        BuildContext, Control, HorizontalAlignment, Orientation, Thickness, VerticalAlignment,
+    },
+    resource::texture::TextureKind,
+    utils::into_gui_texture,
+};
+use std::{
+    collections::HashMap,
+    fmt::{Display, Formatter},
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+/// All possible errors that can occur during loading of a layout.
+#[derive(Debug)]
+pub enum UiLayoutError {
+    /// An input/output error has occurred.
+    Io(std::io::Error),
+    /// Layout is not well-formed.
+    Syntax {
+        /// Line where error was found, starting from one.
+        line: usize,
+        /// Description of the error.
+        message: String,
+    },
+    /// Element is not supported.
+    UnknownElement {
+        /// Line of the element.
+        line: usize,
+        /// Name of the element.
+        element: String,
+    },
+    /// Attribute is not supported by an element or its value is invalid.
+    InvalidAttribute {
+        /// Line of the element.
+        line: usize,
+        /// Name of the attribute.
+        attribute: String,
+        /// Value of the attribute.
+        value: String,
+    },
+}
+
+impl Display for UiLayoutError {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), std::fmt::Error> {
+        match self {
+            UiLayoutError::Io(io) => write!(f, "Io error: {}", io),
+            UiLayoutError::Syntax { line, message } => {
+                write!(f, "Syntax error at line {}: {}", line, message)
+            }
+            UiLayoutError::UnknownElement { line, element } => {
+                write!(f, "Unknown element {} at line {}", element, line)
+            }
+            UiLayoutError::InvalidAttribute {
+                line,
+                attribute,
+                value,
+            } => write!(
+                f,
+                "Invalid attribute {}=\"{}\" at line {}",
+                attribute, value, line
+            ),
+        }
+    }
+}
+
+impl From<std::io::Error> for UiLayoutError {
+    fn from(err: std::io::Error) -> Self {
+        UiLayoutError::Io(err)
+    }
+}
+
+/// Element of markup as it is written in the file.
+#[derive(Debug, PartialEq)]
+struct RawElement {
+    line: usize,
+    tag: String,
+    attributes: Vec<(String, String)>,
+    children: Vec<RawElement>,
+}
+
+struct Parser<'a> {
+    source: &'a str,
+    position: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn line(&self) -> usize {
+        self.source[..self.position].matches('\n').count() + 1
+    }
+
+    fn error<T, S: Into<String>>(&self, message: S) -> Result<T, UiLayoutError> {
+        Err(UiLayoutError::Syntax {
+            line: self.line(),
+            message: message.into(),
+        })
+    }
+
+    fn rest(&self) -> &'a str {
+        &self.source[self.position..]
+    }
+
+    fn skip_whitespace(&mut self) {
+        let rest = self.rest();
+        self.position += rest.len() - rest.trim_start().len();
+    }
+
+    fn skip_misc(&mut self) -> Result<(), UiLayoutError> {
+        loop {
+            self.skip_whitespace();
+            if self.rest().starts_with("<!--") {
+                match self.rest().find("-->") {
+                    Some(end) => self.position += end + 3,
+                    None => return self.error("Unterminated comment"),
+                }
+            } else if self.rest().starts_with("<?") {
+                match self.rest().find("?>") {
+                    Some(end) => self.position += end + 2,
+                    None => return self.error("Unterminated declaration"),
+                }
+            } else {
+                return Ok(());
+            }
+        }
+    }
+
+    fn expect(&mut self, token: &str) -> Result<(), UiLayoutError> {
+        if self.rest().starts_with(token) {
+            self.position += token.len();
+            Ok(())
+        } else {
+            self.error(format!("Expected {}", token))
+        }
+    }
+
+    fn name(&mut self) -> Result<String, UiLayoutError> {
+        let rest = self.rest();
+        let length = rest
+            .find(|c: char| !(c.is_alphanumeric() || c == '_' || c == '-' || c == '.'))
+            .unwrap_or(rest.len());
+        if length == 0 {
+            return self.error("Expected name");
+        }
+        self.position += length;
+        Ok(rest[..length].to_owned())
+    }
+
+    fn attribute_value(&mut self) -> Result<String, UiLayoutError> {
+        let quote = match self.rest().chars().next() {
+            Some(quote) if quote == '"' || quote == '\'' => quote,
+            _ => return self.error("Expected quoted value"),
+        };
+        self.position += 1;
+        let end = match self.rest().find(quote) {
+            Some(end) => end,
+            None => return self.error("Unterminated value"),
+        };
+        let value = self.rest()[..end]
+            .replace("&lt;", "<")
+            .replace("&gt;", ">")
+            .replace("&quot;", "\"")
+            .replace("&apos;", "'")
+            .replace("&amp;", "&");
+        self.position += end + 1;
+        Ok(value)
+    }
+
+    fn element(&mut self) -> Result<RawElement, UiLayoutError> {
+        let line = self.line();
+        self.expect("<")?;
+        let tag = self.name()?;
+        let mut element = RawElement {
+            line,
+            tag,
+            attributes: Vec::new(),
+            children: Vec::new(),
+        };
+
+        loop {
+            self.skip_whitespace();
+            if self.rest().starts_with("/>") {
+                self.position += 2;
+                return Ok(element);
+            } else if self.rest().starts_with('>') {
+                self.position += 1;
+                break;
+            }
+            let name = self.name()?;
+            self.skip_whitespace();
+            self.expect("=")?;
+            self.skip_whitespace();
+            let value = self.attribute_value()?;
+            element.attributes.push((name, value));
+        }
+
+        loop {
+            self.skip_misc()?;
+            if self.rest().starts_with("</") {
+                self.position += 2;
+                let tag = self.name()?;
+                if tag != element.tag {
+                    return self.error(format!("Expected </{}>, found </{}>", element.tag, tag));
+                }
+                self.skip_whitespace();
+                self.expect(">")?;
+                return Ok(element);
+            } else if self.rest().starts_with('<') {
+                element.children.push(self.element()?);
+            } else if self.rest().is_empty() {
+                return self.error(format!("Unterminated element {}", element.tag));
+            } else {
+                return self.error("Text content is not supported, use text attribute");
+            }
+        }
+    }
+
+    fn document(&mut self) -> Result<RawElement, UiLayoutError> {
+        self.skip_misc()?;
+        let root = self.element()?;
+        self.skip_misc()?;
+        if !self.rest().is_empty() {
+            return self.error("Layout must have single root element");
+        }
+        Ok(root)
+    }
+}
+
+/// Size of a row or column of a grid.
+#[derive(Copy, Clone, Debug, PartialEq)]
+enum Dimension {
+    Auto,
+    Stretch,
+    Strict(f32),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum ElementKind {
+    Grid {
+        rows: Vec<Dimension>,
+        columns: Vec<Dimension>,
+    },
+    StackPanel {
+        orientation: Orientation,
+    },
+    Border,
+    Canvas,
+    Text {
+        text: String,
+    },
+    Button {
+        text: String,
+    },
+    ScrollBar {
+        min: f32,
+        max: f32,
+        value: f32,
+        step: f32,
+        orientation: Orientation,
+    },
+    CheckBox {
+        checked: bool,
+    },
+    Image {
+        texture: Option<PathBuf>,
+    },
+    TextBox {
+        text: String,
+    },
+    ListView,
+    DropdownList {
+        selected: Option<usize>,
+    },
+    Window {
+        title: String,
+        can_close: bool,
+    },
+    ScrollViewer,
+}
+
+impl ElementKind {
+    /// Returns true if children of the element are its items or content instead of children
+    /// of its widget.
+    fn owns_children(&self) -> bool {
+        matches!(
+            self,
+            ElementKind::ListView
+                | ElementKind::DropdownList { .. }
+                | ElementKind::Window { .. }
+                | ElementKind::ScrollViewer
+        )
+    }
+
+    fn has_single_child(&self) -> bool {
+        matches!(self, ElementKind::Window { .. } | ElementKind::ScrollViewer)
+    }
+}
+
+type Styles = HashMap<String, Vec<(String, String)>>;
+
+const STYLE_TAG: &str = "Style";
+
+/// Collects styles of the whole layout, style must have a name and must not have children.
+fn collect_styles(raw: &RawElement, styles: &mut Styles) -> Result<(), UiLayoutError> {
+    for child in raw.children.iter() {
+        if child.tag == STYLE_TAG {
+            if !child.children.is_empty() {
+                return Err(UiLayoutError::Syntax {
+                    line: child.line,
+                    message: "Style must not have children".to_owned(),
+                });
+            }
+            let mut name = None;
+            let mut attributes = Vec::new();
+            for (attribute, value) in child.attributes.iter() {
+                if attribute == "name" {
+                    name = Some(value.clone());
+                } else {
+                    attributes.push((attribute.clone(), value.clone()));
+                }
+            }
+            match name {
+                Some(name) => {
+                    styles.insert(name, attributes);
+                }
+                None => {
+                    return Err(UiLayoutError::Syntax {
+                        line: child.line,
+                        message: "Style must have a name".to_owned(),
+                    })
+                }
+            }
+        } else {
+            collect_styles(child, styles)?;
+        }
+    }
+    Ok(())
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+struct WidgetProperties {
+    name: Option<String>,
+    width: Option<f32>,
+    height: Option<f32>,
+    margin: Option<Thickness>,
+    horizontal_alignment: Option<HorizontalAlignment>,
+    vertical_alignment: Option<VerticalAlignment>,
+    row: usize,
+    column: usize,
+    visible: Option<bool>,
+    background: Option<Color>,
+    foreground: Option<Color>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+struct LayoutElement {
+    kind: ElementKind,
+    properties: WidgetProperties,
+    children: Vec<LayoutElement>,
+}
+
+fn parse_list<T, F: Fn(&str) -> Option<T>>(value: &str, f: F) -> Option<Vec<T>> {
+    value.split(',').map(|item| f(item.trim())).collect()
+}
+
+fn parse_dimension(value: &str) -> Option<Dimension> {
+    match value {
+        "auto" => Some(Dimension::Auto),
+        "stretch" | "*" => Some(Dimension::Stretch),
+        _ => value.parse().ok().map(Dimension::Strict),
+    }
+}
+
+fn parse_orientation(value: &str) -> Option<Orientation> {
+    match value {
+        "horizontal" => Some(Orientation::Horizontal),
+        "vertical" => Some(Orientation::Vertical),
+        _ => None,
+    }
+}
+
+fn parse_thickness(value: &str) -> Option<Thickness> {
+    match parse_list(value, |v| v.parse::<f32>().ok())?.as_slice() {
+        [uniform] => Some(Thickness::uniform(*uniform)),
+        [left, top, right, bottom] => Some(Thickness {
+            left: *left,
+            top: *top,
+            right: *right,
+            bottom: *bottom,
+        }),
+        _ => None,
+    }
+}
+
+fn parse_color(value: &str) -> Option<Color> {
+    match parse_list(value, |v| v.parse::<u8>().ok())?.as_slice() {
+        [r, g, b] => Some(Color::opaque(*r, *g, *b)),
+        [r, g, b, a] => Some(Color::from_rgba(*r, *g, *b, *a)),
+        _ => None,
+    }
+}
+
+impl LayoutElement {
+    fn from_raw(raw: &RawElement, styles: &Styles) -> Result<Self, UiLayoutError> {
+        let mut kind = match raw.tag.as_str() {
+            "Grid" => ElementKind::Grid {
+                rows: Vec::new(),
+                columns: Vec::new(),
+            },
+            "StackPanel" => ElementKind::StackPanel {
+                orientation: Orientation::Vertical,
+            },
+            "Border" => ElementKind::Border,
+            "Canvas" => ElementKind::Canvas,
+            "Text" => ElementKind::Text {
+                text: Default::default(),
+            },
+            "Button" => ElementKind::Button {
+                text: Default::default(),
+            },
+            "ScrollBar" => ElementKind::ScrollBar {
+                min: 0.0,
+                max: 100.0,
+                value: 0.0,
+                step: 1.0,
+                orientation: Orientation::Horizontal,
+            },
+            "CheckBox" => ElementKind::CheckBox { checked: false },
+            "Image" => ElementKind::Image { texture: None },
+            "TextBox" => ElementKind::TextBox {
+                text: Default::default(),
+            },
+            "ListView" => ElementKind::ListView,
+            "DropdownList" => ElementKind::DropdownList { selected: None },
+            "Window" => ElementKind::Window {
+                title: Default::default(),
+                can_close: true,
+            },
+            "ScrollViewer" => ElementKind::ScrollViewer,
+            _ => {
+                return Err(UiLayoutError::UnknownElement {
+                    line: raw.line,
+                    element: raw.tag.clone(),
+                })
+            }
+        };
+
+        let mut attributes = Vec::new();
+        for (attribute, value) in raw.attributes.iter() {
+            if attribute == "style" {
+                match styles.get(value) {
+                    Some(style) => attributes.splice(0..0, style.iter().cloned()),
+                    None => {
+                        return Err(UiLayoutError::InvalidAttribute {
+                            line: raw.line,
+                            attribute: attribute.clone(),
+                            value: value.clone(),
+                        })
+                    }
+                };
+            } else {
+                attributes.push((attribute.clone(), value.clone()));
+            }
+        }
+
+        let mut properties = WidgetProperties::default();
+        for (attribute, value) in attributes.iter() {
+            let v = value.as_str();
+            let valid = match (attribute.as_str(), &mut kind) {
+                ("name", _) => {
+                    properties.name = Some(value.clone());
+                    Some(())
+                }
+                ("width", _) => v.parse().ok().map(|w| properties.width = Some(w)),
+                ("height", _) => v.parse().ok().map(|h| properties.height = Some(h)),
+                ("margin", _) => parse_thickness(v).map(|m| properties.margin = Some(m)),
+                ("halign", _) => match v {
+                    "left" => Some(HorizontalAlignment::Left),
+                    "center" => Some(HorizontalAlignment::Center),
+                    "right" => Some(HorizontalAlignment::Right),
+                    "stretch" => Some(HorizontalAlignment::Stretch),
+                    _ => None,
+                }
+                .map(|a| properties.horizontal_alignment = Some(a)),
+                ("valign", _) => match v {
+                    "top" => Some(VerticalAlignment::Top),
+                    "center" => Some(VerticalAlignment::Center),
+                    "bottom" => Some(VerticalAlignment::Bottom),
+                    "stretch" => Some(VerticalAlignment::Stretch),
+                    _ => None,
+                }
+                .map(|a| properties.vertical_alignment = Some(a)),
+                ("row", _) => v.parse().ok().map(|r| properties.row = r),
+                ("column", _) => v.parse().ok().map(|c| properties.column = c),
+                ("visible", _) => v.parse().ok().map(|b| properties.visible = Some(b)),
+                ("background", _) => parse_color(v).map(|c| properties.background = Some(c)),
+                ("foreground", _) => parse_color(v).map(|c| properties.foreground = Some(c)),
+                ("rows", ElementKind::Grid { rows, .. }) => {
+                    parse_list(v, parse_dimension).map(|list| *rows = list)
+                }
+                ("columns", ElementKind::Grid { columns, .. }) => {
+                    parse_list(v, parse_dimension).map(|list| *columns = list)
+                }
+                ("orientation", ElementKind::StackPanel { orientation })
+                | ("orientation", ElementKind::ScrollBar { orientation, .. }) => {
+                    parse_orientation(v).map(|o| *orientation = o)
+                }
+                ("text", ElementKind::Text { text })
+                | ("text", ElementKind::Button { text })
+                | ("text", ElementKind::TextBox { text })
+                | ("title", ElementKind::Window { title: text, .. }) => {
+                    *text = value.clone();
+                    Some(())
+                }
+                ("min", ElementKind::ScrollBar { min, .. }) => v.parse().ok().map(|x| *min = x),
+                ("max", ElementKind::ScrollBar { max, .. }) => v.parse().ok().map(|x| *max = x),
+                ("value", ElementKind::ScrollBar { value, .. }) => {
+                    v.parse().ok().map(|x| *value = x)
+                }
+                ("step", ElementKind::ScrollBar { step, .. }) => v.parse().ok().map(|x| *step = x),
+                ("checked", ElementKind::CheckBox { checked }) => {
+                    v.parse().ok().map(|b| *checked = b)
+                }
+                ("texture", ElementKind::Image { texture }) => {
+                    *texture = Some(PathBuf::from(v));
+                    Some(())
+                }
+                ("selected", ElementKind::DropdownList { selected }) => {
+                    v.parse().ok().map(|i| *selected = Some(i))
+                }
+                ("can_close", ElementKind::Window { can_close, .. }) => {
+                    v.parse().ok().map(|b| *can_close = b)
+                }
+                _ => None,
+            };
+            if valid.is_none() {
+                return Err(UiLayoutError::InvalidAttribute {
+                    line: raw.line,
+                    attribute: attribute.clone(),
+                    value: value.clone(),
+                });
+            }
+        }
+
+        let children = raw
+            .children
+            .iter()
+            .filter(|child| child.tag != STYLE_TAG)
+            .map(|child| LayoutElement::from_raw(child, styles))
+            .collect::<Result<Vec<_>, _>>()?;
+        if kind.has_single_child() && children.len() > 1 {
+            return Err(UiLayoutError::Syntax {
+                line: raw.line,
+                message: format!("{} must have at most one child", raw.tag),
+            });
+        }
+
+        Ok(Self {
+            kind,
+            properties,
+            children,
+        })
+    }
+
+    fn build<M: MessageData, C: Control<M, C>>(
+        &self,
+        ctx: &mut BuildContext<M, C>,
+        names: &mut HashMap<String, Handle<UINode<M, C>>>,
+        mut resource_manager: Option<&mut ResourceManager>,
+    ) -> Handle<UINode<M, C>> {
+        let properties = &self.properties;
+        let mut widget = WidgetBuilder::new()
+            .on_row(properties.row)
+            .on_column(properties.column);
+        if let Some(width) = properties.width {
+            widget = widget.with_width(width);
+        }
+        if let Some(height) = properties.height {
+            widget = widget.with_height(height);
+        }
+        if let Some(margin) = properties.margin {
+            widget = widget.with_margin(margin);
+        }
+        if let Some(alignment) = properties.horizontal_alignment {
+            widget = widget.with_horizontal_alignment(alignment);
+        }
+        if let Some(alignment) = properties.vertical_alignment {
+            widget = widget.with_vertical_alignment(alignment);
+        }
+        if let Some(visible) = properties.visible {
+            widget = widget.with_visibility(visible);
+        }
+        if let Some(color) = properties.background {
+            widget = widget.with_background(Brush::Solid(color));
+        }
+        if let Some(color) = properties.foreground {
+            widget = widget.with_foreground(Brush::Solid(color));
+        }
+        let children = self
+            .children
+            .iter()
+            .map(|child| child.build(ctx, names, resource_manager.as_deref_mut()))
+            .collect::<Vec<_>>();
+        if !self.kind.owns_children() {
+            widget = widget.with_children(&children);
+        }
+        let content = children.first().cloned().unwrap_or(Handle::NONE);
+
+        let handle = match &self.kind {
+            ElementKind::Grid { rows, columns } => {
+                let mut grid = GridBuilder::new(widget);
+                for row in rows {
+                    grid = grid.add_row(match *row {
+                        Dimension::Auto => Row::auto(),
+                        Dimension::Stretch => Row::stretch(),
+                        Dimension::Strict(size) => Row::strict(size),
+                    });
+                }
+                for column in columns {
+                    grid = grid.add_column(match *column {
+                        Dimension::Auto => Column::auto(),
+                        Dimension::Stretch => Column::stretch(),
+                        Dimension::Strict(size) => Column::strict(size),
+                    });
+                }
+                grid.build(ctx)
+            }
+            ElementKind::StackPanel { orientation } => StackPanelBuilder::new(widget)
+                .with_orientation(*orientation)
+                .build(ctx),
+            ElementKind::Border => BorderBuilder::new(widget).build(ctx),
+            ElementKind::Canvas => CanvasBuilder::new(widget).build(ctx),
+            ElementKind::Text { text } => TextBuilder::new(widget).with_text(text).build(ctx),
+            ElementKind::Button { text } => ButtonBuilder::new(widget).with_text(text).build(ctx),
+            ElementKind::ScrollBar {
+                min,
+                max,
+                value,
+                step,
+                orientation,
+            } => ScrollBarBuilder::new(widget)
+                .with_min(*min)
+                .with_max(*max)
+                .with_value(*value)
+                .with_step(*step)
+                .with_orientation(*orientation)
+                .build(ctx),
+            ElementKind::CheckBox { checked } => CheckBoxBuilder::new(widget)
+                .checked(Some(*checked))
+                .build(ctx),
+            ElementKind::Image { texture } => {
+                let texture = match (texture, resource_manager) {
+                    (Some(path), Some(resource_manager)) => {
+                        into_gui_texture(resource_manager.request_texture(path, TextureKind::RGBA8))
+                    }
+                    _ => None,
+                };
+                ImageBuilder::new(widget)
+                    .with_opt_texture(texture)
+                    .build(ctx)
+            }
+            ElementKind::TextBox { text } => TextBoxBuilder::new(widget).with_text(text).build(ctx),
+            ElementKind::ListView => ListViewBuilder::new(widget).with_items(children).build(ctx),
+            ElementKind::DropdownList { selected } => {
+                let mut dropdown_list = DropdownListBuilder::new(widget).with_items(children);
+                if let Some(selected) = *selected {
+                    dropdown_list = dropdown_list.with_selected(selected);
+                }
+                dropdown_list.build(ctx)
+            }
+            ElementKind::Window { title, can_close } => WindowBuilder::new(widget)
+                .with_content(content)
+                .with_title(WindowTitle::text(title))
+                .can_close(*can_close)
+                .build(ctx),
+            ElementKind::ScrollViewer => ScrollViewerBuilder::new(widget)
+                .with_content(content)
+                .build(ctx),
+        };
+
+        if let Some(name) = properties.name.as_ref() {
+            names.insert(name.clone(), handle);
+        }
+        handle
+    }
+}
+
+/// Parsed and validated layout. See module docs.
+#[derive(Clone, Debug, PartialEq)]
+pub struct UiLayout {
+    root: LayoutElement,
+}
+
+/// Widgets built from a layout.
+pub struct BuiltLayout<M: MessageData, C: Control<M, C>> {
+    /// Root widget of the layout.
+    pub root: Handle<UINode<M, C>>,
+    names: HashMap<String, Handle<UINode<M, C>>>,
+}
+
+impl<M: MessageData, C: Control<M, C>> BuiltLayout<M, C> {
+    /// Returns handle of a widget with given name or `Handle::NONE` if there is no such widget.
+    pub fn find<S: AsRef<str>>(&self, name: S) -> Handle<UINode<M, C>> {
+        self.names
+            .get(name.as_ref())
+            .cloned()
+            .unwrap_or(Handle::NONE)
+    }
+}
+
+impl FromStr for UiLayout {
+    type Err = UiLayoutError;
+
+    /// Parses layout from a string.
+    fn from_str(source: &str) -> Result<Self, Self::Err> {
+        let raw = Parser {
+            source,
+            position: 0,
+        }
+        .document()?;
+        if raw.tag == STYLE_TAG {
+            return Err(UiLayoutError::Syntax {
+                line: raw.line,
+                message: "Root element must be a widget".to_owned(),
+            });
+        }
+        let mut styles = Styles::new();
+        collect_styles(&raw, &mut styles)?;
+        Ok(Self {
+            root: LayoutElement::from_raw(&raw, &styles)?,
+        })
+    }
+}
+
+impl UiLayout {
+    /// Loads layout from a file.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, UiLayoutError> {
+        std::fs::read_to_string(path)?.parse()
+    }
+
+    /// Builds widgets of the layout. To apply changes of layout file, remove root of previous
+    /// build from user interface, load the layout again and build it. Images are built without
+    /// textures, use [build_with_resources](#method.build_with_resources) to load them.
+    pub fn build<M: MessageData, C: Control<M, C>>(
+        &self,
+        ctx: &mut BuildContext<M, C>,
+    ) -> BuiltLayout<M, C> {
+        let mut names = HashMap::new();
+        let root = self.root.build(ctx, &mut names, None);
+        BuiltLayout { root, names }
+    }
+
+    /// Builds widgets of the layout and loads textures of images using given resource manager.
+    pub fn build_with_resources<M: MessageData, C: Control<M, C>>(
+        &self,
+        ctx: &mut BuildContext<M, C>,
+        resource_manager: &mut ResourceManager,
+    ) -> BuiltLayout<M, C> {
+        let mut names = HashMap::new();
+        let root = self.root.build(ctx, &mut names, Some(resource_manager));
+        BuiltLayout { root, names }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        core::color::Color,
+        gui::{Orientation, Thickness, VerticalAlignment},
+        resource::ui_layout::{Dimension, ElementKind, UiLayout, UiLayoutError},
+    };
+    use std::path::PathBuf;
+
+    #[test]
+    fn ui_layout_test() {
+        let layout: UiLayout = r#"<?xml version="1.0"?>
+            <!-- Options -->
+            <Grid rows="30, stretch" columns="auto" margin="1,2,3,4">
+                <Text name="title" text="A &amp; B"/>
+                <StackPanel row="1" orientation="horizontal"></StackPanel>
+            </Grid>"#
+            .parse()
+            .unwrap();
+        let root = &layout.root;
+        assert_eq!(
+            root.kind,
+            ElementKind::Grid {
+                rows: vec![Dimension::Strict(30.0), Dimension::Stretch],
+                columns: vec![Dimension::Auto],
+            }
+        );
+        assert_eq!(
+            root.properties.margin,
+            Some(Thickness {
+                left: 1.0,
+                top: 2.0,
+                right: 3.0,
+                bottom: 4.0
+            })
+        );
+        assert_eq!(
+            root.children[0].kind,
+            ElementKind::Text {
+                text: "A & B".to_owned()
+            }
+        );
+        assert_eq!(root.children[0].properties.name.as_deref(), Some("title"));
+        assert_eq!(root.children[1].properties.row, 1);
+        assert_eq!(
+            root.children[1].kind,
+            ElementKind::StackPanel {
+                orientation: Orientation::Horizontal
+            }
+        );
+
+        match "<Grid>\n<Text text=\"a\">\n</Grid>".parse::<UiLayout>() {
+            Err(UiLayoutError::Syntax { line, .. }) => assert_eq!(line, 3),
+            _ => panic!("mismatched tag must be an error"),
+        }
+        match "<Text rows=\"1\"/>".parse::<UiLayout>() {
+            Err(UiLayoutError::InvalidAttribute { attribute, .. }) => assert_eq!(attribute, "rows"),
+            _ => panic!("attribute of other element must be an error"),
+        }
+        assert!(matches!(
+            "<Slider/>".parse::<UiLayout>(),
+            Err(UiLayoutError::UnknownElement { .. })
+        ));
+    }
+
+    #[test]
+    fn ui_layout_controls_test() {
+        let layout: UiLayout = r#"
+            <Window title="Options" can_close="false">
+                <ScrollViewer>
+                    <StackPanel>
+                        <CheckBox name="vsync" checked="true"/>
+                        <TextBox text="Player"/>
+                        <Image texture="data/ui/logo.png"/>
+                        <DropdownList selected="1">
+                            <Text text="Low"/>
+                            <Text text="High"/>
+                        </DropdownList>
+                        <ListView/>
+                    </StackPanel>
+                </ScrollViewer>
+            </Window>"#
+            .parse()
+            .unwrap();
+        let root = &layout.root;
+        assert_eq!(
+            root.kind,
+            ElementKind::Window {
+                title: "Options".to_owned(),
+                can_close: false
+            }
+        );
+        assert_eq!(root.children[0].kind, ElementKind::ScrollViewer);
+        let panel = &root.children[0].children[0];
+        assert_eq!(
+            panel.children[0].kind,
+            ElementKind::CheckBox { checked: true }
+        );
+        assert_eq!(
+            panel.children[1].kind,
+            ElementKind::TextBox {
+                text: "Player".to_owned()
+            }
+        );
+        assert_eq!(
+            panel.children[2].kind,
+            ElementKind::Image {
+                texture: Some(PathBuf::from("data/ui/logo.png"))
+            }
+        );
+        assert_eq!(
+            panel.children[3].kind,
+            ElementKind::DropdownList { selected: Some(1) }
+        );
+        assert_eq!(panel.children[3].children.len(), 2);
+        assert_eq!(panel.children[4].kind, ElementKind::ListView);
+
+        assert!(matches!(
+            "<Window><Text/><Text/></Window>".parse::<UiLayout>(),
+            Err(UiLayoutError::Syntax { .. })
+        ));
+    }
+
+    #[test]
+    fn ui_layout_style_test() {
+        let layout: UiLayout = r#"
+            <StackPanel>
+                <Text text="A" style="header" valign="top"/>
+                <Border>
+                    <Style name="header" margin="2" valign="center" foreground="1,2,3"/>
+                </Border>
+            </StackPanel>"#
+            .parse()
+            .unwrap();
+        let text = &layout.root.children[0];
+        assert_eq!(text.properties.margin, Some(Thickness::uniform(2.0)));
+        assert_eq!(text.properties.foreground, Some(Color::opaque(1, 2, 3)));
+        // Own attributes override attributes of a style.
+        assert_eq!(
+            text.properties.vertical_alignment,
+            Some(VerticalAlignment::Top)
+        );
+        // Style is not a child widget.
+        assert!(layout.root.children[1].children.is_empty());
+
+        assert!(matches!(
+            r#"<Text style="missing"/>"#.parse::<UiLayout>(),
+            Err(UiLayoutError::InvalidAttribute { .. })
+        ));
+        assert!(matches!(
+            r#"<Grid><Style margin="1"/></Grid>"#.parse::<UiLayout>(),
+            Err(UiLayoutError::Syntax { .. })
+        ));
+        // Attributes of a style are validated against the element that uses it.
+        assert!(matches!(
+            r#"<Grid style="s"><Style name="s" text="a"/></Grid>"#.parse::<UiLayout>(),
+            Err(UiLayoutError::InvalidAttribute { .. })
+        ));
+    }
+}
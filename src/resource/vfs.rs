@@ -0,0 +1,241 @@
+//! Virtual file system allows resources to be loaded from mounted resource packs and
+//! loose directories using the same relative paths.
+//!
+//! Every mount point has priority, sources with higher priority are checked first, so a
+//! patch or a mod can override some files of the game by mounting its own pack or directory
+//! with priority higher than priority of main pack. If file was not found in any mount point,
+//! it is read from the real file system using the path as is, so loose files works without
+//! any setup.
+//!
+//! Files can also be mounted from memory. This is useful on platforms without file system
+//! access, for example when files are downloaded by a host application and passed to the
+//! engine as bytes.
+
+use crate::resource::pack::{normalize_path, PackError, ResourcePack};
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+};
+
+/// Source of files of a mount point.
+#[derive(Debug)]
+pub enum MountSource {
+    /// Loose directory, paths are resolved relative to it.
+    Directory(PathBuf),
+    /// Resource pack.
+    Pack(ResourcePack),
+    /// Files stored in memory.
+    Memory {
+        /// Name of the mount point, it is used instead of path to unmount it.
+        name: PathBuf,
+        /// Files by normalized paths.
+        files: HashMap<String, Vec<u8>>,
+    },
+}
+
+impl MountSource {
+    fn path(&self) -> &Path {
+        match self {
+            MountSource::Directory(path) => path,
+            MountSource::Pack(pack) => pack.path(),
+            MountSource::Memory { name, .. } => name,
+        }
+    }
+}
+
+/// Mounted source with priority.
+#[derive(Debug)]
+pub struct MountPoint {
+    source: MountSource,
+    priority: i32,
+}
+
+impl MountPoint {
+    /// Returns source of the mount point.
+    pub fn source(&self) -> &MountSource {
+        &self.source
+    }
+
+    /// Returns priority of the mount point.
+    pub fn priority(&self) -> i32 {
+        self.priority
+    }
+}
+
+/// See module docs.
+#[derive(Default, Debug)]
+pub struct VirtualFileSystem {
+    // Sorted by priority in descending order.
+    mounts: Vec<MountPoint>,
+}
+
+impl VirtualFileSystem {
+    /// Creates new virtual file system without mount points, every read will go directly
+    /// to the real file system.
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    fn mount(&mut self, source: MountSource, priority: i32) {
+        // Mount points with same priority are checked in order of mounting, latest first.
+        let index = self
+            .mounts
+            .iter()
+            .position(|m| m.priority <= priority)
+            .unwrap_or_else(|| self.mounts.len());
+        self.mounts.insert(index, MountPoint { source, priority });
+    }
+
+    /// Mounts loose directory with given priority.
+    pub fn mount_directory<P: AsRef<Path>>(&mut self, path: P, priority: i32) {
+        self.mount(MountSource::Directory(path.as_ref().to_owned()), priority)
+    }
+
+    /// Mounts resource pack with given priority.
+    pub fn mount_pack(&mut self, pack: ResourcePack, priority: i32) {
+        self.mount(MountSource::Pack(pack), priority)
+    }
+
+    /// Opens resource pack at given path and mounts it with given priority.
+    pub fn mount_pack_file<P: AsRef<Path>>(
+        &mut self,
+        path: P,
+        priority: i32,
+    ) -> Result<(), PackError> {
+        self.mount_pack(ResourcePack::open(path)?, priority);
+        Ok(())
+    }
+
+    /// Mounts empty in-memory source with given name and priority, files are added to it by
+    /// [insert_memory_file](#method.insert_memory_file).
+    pub fn mount_memory<P: AsRef<Path>>(&mut self, name: P, priority: i32) {
+        self.mount(
+            MountSource::Memory {
+                name: name.as_ref().to_owned(),
+                files: Default::default(),
+            },
+            priority,
+        )
+    }
+
+    /// Adds file to in-memory mount point with given name, existing file at same path is
+    /// replaced. Returns false if there is no such mount point.
+    pub fn insert_memory_file<N: AsRef<Path>, P: AsRef<Path>>(
+        &mut self,
+        name: N,
+        path: P,
+        data: Vec<u8>,
+    ) -> bool {
+        for mount in self.mounts.iter_mut() {
+            if let MountSource::Memory {
+                name: mount_name,
+                files,
+            } = &mut mount.source
+            {
+                if mount_name == name.as_ref() {
+                    files.insert(normalize_path(path), data);
+                    return true;
+                }
+            }
+        }
+        false
+    }
+
+    /// Removes every mount point with given path (path of directory or path of pack).
+    /// Returns true if something was unmounted.
+    pub fn unmount<P: AsRef<Path>>(&mut self, path: P) -> bool {
+        let count = self.mounts.len();
+        self.mounts.retain(|m| m.source.path() != path.as_ref());
+        count != self.mounts.len()
+    }
+
+    /// Returns mount points sorted by priority in descending order.
+    pub fn mount_points(&self) -> &[MountPoint] {
+        &self.mounts
+    }
+
+    /// Reads whole file at given path from first mount point that contains it.
+    pub fn read<P: AsRef<Path>>(&self, path: P) -> std::io::Result<Vec<u8>> {
+        let path = path.as_ref();
+        for mount in self.mounts.iter() {
+            match &mount.source {
+                MountSource::Directory(dir) => {
+                    let full_path = dir.join(path);
+                    if full_path.is_file() {
+                        return std::fs::read(full_path);
+                    }
+                }
+                MountSource::Pack(pack) => {
+                    if let Some(data) = pack.read(path)? {
+                        return Ok(data);
+                    }
+                }
+                MountSource::Memory { files, .. } => {
+                    if let Some(data) = files.get(&normalize_path(path)) {
+                        return Ok(data.clone());
+                    }
+                }
+            }
+        }
+        std::fs::read(path)
+    }
+
+    /// Returns true if file at given path exists in any mount point or in the real file system.
+    pub fn exists<P: AsRef<Path>>(&self, path: P) -> bool {
+        let path = path.as_ref();
+        self.mounts.iter().any(|m| match &m.source {
+            MountSource::Directory(dir) => dir.join(path).is_file(),
+            MountSource::Pack(pack) => pack.contains(path),
+            MountSource::Memory { files, .. } => files.contains_key(&normalize_path(path)),
+        }) || path.is_file()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::resource::{
+        pack::{PackBuilder, ResourcePack},
+        vfs::VirtualFileSystem,
+    };
+
+    #[test]
+    fn vfs_priority_test() {
+        let dir = std::env::temp_dir().join("rg3d_vfs_priority_test");
+        let _ = std::fs::create_dir_all(dir.join("data"));
+        std::fs::write(dir.join("data/a.txt"), b"loose").unwrap();
+
+        let pack_path = dir.join("base.pak");
+        PackBuilder::new()
+            .add_file("data/a.txt", b"pack".to_vec())
+            .add_file("data/b.txt", b"pack".to_vec())
+            .build(&pack_path)
+            .unwrap();
+
+        let mut vfs = VirtualFileSystem::new();
+        vfs.mount_pack(ResourcePack::open(&pack_path).unwrap(), 0);
+        vfs.mount_directory(&dir, 10);
+
+        assert_eq!(vfs.read("data/a.txt").unwrap(), b"loose");
+        assert_eq!(vfs.read("data/b.txt").unwrap(), b"pack");
+        assert!(!vfs.exists("data/c.txt"));
+
+        assert!(vfs.unmount(&dir));
+        assert_eq!(vfs.read("data/a.txt").unwrap(), b"pack");
+
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn vfs_memory_test() {
+        let mut vfs = VirtualFileSystem::new();
+        assert!(!vfs.insert_memory_file("web", "data/a.txt", b"memory".to_vec()));
+        vfs.mount_memory("web", 0);
+        assert!(vfs.insert_memory_file("web", "./data\\a.txt", b"memory".to_vec()));
+
+        assert!(vfs.exists("data/a.txt"));
+        assert_eq!(vfs.read("data/a.txt").unwrap(), b"memory");
+
+        assert!(vfs.unmount("web"));
+        assert!(!vfs.exists("data/a.txt"));
+    }
+}
@@ -32,7 +32,7 @@ use crate::{
         visitor::{Visit, VisitResult, Visitor},
     },
     scene::node::Node,
-    utils::log::Log,
+    utils::{jobs::JobSystem, log::Log},
 };
 use std::{
     collections::HashMap,
@@ -424,7 +424,12 @@ impl Graph {
     pub fn update_nodes(&mut self, frame_size: Vec2, dt: f32) {
         self.update_hierachical_data();
 
-        for node in self.pool.iter_mut() {
+        // Nodes do not depend on each other here, so they're updated in parallel on frame job
+        // system (it is never blocked by loading of resources, and it can be forced to run
+        // serially, see JobSystem::set_serial). Everything that changes structure of the
+        // graph is done below on current thread.
+        let mut nodes = self.pool.iter_mut().collect::<Vec<_>>();
+        JobSystem::global().parallel_for(&mut nodes, |node: &mut &mut Node| {
             if let Some(lifetime) = node.lifetime() {
                 node.set_lifetime(lifetime - dt);
             }
@@ -434,7 +439,7 @@ impl Graph {
                 Node::ParticleSystem(particle_system) => particle_system.update(dt),
                 _ => (),
             }
-        }
+        });
 
         for i in 0..self.pool.get_capacity() {
             let remove = if let Some(node) = self.pool.at(i) {
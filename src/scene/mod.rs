@@ -22,8 +22,9 @@ use crate::{
         visitor::{Visit, VisitError, VisitResult, Visitor},
     },
     engine::resource_manager::ResourceManager,
+    frame_scope,
     physics::{rigid_body::RigidBody, Physics},
-    resource::texture::Texture,
+    resource::{model::Model, texture::Texture},
     scene::{graph::Graph, node::Node},
     utils::{lightmap::Lightmap, log::Log},
 };
@@ -251,6 +252,49 @@ impl Scene {
         Log::writeln("Resolve succeeded!".to_owned());
     }
 
+    /// Collects resources which are directly used by the scene. Textures of models are not
+    /// included, use [Model::dependencies] to get them. Every resource is listed only once.
+    pub fn dependencies(&self) -> SceneDependencies {
+        let mut dependencies = SceneDependencies::default();
+
+        for node in self.graph.linear_iter() {
+            if let Some(model) = node.resource() {
+                dependencies.add_model(model);
+            }
+            match node {
+                Node::Mesh(mesh) => {
+                    for surface in mesh.surfaces() {
+                        dependencies.add_texture(surface.diffuse_texture());
+                        dependencies.add_texture(surface.normal_texture());
+                        dependencies.add_texture(surface.lightmap_texture());
+                        dependencies.add_texture(surface.specular_texture());
+                    }
+                }
+                Node::Sprite(sprite) => dependencies.add_texture(sprite.texture()),
+                Node::ParticleSystem(particle_system) => {
+                    dependencies.add_texture(particle_system.texture())
+                }
+                _ => (),
+            }
+        }
+
+        for animation in self.animations.iter() {
+            if let Some(model) = animation.get_resource() {
+                dependencies.add_model(model);
+            }
+        }
+
+        if let Some(lightmap) = self.lightmap.as_ref() {
+            for entry in lightmap.map.values().flatten() {
+                dependencies.add_texture(entry.texture.clone());
+            }
+        }
+
+        dependencies.add_texture(self.render_target.clone());
+
+        dependencies
+    }
+
     /// Tries to set new lightmap to scene.
     pub fn set_lightmap(&mut self, lightmap: Lightmap) -> Result<Option<Lightmap>, &'static str> {
         // Assign textures to surfaces.
@@ -273,10 +317,23 @@ impl Scene {
 
     /// Performs single update tick with given delta time from last frame. Internally
     /// it updates physics, animations, and each graph node. In most cases there is
-    /// no need to call it directly, engine automatically updates all available scenes.
+    /// no need to call it directly, engine automatically updates all available scenes. Physics
+    /// and animations are not updated if `dt` is zero.
     pub fn update(&mut self, frame_size: Vec2, dt: f32) {
-        self.update_physics(dt);
-        self.animations.update_animations(dt);
+        frame_scope!("Scene");
+        // Zero time step means that game is paused, there is nothing to simulate, but nodes
+        // still must be updated to render the scene correctly.
+        if dt > 0.0 {
+            {
+                frame_scope!("Physics");
+                self.update_physics(dt);
+            }
+            {
+                frame_scope!("Animation");
+                self.animations.update_animations(dt);
+            }
+        }
+        frame_scope!("Graph");
         self.graph.update_nodes(frame_size, dt);
     }
 
@@ -329,6 +386,31 @@ impl Visit for Scene {
     }
 }
 
+/// Resources which are used by a scene, see [Scene::dependencies].
+#[derive(Default)]
+pub struct SceneDependencies {
+    /// Models which have instances (or instantiated animations) in the scene.
+    pub models: Vec<Arc<Mutex<Model>>>,
+    /// Textures which are used by nodes, lightmaps and render target of the scene.
+    pub textures: Vec<Arc<Mutex<Texture>>>,
+}
+
+impl SceneDependencies {
+    fn add_model(&mut self, model: Arc<Mutex<Model>>) {
+        if !self.models.iter().any(|m| Arc::ptr_eq(m, &model)) {
+            self.models.push(model);
+        }
+    }
+
+    fn add_texture(&mut self, texture: Option<Arc<Mutex<Texture>>>) {
+        if let Some(texture) = texture {
+            if !self.textures.iter().any(|t| Arc::ptr_eq(t, &texture)) {
+                self.textures.push(texture);
+            }
+        }
+    }
+}
+
 /// Container for scenes in the engine. It just a simple wrapper around Pool.
 pub struct SceneContainer {
     pool: Pool<Scene>,
@@ -363,6 +445,12 @@ impl SceneContainer {
         self.pool.clear()
     }
 
+    /// Checks if given handle points to a scene in container.
+    #[inline]
+    pub fn is_valid_handle(&self, handle: Handle<Scene>) -> bool {
+        self.pool.is_valid_handle(handle)
+    }
+
     /// Removes given scene from container.
     #[inline]
     pub fn remove(&mut self, handle: Handle<Scene>) {
@@ -12,6 +12,7 @@ use crate::scene::node::Node;
 use crate::{
     core::{
         color::Color,
+        math::Rect,
         visitor::{Visit, VisitResult, Visitor},
     },
     resource::texture::Texture,
@@ -30,6 +31,7 @@ pub struct Sprite {
     color: Color,
     size: f32,
     rotation: f32,
+    uv_rect: Rect<f32>,
 }
 
 impl Deref for Sprite {
@@ -94,6 +96,28 @@ impl Sprite {
     pub fn texture(&self) -> Option<Arc<Mutex<Texture>>> {
         self.texture.clone()
     }
+
+    /// Sets rectangle of texture (in normalized coordinates) which will be used to draw
+    /// sprite. Useful when sprite uses a region of texture atlas.
+    pub fn set_uv_rect(&mut self, uv_rect: Rect<f32>) {
+        self.uv_rect = uv_rect;
+    }
+
+    /// Returns current rectangle of texture which is used to draw sprite.
+    pub fn uv_rect(&self) -> Rect<f32> {
+        self.uv_rect
+    }
+
+    fn visit_uv_rect(&mut self, visitor: &mut Visitor) -> VisitResult {
+        visitor.enter_region("UvRect")?;
+
+        self.uv_rect.x.visit("X", visitor)?;
+        self.uv_rect.y.visit("Y", visitor)?;
+        self.uv_rect.w.visit("W", visitor)?;
+        self.uv_rect.h.visit("H", visitor)?;
+
+        visitor.leave_region()
+    }
 }
 
 impl Visit for Sprite {
@@ -105,6 +129,8 @@ impl Visit for Sprite {
         self.size.visit("Size", visitor)?;
         self.rotation.visit("Rotation", visitor)?;
         self.base.visit("Base", visitor)?;
+        // Backward compatibility, old versions does not have uv rect.
+        let _ = self.visit_uv_rect(visitor);
 
         visitor.leave_region()
     }
@@ -118,10 +144,12 @@ pub struct SpriteBuilder {
     color: Color,
     size: f32,
     rotation: f32,
+    uv_rect: Rect<f32>,
 }
 
 impl SpriteBuilder {
-    /// Creates new builder with default state (white opaque color, 0.2 size, zero rotation).
+    /// Creates new builder with default state (white opaque color, 0.2 size, zero rotation,
+    /// whole texture).
     pub fn new(base_builder: BaseBuilder) -> Self {
         Self {
             base_builder,
@@ -129,6 +157,7 @@ impl SpriteBuilder {
             color: Color::WHITE,
             size: 0.2,
             rotation: 0.0,
+            uv_rect: Rect::new(0.0, 0.0, 1.0, 1.0),
         }
     }
 
@@ -162,6 +191,12 @@ impl SpriteBuilder {
         self
     }
 
+    /// Sets desired rectangle of texture.
+    pub fn with_uv_rect(mut self, uv_rect: Rect<f32>) -> Self {
+        self.uv_rect = uv_rect;
+        self
+    }
+
     /// Creates new sprite instance.
     pub fn build(self) -> Sprite {
         Sprite {
@@ -170,6 +205,7 @@ impl SpriteBuilder {
             color: self.color,
             size: self.size,
             rotation: self.rotation,
+            uv_rect: self.uv_rect,
         }
     }
 
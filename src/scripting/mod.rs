@@ -0,0 +1,617 @@
+//! Lua scripting of scene nodes, available with `lua` feature.
+//!
+//! Script is attached to a node of a scene. Script is a Lua chunk that returns a table with
+//! optional callbacks, every attachment gets its own copy of the table, so the table can be
+//! used to store state of the script:
+//!
+//! ```lua
+//! local Door = {}
+//!
+//! function Door:init(node, scene)
+//!     self.open = false
+//! end
+//!
+//! function Door:update(node, scene, dt)
+//!     if self.open then
+//!         scene:move(node, 0, dt, 0)
+//!     end
+//! end
+//!
+//! function Door:on_event(node, scene, event)
+//!     if event.type == "key" and event.key == "E" and event.pressed then
+//!         self.open = true
+//!         log(scene:name(node) .. " is opening")
+//!     end
+//! end
+//!
+//! return Door
+//! ```
+//!
+//! `init` is called once before first update, `update` is called every frame and `on_event`
+//! is called for every event that was pushed to the script engine since last update. Events
+//! are tables with `type` field:
+//!
+//! - `key` - `key` (name of [VirtualKeyCode](crate::event::VirtualKeyCode) variant, for
+//! example "W" or "Space") and `pressed`.
+//! - `mouse_button` - `button` ("Left", "Right", "Middle") and `pressed`.
+//! - `mouse_move` - `x` and `y`.
+//! - `button_click` - `widget` - index of a handle of clicked UI button.
+//! - any other type - custom events sent by the game, with `value` field.
+//!
+//! `scene` is an object with methods to query and modify nodes and to load resources, see
+//! [SceneProxy] for the list of methods. Errors of scripts are written to the log, one
+//! failing script does not stop others.
+//!
+//! Script engine is not a part of [Engine](crate::engine::Engine), game creates one per
+//! scene and updates it together with the engine:
+//!
+//! ```no_run
+//! # use rg3d::{
+//! #     core::pool::Handle, engine::resource_manager::ResourceManager, scene::Scene,
+//! #     scripting::{ScriptEngine, ScriptEvent},
+//! # };
+//! # use std::sync::{Arc, Mutex};
+//! # fn f(scene: &mut Scene, resource_manager: Arc<Mutex<ResourceManager>>) {
+//! let mut scripts = ScriptEngine::new().unwrap();
+//! let door = scene.graph.find_by_name_from_root("Door");
+//! scripts
+//!     .attach_file(door, &resource_manager.lock().unwrap(), "data/scripts/door.lua")
+//!     .unwrap();
+//!
+//! // In game loop:
+//! scripts.push_event(ScriptEvent::Custom {
+//!     name: "alarm".to_owned(),
+//!     value: "on".to_owned(),
+//! });
+//! scripts.update(scene, &resource_manager, 1.0 / 60.0);
+//! # }
+//! ```
+//!
+//! Games can add their own bindings through [ScriptEngine::lua].
+
+use crate::{
+    core::{
+        math::{
+            quat::{Quat, RotationOrder},
+            vec3::Vec3,
+        },
+        pool::Handle,
+    },
+    engine::resource_manager::ResourceManager,
+    event::{ElementState, WindowEvent},
+    gui::{
+        message::{ButtonMessage, MessageData, UiMessage, UiMessageData},
+        Control,
+    },
+    resource::texture::TextureKind,
+    scene::{node::Node, Scene},
+    utils::log::Log,
+};
+use mlua::{AnyUserData, Function, Lua, MetaMethod, RegistryKey, Table, UserData, UserDataMethods};
+use std::{
+    fmt::Formatter,
+    path::Path,
+    sync::{Arc, Mutex},
+};
+
+/// All possible errors that can occur when attaching a script.
+#[derive(Debug)]
+pub enum ScriptError {
+    /// An input/output error has occurred.
+    Io(std::io::Error),
+    /// Script has syntax error or failed to run.
+    Lua(mlua::Error),
+}
+
+impl std::fmt::Display for ScriptError {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), std::fmt::Error> {
+        match self {
+            ScriptError::Io(io) => write!(f, "Io error: {}", io),
+            ScriptError::Lua(lua) => write!(f, "Lua error: {}", lua),
+        }
+    }
+}
+
+impl From<std::io::Error> for ScriptError {
+    fn from(err: std::io::Error) -> Self {
+        ScriptError::Io(err)
+    }
+}
+
+impl From<mlua::Error> for ScriptError {
+    fn from(err: mlua::Error) -> Self {
+        ScriptError::Lua(err)
+    }
+}
+
+/// Handle of a node passed to scripts.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct LuaNode(pub Handle<Node>);
+
+impl UserData for LuaNode {
+    fn add_methods<'lua, M: UserDataMethods<'lua, Self>>(methods: &mut M) {
+        methods.add_method("is_some", |_, this, ()| Ok(this.0.is_some()));
+        methods.add_meta_method(MetaMethod::Eq, |_, this, other: LuaNode| Ok(*this == other));
+    }
+}
+
+/// Event that is passed to `on_event` callback of scripts.
+#[derive(Clone, Debug, PartialEq)]
+pub enum ScriptEvent {
+    /// Keyboard key was pressed or released.
+    Key {
+        /// Name of a key.
+        key: String,
+        /// Pressed or released.
+        pressed: bool,
+    },
+    /// Mouse button was pressed or released.
+    MouseButton {
+        /// Name of a button.
+        button: String,
+        /// Pressed or released.
+        pressed: bool,
+    },
+    /// Mouse cursor was moved.
+    MouseMove {
+        /// Position of the cursor in window.
+        x: f32,
+        /// Position of the cursor in window.
+        y: f32,
+    },
+    /// UI button was clicked.
+    ButtonClick {
+        /// Index of a handle of the button.
+        widget: u32,
+    },
+    /// Game-specific event.
+    Custom {
+        /// Name of the event, it is `type` field of event in scripts.
+        name: String,
+        /// Payload of the event.
+        value: String,
+    },
+}
+
+impl ScriptEvent {
+    /// Converts window event to script event, if it has script counterpart.
+    pub fn from_window_event(event: &WindowEvent) -> Option<Self> {
+        match event {
+            WindowEvent::KeyboardInput { input, .. } => {
+                input.virtual_keycode.map(|key| ScriptEvent::Key {
+                    key: format!("{:?}", key),
+                    pressed: input.state == ElementState::Pressed,
+                })
+            }
+            WindowEvent::MouseInput { button, state, .. } => Some(ScriptEvent::MouseButton {
+                button: format!("{:?}", button),
+                pressed: *state == ElementState::Pressed,
+            }),
+            WindowEvent::CursorMoved { position, .. } => Some(ScriptEvent::MouseMove {
+                x: position.x as f32,
+                y: position.y as f32,
+            }),
+            _ => None,
+        }
+    }
+
+    /// Converts UI message to script event, if it has script counterpart.
+    pub fn from_ui_message<M: MessageData, C: Control<M, C>>(
+        message: &UiMessage<M, C>,
+    ) -> Option<Self> {
+        match message.data() {
+            UiMessageData::Button(ButtonMessage::Click) => Some(ScriptEvent::ButtonClick {
+                widget: message.destination().index(),
+            }),
+            _ => None,
+        }
+    }
+
+    fn to_table<'lua>(&self, lua: &'lua Lua) -> mlua::Result<Table<'lua>> {
+        let table = lua.create_table()?;
+        match self {
+            ScriptEvent::Key { key, pressed } => {
+                table.set("type", "key")?;
+                table.set("key", key.as_str())?;
+                table.set("pressed", *pressed)?;
+            }
+            ScriptEvent::MouseButton { button, pressed } => {
+                table.set("type", "mouse_button")?;
+                table.set("button", button.as_str())?;
+                table.set("pressed", *pressed)?;
+            }
+            ScriptEvent::MouseMove { x, y } => {
+                table.set("type", "mouse_move")?;
+                table.set("x", *x)?;
+                table.set("y", *y)?;
+            }
+            ScriptEvent::ButtonClick { widget } => {
+                table.set("type", "button_click")?;
+                table.set("widget", *widget)?;
+            }
+            ScriptEvent::Custom { name, value } => {
+                table.set("type", name.as_str())?;
+                table.set("value", value.as_str())?;
+            }
+        }
+        Ok(table)
+    }
+}
+
+/// Scene as it is seen by scripts. Methods (node is a handle passed to callbacks or returned
+/// by `find`/`instantiate`, vectors are passed and returned as separate numbers):
+///
+/// - `find(name) -> node` - finds node by name, returned handle is none if there is no such
+/// node, check it with `node:is_some()`.
+/// - `name(node) -> string`
+/// - `position(node) -> x, y, z` and `set_position(node, x, y, z)` - local position.
+/// - `move(node, x, y, z)` - moves node by given offset.
+/// - `global_position(node) -> x, y, z`
+/// - `look_vector(node)`, `side_vector(node)`, `up_vector(node)` `-> x, y, z`
+/// - `set_rotation(node, axis_x, axis_y, axis_z, angle)` - sets rotation around axis.
+/// - `set_rotation_euler(node, x, y, z)` - sets rotation from Euler angles in radians.
+/// - `scale(node) -> x, y, z` and `set_scale(node, x, y, z)`
+/// - `visible(node) -> bool` and `set_visible(node, bool)`
+/// - `set_lifetime(node, seconds)` - node will be removed when its lifetime ends.
+/// - `remove(node)` - removes node with its descendants.
+/// - `instantiate(path) -> node` - loads model and instantiates its geometry.
+/// - `set_texture(node, path)` - sets diffuse texture of every surface of a mesh.
+pub struct SceneProxy<'a> {
+    scene: &'a mut Scene,
+    resource_manager: &'a Mutex<ResourceManager>,
+}
+
+impl<'a> SceneProxy<'a> {
+    fn node(&self, node: LuaNode) -> mlua::Result<&Node> {
+        if self.scene.graph.is_valid_handle(node.0) {
+            Ok(&self.scene.graph[node.0])
+        } else {
+            Err(mlua::Error::RuntimeError(format!(
+                "Invalid node handle {:?}",
+                node.0
+            )))
+        }
+    }
+
+    fn node_mut(&mut self, node: LuaNode) -> mlua::Result<&mut Node> {
+        self.node(node)?;
+        Ok(&mut self.scene.graph[node.0])
+    }
+}
+
+fn unpack(v: Vec3) -> (f32, f32, f32) {
+    (v.x, v.y, v.z)
+}
+
+impl<'a> UserData for SceneProxy<'a> {
+    fn add_methods<'lua, M: UserDataMethods<'lua, Self>>(methods: &mut M) {
+        methods.add_method("find", |_, this, name: String| {
+            Ok(LuaNode(this.scene.graph.find_by_name_from_root(&name)))
+        });
+        methods.add_method("name", |_, this, node: LuaNode| {
+            Ok(this.node(node)?.name().to_owned())
+        });
+        methods.add_method("position", |_, this, node: LuaNode| {
+            Ok(unpack(this.node(node)?.local_transform().position()))
+        });
+        methods.add_method_mut(
+            "set_position",
+            |_, this, (node, x, y, z): (LuaNode, f32, f32, f32)| {
+                this.node_mut(node)?
+                    .local_transform_mut()
+                    .set_position(Vec3::new(x, y, z));
+                Ok(())
+            },
+        );
+        methods.add_method_mut(
+            "move",
+            |_, this, (node, x, y, z): (LuaNode, f32, f32, f32)| {
+                this.node_mut(node)?
+                    .local_transform_mut()
+                    .offset(Vec3::new(x, y, z));
+                Ok(())
+            },
+        );
+        methods.add_method("global_position", |_, this, node: LuaNode| {
+            Ok(unpack(this.node(node)?.global_position()))
+        });
+        methods.add_method("look_vector", |_, this, node: LuaNode| {
+            Ok(unpack(this.node(node)?.look_vector()))
+        });
+        methods.add_method("side_vector", |_, this, node: LuaNode| {
+            Ok(unpack(this.node(node)?.side_vector()))
+        });
+        methods.add_method("up_vector", |_, this, node: LuaNode| {
+            Ok(unpack(this.node(node)?.up_vector()))
+        });
+        methods.add_method_mut(
+            "set_rotation",
+            |_, this, (node, x, y, z, angle): (LuaNode, f32, f32, f32, f32)| {
+                this.node_mut(node)?
+                    .local_transform_mut()
+                    .set_rotation(Quat::from_axis_angle(Vec3::new(x, y, z), angle));
+                Ok(())
+            },
+        );
+        methods.add_method_mut(
+            "set_rotation_euler",
+            |_, this, (node, x, y, z): (LuaNode, f32, f32, f32)| {
+                this.node_mut(node)?
+                    .local_transform_mut()
+                    .set_rotation(Quat::from_euler(Vec3::new(x, y, z), RotationOrder::XYZ));
+                Ok(())
+            },
+        );
+        methods.add_method("scale", |_, this, node: LuaNode| {
+            Ok(unpack(this.node(node)?.local_transform().scale()))
+        });
+        methods.add_method_mut(
+            "set_scale",
+            |_, this, (node, x, y, z): (LuaNode, f32, f32, f32)| {
+                this.node_mut(node)?
+                    .local_transform_mut()
+                    .set_scale(Vec3::new(x, y, z));
+                Ok(())
+            },
+        );
+        methods.add_method("visible", |_, this, node: LuaNode| {
+            Ok(this.node(node)?.visibility())
+        });
+        methods.add_method_mut(
+            "set_visible",
+            |_, this, (node, visible): (LuaNode, bool)| {
+                this.node_mut(node)?.set_visibility(visible);
+                Ok(())
+            },
+        );
+        methods.add_method_mut(
+            "set_lifetime",
+            |_, this, (node, lifetime): (LuaNode, f32)| {
+                this.node_mut(node)?.set_lifetime(lifetime);
+                Ok(())
+            },
+        );
+        methods.add_method_mut("remove", |_, this, node: LuaNode| {
+            this.node(node)?;
+            this.scene.graph.remove_node(node.0);
+            Ok(())
+        });
+        methods.add_method_mut("instantiate", |_, this, path: String| {
+            let model = this
+                .resource_manager
+                .lock()
+                .unwrap()
+                .request_model(&path)
+                .ok_or_else(|| {
+                    mlua::Error::RuntimeError(format!("Unable to load model {}", path))
+                })?;
+            let root = model.lock().unwrap().instantiate_geometry(this.scene);
+            Ok(LuaNode(root))
+        });
+        methods.add_method_mut("set_texture", |_, this, (node, path): (LuaNode, String)| {
+            let texture = this
+                .resource_manager
+                .lock()
+                .unwrap()
+                .request_texture_async(&path, TextureKind::RGBA8);
+            if let Node::Mesh(mesh) = this.node_mut(node)? {
+                for surface in mesh.surfaces_mut() {
+                    surface.set_diffuse_texture(texture.clone());
+                }
+            }
+            Ok(())
+        });
+    }
+}
+
+struct NodeScript {
+    node: Handle<Node>,
+    name: String,
+    instance: RegistryKey,
+    initialized: bool,
+}
+
+impl NodeScript {
+    fn run<'lua>(
+        &mut self,
+        lua: &'lua Lua,
+        scene: &AnyUserData<'lua>,
+        events: &[Table<'lua>],
+        dt: f32,
+    ) -> mlua::Result<()> {
+        let instance: Table = lua.registry_value(&self.instance)?;
+        let node = LuaNode(self.node);
+
+        if !self.initialized {
+            self.initialized = true;
+            if let Some(init) = instance.get::<_, Option<Function>>("init")? {
+                init.call::<_, ()>((instance.clone(), node, scene.clone()))?;
+            }
+        }
+
+        if let Some(on_event) = instance.get::<_, Option<Function>>("on_event")? {
+            for event in events {
+                on_event.call::<_, ()>((instance.clone(), node, scene.clone(), event.clone()))?;
+            }
+        }
+
+        if let Some(update) = instance.get::<_, Option<Function>>("update")? {
+            update.call::<_, ()>((instance, node, scene.clone(), dt))?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Lua runtime with scripts attached to nodes of a scene. See module docs.
+pub struct ScriptEngine {
+    lua: Lua,
+    scripts: Vec<NodeScript>,
+    events: Vec<ScriptEvent>,
+}
+
+impl ScriptEngine {
+    /// Creates new Lua runtime with engine bindings.
+    pub fn new() -> Result<Self, ScriptError> {
+        let lua = Lua::new();
+        let log = lua.create_function(|_, message: String| {
+            Log::writeln(message);
+            Ok(())
+        })?;
+        lua.globals().set("log", log)?;
+        Ok(Self {
+            lua,
+            scripts: Default::default(),
+            events: Default::default(),
+        })
+    }
+
+    /// Returns Lua runtime, it can be used to add game-specific bindings.
+    pub fn lua(&self) -> &Lua {
+        &self.lua
+    }
+
+    /// Attaches script with given source to a node. Name is used in error messages. Node can
+    /// have any number of scripts.
+    pub fn attach(
+        &mut self,
+        node: Handle<Node>,
+        name: &str,
+        source: &str,
+    ) -> Result<(), ScriptError> {
+        let instance: Table = self.lua.load(source).set_name(name)?.eval()?;
+        let instance = self.lua.create_registry_value(instance)?;
+        self.scripts.push(NodeScript {
+            node,
+            name: name.to_owned(),
+            instance,
+            initialized: false,
+        });
+        Ok(())
+    }
+
+    /// Loads script from a file through virtual file system of resource manager and attaches
+    /// it to a node.
+    pub fn attach_file<P: AsRef<Path>>(
+        &mut self,
+        node: Handle<Node>,
+        resource_manager: &ResourceManager,
+        path: P,
+    ) -> Result<(), ScriptError> {
+        let data = resource_manager.vfs().lock().unwrap().read(path.as_ref())?;
+        self.attach(
+            node,
+            &path.as_ref().to_string_lossy(),
+            &String::from_utf8_lossy(&data),
+        )
+    }
+
+    /// Detaches every script from a node.
+    pub fn detach(&mut self, node: Handle<Node>) {
+        self.scripts.retain(|script| script.node != node);
+        self.lua.expire_registry_values();
+    }
+
+    /// Queues event for `on_event` callbacks, events are delivered on next update.
+    pub fn push_event(&mut self, event: ScriptEvent) {
+        self.events.push(event);
+    }
+
+    /// Converts window event and queues it, if it has script counterpart.
+    pub fn push_window_event(&mut self, event: &WindowEvent) {
+        if let Some(event) = ScriptEvent::from_window_event(event) {
+            self.push_event(event);
+        }
+    }
+
+    /// Delivers queued events and updates every script. Scripts of nodes that were removed
+    /// from the scene are detached.
+    pub fn update(
+        &mut self,
+        scene: &mut Scene,
+        resource_manager: &Arc<Mutex<ResourceManager>>,
+        dt: f32,
+    ) {
+        let Self {
+            lua,
+            scripts,
+            events,
+        } = self;
+
+        scripts.retain(|script| scene.graph.is_valid_handle(script.node));
+        lua.expire_registry_values();
+
+        let events = std::mem::take(events);
+        let result = lua.scope(|scope| {
+            let proxy = scope.create_nonstatic_userdata(SceneProxy {
+                scene,
+                resource_manager,
+            })?;
+            let events = events
+                .iter()
+                .map(|event| event.to_table(lua))
+                .collect::<mlua::Result<Vec<_>>>()?;
+            for script in scripts.iter_mut() {
+                if let Err(e) = script.run(lua, &proxy, &events, dt) {
+                    Log::writeln(format!("Script {} failed! Reason: {}", script.name, e));
+                }
+            }
+            Ok(())
+        });
+        if let Err(e) = result {
+            Log::writeln(format!("Unable to update scripts! Reason: {}", e));
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        engine::resource_manager::ResourceManager,
+        scene::{base::BaseBuilder, Scene},
+        scripting::{ScriptEngine, ScriptEvent},
+    };
+    use std::sync::{Arc, Mutex};
+
+    #[test]
+    fn script_test() {
+        let mut scene = Scene::new();
+        let node = scene
+            .graph
+            .add_node(BaseBuilder::new().with_name("Door").build_node());
+        let resource_manager = Arc::new(Mutex::new(ResourceManager::new()));
+
+        let mut scripts = ScriptEngine::new().unwrap();
+        scripts
+            .attach(
+                node,
+                "door",
+                r#"
+                local Door = {}
+                function Door:init(node, scene)
+                    self.speed = 1
+                end
+                function Door:update(node, scene, dt)
+                    scene:move(node, 0, self.speed * dt, 0)
+                end
+                function Door:on_event(node, scene, event)
+                    if event.type == "key" and event.key == "E" and event.pressed then
+                        self.speed = 2
+                    end
+                end
+                return Door
+                "#,
+            )
+            .unwrap();
+
+        scripts.update(&mut scene, &resource_manager, 1.0);
+        scripts.push_event(ScriptEvent::Key {
+            key: "E".to_owned(),
+            pressed: true,
+        });
+        scripts.update(&mut scene, &resource_manager, 1.0);
+        assert_eq!(scene.graph[node].local_transform().position().y, 3.0);
+
+        assert!(scripts.attach(node, "broken", "return {").is_err());
+    }
+}
@@ -0,0 +1,893 @@
+//! Behavior trees for artificial intelligence of agents.
+//!
+//! Behavior tree is a tree of nodes which is traversed (ticked) from the root every update of
+//! an agent. Each node returns [Status] - success, failure or running (not finished yet, it
+//! will be continued on next tick). There are three kinds of nodes:
+//!
+//! - Composites - [BehaviorNode::Sequence] runs children one by one until one of them fails,
+//! [BehaviorNode::Selector] runs children one by one until one of them succeeds,
+//! [BehaviorNode::Parallel] runs all children at once.
+//! - Decorators - modify result of single child: [BehaviorNode::Inverter],
+//! [BehaviorNode::Succeeder], [BehaviorNode::Repeat].
+//! - Leaves - do actual work: [BehaviorNode::Action], [BehaviorNode::Condition],
+//! [BehaviorNode::Wait].
+//!
+//! Actions and conditions are referenced by name, their code is registered in [Behaviors].
+//! This way trees are pure data - they can be saved, loaded and shared by any amount of agents,
+//! while each agent has its own [Blackboard] (memory of the agent) and state of running nodes.
+//! Together with [navmesh](../navmesh/index.html) this is enough to build most of the
+//! typical game AI.
+//!
+//! # Example
+//!
+//! ```
+//! use rg3d::utils::behavior::{
+//!     BehaviorAgent, BehaviorNode, BehaviorTree, BehaviorWorld, Status,
+//! };
+//! # use rg3d::core::pool::Handle;
+//!
+//! struct Game {
+//!     ammo: u32,
+//! }
+//!
+//! let mut tree = BehaviorTree::new("Soldier");
+//! let has_ammo = tree.add_node(BehaviorNode::Condition("HasAmmo".to_owned()));
+//! let shoot = tree.add_node(BehaviorNode::Action("Shoot".to_owned()));
+//! let attack = tree.add_node(BehaviorNode::Sequence(vec![has_ammo, shoot]));
+//! let idle = tree.add_node(BehaviorNode::Wait(1.0));
+//! let root = tree.add_node(BehaviorNode::Selector(vec![attack, idle]));
+//! tree.set_root(root);
+//!
+//! let mut world = BehaviorWorld::new();
+//! world
+//!     .behaviors
+//!     .add_condition("HasAmmo", |game: &Game, _| game.ammo > 0);
+//! world.behaviors.add_action("Shoot", |game: &mut Game, _| {
+//!     game.ammo -= 1;
+//!     Status::Success
+//! });
+//! let tree = world.add_tree(tree);
+//! world.add_agent(BehaviorAgent::new(tree, Handle::NONE));
+//!
+//! let mut game = Game { ammo: 2 };
+//! // Every frame, after engine.update(dt):
+//! world.update(&mut game, 1.0 / 60.0);
+//! assert_eq!(game.ammo, 1);
+//! ```
+
+#![warn(missing_docs)]
+
+use crate::{
+    core::{
+        math::vec3::Vec3,
+        pool::{Handle, Pool},
+        visitor::{Visit, VisitError, VisitResult, Visitor},
+    },
+    scene::node::Node,
+    utils::log::Log,
+};
+use std::{
+    collections::{HashMap, HashSet},
+    path::Path,
+};
+
+/// Result of a tick of a node.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Status {
+    /// Node has finished successfully.
+    Success,
+    /// Node has failed.
+    Failure,
+    /// Node is not finished yet, it will be ticked again on next update.
+    Running,
+}
+
+/// Node of behavior tree. See module docs.
+#[derive(Clone, Debug, PartialEq)]
+pub enum BehaviorNode {
+    /// Ticks children in order until one of them fails or is running. Succeeds if every
+    /// child has succeeded.
+    Sequence(Vec<Handle<BehaviorNode>>),
+    /// Ticks children in order until one of them succeeds or is running. Fails if every
+    /// child has failed.
+    Selector(Vec<Handle<BehaviorNode>>),
+    /// Ticks every child each time. Succeeds when at least `required_successes` children have
+    /// succeeded on the same tick, fails when it is no longer possible.
+    Parallel {
+        /// Children of the node.
+        children: Vec<Handle<BehaviorNode>>,
+        /// Amount of succeeded children required for success.
+        required_successes: u32,
+    },
+    /// Turns success of a child into failure and vice versa.
+    Inverter(Handle<BehaviorNode>),
+    /// Succeeds when child is finished, regardless of its result.
+    Succeeder(Handle<BehaviorNode>),
+    /// Runs child again each time it succeeds, `count` times or forever if `count` is zero.
+    /// Fails as soon as child fails.
+    Repeat {
+        /// Node to repeat.
+        child: Handle<BehaviorNode>,
+        /// Amount of repetitions, zero means forever.
+        count: u32,
+    },
+    /// Calls action with given name, see [Behaviors::add_action].
+    Action(String),
+    /// Calls condition with given name, see [Behaviors::add_condition].
+    Condition(String),
+    /// Is running for given amount of seconds, then succeeds.
+    Wait(f32),
+}
+
+impl Default for BehaviorNode {
+    fn default() -> Self {
+        BehaviorNode::Wait(0.0)
+    }
+}
+
+impl BehaviorNode {
+    fn from_id(id: u32) -> Result<Self, String> {
+        match id {
+            0 => Ok(BehaviorNode::Sequence(Default::default())),
+            1 => Ok(BehaviorNode::Selector(Default::default())),
+            2 => Ok(BehaviorNode::Parallel {
+                children: Default::default(),
+                required_successes: 0,
+            }),
+            3 => Ok(BehaviorNode::Inverter(Default::default())),
+            4 => Ok(BehaviorNode::Succeeder(Default::default())),
+            5 => Ok(BehaviorNode::Repeat {
+                child: Default::default(),
+                count: 0,
+            }),
+            6 => Ok(BehaviorNode::Action(Default::default())),
+            7 => Ok(BehaviorNode::Condition(Default::default())),
+            8 => Ok(BehaviorNode::Wait(0.0)),
+            _ => Err(format!("Invalid behavior node id {}", id)),
+        }
+    }
+
+    fn id(&self) -> u32 {
+        match self {
+            BehaviorNode::Sequence(_) => 0,
+            BehaviorNode::Selector(_) => 1,
+            BehaviorNode::Parallel { .. } => 2,
+            BehaviorNode::Inverter(_) => 3,
+            BehaviorNode::Succeeder(_) => 4,
+            BehaviorNode::Repeat { .. } => 5,
+            BehaviorNode::Action(_) => 6,
+            BehaviorNode::Condition(_) => 7,
+            BehaviorNode::Wait(_) => 8,
+        }
+    }
+
+    /// Returns children of the node.
+    pub fn children(&self) -> &[Handle<BehaviorNode>] {
+        match self {
+            BehaviorNode::Sequence(children)
+            | BehaviorNode::Selector(children)
+            | BehaviorNode::Parallel { children, .. } => children,
+            BehaviorNode::Inverter(child)
+            | BehaviorNode::Succeeder(child)
+            | BehaviorNode::Repeat { child, .. } => std::slice::from_ref(child),
+            BehaviorNode::Action(_) | BehaviorNode::Condition(_) | BehaviorNode::Wait(_) => &[],
+        }
+    }
+}
+
+impl Visit for BehaviorNode {
+    fn visit(&mut self, name: &str, visitor: &mut Visitor) -> VisitResult {
+        visitor.enter_region(name)?;
+
+        let mut id = self.id();
+        id.visit("Id", visitor)?;
+        if visitor.is_reading() {
+            *self = Self::from_id(id)?;
+        }
+
+        match self {
+            BehaviorNode::Sequence(children) | BehaviorNode::Selector(children) => {
+                children.visit("Children", visitor)?
+            }
+            BehaviorNode::Parallel {
+                children,
+                required_successes,
+            } => {
+                children.visit("Children", visitor)?;
+                required_successes.visit("RequiredSuccesses", visitor)?;
+            }
+            BehaviorNode::Inverter(child) | BehaviorNode::Succeeder(child) => {
+                child.visit("Child", visitor)?
+            }
+            BehaviorNode::Repeat { child, count } => {
+                child.visit("Child", visitor)?;
+                count.visit("Count", visitor)?;
+            }
+            BehaviorNode::Action(name) | BehaviorNode::Condition(name) => {
+                name.visit("Name", visitor)?
+            }
+            BehaviorNode::Wait(duration) => duration.visit("Duration", visitor)?,
+        }
+
+        visitor.leave_region()
+    }
+}
+
+/// Behavior tree. Trees are shared between agents, every agent keeps its own state. See
+/// module docs.
+pub struct BehaviorTree {
+    name: String,
+    nodes: Pool<BehaviorNode>,
+    root: Handle<BehaviorNode>,
+}
+
+impl Default for BehaviorTree {
+    fn default() -> Self {
+        Self::new("")
+    }
+}
+
+impl BehaviorTree {
+    /// Creates new empty tree.
+    pub fn new<S: AsRef<str>>(name: S) -> Self {
+        Self {
+            name: name.as_ref().to_owned(),
+            nodes: Pool::new(),
+            root: Handle::NONE,
+        }
+    }
+
+    /// Returns name of the tree.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Adds new node to the tree. Children of the node must be added first.
+    pub fn add_node(&mut self, node: BehaviorNode) -> Handle<BehaviorNode> {
+        self.nodes.spawn(node)
+    }
+
+    /// Returns reference to a node.
+    pub fn node(&self, handle: Handle<BehaviorNode>) -> &BehaviorNode {
+        &self.nodes[handle]
+    }
+
+    /// Sets root node of the tree.
+    pub fn set_root(&mut self, root: Handle<BehaviorNode>) {
+        self.root = root;
+    }
+
+    /// Returns root node of the tree.
+    pub fn root(&self) -> Handle<BehaviorNode> {
+        self.root
+    }
+
+    /// Saves tree to a file.
+    pub fn save<P: AsRef<Path>>(&mut self, path: P) -> VisitResult {
+        let mut visitor = Visitor::new();
+        self.visit("BehaviorTree", &mut visitor)?;
+        visitor.save_binary(path)
+    }
+
+    /// Loads tree from a file.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, VisitError> {
+        let mut visitor = Visitor::load_binary(path)?;
+        let mut tree = BehaviorTree::default();
+        tree.visit("BehaviorTree", &mut visitor)?;
+        Ok(tree)
+    }
+}
+
+impl Visit for BehaviorTree {
+    fn visit(&mut self, name: &str, visitor: &mut Visitor) -> VisitResult {
+        visitor.enter_region(name)?;
+
+        self.name.visit("Name", visitor)?;
+        self.nodes.visit("Nodes", visitor)?;
+        self.root.visit("Root", visitor)?;
+
+        visitor.leave_region()
+    }
+}
+
+/// Value stored in a blackboard.
+#[derive(Clone, Debug, PartialEq)]
+pub enum BlackboardValue {
+    /// Flag.
+    Bool(bool),
+    /// Number.
+    Number(f32),
+    /// Position or direction.
+    Vector(Vec3),
+    /// Scene node, for example current target of an agent.
+    Node(Handle<Node>),
+    /// Text.
+    Text(String),
+}
+
+impl Default for BlackboardValue {
+    fn default() -> Self {
+        BlackboardValue::Bool(false)
+    }
+}
+
+impl BlackboardValue {
+    fn from_id(id: u32) -> Result<Self, String> {
+        match id {
+            0 => Ok(BlackboardValue::Bool(false)),
+            1 => Ok(BlackboardValue::Number(0.0)),
+            2 => Ok(BlackboardValue::Vector(Default::default())),
+            3 => Ok(BlackboardValue::Node(Default::default())),
+            4 => Ok(BlackboardValue::Text(Default::default())),
+            _ => Err(format!("Invalid blackboard value id {}", id)),
+        }
+    }
+
+    fn id(&self) -> u32 {
+        match self {
+            BlackboardValue::Bool(_) => 0,
+            BlackboardValue::Number(_) => 1,
+            BlackboardValue::Vector(_) => 2,
+            BlackboardValue::Node(_) => 3,
+            BlackboardValue::Text(_) => 4,
+        }
+    }
+}
+
+impl Visit for BlackboardValue {
+    fn visit(&mut self, name: &str, visitor: &mut Visitor) -> VisitResult {
+        visitor.enter_region(name)?;
+
+        let mut id = self.id();
+        id.visit("Id", visitor)?;
+        if visitor.is_reading() {
+            *self = Self::from_id(id)?;
+        }
+
+        match self {
+            BlackboardValue::Bool(value) => value.visit("Value", visitor)?,
+            BlackboardValue::Number(value) => value.visit("Value", visitor)?,
+            BlackboardValue::Vector(value) => value.visit("Value", visitor)?,
+            BlackboardValue::Node(value) => value.visit("Value", visitor)?,
+            BlackboardValue::Text(value) => value.visit("Value", visitor)?,
+        }
+
+        visitor.leave_region()
+    }
+}
+
+/// Named values that agent remembers between ticks - targets, positions, flags, etc.
+#[derive(Clone, Debug, Default)]
+pub struct Blackboard {
+    values: HashMap<String, BlackboardValue>,
+}
+
+impl Blackboard {
+    /// Creates empty blackboard.
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Sets value with given name.
+    pub fn set<S: AsRef<str>>(&mut self, name: S, value: BlackboardValue) {
+        self.values.insert(name.as_ref().to_owned(), value);
+    }
+
+    /// Returns value with given name.
+    pub fn get<S: AsRef<str>>(&self, name: S) -> Option<&BlackboardValue> {
+        self.values.get(name.as_ref())
+    }
+
+    /// Removes value with given name.
+    pub fn remove<S: AsRef<str>>(&mut self, name: S) -> Option<BlackboardValue> {
+        self.values.remove(name.as_ref())
+    }
+
+    /// Returns flag with given name, false if there is no such flag.
+    pub fn get_bool<S: AsRef<str>>(&self, name: S) -> bool {
+        match self.get(name) {
+            Some(BlackboardValue::Bool(value)) => *value,
+            _ => false,
+        }
+    }
+
+    /// Returns number with given name.
+    pub fn get_number<S: AsRef<str>>(&self, name: S) -> Option<f32> {
+        match self.get(name) {
+            Some(BlackboardValue::Number(value)) => Some(*value),
+            _ => None,
+        }
+    }
+
+    /// Returns vector with given name.
+    pub fn get_vector<S: AsRef<str>>(&self, name: S) -> Option<Vec3> {
+        match self.get(name) {
+            Some(BlackboardValue::Vector(value)) => Some(*value),
+            _ => None,
+        }
+    }
+
+    /// Returns handle of scene node with given name.
+    pub fn get_node<S: AsRef<str>>(&self, name: S) -> Handle<Node> {
+        match self.get(name) {
+            Some(BlackboardValue::Node(value)) => *value,
+            _ => Handle::NONE,
+        }
+    }
+}
+
+impl Visit for Blackboard {
+    fn visit(&mut self, name: &str, visitor: &mut Visitor) -> VisitResult {
+        visitor.enter_region(name)?;
+
+        self.values.visit("Values", visitor)?;
+
+        visitor.leave_region()
+    }
+}
+
+/// State of a running node.
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+struct NodeState {
+    /// Index of current child for composites, amount of repetitions for repeat.
+    index: u32,
+    /// Elapsed time for wait.
+    timer: f32,
+}
+
+impl Visit for NodeState {
+    fn visit(&mut self, name: &str, visitor: &mut Visitor) -> VisitResult {
+        visitor.enter_region(name)?;
+
+        self.index.visit("Index", visitor)?;
+        self.timer.visit("Timer", visitor)?;
+
+        visitor.leave_region()
+    }
+}
+
+/// Everything that action or condition knows about the agent which is ticked.
+pub struct AgentContext<'a> {
+    /// Handle of the agent.
+    pub agent: Handle<BehaviorAgent>,
+    /// Scene node controlled by the agent.
+    pub owner: Handle<Node>,
+    /// Blackboard of the agent.
+    pub blackboard: &'a mut Blackboard,
+    /// Time passed since previous tick of the agent.
+    pub dt: f32,
+}
+
+/// Agent which is controlled by a behavior tree.
+#[derive(Clone, Debug, Default)]
+pub struct BehaviorAgent {
+    tree: Handle<BehaviorTree>,
+    owner: Handle<Node>,
+    /// Memory of the agent.
+    pub blackboard: Blackboard,
+    states: HashMap<Handle<BehaviorNode>, NodeState>,
+    tick_interval: f32,
+    elapsed: f32,
+    enabled: bool,
+}
+
+impl BehaviorAgent {
+    /// Creates new agent that uses given tree and controls given scene node. Agent is ticked
+    /// every update.
+    pub fn new(tree: Handle<BehaviorTree>, owner: Handle<Node>) -> Self {
+        Self {
+            tree,
+            owner,
+            blackboard: Default::default(),
+            states: Default::default(),
+            tick_interval: 0.0,
+            elapsed: 0.0,
+            enabled: true,
+        }
+    }
+
+    /// Sets minimal interval between ticks in seconds. Large amount of agents can be ticked
+    /// less often than every frame to save time.
+    pub fn set_tick_interval(&mut self, interval: f32) {
+        self.tick_interval = interval;
+    }
+
+    /// Returns tree of the agent.
+    pub fn tree(&self) -> Handle<BehaviorTree> {
+        self.tree
+    }
+
+    /// Returns scene node controlled by the agent.
+    pub fn owner(&self) -> Handle<Node> {
+        self.owner
+    }
+
+    /// Enables or disables agent, disabled agents are not ticked.
+    pub fn set_enabled(&mut self, enabled: bool) {
+        self.enabled = enabled;
+    }
+
+    /// Returns true if agent is enabled.
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    /// Aborts every running node, next tick starts from the root.
+    pub fn reset(&mut self) {
+        self.states.clear();
+    }
+}
+
+impl Visit for BehaviorAgent {
+    fn visit(&mut self, name: &str, visitor: &mut Visitor) -> VisitResult {
+        visitor.enter_region(name)?;
+
+        self.tree.visit("Tree", visitor)?;
+        self.owner.visit("Owner", visitor)?;
+        self.blackboard.visit("Blackboard", visitor)?;
+        self.states.visit("States", visitor)?;
+        self.tick_interval.visit("TickInterval", visitor)?;
+        self.elapsed.visit("Elapsed", visitor)?;
+        self.enabled.visit("Enabled", visitor)?;
+
+        visitor.leave_region()
+    }
+}
+
+type Action<T> = Box<dyn FnMut(&mut T, &mut AgentContext) -> Status>;
+type Condition<T> = Box<dyn Fn(&T, &AgentContext) -> bool>;
+
+/// Code of actions and conditions, `T` is the game (or any other context) which is passed to
+/// them.
+pub struct Behaviors<T> {
+    actions: HashMap<String, Action<T>>,
+    conditions: HashMap<String, Condition<T>>,
+    reported: HashSet<String>,
+}
+
+impl<T> Default for Behaviors<T> {
+    fn default() -> Self {
+        Self {
+            actions: Default::default(),
+            conditions: Default::default(),
+            reported: Default::default(),
+        }
+    }
+}
+
+impl<T> Behaviors<T> {
+    /// Creates empty set of behaviors.
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Registers action with given name. Action returns [Status::Running] if it needs more
+    /// time, it will be called again on next tick.
+    pub fn add_action<S, F>(&mut self, name: S, action: F)
+    where
+        S: AsRef<str>,
+        F: FnMut(&mut T, &mut AgentContext) -> Status + 'static,
+    {
+        self.actions
+            .insert(name.as_ref().to_owned(), Box::new(action));
+    }
+
+    /// Registers condition with given name.
+    pub fn add_condition<S, F>(&mut self, name: S, condition: F)
+    where
+        S: AsRef<str>,
+        F: Fn(&T, &AgentContext) -> bool + 'static,
+    {
+        self.conditions
+            .insert(name.as_ref().to_owned(), Box::new(condition));
+    }
+
+    fn report_missing(&mut self, kind: &str, name: &str) {
+        // Report once, otherwise log will be flooded every tick.
+        if self.reported.insert(name.to_owned()) {
+            Log::writeln(format!("{} {} is not registered, node fails.", kind, name));
+        }
+    }
+}
+
+fn reset(
+    tree: &BehaviorTree,
+    node: Handle<BehaviorNode>,
+    states: &mut HashMap<Handle<BehaviorNode>, NodeState>,
+) {
+    states.remove(&node);
+    for &child in tree.nodes[node].children() {
+        reset(tree, child, states);
+    }
+}
+
+fn tick<T>(
+    tree: &BehaviorTree,
+    node: Handle<BehaviorNode>,
+    states: &mut HashMap<Handle<BehaviorNode>, NodeState>,
+    behaviors: &mut Behaviors<T>,
+    game: &mut T,
+    context: &mut AgentContext,
+) -> Status {
+    match &tree.nodes[node] {
+        BehaviorNode::Sequence(children) | BehaviorNode::Selector(children) => {
+            let is_sequence = matches!(tree.nodes[node], BehaviorNode::Sequence(_));
+            let mut current = states.get(&node).map_or(0, |state| state.index as usize);
+            while let Some(&child) = children.get(current) {
+                match tick(tree, child, states, behaviors, game, context) {
+                    Status::Running => {
+                        states.insert(
+                            node,
+                            NodeState {
+                                index: current as u32,
+                                timer: 0.0,
+                            },
+                        );
+                        return Status::Running;
+                    }
+                    Status::Success if !is_sequence => {
+                        states.remove(&node);
+                        return Status::Success;
+                    }
+                    Status::Failure if is_sequence => {
+                        states.remove(&node);
+                        return Status::Failure;
+                    }
+                    _ => current += 1,
+                }
+            }
+            states.remove(&node);
+            if is_sequence {
+                Status::Success
+            } else {
+                Status::Failure
+            }
+        }
+        BehaviorNode::Parallel {
+            children,
+            required_successes,
+        } => {
+            let (mut successes, mut failures) = (0, 0);
+            for &child in children.iter() {
+                match tick(tree, child, states, behaviors, game, context) {
+                    Status::Success => successes += 1,
+                    Status::Failure => failures += 1,
+                    Status::Running => (),
+                }
+            }
+            let required = (*required_successes as usize).min(children.len());
+            let status = if successes >= required {
+                Status::Success
+            } else if failures > children.len() - required {
+                Status::Failure
+            } else {
+                Status::Running
+            };
+            if status != Status::Running {
+                reset(tree, node, states);
+            }
+            status
+        }
+        BehaviorNode::Inverter(child) => {
+            match tick(tree, *child, states, behaviors, game, context) {
+                Status::Success => Status::Failure,
+                Status::Failure => Status::Success,
+                Status::Running => Status::Running,
+            }
+        }
+        BehaviorNode::Succeeder(child) => {
+            match tick(tree, *child, states, behaviors, game, context) {
+                Status::Running => Status::Running,
+                _ => Status::Success,
+            }
+        }
+        BehaviorNode::Repeat { child, count } => {
+            match tick(tree, *child, states, behaviors, game, context) {
+                Status::Running => Status::Running,
+                Status::Failure => {
+                    states.remove(&node);
+                    Status::Failure
+                }
+                Status::Success => {
+                    let state = states.entry(node).or_default();
+                    state.index += 1;
+                    if *count != 0 && state.index >= *count {
+                        states.remove(&node);
+                        Status::Success
+                    } else {
+                        Status::Running
+                    }
+                }
+            }
+        }
+        BehaviorNode::Action(name) => match behaviors.actions.get_mut(name) {
+            Some(action) => action(game, context),
+            None => {
+                behaviors.report_missing("Action", name);
+                Status::Failure
+            }
+        },
+        BehaviorNode::Condition(name) => match behaviors.conditions.get(name) {
+            Some(condition) => {
+                if condition(game, context) {
+                    Status::Success
+                } else {
+                    Status::Failure
+                }
+            }
+            None => {
+                behaviors.report_missing("Condition", name);
+                Status::Failure
+            }
+        },
+        BehaviorNode::Wait(duration) => {
+            let state = states.entry(node).or_default();
+            state.timer += context.dt;
+            if state.timer >= *duration {
+                states.remove(&node);
+                Status::Success
+            } else {
+                Status::Running
+            }
+        }
+    }
+}
+
+/// Container of trees and agents which ticks every agent. Trees and agents are saved with
+/// [Visit], behaviors are code and must be registered again after load.
+pub struct BehaviorWorld<T> {
+    trees: Pool<BehaviorTree>,
+    agents: Pool<BehaviorAgent>,
+    /// Actions and conditions which are used by trees.
+    pub behaviors: Behaviors<T>,
+}
+
+impl<T> Default for BehaviorWorld<T> {
+    fn default() -> Self {
+        Self {
+            trees: Pool::new(),
+            agents: Pool::new(),
+            behaviors: Default::default(),
+        }
+    }
+}
+
+impl<T> BehaviorWorld<T> {
+    /// Creates new empty world.
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Adds new tree.
+    pub fn add_tree(&mut self, tree: BehaviorTree) -> Handle<BehaviorTree> {
+        self.trees.spawn(tree)
+    }
+
+    /// Returns reference to a tree.
+    pub fn tree(&self, handle: Handle<BehaviorTree>) -> &BehaviorTree {
+        &self.trees[handle]
+    }
+
+    /// Adds new agent.
+    pub fn add_agent(&mut self, agent: BehaviorAgent) -> Handle<BehaviorAgent> {
+        self.agents.spawn(agent)
+    }
+
+    /// Removes agent.
+    pub fn remove_agent(&mut self, agent: Handle<BehaviorAgent>) {
+        self.agents.free(agent);
+    }
+
+    /// Returns reference to an agent.
+    pub fn agent(&self, handle: Handle<BehaviorAgent>) -> &BehaviorAgent {
+        &self.agents[handle]
+    }
+
+    /// Returns reference to an agent.
+    pub fn agent_mut(&mut self, handle: Handle<BehaviorAgent>) -> &mut BehaviorAgent {
+        &mut self.agents[handle]
+    }
+
+    /// Ticks every enabled agent whose tick interval has passed. Should be called every frame
+    /// after engine update with the same time step, so AI is paused and slowed down together
+    /// with scenes.
+    pub fn update(&mut self, game: &mut T, dt: f32) {
+        for (handle, agent) in self.agents.pair_iter_mut() {
+            if !agent.enabled || !self.trees.is_valid_handle(agent.tree) {
+                continue;
+            }
+            agent.elapsed += dt;
+            if agent.elapsed < agent.tick_interval {
+                continue;
+            }
+            let tree = &self.trees[agent.tree];
+            if tree.root.is_none() {
+                continue;
+            }
+            let mut context = AgentContext {
+                agent: handle,
+                owner: agent.owner,
+                blackboard: &mut agent.blackboard,
+                dt: agent.elapsed,
+            };
+            tick(
+                tree,
+                tree.root,
+                &mut agent.states,
+                &mut self.behaviors,
+                game,
+                &mut context,
+            );
+            agent.elapsed = 0.0;
+        }
+    }
+}
+
+impl<T> Visit for BehaviorWorld<T> {
+    fn visit(&mut self, name: &str, visitor: &mut Visitor) -> VisitResult {
+        visitor.enter_region(name)?;
+
+        self.trees.visit("Trees", visitor)?;
+        self.agents.visit("Agents", visitor)?;
+
+        visitor.leave_region()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        core::pool::Handle,
+        utils::behavior::{
+            BehaviorAgent, BehaviorNode, BehaviorTree, BehaviorWorld, BlackboardValue, Status,
+        },
+    };
+
+    #[derive(Default)]
+    struct Game {
+        log: Vec<&'static str>,
+    }
+
+    #[test]
+    fn behavior_tree_test() {
+        let mut tree = BehaviorTree::new("Test");
+        let alarm = tree.add_node(BehaviorNode::Condition("Alarm".to_owned()));
+        let flee = tree.add_node(BehaviorNode::Action("Flee".to_owned()));
+        let danger = tree.add_node(BehaviorNode::Sequence(vec![alarm, flee]));
+        let wait = tree.add_node(BehaviorNode::Wait(0.5));
+        let patrol = tree.add_node(BehaviorNode::Action("Patrol".to_owned()));
+        let calm = tree.add_node(BehaviorNode::Sequence(vec![wait, patrol]));
+        let root = tree.add_node(BehaviorNode::Selector(vec![danger, calm]));
+        tree.set_root(root);
+
+        let mut world = BehaviorWorld::new();
+        world
+            .behaviors
+            .add_condition("Alarm", |_: &Game, ctx| ctx.blackboard.get_bool("Alarm"));
+        world.behaviors.add_action("Flee", |game: &mut Game, _| {
+            game.log.push("Flee");
+            Status::Success
+        });
+        world.behaviors.add_action("Patrol", |game: &mut Game, _| {
+            game.log.push("Patrol");
+            Status::Success
+        });
+        let tree = world.add_tree(tree);
+        let agent = world.add_agent(BehaviorAgent::new(tree, Handle::NONE));
+
+        let mut game = Game::default();
+        // Waiting.
+        world.update(&mut game, 0.25);
+        assert!(game.log.is_empty());
+        // Wait is resumed and finished.
+        world.update(&mut game, 0.25);
+        assert_eq!(game.log, vec!["Patrol"]);
+
+        world
+            .agent_mut(agent)
+            .blackboard
+            .set("Alarm", BlackboardValue::Bool(true));
+        world.update(&mut game, 0.25);
+        assert_eq!(game.log, vec!["Patrol", "Flee"]);
+    }
+}
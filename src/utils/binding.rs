@@ -0,0 +1,294 @@
+//! Data binding between state of a game and widgets.
+//!
+//! Values that are shown by user interface are wrapped into [Observable], which stamps every
+//! change of the value with a version unique across all observables, so a binding notices
+//! even if observable is replaced with another one. [Bindings] connects observables with widgets and sends messages to widgets
+//! only when bound value has changed, so code of a game just changes its state and does not
+//! care about user interface at all.
+//!
+//! # Example
+//!
+//! ```no_run
+//! use rg3d::{
+//!     core::pool::Handle,
+//!     engine::Engine,
+//!     gui::node::StubNode,
+//!     utils::binding::{Bindings, Observable},
+//! };
+//!
+//! struct Player {
+//!     health: Observable<u32>,
+//!     ammo: Observable<u32>,
+//! }
+//!
+//! # fn f(engine: &mut Engine<(), StubNode>, player: &mut Player) {
+//! # let health_text = Handle::NONE;
+//! # let ammo_text = Handle::NONE;
+//! let mut bindings = Bindings::new();
+//! bindings.bind_text(health_text, |player: &Player| &player.health);
+//! bindings.bind_text(ammo_text, |player: &Player| &player.ammo);
+//!
+//! // Somewhere in game logic.
+//! player.health.set(50);
+//!
+//! // Every frame - only text of health is changed.
+//! bindings.update(player, &mut engine.user_interface);
+//! # }
+//! ```
+
+use crate::{
+    core::pool::Handle,
+    gui::{
+        message::{
+            CheckBoxMessage, MessageData, MessageDirection, ScrollBarMessage, TextMessage,
+            WidgetMessage,
+        },
+        node::UINode,
+        Control, UserInterface,
+    },
+};
+use std::{
+    fmt::Display,
+    ops::Deref,
+    sync::atomic::{AtomicU64, Ordering},
+};
+
+static NEXT_VERSION: AtomicU64 = AtomicU64::new(0);
+
+fn next_version() -> u64 {
+    NEXT_VERSION.fetch_add(1, Ordering::Relaxed)
+}
+
+/// Value which tracks its changes. See module docs.
+#[derive(Clone, Debug)]
+pub struct Observable<T> {
+    value: T,
+    version: u64,
+}
+
+impl<T: Default> Default for Observable<T> {
+    fn default() -> Self {
+        Self::new(Default::default())
+    }
+}
+
+impl<T> Observable<T> {
+    /// Creates new observable value.
+    pub fn new(value: T) -> Self {
+        Self {
+            value,
+            version: next_version(),
+        }
+    }
+
+    /// Returns current value.
+    pub fn get(&self) -> &T {
+        &self.value
+    }
+
+    /// Changes value using given closure, value is treated as changed even if closure has
+    /// not changed it.
+    pub fn modify<F: FnOnce(&mut T)>(&mut self, func: F) {
+        func(&mut self.value);
+        self.version = next_version();
+    }
+
+    /// Returns version of the value. Every change gets new version which is unique across
+    /// all observables, clones share version with their original until changed.
+    pub fn version(&self) -> u64 {
+        self.version
+    }
+}
+
+impl<T: PartialEq> Observable<T> {
+    /// Sets new value, bound widgets are updated only if new value differs from current.
+    pub fn set(&mut self, value: T) {
+        if self.value != value {
+            self.value = value;
+            self.version = next_version();
+        }
+    }
+}
+
+impl<T> Deref for Observable<T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        &self.value
+    }
+}
+
+type Binding<G, M, C> = Box<dyn FnMut(&G, &mut UserInterface<M, C>)>;
+
+/// Set of bindings between observable values of `G` (game state) and widgets.
+pub struct Bindings<G, M: MessageData, C: Control<M, C>> {
+    bindings: Vec<Binding<G, M, C>>,
+}
+
+impl<G, M: MessageData, C: Control<M, C>> Default for Bindings<G, M, C> {
+    fn default() -> Self {
+        Self {
+            bindings: Default::default(),
+        }
+    }
+}
+
+impl<G: 'static, M: MessageData, C: Control<M, C>> Bindings<G, M, C> {
+    /// Creates empty set of bindings.
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Binds observable value to arbitrary action on user interface. `source` returns
+    /// observable from game state, `apply` is called on first update and then every time
+    /// when the value changes.
+    pub fn bind<T, S, F>(&mut self, source: S, mut apply: F)
+    where
+        T: 'static,
+        S: Fn(&G) -> &Observable<T> + 'static,
+        F: FnMut(&T, &mut UserInterface<M, C>) + 'static,
+    {
+        let mut last_version = None;
+        self.bindings
+            .push(Box::new(move |game: &G, ui: &mut UserInterface<M, C>| {
+                let observable = source(game);
+                if last_version != Some(observable.version()) {
+                    last_version = Some(observable.version());
+                    apply(observable.get(), ui);
+                }
+            }));
+    }
+
+    /// Binds value to text of a `Text` widget.
+    pub fn bind_text<T, S>(&mut self, text: Handle<UINode<M, C>>, source: S)
+    where
+        T: Display + 'static,
+        S: Fn(&G) -> &Observable<T> + 'static,
+    {
+        self.bind(source, move |value, ui| {
+            ui.send_message(TextMessage::text(
+                text,
+                MessageDirection::ToWidget,
+                value.to_string(),
+            ));
+        });
+    }
+
+    /// Binds value to a `ScrollBar` widget, for example to show a progress.
+    pub fn bind_scroll_bar<S>(&mut self, scroll_bar: Handle<UINode<M, C>>, source: S)
+    where
+        S: Fn(&G) -> &Observable<f32> + 'static,
+    {
+        self.bind(source, move |value, ui| {
+            ui.send_message(ScrollBarMessage::value(
+                scroll_bar,
+                MessageDirection::ToWidget,
+                *value,
+            ));
+        });
+    }
+
+    /// Binds flag to a `CheckBox` widget.
+    pub fn bind_check_box<S>(&mut self, check_box: Handle<UINode<M, C>>, source: S)
+    where
+        S: Fn(&G) -> &Observable<bool> + 'static,
+    {
+        self.bind(source, move |value, ui| {
+            ui.send_message(CheckBoxMessage::checked(
+                check_box,
+                MessageDirection::ToWidget,
+                Some(*value),
+            ));
+        });
+    }
+
+    /// Binds flag to visibility of any widget.
+    pub fn bind_visibility<S>(&mut self, widget: Handle<UINode<M, C>>, source: S)
+    where
+        S: Fn(&G) -> &Observable<bool> + 'static,
+    {
+        self.bind(source, move |value, ui| {
+            ui.send_message(WidgetMessage::visibility(
+                widget,
+                MessageDirection::ToWidget,
+                *value,
+            ));
+        });
+    }
+
+    /// Removes every binding.
+    pub fn clear(&mut self) {
+        self.bindings.clear();
+    }
+
+    /// Sends messages to widgets whose bound values have changed since previous update. Must
+    /// be called every frame.
+    pub fn update(&mut self, game: &G, ui: &mut UserInterface<M, C>) {
+        for binding in self.bindings.iter_mut() {
+            binding(game, ui);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        core::math::vec2::Vec2,
+        gui::{node::StubNode, UserInterface},
+        utils::binding::{Bindings, Observable},
+    };
+    use std::{cell::Cell, rc::Rc};
+
+    #[test]
+    fn observable_test() {
+        let mut value = Observable::new(1);
+        let initial = value.version();
+        value.set(1);
+        assert_eq!(value.version(), initial);
+        value.set(2);
+        let changed = value.version();
+        assert_ne!(changed, initial);
+        assert_eq!(*value, 2);
+        value.modify(|v| *v += 1);
+        assert_ne!(value.version(), changed);
+        assert_eq!(*value.get(), 3);
+    }
+
+    #[test]
+    fn replaced_observable_test() {
+        struct Game {
+            health: Observable<u32>,
+        }
+
+        let mut ui = UserInterface::<(), StubNode>::new(Vec2::new(100.0, 100.0));
+        let mut game = Game {
+            health: Observable::new(100),
+        };
+        let applied = Rc::new(Cell::new(0));
+
+        let mut bindings = Bindings::new();
+        let counter = applied.clone();
+        bindings.bind(
+            |game: &Game| &game.health,
+            move |_, _| counter.set(counter.get() + 1),
+        );
+
+        bindings.update(&game, &mut ui);
+        assert_eq!(applied.get(), 1);
+
+        game.health.set(50);
+        bindings.update(&game, &mut ui);
+        assert_eq!(applied.get(), 2);
+
+        // New observable changed once must not be mistaken for the old one.
+        game.health = Observable::new(100);
+        game.health.set(75);
+        bindings.update(&game, &mut ui);
+        assert_eq!(applied.get(), 3);
+
+        let clone = game.health.clone();
+        game.health = clone;
+        bindings.update(&game, &mut ui);
+        assert_eq!(applied.get(), 3);
+    }
+}
@@ -0,0 +1,486 @@
+//! Curves and splines.
+//!
+//! [Curve] is a function of one variable defined by keys - it is used for easing of
+//! animations, parameters of particles over lifetime, etc. Every key defines how the curve
+//! goes to the next key: stays constant, changes linearly or follows cubic Hermite segment
+//! with given tangents.
+//!
+//! Splines are curves in space - [CubicBezier] and [CatmullRomSpline], the latter passes
+//! through all its control points, so it is handy for camera paths. Parameter of a spline
+//! is not proportional to passed distance, [ArcLengthTable] maps distance along a spline to
+//! parameter, so objects can move along splines with constant speed.
+
+use crate::core::{
+    math::vec3::Vec3,
+    visitor::{Visit, VisitResult, Visitor},
+};
+
+/// Defines how a curve goes from a key to the next one.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum CurveKeyKind {
+    /// Value of the key is held until the next key.
+    Constant,
+    /// Value changes linearly to the value of the next key.
+    Linear,
+    /// Cubic Hermite segment, tangents are derivatives of the curve on the left and on the
+    /// right side of the key.
+    Cubic {
+        /// Derivative of the curve before the key.
+        left_tangent: f32,
+        /// Derivative of the curve after the key.
+        right_tangent: f32,
+    },
+}
+
+impl Default for CurveKeyKind {
+    fn default() -> Self {
+        CurveKeyKind::Linear
+    }
+}
+
+impl Visit for CurveKeyKind {
+    fn visit(&mut self, name: &str, visitor: &mut Visitor) -> VisitResult {
+        visitor.enter_region(name)?;
+
+        let mut id: u8 = match self {
+            CurveKeyKind::Constant => 0,
+            CurveKeyKind::Linear => 1,
+            CurveKeyKind::Cubic { .. } => 2,
+        };
+        id.visit("Id", visitor)?;
+        if visitor.is_reading() {
+            *self = match id {
+                0 => CurveKeyKind::Constant,
+                1 => CurveKeyKind::Linear,
+                2 => CurveKeyKind::Cubic {
+                    left_tangent: 0.0,
+                    right_tangent: 0.0,
+                },
+                _ => return Err(format!("Invalid curve key kind {}", id).into()),
+            };
+        }
+        if let CurveKeyKind::Cubic {
+            left_tangent,
+            right_tangent,
+        } = self
+        {
+            left_tangent.visit("LeftTangent", visitor)?;
+            right_tangent.visit("RightTangent", visitor)?;
+        }
+
+        visitor.leave_region()
+    }
+}
+
+/// Key of a curve.
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct CurveKey {
+    /// Location of the key on the axis of the curve.
+    pub location: f32,
+    /// Value of the curve at the location.
+    pub value: f32,
+    /// Shape of a segment to the next key.
+    pub kind: CurveKeyKind,
+}
+
+impl CurveKey {
+    /// Creates new key.
+    pub fn new(location: f32, value: f32, kind: CurveKeyKind) -> Self {
+        Self {
+            location,
+            value,
+            kind,
+        }
+    }
+}
+
+impl Visit for CurveKey {
+    fn visit(&mut self, name: &str, visitor: &mut Visitor) -> VisitResult {
+        visitor.enter_region(name)?;
+
+        self.location.visit("Location", visitor)?;
+        self.value.visit("Value", visitor)?;
+        self.kind.visit("Kind", visitor)?;
+
+        visitor.leave_region()
+    }
+}
+
+/// Piecewise curve defined by keys. See module docs.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Curve {
+    /// Sorted by location.
+    keys: Vec<CurveKey>,
+}
+
+impl Curve {
+    /// Creates new curve from a set of keys, keys are sorted by location.
+    pub fn new(mut keys: Vec<CurveKey>) -> Self {
+        keys.sort_by(|a, b| a.location.partial_cmp(&b.location).unwrap());
+        Self { keys }
+    }
+
+    /// Returns keys sorted by location.
+    pub fn keys(&self) -> &[CurveKey] {
+        &self.keys
+    }
+
+    /// Adds new key, keeps keys sorted. Returns index of the key.
+    pub fn add_key(&mut self, key: CurveKey) -> usize {
+        let index = self
+            .keys
+            .iter()
+            .position(|k| k.location > key.location)
+            .unwrap_or_else(|| self.keys.len());
+        self.keys.insert(index, key);
+        index
+    }
+
+    /// Removes key at given index.
+    pub fn remove_key(&mut self, index: usize) -> CurveKey {
+        self.keys.remove(index)
+    }
+
+    /// Moves key to a new location and changes its value. Returns new index of the key,
+    /// because keys are kept sorted.
+    pub fn move_key(&mut self, index: usize, location: f32, value: f32) -> usize {
+        let mut key = self.keys.remove(index);
+        key.location = location;
+        key.value = value;
+        self.add_key(key)
+    }
+
+    /// Returns value of the curve at given location. Curve is constant before the first key
+    /// and after the last key, empty curve is zero.
+    pub fn fetch(&self, location: f32) -> f32 {
+        let (first, last) = match (self.keys.first(), self.keys.last()) {
+            (Some(first), Some(last)) => (first, last),
+            _ => return 0.0,
+        };
+        if location <= first.location {
+            return first.value;
+        }
+        if location >= last.location {
+            return last.value;
+        }
+
+        // Index of the first key which is to the right of location.
+        let right_index = self
+            .keys
+            .iter()
+            .position(|k| k.location > location)
+            .unwrap();
+        let left = &self.keys[right_index - 1];
+        let right = &self.keys[right_index];
+        let length = right.location - left.location;
+        let t = (location - left.location) / length;
+
+        match left.kind {
+            CurveKeyKind::Constant => left.value,
+            CurveKeyKind::Linear => left.value + (right.value - left.value) * t,
+            CurveKeyKind::Cubic { right_tangent, .. } => {
+                let right_key_tangent = match right.kind {
+                    CurveKeyKind::Cubic { left_tangent, .. } => left_tangent,
+                    // Non-cubic key is approached with tangent of straight line.
+                    _ => (right.value - left.value) / length,
+                };
+                hermite(
+                    left.value,
+                    right_tangent * length,
+                    right.value,
+                    right_key_tangent * length,
+                    t,
+                )
+            }
+        }
+    }
+}
+
+impl Visit for Curve {
+    fn visit(&mut self, name: &str, visitor: &mut Visitor) -> VisitResult {
+        visitor.enter_region(name)?;
+
+        self.keys.visit("Keys", visitor)?;
+
+        visitor.leave_region()
+    }
+}
+
+/// Cubic Hermite interpolation on unit interval.
+fn hermite(p0: f32, m0: f32, p1: f32, m1: f32, t: f32) -> f32 {
+    let t2 = t * t;
+    let t3 = t2 * t;
+    (2.0 * t3 - 3.0 * t2 + 1.0) * p0
+        + (t3 - 2.0 * t2 + t) * m0
+        + (-2.0 * t3 + 3.0 * t2) * p1
+        + (t3 - t2) * m1
+}
+
+/// Curve in space.
+pub trait Spline {
+    /// Returns point of the spline at given parameter, parameter is in range
+    /// [0; max_parameter].
+    fn point(&self, t: f32) -> Vec3;
+
+    /// Returns maximum value of the parameter.
+    fn max_parameter(&self) -> f32;
+
+    /// Returns direction of the spline at given parameter, it is not normalized.
+    fn tangent(&self, t: f32) -> Vec3 {
+        let dt = 0.001 * self.max_parameter().max(1.0);
+        let a = self.point((t - dt).max(0.0));
+        let b = self.point((t + dt).min(self.max_parameter()));
+        b - a
+    }
+}
+
+/// Cubic Bezier curve, it starts at the first point, ends at the last point and is pulled
+/// towards two middle points.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct CubicBezier {
+    /// Control points.
+    pub points: [Vec3; 4],
+}
+
+impl CubicBezier {
+    /// Creates new curve from control points.
+    pub fn new(p0: Vec3, p1: Vec3, p2: Vec3, p3: Vec3) -> Self {
+        Self {
+            points: [p0, p1, p2, p3],
+        }
+    }
+}
+
+impl Spline for CubicBezier {
+    fn point(&self, t: f32) -> Vec3 {
+        let t = t.max(0.0).min(1.0);
+        let u = 1.0 - t;
+        let [p0, p1, p2, p3] = self.points;
+        p0.scale(u * u * u)
+            + p1.scale(3.0 * u * u * t)
+            + p2.scale(3.0 * u * t * t)
+            + p3.scale(t * t * t)
+    }
+
+    fn max_parameter(&self) -> f32 {
+        1.0
+    }
+
+    fn tangent(&self, t: f32) -> Vec3 {
+        let t = t.max(0.0).min(1.0);
+        let u = 1.0 - t;
+        let [p0, p1, p2, p3] = self.points;
+        (p1 - p0).scale(3.0 * u * u) + (p2 - p1).scale(6.0 * u * t) + (p3 - p2).scale(3.0 * t * t)
+    }
+}
+
+/// Catmull-Rom spline, it passes through every control point. Parameter is in range
+/// [0; segment count], every integer value of the parameter is a control point.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct CatmullRomSpline {
+    /// Control points.
+    pub points: Vec<Vec3>,
+    /// Closed spline goes from the last point back to the first one.
+    pub closed: bool,
+}
+
+impl CatmullRomSpline {
+    /// Creates new spline from control points.
+    pub fn new(points: Vec<Vec3>, closed: bool) -> Self {
+        Self { points, closed }
+    }
+
+    /// Returns amount of segments of the spline.
+    pub fn segment_count(&self) -> usize {
+        match self.points.len() {
+            0 | 1 => 0,
+            n if self.closed => n,
+            n => n - 1,
+        }
+    }
+
+    /// Returns control point by index, indices out of range are wrapped for closed spline
+    /// and clamped for open one.
+    fn control_point(&self, index: isize) -> Vec3 {
+        let count = self.points.len() as isize;
+        let index = if self.closed {
+            index.rem_euclid(count)
+        } else {
+            index.max(0).min(count - 1)
+        };
+        self.points[index as usize]
+    }
+}
+
+impl Spline for CatmullRomSpline {
+    fn point(&self, t: f32) -> Vec3 {
+        match self.points.len() {
+            0 => return Vec3::ZERO,
+            1 => return self.points[0],
+            _ => (),
+        }
+        let t = t.max(0.0).min(self.max_parameter());
+        let segment = (t.floor() as usize).min(self.segment_count() - 1);
+        let t = t - segment as f32;
+
+        let i = segment as isize;
+        let p0 = self.control_point(i - 1);
+        let p1 = self.control_point(i);
+        let p2 = self.control_point(i + 1);
+        let p3 = self.control_point(i + 2);
+
+        // Uniform Catmull-Rom is a Hermite segment with tangents from neighbour points.
+        let m1 = (p2 - p0).scale(0.5);
+        let m2 = (p3 - p1).scale(0.5);
+        let t2 = t * t;
+        let t3 = t2 * t;
+        p1.scale(2.0 * t3 - 3.0 * t2 + 1.0)
+            + m1.scale(t3 - 2.0 * t2 + t)
+            + p2.scale(-2.0 * t3 + 3.0 * t2)
+            + m2.scale(t3 - t2)
+    }
+
+    fn max_parameter(&self) -> f32 {
+        self.segment_count() as f32
+    }
+}
+
+/// Table that maps distance along a spline to parameter of the spline. See module docs.
+#[derive(Clone, Debug, Default)]
+pub struct ArcLengthTable {
+    parameters: Vec<f32>,
+    distances: Vec<f32>,
+}
+
+impl ArcLengthTable {
+    /// Creates new table by sampling a spline, more samples give more precise mapping.
+    /// Table must be re-created when spline changes.
+    pub fn new<S: Spline>(spline: &S, samples: usize) -> Self {
+        let samples = samples.max(1);
+        let max_parameter = spline.max_parameter();
+        let mut parameters = Vec::with_capacity(samples + 1);
+        let mut distances = Vec::with_capacity(samples + 1);
+        let mut previous = spline.point(0.0);
+        let mut distance = 0.0;
+        for i in 0..=samples {
+            let t = max_parameter * i as f32 / samples as f32;
+            let point = spline.point(t);
+            distance += point.distance(&previous);
+            previous = point;
+            parameters.push(t);
+            distances.push(distance);
+        }
+        Self {
+            parameters,
+            distances,
+        }
+    }
+
+    /// Returns total length of the spline.
+    pub fn length(&self) -> f32 {
+        self.distances.last().cloned().unwrap_or(0.0)
+    }
+
+    /// Returns parameter of the spline at given distance from its beginning.
+    pub fn parameter(&self, distance: f32) -> f32 {
+        if self.parameters.is_empty() {
+            return 0.0;
+        }
+        let distance = distance.max(0.0).min(self.length());
+        let index = match self
+            .distances
+            .binary_search_by(|d| d.partial_cmp(&distance).unwrap())
+        {
+            Ok(index) => return self.parameters[index],
+            Err(index) => index,
+        };
+        let (d0, d1) = (self.distances[index - 1], self.distances[index]);
+        let (t0, t1) = (self.parameters[index - 1], self.parameters[index]);
+        t0 + (t1 - t0) * (distance - d0) / (d1 - d0)
+    }
+
+    /// Returns point of a spline at given distance from its beginning, the spline must be
+    /// the one that was used to create the table.
+    pub fn point<S: Spline>(&self, spline: &S, distance: f32) -> Vec3 {
+        spline.point(self.parameter(distance))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        core::math::vec3::Vec3,
+        utils::curve::{
+            ArcLengthTable, CatmullRomSpline, CubicBezier, Curve, CurveKey, CurveKeyKind, Spline,
+        },
+    };
+
+    #[test]
+    fn curve_test() {
+        let curve = Curve::new(vec![
+            CurveKey::new(2.0, 1.0, CurveKeyKind::Constant),
+            CurveKey::new(0.0, 0.0, CurveKeyKind::Linear),
+            CurveKey::new(3.0, 3.0, CurveKeyKind::Linear),
+        ]);
+        assert_eq!(curve.fetch(-1.0), 0.0);
+        assert_eq!(curve.fetch(1.0), 0.5);
+        assert_eq!(curve.fetch(2.5), 1.0);
+        assert_eq!(curve.fetch(10.0), 3.0);
+        assert_eq!(Curve::default().fetch(1.0), 0.0);
+
+        // Smooth step with zero tangents.
+        let cubic = Curve::new(vec![
+            CurveKey::new(
+                0.0,
+                0.0,
+                CurveKeyKind::Cubic {
+                    left_tangent: 0.0,
+                    right_tangent: 0.0,
+                },
+            ),
+            CurveKey::new(
+                1.0,
+                1.0,
+                CurveKeyKind::Cubic {
+                    left_tangent: 0.0,
+                    right_tangent: 0.0,
+                },
+            ),
+        ]);
+        assert_eq!(cubic.fetch(0.5), 0.5);
+        assert!(cubic.fetch(0.25) < 0.25);
+    }
+
+    #[test]
+    fn spline_test() {
+        let points = vec![
+            Vec3::new(0.0, 0.0, 0.0),
+            Vec3::new(1.0, 0.0, 0.0),
+            Vec3::new(2.0, 0.0, 0.0),
+            Vec3::new(4.0, 0.0, 0.0),
+        ];
+        let spline = CatmullRomSpline::new(points.clone(), false);
+        assert_eq!(spline.max_parameter(), 3.0);
+        for (i, point) in points.iter().enumerate() {
+            assert!(spline.point(i as f32).distance(point) < 1e-5);
+        }
+
+        let table = ArcLengthTable::new(&spline, 300);
+        assert!((table.length() - 4.0).abs() < 1e-3);
+        assert!(
+            table
+                .point(&spline, 3.0)
+                .distance(&Vec3::new(3.0, 0.0, 0.0))
+                < 1e-2
+        );
+
+        let bezier = CubicBezier::new(
+            Vec3::new(0.0, 0.0, 0.0),
+            Vec3::new(0.0, 1.0, 0.0),
+            Vec3::new(1.0, 1.0, 0.0),
+            Vec3::new(1.0, 0.0, 0.0),
+        );
+        assert!(bezier.point(0.5).distance(&Vec3::new(0.5, 0.75, 0.0)) < 1e-5);
+        assert!(bezier.tangent(0.5).distance(&Vec3::new(1.5, 0.0, 0.0)) < 1e-5);
+    }
+}
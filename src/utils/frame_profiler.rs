@@ -0,0 +1,387 @@
+//! Frame profiler - hierarchical timeline of CPU work done in every frame.
+//!
+//! Function profiler of rg3d-core (`scope_profile!` macro, `enable_profiler` feature)
+//! accumulates time of every function over whole run. Frame profiler records when every
+//! scope has started and how long it took in each frame, so it answers "where did time of
+//! this slow frame go". It is always available and costs almost nothing while disabled.
+//!
+//! Engine marks its own stages (update, resources, physics, animation, UI, render); game code
+//! can add its own scopes, they'll be nested into the tree:
+//!
+//! ```no_run
+//! use rg3d::{frame_scope, utils::frame_profiler};
+//!
+//! frame_profiler::set_enabled(true);
+//!
+//! fn update_ai() {
+//!     frame_scope!("AI");
+//!     // ...
+//! }
+//!
+//! // Later, after few frames.
+//! if let Some(frame) = frame_profiler::last_frame() {
+//!     println!("{}", frame);
+//! }
+//! frame_profiler::save_chrome_trace("frames.json").unwrap();
+//! ```
+//!
+//! Saved file can be opened in chrome://tracing (or any viewer of Trace Event Format).
+
+use std::{
+    cell::RefCell,
+    collections::VecDeque,
+    fmt::{Display, Formatter},
+    fs::File,
+    io::{BufWriter, Write},
+    path::Path,
+    sync::{
+        atomic::{AtomicBool, AtomicU32, Ordering},
+        Mutex,
+    },
+    time::{Duration, Instant},
+};
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+static NEXT_THREAD_ID: AtomicU32 = AtomicU32::new(0);
+
+lazy_static! {
+    static ref STATE: Mutex<State> = Mutex::new(State::new());
+}
+
+thread_local! {
+    static THREAD_ID: u32 = NEXT_THREAD_ID.fetch_add(1, Ordering::Relaxed);
+    /// Scopes which are open on current thread - frame index and index of a sample.
+    static STACK: RefCell<Vec<(u64, usize)>> = RefCell::new(Vec::new());
+}
+
+struct State {
+    epoch: Instant,
+    frame_index: u64,
+    frame_start: Instant,
+    samples: Vec<ScopeSample>,
+    history: VecDeque<FrameProfile>,
+    history_size: usize,
+}
+
+impl State {
+    fn new() -> Self {
+        let now = Instant::now();
+        Self {
+            epoch: now,
+            frame_index: 0,
+            frame_start: now,
+            samples: Vec::new(),
+            history: VecDeque::new(),
+            history_size: 120,
+        }
+    }
+}
+
+/// Single run of a scope.
+#[derive(Clone, Debug)]
+pub struct ScopeSample {
+    /// Name of the scope.
+    pub name: &'static str,
+    /// Index of a thread on which the scope was run, indices are assigned by profiler.
+    pub thread: u32,
+    /// Index of enclosing scope in [FrameProfile::samples].
+    pub parent: Option<usize>,
+    /// Time from beginning of the frame to beginning of the scope.
+    pub start: Duration,
+    /// Time spent in the scope.
+    pub duration: Duration,
+}
+
+/// Timings of a frame.
+#[derive(Clone, Debug)]
+pub struct FrameProfile {
+    /// Index of the frame.
+    pub index: u64,
+    /// Index of a thread which has started the frame.
+    pub thread: u32,
+    /// Time from start of profiling to beginning of the frame.
+    pub start: Duration,
+    /// Total duration of the frame.
+    pub duration: Duration,
+    /// Every scope that was run during the frame, in order of their beginnings.
+    pub samples: Vec<ScopeSample>,
+}
+
+impl FrameProfile {
+    /// Returns indices of scopes which are not nested into other scopes.
+    pub fn roots(&self) -> impl Iterator<Item = usize> + '_ {
+        self.children_of(None)
+    }
+
+    /// Returns indices of scopes which are directly nested into given scope.
+    pub fn children(&self, index: usize) -> impl Iterator<Item = usize> + '_ {
+        self.children_of(Some(index))
+    }
+
+    fn children_of(&self, parent: Option<usize>) -> impl Iterator<Item = usize> + '_ {
+        self.samples
+            .iter()
+            .enumerate()
+            .filter(move |(_, sample)| sample.parent == parent)
+            .map(|(index, _)| index)
+    }
+
+    /// Returns total time of every run of scopes with given name.
+    pub fn total_time(&self, name: &str) -> Duration {
+        self.samples
+            .iter()
+            .filter(|sample| sample.name == name)
+            .map(|sample| sample.duration)
+            .sum()
+    }
+
+    fn fmt_sample(&self, f: &mut Formatter, index: usize, depth: usize) -> std::fmt::Result {
+        let sample = &self.samples[index];
+        writeln!(
+            f,
+            "{:indent$}{} - {:.3} ms",
+            "",
+            sample.name,
+            sample.duration.as_secs_f64() * 1000.0,
+            indent = depth * 4
+        )?;
+        for child in self.children(index) {
+            self.fmt_sample(f, child, depth + 1)?;
+        }
+        Ok(())
+    }
+
+    /// Writes frame as events of Trace Event Format, events are separated by commas.
+    fn write_trace_events<W: Write>(
+        &self,
+        writer: &mut W,
+        first: &mut bool,
+    ) -> std::io::Result<()> {
+        let mut event = |writer: &mut W, name: &str, thread, start, duration| {
+            let separator = if *first { "" } else { "," };
+            *first = false;
+            writeln!(
+                writer,
+                concat!(
+                    "{}{{\"name\":\"{}\",\"cat\":\"rg3d\",\"ph\":\"X\",",
+                    "\"ts\":{:.3},\"dur\":{:.3},\"pid\":0,\"tid\":{}}}"
+                ),
+                separator,
+                escape(name),
+                micros(start),
+                micros(duration),
+                thread
+            )
+        };
+        event(
+            writer,
+            &format!("Frame {}", self.index),
+            self.thread,
+            self.start,
+            self.duration,
+        )?;
+        for sample in self.samples.iter() {
+            event(
+                writer,
+                sample.name,
+                sample.thread,
+                self.start + sample.start,
+                sample.duration,
+            )?;
+        }
+        Ok(())
+    }
+}
+
+impl Display for FrameProfile {
+    fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
+        writeln!(
+            f,
+            "Frame {} - {:.3} ms",
+            self.index,
+            self.duration.as_secs_f64() * 1000.0
+        )?;
+        for root in self.roots() {
+            self.fmt_sample(f, root, 1)?;
+        }
+        Ok(())
+    }
+}
+
+fn micros(duration: Duration) -> f64 {
+    duration.as_secs_f64() * 1_000_000.0
+}
+
+fn escape(name: &str) -> String {
+    name.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+/// Guard that measures time until it is dropped, use [frame_scope](../../macro.frame_scope.html)
+/// macro instead of creating it directly.
+pub struct FrameScope {
+    /// Frame index and index of a sample, None if profiler is disabled.
+    entry: Option<(u64, usize)>,
+}
+
+impl FrameScope {
+    /// Starts new scope with given name.
+    #[inline]
+    pub fn new(name: &'static str) -> Self {
+        if !ENABLED.load(Ordering::Relaxed) {
+            return Self { entry: None };
+        }
+        let mut state = STATE.lock().unwrap();
+        let frame = state.frame_index;
+        let parent = STACK.with(|stack| match stack.borrow().last() {
+            Some(&(parent_frame, parent)) if parent_frame == frame => Some(parent),
+            _ => None,
+        });
+        let index = state.samples.len();
+        let start = Instant::now() - state.frame_start;
+        state.samples.push(ScopeSample {
+            name,
+            thread: THREAD_ID.with(|id| *id),
+            parent,
+            start,
+            duration: Duration::default(),
+        });
+        STACK.with(|stack| stack.borrow_mut().push((frame, index)));
+        Self {
+            entry: Some((frame, index)),
+        }
+    }
+}
+
+impl Drop for FrameScope {
+    #[inline]
+    fn drop(&mut self) {
+        if let Some((frame, index)) = self.entry {
+            STACK.with(|stack| stack.borrow_mut().pop());
+            let mut state = STATE.lock().unwrap();
+            // Scope that was started in previous frame is not recorded.
+            if state.frame_index == frame {
+                let end = Instant::now() - state.frame_start;
+                let sample = &mut state.samples[index];
+                sample.duration = end - sample.start;
+            }
+        }
+    }
+}
+
+/// Measures time from this point to the end of enclosing block and records it into the
+/// current frame.
+#[macro_export]
+macro_rules! frame_scope {
+    ($name:expr) => {
+        let _frame_scope = $crate::utils::frame_profiler::FrameScope::new($name);
+    };
+}
+
+/// Enables or disables profiler.
+pub fn set_enabled(enabled: bool) {
+    ENABLED.store(enabled, Ordering::Relaxed);
+}
+
+/// Returns true if profiler is enabled.
+pub fn is_enabled() -> bool {
+    ENABLED.load(Ordering::Relaxed)
+}
+
+/// Sets how many last frames are kept by profiler, default is 120.
+pub fn set_history_size(size: usize) {
+    let mut state = STATE.lock().unwrap();
+    state.history_size = size.max(1);
+    while state.history.len() > state.history_size {
+        state.history.pop_front();
+    }
+}
+
+/// Finishes current frame and starts next one. Engine calls it at the beginning of every
+/// update, so a frame covers update, rendering and everything game does between them.
+pub fn next_frame() {
+    let mut state = STATE.lock().unwrap();
+    let now = Instant::now();
+    if is_enabled() || !state.samples.is_empty() {
+        let frame = FrameProfile {
+            index: state.frame_index,
+            thread: THREAD_ID.with(|id| *id),
+            start: state.frame_start - state.epoch,
+            duration: now - state.frame_start,
+            samples: std::mem::take(&mut state.samples),
+        };
+        state.history.push_back(frame);
+        while state.history.len() > state.history_size {
+            state.history.pop_front();
+        }
+    }
+    state.frame_index += 1;
+    state.frame_start = now;
+}
+
+/// Returns profile of last finished frame.
+pub fn last_frame() -> Option<FrameProfile> {
+    STATE.lock().unwrap().history.back().cloned()
+}
+
+/// Returns profiles of last finished frames, oldest first.
+pub fn frames() -> Vec<FrameProfile> {
+    STATE.lock().unwrap().history.iter().cloned().collect()
+}
+
+/// Writes given frames in Trace Event Format.
+pub fn write_chrome_trace<W: Write>(
+    frames: &[FrameProfile],
+    writer: &mut W,
+) -> std::io::Result<()> {
+    writeln!(writer, "{{\"traceEvents\":[")?;
+    let mut first = true;
+    for frame in frames {
+        frame.write_trace_events(writer, &mut first)?;
+    }
+    writeln!(writer, "]}}")
+}
+
+/// Saves every frame kept by profiler into a file which can be opened in chrome://tracing.
+pub fn save_chrome_trace<P: AsRef<Path>>(path: P) -> std::io::Result<()> {
+    let mut writer = BufWriter::new(File::create(path)?);
+    write_chrome_trace(&frames(), &mut writer)?;
+    writer.flush()
+}
+
+#[cfg(test)]
+mod test {
+    use crate::utils::frame_profiler::{self, write_chrome_trace};
+
+    #[test]
+    fn frame_profiler_test() {
+        frame_profiler::set_enabled(true);
+        frame_profiler::next_frame();
+        {
+            frame_scope!("Outer");
+            {
+                frame_scope!("Inner");
+            }
+            frame_scope!("Inner");
+        }
+        frame_profiler::next_frame();
+        frame_profiler::set_enabled(false);
+
+        let frame = frame_profiler::last_frame().unwrap();
+        let outer = frame
+            .roots()
+            .find(|&i| frame.samples[i].name == "Outer")
+            .unwrap();
+        let children = frame.children(outer).collect::<Vec<_>>();
+        assert_eq!(children.len(), 2);
+        assert!(children.iter().all(|&i| frame.samples[i].name == "Inner"));
+        assert!(frame.total_time("Inner") <= frame.samples[outer].duration);
+        assert!(frame.to_string().contains("        Inner"));
+
+        let mut trace = Vec::new();
+        write_chrome_trace(&[frame], &mut trace).unwrap();
+        let trace = String::from_utf8(trace).unwrap();
+        assert!(trace.starts_with("{\"traceEvents\":["));
+        assert!(trace.contains("\"name\":\"Outer\""));
+        assert!(trace.trim_end().ends_with("]}"));
+    }
+}
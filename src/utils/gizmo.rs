@@ -0,0 +1,653 @@
+//! Transform gizmos.
+//!
+//! Gizmo is an interactive widget in 3D viewport that allows to move, rotate or scale a scene
+//! node with mouse. It is drawn using [debug renderer](../../renderer/debug_renderer/index.html)
+//! and picked using rays made by camera, so it does not add any nodes to the scene and can be
+//! used both in editors and in in-game level tools.
+//!
+//! Translation and scaling can be constrained to a single axis or to a plane of two axes,
+//! rotation is always performed around one axis. Axes are taken either from world space or from
+//! global transform of target node, scaling always uses local axes of the node. Every operation
+//! can be snapped to given steps.
+//!
+//! Gizmo keeps constant size on screen, so it is equally easy to grab near and far objects.
+//!
+//! # Usage
+//!
+//! Typical usage is:
+//! - Call [Gizmo::set_target] when selection changes.
+//! - Call [Gizmo::begin_drag] when left mouse button is pressed, if it returns true mouse was
+//!   over gizmo and other picking must be skipped.
+//! - Call [Gizmo::drag] when mouse moves and [Gizmo::end_drag] when button is released.
+//! - Call [Gizmo::draw] every frame, debug renderer must be cleared before it.
+//!
+//! [Gizmo::pick] can be used to highlight hovered part of gizmo.
+
+use crate::{
+    core::{
+        color::Color,
+        math::{mat4::Mat4, quat::Quat, ray::Ray, vec2::Vec2, vec3::Vec3},
+        pool::Handle,
+    },
+    renderer::debug_renderer::{DebugRenderer, Line},
+    scene::{graph::Graph, node::Node},
+};
+
+/// Amount of line segments in a rotation ring.
+const RING_SEGMENTS: usize = 48;
+/// Size of plane handles relative to the size of gizmo.
+const PLANE_HANDLE_SIZE: f32 = 0.3;
+/// Distance from handle (relative to the size of gizmo) in which it is still picked.
+const PICK_TOLERANCE: f32 = 0.08;
+
+/// Operation performed by gizmo.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum GizmoMode {
+    /// Moves node.
+    Translate,
+    /// Rotates node.
+    Rotate,
+    /// Scales node.
+    Scale,
+}
+
+/// Coordinate system in which gizmo axes are defined.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum GizmoSpace {
+    /// Axes of the world.
+    World,
+    /// Axes of global transform of target node.
+    Local,
+}
+
+/// Part of the gizmo that constrains an operation.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum GizmoAxis {
+    /// X axis.
+    X,
+    /// Y axis.
+    Y,
+    /// Z axis.
+    Z,
+    /// Plane of X and Y axes. Not used by rotation.
+    XY,
+    /// Plane of Y and Z axes. Not used by rotation.
+    YZ,
+    /// Plane of Z and X axes. Not used by rotation.
+    ZX,
+}
+
+impl GizmoAxis {
+    /// Returns indices of axes that are affected by this part of gizmo.
+    fn axes(self) -> &'static [usize] {
+        match self {
+            GizmoAxis::X => &[0],
+            GizmoAxis::Y => &[1],
+            GizmoAxis::Z => &[2],
+            GizmoAxis::XY => &[0, 1],
+            GizmoAxis::YZ => &[1, 2],
+            GizmoAxis::ZX => &[2, 0],
+        }
+    }
+
+    fn is_plane(self) -> bool {
+        self.axes().len() == 2
+    }
+}
+
+/// Steps of snapping, `None` disables snapping of an operation.
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct GizmoSnapping {
+    /// Step of translation in world units.
+    pub translation: Option<f32>,
+    /// Step of rotation in degrees.
+    pub rotation: Option<f32>,
+    /// Step of scale factor.
+    pub scale: Option<f32>,
+}
+
+fn snap(value: f32, step: Option<f32>) -> f32 {
+    match step {
+        Some(step) if step > 0.0 => (value / step).round() * step,
+        _ => value,
+    }
+}
+
+/// Gizmo frame in world space.
+struct Frame {
+    origin: Vec3,
+    axes: [Vec3; 3],
+    size: f32,
+}
+
+struct DragState {
+    axis: GizmoAxis,
+    frame: Frame,
+    /// Point on the constraint where drag has started. For rotation it is a vector from the
+    /// origin of gizmo to the point on rotation plane.
+    start: Vec3,
+    initial_position: Vec3,
+    initial_rotation: Quat,
+    initial_scale: Vec3,
+}
+
+/// See module docs.
+pub struct Gizmo {
+    mode: GizmoMode,
+    space: GizmoSpace,
+    snapping: GizmoSnapping,
+    screen_size: f32,
+    target: Handle<Node>,
+    drag: Option<DragState>,
+}
+
+impl Default for Gizmo {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn closest_parameter_on_line(origin: Vec3, direction: Vec3, ray: &Ray) -> Option<f32> {
+    let w = origin - ray.origin;
+    let a = direction.dot(&direction);
+    let b = direction.dot(&ray.dir);
+    let c = ray.dir.dot(&ray.dir);
+    let d = direction.dot(&w);
+    let e = ray.dir.dot(&w);
+    let denominator = a * c - b * b;
+    // Ray is parallel to the line.
+    if denominator.abs() < std::f32::EPSILON {
+        None
+    } else {
+        Some((b * e - c * d) / denominator)
+    }
+}
+
+fn distance_to_segment(begin: Vec3, end: Vec3, ray: &Ray) -> f32 {
+    let direction = end - begin;
+    let t = closest_parameter_on_line(begin, direction, ray)
+        .unwrap_or(0.0)
+        .max(0.0)
+        .min(1.0);
+    let point = begin + direction.scale(t);
+    // Distance from point to the ray line.
+    let to_point = point - ray.origin;
+    let along = to_point.dot(&ray.dir) / ray.dir.dot(&ray.dir);
+    point.distance(&(ray.origin + ray.dir.scale(along.max(0.0))))
+}
+
+fn intersect_plane(origin: Vec3, normal: Vec3, ray: &Ray) -> Option<Vec3> {
+    let denominator = normal.dot(&ray.dir);
+    if denominator.abs() < std::f32::EPSILON {
+        None
+    } else {
+        let t = normal.dot(&(origin - ray.origin)) / denominator;
+        if t >= 0.0 {
+            Some(ray.origin + ray.dir.scale(t))
+        } else {
+            None
+        }
+    }
+}
+
+fn axis_color(index: usize) -> Color {
+    match index {
+        0 => Color::opaque(255, 0, 0),
+        1 => Color::opaque(0, 255, 0),
+        _ => Color::opaque(0, 0, 255),
+    }
+}
+
+impl Gizmo {
+    /// Creates new translation gizmo in world space without target and snapping.
+    pub fn new() -> Self {
+        Self {
+            mode: GizmoMode::Translate,
+            space: GizmoSpace::World,
+            snapping: Default::default(),
+            screen_size: 0.15,
+            target: Handle::NONE,
+            drag: None,
+        }
+    }
+
+    /// Sets node that is manipulated by gizmo, `Handle::NONE` hides gizmo. Cancels current
+    /// drag.
+    pub fn set_target(&mut self, target: Handle<Node>) -> &mut Self {
+        self.target = target;
+        self.drag = None;
+        self
+    }
+
+    /// Returns node that is manipulated by gizmo.
+    pub fn target(&self) -> Handle<Node> {
+        self.target
+    }
+
+    /// Sets operation of gizmo. Cancels current drag.
+    pub fn set_mode(&mut self, mode: GizmoMode) -> &mut Self {
+        self.mode = mode;
+        self.drag = None;
+        self
+    }
+
+    /// Returns operation of gizmo.
+    pub fn mode(&self) -> GizmoMode {
+        self.mode
+    }
+
+    /// Sets coordinate system of gizmo axes. Cancels current drag.
+    pub fn set_space(&mut self, space: GizmoSpace) -> &mut Self {
+        self.space = space;
+        self.drag = None;
+        self
+    }
+
+    /// Returns coordinate system of gizmo axes.
+    pub fn space(&self) -> GizmoSpace {
+        self.space
+    }
+
+    /// Sets snapping steps.
+    pub fn set_snapping(&mut self, snapping: GizmoSnapping) -> &mut Self {
+        self.snapping = snapping;
+        self
+    }
+
+    /// Returns snapping steps.
+    pub fn snapping(&self) -> GizmoSnapping {
+        self.snapping
+    }
+
+    /// Sets size of gizmo relative to distance from camera, default is 0.15.
+    pub fn set_screen_size(&mut self, size: f32) -> &mut Self {
+        self.screen_size = size.max(0.001);
+        self
+    }
+
+    /// Returns true if gizmo is being dragged.
+    pub fn is_dragging(&self) -> bool {
+        self.drag.is_some()
+    }
+
+    fn frame(&self, graph: &Graph, camera: Handle<Node>) -> Option<Frame> {
+        if !graph.is_valid_handle(self.target) || !graph.is_valid_handle(camera) {
+            return None;
+        }
+        let node = &graph[self.target];
+        let origin = node.global_position();
+        let axes = if self.space == GizmoSpace::Local || self.mode == GizmoMode::Scale {
+            [
+                node.side_vector()
+                    .normalized()
+                    .unwrap_or(Vec3::new(1.0, 0.0, 0.0)),
+                node.up_vector()
+                    .normalized()
+                    .unwrap_or(Vec3::new(0.0, 1.0, 0.0)),
+                node.look_vector()
+                    .normalized()
+                    .unwrap_or(Vec3::new(0.0, 0.0, 1.0)),
+            ]
+        } else {
+            [
+                Vec3::new(1.0, 0.0, 0.0),
+                Vec3::new(0.0, 1.0, 0.0),
+                Vec3::new(0.0, 0.0, 1.0),
+            ]
+        };
+        let size = graph[camera].global_position().distance(&origin) * self.screen_size;
+        Some(Frame {
+            origin,
+            axes,
+            size: size.max(std::f32::EPSILON),
+        })
+    }
+
+    fn make_ray(
+        graph: &Graph,
+        camera: Handle<Node>,
+        mouse_position: Vec2,
+        screen_size: Vec2,
+    ) -> Option<Ray> {
+        graph[camera]
+            .as_camera()
+            .map(|camera| camera.make_ray(mouse_position, screen_size))
+    }
+
+    fn pick_frame(&self, frame: &Frame, ray: &Ray) -> Option<GizmoAxis> {
+        let tolerance = frame.size * PICK_TOLERANCE;
+        let single = [GizmoAxis::X, GizmoAxis::Y, GizmoAxis::Z];
+
+        if self.mode == GizmoMode::Rotate {
+            // Pick closest ring.
+            return single
+                .iter()
+                .zip(frame.axes.iter())
+                .filter_map(|(&axis, &normal)| {
+                    intersect_plane(frame.origin, normal, ray).and_then(|point| {
+                        let error = (point.distance(&frame.origin) - frame.size).abs();
+                        if error <= tolerance {
+                            Some((axis, error))
+                        } else {
+                            None
+                        }
+                    })
+                })
+                .min_by(|a, b| a.1.partial_cmp(&b.1).unwrap())
+                .map(|(axis, _)| axis);
+        }
+
+        // Plane handles are smaller than axes, so they are picked first.
+        let handle_size = frame.size * PLANE_HANDLE_SIZE;
+        for &plane in [GizmoAxis::XY, GizmoAxis::YZ, GizmoAxis::ZX].iter() {
+            let (a, b) = (frame.axes[plane.axes()[0]], frame.axes[plane.axes()[1]]);
+            if let Some(point) = intersect_plane(frame.origin, a.cross(&b), ray) {
+                let offset = point - frame.origin;
+                let (u, v) = (offset.dot(&a), offset.dot(&b));
+                if u >= 0.0 && u <= handle_size && v >= 0.0 && v <= handle_size {
+                    return Some(plane);
+                }
+            }
+        }
+
+        single
+            .iter()
+            .zip(frame.axes.iter())
+            .map(|(&axis, &direction)| {
+                let end = frame.origin + direction.scale(frame.size);
+                (axis, distance_to_segment(frame.origin, end, ray))
+            })
+            .filter(|&(_, distance)| distance <= tolerance)
+            .min_by(|a, b| a.1.partial_cmp(&b.1).unwrap())
+            .map(|(axis, _)| axis)
+    }
+
+    /// Returns part of gizmo under mouse cursor, if any. Camera must be a camera node of the
+    /// same graph.
+    pub fn pick(
+        &self,
+        graph: &Graph,
+        camera: Handle<Node>,
+        mouse_position: Vec2,
+        screen_size: Vec2,
+    ) -> Option<GizmoAxis> {
+        let frame = self.frame(graph, camera)?;
+        let ray = Self::make_ray(graph, camera, mouse_position, screen_size)?;
+        self.pick_frame(&frame, &ray)
+    }
+
+    /// Returns point on constraint of given part of gizmo, see [DragState::start].
+    fn constraint_point(&self, axis: GizmoAxis, frame: &Frame, ray: &Ray) -> Option<Vec3> {
+        let axes = axis.axes();
+        if self.mode == GizmoMode::Rotate {
+            intersect_plane(frame.origin, frame.axes[axes[0]], ray)
+                .map(|point| point - frame.origin)
+        } else if axis.is_plane() {
+            let normal = frame.axes[axes[0]].cross(&frame.axes[axes[1]]);
+            intersect_plane(frame.origin, normal, ray)
+        } else {
+            let direction = frame.axes[axes[0]];
+            closest_parameter_on_line(frame.origin, direction, ray)
+                .map(|t| frame.origin + direction.scale(t))
+        }
+    }
+
+    /// Starts dragging if mouse is over gizmo. Returns true if drag has started.
+    pub fn begin_drag(
+        &mut self,
+        graph: &Graph,
+        camera: Handle<Node>,
+        mouse_position: Vec2,
+        screen_size: Vec2,
+    ) -> bool {
+        let frame = match self.frame(graph, camera) {
+            Some(frame) => frame,
+            None => return false,
+        };
+        let ray = match Self::make_ray(graph, camera, mouse_position, screen_size) {
+            Some(ray) => ray,
+            None => return false,
+        };
+        let axis = match self.pick_frame(&frame, &ray) {
+            Some(axis) => axis,
+            None => return false,
+        };
+        let start = match self.constraint_point(axis, &frame, &ray) {
+            Some(start) => start,
+            None => return false,
+        };
+        let transform = graph[self.target].local_transform();
+        self.drag = Some(DragState {
+            axis,
+            frame,
+            start,
+            initial_position: transform.position(),
+            initial_rotation: transform.rotation(),
+            initial_scale: transform.scale(),
+        });
+        true
+    }
+
+    /// Applies drag to target node, must be called when mouse moves. Does nothing if gizmo is
+    /// not dragged.
+    pub fn drag(
+        &mut self,
+        graph: &mut Graph,
+        camera: Handle<Node>,
+        mouse_position: Vec2,
+        screen_size: Vec2,
+    ) {
+        let drag = match self.drag.as_ref() {
+            Some(drag) if graph.is_valid_handle(self.target) => drag,
+            _ => return,
+        };
+        let ray = match Self::make_ray(graph, camera, mouse_position, screen_size) {
+            Some(ray) => ray,
+            None => return,
+        };
+        let current = match self.constraint_point(drag.axis, &drag.frame, &ray) {
+            Some(current) => current,
+            None => return,
+        };
+
+        // Offsets are calculated in world space and must be converted into space of parent.
+        let parent = graph[self.target].parent();
+        // Parent transform may be not invertible, then offsets are applied as is.
+        let inv_parent_transform = if graph.is_valid_handle(parent) {
+            graph[parent]
+                .global_transform()
+                .inverse()
+                .unwrap_or(Mat4::IDENTITY)
+        } else {
+            Mat4::IDENTITY
+        };
+
+        let frame = &drag.frame;
+        let axes = drag.axis.axes();
+        let transform = graph[self.target].local_transform_mut();
+        match self.mode {
+            GizmoMode::Translate => {
+                let offset = current - drag.start;
+                let mut world_offset = Vec3::ZERO;
+                for &i in axes {
+                    let amount = snap(offset.dot(&frame.axes[i]), self.snapping.translation);
+                    world_offset += frame.axes[i].scale(amount);
+                }
+                transform.set_position(
+                    inv_parent_transform.transform_vector(frame.origin + world_offset),
+                );
+            }
+            GizmoMode::Rotate => {
+                let normal = frame.axes[axes[0]];
+                let angle = drag
+                    .start
+                    .cross(&current)
+                    .dot(&normal)
+                    .atan2(drag.start.dot(&current));
+                let angle = snap(angle.to_degrees(), self.snapping.rotation).to_radians();
+                let rotation = if self.space == GizmoSpace::Local {
+                    let mut local_axis = Vec3::ZERO;
+                    match axes[0] {
+                        0 => local_axis.x = 1.0,
+                        1 => local_axis.y = 1.0,
+                        _ => local_axis.z = 1.0,
+                    }
+                    drag.initial_rotation * Quat::from_axis_angle(local_axis, angle)
+                } else {
+                    let parent_axis = inv_parent_transform.transform_vector(normal)
+                        - inv_parent_transform.transform_vector(Vec3::ZERO);
+                    let parent_axis = parent_axis.normalized().unwrap_or(normal);
+                    Quat::from_axis_angle(parent_axis, angle) * drag.initial_rotation
+                };
+                transform.set_rotation(rotation);
+            }
+            GizmoMode::Scale => {
+                let offset = current - drag.start;
+                let mut scale = drag.initial_scale;
+                for &i in axes {
+                    let factor = 1.0 + offset.dot(&frame.axes[i]) / frame.size;
+                    let factor = snap(factor, self.snapping.scale).max(0.001);
+                    match i {
+                        0 => scale.x *= factor,
+                        1 => scale.y *= factor,
+                        _ => scale.z *= factor,
+                    }
+                }
+                transform.set_scale(scale);
+            }
+        }
+    }
+
+    /// Finishes dragging, target node keeps its new transform.
+    pub fn end_drag(&mut self) {
+        self.drag = None;
+    }
+
+    /// Cancels dragging and restores initial transform of target node.
+    pub fn cancel_drag(&mut self, graph: &mut Graph) {
+        if let Some(drag) = self.drag.take() {
+            if graph.is_valid_handle(self.target) {
+                graph[self.target]
+                    .local_transform_mut()
+                    .set_position(drag.initial_position)
+                    .set_rotation(drag.initial_rotation)
+                    .set_scale(drag.initial_scale);
+            }
+        }
+    }
+
+    /// Draws gizmo using debug renderer. Dragged or hovered part of gizmo is highlighted.
+    pub fn draw(
+        &self,
+        graph: &Graph,
+        camera: Handle<Node>,
+        hovered: Option<GizmoAxis>,
+        debug_renderer: &mut DebugRenderer,
+    ) {
+        let frame = match self.frame(graph, camera) {
+            Some(frame) => frame,
+            None => return,
+        };
+        let active = self.drag.as_ref().map(|drag| drag.axis).or(hovered);
+        let highlight = Color::opaque(255, 255, 0);
+        let color_of = |axis: GizmoAxis, index: usize| {
+            if active == Some(axis) {
+                highlight
+            } else {
+                axis_color(index)
+            }
+        };
+        let single = [GizmoAxis::X, GizmoAxis::Y, GizmoAxis::Z];
+
+        if self.mode == GizmoMode::Rotate {
+            for (index, &axis) in single.iter().enumerate() {
+                let normal = frame.axes[index];
+                let u = frame.axes[(index + 1) % 3];
+                let v = normal.cross(&u);
+                let color = color_of(axis, index);
+                let point = |i: usize| {
+                    let angle = i as f32 / RING_SEGMENTS as f32 * 2.0 * std::f32::consts::PI;
+                    frame.origin + (u.scale(angle.cos()) + v.scale(angle.sin())).scale(frame.size)
+                };
+                for i in 0..RING_SEGMENTS {
+                    debug_renderer.add_line(Line {
+                        begin: point(i),
+                        end: point(i + 1),
+                        color,
+                    });
+                }
+            }
+            return;
+        }
+
+        for (index, &axis) in single.iter().enumerate() {
+            let end = frame.origin + frame.axes[index].scale(frame.size);
+            let color = color_of(axis, index);
+            debug_renderer.add_line(Line {
+                begin: frame.origin,
+                end,
+                color,
+            });
+            // Tip of the axis: arrow for translation, box for scale.
+            let tip = frame.size * 0.1;
+            let (a, b) = (frame.axes[(index + 1) % 3], frame.axes[(index + 2) % 3]);
+            for &side in [a, b, -a, -b].iter() {
+                let begin = if self.mode == GizmoMode::Translate {
+                    end - frame.axes[index].scale(tip * 2.0) + side.scale(tip)
+                } else {
+                    end + side.scale(tip)
+                };
+                debug_renderer.add_line(Line { begin, end, color });
+            }
+        }
+
+        let handle_size = frame.size * PLANE_HANDLE_SIZE;
+        for &plane in [GizmoAxis::XY, GizmoAxis::YZ, GizmoAxis::ZX].iter() {
+            let (i, j) = (plane.axes()[0], plane.axes()[1]);
+            let a = frame.origin + frame.axes[i].scale(handle_size);
+            let b = frame.origin + frame.axes[j].scale(handle_size);
+            let corner = a + frame.axes[j].scale(handle_size);
+            let color = if active == Some(plane) {
+                highlight
+            } else {
+                // Plane handle has color of axis perpendicular to it.
+                axis_color(3 - i - j)
+            };
+            debug_renderer.add_line(Line {
+                begin: a,
+                end: corner,
+                color,
+            });
+            debug_renderer.add_line(Line {
+                begin: b,
+                end: corner,
+                color,
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        core::math::{ray::Ray, vec3::Vec3},
+        utils::gizmo::{distance_to_segment, intersect_plane, snap},
+    };
+
+    #[test]
+    fn gizmo_math_test() {
+        assert_eq!(snap(0.74, Some(0.5)), 0.5);
+        assert_eq!(snap(0.76, Some(0.5)), 1.0);
+        assert_eq!(snap(0.76, None), 0.76);
+
+        let ray = Ray {
+            origin: Vec3::new(0.5, 0.0, -5.0),
+            dir: Vec3::new(0.0, 0.0, 10.0),
+        };
+        let point = intersect_plane(Vec3::ZERO, Vec3::new(0.0, 0.0, 1.0), &ray).unwrap();
+        assert!(point.distance(&Vec3::new(0.5, 0.0, 0.0)) < 0.0001);
+        let distance =
+            distance_to_segment(Vec3::new(0.0, 1.0, 0.0), Vec3::new(2.0, 1.0, 0.0), &ray);
+        assert!((distance - 1.0).abs() < 0.0001);
+    }
+}
@@ -0,0 +1,379 @@
+//! Job system - pool of worker threads that execute small tasks (jobs).
+//!
+//! Every worker has its own queue of jobs, jobs spawned by a worker go to its own queue, jobs
+//! spawned by other threads are distributed between queues. When worker's queue is empty it
+//! steals jobs from other workers, so all workers are busy while there is any work.
+//!
+//! Engine has two shared job systems. [Global](JobSystem::global) job system runs short jobs
+//! which must be finished within a frame, for example update of scenes. [IO](JobSystem::io)
+//! job system runs long blocking jobs - reading and decoding of resources, so loading of a
+//! level never delays jobs of a frame. Games can use both of them as well:
+//!
+//! ```no_run
+//! use rg3d::utils::jobs::JobSystem;
+//!
+//! let jobs = JobSystem::global();
+//!
+//! // Run a task in background and get its result later.
+//! let handle = jobs.spawn(|| (0..1000u64).sum::<u64>());
+//! assert_eq!(handle.join(), 499500);
+//!
+//! // Process every element of a slice in parallel.
+//! let mut values = vec![1.0f32; 10000];
+//! jobs.parallel_for(&mut values, |v| *v *= 2.0);
+//!
+//! // Read a file without blocking frame jobs.
+//! let data = JobSystem::io().spawn(|| std::fs::read("data/level.bin"));
+//! ```
+
+use std::{
+    cell::Cell,
+    collections::VecDeque,
+    panic::{self, AssertUnwindSafe},
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        mpsc::{self, Receiver, TryRecvError},
+        Arc, Condvar, Mutex,
+    },
+    thread::{self, JoinHandle as ThreadJoinHandle},
+    time::Duration,
+};
+
+type Job = Box<dyn FnOnce() + Send + 'static>;
+
+lazy_static! {
+    static ref GLOBAL: JobSystem = JobSystem::with_name("rg3d-worker", default_thread_count());
+    static ref IO: JobSystem = JobSystem::with_name("rg3d-io", default_thread_count() / 2);
+}
+
+thread_local! {
+    /// Identifier of a job system and index of a worker of the current thread, if the thread
+    /// is a worker.
+    static WORKER: Cell<Option<(usize, usize)>> = Cell::new(None);
+}
+
+fn default_thread_count() -> usize {
+    num_cpus::get()
+}
+
+struct Shared {
+    queues: Vec<Mutex<VecDeque<Job>>>,
+    /// Amount of jobs in all queues, workers sleep while it is zero.
+    queued: Mutex<usize>,
+    wakeup: Condvar,
+    shutdown: AtomicBool,
+    next_queue: AtomicUsize,
+}
+
+impl Shared {
+    fn id(&self) -> usize {
+        self as *const _ as usize
+    }
+
+    /// Returns index of a worker of this job system that runs on current thread.
+    fn current_worker(&self) -> Option<usize> {
+        let id = self.id();
+        WORKER.with(|worker| match worker.get() {
+            Some((worker_id, index)) if worker_id == id => Some(index),
+            _ => None,
+        })
+    }
+
+    fn push(&self, job: Job) {
+        let index = self
+            .current_worker()
+            .unwrap_or_else(|| self.next_queue.fetch_add(1, Ordering::Relaxed) % self.queues.len());
+        // Counter is increased before job is pushed so it never goes below zero when some
+        // worker takes the job right after push.
+        *self.queued.lock().unwrap() += 1;
+        self.queues[index].lock().unwrap().push_back(job);
+        self.wakeup.notify_one();
+    }
+
+    /// Takes a job from given queue, or steals it from other queues.
+    fn pop(&self, index: usize) -> Option<Job> {
+        let job = self.queues[index].lock().unwrap().pop_front().or_else(|| {
+            (1..self.queues.len())
+                .map(|offset| (index + offset) % self.queues.len())
+                .find_map(|other| self.queues[other].lock().unwrap().pop_back())
+        });
+        if job.is_some() {
+            *self.queued.lock().unwrap() -= 1;
+        }
+        job
+    }
+
+    fn worker_loop(&self, index: usize) {
+        WORKER.with(|worker| worker.set(Some((self.id(), index))));
+        loop {
+            if let Some(job) = self.pop(index) {
+                job();
+                continue;
+            }
+            let mut queued = self.queued.lock().unwrap();
+            while *queued == 0 && !self.shutdown.load(Ordering::SeqCst) {
+                queued = self.wakeup.wait(queued).unwrap();
+            }
+            if *queued == 0 {
+                // Shutdown and every job is done.
+                break;
+            }
+        }
+    }
+}
+
+/// Handle of a spawned job, allows to wait for its result.
+pub struct JobHandle<T> {
+    receiver: Receiver<thread::Result<T>>,
+    shared: Arc<Shared>,
+}
+
+impl<T> JobHandle<T> {
+    /// Waits until job is done and returns its result. If job has panicked, panic is
+    /// propagated to the calling thread. When called from a worker, the worker executes other
+    /// jobs while waiting, so jobs can wait for jobs they've spawned without deadlocks.
+    pub fn join(self) -> T {
+        let worker = self.shared.current_worker();
+        loop {
+            let result = match worker {
+                Some(index) => match self.receiver.try_recv() {
+                    Ok(result) => result,
+                    Err(TryRecvError::Empty) => {
+                        match self.shared.pop(index) {
+                            Some(job) => job(),
+                            None => thread::yield_now(),
+                        }
+                        continue;
+                    }
+                    Err(TryRecvError::Disconnected) => panic!("Job was dropped without result!"),
+                },
+                None => match self.receiver.recv_timeout(Duration::from_secs(1)) {
+                    Ok(result) => result,
+                    Err(mpsc::RecvTimeoutError::Timeout) => continue,
+                    Err(mpsc::RecvTimeoutError::Disconnected) => {
+                        panic!("Job was dropped without result!")
+                    }
+                },
+            };
+            return result.unwrap_or_else(|payload| panic::resume_unwind(payload));
+        }
+    }
+}
+
+/// Pool of worker threads. See module docs.
+pub struct JobSystem {
+    shared: Arc<Shared>,
+    threads: Vec<ThreadJoinHandle<()>>,
+    serial: AtomicBool,
+}
+
+impl JobSystem {
+    /// Creates new job system with given amount of worker threads.
+    pub fn new(thread_count: usize) -> Self {
+        Self::with_name("rg3d-worker", thread_count)
+    }
+
+    /// Creates new job system with given amount of worker threads, threads are named
+    /// `<name>-<index>` which is handy for debuggers and profilers.
+    pub fn with_name(name: &str, thread_count: usize) -> Self {
+        let thread_count = thread_count.max(1);
+        let shared = Arc::new(Shared {
+            queues: (0..thread_count).map(|_| Default::default()).collect(),
+            queued: Mutex::new(0),
+            wakeup: Condvar::new(),
+            shutdown: AtomicBool::new(false),
+            next_queue: AtomicUsize::new(0),
+        });
+        let threads = (0..thread_count)
+            .map(|index| {
+                let shared = shared.clone();
+                thread::Builder::new()
+                    .name(format!("{}-{}", name, index))
+                    .spawn(move || shared.worker_loop(index))
+                    .unwrap()
+            })
+            .collect();
+        Self {
+            shared,
+            threads,
+            serial: AtomicBool::new(false),
+        }
+    }
+
+    /// Returns job system which is shared across the engine, it has one worker per logical
+    /// CPU core. It is meant for short jobs that are done within a frame, do not block its
+    /// workers with file or network IO - use [io](Self::io) instead.
+    pub fn global() -> &'static JobSystem {
+        &GLOBAL
+    }
+
+    /// Returns job system for long blocking jobs like loading of resources. It has its own
+    /// workers (half of logical CPU cores), so such jobs never delay jobs of
+    /// [global](Self::global) job system.
+    pub fn io() -> &'static JobSystem {
+        &IO
+    }
+
+    /// Returns amount of worker threads.
+    pub fn thread_count(&self) -> usize {
+        self.threads.len()
+    }
+
+    /// Forces [parallel_for](Self::parallel_for) to process every element on the calling
+    /// thread, in order. Engine updates scenes using [global](Self::global) job system, so
+    /// serial mode of it makes update of scenes independent from amount of CPU cores, which
+    /// is required by [deterministic simulation](crate::engine::determinism). Spawned jobs
+    /// are not affected.
+    pub fn set_serial(&self, serial: bool) {
+        self.serial.store(serial, Ordering::SeqCst);
+    }
+
+    /// Returns true if parallel loops are forced to run serially.
+    pub fn is_serial(&self) -> bool {
+        self.serial.load(Ordering::SeqCst)
+    }
+
+    /// Spawns new job, it will be executed by one of the workers. Returned handle can be used
+    /// to wait for result, or can be dropped if result is not needed.
+    pub fn spawn<F, T>(&self, func: F) -> JobHandle<T>
+    where
+        F: FnOnce() -> T + Send + 'static,
+        T: Send + 'static,
+    {
+        let (sender, receiver) = mpsc::channel();
+        self.shared.push(Box::new(move || {
+            // Receiver could be dropped already, it is fine.
+            let _ = sender.send(panic::catch_unwind(AssertUnwindSafe(func)));
+        }));
+        JobHandle {
+            receiver,
+            shared: self.shared.clone(),
+        }
+    }
+
+    /// Calls given function for every element of a slice in parallel and waits until all
+    /// elements are processed. Slice is split into chunks, one of them is processed on the
+    /// calling thread. See also [set_serial](Self::set_serial).
+    pub fn parallel_for<T, F>(&self, items: &mut [T], func: F)
+    where
+        T: Send,
+        F: Fn(&mut T) + Sync,
+    {
+        let chunk_count = (self.thread_count() * 4).min(items.len());
+        if chunk_count <= 1 || self.is_serial() {
+            items.iter_mut().for_each(func);
+            return;
+        }
+        let chunk_size = (items.len() + chunk_count - 1) / chunk_count;
+
+        let func = &func;
+        let mut chunks = items.chunks_mut(chunk_size);
+        let first = chunks.next().unwrap();
+        let handles = chunks
+            .map(|chunk| {
+                let job: Box<dyn FnOnce() + Send + '_> = Box::new(move || {
+                    chunk.iter_mut().for_each(func);
+                });
+                // SAFETY: Job borrows the slice and the function, both outlive the job,
+                // because every job is joined below before return, even if some of them
+                // panicked.
+                let job: Job = unsafe { std::mem::transmute(job) };
+                self.spawn(job)
+            })
+            .collect::<Vec<_>>();
+
+        let first_result = panic::catch_unwind(AssertUnwindSafe(|| {
+            first.iter_mut().for_each(func);
+        }));
+        let results = handles
+            .into_iter()
+            .map(|handle| panic::catch_unwind(AssertUnwindSafe(|| handle.join())))
+            .collect::<Vec<_>>();
+
+        for result in std::iter::once(first_result).chain(results) {
+            if let Err(payload) = result {
+                panic::resume_unwind(payload);
+            }
+        }
+    }
+}
+
+impl Drop for JobSystem {
+    fn drop(&mut self) {
+        // Lock is needed so no worker misses the notification between its check and wait.
+        {
+            let _queued = self.shared.queued.lock().unwrap();
+            self.shared.shutdown.store(true, Ordering::SeqCst);
+            self.shared.wakeup.notify_all();
+        }
+        for thread in self.threads.drain(..) {
+            let _ = thread.join();
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::utils::jobs::JobSystem;
+    use std::sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    };
+
+    #[test]
+    fn spawn_join_test() {
+        let jobs = JobSystem::new(3);
+        let counter = Arc::new(AtomicUsize::new(0));
+        let handles = (0..100)
+            .map(|i| {
+                let counter = counter.clone();
+                jobs.spawn(move || {
+                    counter.fetch_add(1, Ordering::SeqCst);
+                    i * 2
+                })
+            })
+            .collect::<Vec<_>>();
+        let sum: usize = handles.into_iter().map(|h| h.join()).sum();
+        assert_eq!(sum, 9900);
+        assert_eq!(counter.load(Ordering::SeqCst), 100);
+    }
+
+    #[test]
+    fn nested_join_test() {
+        let jobs = Arc::new(JobSystem::new(1));
+        let inner = jobs.clone();
+        // Single worker must not deadlock when a job waits for a job it has spawned.
+        let handle = jobs.spawn(move || inner.spawn(|| 21).join() * 2);
+        assert_eq!(handle.join(), 42);
+    }
+
+    #[test]
+    fn parallel_for_test() {
+        let jobs = JobSystem::new(4);
+        let mut values = (0..1000).collect::<Vec<usize>>();
+        jobs.parallel_for(&mut values, |v| *v *= 3);
+        assert!(values.iter().enumerate().all(|(i, v)| *v == i * 3));
+    }
+
+    #[test]
+    fn serial_parallel_for_test() {
+        let jobs = JobSystem::new(4);
+        jobs.set_serial(true);
+        let thread = std::thread::current().id();
+        let mut values = vec![0; 100];
+        jobs.parallel_for(&mut values, |v| {
+            assert_eq!(std::thread::current().id(), thread);
+            *v += 1;
+        });
+        assert!(values.iter().all(|v| *v == 1));
+    }
+
+    #[test]
+    fn panic_propagation_test() {
+        let jobs = JobSystem::new(2);
+        let handle = jobs.spawn(|| panic!("job panic"));
+        assert!(std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| handle.join())).is_err());
+        // Worker survives panic of a job.
+        assert_eq!(jobs.spawn(|| 1).join(), 1);
+    }
+}
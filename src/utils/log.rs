@@ -1,13 +1,19 @@
-//! Simple logger, it writes in file and in console at the same time.
+//! Simple logger, it writes in file and in console at the same time. Last messages are also
+//! kept in memory, they are included in crash reports.
 
 use std::{
+    collections::VecDeque,
     fs::File,
     io::{self, Write},
     sync::Mutex,
 };
 
+/// Amount of last messages kept in memory.
+const TAIL_SIZE: usize = 100;
+
 lazy_static! {
     static ref LOG_FILE: Mutex<File> = Mutex::new(File::create("rg3d.log").unwrap());
+    static ref LOG_TAIL: Mutex<VecDeque<String>> = Mutex::new(VecDeque::with_capacity(TAIL_SIZE));
 }
 
 /// See module docs.
@@ -17,7 +23,16 @@ impl Log {
     /// Writes string into console and into file.
     pub fn write(msg: String) {
         let _ = io::stdout().write_all(msg.as_bytes());
-        let _ = LOG_FILE.lock().unwrap().write_all(msg.as_bytes());
+        // Log can be used from panic hook, so poisoned mutex must not cause another panic.
+        let _ = LOG_FILE
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .write_all(msg.as_bytes());
+        let mut tail = LOG_TAIL.lock().unwrap_or_else(|e| e.into_inner());
+        if tail.len() == TAIL_SIZE {
+            tail.pop_front();
+        }
+        tail.push_back(msg);
     }
 
     /// Writes line into console and into file.
@@ -25,4 +40,14 @@ impl Log {
         msg.push('\n');
         Self::write(msg)
     }
+
+    /// Returns last messages, oldest first.
+    pub fn tail() -> Vec<String> {
+        LOG_TAIL
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .iter()
+            .cloned()
+            .collect()
+    }
 }
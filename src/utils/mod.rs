@@ -3,9 +3,17 @@
 //! Utilities module provides set of commonly used algorithms.
 
 pub mod astar;
+pub mod behavior;
+pub mod binding;
+pub mod curve;
+pub mod frame_profiler;
+pub mod gizmo;
+pub mod jobs;
 pub mod lightmap;
 pub mod log;
 pub mod navmesh;
+pub mod noise;
+pub mod random;
 pub mod raw_mesh;
 pub mod uvgen;
 
@@ -13,8 +21,11 @@ use crate::gui::draw;
 use crate::resource::texture::Texture;
 use crate::{
     core::math::vec2::Vec2,
-    event::{ElementState, ModifiersState, MouseScrollDelta, VirtualKeyCode, WindowEvent},
-    gui::message::{ButtonState, KeyCode, KeyboardModifiers, OsEvent},
+    event::{
+        ElementState, ModifiersState, MouseScrollDelta, Touch, TouchPhase, VirtualKeyCode,
+        WindowEvent,
+    },
+    gui::message::{ButtonState, KeyCode, KeyboardModifiers, MouseButton, OsEvent},
     physics::static_geometry::{StaticGeometry, StaticTriangle},
     scene::mesh::Mesh,
 };
@@ -311,6 +322,28 @@ pub fn translate_event(event: &WindowEvent) -> Option<OsEvent> {
     }
 }
 
+/// Translates touch to rg3d-ui events. User interface has single pointer, so touch is
+/// translated into cursor motion and left mouse button. Only one finger should be passed
+/// here, for example the first one of
+/// [InputMap::touches](../input/struct.InputMap.html#method.touches).
+pub fn translate_touch(touch: &Touch) -> Vec<OsEvent> {
+    let mut events = vec![OsEvent::CursorMoved {
+        position: Vec2::new(touch.location.x as f32, touch.location.y as f32),
+    }];
+    let state = match touch.phase {
+        TouchPhase::Started => Some(ButtonState::Pressed),
+        TouchPhase::Moved => None,
+        TouchPhase::Ended | TouchPhase::Cancelled => Some(ButtonState::Released),
+    };
+    if let Some(state) = state {
+        events.push(OsEvent::MouseInput {
+            button: MouseButton::Left,
+            state,
+        });
+    }
+    events
+}
+
 /// Translates keyboard modifiers to rg3d-ui keyboard modifiers.
 pub fn translate_keyboard_modifiers(modifiers: ModifiersState) -> KeyboardModifiers {
     KeyboardModifiers {
@@ -0,0 +1,488 @@
+//! Procedural noise.
+//!
+//! [Noise] is a set of coherent noise functions - Perlin, simplex and Worley (cellular) noise
+//! in one, two and three dimensions. Noise is defined by a seed, same seed always gives same
+//! values, so procedural content can be re-generated instead of being stored. [Fbm] sums
+//! several octaves of noise to get natural looking details - it is a common base of
+//! height maps, clouds, turbulence of particles, etc.
+//!
+//! # Example
+//!
+//! ```
+//! use rg3d::utils::noise::{Fbm, Noise, NoiseKind};
+//!
+//! let noise = Noise::new(42);
+//! let fbm = Fbm {
+//!     kind: NoiseKind::Simplex,
+//!     octaves: 5,
+//!     frequency: 0.01,
+//!     ..Default::default()
+//! };
+//! let height = fbm.get2(&noise, 123.0, 456.0) * 10.0;
+//! ```
+
+use rand::{rngs::StdRng, seq::SliceRandom, SeedableRng};
+
+/// Type of noise function.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum NoiseKind {
+    /// Classic gradient noise. Values are in approximately [-1; 1] range.
+    Perlin,
+    /// Gradient noise on simplex grid, it has less directional artifacts than Perlin noise
+    /// and is faster in higher dimensions. Values are in approximately [-1; 1] range.
+    Simplex,
+    /// Distance to closest feature point of cellular grid, gives cell-like patterns. Values
+    /// are in [0; 1] range most of the time but may slightly exceed 1.
+    Worley,
+}
+
+impl Default for NoiseKind {
+    fn default() -> Self {
+        NoiseKind::Perlin
+    }
+}
+
+/// Gradients for 2D and 3D noise - middles of edges of a cube.
+const GRADIENTS: [[f32; 3]; 12] = [
+    [1.0, 1.0, 0.0],
+    [-1.0, 1.0, 0.0],
+    [1.0, -1.0, 0.0],
+    [-1.0, -1.0, 0.0],
+    [1.0, 0.0, 1.0],
+    [-1.0, 0.0, 1.0],
+    [1.0, 0.0, -1.0],
+    [-1.0, 0.0, -1.0],
+    [0.0, 1.0, 1.0],
+    [0.0, -1.0, 1.0],
+    [0.0, 1.0, -1.0],
+    [0.0, -1.0, -1.0],
+];
+
+/// Seeded source of noise. See module docs.
+#[derive(Clone, Debug)]
+pub struct Noise {
+    seed: u64,
+    /// Permutation of 0..256 repeated twice to avoid wrapping of indices.
+    permutation: Vec<u8>,
+}
+
+impl Default for Noise {
+    fn default() -> Self {
+        Self::new(0)
+    }
+}
+
+fn fade(t: f32) -> f32 {
+    t * t * t * (t * (t * 6.0 - 15.0) + 10.0)
+}
+
+fn lerp(a: f32, b: f32, t: f32) -> f32 {
+    a + (b - a) * t
+}
+
+fn grad1(hash: u8, x: f32) -> f32 {
+    let gradient = 1.0 + (hash & 7) as f32;
+    if hash & 8 == 0 {
+        gradient * x
+    } else {
+        -gradient * x
+    }
+}
+
+fn grad2(hash: u8, x: f32, y: f32) -> f32 {
+    let g = &GRADIENTS[hash as usize % 12];
+    g[0] * x + g[1] * y
+}
+
+fn grad3(hash: u8, x: f32, y: f32, z: f32) -> f32 {
+    let g = &GRADIENTS[hash as usize % 12];
+    g[0] * x + g[1] * y + g[2] * z
+}
+
+impl Noise {
+    /// Creates new noise with given seed.
+    pub fn new(seed: u64) -> Self {
+        let mut permutation = (0..=255).collect::<Vec<u8>>();
+        permutation.shuffle(&mut StdRng::seed_from_u64(seed));
+        let copy = permutation.clone();
+        permutation.extend_from_slice(&copy);
+        Self { seed, permutation }
+    }
+
+    /// Returns seed of the noise.
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    fn hash(&self, index: i32) -> u8 {
+        self.permutation[(index & 255) as usize]
+    }
+
+    fn hash2(&self, i: i32, j: i32) -> u8 {
+        self.permutation[(i & 255) as usize + self.hash(j) as usize]
+    }
+
+    fn hash3(&self, i: i32, j: i32, k: i32) -> u8 {
+        self.permutation[(i & 255) as usize + self.hash2(j, k) as usize]
+    }
+
+    /// Returns value of noise of given kind at a point on a line.
+    pub fn sample1(&self, kind: NoiseKind, x: f32) -> f32 {
+        match kind {
+            NoiseKind::Perlin => self.perlin1(x),
+            NoiseKind::Simplex => self.simplex1(x),
+            NoiseKind::Worley => self.worley1(x),
+        }
+    }
+
+    /// Returns value of noise of given kind at a point on a plane.
+    pub fn sample2(&self, kind: NoiseKind, x: f32, y: f32) -> f32 {
+        match kind {
+            NoiseKind::Perlin => self.perlin2(x, y),
+            NoiseKind::Simplex => self.simplex2(x, y),
+            NoiseKind::Worley => self.worley2(x, y),
+        }
+    }
+
+    /// Returns value of noise of given kind at a point in space.
+    pub fn sample3(&self, kind: NoiseKind, x: f32, y: f32, z: f32) -> f32 {
+        match kind {
+            NoiseKind::Perlin => self.perlin3(x, y, z),
+            NoiseKind::Simplex => self.simplex3(x, y, z),
+            NoiseKind::Worley => self.worley3(x, y, z),
+        }
+    }
+
+    /// One-dimensional Perlin noise.
+    pub fn perlin1(&self, x: f32) -> f32 {
+        let i = x.floor() as i32;
+        let x = x - x.floor();
+        let u = fade(x);
+        // Maximal gradient is 8 and maximal value is reached in the middle of the cell.
+        0.25 * lerp(grad1(self.hash(i), x), grad1(self.hash(i + 1), x - 1.0), u)
+    }
+
+    /// Two-dimensional Perlin noise.
+    pub fn perlin2(&self, x: f32, y: f32) -> f32 {
+        let (i, j) = (x.floor() as i32, y.floor() as i32);
+        let (x, y) = (x - x.floor(), y - y.floor());
+        let (u, v) = (fade(x), fade(y));
+        lerp(
+            lerp(
+                grad2(self.hash2(i, j), x, y),
+                grad2(self.hash2(i + 1, j), x - 1.0, y),
+                u,
+            ),
+            lerp(
+                grad2(self.hash2(i, j + 1), x, y - 1.0),
+                grad2(self.hash2(i + 1, j + 1), x - 1.0, y - 1.0),
+                u,
+            ),
+            v,
+        )
+    }
+
+    /// Three-dimensional Perlin noise.
+    pub fn perlin3(&self, x: f32, y: f32, z: f32) -> f32 {
+        let (i, j, k) = (x.floor() as i32, y.floor() as i32, z.floor() as i32);
+        let (x, y, z) = (x - x.floor(), y - y.floor(), z - z.floor());
+        let (u, v, w) = (fade(x), fade(y), fade(z));
+        let corner = |di: i32, dj: i32, dk: i32| {
+            grad3(
+                self.hash3(i + di, j + dj, k + dk),
+                x - di as f32,
+                y - dj as f32,
+                z - dk as f32,
+            )
+        };
+        lerp(
+            lerp(
+                lerp(corner(0, 0, 0), corner(1, 0, 0), u),
+                lerp(corner(0, 1, 0), corner(1, 1, 0), u),
+                v,
+            ),
+            lerp(
+                lerp(corner(0, 0, 1), corner(1, 0, 1), u),
+                lerp(corner(0, 1, 1), corner(1, 1, 1), u),
+                v,
+            ),
+            w,
+        )
+    }
+
+    /// One-dimensional simplex noise.
+    pub fn simplex1(&self, x: f32) -> f32 {
+        let i = x.floor() as i32;
+        let x0 = x - x.floor();
+        let x1 = x0 - 1.0;
+        let contribution = |hash: u8, x: f32| {
+            let t = 1.0 - x * x;
+            let t2 = t * t;
+            t2 * t2 * grad1(hash, x)
+        };
+        // Scale to fit into [-1; 1].
+        0.395 * (contribution(self.hash(i), x0) + contribution(self.hash(i + 1), x1))
+    }
+
+    /// Two-dimensional simplex noise.
+    pub fn simplex2(&self, x: f32, y: f32) -> f32 {
+        let f2 = 0.5 * (3.0f32.sqrt() - 1.0);
+        let g2 = (3.0 - 3.0f32.sqrt()) / 6.0;
+
+        // Skew input space to find simplex cell.
+        let s = (x + y) * f2;
+        let i = (x + s).floor();
+        let j = (y + s).floor();
+        let t = (i + j) * g2;
+        let x0 = x - (i - t);
+        let y0 = y - (j - t);
+
+        // Find out which of two triangles of the cell contains the point.
+        let (i1, j1) = if x0 > y0 { (1, 0) } else { (0, 1) };
+        let x1 = x0 - i1 as f32 + g2;
+        let y1 = y0 - j1 as f32 + g2;
+        let x2 = x0 - 1.0 + 2.0 * g2;
+        let y2 = y0 - 1.0 + 2.0 * g2;
+
+        let (i, j) = (i as i32, j as i32);
+        let contribution = |hash: u8, x: f32, y: f32| {
+            let t = 0.5 - x * x - y * y;
+            if t < 0.0 {
+                0.0
+            } else {
+                let t2 = t * t;
+                t2 * t2 * grad2(hash, x, y)
+            }
+        };
+        70.0 * (contribution(self.hash2(i, j), x0, y0)
+            + contribution(self.hash2(i + i1, j + j1), x1, y1)
+            + contribution(self.hash2(i + 1, j + 1), x2, y2))
+    }
+
+    /// Three-dimensional simplex noise.
+    pub fn simplex3(&self, x: f32, y: f32, z: f32) -> f32 {
+        let f3 = 1.0 / 3.0;
+        let g3 = 1.0 / 6.0;
+
+        let s = (x + y + z) * f3;
+        let i = (x + s).floor();
+        let j = (y + s).floor();
+        let k = (z + s).floor();
+        let t = (i + j + k) * g3;
+        let x0 = x - (i - t);
+        let y0 = y - (j - t);
+        let z0 = z - (k - t);
+
+        // Find out which of six tetrahedrons of the cell contains the point.
+        let ((i1, j1, k1), (i2, j2, k2)) = if x0 >= y0 {
+            if y0 >= z0 {
+                ((1, 0, 0), (1, 1, 0))
+            } else if x0 >= z0 {
+                ((1, 0, 0), (1, 0, 1))
+            } else {
+                ((0, 0, 1), (1, 0, 1))
+            }
+        } else if y0 < z0 {
+            ((0, 0, 1), (0, 1, 1))
+        } else if x0 < z0 {
+            ((0, 1, 0), (0, 1, 1))
+        } else {
+            ((0, 1, 0), (1, 1, 0))
+        };
+
+        let x1 = x0 - i1 as f32 + g3;
+        let y1 = y0 - j1 as f32 + g3;
+        let z1 = z0 - k1 as f32 + g3;
+        let x2 = x0 - i2 as f32 + 2.0 * g3;
+        let y2 = y0 - j2 as f32 + 2.0 * g3;
+        let z2 = z0 - k2 as f32 + 2.0 * g3;
+        let x3 = x0 - 1.0 + 3.0 * g3;
+        let y3 = y0 - 1.0 + 3.0 * g3;
+        let z3 = z0 - 1.0 + 3.0 * g3;
+
+        let (i, j, k) = (i as i32, j as i32, k as i32);
+        let contribution = |hash: u8, x: f32, y: f32, z: f32| {
+            let t = 0.6 - x * x - y * y - z * z;
+            if t < 0.0 {
+                0.0
+            } else {
+                let t2 = t * t;
+                t2 * t2 * grad3(hash, x, y, z)
+            }
+        };
+        32.0 * (contribution(self.hash3(i, j, k), x0, y0, z0)
+            + contribution(self.hash3(i + i1, j + j1, k + k1), x1, y1, z1)
+            + contribution(self.hash3(i + i2, j + j2, k + k2), x2, y2, z2)
+            + contribution(self.hash3(i + 1, j + 1, k + 1), x3, y3, z3))
+    }
+
+    /// Returns pseudo-random offset in [0; 1] range for a cell, `channel` selects one of
+    /// independent offsets.
+    fn cell_offset(&self, hash: u8, channel: u8) -> f32 {
+        self.permutation[hash as usize + channel as usize] as f32 / 255.0
+    }
+
+    /// One-dimensional Worley noise.
+    pub fn worley1(&self, x: f32) -> f32 {
+        let i = x.floor() as i32;
+        let mut min_distance = std::f32::MAX;
+        for di in -1..=1 {
+            let cell = i + di;
+            let feature = cell as f32 + self.cell_offset(self.hash(cell), 0);
+            min_distance = min_distance.min((feature - x).abs());
+        }
+        min_distance
+    }
+
+    /// Two-dimensional Worley noise.
+    pub fn worley2(&self, x: f32, y: f32) -> f32 {
+        let (i, j) = (x.floor() as i32, y.floor() as i32);
+        let mut min_sqr_distance = std::f32::MAX;
+        for dj in -1..=1 {
+            for di in -1..=1 {
+                let (ci, cj) = (i + di, j + dj);
+                let hash = self.hash2(ci, cj);
+                let dx = ci as f32 + self.cell_offset(hash, 0) - x;
+                let dy = cj as f32 + self.cell_offset(hash, 1) - y;
+                min_sqr_distance = min_sqr_distance.min(dx * dx + dy * dy);
+            }
+        }
+        min_sqr_distance.sqrt()
+    }
+
+    /// Three-dimensional Worley noise.
+    pub fn worley3(&self, x: f32, y: f32, z: f32) -> f32 {
+        let (i, j, k) = (x.floor() as i32, y.floor() as i32, z.floor() as i32);
+        let mut min_sqr_distance = std::f32::MAX;
+        for dk in -1..=1 {
+            for dj in -1..=1 {
+                for di in -1..=1 {
+                    let (ci, cj, ck) = (i + di, j + dj, k + dk);
+                    let hash = self.hash3(ci, cj, ck);
+                    let dx = ci as f32 + self.cell_offset(hash, 0) - x;
+                    let dy = cj as f32 + self.cell_offset(hash, 1) - y;
+                    let dz = ck as f32 + self.cell_offset(hash, 2) - z;
+                    min_sqr_distance = min_sqr_distance.min(dx * dx + dy * dy + dz * dz);
+                }
+            }
+        }
+        min_sqr_distance.sqrt()
+    }
+}
+
+/// Fractal Brownian motion - sum of octaves of noise, each next octave has higher frequency
+/// and lower amplitude. Result is normalized, so it has the same range as the noise.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Fbm {
+    /// Kind of noise of every octave.
+    pub kind: NoiseKind,
+    /// Amount of octaves, more octaves give more details but cost more.
+    pub octaves: u32,
+    /// Frequency of the first octave.
+    pub frequency: f32,
+    /// Multiplier of frequency for each next octave.
+    pub lacunarity: f32,
+    /// Multiplier of amplitude for each next octave.
+    pub gain: f32,
+}
+
+impl Default for Fbm {
+    fn default() -> Self {
+        Self {
+            kind: NoiseKind::Perlin,
+            octaves: 4,
+            frequency: 1.0,
+            lacunarity: 2.0,
+            gain: 0.5,
+        }
+    }
+}
+
+impl Fbm {
+    fn sum<F: FnMut(f32, usize) -> f32>(&self, mut sample: F) -> f32 {
+        let mut frequency = self.frequency;
+        let mut amplitude = 1.0;
+        let mut total = 0.0;
+        let mut total_amplitude = 0.0;
+        for octave in 0..self.octaves as usize {
+            total += sample(frequency, octave) * amplitude;
+            total_amplitude += amplitude;
+            frequency *= self.lacunarity;
+            amplitude *= self.gain;
+        }
+        if total_amplitude > 0.0 {
+            total / total_amplitude
+        } else {
+            0.0
+        }
+    }
+
+    /// Returns value at a point on a line.
+    pub fn get1(&self, noise: &Noise, x: f32) -> f32 {
+        self.sum(|f, octave| noise.sample1(self.kind, x * f + octave_offset(octave)))
+    }
+
+    /// Returns value at a point on a plane.
+    pub fn get2(&self, noise: &Noise, x: f32, y: f32) -> f32 {
+        self.sum(|f, octave| {
+            let offset = octave_offset(octave);
+            noise.sample2(self.kind, x * f + offset, y * f + offset)
+        })
+    }
+
+    /// Returns value at a point in space.
+    pub fn get3(&self, noise: &Noise, x: f32, y: f32, z: f32) -> f32 {
+        self.sum(|f, octave| {
+            let offset = octave_offset(octave);
+            noise.sample3(self.kind, x * f + offset, y * f + offset, z * f + offset)
+        })
+    }
+}
+
+/// Shifts octaves relative to each other, otherwise all octaves would have the same value
+/// at the origin.
+fn octave_offset(octave: usize) -> f32 {
+    octave as f32 * 17.31
+}
+
+#[cfg(test)]
+mod test {
+    use crate::utils::noise::{Fbm, Noise, NoiseKind};
+
+    #[test]
+    fn noise_test() {
+        let a = Noise::new(123);
+        let b = Noise::new(123);
+        let c = Noise::new(321);
+
+        let mut differs = false;
+        for n in 0..1000 {
+            let x = n as f32 * 0.173 - 50.0;
+            let y = n as f32 * 0.071 + 3.0;
+            let z = n as f32 * -0.029;
+            for &kind in &[NoiseKind::Perlin, NoiseKind::Simplex, NoiseKind::Worley] {
+                for &value in &[
+                    a.sample1(kind, x),
+                    a.sample2(kind, x, y),
+                    a.sample3(kind, x, y, z),
+                ] {
+                    assert!(value >= -1.1 && value <= 1.75);
+                    if kind == NoiseKind::Worley {
+                        assert!(value >= 0.0);
+                    }
+                }
+                assert_eq!(a.sample3(kind, x, y, z), b.sample3(kind, x, y, z));
+                differs |= a.sample3(kind, x, y, z) != c.sample3(kind, x, y, z);
+            }
+        }
+        assert!(differs);
+
+        // Gradient noise is zero at lattice points.
+        assert_eq!(a.perlin2(3.0, -7.0), 0.0);
+        assert_eq!(a.perlin3(1.0, 2.0, 3.0), 0.0);
+
+        let fbm = Fbm::default();
+        let value = fbm.get2(&a, 0.37, 1.91);
+        assert!(value.abs() <= 1.1);
+    }
+}
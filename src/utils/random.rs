@@ -0,0 +1,227 @@
+//! Seedable random number generator.
+//!
+//! [Random] is a small and fast PCG32 generator, unlike `rand::thread_rng` its sequence is
+//! fully defined by a seed and does not depend on platform or version of `rand` crate. Use it
+//! for gameplay randomness which must be reproducible - replays, networked simulations and
+//! tests. State of the generator can be saved with the rest of game state, so sequence
+//! continues after load.
+//!
+//! `Random` implements `rand::RngCore`, so it can be used with everything from `rand` crate
+//! as well.
+//!
+//! # Example
+//!
+//! ```
+//! use rg3d::utils::random::Random;
+//!
+//! let mut a = Random::new(1234);
+//! let mut b = Random::new(1234);
+//! assert_eq!(a.range(0.0, 10.0), b.range(0.0, 10.0));
+//!
+//! let loot = ["sword", "shield", "potion"];
+//! let item = loot[a.weighted_choice(&[1.0, 1.0, 8.0]).unwrap()];
+//! ```
+
+use crate::core::{
+    math::vec3::Vec3,
+    visitor::{Visit, VisitResult, Visitor},
+};
+use rand::RngCore;
+
+const MULTIPLIER: u64 = 6_364_136_223_846_793_005;
+const INCREMENT: u64 = 1_442_695_040_888_963_407;
+
+/// Seedable random number generator. See module docs.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Random {
+    state: u64,
+}
+
+impl Default for Random {
+    fn default() -> Self {
+        Self::new(0)
+    }
+}
+
+impl Random {
+    /// Creates new generator with given seed.
+    pub fn new(seed: u64) -> Self {
+        let mut random = Self { state: 0 };
+        random.next();
+        random.state = random.state.wrapping_add(seed);
+        random.next();
+        random
+    }
+
+    /// Creates new generator with a seed taken from system source of entropy. Use it when
+    /// reproducibility is not needed.
+    pub fn from_entropy() -> Self {
+        Self::new(rand::thread_rng().next_u64())
+    }
+
+    fn next(&mut self) -> u32 {
+        let old = self.state;
+        self.state = old.wrapping_mul(MULTIPLIER).wrapping_add(INCREMENT);
+        let shifted = (((old >> 18) ^ old) >> 27) as u32;
+        shifted.rotate_right((old >> 59) as u32)
+    }
+
+    /// Returns random number in [0; 1) range.
+    pub fn next_f32(&mut self) -> f32 {
+        // 24 bits is the precision of f32 mantissa.
+        (self.next() >> 8) as f32 / (1u32 << 24) as f32
+    }
+
+    /// Returns random number in [min; max) range.
+    pub fn range(&mut self, min: f32, max: f32) -> f32 {
+        min + (max - min) * self.next_f32()
+    }
+
+    /// Returns random integer in [min; max) range. Returns `min` if range is empty.
+    pub fn range_int(&mut self, min: i32, max: i32) -> i32 {
+        if max <= min {
+            return min;
+        }
+        let span = (max as i64 - min as i64) as u32;
+        // Reject values from incomplete last span to avoid bias.
+        let threshold = span.wrapping_neg() % span;
+        loop {
+            let value = self.next();
+            if value >= threshold {
+                return (min as i64 + (value % span) as i64) as i32;
+            }
+        }
+    }
+
+    /// Returns `true` with given probability.
+    pub fn chance(&mut self, probability: f32) -> bool {
+        self.next_f32() < probability
+    }
+
+    /// Returns random direction, directions are distributed uniformly.
+    pub fn unit_vector(&mut self) -> Vec3 {
+        let z = self.range(-1.0, 1.0);
+        let angle = self.range(0.0, 2.0 * std::f32::consts::PI);
+        let r = (1.0 - z * z).max(0.0).sqrt();
+        Vec3::new(r * angle.cos(), r * angle.sin(), z)
+    }
+
+    /// Returns random point inside sphere with given radius and center at origin, points are
+    /// distributed uniformly by volume.
+    pub fn point_in_sphere(&mut self, radius: f32) -> Vec3 {
+        self.unit_vector().scale(radius * self.next_f32().cbrt())
+    }
+
+    /// Returns random element of a slice, `None` if slice is empty.
+    pub fn choose<'a, T>(&mut self, items: &'a [T]) -> Option<&'a T> {
+        if items.is_empty() {
+            None
+        } else {
+            items.get(self.range_int(0, items.len() as i32) as usize)
+        }
+    }
+
+    /// Returns random index of a weight, probability of each index is proportional to its
+    /// weight. Negative weights are treated as zero. Returns `None` if there is no positive
+    /// weight.
+    pub fn weighted_choice(&mut self, weights: &[f32]) -> Option<usize> {
+        let total = weights.iter().map(|w| w.max(0.0)).sum::<f32>();
+        if total <= 0.0 {
+            return None;
+        }
+        let mut value = self.next_f32() * total;
+        let mut last_positive = None;
+        for (i, weight) in weights.iter().enumerate() {
+            if *weight > 0.0 {
+                if value < *weight {
+                    return Some(i);
+                }
+                value -= weight;
+                last_positive = Some(i);
+            }
+        }
+        // Rounding errors may leave small remainder.
+        last_positive
+    }
+
+    /// Shuffles a slice in place.
+    pub fn shuffle<T>(&mut self, items: &mut [T]) {
+        for i in (1..items.len()).rev() {
+            let j = self.range_int(0, i as i32 + 1) as usize;
+            items.swap(i, j);
+        }
+    }
+}
+
+impl RngCore for Random {
+    fn next_u32(&mut self) -> u32 {
+        self.next()
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        ((self.next() as u64) << 32) | self.next() as u64
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        for chunk in dest.chunks_mut(4) {
+            let bytes = self.next().to_le_bytes();
+            chunk.copy_from_slice(&bytes[..chunk.len()]);
+        }
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand::Error> {
+        self.fill_bytes(dest);
+        Ok(())
+    }
+}
+
+impl Visit for Random {
+    fn visit(&mut self, name: &str, visitor: &mut Visitor) -> VisitResult {
+        visitor.enter_region(name)?;
+
+        self.state.visit("State", visitor)?;
+
+        visitor.leave_region()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{core::math::vec3::Vec3, utils::random::Random};
+
+    #[test]
+    fn random_test() {
+        let mut a = Random::new(42);
+        let mut b = Random::new(42);
+        let mut c = Random::new(43);
+        let mut differs = false;
+        for _ in 0..1000 {
+            let value = a.range(-2.0, 3.0);
+            assert!(value >= -2.0 && value < 3.0);
+            assert_eq!(value, b.range(-2.0, 3.0));
+            differs |= value != c.range(-2.0, 3.0);
+
+            let int = a.range_int(-3, 4);
+            assert!(int >= -3 && int < 4);
+            b.range_int(-3, 4);
+
+            assert!((a.unit_vector().distance(&Vec3::ZERO) - 1.0).abs() < 1e-4);
+            assert!(a.point_in_sphere(2.0).distance(&Vec3::ZERO) <= 2.0 + 1e-4);
+        }
+        assert!(differs);
+        assert_eq!(a.range_int(5, 5), 5);
+
+        let mut counts = [0; 3];
+        for _ in 0..1000 {
+            counts[a.weighted_choice(&[1.0, 0.0, 3.0]).unwrap()] += 1;
+        }
+        assert_eq!(counts[1], 0);
+        assert!(counts[2] > counts[0]);
+        assert_eq!(a.weighted_choice(&[0.0, -1.0]), None);
+
+        let mut items = [1, 2, 3, 4, 5];
+        a.shuffle(&mut items);
+        items.sort();
+        assert_eq!(items, [1, 2, 3, 4, 5]);
+    }
+}